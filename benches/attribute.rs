@@ -0,0 +1,60 @@
+mod common;
+
+use criterion::{black_box,criterion_group,criterion_main,BenchmarkId,Criterion,Throughput};
+use construct::geometry::Vector;
+use construct::part::{Attribute,AttributeItem};
+
+fn attribute_revise(c: &mut Criterion) {
+    let mut group = c.benchmark_group("attribute_revise");
+
+    for size in common::SIZES {
+        let geometry = common::grid(size);
+        let count = geometry.vertices().len();
+
+        // move the first half of the grid and scale all of it
+        let mut attribute = Attribute::new("Length".into(),vec![
+            AttributeItem::translate_range(Vector::new(1.0,0.0,0.0),0..count / 2),
+            AttributeItem::scale_all(Vector::new(1.0,1.0,1.0)),
+        ]);
+        attribute.update(1.5);
+
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &geometry, |bench, g| {
+            bench.iter_batched_ref(
+                || g.clone(),
+                |data| black_box(&attribute).revise(data).unwrap(),
+                criterion::BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn attribute_specific(c: &mut Criterion) {
+    let mut group = c.benchmark_group("attribute_specific");
+
+    for size in common::SIZES {
+        let geometry = common::grid(size);
+        let indices: Vec<usize> = (0..geometry.vertices().len()).step_by(3).collect();
+
+        let mut attribute = Attribute::new("Offset".into(),vec![
+            AttributeItem::translate_specific(Vector::new(0.0,0.0,1.0),indices.clone()),
+        ]);
+        attribute.update(0.5);
+
+        group.throughput(Throughput::Elements(indices.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &geometry, |bench, g| {
+            bench.iter_batched_ref(
+                || g.clone(),
+                |data| black_box(&attribute).revise(data).unwrap(),
+                criterion::BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, attribute_revise, attribute_specific);
+criterion_main!(benches);
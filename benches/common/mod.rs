@@ -0,0 +1,42 @@
+// each bench binary uses a different subset of these helpers
+#![allow(dead_code)]
+
+use construct::geometry::{Geometry,Vertex};
+
+// vertex counts used for the size-scaling benchmarks
+pub const SIZES: [usize;4] = [1_000, 10_000, 100_000, 1_000_000];
+
+// Builds a flat square grid with roughly `count` vertices
+// and two triangles for every cell.
+pub fn grid(count: usize) -> Geometry {
+    let side = (count as f64).sqrt().ceil().max(2.0) as usize;
+
+    let mut values = Vec::with_capacity(side * side * 3);
+    for i in 0..side {
+        for j in 0..side {
+            values.push(i as f64);
+            values.push(j as f64);
+            values.push(0.0);
+        }
+    }
+
+    let mut indices = Vec::with_capacity((side - 1) * (side - 1) * 6);
+    for i in 0..side - 1 {
+        for j in 0..side - 1 {
+            // 1-indexed corners of the cell
+            let a = i * side + j + 1;
+            let b = a + 1;
+            let c = a + side;
+            let d = c + 1;
+            indices.extend_from_slice(&[a, b, d, a, d, c]);
+        }
+    }
+
+    Geometry::make(values,indices)
+}
+
+pub fn vertices(count: usize) -> Vec<Vertex> {
+    (0..count)
+        .map(|i| Vertex::new(i as f64, (i % 7) as f64, (i % 13) as f64))
+        .collect()
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use criterion::{black_box,criterion_group,criterion_main,BenchmarkId,Criterion,Throughput};
+use construct::geometry::Geometry;
+
+fn geometry_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("geometry_parse");
+    group.sample_size(10);
+
+    for size in common::SIZES {
+        let text = String::from(common::grid(size));
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &text, |bench, t| {
+            bench.iter(|| Geometry::try_from(black_box(t.clone())).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn geometry_export(c: &mut Criterion) {
+    let mut group = c.benchmark_group("geometry_export");
+    group.sample_size(10);
+
+    for size in common::SIZES {
+        let geometry = common::grid(size);
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &geometry, |bench, g| {
+            bench.iter(|| String::from(black_box(g.clone())))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, geometry_parse, geometry_export);
+criterion_main!(benches);
@@ -0,0 +1,41 @@
+mod common;
+
+use criterion::{black_box,criterion_group,criterion_main,BenchmarkId,Criterion,Throughput};
+use construct::geometry::{Matrix,Transform};
+
+fn matrix_multiply(c: &mut Criterion) {
+    let a = Matrix::rotate(1.0,2.0,3.0);
+    let b = Matrix::translate(4.0,5.0,6.0);
+
+    c.bench_function("matrix_multiply", |bench| {
+        bench.iter(|| black_box(a) * black_box(b))
+    });
+}
+
+fn matrix_compose(c: &mut Criterion) {
+    c.bench_function("matrix_rotate", |bench| {
+        bench.iter(|| Matrix::rotate(black_box(1.0),black_box(2.0),black_box(3.0)))
+    });
+}
+
+fn vertex_transform(c: &mut Criterion) {
+    let mut group = c.benchmark_group("vertex_transform");
+    let matrix = Matrix::rotate(1.0,2.0,3.0) * Matrix::translate(4.0,5.0,6.0);
+
+    for size in common::SIZES {
+        let vertices = common::vertices(size);
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &vertices, |bench, v| {
+            bench.iter_batched_ref(
+                || v.clone(),
+                |data| data.transform(black_box(&matrix)),
+                criterion::BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, matrix_multiply, matrix_compose, vertex_transform);
+criterion_main!(benches);
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use construct::geometry::Face;
+
+fuzz_target!(|data: &str| {
+    if let Ok(face) = Face::try_from(data) {
+        let _ = String::from(face);
+    }
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use construct::geometry::Geometry;
+
+fuzz_target!(|data: &str| {
+    if let Ok(geometry) = Geometry::try_from(data.to_string()) {
+        // anything that parses must be safe to walk and write back out
+        for triangle in geometry.clone().into_iter() {
+            let _ = triangle.normal();
+        }
+        let _ = String::from(geometry);
+    }
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use construct::geometry::Vector;
+
+fuzz_target!(|data: &str| {
+    if let Ok(vector) = Vector::try_from(data) {
+        let _ = String::from(vector);
+    }
+});
@@ -0,0 +1,231 @@
+use crate::assembly::Assembly;
+use crate::geometry::{Vertex,Polyline,Point};
+use crate::materials::Database;
+use crate::errors::Error;
+use crate::constant::Index;
+
+/// Settings for checking whether an assembly standing on the
+/// ground, with Z up, is at risk of tipping over
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub struct Balance {
+    limit: f64,
+    contact: f64,
+}
+
+/// How steady an assembly is standing on its lowest points
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Stability {
+    /// the total mass in kilograms
+    pub mass: f64,
+    pub center: Vertex,
+    /// the convex outline of the points touching the ground
+    pub support: Polyline,
+    /// how far the centre of mass is inside the support outline,
+    /// seen from above, and negative when it's outside
+    pub margin: f64,
+    /// how far in radians it can lean before it tips, which is
+    /// negative when it's already tipping
+    pub angle: f64,
+    pub tipping: bool,
+    /// points on the ground where another support would steady it
+    pub suggestions: Vec<Point>,
+    /// instances that were left out because their mass isn't known
+    pub unknown: Vec<Index>,
+}
+
+impl Default for Balance {
+    fn default() -> Self {
+        Self {
+            limit: 10f64.to_radians(),
+            contact: 1e-3,
+        }
+    }
+}
+
+impl Balance {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The smallest lean in radians it should survive without
+    // tipping, which is 10 degrees by default
+    pub fn with_limit(mut self, limit: f64) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    // How close to the lowest point a vertex has to be to count
+    // as touching the ground, in meters
+    pub fn with_contact(mut self, contact: f64) -> Self {
+        self.contact = contact;
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn limit(&self) -> f64 {
+        self.limit
+    }
+
+    pub fn contact(&self) -> f64 {
+        self.contact
+    }
+
+    // Finds the centre of mass of the instances with a known
+    // material and checks it against the outline of the points
+    // the whole assembly stands on
+    pub fn check(&self, assembly: &Assembly, database: &Database) -> Result<Stability,Error> {
+        span!("analysis.balance", instances = assembly.instances().len());
+        let geometries = assembly
+            .instances()
+            .iter()
+            .map(|i| i.geometry())
+            .collect::<Vec<_>>();
+
+        let mut stability = Stability::default();
+        let mut moment = Vertex::default();
+        for (index,(instance,geometry)) in assembly.instances().iter().zip(geometries.iter()).enumerate() {
+            match (database.mass(instance.part()),geometry.centroid()) {
+                (Some(mass),Some(center)) if mass > 0.0 => {
+                    stability.mass += mass;
+                    moment = moment + center * mass;
+                },
+                _ => stability.unknown.push(index),
+            }
+        }
+
+        if stability.mass <= 0.0 {
+            return Err(Error::EmptyGeometry);
+        }
+        stability.center = moment * (1.0 / stability.mass);
+
+        let ground = geometries
+            .iter()
+            .filter_map(|g| g.bounds())
+            .map(|b| b.min.z)
+            .fold(f64::MAX,f64::min);
+
+        let contacts = geometries
+            .iter()
+            .flat_map(|g| g.vertices().iter())
+            .filter(|v| v.z <= ground + self.contact)
+            .map(|v| (v.x,v.y))
+            .collect::<Vec<Point>>();
+
+        let center = (stability.center.x,stability.center.y);
+        let support = Polyline::hull(&contacts);
+        let (nearest,normal) = nearest(&support,center);
+        let distance = (nearest.0 - center.0).hypot(nearest.1 - center.1);
+        let inside = support.len() > 2 && support.contains(center);
+
+        let height = stability.center.z - ground;
+        stability.margin = if inside { distance } else { -distance };
+        stability.angle = stability.margin.atan2(height);
+        stability.tipping = stability.angle < self.limit;
+
+        if stability.tipping {
+            // far enough past the centre of mass, away from the
+            // middle of the support, to survive the limit
+            let reach = height * self.limit.tan() - stability.margin.min(0.0);
+            let away = match (distance > f64::EPSILON,inside) {
+                (true,true) => vec![((nearest.0 - center.0) / distance,(nearest.1 - center.1) / distance)],
+                (true,false) => vec![((center.0 - nearest.0) / distance,(center.1 - nearest.1) / distance)],
+                (false,_) => vec![normal,(-normal.0,-normal.1)],
+            };
+            stability.suggestions = away
+                .into_iter()
+                .map(|(x,y)| (center.0 + x * reach,center.1 + y * reach))
+                .collect();
+        }
+
+        stability.support = support;
+        Ok(stability)
+    }
+
+}
+
+// The closest point on the edges of a loop, and the outward
+// normal of that edge, which is along X if there isn't one
+fn nearest(support: &Polyline, point: Point) -> (Point,Point) {
+    let mut best = (support.points().first().copied().unwrap_or(point),(1.0,0.0));
+    let mut distance = f64::MAX;
+    for (a,b) in support.segments() {
+        let (dx,dy) = (b.0 - a.0,b.1 - a.1);
+        let length = dx * dx + dy * dy;
+        let t = match length > 0.0 {
+            true => (((point.0 - a.0) * dx + (point.1 - a.1) * dy) / length).clamp(0.0,1.0),
+            false => 0.0,
+        };
+        let closest = (a.0 + dx * t,a.1 + dy * t);
+        let d = (closest.0 - point.0).hypot(closest.1 - point.1);
+        if d < distance && length > 0.0 {
+            distance = d;
+            let l = length.sqrt();
+            best = (closest,(dy / l,-dx / l));
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::geometry::Matrix;
+    use crate::part::{Part,Metadata};
+    use crate::testing;
+
+    // A one meter steel cube
+    fn steel() -> Part {
+        Part::new("cube")
+            .with_geometry(testing::cube())
+            .with_metadata(Metadata::new().with_material("steel"))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_balance_steady() {
+        let assembly = Assembly::new("block").with_part(steel(),Matrix::identity());
+        let stability = Balance::new().check(&assembly,&Database::builtin()).unwrap();
+
+        assert_relative_eq!(stability.mass,7850.0,epsilon = 1e-6);
+        assert_relative_eq!(stability.center.z,0.5,epsilon = 1e-9);
+        assert_relative_eq!(stability.support.area(),1.0);
+        assert_relative_eq!(stability.margin,0.5,epsilon = 1e-9);
+        assert_relative_eq!(stability.angle,std::f64::consts::FRAC_PI_4,epsilon = 1e-9);
+        assert!(!stability.tipping);
+        assert!(stability.suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_balance_tipping() {
+        // a cube hanging over the edge of another one, and a
+        // part without a material that's left out
+        let mut unknown = steel();
+        unknown.metadata_mut().set_material(None);
+
+        let assembly = Assembly::new("stack")
+            .with_part(steel(),Matrix::identity())
+            .with_part(steel(),Matrix::translate(0.9,0.0,1.0))
+            .with_part(unknown,Matrix::translate(-5.0,0.0,3.0));
+
+        let stability = Balance::new().check(&assembly,&Database::builtin()).unwrap();
+        assert_eq!(stability.unknown,vec![2]);
+        assert_relative_eq!(stability.center.x,0.95,epsilon = 1e-9);
+        assert_relative_eq!(stability.margin,0.05,epsilon = 1e-9);
+        assert!(stability.tipping);
+
+        // the suggestion is past the right hand edge
+        let suggestion = stability.suggestions[0];
+        assert!(suggestion.0 > 1.0);
+        assert_relative_eq!(suggestion.1,0.5,epsilon = 1e-9);
+
+        let empty = Assembly::new("empty");
+        assert!(matches!(Balance::new().check(&empty,&Database::builtin()),Err(Error::EmptyGeometry)));
+    }
+
+}
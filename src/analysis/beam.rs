@@ -0,0 +1,141 @@
+use crate::geometry::SectionProperties;
+
+/// A load on a simply supported member
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub enum Load {
+    /// newtons per meter spread along the whole span
+    Uniform(f64),
+    /// newtons at a point, as a fraction of the span from one end
+    Point { force: f64, position: f64 },
+}
+
+/// A straight member resting on a support at each end, with the
+/// stiffness of its section in the direction it's loaded. Units
+/// are meters, pascals and newtons.
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct Beam {
+    span: f64,
+    modulus: f64,
+    inertia: f64,
+    section: f64,
+}
+
+impl Load {
+
+    // A point load half way along the span
+    pub fn center(force: f64) -> Self {
+        Load::Point { force, position: 0.5 }
+    }
+
+}
+
+impl Beam {
+
+    // A beam from its elastic modulus, the second moment of area
+    // of its section and the section modulus
+    pub fn new(span: f64, modulus: f64, inertia: f64, section: f64) -> Self {
+        Self { span, modulus, inertia, section }
+    }
+
+    // A beam with a solid rectangular section, with the depth
+    // measured in the direction of the load
+    pub fn rectangle(span: f64, width: f64, depth: f64, modulus: f64) -> Self {
+        Self::new(
+            span,
+            modulus,
+            width * depth.powi(3) / 12.0,
+            width * depth.powi(2) / 6.0)
+    }
+
+    // A beam with any section, bending about the section's X axis
+    // when the load runs along its Y axis, or about Y otherwise
+    pub fn from_properties(span: f64, properties: &SectionProperties, modulus: f64, along_y: bool) -> Self {
+        match along_y {
+            true => Self::new(span,modulus,properties.ixx,properties.sxx),
+            false => Self::new(span,modulus,properties.iyy,properties.syy),
+        }
+    }
+
+    pub fn span(&self) -> f64 {
+        self.span
+    }
+
+    pub fn modulus(&self) -> f64 {
+        self.modulus
+    }
+
+    pub fn inertia(&self) -> f64 {
+        self.inertia
+    }
+
+    pub fn section(&self) -> f64 {
+        self.section
+    }
+
+    // The largest bending moment in newton meters
+    pub fn moment(&self, load: &Load) -> f64 {
+        let l = self.span;
+        match *load {
+            Load::Uniform(w) => w * l * l / 8.0,
+            Load::Point { force, position } => {
+                let a = position.clamp(0.0,1.0) * l;
+                force * a * (l - a) / l
+            },
+        }
+    }
+
+    // The largest bending stress in pascals, at the top and
+    // bottom faces where the moment is largest
+    pub fn stress(&self, load: &Load) -> f64 {
+        self.moment(load) / self.section
+    }
+
+    // The largest deflection in meters
+    pub fn deflection(&self, load: &Load) -> f64 {
+        let (l,ei) = (self.span,self.modulus * self.inertia);
+        match *load {
+            Load::Uniform(w) => 5.0 * w * l.powi(4) / (384.0 * ei),
+            Load::Point { force, position } => {
+                // measured from the nearer support
+                let b = position.clamp(0.0,1.0).min(1.0 - position.clamp(0.0,1.0)) * l;
+                force * b * (l * l - b * b).powf(1.5) / (9.0 * 3f64.sqrt() * l * ei)
+            },
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::geometry::{Profile,Polyline};
+
+    #[test]
+    fn test_beam_formulas() {
+        let beam = Beam::rectangle(2.0,0.05,0.1,10.0e9);
+        assert_relative_eq!(beam.inertia(),0.05 * 0.001 / 12.0);
+        assert_relative_eq!(beam.section(),0.05 * 0.01 / 6.0);
+
+        let profile = Profile::new(Polyline::closed(vec![(0.0,0.0),(0.05,0.0),(0.05,0.1),(0.0,0.1)]));
+        let upright = Beam::from_properties(2.0,&profile.properties(),10.0e9,true);
+        assert_relative_eq!(upright.inertia(),beam.inertia(),epsilon = 1e-15);
+        assert_relative_eq!(upright.section(),beam.section(),epsilon = 1e-15);
+
+        let uniform = Load::Uniform(1000.0);
+        assert_relative_eq!(beam.moment(&uniform),500.0);
+        assert_relative_eq!(beam.stress(&uniform),500.0 / beam.section());
+        assert_relative_eq!(beam.deflection(&uniform),5.0 * 1000.0 * 16.0 / (384.0 * 10.0e9 * beam.inertia()));
+
+        // off center loads bend it less than central ones
+        let center = Load::center(1000.0);
+        assert_relative_eq!(beam.moment(&center),500.0);
+        assert_relative_eq!(beam.deflection(&center),1000.0 * 8.0 / (48.0 * 10.0e9 * beam.inertia()),epsilon = 1e-12);
+
+        let side = Load::Point { force: 1000.0, position: 0.25 };
+        assert_relative_eq!(beam.moment(&side),375.0);
+        assert!(beam.deflection(&side) < beam.deflection(&center));
+        assert_relative_eq!(beam.deflection(&side),beam.deflection(&Load::Point { force: 1000.0, position: 0.75 }));
+    }
+
+}
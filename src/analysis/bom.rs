@@ -0,0 +1,233 @@
+use std::cmp::Ordering;
+
+use crate::assembly::{Assembly,Route};
+use crate::materials::{Database,PricingProvider,Item};
+use crate::part::Part;
+use crate::constant::TOLERANCE;
+
+/// Identical parts in a bill of materials, counted together
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Line {
+    /// the name of the part or route
+    pub name: String,
+    /// the material named in the part's metadata
+    pub material: Option<String>,
+    /// the code a supplier sells the part under
+    pub sku: Option<String>,
+    /// the sides of the part's bounding box, longest first. Routes
+    /// only have a length.
+    pub size: [f64;3],
+    pub quantity: usize,
+    /// the mass of one, if its material is known
+    pub mass: Option<f64>,
+    /// the price of one, if its material has a cost
+    pub price: Option<f64>,
+}
+
+/// The parts needed to build an assembly, with identical parts on
+/// one line. Lines are grouped by material and sorted by name.
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Bom {
+    lines: Vec<Line>,
+}
+
+impl Line {
+
+    // The mass of every part on the line
+    pub fn total_mass(&self) -> Option<f64> {
+        self.mass.map(|m| m * self.quantity as f64)
+    }
+
+    // The price of every part on the line
+    pub fn total_price(&self) -> Option<f64> {
+        self.price.map(|p| p * self.quantity as f64)
+    }
+
+    // True if the lines are for the same thing and can be counted
+    // together
+    fn same(&self, other: &Line) -> bool {
+        self.name == other.name &&
+        self.material == other.material &&
+        self.sku == other.sku &&
+        self.size.iter().zip(other.size.iter()).all(|(a,b)| (a - b).abs() <= TOLERANCE)
+    }
+
+}
+
+impl Bom {
+
+    // Counts the parts in an assembly, looking up the mass and price
+    // of each in the database by its material
+    pub fn new(assembly: &Assembly, database: &Database) -> Self {
+        Self::priced(assembly,database,database)
+    }
+
+    // Counts the parts in an assembly with masses from the database
+    // and prices from a supplier
+    pub fn priced<P: PricingProvider>(assembly: &Assembly, database: &Database, pricing: &P) -> Self {
+        span!("analysis.bom", instances = assembly.instances().len());
+        let mut bom = Self::default();
+        for instance in assembly.instances().iter() {
+            bom.add(line(instance.part(),database,pricing));
+        }
+        bom
+    }
+
+    // Adds a cable, pipe or conduit by the length of its route
+    pub fn with_route<T: Into<String>>(mut self, name: T, route: &Route) -> Self {
+        self.add(Line {
+            name: name.into(),
+            size: [route.length(),0.0,0.0],
+            quantity: 1,
+            ..Default::default()
+        });
+        self
+    }
+
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    // The lines for each material, in order, with parts that
+    // don't have one last
+    pub fn groups(&self) -> impl Iterator<Item = (Option<&str>,&[Line])> {
+        self.lines
+            .chunk_by(|a,b| a.material == b.material)
+            .map(|g| (g[0].material.as_deref(),g))
+    }
+
+    // The number of parts on every line
+    pub fn quantity(&self) -> usize {
+        self.lines.iter().map(|l| l.quantity).sum()
+    }
+
+    // The mass of every part with a known material
+    pub fn mass(&self) -> f64 {
+        self.lines.iter().filter_map(Line::total_mass).sum()
+    }
+
+    // The price of every part with a priced material
+    pub fn price(&self) -> f64 {
+        self.lines.iter().filter_map(Line::total_price).sum()
+    }
+
+    // Adds a line, counting it with an existing one if it's the same
+    fn add(&mut self, line: Line) {
+        match self.lines.iter_mut().find(|l| l.same(&line)) {
+            Some(existing) => existing.quantity += line.quantity,
+            None => {
+                let index = self.lines
+                    .iter()
+                    .position(|l| order(&line,l) == Ordering::Less)
+                    .unwrap_or(self.lines.len());
+                self.lines.insert(index,line);
+            },
+        }
+    }
+
+}
+
+// A line for one part
+fn line<P: PricingProvider>(part: &Part, database: &Database, pricing: &P) -> Line {
+    let mut size = part
+        .geometry()
+        .bounds()
+        .map(|b| b.size())
+        .map(|s| [s.x,s.y,s.z])
+        .unwrap_or_default();
+    size.sort_by(|a,b| b.total_cmp(a));
+
+    Line {
+        name: part.name().into(),
+        material: part.metadata().material().map(String::from),
+        sku: part.metadata().sku().map(String::from),
+        size,
+        quantity: 1,
+        mass: database.mass(part),
+        price: pricing.price(&Item::new(part)),
+    }
+}
+
+// Lines by material with no material last, then by name and size
+fn order(a: &Line, b: &Line) -> Ordering {
+    let material = match (&a.material,&b.material) {
+        (Some(x),Some(y)) => x.cmp(y),
+        (x,y) => y.is_some().cmp(&x.is_some()),
+    };
+    material
+        .then_with(|| a.name.cmp(&b.name))
+        .then_with(|| a.size[0].total_cmp(&b.size[0]))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::geometry::Matrix;
+    use crate::materials::{Material,Kind,Cost,PriceTable};
+    use crate::models::{Lumber,SheetGoods};
+
+    fn assembly() -> Assembly {
+        let mut stud = Lumber::TwoByFour.part();
+        stud.metadata_mut().set_material(Some("2x4".into()));
+        let mut short = stud.clone();
+        short.set("Length",-1.2192).unwrap();
+        let mut panel = SheetGoods::Plywood.part();
+        panel.metadata_mut().set_material(Some("plywood 3/4".into()));
+
+        Assembly::new("wall")
+            .with_part(stud.clone(),Matrix::identity())
+            .with_part(panel,Matrix::identity())
+            .with_part(short.clone(),Matrix::translate(0.0,1.0,0.0))
+            .with_part(stud,Matrix::translate(0.0,2.0,0.0))
+            .with_part(short,Matrix::translate(0.0,3.0,0.0))
+            .with_part(Part::new("bracket"),Matrix::identity())
+    }
+
+    #[test]
+    fn test_bom_lines() {
+        let database = Database::builtin()
+            .with_material(Material::new("2x4",Kind::Lumber,450.0).with_cost(Cost::Mass(2.0)));
+        let assembly = assembly();
+        let bom = Bom::new(&assembly,&database);
+        assert_eq!(bom.len(),4);
+        assert_eq!(bom.quantity(),6);
+
+        let lines = bom.lines();
+        assert_eq!((lines[0].name.as_str(),lines[0].quantity),("2x4",2));
+        assert_relative_eq!(lines[0].size[0],1.2192,epsilon = 1e-9);
+        assert_relative_eq!(lines[1].size[0],2.4384,epsilon = 1e-9);
+        assert_eq!(lines[2].material.as_deref(),Some("plywood 3/4"));
+        assert_eq!(lines[3].name,"bracket");
+        assert_eq!(lines[3].mass,None);
+
+        let mass = lines[1].mass.unwrap();
+        let volume = assembly.instances()[0].part().geometry().volume().abs();
+        assert_relative_eq!(mass,450.0 * volume,epsilon = 1e-9);
+        assert_relative_eq!(lines[1].total_price().unwrap(),mass * 4.0,epsilon = 1e-9);
+        assert_eq!(bom.price(),lines.iter().filter_map(Line::total_price).sum::<f64>());
+
+        // a supplier's prices replace the database's
+        let table = PriceTable::new().with_sku("PLY-34",48.0).with_material("2x4",1.0);
+        let mut assembly = assembly;
+        assembly.instances_mut()[1].part_mut().metadata_mut().set_sku(Some("PLY-34".into()));
+        let priced = Bom::priced(&assembly,&database,&table);
+        let lines = priced.lines();
+        assert_eq!(lines[2].sku.as_deref(),Some("PLY-34"));
+        assert_eq!(lines[2].price,Some(48.0));
+        assert_relative_eq!(lines[1].price.unwrap(),2.4384,epsilon = 1e-9);
+        assert_eq!(lines[3].price,None);
+
+        let groups = bom.groups().map(|(m,l)| (m,l.len())).collect::<Vec<_>>();
+        assert_eq!(groups,vec![(Some("2x4"),2),(Some("plywood 3/4"),1),(None,1)]);
+    }
+
+}
@@ -0,0 +1,172 @@
+use crate::assembly::Assembly;
+use crate::materials::{Database,Kind};
+use crate::constant::TOLERANCE;
+
+/// A piece to piece from a length of stock
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Piece {
+    /// the name of the part
+    pub name: String,
+    /// the material it's piece from
+    pub material: String,
+    /// the longest side of the part
+    pub length: f64,
+}
+
+/// One stock length and the pieces piece from it
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Board {
+    pub material: String,
+    /// the length of the stock as it's bought
+    pub length: f64,
+    /// the pieces piece from it, longest first
+    pub pieces: Vec<Piece>,
+    /// the width of the saw blade taken by each piece
+    pub kerf: f64,
+}
+
+/// The stock lengths needed to piece every part in an assembly that's
+/// made of something sold by length, like lumber or metal bar
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct CutList {
+    boards: Vec<Board>,
+    oversized: Vec<Piece>,
+}
+
+impl Board {
+
+    // The length taken by the pieces and the saw between them
+    pub fn used(&self) -> f64 {
+        self.pieces.iter().map(|c| c.length + self.kerf).sum()
+    }
+
+    // The offcut left over after every piece is cut
+    pub fn waste(&self) -> f64 {
+        (self.length - self.used()).max(0.0)
+    }
+
+    // True if another piece can be piece from what's left
+    fn fits(&self, length: f64) -> bool {
+        self.used() + length <= self.length + TOLERANCE
+    }
+
+}
+
+impl CutList {
+
+    // Packs the parts of an assembly into stock lengths, cutting the
+    // longest pieces first and each from the first board it fits on.
+    // Parts are included if the material in their metadata is in the
+    // database with a stock length and isn't a sheet good, which is
+    // laid out with `Nesting` instead.
+    pub fn new(assembly: &Assembly, database: &Database, kerf: f64) -> Self {
+        span!("analysis.cuts", instances = assembly.instances().len());
+        let mut pieces = Vec::new();
+        for instance in assembly.instances().iter() {
+            let part = instance.part();
+            let Some(material) = database.material(part).filter(|m| m.kind() != Kind::Sheet) else {
+                continue;
+            };
+            let (Some(stock),Some(bounds)) = (material.length(),part.geometry().bounds()) else {
+                continue;
+            };
+
+            let size = bounds.size();
+            let piece = Piece {
+                name: part.name().into(),
+                material: material.name().into(),
+                length: size.x.max(size.y).max(size.z),
+            };
+            pieces.push((piece,stock));
+        }
+
+        pieces.sort_by(|(a,_),(b,_)| a.material.cmp(&b.material).then(b.length.total_cmp(&a.length)));
+
+        let mut list = Self::default();
+        for (piece,stock) in pieces.into_iter() {
+            if piece.length > stock + TOLERANCE {
+                list.oversized.push(piece);
+                continue;
+            }
+
+            let board = list.boards
+                .iter_mut()
+                .find(|b| b.material == piece.material && b.fits(piece.length));
+
+            match board {
+                Some(board) => board.pieces.push(piece),
+                None => list.boards.push(Board {
+                    material: piece.material.clone(),
+                    length: stock,
+                    pieces: vec![piece],
+                    kerf,
+                }),
+            }
+        }
+        list
+    }
+
+    pub fn boards(&self) -> &[Board] {
+        &self.boards
+    }
+
+    // Pieces that are longer than the stock they're made from
+    pub fn oversized(&self) -> &[Piece] {
+        &self.oversized
+    }
+
+    // The number of stock lengths to buy of a material
+    pub fn count(&self, material: &str) -> usize {
+        self.boards.iter().filter(|b| b.material == material).count()
+    }
+
+    // The offcuts from every board
+    pub fn waste(&self) -> f64 {
+        self.boards.iter().map(Board::waste).sum()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::geometry::Matrix;
+    use crate::models::{Lumber,SheetGoods};
+    use crate::part::Part;
+
+    fn board(length: f64) -> Part {
+        let mut part = Lumber::TwoByFour.part();
+        part.metadata_mut().set_material(Some("2x4".into()));
+        part.set("Length",length - 2.4384).unwrap();
+        part
+    }
+
+    #[test]
+    fn test_cut_list() {
+        let mut panel = SheetGoods::Plywood.part();
+        panel.metadata_mut().set_material(Some("plywood 3/4".into()));
+
+        let assembly = Assembly::new("frame")
+            .with_part(board(1.0),Matrix::identity())
+            .with_part(board(1.5),Matrix::identity())
+            .with_part(board(0.8),Matrix::identity())
+            .with_part(board(1.2),Matrix::identity())
+            .with_part(board(3.0),Matrix::identity())
+            .with_part(panel,Matrix::identity())
+            .with_part(Part::new("bracket"),Matrix::identity());
+
+        let list = CutList::new(&assembly,&Database::builtin(),0.003);
+        assert_eq!(list.count("2x4"),2);
+        assert_eq!(list.count("plywood 3/4"),0);
+        assert_eq!(list.oversized().len(),1);
+
+        // 1.5 and 0.8 on one board, 1.2 and 1.0 on the other
+        let lengths = |b: &Board| b.pieces.iter().map(|c| (c.length * 10.0).round() as usize).collect::<Vec<_>>();
+        assert_eq!(lengths(&list.boards()[0]),vec![15,8]);
+        assert_eq!(lengths(&list.boards()[1]),vec![12,10]);
+        assert_relative_eq!(list.boards()[0].waste(),2.4384 - 2.3 - 0.006,epsilon = 1e-9);
+        assert_relative_eq!(list.waste(),2.0 * 2.4384 - 4.5 - 0.012,epsilon = 1e-9);
+    }
+
+}
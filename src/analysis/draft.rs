@@ -0,0 +1,190 @@
+use crate::geometry::{Geometry,Direction,Triangle};
+use crate::constant::{Index,TOLERANCE};
+
+/// Settings for checking whether a part can be pulled out of a
+/// two-part mold, where the halves separate along a direction
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub struct Draft {
+    pull: Direction,
+    minimum: f64,
+}
+
+/// How well each face of a mesh releases from a mold
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Moldability {
+    /// the draft of each face in radians, which is positive when it
+    /// faces along the pull and comes away with that half of the
+    /// mold, negative when it faces against it, and zero for walls
+    pub angles: Vec<f64>,
+    /// faces with less draft than the minimum either way, which
+    /// drag against the mold as it opens
+    pub shallow: Vec<Index>,
+    /// faces that would be trapped by another part of the mesh in
+    /// front of them as the mold opens
+    pub undercuts: Vec<Index>,
+}
+
+impl Default for Draft {
+    fn default() -> Self {
+        Self {
+            pull: Direction::new(0.0,0.0,1.0),
+            minimum: 1f64.to_radians(),
+        }
+    }
+}
+
+impl Draft {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The direction the top half of the mold is pulled off in, which
+    // is +Z by default. The bottom half comes off the other way.
+    pub fn with_pull(mut self, pull: Direction) -> Self {
+        self.pull = pull.normalize();
+        self
+    }
+
+    // The least draft in radians a face needs to release cleanly,
+    // which is one degree by default
+    pub fn with_minimum(mut self, minimum: f64) -> Self {
+        self.minimum = minimum.abs();
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn pull(&self) -> Direction {
+        self.pull
+    }
+
+    pub fn minimum(&self) -> f64 {
+        self.minimum
+    }
+
+    // Measures the draft of every face against the pull, and looks
+    // straight out from the middle of each face the way it releases
+    // for any face of the mesh it would run into. Only faces that
+    // are entered from outside count, so pieces of a mesh that
+    // overlap each other don't trap their own faces.
+    pub fn check(&self, geometry: &Geometry) -> Moldability {
+        span!("analysis.draft", faces = geometry.faces().len());
+        let pull = self.pull.vector();
+        let triangles = geometry
+            .faces()
+            .iter()
+            .map(|f| match f.is_valid(geometry.vertices()) {
+                true => Some(f.triangle(geometry.vertices())),
+                false => None,
+            })
+            .collect::<Vec<Option<Triangle>>>();
+
+        let reach = geometry
+            .bounds()
+            .map(|b| b.size().magnitude())
+            .unwrap_or_default() + 1.0;
+
+        let mut result = Moldability::default();
+        for (index,triangle) in triangles.iter().enumerate() {
+            let Some(triangle) = triangle else {
+                result.angles.push(0.0);
+                continue;
+            };
+
+            let facing = triangle.normal().vector().dot(&pull).clamp(-1.0,1.0);
+            let angle = facing.asin();
+            result.angles.push(angle);
+
+            if angle.abs() < self.minimum - TOLERANCE {
+                result.shallow.push(index);
+                continue;
+            }
+
+            let out = match angle > 0.0 {
+                true => pull,
+                false => pull * -1.0,
+            };
+            let start = triangle.centroid();
+            let end = start + out * reach;
+            let trapped = triangles
+                .iter()
+                .enumerate()
+                .filter(|(other,_)| *other != index)
+                .filter_map(|(_,t)| t.as_ref())
+                .filter(|t| t.normal().vector().dot(&out) < 0.0)
+                .any(|t| t.crossing(&start,&end).is_some());
+
+            if trapped {
+                result.undercuts.push(index);
+            }
+        }
+        result
+    }
+
+}
+
+impl Moldability {
+
+    // True if every face has enough draft and nothing is trapped
+    pub fn is_moldable(&self) -> bool {
+        self.shallow.is_empty() && self.undercuts.is_empty()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::geometry::{Bounds,Vertex,Matrix,Vector};
+
+    fn block(min: (f64,f64,f64), max: (f64,f64,f64)) -> Geometry {
+        Geometry::from_bounds(&Bounds::new(Vertex::new(min.0,min.1,min.2),Vertex::new(max.0,max.1,max.2)))
+    }
+
+    #[test]
+    fn test_draft_walls() {
+        // a box has straight walls all round whichever way it's pulled
+        let geometry = block((0.0,0.0,0.0),(1.0,1.0,1.0));
+        let result = Draft::new().check(&geometry);
+        assert_eq!(result.angles.len(),12);
+        assert_eq!(result.shallow.len(),8);
+        assert!(result.undercuts.is_empty());
+        assert!(!result.is_moldable());
+
+        // tapering the sides in towards the top gives them draft
+        let mut taper = geometry.clone();
+        for vertex in taper.vertices_mut().iter_mut().filter(|v| v.z > 0.5) {
+            *vertex = Vertex::new(0.5,0.5,1.0) + (*vertex - Vertex::new(0.5,0.5,1.0)) * 0.9;
+        }
+        let result = Draft::new().check(&taper);
+        assert!(result.is_moldable());
+        assert!(result.angles.iter().all(|a| a.abs() > 2f64.to_radians()));
+
+        let result = Draft::new().with_minimum(10f64.to_radians()).check(&taper);
+        assert_eq!(result.shallow.len(),8);
+    }
+
+    #[test]
+    fn test_draft_undercuts() {
+        // a spool, where the flanges trap each other's inside faces
+        let mut spool = block((0.0,0.0,0.0),(3.0,3.0,1.0));
+        spool.append(&block((1.2,1.2,1.0),(1.8,1.8,2.0)),&Matrix::identity());
+        spool.append(&block((0.0,0.0,2.0),(3.0,3.0,3.0)),&Matrix::identity());
+
+        let result = Draft::new().check(&spool);
+        assert_eq!(result.undercuts.len(),4);
+        for index in result.undercuts.iter() {
+            let triangle = spool.get(*index);
+            assert!((1.0..=2.0).contains(&triangle.centroid().z));
+        }
+
+        // pulled sideways it's just walls
+        let result = Draft::new().with_pull(Direction::from(Vector::new(1.0,0.0,0.0))).check(&spool);
+        assert!(result.undercuts.is_empty());
+    }
+
+}
@@ -0,0 +1,446 @@
+use std::collections::BTreeSet;
+
+use crate::geometry::{Geometry,Vertex,Vector,Direction};
+use crate::geometry::fit::{basis,circle,rms};
+use crate::part::{Part,Selection};
+use crate::errors::{Error,Context};
+use crate::constant::{Index,TOLERANCE};
+
+// faces within this of facing across an axis are walls along it
+const FLAT: f64 = 1e-3;
+
+// the most directions that are searched for features
+const AXES: usize = 6;
+
+// slots are at least this many times longer than they are wide
+const ELONGATED: f64 = 2.0;
+
+// holes have at least this many sides, so that the corners of
+// a rectangle, which are also on a circle, aren't a hole
+const SIDES: usize = 6;
+
+/// The shape of a feature seen down its axis
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub enum Shape {
+    /// a round hole
+    Hole {
+        diameter: f64,
+    },
+    /// a long rectangular cut with straight sides
+    Slot {
+        length: f64,
+        width: f64,
+        /// the direction the slot runs along
+        direction: Direction,
+    },
+    /// any other recess, with the size of the rectangle around it
+    Pocket {
+        length: f64,
+        width: f64,
+        direction: Direction,
+    },
+}
+
+/// A hole, slot or pocket cut into a part, found from the walls
+/// around it
+#[derive(Debug,Clone,PartialEq)]
+pub struct RecognizedFeature {
+    pub shape: Shape,
+    /// the middle of the feature where it opens onto the surface
+    pub center: Vertex,
+    /// the direction the feature goes into the part
+    pub axis: Direction,
+    pub depth: f64,
+    /// true if the feature goes all the way through the part
+    pub through: bool,
+    /// the vertices of the walls
+    pub vertices: Vec<Index>,
+}
+
+// A set of connected faces that are walls along one axis, in a
+// frame where the axis is Z
+struct Walls {
+    faces: Vec<Index>,
+    vertices: Vec<Index>,
+}
+
+impl RecognizedFeature {
+
+    // Finds the holes, slots and pockets in a part. Features are
+    // searched for along the directions with the most flat area,
+    // where their walls run straight along the direction and open
+    // onto the top or bottom of the part.
+    pub fn from_part(part: &Part) -> Result<Vec<RecognizedFeature>,Error> {
+        Self::features(part).in_part(part.name())
+    }
+
+    fn features(part: &Part) -> Result<Vec<RecognizedFeature>,Error> {
+        span!("analysis.features", part = part.name());
+        let geometry = part.geometry();
+        if geometry.is_empty() {
+            return Err(Error::EmptyGeometry);
+        }
+
+        let mut features: Vec<RecognizedFeature> = Vec::new();
+        for axis in axes(geometry) {
+            for feature in along(geometry,axis) {
+                if !features.iter().any(|f| f.vertices == feature.vertices) {
+                    features.push(feature);
+                }
+            }
+        }
+        Ok(features)
+    }
+
+    // A selection of the walls, for making attributes that
+    // change the feature
+    pub fn selection(&self) -> Selection {
+        Selection::specific(self.vertices.clone())
+    }
+
+    // How much wider a hole or slot is than a fastener of some
+    // diameter, which is negative if the fastener won't fit. None
+    // for pockets.
+    pub fn clearance(&self, diameter: f64) -> Option<f64> {
+        match self.shape {
+            Shape::Hole { diameter: d } => Some(d - diameter),
+            Shape::Slot { width, .. } => Some(width - diameter),
+            Shape::Pocket { .. } => None,
+        }
+    }
+
+}
+
+// The directions with the most flat area facing along them, from
+// the normals of every face with opposite directions counted together
+fn axes(geometry: &Geometry) -> Vec<Vector> {
+    let mut areas: Vec<(Vector,f64)> = Vec::new();
+    let vertices = geometry.vertices();
+    for face in geometry.faces().iter().filter(|f| f.is_valid(vertices)) {
+        let triangle = face.triangle(vertices);
+        let normal = triangle.normal().vector();
+        let area = triangle.area();
+        match areas.iter_mut().find(|(n,_)| n.dot(&normal).abs() > 1.0 - FLAT) {
+            Some((_,total)) => *total += area,
+            None => areas.push((normal,area)),
+        }
+    }
+    areas.sort_by(|a,b| b.1.total_cmp(&a.1));
+
+    // pointing along the largest positive component, so that the
+    // top and bottom of a part don't depend on which face was first
+    areas
+        .into_iter()
+        .take(AXES)
+        .map(|(n,_)| {
+            let largest = [n.x,n.y,n.z].into_iter().fold(0.0,|l: f64,c| if c.abs() > l.abs() { c } else { l });
+            match largest < 0.0 {
+                true => n * -1.0,
+                false => n,
+            }
+        })
+        .collect()
+}
+
+// The features whose walls run along `axis`
+fn along(geometry: &Geometry, axis: Vector) -> Vec<RecognizedFeature> {
+    let (u,v) = basis(&axis);
+    let vertices = geometry.vertices();
+    let heights = vertices.iter().map(|p| p.dot(&axis));
+    let (bottom,top) = heights.fold((f64::MAX,f64::MIN),|(l,h),z| (l.min(z),h.max(z)));
+    let level = TOLERANCE.max((top - bottom) * FLAT);
+
+    let mut result = Vec::new();
+    for walls in walls(geometry,&axis) {
+        let points = walls.vertices
+            .iter()
+            .map(|i| (vertices[*i].dot(&u),vertices[*i].dot(&v)))
+            .collect::<Vec<(f64,f64)>>();
+        let middle = points.iter().fold((0.0,0.0),|(x,y),p| (x + p.0,y + p.1));
+        let middle = (middle.0 / points.len() as f64,middle.1 / points.len() as f64);
+
+        // walls around a recess face in towards the middle of it,
+        // and the outside of the part faces away
+        let facing = walls.faces
+            .iter()
+            .map(|f| {
+                let triangle = geometry.get(*f);
+                let (n,c) = (triangle.normal().vector(),triangle.centroid());
+                let (x,y) = (c.dot(&u) - middle.0,c.dot(&v) - middle.1);
+                (n.dot(&u) * x + n.dot(&v) * y) * triangle.area()
+            })
+            .sum::<f64>();
+        if facing >= 0.0 {
+            continue;
+        }
+
+        let heights = walls.vertices.iter().map(|i| vertices[*i].dot(&axis));
+        let (low,high) = heights.fold((f64::MAX,f64::MIN),|(l,h),z| (l.min(z),h.max(z)));
+        let (opens_top,opens_bottom) = (top - high <= level,low - bottom <= level);
+
+        // walls that don't reach the surface aren't cut from it
+        let (through,depth,surface,inward) = match (opens_top,opens_bottom) {
+            (true,true) => (true,top - bottom,top,axis * -1.0),
+            (true,false) => (false,top - low,top,axis * -1.0),
+            (false,true) => (false,high - bottom,bottom,axis),
+            (false,false) => continue,
+        };
+
+        let Some((shape,(x,y))) = shape(&walls,geometry,&points,(u,v),axis) else {
+            continue;
+        };
+        result.push(RecognizedFeature {
+            shape,
+            center: u * x + v * y + axis * surface,
+            axis: inward.into(),
+            depth,
+            through,
+            vertices: walls.vertices,
+        });
+    }
+    result
+}
+
+// Every connected group of faces that face across the axis
+fn walls(geometry: &Geometry, axis: &Vector) -> Vec<Walls> {
+    let vertices = geometry.vertices();
+    let is_wall = geometry
+        .faces()
+        .iter()
+        .map(|f| f.is_valid(vertices) && f.normal(vertices).dot(axis).abs() < FLAT)
+        .collect::<Vec<bool>>();
+
+    let neighbours = geometry.face_neighbours();
+    let mut seen = vec![false;is_wall.len()];
+    let mut result = Vec::new();
+    for start in 0..is_wall.len() {
+        if !is_wall[start] || seen[start] {
+            continue;
+        }
+        seen[start] = true;
+
+        let mut faces = vec![start];
+        let mut next = 0;
+        while next < faces.len() {
+            for neighbour in neighbours[faces[next]].iter() {
+                if is_wall[*neighbour] && !seen[*neighbour] {
+                    seen[*neighbour] = true;
+                    faces.push(*neighbour);
+                }
+            }
+            next += 1;
+        }
+
+        let vertices = faces
+            .iter()
+            .flat_map(|f| {
+                let face = &geometry.faces()[*f];
+                [face.a,face.b,face.c]
+            })
+            .collect::<BTreeSet<Index>>()
+            .into_iter()
+            .collect();
+        result.push(Walls { faces, vertices });
+    }
+    result
+}
+
+// Whether the walls go around a circle, a long rectangle or something
+// else, and where the middle of it is seen down the axis
+fn shape(walls: &Walls, geometry: &Geometry, points: &[(f64,f64)], (u,v): (Vector,Vector), axis: Vector) -> Option<(Shape,(f64,f64))> {
+    if points.len() < 3 {
+        return None;
+    }
+
+    let mut sides: Vec<Vector> = Vec::new();
+    for face in walls.faces.iter() {
+        let normal = geometry.get(*face).normal().vector();
+        if !sides.iter().any(|n| n.dot(&normal) > 1.0 - FLAT) {
+            sides.push(normal);
+        }
+    }
+
+    let round = sides.len() >= SIDES;
+    if let Some((x,y,radius)) = circle(points).filter(|_| round) {
+        let residual = rms(points.iter().map(|(px,py)| (px - x).hypot(py - y) - radius));
+        if residual <= radius * FLAT + TOLERANCE {
+            return Some((Shape::Hole { diameter: radius * 2.0 },(x,y)));
+        }
+    }
+
+    // the rectangle lined up with the first wall
+    let normal = geometry.get(walls.faces[0]).normal().vector();
+    let across = (u * normal.dot(&u) + v * normal.dot(&v)).normalize();
+    let side = axis.cross(&across).normalize();
+    let a = (across.dot(&u),across.dot(&v));
+    let b = (side.dot(&u),side.dot(&v));
+
+    let local = points
+        .iter()
+        .map(|(x,y)| (x * a.0 + y * a.1,x * b.0 + y * b.1))
+        .collect::<Vec<(f64,f64)>>();
+    let (mut min,mut max) = ((f64::MAX,f64::MAX),(f64::MIN,f64::MIN));
+    for (x,y) in local.iter() {
+        min = (min.0.min(*x),min.1.min(*y));
+        max = (max.0.max(*x),max.1.max(*y));
+    }
+
+    let (w,l) = (max.0 - min.0,max.1 - min.1);
+    let (cx,cy) = ((min.0 + max.0) / 2.0,(min.1 + max.1) / 2.0);
+    let middle = (cx * a.0 + cy * b.0,cx * a.1 + cy * b.1);
+    let limit = w.max(l) * FLAT + TOLERANCE;
+    let square = local.iter().all(|(x,y)| {
+        let edge = (x - min.0).abs().min((x - max.0).abs());
+        let end = (y - min.1).abs().min((y - max.1).abs());
+        edge.min(end) <= limit
+    });
+
+    let (length,width,direction) = match l >= w {
+        true => (l,w,side),
+        false => (w,l,across),
+    };
+    let direction = direction.into();
+    match square && length >= width * ELONGATED {
+        true => Some((Shape::Slot { length, width, direction },middle)),
+        false => Some((Shape::Pocket { length, width, direction },middle)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::geometry::ParseMode;
+    use std::f64::consts::TAU;
+
+    // A round plate with a hole through the middle, or down to a
+    // floor if it's blind
+    fn washer(floor: Option<f64>) -> Part {
+        const SIDES: usize = 16;
+        let ring = |radius: f64, z: f64| (0..SIDES).map(move |i| {
+            let angle = TAU * i as f64 / SIDES as f64;
+            Vertex::new(radius * angle.cos(),radius * angle.sin(),z)
+        });
+
+        let mut points = Vec::new();
+        points.extend(ring(2.0,1.0));
+        points.extend(ring(2.0,0.0));
+        points.extend(ring(0.5,1.0));
+        points.extend(ring(0.5,floor.unwrap_or(0.0)));
+        points.push(Vertex::new(0.0,0.0,floor.unwrap_or(0.0)));
+        points.push(Vertex::new(0.0,0.0,0.0));
+
+        // each triangle wound to face the way it should
+        let mut indices = Vec::new();
+        let mut add = |[a,b,c]: [usize;3], out: Vector| {
+            let n = (points[b] - points[a]).cross(&(points[c] - points[a]));
+            match n.dot(&out) > 0.0 {
+                true => indices.extend([a + 1,b + 1,c + 1]),
+                false => indices.extend([a + 1,c + 1,b + 1]),
+            }
+        };
+
+        let (up,down) = (Vector::new(0.0,0.0,1.0),Vector::new(0.0,0.0,-1.0));
+        let (floor_center,bottom_center) = (SIDES * 4,SIDES * 4 + 1);
+        for i in 0..SIDES {
+            let j = (i + 1) % SIDES;
+            let angle = TAU * (i as f64 + 0.5) / SIDES as f64;
+            let radial = Vector::new(angle.cos(),angle.sin(),0.0);
+            let (ot,ob,it,ib) = (0,SIDES,SIDES * 2,SIDES * 3);
+
+            add([ot + i,ot + j,ob + j],radial);
+            add([ot + i,ob + j,ob + i],radial);
+            add([it + i,it + j,ib + j],radial * -1.0);
+            add([it + i,ib + j,ib + i],radial * -1.0);
+            add([ot + i,ot + j,it + j],up);
+            add([ot + i,it + j,it + i],up);
+            match floor {
+                Some(_) => {
+                    add([ib + i,ib + j,floor_center],up);
+                    add([ob + i,ob + j,bottom_center],down);
+                },
+                None => {
+                    add([ob + i,ob + j,ib + j],down);
+                    add([ob + i,ib + j,ib + i],down);
+                },
+            }
+        }
+
+        let values = points.iter().flat_map(|p| [p.x,p.y,p.z]).collect();
+        let geometry = Geometry::make(values,indices);
+        assert!(geometry.volume() > 0.0);
+        Part::new("washer").with_geometry(geometry).build().unwrap()
+    }
+
+    #[test]
+    fn test_features_holes() {
+        let features = RecognizedFeature::from_part(&washer(None)).unwrap();
+        assert_eq!(features.len(),1);
+
+        let hole = &features[0];
+        assert!(matches!(hole.shape,Shape::Hole { diameter } if (diameter - 1.0).abs() < 1e-9));
+        assert!(hole.through);
+        assert_relative_eq!(hole.depth,1.0,epsilon = 1e-9);
+        assert_relative_eq!(hole.center.distance(&Vertex::new(0.0,0.0,1.0)),0.0,epsilon = 1e-9);
+        assert_relative_eq!(hole.axis.z.abs(),1.0,epsilon = 1e-9);
+        assert_eq!(hole.vertices.len(),32);
+        assert_relative_eq!(hole.clearance(0.8).unwrap(),0.2,epsilon = 1e-9);
+
+        // a blind hole goes down from the top to its floor
+        let features = RecognizedFeature::from_part(&washer(Some(0.4))).unwrap();
+        assert_eq!(features.len(),1);
+        let hole = &features[0];
+        assert!(!hole.through);
+        assert_relative_eq!(hole.depth,0.6,epsilon = 1e-9);
+        assert_relative_eq!(hole.axis.z,-1.0,epsilon = 1e-9);
+        assert_relative_eq!(hole.center.z,1.0,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_features_slots_and_pockets() {
+        // a 4 x 3 x 1 plate with a 1 x 1 pocket half way down and a
+        // 0.5 x 1 slot through it, with the top left open
+        let plate = "
+            v 0 0 0\nv 4 0 0\nv 4 3 0\nv 0 3 0
+            v 0 0 1\nv 4 0 1\nv 4 3 1\nv 0 3 1
+            v 1 1 1\nv 2 1 1\nv 2 2 1\nv 1 2 1
+            v 1 1 0.5\nv 2 1 0.5\nv 2 2 0.5\nv 1 2 0.5
+            v 3 1 0\nv 3.5 1 0\nv 3.5 2 0\nv 3 2 0
+            v 3 1 1\nv 3.5 1 1\nv 3.5 2 1\nv 3 2 1
+            f 1 3 2\nf 1 4 3
+            f 1 2 6\nf 1 6 5\nf 2 3 7\nf 2 7 6
+            f 3 4 8\nf 3 8 7\nf 4 1 5\nf 4 5 8
+            f 13 14 15\nf 13 15 16
+            f 9 14 13\nf 9 10 14\nf 10 15 14\nf 10 11 15
+            f 11 16 15\nf 11 12 16\nf 12 13 16\nf 12 9 13
+            f 17 21 22\nf 17 22 18\nf 18 22 23\nf 18 23 19
+            f 19 23 24\nf 19 24 20\nf 20 24 21\nf 20 21 17
+        ";
+        let geometry = Geometry::parse(plate,ParseMode::Lenient).unwrap();
+        let part = Part::new("plate").with_geometry(geometry).build().unwrap();
+
+        let features = RecognizedFeature::from_part(&part).unwrap();
+        assert_eq!(features.len(),2);
+
+        let pocket = features.iter().find(|f| matches!(f.shape,Shape::Pocket { .. })).unwrap();
+        assert!(!pocket.through);
+        assert_relative_eq!(pocket.depth,0.5,epsilon = 1e-9);
+        assert_relative_eq!(pocket.center.distance(&Vertex::new(1.5,1.5,1.0)),0.0,epsilon = 1e-9);
+        assert_eq!(pocket.clearance(0.1),None);
+
+        let slot = features.iter().find(|f| matches!(f.shape,Shape::Slot { .. })).unwrap();
+        let Shape::Slot { length, width, direction } = slot.shape else {
+            unreachable!();
+        };
+        assert!(slot.through);
+        assert_relative_eq!(length,1.0,epsilon = 1e-9);
+        assert_relative_eq!(width,0.5,epsilon = 1e-9);
+        assert_relative_eq!(direction.y.abs(),1.0,epsilon = 1e-9);
+        assert_relative_eq!(slot.center.distance(&Vertex::new(3.25,1.5,1.0)),0.0,epsilon = 1e-9);
+        assert!(matches!(slot.selection(),Selection::Specific(v) if v == vec![16,17,18,19,20,21,22,23]));
+
+        let error = RecognizedFeature::from_part(&Part::new("empty")).unwrap_err();
+        assert!(matches!(error.root(),Error::EmptyGeometry));
+    }
+
+}
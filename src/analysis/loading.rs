@@ -0,0 +1,247 @@
+use std::collections::BTreeMap;
+
+use crate::analysis::{Beam,Load};
+use crate::assembly::{Assembly,Instance};
+use crate::geometry::{Vector,Direction,Transform,Matrix,Profile};
+use crate::materials::Database;
+use crate::errors::{Error,Context};
+use crate::constant::Index;
+
+/// Settings for estimating how the members of a frame respond
+/// to loads, treating each as simply supported at its ends
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub struct Loading {
+    direction: Vector,
+    limit: f64,
+}
+
+/// How one loaded member responds
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct Estimate {
+    pub instance: Index,
+    pub beam: Beam,
+    /// the largest bending moment in newton meters
+    pub moment: f64,
+    /// the largest bending stress in pascals
+    pub stress: f64,
+    /// the largest deflection in meters
+    pub deflection: f64,
+}
+
+/// A problem found while estimating
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub enum Warning {
+    /// the member sags more than the span over the limit allows
+    Deflection { instance: Index, deflection: f64, allowed: f64 },
+    /// the member's material or its modulus isn't known, so
+    /// it wasn't estimated
+    UnknownModulus { instance: Index },
+}
+
+/// The estimates for every loaded member, and any warnings
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Report {
+    pub estimates: Vec<Estimate>,
+    pub warnings: Vec<Warning>,
+}
+
+impl Default for Loading {
+    fn default() -> Self {
+        Self {
+            direction: Vector::new(0.0,0.0,-1.0),
+            limit: 360.0,
+        }
+    }
+}
+
+impl Loading {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The direction loads push in assembly space, which is
+    // straight down the Z axis by default
+    pub fn with_direction(mut self, direction: Vector) -> Self {
+        self.direction = direction.normalize();
+        self
+    }
+
+    // The allowed deflection as a fraction of the span, like 360
+    // for L/360, the usual limit for floors
+    pub fn with_limit(mut self, limit: f64) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn direction(&self) -> Vector {
+        self.direction
+    }
+
+    pub fn limit(&self) -> f64 {
+        self.limit
+    }
+
+    // Estimates each loaded instance as a beam spanning its longest
+    // side, with the section of the part half way along and
+    // stiffness from its material. Loads on one member
+    // are added, peak to peak, which overestimates loads that peak
+    // in different places.
+    pub fn estimate(&self, assembly: &Assembly, database: &Database, loads: &[(Index,Load)]) -> Result<Report,Error> {
+        span!("analysis.loading", loads = loads.len());
+        let len = assembly.instances().len();
+
+        let mut members: BTreeMap<Index,Vec<Load>> = BTreeMap::new();
+        for (i,(index,load)) in loads.iter().enumerate() {
+            assembly
+                .get(*index)
+                .ok_or(Error::IndexOutOfRange { index: *index, len })
+                .in_item(i)?;
+            members.entry(*index).or_default().push(*load);
+        }
+
+        let mut report = Report::default();
+        for (index,loads) in members.into_iter() {
+            let instance = &assembly.instances()[index];
+            let modulus = database
+                .material(instance.part())
+                .and_then(|m| m.modulus());
+
+            let Some(beam) = modulus.and_then(|m| self.beam(instance,m)) else {
+                report.warnings.push(Warning::UnknownModulus { instance: index });
+                continue;
+            };
+
+            let estimate = Estimate {
+                instance: index,
+                beam,
+                moment: loads.iter().map(|l| beam.moment(l)).sum(),
+                stress: loads.iter().map(|l| beam.stress(l)).sum(),
+                deflection: loads.iter().map(|l| beam.deflection(l)).sum(),
+            };
+
+            let allowed = beam.span() / self.limit;
+            if estimate.deflection > allowed {
+                report.warnings.push(Warning::Deflection {
+                    instance: index,
+                    deflection: estimate.deflection,
+                    allowed,
+                });
+            }
+            report.estimates.push(estimate);
+        }
+        Ok(report)
+    }
+
+    // The beam an instance makes, spanning the longest side of the
+    // part. The section is the part cut in half across the span, or
+    // the rectangle of the other two sides if it can't be cut, and
+    // bends along whichever of those sides lines up best with the
+    // load. None if the part doesn't have any geometry.
+    fn beam(&self, instance: &Instance, modulus: f64) -> Option<Beam> {
+        let geometry = instance.part().geometry();
+        let bounds = geometry.bounds()?;
+        let (min,size) = (axes(&bounds.min),axes(&bounds.size()));
+
+        let long = (0..3).max_by(|a,b| size[*a].total_cmp(&size[*b]))?;
+        let (a,b) = ((long + 1) % 3,(long + 2) % 3);
+
+        let alignment = |axis: Index| {
+            let [x,y,z] = unit(axis);
+            let mut direction = Direction::new(x,y,z);
+            direction.transform(instance.transform());
+            direction.normalize().dot(&self.direction).abs()
+        };
+        let along_b = alignment(b) > alignment(a);
+
+        // turn the part so that the span runs up Z, with the
+        // other two sides along X and Y, and cut it half way
+        let rows = [unit(a),unit(b),unit(long)];
+        let mut turned = geometry.clone();
+        turned.transform(&Matrix::new([
+            rows[0][0],rows[0][1],rows[0][2],0.0,
+            rows[1][0],rows[1][1],rows[1][2],0.0,
+            rows[2][0],rows[2][1],rows[2][2],0.0,
+            0.0,0.0,0.0,1.0,
+        ]));
+
+        let loops = turned
+            .slice(min[long] + size[long] / 2.0)
+            .into_iter()
+            .filter(|l| l.is_closed())
+            .collect();
+
+        Some(match Profile::from_loops(loops) {
+            Ok(profile) => Beam::from_properties(size[long],&profile.properties(),modulus,along_b),
+            Err(_) if along_b => Beam::rectangle(size[long],size[a],size[b],modulus),
+            Err(_) => Beam::rectangle(size[long],size[b],size[a],modulus),
+        })
+    }
+
+}
+
+fn axes(v: &Vector) -> [f64;3] {
+    [v.x,v.y,v.z]
+}
+
+fn unit(axis: Index) -> [f64;3] {
+    let mut unit = [0.0;3];
+    unit[axis] = 1.0;
+    unit
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::models;
+    use crate::part::{Part,Metadata};
+    use crate::geometry::Matrix;
+
+    fn stud(material: &str) -> Part {
+        Part::new("stud")
+            .with_geometry(models::M2X4.geometry())
+            .with_metadata(Metadata::new().with_material(material))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_loading_estimate() {
+        let database = Database::builtin();
+
+        // lying flat, then turned onto its edge
+        let assembly = Assembly::new("floor")
+            .with_part(stud("2x4"),Matrix::identity())
+            .with_part(stud("2x4"),Matrix::rotate_x(std::f64::consts::FRAC_PI_2))
+            .with_part(stud("cheese"),Matrix::identity());
+
+        let load = Load::Uniform(200.0);
+        let report = Loading::new()
+            .build()
+            .estimate(&assembly,&database,&[(0,load),(1,load),(2,load)])
+            .unwrap();
+
+        assert_eq!(report.estimates.len(),2);
+        let (flat,edge) = (report.estimates[0],report.estimates[1]);
+        assert_relative_eq!(flat.beam.span(),2.4384);
+        assert_relative_eq!(flat.beam.inertia(),0.0889 * 0.0381f64.powi(3) / 12.0,epsilon = 1e-15);
+        assert_relative_eq!(flat.moment,edge.moment);
+        assert!(edge.deflection < flat.deflection);
+
+        // only the flat one sags past L/360
+        assert!(flat.deflection > 2.4384 / 360.0);
+        assert!(edge.deflection < 2.4384 / 360.0);
+        assert_eq!(report.warnings.len(),2);
+        assert!(matches!(report.warnings[0],Warning::Deflection { instance: 0, .. }));
+        assert_eq!(report.warnings[1],Warning::UnknownModulus { instance: 2 });
+
+        let invalid = Loading::new().estimate(&assembly,&database,&[(5,load)]);
+        assert!(matches!(invalid,Err(Error::InItem(0,_))));
+    }
+
+}
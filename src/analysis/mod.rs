@@ -0,0 +1,21 @@
+mod beam;
+mod loading;
+mod balance;
+mod rules;
+mod stack;
+mod features;
+mod bom;
+mod cuts;
+mod sequence;
+mod draft;
+
+pub use beam::{Beam,Load};
+pub use loading::{Loading,Estimate,Warning,Report};
+pub use balance::{Balance,Stability};
+pub use rules::{Rules,Rule,Violation};
+pub use stack::{Stack,Link,Stackup};
+pub use features::{RecognizedFeature,Shape};
+pub use bom::{Bom,Line};
+pub use cuts::{CutList,Board,Piece};
+pub use sequence::{Sequence,Step};
+pub use draft::{Draft,Moldability};
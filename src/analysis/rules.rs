@@ -0,0 +1,296 @@
+use std::fs;
+use std::path::Path;
+use std::f64::consts::FRAC_PI_4;
+use std::convert::TryFrom;
+
+use crate::assembly::Assembly;
+use crate::geometry::{Geometry,Vertex,Bounds};
+use crate::part::{Filter,Units};
+use crate::errors::Error;
+use crate::constant::{Index,COMMENT_TAG};
+use crate::utilities;
+
+// surfaces bending more than this count as an edge
+// for fastener spacing
+const EDGE_ANGLE: f64 = FRAC_PI_4;
+
+/// A design rule that an assembly should follow. Distances are
+/// in meters.
+#[derive(Debug,Clone,PartialEq)]
+pub enum Rule {
+    /// the surfaces of parts matching one filter stay at least
+    /// this far from parts matching the other
+    Clearance { a: Filter, b: Filter, distance: f64 },
+    /// connections on matching parts are at least this far
+    /// from an edge of the part
+    EdgeDistance { filter: Filter, distance: f64 },
+    /// matching parts are no longer than this between supports
+    Span { filter: Filter, distance: f64 },
+}
+
+/// Something in an assembly that breaks a rule
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub enum Violation {
+    /// two instances are closer than the clearance allows
+    Clearance { rule: Index, a: Index, b: Index, distance: f64 },
+    /// a connection on an instance is too close to an edge
+    EdgeDistance { rule: Index, instance: Index, connection: Index, distance: f64 },
+    /// an instance spans further than allowed
+    Span { rule: Index, instance: Index, span: f64 },
+}
+
+/// A set of design rules, usually read from a rules file
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_rule(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn add(&mut self, rule: Rule) {
+        self.rules.push(rule);
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self,Error> {
+        Self::try_from(fs::read_to_string(path)?.as_str())
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(),Error> {
+        fs::write(path,String::from(self))?;
+        Ok(())
+    }
+
+    // Every place the assembly breaks a rule, in rule order
+    pub fn check(&self, assembly: &Assembly) -> Vec<Violation> {
+        span!("analysis.rules", rules = self.rules.len());
+        let geometries = utilities::map(assembly.instances(),|i| i.geometry());
+        let matching = |filter: &Filter| assembly
+            .instances()
+            .iter()
+            .enumerate()
+            .filter(|(_,i)| filter.matches(i.part().metadata()))
+            .map(|(i,_)| i)
+            .collect::<Vec<Index>>();
+
+        let mut violations = Vec::new();
+        for (rule,item) in self.rules.iter().enumerate() {
+            match item {
+                Rule::Clearance { a, b, distance } => {
+                    let (a,b) = (matching(a),matching(b));
+                    let mut pairs = a
+                        .iter()
+                        .flat_map(|i| b.iter().map(move |j| (*i.min(j),*i.max(j))))
+                        .filter(|(i,j)| i != j)
+                        .collect::<Vec<(Index,Index)>>();
+                    pairs.sort();
+                    pairs.dedup();
+
+                    for (a,b) in pairs.into_iter() {
+                        let gap = gap(&geometries[a],&geometries[b],*distance);
+                        if gap < *distance {
+                            violations.push(Violation::Clearance { rule, a, b, distance: gap });
+                        }
+                    }
+                },
+                Rule::EdgeDistance { filter, distance } => {
+                    for instance in matching(filter) {
+                        let part = assembly.instances()[instance].part();
+                        let edges = part.geometry().feature_edges(EDGE_ANGLE);
+                        let vertices = part.geometry().vertices();
+                        for (connection,c) in part.connections().iter().enumerate() {
+                            let nearest = edges
+                                .iter()
+                                .map(|(a,b)| segment(&c.point(),&vertices[*a],&vertices[*b]))
+                                .fold(f64::INFINITY,f64::min);
+                            if nearest < *distance {
+                                violations.push(Violation::EdgeDistance { rule, instance, connection, distance: nearest });
+                            }
+                        }
+                    }
+                },
+                Rule::Span { filter, distance } => {
+                    for instance in matching(filter) {
+                        let span = geometries[instance]
+                            .bounds()
+                            .map(|b| b.size())
+                            .map(|s| s.x.max(s.y).max(s.z))
+                            .unwrap_or(0.0);
+                        if span > *distance {
+                            violations.push(Violation::Span { rule, instance, span });
+                        }
+                    }
+                },
+            }
+        }
+        violations
+    }
+
+}
+
+// The smallest distance between the surfaces of two meshes, from
+// the vertices of each to the faces of the other. Anything further
+// apart than the limit, going by their bounds, is only checked that
+// far.
+fn gap(a: &Geometry, b: &Geometry, limit: f64) -> f64 {
+    let (Some(ab),Some(bb)) = (a.bounds(),b.bounds()) else {
+        return f64::INFINITY;
+    };
+
+    let grown = |mut bounds: Bounds| {
+        bounds.min = bounds.min - Vertex::new(limit,limit,limit);
+        bounds.max = bounds.max + Vertex::new(limit,limit,limit);
+        bounds
+    };
+
+    if !grown(ab).intersects(&bb) {
+        return limit;
+    }
+
+    let one = a.vertices().iter().map(|v| b.distance(v));
+    let two = b.vertices().iter().map(|v| a.distance(v));
+    one.chain(two).fold(f64::INFINITY,f64::min)
+}
+
+// The distance from a point to a line segment
+fn segment(point: &Vertex, a: &Vertex, b: &Vertex) -> f64 {
+    let edge = *b - *a;
+    let length = edge.dot(&edge);
+    let t = match length > 0.0 {
+        true => ((*point - *a).dot(&edge) / length).clamp(0.0,1.0),
+        false => 0.0,
+    };
+    point.distance(&(*a + edge * t))
+}
+
+// Rules files have one rule per line, with `#` comments, like:
+//
+//     units mm
+//     clearance tag=drawer tag=hinge 3
+//     edge tag=panel 12
+//     span layer=shelves 800
+//
+// Distances are in meters unless a `units` line comes before them.
+impl TryFrom<&str> for Rules {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut rules = Rules::new();
+        let mut scale = 1.0;
+
+        for (index,line) in value.lines().enumerate() {
+            let invalid = || Error::InvalidLine { line: index + 1, text: line.into() };
+            let text = line.split(COMMENT_TAG).next().unwrap_or_default();
+            let words = text.split_whitespace().collect::<Vec<&str>>();
+
+            let filter = |text: &str| Filter::try_from(text).map_err(|_| invalid());
+            let distance = |text: &str| text
+                .parse::<f64>()
+                .map(|d| d / scale)
+                .map_err(|_| invalid());
+
+            match words[..] {
+                [] => {},
+                ["units",units] => scale = Units::try_from(units).map_err(|_| invalid())?.per_meter(),
+                ["clearance",a,b,d] => rules.add(Rule::Clearance { a: filter(a)?, b: filter(b)?, distance: distance(d)? }),
+                ["edge",f,d] => rules.add(Rule::EdgeDistance { filter: filter(f)?, distance: distance(d)? }),
+                ["span",f,d] => rules.add(Rule::Span { filter: filter(f)?, distance: distance(d)? }),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(rules)
+    }
+}
+
+impl From<&Rules> for String {
+    fn from(rules: &Rules) -> Self {
+        rules.rules
+            .iter()
+            .map(|r| match r {
+                Rule::Clearance { a, b, distance } => format!("clearance {} {} {}",String::from(a),String::from(b),distance),
+                Rule::EdgeDistance { filter, distance } => format!("edge {} {}",String::from(filter),distance),
+                Rule::Span { filter, distance } => format!("span {} {}",String::from(filter),distance),
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::geometry::Matrix;
+    use crate::part::{Part,Metadata,Connection};
+    use crate::testing;
+
+    // a unit cube tagged and named for what it stands in for
+    fn tagged(tag: &str) -> Part {
+        Part::new(tag)
+            .with_geometry(testing::cube())
+            .with_metadata(Metadata::new().with_tag(tag))
+            .with_connection(Connection::new(Vertex::new(0.5,0.05,1.0),0.01))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_rules_parse() {
+        let text = "
+            # cabinet rules
+            units mm
+            clearance tag=door tag=hinge,!layer=hidden 3
+            edge tag=panel 12.5
+            span * 800 # shelves sag
+        ";
+        let rules = Rules::try_from(text).unwrap();
+        assert_eq!(rules.rules().len(),3);
+        assert_eq!(rules.rules()[1],Rule::EdgeDistance { filter: Filter::tag("panel"), distance: 0.0125 });
+
+        let again = Rules::try_from(String::from(&rules).as_str()).unwrap();
+        assert_eq!(again,rules);
+
+        assert!(matches!(Rules::try_from("span *"),Err(Error::InvalidLine { line: 1, .. })));
+        assert!(matches!(Rules::try_from("\nedge size=2 1"),Err(Error::InvalidLine { line: 2, .. })));
+        assert!(Rules::try_from("units furlongs").is_err());
+    }
+
+    #[test]
+    fn test_rules_check() {
+        let assembly = Assembly::new("cabinet")
+            .with_part(tagged("door"),Matrix::identity())
+            .with_part(tagged("hinge"),Matrix::translate(1.002,0.0,0.0))
+            .with_part(tagged("hinge"),Matrix::translate(5.0,0.0,0.0));
+
+        let rules = Rules::try_from("
+            units mm
+            clearance tag=door tag=hinge 3
+            edge tag=hinge 60
+            span tag=door 800
+        ").unwrap();
+
+        let violations = rules.check(&assembly);
+        assert_eq!(violations.len(),4);
+        assert!(matches!(violations[0],Violation::Clearance { rule: 0, a: 0, b: 1, distance } if (distance - 0.002).abs() < 1e-9));
+        assert!(matches!(violations[1],Violation::EdgeDistance { rule: 1, instance: 1, connection: 0, distance } if (distance - 0.05).abs() < 1e-9));
+        assert!(matches!(violations[2],Violation::EdgeDistance { rule: 1, instance: 2, .. }));
+        assert!(matches!(violations[3],Violation::Span { rule: 2, instance: 0, span } if span == 1.0));
+    }
+
+}
@@ -0,0 +1,260 @@
+use crate::assembly::{Assembly,Instance};
+use crate::geometry::{Geometry,Direction,Transform,Vertex};
+use crate::errors::{Error,Context};
+use crate::constant::{Index,TOLERANCE};
+
+/// An instance slid out of an assembly, or into it, along a
+/// direction in assembly space
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct Step {
+    pub instance: Index,
+    pub direction: Direction,
+}
+
+/// An order to take an assembly apart one instance at a time, where
+/// each instance slides straight out along one of its connection
+/// axes without passing through anything still in the assembly.
+/// Instances that can't be taken out that way are left as blocked.
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Sequence {
+    steps: Vec<Step>,
+    blocked: Vec<Index>,
+}
+
+impl Sequence {
+
+    // Takes out the first instance that's free until there are none
+    // left. Taking an instance out never blocks another, so if this
+    // gets stuck any other order would too.
+    pub fn new(assembly: &Assembly) -> Self {
+        span!("analysis.sequence", instances = assembly.instances().len());
+        let geometries = assembly
+            .instances()
+            .iter()
+            .map(Instance::geometry)
+            .collect::<Vec<Geometry>>();
+
+        let mut present = vec![true;geometries.len()];
+        let mut steps = Vec::new();
+        while let Some(step) = (0..geometries.len())
+            .filter(|i| present[*i])
+            .find_map(|i| clear(assembly,&geometries,&present,i).map(|direction| Step { instance: i, direction }))
+        {
+            present[step.instance] = false;
+            steps.push(step);
+        }
+
+        let blocked = (0..present.len()).filter(|i| present[*i]).collect();
+        Self { steps, blocked }
+    }
+
+    // Checks that the instances can be put together in the given
+    // order, each sliding in without passing through the ones that
+    // are already there. Fails at the first instance that can't.
+    pub fn check(assembly: &Assembly, order: &[Index]) -> Result<Vec<Step>,Error> {
+        span!("analysis.sequence.check", instances = order.len());
+        let count = assembly.instances().len();
+        let geometries = assembly
+            .instances()
+            .iter()
+            .map(Instance::geometry)
+            .collect::<Vec<Geometry>>();
+
+        let mut present = vec![false;count];
+        let mut steps = Vec::with_capacity(order.len());
+        for (index,&instance) in order.iter().enumerate() {
+            if instance >= count {
+                return Err(Error::IndexOutOfRange { index: instance, len: count }).in_item(index);
+            }
+            present[instance] = true;
+            let direction = clear(assembly,&geometries,&present,instance)
+                .ok_or(Error::Blocked(instance))
+                .in_item(index)?;
+            steps.push(Step { instance, direction: -direction });
+        }
+        Ok(steps)
+    }
+
+    // The instances in the order they come out, each with the
+    // direction it slides out in
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    // The instances in the order they go in, each with the
+    // direction it slides in, which is the disassembly backwards
+    pub fn assembly(&self) -> Vec<Step> {
+        self.steps
+            .iter()
+            .rev()
+            .map(|s| Step { instance: s.instance, direction: -s.direction })
+            .collect()
+    }
+
+    // Instances that couldn't be taken out, in order
+    pub fn blocked(&self) -> &[Index] {
+        &self.blocked
+    }
+
+    // True if every instance could be taken out
+    pub fn is_feasible(&self) -> bool {
+        self.blocked.is_empty()
+    }
+
+}
+
+// The directions an instance can slide out along in assembly space.
+// A sliding or turning joint can move either way along its axis, and
+// a fixed joint pulls away from its connection towards the middle of
+// the part. A part without connections can move along any axis.
+fn directions(instance: &Instance) -> Vec<Direction> {
+    let part = instance.part();
+    let center = part
+        .geometry()
+        .bounds()
+        .map(|b| b.center())
+        .unwrap_or_default();
+
+    let mut result: Vec<Direction> = Vec::new();
+    for connection in part.connections().iter() {
+        let found = match connection.joint().axis() {
+            Some(axis) => vec![axis,-axis],
+            None => vec![Direction::from(center - connection.point())],
+        };
+        for mut direction in found {
+            if direction.magnitude() <= TOLERANCE {
+                continue;
+            }
+            direction.transform(instance.transform());
+            let direction = direction.normalize();
+            if !result.iter().any(|d| (d.vector() - direction.vector()).magnitude() <= TOLERANCE) {
+                result.push(direction);
+            }
+        }
+    }
+
+    if part.connections().is_empty() {
+        result = vec![
+            Direction::new(1.0,0.0,0.0), Direction::new(-1.0,0.0,0.0),
+            Direction::new(0.0,1.0,0.0), Direction::new(0.0,-1.0,0.0),
+            Direction::new(0.0,0.0,1.0), Direction::new(0.0,0.0,-1.0),
+        ];
+    }
+    result
+}
+
+// The first direction an instance can slide along until it's clear
+// of everything else that's present, if there is one. The path is
+// the hull of the instance where it starts and where it ends up.
+fn clear(assembly: &Assembly, geometries: &[Geometry], present: &[bool], instance: Index) -> Option<Direction> {
+    let others = (0..geometries.len())
+        .filter(|i| present[*i] && *i != instance)
+        .collect::<Vec<Index>>();
+
+    let hull = geometries[instance].convex_hull();
+    let Some(bounds) = others
+        .iter()
+        .filter_map(|i| geometries[*i].bounds())
+        .chain(hull.bounds())
+        .reduce(|a,b| a.union(&b))
+    else {
+        return directions(&assembly.instances()[instance]).first().copied();
+    };
+
+    let distance = bounds.size().magnitude() + TOLERANCE;
+    directions(&assembly.instances()[instance])
+        .into_iter()
+        .find(|direction| {
+            let offset = direction.vector() * distance;
+            let points = hull
+                .vertices()
+                .iter()
+                .flat_map(|v| [*v,*v + offset])
+                .collect::<Vec<Vertex>>();
+            let path = Geometry::hull(&points);
+            others
+                .iter()
+                .all(|i| !path.intersects(&geometries[*i]))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::geometry::Matrix;
+    use crate::part::{Part,Connection,Joint};
+
+    // A box between two corners
+    fn block(min: (f64,f64,f64), max: (f64,f64,f64)) -> Geometry {
+        let (a,b) = (min,max);
+        Geometry::make(
+            vec![
+                a.0,a.1,a.2, b.0,a.1,a.2, b.0,b.1,a.2, a.0,b.1,a.2,
+                a.0,a.1,b.2, b.0,a.1,b.2, b.0,b.1,b.2, a.0,b.1,b.2,
+            ],
+            vec![
+                1,3,2, 1,4,3, 5,6,7, 5,7,8,
+                1,2,6, 1,6,5, 2,3,7, 2,7,6,
+                3,4,8, 3,8,7, 4,1,5, 4,5,8,
+            ])
+    }
+
+    fn part(name: &str, geometry: Geometry, connection: Connection) -> Part {
+        Part::new(name)
+            .with_geometry(geometry)
+            .with_connection(connection)
+            .build()
+            .unwrap()
+    }
+
+    // A base with a post screwed into it and a cap screwed onto the
+    // top of the post
+    fn post() -> Assembly {
+        let point = Vertex::new(0.5,0.5,0.1);
+        Assembly::new("post")
+            .with_part(part("base",block((0.0,0.0,0.0),(1.0,1.0,0.1)),Connection::new(point,0.01)),Matrix::identity())
+            .with_part(part("post",block((0.4,0.4,0.1),(0.6,0.6,0.5)),Connection::new(point,0.01)),Matrix::identity())
+            .with_part(part("cap",block((0.3,0.3,0.5),(0.7,0.7,0.6)),Connection::new(Vertex::new(0.5,0.5,0.5),0.01)),Matrix::identity())
+    }
+
+    #[test]
+    fn test_sequence_order() {
+        let assembly = post();
+        let sequence = Sequence::new(&assembly);
+        assert!(sequence.is_feasible());
+
+        // the base drops off the bottom, then the cap has to come off
+        // the top before the post can
+        let order = sequence.steps().iter().map(|s| s.instance).collect::<Vec<Index>>();
+        assert_eq!(order,vec![0,2,1]);
+        assert_relative_eq!(sequence.steps()[0].direction.z,-1.0);
+        assert_relative_eq!(sequence.assembly()[0].direction.z,-1.0);
+
+        assert_eq!(Sequence::check(&assembly,&[1,2,0]).unwrap().len(),3);
+        assert!(matches!(
+            Sequence::check(&assembly,&[2,1,0]),
+            Err(Error::InItem(1,e)) if matches!(*e,Error::Blocked(1))));
+        assert!(matches!(Sequence::check(&assembly,&[3]),Err(Error::InItem(0,_))));
+    }
+
+    #[test]
+    fn test_sequence_blocked() {
+        // a ship in a bottle, where the bottle is a box with a hollow
+        // inside it and the ship slides along its keel
+        let mut bottle = block((0.0,0.0,0.0),(1.0,1.0,1.0));
+        bottle.append(&block((0.1,0.1,0.1),(0.9,0.9,0.9)),&Matrix::identity());
+        let ship = Connection::new(Vertex::new(0.3,0.5,0.5),0.01)
+            .with_joint(Joint::prismatic(Direction::new(1.0,0.0,0.0),0.0,1.0));
+
+        let assembly = Assembly::new("bottle")
+            .with_part(part("bottle",bottle,Connection::new(Vertex::new(0.5,0.5,0.0),0.01)),Matrix::identity())
+            .with_part(part("ship",block((0.3,0.4,0.4),(0.7,0.6,0.6)),ship),Matrix::identity());
+
+        let sequence = Sequence::new(&assembly);
+        assert!(!sequence.is_feasible());
+        assert!(sequence.steps().is_empty());
+        assert_eq!(sequence.blocked(),&[0,1]);
+    }
+
+}
@@ -0,0 +1,219 @@
+use crate::assembly::Assembly;
+use crate::geometry::{Vertex,Direction,Transform};
+use crate::errors::{Error,Context};
+use crate::constant::Index;
+
+/// One part in a chain of connected parts, entered at one of its
+/// connections and left at another
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq)]
+pub struct Link {
+    pub instance: Index,
+    pub from: Index,
+    pub to: Index,
+}
+
+/// A tolerance stack-up along a chain of connected parts, measuring
+/// the assembled dimension from the first connection in the chain
+/// to the last
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Stack {
+    links: Vec<Link>,
+    direction: Option<Direction>,
+    limits: Option<(f64,f64)>,
+}
+
+/// How much an assembled dimension can vary
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Stackup {
+    /// the dimension with every connection where it should be
+    pub nominal: f64,
+    /// the most it can be off with every tolerance at its limit
+    pub worst_case: f64,
+    /// the root sum of squares of the tolerances, which is what
+    /// it's likely to be off when they vary independently
+    pub rss: f64,
+    /// the smallest and largest dimension that's within spec
+    pub limits: Option<(f64,f64)>,
+    /// each connection in the chain and its tolerance
+    pub contributions: Vec<(Index,Index,f64)>,
+}
+
+impl Stack {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Adds the next part to the chain, where `from` is the
+    // connection joined to the last part and `to` the connection
+    // joined to the next
+    pub fn with_link(mut self, instance: Index, from: Index, to: Index) -> Self {
+        self.links.push(Link { instance, from, to });
+        self
+    }
+
+    // Measures the dimension along a direction in assembly
+    // space, instead of as a straight distance
+    pub fn with_direction(mut self, direction: Direction) -> Self {
+        self.direction = Some(direction.normalize());
+        self
+    }
+
+    // The smallest and largest dimension that's within spec
+    pub fn with_limits(mut self, min: f64, max: f64) -> Self {
+        self.limits = Some((min.min(max),min.max(max)));
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn links(&self) -> &[Link] {
+        &self.links
+    }
+
+    // Adds up the tolerances of every connection in the chain. The
+    // same connection is counted once, even if it both enters and
+    // leaves a part.
+    pub fn analyze(&self, assembly: &Assembly) -> Result<Stackup,Error> {
+        span!("analysis.stack", links = self.links.len());
+        let mut points = Vec::with_capacity(self.links.len() * 2);
+        let mut stackup = Stackup { limits: self.limits, ..Default::default() };
+
+        for (index,link) in self.links.iter().enumerate() {
+            let instance = assembly
+                .get(link.instance)
+                .ok_or(Error::IndexOutOfRange { index: link.instance, len: assembly.instances().len() })
+                .in_item(index)?;
+
+            let connections = instance.part().connections();
+            for connection in dedup(link.from,link.to) {
+                let item = connections
+                    .get(connection)
+                    .ok_or(Error::IndexOutOfRange { index: connection, len: connections.len() })
+                    .in_part(instance.part().name())
+                    .in_item(index)?;
+
+                let mut point = item.point();
+                point.transform(instance.transform());
+                points.push(point);
+                stackup.contributions.push((link.instance,connection,item.tolerance()));
+            }
+        }
+
+        let (Some(first),Some(last)) = (points.first(),points.last()) else {
+            return Err(Error::IndexOutOfRange { index: 0, len: 0 });
+        };
+
+        let span: Vertex = *last - *first;
+        stackup.nominal = match self.direction {
+            Some(direction) => span.dot(&direction.vector()).abs(),
+            None => first.distance(last),
+        };
+
+        let tolerances = stackup.contributions.iter().map(|(_,_,t)| *t);
+        stackup.worst_case = tolerances.clone().sum();
+        stackup.rss = tolerances.map(|t| t * t).sum::<f64>().sqrt();
+        Ok(stackup)
+    }
+
+}
+
+impl Stackup {
+
+    // Whether the dimension stays in spec with every tolerance at
+    // its limit, which it always does without limits
+    pub fn fits_worst_case(&self) -> bool {
+        self.fits(self.worst_case)
+    }
+
+    // Whether the likely variation stays in spec
+    pub fn fits_rss(&self) -> bool {
+        self.fits(self.rss)
+    }
+
+    fn fits(&self, variation: f64) -> bool {
+        match self.limits {
+            Some((min,max)) => self.nominal - variation >= min && self.nominal + variation <= max,
+            None => true,
+        }
+    }
+
+}
+
+// The connections a part is entered and left at, which are
+// the same one for a part the chain only touches
+fn dedup(from: Index, to: Index) -> Vec<Index> {
+    match from == to {
+        true => vec![from],
+        false => vec![from,to],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::geometry::{Geometry,Matrix};
+    use crate::part::{Part,Connection};
+
+    // A one meter long spacer with a connection at each end
+    // that's good to a millimeter either way
+    fn spacer() -> Part {
+        let geometry = Geometry::make(
+            vec![
+                0.0,0.0,0.0, 1.0,0.0,0.0, 1.0,0.1,0.0, 0.0,0.1,0.0,
+                0.0,0.0,0.1, 1.0,0.0,0.1, 1.0,0.1,0.1, 0.0,0.1,0.1,
+            ],
+            vec![
+                1,3,2, 1,4,3, 5,6,7, 5,7,8,
+                1,2,6, 1,6,5, 2,3,7, 2,7,6,
+                3,4,8, 3,8,7, 4,1,5, 4,5,8,
+            ]);
+        Part::new("spacer")
+            .with_geometry(geometry)
+            .with_connection(Connection::new(Vertex::new(0.0,0.05,0.05),0.01).with_tolerance(0.001))
+            .with_connection(Connection::new(Vertex::new(1.0,0.05,0.05),0.01).with_tolerance(-0.001))
+            .build()
+            .unwrap()
+    }
+
+    fn assembly() -> Assembly {
+        (0..4).fold(Assembly::new("rail"),|a,i| a.with_part(spacer(),Matrix::translate(i as f64,0.0,0.0)))
+    }
+
+    #[test]
+    fn test_stack_chain() {
+        let stack = (0..4)
+            .fold(Stack::new(),|s,i| s.with_link(i,0,1))
+            .with_direction(Direction::new(-2.0,0.0,0.0))
+            .with_limits(4.006,3.994);
+
+        let stackup = stack.analyze(&assembly()).unwrap();
+        assert_eq!(stackup.contributions.len(),8);
+        assert_relative_eq!(stackup.nominal,4.0);
+        assert_relative_eq!(stackup.worst_case,0.008);
+        assert_relative_eq!(stackup.rss,(8.0 * 0.001f64.powi(2)).sqrt(),epsilon = 1e-12);
+        assert!(!stackup.fits_worst_case());
+        assert!(stackup.fits_rss());
+
+        // a single connection on the last part is only counted once
+        let stackup = Stack::new().with_link(0,0,1).with_link(1,1,1).analyze(&assembly()).unwrap();
+        assert_eq!(stackup.contributions.len(),3);
+        assert_relative_eq!(stackup.nominal,2.0);
+        assert!(stackup.fits_worst_case());
+    }
+
+    #[test]
+    fn test_stack_invalid() {
+        let error = Stack::new().with_link(0,0,1).with_link(9,0,1).analyze(&assembly()).unwrap_err();
+        assert!(matches!(error,Error::InItem(1,_)));
+        assert!(matches!(error.root(),Error::IndexOutOfRange { index: 9, len: 4 }));
+
+        let error = Stack::new().with_link(0,0,2).analyze(&assembly()).unwrap_err();
+        assert!(matches!(error.root(),Error::IndexOutOfRange { index: 2, len: 2 }));
+        assert!(Stack::new().analyze(&assembly()).is_err());
+    }
+
+}
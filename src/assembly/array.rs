@@ -0,0 +1,637 @@
+use std::f64::consts::PI;
+
+use crate::utilities::Random;
+use crate::geometry::{Matrix,Vector,Vertex,Direction,Geometry,Polyline};
+use crate::assembly::Instance;
+use crate::part::Part;
+use crate::constant::TOLERANCE;
+use crate::errors::Error;
+
+/// How far apart copies are along a path
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub enum Spacing {
+    /// a copy every distance along the path from its start
+    Distance(f64),
+    /// a number of copies spread evenly from end to end, or around
+    /// the whole loop if the path is closed
+    Count(usize),
+}
+
+/// How the copies of an array are laid out
+#[derive(Debug,Clone,PartialEq)]
+pub enum Arrangement {
+    /// copies in a row, each a step on from the last
+    Linear {
+        count: usize,
+        step: Vector,
+    },
+    /// copies in rows along X, Y and Z, a spacing apart along each
+    Grid {
+        counts: [usize;3],
+        spacing: Vector,
+    },
+    /// copies turned around an axis through a center, each an angle
+    /// in radians on from the last
+    Circular {
+        count: usize,
+        center: Vertex,
+        axis: Direction,
+        angle: f64,
+    },
+    /// copies along a path of straight pieces, each turned so its +X
+    /// points along the path
+    Path {
+        points: Vec<Vertex>,
+        closed: bool,
+        spacing: Spacing,
+    },
+    /// copies scattered at random over triangles, a number for each
+    /// unit of area, each turned so its +Z points out of the surface
+    Surface {
+        triangles: Vec<[Vertex;3]>,
+        density: f64,
+        seed: u64,
+    },
+}
+
+/// Random changes to the position, rotation and size of each copy
+/// in an array, so repeated things like fence pickets or paving
+/// stones don't look stamped out. The same seed always gives the
+/// same changes.
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct Jitter {
+    seed: u64,
+    position: Vector,
+    rotation: Vector,
+    scale: f64,
+}
+
+/// Copies of a part laid out in a pattern, with optional jitter
+#[derive(Debug,Clone,PartialEq)]
+pub struct Array {
+    layout: Arrangement,
+    jitter: Option<Jitter>,
+}
+
+impl Jitter {
+
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            ..Default::default()
+        }
+    }
+
+    // The most each copy moves either way along X, Y and Z
+    pub fn with_position(mut self, limit: Vector) -> Self {
+        self.position = Vector::new(limit.x.abs(),limit.y.abs(),limit.z.abs());
+        self
+    }
+
+    // The most each copy turns either way around X, Y and Z in
+    // radians, around its own origin
+    pub fn with_rotation(mut self, limit: Vector) -> Self {
+        self.rotation = Vector::new(limit.x.abs(),limit.y.abs(),limit.z.abs());
+        self
+    }
+
+    // The most each copy grows or shrinks as a fraction of its size,
+    // from 0 up to 1
+    pub fn with_scale(mut self, limit: f64) -> Self {
+        self.scale = limit.clamp(0.0,1.0);
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn position(&self) -> Vector {
+        self.position
+    }
+
+    pub fn rotation(&self) -> Vector {
+        self.rotation
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    // The change to the copy at an index, in the copy's own space.
+    // Each copy draws from its own sequence made from the seed and
+    // its index, so adding copies to an array doesn't change the
+    // ones that were already there.
+    pub fn matrix(&self, index: usize) -> Matrix {
+        let mut random = Random::new(Random::new(self.seed).next_u64() ^ index as u64);
+        let (p,r) = (self.position,self.rotation);
+        let offset = Vector::new(random.spread(p.x),random.spread(p.y),random.spread(p.z));
+        let turn = Vector::new(random.spread(r.x),random.spread(r.y),random.spread(r.z));
+        let size = 1.0 + random.spread(self.scale);
+        Matrix::translate(offset.x,offset.y,offset.z) *
+        Matrix::rotate(turn.x,turn.y,turn.z) *
+        Matrix::scale(size,size,size)
+    }
+
+}
+
+impl Array {
+
+    pub fn new(layout: Arrangement) -> Self {
+        Self {
+            layout,
+            jitter: None,
+        }
+    }
+
+    pub fn linear(count: usize, step: Vector) -> Self {
+        Self::new(Arrangement::Linear { count, step })
+    }
+
+    pub fn grid(counts: [usize;3], spacing: Vector) -> Self {
+        Self::new(Arrangement::Grid { counts, spacing })
+    }
+
+    pub fn circular(count: usize, center: Vertex, axis: Direction, angle: f64) -> Self {
+        Self::new(Arrangement::Circular { count, center, axis, angle })
+    }
+
+    pub fn along(points: Vec<Vertex>, closed: bool, spacing: Spacing) -> Self {
+        Self::new(Arrangement::Path { points, closed, spacing })
+    }
+
+    // Copies along a 2D polyline laid flat in the XY plane
+    pub fn along_polyline(line: &Polyline, spacing: Spacing) -> Self {
+        let points = line
+            .points()
+            .iter()
+            .map(|(x,y)| Vertex::new(*x,*y,0.0))
+            .collect();
+        Self::along(points,line.is_closed(),spacing)
+    }
+
+    // Copies scattered over the surface of a mesh, where the seed
+    // picks the places and the density is the number of copies for
+    // each unit of area
+    pub fn over(geometry: &Geometry, density: f64, seed: u64) -> Self {
+        let triangles = geometry
+            .faces()
+            .iter()
+            .filter(|f| f.is_valid(geometry.vertices()))
+            .map(|f| f.triangle(geometry.vertices()))
+            .map(|t| [t.p1,t.p2,t.p3])
+            .collect();
+        Self::new(Arrangement::Surface { triangles, density: density.max(0.0), seed })
+    }
+
+    pub fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = Some(jitter);
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn layout(&self) -> &Arrangement {
+        &self.layout
+    }
+
+    pub fn jitter(&self) -> Option<&Jitter> {
+        self.jitter.as_ref()
+    }
+
+    // The number of copies in the array
+    pub fn count(&self) -> usize {
+        match &self.layout {
+            Arrangement::Linear { count, .. } => *count,
+            Arrangement::Grid { counts, .. } => counts.iter().product(),
+            Arrangement::Circular { count, .. } => *count,
+            Arrangement::Path { .. } | Arrangement::Surface { .. } => self.transforms().len(),
+        }
+    }
+
+    // A copy with the seeds of its jitter and of a scattered layout
+    // mixed with another, like the seed of a build, so the same array
+    // can give different copies each time it's built from
+    pub fn seeded(&self, seed: u64) -> Self {
+        let mut array = self.clone();
+        if let Arrangement::Surface { seed: s, .. } = &mut array.layout {
+            *s ^= seed;
+        }
+        if let Some(jitter) = array.jitter.as_mut() {
+            jitter.seed ^= seed;
+        }
+        array
+    }
+
+    // The placement of each copy relative to the first, with the
+    // jitter applied. A grid counts along X first, then Y, then Z.
+    pub fn transforms(&self) -> Vec<Matrix> {
+        let placements = match &self.layout {
+            Arrangement::Linear { count, step } => (0..*count)
+                .map(|i| *step * i as f64)
+                .map(|v| Matrix::translate(v.x,v.y,v.z))
+                .collect::<Vec<Matrix>>(),
+            Arrangement::Grid { counts: [nx,ny,nz], spacing } => (0..*nz)
+                .flat_map(|k| (0..*ny).flat_map(move |j| (0..*nx).map(move |i| (i,j,k))))
+                .map(|(i,j,k)| Matrix::translate(
+                    i as f64 * spacing.x,
+                    j as f64 * spacing.y,
+                    k as f64 * spacing.z))
+                .collect(),
+            Arrangement::Circular { count, center, axis, angle } => (0..*count)
+                .map(|i| {
+                    Matrix::translate(center.x,center.y,center.z) *
+                    Matrix::rotate_axis(axis.vector(),angle * i as f64) *
+                    Matrix::translate(-center.x,-center.y,-center.z)
+                })
+                .collect(),
+            Arrangement::Path { points, closed, spacing } => along(points,*closed,*spacing),
+            Arrangement::Surface { triangles, density, seed } => over(triangles,*density,*seed),
+        };
+
+        match self.jitter {
+            Some(jitter) => placements
+                .into_iter()
+                .enumerate()
+                .map(|(i,m)| m * jitter.matrix(i))
+                .collect(),
+            None => placements,
+        }
+    }
+
+    // Instances of a part for each copy, placed by the array after
+    // `transform` and named after the part with a number from 1
+    pub fn instances(&self, part: &Part, transform: &Matrix) -> Vec<Instance> {
+        self.transforms()
+            .into_iter()
+            .enumerate()
+            .map(|(i,m)| {
+                let name = format!("{} {}",part.name(),i + 1);
+                Instance::new(part.clone(),*transform * m).with_name(name)
+            })
+            .collect()
+    }
+
+}
+
+// The placements along a path, at distances from its start
+fn along(points: &[Vertex], closed: bool, spacing: Spacing) -> Vec<Matrix> {
+    let mut points = points.to_vec();
+    if closed && points.len() > 1 {
+        points.push(points[0]);
+    }
+
+    let segments = points
+        .windows(2)
+        .map(|w| (w[0],w[1]))
+        .filter(|(a,b)| a.distance(b) > TOLERANCE)
+        .collect::<Vec<(Vertex,Vertex)>>();
+    let length = segments.iter().map(|(a,b)| a.distance(b)).sum::<f64>();
+    if segments.is_empty() {
+        return Vec::new();
+    }
+
+    // a closed path doesn't get a second copy where it meets itself
+    let distances = match spacing {
+        Spacing::Distance(step) if step > TOLERANCE => (0..)
+            .map(|i| i as f64 * step)
+            .take_while(|d| match closed {
+                true => *d < length - TOLERANCE,
+                false => *d <= length + TOLERANCE,
+            })
+            .collect::<Vec<f64>>(),
+        Spacing::Count(count) => {
+            let gaps = match closed {
+                true => count,
+                false => count.saturating_sub(1),
+            };
+            (0..count)
+                .map(|i| length * i as f64 / gaps.max(1) as f64)
+                .collect()
+        },
+        Spacing::Distance(_) => Vec::new(),
+    };
+
+    let mut result = Vec::with_capacity(distances.len());
+    let (mut index,mut start) = (0,0.0);
+    for distance in distances {
+        while index + 1 < segments.len() && distance > start + segments[index].0.distance(&segments[index].1) {
+            start += segments[index].0.distance(&segments[index].1);
+            index += 1;
+        }
+        let (a,b) = segments[index];
+        let tangent = (b - a).normalize();
+        let point = a + tangent * (distance - start).min(a.distance(&b));
+        result.push(Matrix::translate(point.x,point.y,point.z) * facing(Vector::new(1.0,0.0,0.0),tangent));
+    }
+    result
+}
+
+// The placements scattered over triangles. Area is added up face by
+// face and a copy put at a random point on the face each time it
+// comes to another copy's worth, so the total is the area times the
+// density to the nearest whole copy.
+fn over(triangles: &[[Vertex;3]], density: f64, seed: u64) -> Vec<Matrix> {
+    let mut random = Random::new(seed);
+    let mut result = Vec::new();
+    let mut carry = 0.5;
+    for [a,b,c] in triangles.iter().copied() {
+        let normal = (b - a).cross(&(c - a));
+        if normal.magnitude() <= TOLERANCE {
+            continue;
+        }
+        carry += normal.magnitude() / 2.0 * density;
+        while carry >= 1.0 {
+            carry -= 1.0;
+            let (mut u,mut v) = (random.next_f64(),random.next_f64());
+            if u + v > 1.0 {
+                (u,v) = (1.0 - u,1.0 - v);
+            }
+            let point = a + (b - a) * u + (c - a) * v;
+            result.push(Matrix::translate(point.x,point.y,point.z) * facing(Vector::new(0.0,0.0,1.0),normal.normalize()));
+        }
+    }
+    result
+}
+
+// The smallest turn that points one direction along another
+fn facing(from: Vector, to: Vector) -> Matrix {
+    let axis = from.cross(&to);
+    if axis.magnitude() > TOLERANCE {
+        return Matrix::rotate_axis(axis,from.angle(&to));
+    }
+    if from.dot(&to) > 0.0 {
+        return Matrix::identity();
+    }
+    let side = match from.cross(&Vector::new(1.0,0.0,0.0)) {
+        v if v.magnitude() > TOLERANCE => v,
+        _ => from.cross(&Vector::new(0.0,1.0,0.0)),
+    };
+    Matrix::rotate_axis(side,PI)
+}
+
+// An array is written as one token for its layout, and another for
+// its jitter if it has one, with the values in each separated by
+// commas:
+//
+//   linear:<count>,<x>,<y>,<z>
+//   grid:<nx>,<ny>,<nz>,<x>,<y>,<z>
+//   circular:<count>,<x>,<y>,<z>,<ax>,<ay>,<az>,<angle>
+//   path:open|closed,distance=<step>|count=<count>,<x>,<y>,<z>,...
+//   surface:<density>,<seed>,<x>,<y>,<z>,...
+//   jitter:<seed>,<px>,<py>,<pz>,<rx>,<ry>,<rz>,<scale>
+impl From<&Array> for String {
+    fn from(array: &Array) -> Self {
+        let points = |points: &mut dyn Iterator<Item = &Vertex>| points
+            .map(|p| format!(",{},{},{}",p.x,p.y,p.z))
+            .collect::<String>();
+        let mut result = match &array.layout {
+            Arrangement::Linear { count, step } => format!("linear:{},{},{},{}",count,step.x,step.y,step.z),
+            Arrangement::Grid { counts: [nx,ny,nz], spacing } => format!("grid:{},{},{},{},{},{}",
+                nx,ny,nz,spacing.x,spacing.y,spacing.z),
+            Arrangement::Circular { count, center, axis, angle } => format!("circular:{},{},{},{},{},{},{},{}",
+                count,center.x,center.y,center.z,axis.x,axis.y,axis.z,angle),
+            Arrangement::Path { points: path, closed, spacing } => format!("path:{},{}{}",
+                if *closed { "closed" } else { "open" },
+                match spacing {
+                    Spacing::Distance(step) => format!("distance={}",step),
+                    Spacing::Count(count) => format!("count={}",count),
+                },
+                points(&mut path.iter())),
+            Arrangement::Surface { triangles, density, seed } => format!("surface:{},{}{}",
+                density,seed,points(&mut triangles.iter().flatten())),
+        };
+        if let Some(j) = array.jitter {
+            result.push_str(&format!(" jitter:{},{},{},{},{},{},{},{}",
+                j.seed,j.position.x,j.position.y,j.position.z,j.rotation.x,j.rotation.y,j.rotation.z,j.scale));
+        }
+        result
+    }
+}
+
+impl TryFrom<&str> for Array {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self,Self::Error> {
+        let fields = value.split_whitespace().collect::<Vec<&str>>();
+        let (layout,jitter) = match fields.as_slice() {
+            [layout] => (layout,None),
+            [layout,jitter] => (layout,Some(jitter)),
+            _ => return Err(Error::ParseError),
+        };
+
+        let (kind,values) = layout.split_once(':').ok_or(Error::ParseError)?;
+        let values = values.split(',').collect::<Vec<&str>>();
+        let number = |text: &str| text.parse::<f64>().or(Err(Error::ParseError));
+        let count = |text: &str| text.parse::<usize>().or(Err(Error::ParseError));
+        let vertices = |values: &[&str]| match values.len() % 3 {
+            0 => values
+                .chunks(3)
+                .map(|c| Ok(Vertex::new(number(c[0])?,number(c[1])?,number(c[2])?)))
+                .collect::<Result<Vec<Vertex>,Error>>(),
+            _ => Err(Error::ParseError),
+        };
+
+        let layout = match (kind,values.as_slice()) {
+            ("linear",[n,x,y,z]) => Arrangement::Linear {
+                count: count(n)?,
+                step: Vector::new(number(x)?,number(y)?,number(z)?),
+            },
+            ("grid",[nx,ny,nz,x,y,z]) => Arrangement::Grid {
+                counts: [count(nx)?,count(ny)?,count(nz)?],
+                spacing: Vector::new(number(x)?,number(y)?,number(z)?),
+            },
+            ("circular",[n,x,y,z,ax,ay,az,angle]) => Arrangement::Circular {
+                count: count(n)?,
+                center: Vertex::new(number(x)?,number(y)?,number(z)?),
+                axis: Direction::new(number(ax)?,number(ay)?,number(az)?),
+                angle: number(angle)?,
+            },
+            ("path",[closed,spacing,rest @ ..]) => Arrangement::Path {
+                points: vertices(rest)?,
+                closed: match *closed {
+                    "closed" => true,
+                    "open" => false,
+                    _ => return Err(Error::ParseError),
+                },
+                spacing: match spacing.split_once('=') {
+                    Some(("distance",step)) => Spacing::Distance(number(step)?),
+                    Some(("count",n)) => Spacing::Count(count(n)?),
+                    _ => return Err(Error::ParseError),
+                },
+            },
+            ("surface",[density,seed,rest @ ..]) => Arrangement::Surface {
+                triangles: vertices(rest)?
+                    .chunks(3)
+                    .map(|c| <[Vertex;3]>::try_from(c).or(Err(Error::ParseError)))
+                    .collect::<Result<Vec<[Vertex;3]>,Error>>()?,
+                density: number(density)?,
+                seed: seed.parse()?,
+            },
+            _ => return Err(Error::ParseError),
+        };
+
+        let jitter = match jitter.map(|j| j.strip_prefix("jitter:").ok_or(Error::ParseError)).transpose()? {
+            None => None,
+            Some(values) => match values.split(',').collect::<Vec<&str>>().as_slice() {
+                [seed,px,py,pz,rx,ry,rz,scale] => Some(Jitter {
+                    seed: seed.parse()?,
+                    position: Vector::new(number(px)?,number(py)?,number(pz)?),
+                    rotation: Vector::new(number(rx)?,number(ry)?,number(rz)?),
+                    scale: number(scale)?,
+                }),
+                _ => return Err(Error::ParseError),
+            },
+        };
+
+        Ok(Self { layout, jitter })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::f64::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::geometry::{Transform,Bounds};
+    use crate::assembly::Assembly;
+    use crate::testing;
+
+    fn origin(matrix: &Matrix) -> Vertex {
+        let mut point = Vertex::default();
+        point.transform(matrix);
+        point
+    }
+
+    #[test]
+    fn test_array_layouts() {
+        let row = Array::linear(4,Vector::new(0.5,0.0,0.0));
+        let points = row.transforms().iter().map(origin).collect::<Vec<Vertex>>();
+        assert_eq!(points.len(),4);
+        assert_relative_eq!(points[3].x,1.5);
+
+        let grid = Array::grid([2,3,1],Vector::new(1.0,2.0,0.0));
+        assert_eq!(grid.count(),6);
+        let last = origin(&grid.transforms()[5]);
+        assert_relative_eq!(last.x,1.0);
+        assert_relative_eq!(last.y,4.0);
+
+        let ring = Array::circular(4,Vertex::new(1.0,0.0,0.0),Direction::new(0.0,0.0,1.0),FRAC_PI_2);
+        let points = ring.transforms().iter().map(origin).collect::<Vec<Vertex>>();
+        assert_relative_eq!(points[1].x,1.0,epsilon = 1e-9);
+        assert_relative_eq!(points[1].y,-1.0,epsilon = 1e-9);
+        assert_relative_eq!(points[2].x,2.0,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_array_path() {
+        // an L two long each way, with +X of each copy along it
+        let points = vec![Vertex::new(0.0,0.0,0.0),Vertex::new(2.0,0.0,0.0),Vertex::new(2.0,2.0,0.0)];
+        let path = Array::along(points.clone(),false,Spacing::Distance(0.5));
+        let transforms = path.transforms();
+        assert_eq!(transforms.len(),9);
+        let point = origin(&transforms[6]);
+        assert_relative_eq!(point.x,2.0,epsilon = 1e-9);
+        assert_relative_eq!(point.y,1.0,epsilon = 1e-9);
+        let mut forward = Vector::new(1.0,0.0,0.0);
+        forward.transform(&transforms[6]);
+        assert_relative_eq!((forward - point).y,1.0,epsilon = 1e-9);
+
+        // a count spreads copies from end to end, or around a loop
+        assert_relative_eq!(origin(&Array::along(points.clone(),false,Spacing::Count(3)).transforms()[2]).y,2.0,epsilon = 1e-9);
+        let square = Polyline::closed(vec![(0.0,0.0),(1.0,0.0),(1.0,1.0),(0.0,1.0)]);
+        let ring = Array::along_polyline(&square,Spacing::Count(8));
+        assert_eq!(ring.count(),8);
+        assert_relative_eq!(origin(&ring.transforms()[7]).y,0.5,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_array_surface() {
+        let cube = testing::cube();
+        let stones = Array::over(&cube,10.0,3);
+        let transforms = stones.transforms();
+        assert_eq!(transforms.len(),60);
+        assert_eq!(transforms,Array::over(&cube,10.0,3).transforms());
+        assert_ne!(transforms,Array::over(&cube,10.0,4).transforms());
+
+        // every copy is on the surface with +Z pointing out of it
+        for matrix in transforms.iter() {
+            let point = origin(matrix);
+            let mut up = Vector::new(0.0,0.0,1.0);
+            up.transform(matrix);
+            let outside = point + (up - point) * 0.01;
+            assert!(!cube.encloses(&outside));
+            assert!(Bounds::new(Vertex::new(0.0,0.0,0.0),Vertex::new(1.0,1.0,1.0)).contains(&point));
+        }
+    }
+
+    #[test]
+    fn test_array_jitter() {
+        let jitter = Jitter::new(9)
+            .with_position(Vector::new(0.1,0.0,0.0))
+            .with_rotation(Vector::new(0.0,0.0,0.2))
+            .with_scale(0.1)
+            .build();
+        let pickets = Array::linear(20,Vector::new(1.0,0.0,0.0)).with_jitter(jitter);
+
+        // the same seed always gives the same fence
+        let transforms = pickets.transforms();
+        assert_eq!(transforms,pickets.transforms());
+        assert_ne!(transforms,pickets.clone().with_jitter(Jitter { seed: 10, ..jitter }).transforms());
+
+        // every picket moves, but only along X and within the limit
+        for (i,matrix) in transforms.iter().enumerate() {
+            let point = origin(matrix);
+            assert!((point.x - i as f64).abs() <= 0.1);
+            assert!((point.x - i as f64).abs() > 0.0);
+            assert_eq!((point.y,point.z),(0.0,0.0));
+        }
+
+        // a longer fence starts the same way
+        let longer = Array::linear(25,Vector::new(1.0,0.0,0.0)).with_jitter(jitter);
+        assert_eq!(longer.transforms()[..20],transforms[..]);
+
+        let picket = Geometry::from_bounds(&Bounds::new(Vertex::new(0.0,0.0,0.0),Vertex::new(0.1,0.02,1.0)));
+        let part = Part::new("picket").with_geometry(picket).build().unwrap();
+        let mut fence = Assembly::new("fence");
+        let added = fence.add_array(part,Matrix::translate(0.0,5.0,0.0),&pickets);
+        assert_eq!(added,(0..20).collect::<Vec<usize>>());
+        assert_eq!(fence.instances()[19].name(),"picket 20");
+        assert_relative_eq!(origin(fence.instances()[0].transform()).y,5.0);
+    }
+
+    #[test]
+    fn test_array_string() {
+        let cube = testing::cube();
+        let points = vec![Vertex::new(0.0,0.0,0.0),Vertex::new(2.0,0.0,0.1),Vertex::new(2.0,2.0,0.0)];
+        let jitter = Jitter::new(u64::MAX)
+            .with_position(Vector::new(0.1,0.0,0.3))
+            .with_rotation(Vector::new(0.0,0.0,0.2))
+            .with_scale(0.1);
+        let arrays = [
+            Array::linear(4,Vector::new(0.5,-0.25,1e-9)),
+            Array::grid([2,3,1],Vector::new(1.0,2.0,0.0)).with_jitter(jitter),
+            Array::circular(6,Vertex::new(1.0,0.0,0.0),Direction::new(0.0,0.6,0.8),FRAC_PI_2 / 3.0),
+            Array::along(points.clone(),false,Spacing::Distance(0.5)),
+            Array::along(points,true,Spacing::Count(7)),
+            Array::over(&cube,10.0,3),
+        ];
+        for array in arrays.iter() {
+            let text = String::from(array);
+            assert_eq!(&Array::try_from(text.as_str()).unwrap(),array,"{}",text);
+        }
+
+        assert!(Array::try_from("linear:4,0.5,0.0").is_err());
+        assert!(Array::try_from("path:open,every=2,0,0,0").is_err());
+        assert!(Array::try_from("surface:1,2,0,0,0,1,0,0").is_err());
+        assert!(Array::try_from("linear:4,0.5,0,0 wobble:1").is_err());
+    }
+
+}
@@ -0,0 +1,800 @@
+use std::collections::HashSet;
+
+use crate::utilities;
+use crate::geometry::{Matrix,Geometry,Vector,Transform,Frustum};
+use crate::part::{Part,EvalContext,Units,Filter,Connection};
+use crate::errors::{Error,Context};
+use crate::assembly::{Instance,Query,Transaction,LevelOfDetail,Graph,Array};
+use crate::constant::Index;
+
+/// Two joined connections on instances of an assembly, each as
+/// the index of the instance and the index of its connection
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq)]
+pub struct Mate {
+    pub first: (Index,Index),
+    pub second: (Index,Index),
+}
+
+/// A collection of parts positioned in a shared space
+#[derive(Default,Debug,Clone)]
+pub struct Assembly {
+    name: String,
+    instances: Vec<Instance>,
+    mates: Vec<Mate>,
+}
+
+impl Assembly {
+
+    pub fn new<T: Into<String>>(name: T) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_part(mut self, part: Part, transform: Matrix) -> Self {
+        self.add(Instance::new(part,transform));
+        self
+    }
+
+    pub fn with_instance(mut self, instance: Instance) -> Self {
+        self.add(instance);
+        self
+    }
+
+    pub fn add(&mut self, instance: Instance) -> Index {
+        self.instances.push(instance);
+        self.instances.len() - 1
+    }
+
+    // Adds a copy of a part for each place in an array, placed by
+    // the array after `transform`, and returns their indices
+    pub fn add_array(&mut self, part: Part, transform: Matrix, array: &Array) -> Vec<Index> {
+        array
+            .instances(&part,&transform)
+            .into_iter()
+            .map(|i| self.add(i))
+            .collect()
+    }
+
+    // Removes an instance along with any mates it's in
+    pub fn remove(&mut self, index: Index) -> Option<Instance> {
+        if index >= self.instances.len() {
+            return None;
+        }
+        self.mates.retain(|m| m.first.0 != index && m.second.0 != index);
+        for mate in self.mates.iter_mut() {
+            for end in [&mut mate.first,&mut mate.second] {
+                if end.0 > index {
+                    end.0 -= 1;
+                }
+            }
+        }
+        Some(self.instances.remove(index))
+    }
+
+    // Joins a connection on one instance to a connection on another,
+    // given as the index of the instance and of the connection. Fails
+    // if either doesn't exist or their interfaces don't fit.
+    pub fn mate(&mut self, first: (Index,Index), second: (Index,Index)) -> Result<(),Error> {
+        let a = self.connection(first)?;
+        let b = self.connection(second)?;
+        a.mates(b)?;
+
+        let mate = Mate { first, second };
+        let reverse = Mate { first: second, second: first };
+        if !self.mates.contains(&mate) && !self.mates.contains(&reverse) {
+            self.mates.push(mate);
+        }
+        Ok(())
+    }
+
+    pub fn mates(&self) -> &[Mate] {
+        &self.mates
+    }
+
+    // Mates every pair of connections on different instances that
+    // are within `tolerance` of each other in assembly space and have
+    // interfaces that fit, closest pairs first, so that a model that's
+    // already in place doesn't have to be joined by hand. Connections
+    // that are already mated are left alone. Returns the new mates.
+    pub fn auto_connect(&mut self, tolerance: f64) -> Vec<Mate> {
+        span!("assembly.auto_connect", instances = self.instances.len());
+        let mut ends = Vec::new();
+        for (index,instance) in self.instances.iter().enumerate() {
+            for (connection,item) in instance.part().connections().iter().enumerate() {
+                let mut point = item.point();
+                point.transform(instance.transform());
+                ends.push(((index,connection),point,item));
+            }
+        }
+
+        // sorted along X, so that only the ends close to each
+        // other along X have to be compared
+        ends.sort_by(|a,b| a.1.x.total_cmp(&b.1.x));
+        let mut pairs = Vec::new();
+        for (i,(a,p,x)) in ends.iter().enumerate() {
+            for (b,q,y) in ends[i + 1..].iter().take_while(|e| e.1.x - p.x <= tolerance) {
+                let distance = p.distance(q);
+                if a.0 != b.0 && distance <= tolerance && x.mates(y).is_ok() {
+                    pairs.push((distance,*a,*b));
+                }
+            }
+        }
+        pairs.sort_by(|a,b| a.0.total_cmp(&b.0));
+
+        let mut used = self.mates
+            .iter()
+            .flat_map(|m| [m.first,m.second])
+            .collect::<HashSet<(Index,Index)>>();
+
+        let mut result = Vec::new();
+        for (_,first,second) in pairs.into_iter() {
+            if used.contains(&first) || used.contains(&second) {
+                continue;
+            }
+            used.extend([first,second]);
+            let mate = Mate { first: first.min(second), second: first.max(second) };
+            self.mates.push(mate);
+            result.push(mate);
+        }
+        result
+    }
+
+    // A connection on an instance, by the index of both
+    fn connection(&self, (instance,connection): (Index,Index)) -> Result<&Connection,Error> {
+        let len = self.instances.len();
+        let connections = self.instances
+            .get(instance)
+            .ok_or(Error::IndexOutOfRange { index: instance, len })?
+            .part()
+            .connections();
+        connections
+            .get(connection)
+            .ok_or(Error::IndexOutOfRange { index: connection, len: connections.len() })
+            .in_item(instance)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_name<T: Into<String>>(&mut self, name: T) {
+        self.name = name.into();
+    }
+
+    pub fn instances(&self) -> &[Instance] {
+        &self.instances
+    }
+
+    pub fn instances_mut(&mut self) -> &mut [Instance] {
+        &mut self.instances
+    }
+
+    pub fn get(&self, index: Index) -> Option<&Instance> {
+        self.instances.get(index)
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Instance> {
+        self.instances
+            .iter()
+            .find(|i| i.name() == name)
+    }
+
+    // Starts a query that matches every instance until
+    // conditions are added to it
+    pub fn query(&self) -> Query<'_> {
+        Query::new(self)
+    }
+
+    // The instances and the mates between them as a graph
+    pub fn graph(&self) -> Graph {
+        Graph::new(self)
+    }
+
+    // Starts a set of edits that can be committed or rolled back
+    // together
+    pub fn transaction(&mut self) -> Transaction<'_> {
+        Transaction::new(self)
+    }
+
+    // Sets an attribute on the part of every instance the query
+    // matches and returns the result for each of them, in assembly
+    // order. A part that fails to update is left as it was.
+    //
+    // Parts are updated in dependency order because there are no
+    // dependencies between them: each instance has its own copy of
+    // its part, nothing in a part refers to another, and attributes
+    // don't move connections, so the mates of the assembly are the
+    // same before and after. Any order gives the same result.
+    pub fn set_all<F>(&mut self, query: F, name: &str, value: f64) -> Vec<(Index,Result<(),Error>)>
+    where
+        F: Fn(Query) -> Query
+    {
+        let indices = query(self.query()).indices();
+
+        // every part is updated on its own, so they can all be
+        // updated at once
+        let results = utilities::map_mut(&mut self.instances,|index,instance| {
+            if indices.binary_search(&index).is_err() {
+                return None;
+            }
+            let mut part = instance.part().clone();
+            let result = part.set(name,value);
+            if result.is_ok() {
+                *instance.part_mut() = part;
+            }
+            Some((index,result))
+        });
+
+        results.into_iter().flatten().collect()
+    }
+
+    // Instances whose part metadata matches the filter
+    pub fn matching<'a>(&'a self, filter: &'a Filter) -> impl Iterator<Item = &'a Instance> {
+        self.instances
+            .iter()
+            .filter(move |i| filter.matches(i.part().metadata()))
+    }
+
+    // Instances with a box around them that reaches into the frustum,
+    // which are the only ones a camera with that frustum might see.
+    // Instances without any geometry are left out.
+    pub fn visible_parts<'a>(&'a self, frustum: &'a Frustum) -> impl Iterator<Item = &'a Instance> {
+        self.instances
+            .iter()
+            .filter(move |i| i.bounds().is_some_and(|b| frustum.intersects(&b)))
+    }
+
+    // A copy holding only the instances that match the filter, and
+    // the mates between them
+    pub fn filtered(&self, filter: &Filter) -> Assembly {
+        let kept = self.instances
+            .iter()
+            .enumerate()
+            .filter(|(_,i)| filter.matches(i.part().metadata()))
+            .map(|(i,_)| i)
+            .collect::<Vec<Index>>();
+
+        let moved = |(instance,connection): (Index,Index)| kept
+            .binary_search(&instance)
+            .ok()
+            .map(|i| (i,connection));
+
+        Self {
+            name: self.name.clone(),
+            instances: kept.iter().map(|i| self.instances[*i].clone()).collect(),
+            mates: self.mates
+                .iter()
+                .filter_map(|m| Some(Mate { first: moved(m.first)?, second: moved(m.second)? }))
+                .collect(),
+        }
+    }
+
+    // Indices of instances with a grain that's more than `limit` radians
+    // away from a load in assembly space. Instances without a grain
+    // aren't checked.
+    pub fn across_grain(&self, load: &Vector, limit: f64) -> Vec<Index> {
+        self.instances
+            .iter()
+            .enumerate()
+            .filter(|(_,i)| i.grain_angle(load).is_some_and(|a| a > limit))
+            .map(|(i,_)| i)
+            .collect()
+    }
+
+    // Bakes every instance transform into a single geometry. The vertices
+    // of each instance are grouped under the instance name, and the part's
+    // own groups and channels are kept as `<instance>.<name>`.
+    pub fn flatten(&self) -> Geometry {
+        self.flatten_with(&LevelOfDetail::default())
+    }
+
+    // Like `flatten`, but small parts are swapped for simpler stand-ins
+    // so that very large assemblies stay a manageable size
+    pub fn flatten_with(&self, detail: &LevelOfDetail) -> Geometry {
+        span!("assembly.flatten", instances = self.instances.len());
+        let parts = utilities::map(&self.instances,|i| detail.geometry(i));
+        self.combine(&parts)
+    }
+
+    // Evaluates every part for the context and flattens the result.
+    // Parameters named `<instance>.<name>` only apply to that instance.
+    pub fn evaluate_with(&self, context: &EvalContext) -> Result<Geometry,Error> {
+        span!("assembly.evaluate", instances = self.instances.len());
+
+        // parts are built in meters so the instance transforms
+        // still line up, then the whole result is converted. Parts
+        // don't depend on each other: each one only reads the context,
+        // and a parameter is either read by every part or, as
+        // `<instance>.<name>`, by one, so there's nothing to schedule
+        // and they can all be built at once.
+        let parts = utilities::map(&self.instances,|i| {
+            let context = context
+                .scoped(i.name())
+                .with_units(Units::Meters)
+                .with_frame(*i.transform());
+            i.part().evaluate_with(&context)
+        });
+
+        let parts = parts.into_iter().collect::<Result<Vec<Geometry>,Error>>()?;
+
+        let mut geometry = self.combine(&parts);
+        let scale = context.units().per_meter();
+        if scale != 1.0 {
+            geometry.transform(&Matrix::scale(scale,scale,scale));
+            geometry.clear_changes();
+        }
+
+        Ok(geometry)
+    }
+
+    fn combine(&self, parts: &[Geometry]) -> Geometry {
+        let mut geometry = Geometry::default();
+
+        for (instance,part) in self.instances.iter().zip(parts.iter()) {
+            let appended = geometry.append_prefixed(
+                instance.name(),
+                part,
+                instance.transform());
+
+            let (start,end) = appended.vertices;
+            let mut indices = geometry
+                .group(instance.name())
+                .cloned()
+                .unwrap_or_default();
+            indices.extend(start..end);
+
+            // the indices were just created, so this can't fail
+            let _ = geometry.add_group(instance.name(),indices);
+        }
+
+        geometry.clear_changes();
+        geometry
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::models;
+    use crate::geometry::Vertex;
+
+    fn stud() -> Part {
+        let mut geometry = models::M2X4.geometry();
+        geometry.add_group("front",[4,5,6,7]).unwrap();
+
+        Part::new("2x4")
+            .with_geometry(geometry)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_assembly_mate() {
+        use crate::part::{Connection,Interface};
+
+        let bolt = Part::new("bolt")
+            .with_connection(Connection::new(Vertex::new(0.0,0.0,0.0),0.004)
+                .with_interface(Interface::bolt(0.008).with_standard("M8")));
+        let plate = |size: f64| Part::new("plate")
+            .with_connection(Connection::new(Vertex::new(0.0,0.0,0.0),size / 2.0)
+                .with_interface(Interface::hole(size)))
+            .with_connection(Connection::new(Vertex::new(1.0,0.0,0.0),0.01));
+
+        let mut assembly = Assembly::new("joint")
+            .with_part(stud(),Matrix::identity())
+            .with_part(bolt,Matrix::identity())
+            .with_part(plate(0.008),Matrix::identity())
+            .with_part(plate(0.006),Matrix::identity());
+
+        assembly.mate((1,0),(2,0)).unwrap();
+        assembly.mate((2,0),(1,0)).unwrap();
+        assembly.mate((2,1),(3,1)).unwrap();
+        assert_eq!(assembly.mates().len(),2);
+
+        let error = assembly.mate((1,0),(3,0)).unwrap_err();
+        assert_eq!(error.to_string(),"M8 bolt cannot mate with 6mm hole");
+        assert!(matches!(assembly.mate((1,1),(2,0)),Err(Error::InItem(1,_))));
+        assert!(matches!(assembly.mate((4,0),(2,0)),Err(Error::IndexOutOfRange { index: 4, .. })));
+
+        // mates follow their instances when others are removed
+        assembly.remove(0);
+        assert_eq!(assembly.mates()[0],Mate { first: (0,0), second: (1,0) });
+        assembly.remove(1);
+        assert!(assembly.mates().is_empty());
+    }
+
+    #[test]
+    fn test_assembly_auto_connect() {
+        use crate::part::{Connection,Interface};
+
+        let peg = |interface: Option<Interface>| {
+            let connection = Connection::new(Vertex::new(0.0,0.0,0.0),0.004);
+            Part::new("peg").with_connection(match interface {
+                Some(interface) => connection.with_interface(interface),
+                None => connection,
+            })
+        };
+
+        let mut assembly = Assembly::new("rack")
+            .with_part(peg(Some(Interface::bolt(0.008))),Matrix::identity())
+            .with_part(peg(Some(Interface::hole(0.008))),Matrix::translate(0.0005,0.0,0.0))
+            .with_part(peg(Some(Interface::hole(0.006))),Matrix::translate(-0.0002,0.0,0.0))
+            .with_part(peg(None),Matrix::translate(0.0,0.0,1.0))
+            .with_part(peg(None),Matrix::translate(0.0,0.0,1.0003))
+            .with_part(stud(),Matrix::translate(5.0,0.0,0.0));
+
+        // the bolt takes the closest hole that fits, and connections
+        // without an interface take whatever is nearby
+        let mates = assembly.auto_connect(0.001);
+        assert_eq!(mates,vec![
+            Mate { first: (3,0), second: (4,0) },
+            Mate { first: (0,0), second: (1,0) },
+        ]);
+        assert_eq!(assembly.mates().len(),2);
+        assert!(assembly.auto_connect(0.001).is_empty());
+    }
+
+    #[test]
+    fn test_assembly_flatten() {
+        let assembly = Assembly::new("wall")
+            .with_instance(Instance::new(stud(),Matrix::identity()).with_name("left"))
+            .with_instance(Instance::new(stud(),Matrix::translate(0.0,0.4,0.0)).with_name("right"));
+
+        let geometry = assembly.flatten();
+
+        assert_eq!(geometry.vertices().len(),16);
+        assert_eq!(geometry.size(),24);
+        assert_eq!(geometry.group("left"),Some(&(0..8).collect::<Vec<_>>()));
+        assert_eq!(geometry.group("right"),Some(&(8..16).collect::<Vec<_>>()));
+        assert_eq!(geometry.group("right.front"),Some(&vec![12,13,14,15]));
+
+        let vertex: Vertex = geometry.vertices()[8];
+        assert_relative_eq!(vertex.y,0.4 - 0.04445,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_assembly_evaluate_with_context() {
+        use crate::part::{Attribute,AttributeItem};
+        use crate::geometry::Vector;
+
+        let part = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_attribute(Attribute::new("Length".into(),vec![
+                AttributeItem::translate_specific(Vector::new(1.0,0.0,0.0),vec![4,5,6,7])
+            ]))
+            .build()
+            .unwrap();
+
+        let assembly = Assembly::new("wall")
+            .with_instance(Instance::new(part.clone(),Matrix::identity()).with_name("left"))
+            .with_instance(Instance::new(part,Matrix::translate(0.0,1.0,0.0)).with_name("right"));
+
+        let context = EvalContext::new()
+            .with_parameter("Length",1.0)
+            .with_parameter("right.Length",2.0)
+            .with_units(Units::Centimeters);
+
+        let geometry = assembly.evaluate_with(&context).unwrap();
+        assert_relative_eq!(geometry.vertices()[4].x,221.92,epsilon = 1e-9);
+        assert_relative_eq!(geometry.vertices()[12].x,321.92,epsilon = 1e-9);
+        assert_relative_eq!(geometry.vertices()[12].y,100.0 - 4.445,epsilon = 1e-9);
+
+        // the same context always gives the same geometry
+        let again = assembly.evaluate_with(&context).unwrap();
+        assert_eq!(geometry.vertices(),again.vertices());
+    }
+
+    #[test]
+    fn test_assembly_evaluate_independent() {
+        use crate::testing;
+
+        // mated instances sharing a parameter, with another for
+        // only one of them, build the same as each part on its own
+        let mut assembly = Assembly::new("rail")
+            .with_instance(Instance::new(testing::stud(),Matrix::identity()).with_name("a"))
+            .with_instance(Instance::new(testing::stud(),Matrix::translate(2.4384,0.0,0.0)).with_name("b"));
+        assembly.mate((0,1),(1,0)).unwrap();
+
+        let context = EvalContext::new()
+            .with_parameter("Length",0.5)
+            .with_parameter("b.Length",1.0);
+        let geometry = assembly.evaluate_with(&context).unwrap();
+
+        let mut expected = Vec::new();
+        for instance in assembly.instances() {
+            let context = context.scoped(instance.name()).with_frame(*instance.transform());
+            let mut part = instance.part().evaluate_with(&context).unwrap();
+            part.transform(instance.transform());
+            expected.extend_from_slice(part.vertices());
+        }
+        assert_eq!(geometry.vertices().len(),expected.len());
+        for (a,b) in geometry.vertices().iter().zip(expected.iter()) {
+            assert_relative_eq!(a.distance(b),0.0,epsilon = 1e-12);
+        }
+        assert_relative_eq!(geometry.vertices()[12].x - geometry.vertices()[4].x,2.4384 + 0.5,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_assembly_world_space() {
+        use crate::part::{Attribute,AttributeItem,Selection,Alteration,Space};
+        use crate::geometry::Vector;
+
+        // raises the front end of the stud, whichever way it's turned
+        let raise = AttributeItem::new(
+            Selection::group("front"),
+            Alteration::translate(Vector::new(0.0,0.0,1.0)).with_space(Space::World));
+        let part = stud()
+            .with_attribute(Attribute::new("Raise".into(),vec![raise]))
+            .build()
+            .unwrap();
+
+        let mut assembly = Assembly::new("wall")
+            .with_instance(Instance::new(part,Matrix::rotate_x(std::f64::consts::FRAC_PI_2)));
+
+        let before = assembly.instances()[0].geometry().vertices()[4];
+        let results = assembly.set_all(|q| q,"Raise",0.5);
+        assert!(results[0].1.is_ok());
+        let after = assembly.instances()[0].geometry().vertices()[4];
+        assert_relative_eq!(after.z - before.z,0.5,epsilon = 1e-9);
+        assert_relative_eq!(after.y,before.y,epsilon = 1e-9);
+
+        let geometry = assembly.evaluate_with(&EvalContext::new().with_parameter("Raise",0.25)).unwrap();
+        assert_relative_eq!(geometry.vertices()[4].z - before.z,0.25,epsilon = 1e-9);
+
+        assembly.instances_mut()[0].set("Raise",1.0).unwrap();
+        assert_relative_eq!(assembly.instances()[0].geometry().vertices()[4].z - before.z,1.0,epsilon = 1e-9);
+
+        // rebuilding the part on its own still goes through the frame
+        assembly.instances_mut()[0].part_mut().evaluate().unwrap();
+        let again = assembly.instances()[0].geometry().vertices()[4];
+        assert_relative_eq!(again.z - before.z,1.0,epsilon = 1e-9);
+        assert_relative_eq!(again.y,before.y,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_assembly_world_space_placed() {
+        use crate::part::{Attribute,AttributeItem,Selection,Alteration,Space};
+        use crate::geometry::Vector;
+
+        // a part already raised before it's placed
+        let raise = AttributeItem::new(
+            Selection::group("front"),
+            Alteration::translate(Vector::new(0.0,0.0,1.0)).with_space(Space::World));
+        let mut part = stud()
+            .with_attribute(Attribute::new("Raise".into(),vec![raise]))
+            .build()
+            .unwrap();
+        part.set("Raise",0.5).unwrap();
+
+        let turned = Matrix::rotate_x(std::f64::consts::FRAC_PI_2);
+        let mut instance = Instance::new(part.clone(),turned);
+        let expected = part.evaluate_with(&EvalContext::new().with_frame(turned)).unwrap();
+        assert_eq!(instance.part().geometry().vertices(),expected.vertices());
+
+        // moving the instance rebuilds the part where it is now
+        instance.set_transform(Matrix::identity()).unwrap();
+        let expected = part.evaluate_with(&EvalContext::new().with_frame(Matrix::identity())).unwrap();
+        assert_eq!(instance.part().geometry().vertices(),expected.vertices());
+        assert_relative_eq!(instance.part().geometry().vertices()[4].z - stud().geometry().vertices()[4].z,0.5,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_assembly_world_space_unbuilt() {
+        use crate::part::{Attribute,AttributeItem,Selection,Alteration,Space,Hole};
+        use crate::geometry::Vector;
+        use std::f64::consts::FRAC_PI_2;
+
+        // squashes the front end along Z, which leaves the hole in it
+        // no way in unless the part is turned on its end
+        let squash = AttributeItem::new(
+            Selection::group("front"),
+            Alteration::scale_relative(Vector::new(0.0,0.0,-1.0)).with_space(Space::World));
+        let part = stud()
+            .with_attribute(Attribute::new("Squash".into(),vec![squash]))
+            .with_feature(Hole::new("bore","front",0.01))
+            .build()
+            .unwrap();
+
+        let mut instance = Instance::try_new(part,Matrix::rotate_y(FRAC_PI_2)).unwrap();
+        instance.set("Squash",1.0 - 1e-15).unwrap();
+        assert!(instance.set_transform(Matrix::identity()).is_err());
+        assert_eq!(instance.part().frame(),Some(&Matrix::rotate_y(FRAC_PI_2)));
+
+        let part = instance.part().clone();
+        let error = Instance::try_new(part.clone(),Matrix::identity()).unwrap_err();
+        assert!(matches!(error.root(),Error::UnknownDirection(_)));
+
+        // the part keeps its geometry, but is rebuilt through the
+        // transform of the instance from then on
+        let mut instance = Instance::new(part.clone(),Matrix::identity());
+        assert_eq!(instance.part().frame(),Some(instance.transform()));
+        assert_eq!(instance.part().geometry().vertices(),part.geometry().vertices());
+        instance.set("Squash",0.0).unwrap();
+        let context = EvalContext::new().with_frame(Matrix::identity()).with_parameter("Squash",0.0);
+        assert_eq!(instance.part().geometry().vertices(),part.evaluate_with(&context).unwrap().vertices());
+    }
+
+    #[test]
+    fn test_assembly_filtered() {
+        use crate::part::Metadata;
+
+        let mut roof = stud();
+        roof.metadata_mut().set_layer(Some("roof".into()));
+
+        let assembly = Assembly::new("house")
+            .with_instance(Instance::new(stud(),Matrix::identity()).with_name("wall"))
+            .with_instance(Instance::new(roof,Matrix::identity()).with_name("rafter"))
+            .with_instance(Instance::new(stud().with_metadata(Metadata::new().with_layer("roof")),Matrix::identity()));
+
+        let filter = Filter::layer("roof");
+        assert_eq!(assembly.matching(&filter).count(),2);
+
+        let roof = assembly.filtered(&filter);
+        assert_eq!(roof.name(),"house");
+        assert_eq!(roof.instances()[0].name(),"rafter");
+        assert_eq!(roof.flatten().vertices().len(),16);
+
+        assert_eq!(assembly.filtered(&Filter::All).instances().len(),3);
+        assert!(assembly.filtered(&Filter::tag("none")).instances().is_empty());
+    }
+
+    #[test]
+    fn test_assembly_visible_parts() {
+        use crate::geometry::Frustum;
+
+        let assembly = Assembly::new("row")
+            .with_instance(Instance::new(stud(),Matrix::identity()).with_name("middle"))
+            .with_instance(Instance::new(stud(),Matrix::translate(0.0,20.0,0.0)).with_name("side"))
+            .with_instance(Instance::new(stud(),Matrix::translate(0.0,0.0,-20.0)).with_name("far"))
+            .with_instance(Instance::new(Part::new("empty"),Matrix::identity()));
+
+        // looking down from above at the middle
+        let view = Matrix::look_at(
+            Vertex::new(0.0,0.0,5.0),
+            Vertex::new(0.0,0.0,0.0),
+            Vector::new(0.0,1.0,0.0));
+        let projection = Matrix::orthographic(-2.0,2.0,-2.0,2.0,0.1,10.0);
+        let frustum = Frustum::new(&view,&projection);
+
+        let visible = assembly.visible_parts(&frustum).map(|i| i.name()).collect::<Vec<&str>>();
+        assert_eq!(visible,vec!["middle"]);
+
+        // turned, the box around the stud is still around it
+        let turned = Instance::new(stud(),Matrix::rotate_z(std::f64::consts::FRAC_PI_2));
+        let bounds = turned.bounds().unwrap();
+        assert_relative_eq!(bounds.max.y,1.2192,epsilon = 1e-9);
+        assert_relative_eq!(bounds.max.x,0.04445,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_assembly_across_grain() {
+        use crate::part::Metadata;
+        use crate::geometry::Direction;
+        use std::f64::consts::FRAC_PI_2;
+
+        let lumber = stud().with_metadata(Metadata::new().with_grain(Direction::new(1.0,0.0,0.0)));
+
+        // a stud stood upright has its grain along Z
+        let assembly = Assembly::new("wall")
+            .with_instance(Instance::new(lumber.clone(),Matrix::identity()).with_name("plate"))
+            .with_instance(Instance::new(lumber,Matrix::rotate_y(-FRAC_PI_2)).with_name("stud"))
+            .with_instance(Instance::new(stud(),Matrix::identity()).with_name("unknown"));
+
+        let grain = assembly.instances()[1].grain().unwrap();
+        assert_relative_eq!(grain.z,1.0,epsilon = 1e-9);
+
+        let gravity = Vector::new(0.0,0.0,-1.0);
+        assert_eq!(assembly.across_grain(&gravity,0.1),vec![0]);
+        assert!(assembly.instances()[2].grain_angle(&gravity).is_none());
+    }
+
+    #[test]
+    fn test_assembly_set_all() {
+        use crate::part::{Attribute,AttributeItem,Metadata};
+        use crate::geometry::Vector;
+
+        let framing = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_metadata(Metadata::new().with_tag("framing"))
+            .with_attribute(Attribute::new("Length".into(),vec![
+                AttributeItem::translate_specific(Vector::new(1.0,0.0,0.0),vec![4,5,6,7])
+            ]))
+            .build()
+            .unwrap();
+
+        let mut assembly = Assembly::new("wall")
+            .with_instance(Instance::new(framing.clone(),Matrix::identity()).with_name("left"))
+            .with_instance(Instance::new(framing.clone(),Matrix::identity()).with_name("right"))
+            .with_instance(Instance::new(stud().with_metadata(Metadata::new().with_tag("framing")),Matrix::identity()))
+            .with_part(framing.with_metadata(Metadata::new()),Matrix::identity());
+
+        let results = assembly.set_all(|q| q.tag("framing"),"Length",1.0);
+        assert_eq!(results.len(),3);
+        assert!(results[0].1.is_ok() && results[1].1.is_ok());
+
+        // the part without the attribute fails on its own
+        assert_eq!(results[2].0,2);
+        assert!(matches!(results[2].1.as_ref().unwrap_err().root(),Error::UnknownAttribute(_)));
+
+        let x = |i: usize| assembly.instances()[i].part().geometry().vertices()[4].x;
+        assert_relative_eq!(x(0),2.2192,epsilon = 1e-9);
+        assert_relative_eq!(x(1),2.2192,epsilon = 1e-9);
+        assert_relative_eq!(x(3),1.2192,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_assembly_set_all_order() {
+        use crate::testing;
+
+        // three studs end to end, each mated to the one before
+        let mut assembly = Assembly::new("rail");
+        for i in 0..3 {
+            let transform = Matrix::translate(2.4384 * i as f64,0.0,0.0) * Matrix::rotate_z(0.1 * i as f64);
+            assembly.add(Instance::new(testing::stud(),transform));
+        }
+        assembly.mate((0,1),(1,0)).unwrap();
+        assembly.mate((1,1),(2,0)).unwrap();
+        let mates = assembly.mates().to_vec();
+
+        // the same values set one at a time, last instance first
+        let mut expected = assembly.clone();
+        for index in (0..3).rev() {
+            expected.instances_mut()[index].set("Length",0.5).unwrap();
+        }
+
+        let results = assembly.set_all(|q| q,"Length",0.5);
+        assert_eq!(results.iter().map(|(i,_)| *i).collect::<Vec<Index>>(),vec![0,1,2]);
+        assert!(results.iter().all(|(_,r)| r.is_ok()));
+        assert_eq!(assembly.mates(),&mates[..]);
+
+        for (a,b) in assembly.instances().iter().zip(expected.instances()) {
+            assert_eq!(testing::compare(&a.geometry(),&b.geometry(),1e-12),None);
+            assert_eq!(a.part().connections()[1].point(),testing::stud().connections()[1].point());
+        }
+    }
+
+    #[test]
+    fn test_assembly_flatten_mirrored() {
+        use crate::testing;
+
+        // a left and right hand pair, with the left mirrored
+        let block = Part::new("block").with_geometry(testing::cube());
+        let assembly = Assembly::new("pair")
+            .with_instance(Instance::new(block.clone(),Matrix::identity()).with_name("right"))
+            .with_instance(Instance::new(block,Matrix::translate(0.0,2.0,0.0) * Matrix::scale(-1.0,1.0,1.0)).with_name("left"));
+
+        let geometry = assembly.flatten();
+        assert_relative_eq!(geometry.volume(),2.0,epsilon = 1e-9);
+
+        // every face of the mirrored block still points out of it
+        let left = assembly.instances()[1].geometry();
+        assert_relative_eq!(left.volume(),1.0,epsilon = 1e-9);
+        let center = left.bounds().unwrap().center();
+        for face in left.faces() {
+            let triangle = face.triangle(left.vertices());
+            assert!(triangle.normal().dot(&(triangle.p1 - center)) > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_assembly_flatten_empty() {
+        let geometry = Assembly::new("empty").flatten();
+        assert!(geometry.is_empty());
+    }
+
+    #[test]
+    fn test_assembly_find_remove() {
+        let mut assembly = Assembly::new("wall")
+            .with_part(stud(),Matrix::identity());
+
+        assert_eq!(assembly.find("2x4").unwrap().name(),"2x4");
+        assert!(assembly.remove(1).is_none());
+        assert!(assembly.remove(0).is_some());
+        assert!(assembly.instances().is_empty());
+    }
+
+}
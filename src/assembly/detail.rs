@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+
+use crate::assembly::Instance;
+use crate::geometry::{Geometry,Vector};
+
+/// What a part is drawn as when an assembly is flattened or exported
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub enum Detail {
+    /// the whole geometry
+    #[default]
+    Full,
+    /// a rougher copy with about this many cubes of vertices
+    /// merged together along the longest side of the part
+    Decimated(usize),
+    /// a box around the part
+    Bounds,
+    /// nothing at all
+    Hidden,
+}
+
+/// Settings for swapping small parts in a large assembly for simpler
+/// stand-ins, so that flattened and exported files stay manageable.
+/// Parts are small if the longest side of the box around them is
+/// shorter than the threshold, and overrides are by part name.
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct LevelOfDetail {
+    threshold: f64,
+    detail: Detail,
+    overrides: BTreeMap<String,Detail>,
+}
+
+impl LevelOfDetail {
+
+    // Small parts are decimated to eight cubes along their
+    // longest side unless something else is chosen
+    pub fn new(threshold: f64) -> Self {
+        Self {
+            threshold,
+            detail: Detail::Decimated(8),
+            overrides: BTreeMap::new(),
+        }
+    }
+
+    // What parts below the threshold are drawn as
+    pub fn with_detail(mut self, detail: Detail) -> Self {
+        self.detail = detail;
+        self
+    }
+
+    // What a part is drawn as whatever its size
+    pub fn with_override<T: Into<String>>(mut self, part: T, detail: Detail) -> Self {
+        self.overrides.insert(part.into(),detail);
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    pub fn detail(&self) -> Detail {
+        self.detail
+    }
+
+    pub fn overrides(&self) -> &BTreeMap<String,Detail> {
+        &self.overrides
+    }
+
+    // What an instance is drawn as, from its part
+    pub fn detail_of(&self, instance: &Instance) -> Detail {
+        let part = instance.part();
+        if let Some(detail) = self.overrides.get(part.name()) {
+            return *detail;
+        }
+        match part.geometry().bounds() {
+            Some(b) if longest(&b.size()) < self.threshold => self.detail,
+            _ => Detail::Full,
+        }
+    }
+
+    // The geometry an instance is drawn with, in the part's own space
+    pub fn geometry(&self, instance: &Instance) -> Geometry {
+        let geometry = instance.part().geometry();
+        let Some(bounds) = geometry.bounds() else {
+            return geometry.clone();
+        };
+        match self.detail_of(instance) {
+            Detail::Full => geometry.clone(),
+            Detail::Decimated(cells) => geometry.decimate(longest(&bounds.size()) / cells.max(1) as f64),
+            Detail::Bounds => Geometry::from_bounds(&bounds),
+            Detail::Hidden => Geometry::default(),
+        }
+    }
+
+}
+
+fn longest(size: &Vector) -> f64 {
+    size.x.max(size.y).max(size.z)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::models;
+    use crate::geometry::{Matrix,Transform};
+    use crate::assembly::Assembly;
+    use crate::part::Part;
+
+    fn block(name: &str, scale: f64) -> Part {
+        let mut geometry = models::M2X4.geometry();
+        geometry.transform(&Matrix::scale(scale,scale,scale));
+        Part::new(name).with_geometry(geometry)
+    }
+
+    #[test]
+    fn test_level_of_detail() {
+        let assembly = Assembly::new("shop")
+            .with_part(block("bench",1.0),Matrix::identity())
+            .with_part(block("screw",0.01),Matrix::translate(0.0,1.0,0.0))
+            .with_part(block("bolt",0.01),Matrix::translate(0.0,2.0,0.0))
+            .with_part(block("washer",0.01),Matrix::translate(0.0,3.0,0.0));
+
+        let detail = LevelOfDetail::new(0.1)
+            .with_detail(Detail::Bounds)
+            .with_override("bolt",Detail::Full)
+            .with_override("washer",Detail::Hidden)
+            .with_override("bench",Detail::Decimated(1))
+            .build();
+
+        let kinds = assembly.instances().iter().map(|i| detail.detail_of(i)).collect::<Vec<Detail>>();
+        assert_eq!(kinds,vec![Detail::Decimated(1),Detail::Bounds,Detail::Full,Detail::Hidden]);
+
+        // the bench is one cube long, so its ends each merge into a point
+        let geometry = assembly.flatten_with(&detail);
+        assert_eq!(geometry.group("bench").map(Vec::len),Some(0));
+        assert_eq!(geometry.group("screw").map(Vec::len),Some(8));
+        assert_eq!(geometry.group("bolt").map(Vec::len),Some(8));
+        assert_eq!(geometry.group("washer").map(Vec::len),Some(0));
+
+        // nothing changes without any settings
+        assert_eq!(assembly.flatten_with(&LevelOfDetail::default()).vertices(),assembly.flatten().vertices());
+    }
+
+}
@@ -0,0 +1,180 @@
+use crate::assembly::Assembly;
+use crate::constant::Index;
+
+/// The instances of an assembly and the mates between them, as an
+/// undirected graph with an edge for each mate
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Graph {
+    edges: Vec<(Index,Index)>,
+    neighbours: Vec<Vec<Index>>,
+}
+
+impl Graph {
+
+    pub fn new(assembly: &Assembly) -> Self {
+        let mut neighbours = vec![Vec::new();assembly.instances().len()];
+        let mut edges = Vec::new();
+        for mate in assembly.mates().iter() {
+            let (a,b) = (mate.first.0,mate.second.0);
+            edges.push((a,b));
+            if a != b && !neighbours[a].contains(&b) {
+                neighbours[a].push(b);
+                neighbours[b].push(a);
+            }
+        }
+        Self { edges, neighbours }
+    }
+
+    // The number of instances
+    pub fn len(&self) -> usize {
+        self.neighbours.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.neighbours.is_empty()
+    }
+
+    // The instances at each end of each mate, in the order of the
+    // mates, so that a pair mated twice is here twice
+    pub fn edges(&self) -> &[(Index,Index)] {
+        &self.edges
+    }
+
+    // The instances mated to an instance
+    pub fn neighbours(&self, index: Index) -> &[Index] {
+        self.neighbours
+            .get(index)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    // Groups of instances that are joined to each other through
+    // mates, each sorted and in order of their first instance. An
+    // instance without any mates is a group on its own.
+    pub fn components(&self) -> Vec<Vec<Index>> {
+        let mut seen = vec![false;self.len()];
+        let mut result = Vec::new();
+        for start in 0..self.len() {
+            if seen[start] {
+                continue;
+            }
+            seen[start] = true;
+            let mut component = vec![start];
+            let mut stack = vec![start];
+            while let Some(node) = stack.pop() {
+                for &next in self.neighbours[node].iter() {
+                    if !seen[next] {
+                        seen[next] = true;
+                        component.push(next);
+                        stack.push(next);
+                    }
+                }
+            }
+            component.sort();
+            result.push(component);
+        }
+        result
+    }
+
+    // Instances that hold the assembly together, which would split
+    // their group in two or more if they were taken out, in order.
+    // Uses the lowest depth reachable from each instance in a depth
+    // first search, kept on a stack so that long chains of parts
+    // don't run out of call stack.
+    pub fn articulations(&self) -> Vec<Index> {
+        let count = self.len();
+        let mut depth = vec![usize::MAX;count];
+        let mut low = vec![0;count];
+        let mut found = vec![false;count];
+
+        for root in 0..count {
+            if depth[root] != usize::MAX {
+                continue;
+            }
+            depth[root] = 0;
+            low[root] = 0;
+            let mut children = 0;
+
+            // each instance with its parent and the next neighbour
+            // to visit
+            let mut stack: Vec<(Index,Option<Index>,usize)> = vec![(root,None,0)];
+            while let Some(top) = stack.last_mut() {
+                let (node,parent) = (top.0,top.1);
+                let child = self.neighbours[node].get(top.2).copied();
+                top.2 += 1;
+
+                match child {
+                    Some(child) if Some(child) == parent => (),
+                    Some(child) if depth[child] == usize::MAX => {
+                        depth[child] = depth[node] + 1;
+                        low[child] = depth[child];
+                        if node == root {
+                            children += 1;
+                        }
+                        stack.push((child,Some(node),0));
+                    },
+                    Some(child) => low[node] = low[node].min(depth[child]),
+                    None => {
+                        stack.pop();
+                        if let Some(parent) = parent {
+                            low[parent] = low[parent].min(low[node]);
+                            if parent != root && low[node] >= depth[parent] {
+                                found[parent] = true;
+                            }
+                        }
+                    },
+                }
+            }
+            found[root] = children > 1;
+        }
+
+        (0..count).filter(|i| found[*i]).collect()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::geometry::{Matrix,Vertex};
+    use crate::part::{Part,Connection};
+
+    // a part with a connection at each end, so that mates don't
+    // need to be placed anywhere in particular
+    fn chain(links: usize, mates: &[(Index,Index)]) -> Assembly {
+        let link = Part::new("link")
+            .with_connection(Connection::new(Vertex::new(0.0,0.0,0.0),0.01))
+            .with_connection(Connection::new(Vertex::new(1.0,0.0,0.0),0.01));
+        let mut assembly = Assembly::new("chain");
+        for i in 0..links {
+            assembly.add(crate::assembly::Instance::new(link.clone(),Matrix::identity()).with_name(format!("link {}",i)));
+        }
+        for (a,b) in mates.iter() {
+            assembly.mate((*a,1),(*b,0)).unwrap();
+        }
+        assembly
+    }
+
+    #[test]
+    fn test_graph_queries() {
+        // a triangle of 0, 1 and 2 hanging off 3 by 2, with 4 on
+        // the end of 3, and 5 on its own
+        let assembly = chain(6,&[(0,1),(1,2),(2,0),(2,3),(3,4)]);
+        let graph = Graph::new(&assembly);
+        assert_eq!(graph.len(),6);
+        assert_eq!(graph.edges().len(),5);
+        assert_eq!(graph.neighbours(2),&[1,0,3]);
+        assert_eq!(graph.components(),vec![vec![0,1,2,3,4],vec![5]]);
+        assert_eq!(graph.articulations(),vec![2,3]);
+
+        // closing the loop leaves nothing holding it together
+        let ring = chain(4,&[(0,1),(1,2),(2,3),(3,0)]);
+        assert!(Graph::new(&ring).articulations().is_empty());
+
+        // a star comes apart at the middle
+        let star = chain(4,&[(0,1),(0,2),(0,3)]);
+        assert_eq!(Graph::new(&star).articulations(),vec![0]);
+    }
+
+}
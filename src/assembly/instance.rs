@@ -0,0 +1,125 @@
+use log::warn;
+
+use crate::geometry::{Matrix,Geometry,Vector,Direction,Transform,Bounds};
+use crate::part::{Part,Origin};
+use crate::errors::Error;
+
+/// A part placed in an assembly by a transform
+#[derive(Debug,Clone)]
+pub struct Instance {
+    name: String,
+    part: Part,
+    transform: Matrix,
+}
+
+impl Instance {
+
+    // Creates an instance named after its part, which makes changes
+    // in world space through the transform from then on. A part with
+    // changes in world space is rebuilt, and its geometry is kept as
+    // it was if it can't be, until it's next rebuilt.
+    pub fn new(mut part: Part, transform: Matrix) -> Self {
+        if let Err(error) = part.set_frame(Some(transform)) {
+            warn!("part '{}' can't be rebuilt where it's placed: {}",part.name(),error);
+            part.place(Some(transform));
+        }
+        Self {
+            name: part.name().into(),
+            part,
+            transform,
+        }
+    }
+
+    // Like `new`, failing if the part can't be rebuilt where it's
+    // placed
+    pub fn try_new(mut part: Part, transform: Matrix) -> Result<Self,Error> {
+        part.set_frame(Some(transform))?;
+        Ok(Self {
+            name: part.name().into(),
+            part,
+            transform,
+        })
+    }
+
+    pub fn with_name<T: Into<String>>(mut self, name: T) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn part(&self) -> &Part {
+        &self.part
+    }
+
+    pub fn part_mut(&mut self) -> &mut Part {
+        &mut self.part
+    }
+
+    pub fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    // Moves the instance, rebuilding the part if it makes changes in
+    // world space. The instance isn't moved if the part can't be
+    // rebuilt where it would be.
+    pub fn set_transform(&mut self, transform: Matrix) -> Result<(),Error> {
+        self.part.set_frame(Some(transform))?;
+        self.transform = transform;
+        Ok(())
+    }
+
+    // Changes the value of an attribute of the part, making changes
+    // in world space through the instance's transform
+    pub fn set(&mut self, name: &str, value: f64) -> Result<(),Error> {
+        self.part.set(name,value)
+    }
+
+    // Moves the origin of the part and changes the transform to
+    // make up for it, so the instance stays where it is. The instance
+    // is left unchanged if the part can't be rebuilt.
+    pub fn normalize_origin(&mut self, origin: Origin) -> Result<(),Error> {
+        let part = self.part.clone();
+        let back = self.part.normalize_origin(origin)?;
+        if let Err(error) = self.set_transform(self.transform * back) {
+            self.part = part;
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    // The part's grain turned into assembly space
+    pub fn grain(&self) -> Option<Direction> {
+        self.part.metadata().grain().map(|mut g| {
+            g.transform(&self.transform);
+            g.normalize()
+        })
+    }
+
+    // The angle between a load in assembly space and the grain,
+    // from 0 along the grain to PI/2 across it
+    pub fn grain_angle(&self, load: &Vector) -> Option<f64> {
+        self.grain().map(|g| g.axis_angle(load))
+    }
+
+    // A box around the part in assembly space, from the corners of
+    // the box around it in its own space, so it's a little loose if
+    // the instance is turned
+    pub fn bounds(&self) -> Option<Bounds> {
+        let mut corners = self.part.geometry().bounds()?.corners();
+        for corner in corners.iter_mut() {
+            corner.transform(&self.transform);
+        }
+        Bounds::from_points(&corners)
+    }
+
+    // The part geometry moved into assembly space
+    pub fn geometry(&self) -> Geometry {
+        let mut geometry = Geometry::default();
+        geometry.append(self.part.geometry(),&self.transform);
+        geometry
+    }
+
+}
@@ -0,0 +1,611 @@
+use std::convert::TryFrom;
+use std::fs::{self,OpenOptions};
+use std::io::Write;
+use std::path::{Path,PathBuf};
+use log::warn;
+
+use crate::assembly::{Assembly,Instance};
+use crate::part::Part;
+use crate::geometry::{Matrix,ParseMode};
+use crate::errors::Error;
+use crate::constant::Index;
+
+/// One change to an assembly, as stored in a journal
+#[derive(Debug,Clone)]
+pub enum Edit {
+    /// rename the assembly
+    Name(String),
+    /// add an instance of a part
+    Add { name: String, part: Box<Part>, transform: Matrix },
+    /// remove the instance at an index
+    Remove(Index),
+    /// change an attribute of an instance's part
+    Set { index: Index, attribute: String, value: f64 },
+    /// move an instance
+    Transform { index: Index, transform: Matrix },
+    /// mate two connections, each given as the index of its
+    /// instance and of the connection on the instance's part
+    Mate { first: (Index,Index), second: (Index,Index) },
+}
+
+/// An append-only log of edits kept next to a project. Each edit
+/// is written as it's made, so saving costs one small write, and
+/// the assembly is rebuilt on load by replaying the edits. Once
+/// the log gets long it's rewritten as the edits that build the
+/// current assembly from nothing.
+#[derive(Debug,Clone)]
+pub struct Journal {
+    path: PathBuf,
+    count: usize,
+    limit: Option<usize>,
+}
+
+impl Edit {
+
+    pub fn apply(&self, assembly: &mut Assembly) -> Result<(),Error> {
+        let len = assembly.instances().len();
+        let missing = |index: Index| Error::IndexOutOfRange { index, len };
+        match self {
+            Edit::Name(name) => assembly.set_name(name.clone()),
+            Edit::Add { name, part, transform } => {
+                assembly.add(Instance::try_new(*part.clone(),*transform)?.with_name(name.clone()));
+            },
+            Edit::Remove(index) => {
+                assembly.remove(*index).ok_or(missing(*index))?;
+            },
+            Edit::Set { index, attribute, value } => {
+                assembly
+                    .instances_mut()
+                    .get_mut(*index)
+                    .ok_or(missing(*index))?
+                    .part_mut()
+                    .set(attribute,*value)?;
+            },
+            Edit::Transform { index, transform } => {
+                assembly
+                    .instances_mut()
+                    .get_mut(*index)
+                    .ok_or(missing(*index))?
+                    .set_transform(*transform)?;
+            },
+            Edit::Mate { first, second } => assembly.mate(*first,*second)?,
+        }
+        Ok(())
+    }
+
+    // The edits that build the assembly as it is now
+    pub fn snapshot(assembly: &Assembly) -> Vec<Edit> {
+        let mut edits = vec![Edit::Name(assembly.name().into())];
+        edits.extend(assembly.instances().iter().map(|i| Edit::Add {
+            name: i.name().into(),
+            part: Box::new(i.part().clone()),
+            transform: *i.transform(),
+        }));
+        edits.extend(assembly.mates().iter().map(|m| Edit::Mate {
+            first: m.first,
+            second: m.second,
+        }));
+        edits
+    }
+
+    // Reads every edit from text written by `String::from(&Edit)`
+    pub fn parse_all(text: &str) -> Result<Vec<Edit>,Error> {
+        let lines = text.lines().collect::<Vec<&str>>();
+        let mut edits = Vec::new();
+        let mut number = 0;
+
+        while number < lines.len() {
+            let line = lines[number];
+            let invalid = || Error::InvalidLine { line: number + 1, text: line.into() };
+
+            if line.trim().is_empty() {
+                number += 1;
+                continue;
+            }
+
+            // an added part is followed by the lines of its text
+            let count = match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["add",count,..] => count.parse::<usize>().map_err(|_| invalid())?,
+                _ => 0,
+            };
+
+            if count > lines.len() - number - 1 {
+                return Err(invalid());
+            }
+            let end = number + 1 + count;
+
+            let block = lines[number..end].join("\n");
+            edits.push(Edit::try_from(block.as_str()).map_err(|_| invalid())?);
+            number = end;
+        }
+
+        Ok(edits)
+    }
+
+}
+
+// Edits are written as a keyword followed by their values, with
+// names last so they can contain spaces. An added part also gives
+// the number of lines of part text that follow it.
+impl From<&Edit> for String {
+    fn from(edit: &Edit) -> Self {
+        let matrix = |m: &Matrix| m
+            .unpack()
+            .iter()
+            .map(f64::to_string)
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        match edit {
+            Edit::Name(name) => format!("name {}",name),
+            Edit::Add { name, part, transform } => {
+                let text = String::from(part.as_ref());
+                format!("add {} {} {}\n{}",text.lines().count(),matrix(transform),name,text)
+            },
+            Edit::Remove(index) => format!("remove {}",index),
+            Edit::Set { index, attribute, value } => format!("set {} {} {}",index,value,attribute),
+            Edit::Transform { index, transform } => format!("transform {} {}",index,matrix(transform)),
+            Edit::Mate { first, second } => format!("mate {} {} {} {}",first.0,first.1,second.0,second.1),
+        }
+    }
+}
+
+impl TryFrom<&str> for Edit {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let (first,rest) = value.split_once('\n').unwrap_or((value,""));
+        let (keyword,values) = first.trim().split_once(' ').unwrap_or((first.trim(),""));
+
+        // the first `count` values, and whatever is left as a name
+        let split = |count: usize| {
+            let mut tokens = values.splitn(count + 1,' ');
+            let head = tokens.by_ref().take(count).collect::<Vec<&str>>();
+            match head.len() == count {
+                true => Ok((head,tokens.next().unwrap_or("").to_string())),
+                false => Err(Error::ParseError),
+            }
+        };
+
+        let matrix = |tokens: &[&str]| -> Result<Matrix,Error> {
+            let values = tokens
+                .iter()
+                .map(|t| t.parse::<f64>())
+                .collect::<Result<Vec<f64>,_>>()?;
+            let values: [f64;16] = values.try_into().map_err(|_| Error::ParseError)?;
+            Ok(Matrix::new(values))
+        };
+
+        match keyword {
+            "name" => Ok(Edit::Name(values.to_string())),
+            "add" => {
+                let (head,name) = split(17)?;
+                Ok(Edit::Add {
+                    name,
+                    part: Box::new(Part::parse(rest,ParseMode::Strict)?),
+                    transform: matrix(&head[1..])?,
+                })
+            },
+            "remove" => Ok(Edit::Remove(values.trim().parse()?)),
+            "set" => {
+                let (head,attribute) = split(2)?;
+                Ok(Edit::Set {
+                    index: head[0].parse()?,
+                    attribute,
+                    value: head[1].parse()?,
+                })
+            },
+            "transform" => {
+                let (head,_) = split(17)?;
+                Ok(Edit::Transform {
+                    index: head[0].parse()?,
+                    transform: matrix(&head[1..])?,
+                })
+            },
+            "mate" => {
+                let (head,_) = split(4)?;
+                Ok(Edit::Mate {
+                    first: (head[0].parse()?,head[1].parse()?),
+                    second: (head[2].parse()?,head[3].parse()?),
+                })
+            },
+            _ => Err(Error::ParseError),
+        }
+    }
+}
+
+impl Journal {
+
+    // Rebuilds the assembly from the journal at `path`, or starts
+    // an empty journal if there isn't one. An edit left half written
+    // at the end by a crash is dropped from the file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<(Journal,Assembly),Error> {
+        let path = path.as_ref().to_path_buf();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let written = complete(&text)?;
+        if written.len() < text.len() {
+            warn!("dropping an incomplete edit from the end of {}",path.display());
+            let file = OpenOptions::new().write(true).open(&path)?;
+            file.set_len(written.len() as u64)?;
+            file.sync_data()?;
+        }
+
+        let edits = Edit::parse_all(written)?;
+        let mut assembly = Assembly::default();
+        for edit in edits.iter() {
+            edit.apply(&mut assembly)?;
+        }
+
+        let journal = Journal {
+            path,
+            count: edits.len(),
+            limit: None,
+        };
+        Ok((journal,assembly))
+    }
+
+    // Compacts the journal whenever it holds more than `limit` edits
+    pub fn with_compaction(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // The number of edits in the journal file
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    // Appends the edit to the journal and then applies it. An edit
+    // that fails to apply is taken back out of the journal, and the
+    // assembly is left unchanged if the edit can't be written. Once
+    // the edit is made this succeeds, even if compacting the journal
+    // afterwards fails, which is logged and tried again on the next
+    // edit.
+    pub fn record(&mut self, assembly: &mut Assembly, edit: Edit) -> Result<(),Error> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let length = file.metadata()?.len();
+        file.write_all((String::from(&edit) + "\n").as_bytes())?;
+        file.sync_data()?;
+
+        if let Err(e) = edit.apply(assembly) {
+            file.set_len(length)?;
+            file.sync_data()?;
+            return Err(e);
+        }
+        self.count += 1;
+
+        if let Some(limit) = self.limit.filter(|l| self.count > *l) {
+            if let Err(e) = self.compact(assembly) {
+                warn!("journal has {} edits, more than {}, but couldn't be compacted: {}",self.count,limit,e);
+            }
+        }
+        Ok(())
+    }
+
+    // Replaces the journal with a snapshot of the assembly. The new
+    // file is written beside the old one and then moved over it, so
+    // the journal is never left half written.
+    pub fn compact(&mut self, assembly: &Assembly) -> Result<(),Error> {
+        span!("journal.compact", edits = self.count);
+        let edits = Edit::snapshot(assembly);
+        let text = edits
+            .iter()
+            .map(|e| String::from(e) + "\n")
+            .collect::<String>();
+
+        let temporary = self.path.with_extension("compact");
+        let mut file = fs::File::create(&temporary)?;
+        file.write_all(text.as_bytes())?;
+        file.sync_data()?;
+        fs::rename(&temporary,&self.path)?;
+        self.count = edits.len();
+        Ok(())
+    }
+
+}
+
+// The text up to the end of the last edit that was written in full,
+// leaving out a last line without a newline and an added part with
+// fewer lines than it says it has. An added part that runs past the
+// end with other edits after it wasn't cut short by a crash, so it's
+// an error rather than being dropped along with them.
+fn complete(text: &str) -> Result<&str,Error> {
+    let text = &text[..text.rfind('\n').map_or(0,|i| i + 1)];
+    let lines = text.split_inclusive('\n').collect::<Vec<&str>>();
+    let (mut number,mut end) = (0,0);
+
+    while number < lines.len() {
+        let count = match lines[number].split_whitespace().collect::<Vec<_>>()[..] {
+            ["add",count,..] => count.parse::<usize>().unwrap_or(0),
+            _ => 0,
+        };
+        if count > lines.len() - number - 1 {
+            if followed(&lines[number + 1..]) {
+                return Err(Error::InvalidLine {
+                    line: number + 1,
+                    text: lines[number].trim_end().into(),
+                });
+            }
+            break;
+        }
+        end += lines[number..number + 1 + count].iter().map(|l| l.len()).sum::<usize>();
+        number += 1 + count;
+    }
+    Ok(&text[..end])
+}
+
+// Whether whole edits start somewhere in the lines. The lines of a
+// part never start with the keyword of an edit, so only those are
+// tried.
+fn followed(lines: &[&str]) -> bool {
+    const KEYWORDS: [&str;6] = ["name","add","remove","set","transform","mate"];
+    (0..lines.len())
+        .filter(|i| lines[*i]
+            .split_whitespace()
+            .next()
+            .is_some_and(|w| KEYWORDS.contains(&w)))
+        .any(|i| Edit::parse_all(&lines[i..].concat()).is_ok_and(|e| !e.is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::models;
+    use crate::part::{Attribute,AttributeItem,Connection,Interface,Hole};
+    use crate::geometry::Vector;
+
+    fn stud() -> Part {
+        Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_attribute(Attribute::new("Length".into(),vec![
+                AttributeItem::translate_specific(Vector::new(1.0,0.0,0.0),vec![4,5,6,7])
+            ]))
+            .build()
+            .unwrap()
+    }
+
+    // A stud with everything a part can carry besides its geometry
+    fn bracket() -> Part {
+        let mut geometry = models::M2X4.geometry();
+        geometry.add_group("end",[4,5,6,7]).unwrap();
+        let corners = geometry.vertices().to_vec();
+        let mut part = Part::new("bracket")
+            .with_geometry(geometry)
+            .with_attribute(stud().attributes()[0].clone())
+            .with_connection(Connection::new(corners[0],0.004)
+                .with_interface(Interface::bolt(0.008).with_standard("M8")))
+            .with_connection(Connection::new(corners[4],0.004)
+                .with_interface(Interface::hole(0.008)))
+            .with_feature(Hole::new("bore","end",0.01))
+            .with_feature(Hole::new("pilot","end",0.002))
+            .build()
+            .unwrap();
+        part.suppress("pilot").unwrap();
+        part.metadata_mut().set_layer(Some("hardware".into()));
+        part.metadata_mut().add_tag("steel");
+        part
+    }
+
+    fn path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("construct-{}-{}.journal",name,std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_edit_string_round_trip() {
+        let edits = [
+            Edit::Name("north wall".into()),
+            Edit::Add { name: "left stud".into(), part: Box::new(stud()), transform: Matrix::translate(1.0,2.0,3.0) },
+            Edit::Set { index: 0, attribute: "Length".into(), value: 0.5 },
+            Edit::Transform { index: 0, transform: Matrix::rotate(0.1,0.2,0.3) },
+            Edit::Remove(3),
+            Edit::Add { name: "bracket".into(), part: Box::new(bracket()), transform: Matrix::identity() },
+            Edit::Mate { first: (0,1), second: (1,0) },
+        ];
+
+        let text = edits.iter().map(|e| String::from(e) + "\n").collect::<String>();
+        let parsed = Edit::parse_all(&text).unwrap();
+        assert_eq!(parsed.len(),7);
+
+        let again = parsed.iter().map(|e| String::from(e) + "\n").collect::<String>();
+        assert_eq!(text,again);
+
+        assert!(matches!(&parsed[1],Edit::Add { name, .. } if name == "left stud"));
+        assert!(matches!(&parsed[6],Edit::Mate { first: (0,1), second: (1,0) }));
+
+        // a part comes back with everything it was written with
+        let Edit::Add { part, .. } = &parsed[5] else { panic!() };
+        assert!(bracket().diff(part).is_empty());
+        assert!(part.is_suppressed("pilot"));
+        assert!(matches!(Edit::parse_all("add 40 0 0"),Err(Error::InvalidLine { line: 1, .. })));
+        assert!(matches!(Edit::parse_all("name a\nmove 1"),Err(Error::InvalidLine { line: 2, .. })));
+    }
+
+    #[test]
+    fn test_journal_replay() {
+        let path = path("replay");
+
+        let (mut journal,mut assembly) = Journal::open(&path).unwrap();
+        assert!(journal.is_empty());
+
+        journal.record(&mut assembly,Edit::Name("wall".into())).unwrap();
+        journal.record(&mut assembly,Edit::Add { name: "left".into(), part: Box::new(stud()), transform: Matrix::identity() }).unwrap();
+        journal.record(&mut assembly,Edit::Add { name: "right".into(), part: Box::new(stud()), transform: Matrix::translate(0.0,1.0,0.0) }).unwrap();
+        journal.record(&mut assembly,Edit::Set { index: 1, attribute: "Length".into(), value: 1.0 }).unwrap();
+        journal.record(&mut assembly,Edit::Remove(0)).unwrap();
+        assert!(journal.record(&mut assembly,Edit::Remove(4)).is_err());
+        assert_eq!(journal.len(),5);
+
+        let (journal,loaded) = Journal::open(&path).unwrap();
+        assert_eq!(journal.len(),5);
+        assert_eq!(loaded.name(),"wall");
+        assert_eq!(loaded.instances().len(),1);
+        assert_eq!(loaded.instances()[0].name(),"right");
+        assert_eq!(
+            loaded.flatten().vertices(),
+            assembly.flatten().vertices());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_journal_compaction() {
+        let path = path("compact");
+
+        let (journal,mut assembly) = Journal::open(&path).unwrap();
+        let mut journal = journal.with_compaction(4);
+
+        journal.record(&mut assembly,Edit::Add { name: "stud".into(), part: Box::new(stud()), transform: Matrix::identity() }).unwrap();
+        for i in 0..4 {
+            journal.record(&mut assembly,Edit::Set { index: 0, attribute: "Length".into(), value: i as f64 }).unwrap();
+        }
+
+        // the name and the one instance
+        assert_eq!(journal.len(),2);
+
+        let (_,loaded) = Journal::open(&path).unwrap();
+        assert_relative_eq!(loaded.instances()[0].part().attribute("Length").unwrap().value(),3.0);
+        assert_eq!(loaded.flatten().vertices(),assembly.flatten().vertices());
+
+        // mates and everything on the parts are kept
+        journal.record(&mut assembly,Edit::Add { name: "a".into(), part: Box::new(bracket()), transform: Matrix::identity() }).unwrap();
+        journal.record(&mut assembly,Edit::Add { name: "b".into(), part: Box::new(bracket()), transform: Matrix::translate(0.1,0.0,0.0) }).unwrap();
+        journal.record(&mut assembly,Edit::Mate { first: (1,1), second: (2,0) }).unwrap();
+        assert_eq!(journal.len(),5);
+        journal.compact(&assembly).unwrap();
+        assert_eq!(journal.len(),5);
+
+        let (_,loaded) = Journal::open(&path).unwrap();
+        assert_eq!(loaded.mates(),assembly.mates());
+        assert!(loaded.instances()[2].part().diff(&bracket()).is_empty());
+        assert_eq!(loaded.flatten().vertices(),assembly.flatten().vertices());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_journal_world_space() {
+        use crate::part::{Selection,Alteration,Space};
+
+        let path = path("world");
+        let raise = AttributeItem::new(
+            Selection::specific([4,5,6,7]),
+            Alteration::translate(Vector::new(0.0,0.0,1.0)).with_space(Space::World));
+        let part = stud()
+            .with_attribute(Attribute::new("Raise".into(),vec![raise]))
+            .build()
+            .unwrap();
+
+        let (journal,mut assembly) = Journal::open(&path).unwrap();
+        let mut journal = journal.with_compaction(2);
+        journal.record(&mut assembly,Edit::Add { name: "stud".into(), part: Box::new(part), transform: Matrix::rotate_x(1.0) }).unwrap();
+        journal.record(&mut assembly,Edit::Set { index: 0, attribute: "Raise".into(), value: 0.5 }).unwrap();
+        journal.record(&mut assembly,Edit::Transform { index: 0, transform: Matrix::rotate_y(0.5) }).unwrap();
+        assert_eq!(journal.len(),2);
+
+        // the part is rebuilt where it's placed when it's read back
+        let (_,loaded) = Journal::open(&path).unwrap();
+        assert_eq!(
+            loaded.instances()[0].part().geometry().vertices(),
+            assembly.instances()[0].part().geometry().vertices());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_journal_torn() {
+        let path = path("torn");
+
+        let (mut journal,mut assembly) = Journal::open(&path).unwrap();
+        journal.record(&mut assembly,Edit::Add { name: "stud".into(), part: Box::new(stud()), transform: Matrix::identity() }).unwrap();
+        journal.record(&mut assembly,Edit::Set { index: 0, attribute: "Length".into(), value: 0.5 }).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+
+        // a crash part way through a line or through an added part
+        let add = String::from(&Edit::Add { name: "b".into(), part: Box::new(stud()), transform: Matrix::identity() });
+        let partial = add.lines().take(3).collect::<Vec<&str>>().join("\n") + "\n";
+        for torn in ["set 0 0.7",partial.as_str()] {
+            fs::write(&path,written.clone() + torn).unwrap();
+            let (mut journal,mut loaded) = Journal::open(&path).unwrap();
+            assert_eq!(journal.len(),2);
+            assert_eq!(fs::read_to_string(&path).unwrap(),written);
+            assert_eq!(loaded.flatten().vertices(),assembly.flatten().vertices());
+
+            // and the next edit starts on a line of its own
+            journal.record(&mut loaded,Edit::Name("wall".into())).unwrap();
+            assert_eq!(Journal::open(&path).unwrap().1.name(),"wall");
+        }
+
+        // a count that runs past the end with edits after it is an
+        // error, and the file is left as it is
+        let (header,part) = add.split_once('\n').unwrap();
+        let (_,rest) = header.strip_prefix("add ").unwrap().split_once(' ').unwrap();
+        let line = written.lines().count() + 1;
+        for count in ["999","18446744073709551615"] {
+            let corrupt = format!("{}add {} {}\n{}\nset 0 0.7\n",written,count,rest,part);
+            fs::write(&path,&corrupt).unwrap();
+            assert!(matches!(Journal::open(&path),Err(Error::InvalidLine { line: l, .. }) if l == line));
+            assert_eq!(fs::read_to_string(&path).unwrap(),corrupt);
+            assert!(matches!(Edit::parse_all(&corrupt),Err(Error::InvalidLine { line: l, .. }) if l == line));
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_journal_compaction_failed() {
+        let path = path("blocked");
+        let blocked = path.with_extension("compact");
+        fs::create_dir_all(&blocked).unwrap();
+
+        let (journal,mut assembly) = Journal::open(&path).unwrap();
+        let mut journal = journal.with_compaction(1);
+        journal.record(&mut assembly,Edit::Add { name: "stud".into(), part: Box::new(stud()), transform: Matrix::identity() }).unwrap();
+
+        // the edit is made even though the journal can't be compacted
+        journal.record(&mut assembly,Edit::Set { index: 0, attribute: "Length".into(), value: 0.5 }).unwrap();
+        assert_eq!(journal.len(),2);
+        assert_relative_eq!(assembly.instances()[0].part().attribute("Length").unwrap().value(),0.5);
+
+        // and it's compacted on the next edit once it can be
+        fs::remove_dir(&blocked).unwrap();
+        journal.record(&mut assembly,Edit::Name("wall".into())).unwrap();
+        assert_eq!(journal.len(),2);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_journal_unwritten() {
+        let path = std::env::temp_dir().join(format!("construct-missing-{}",std::process::id())).join("edits.journal");
+        let (mut journal,mut assembly) = Journal::open(&path).unwrap();
+
+        // an edit that can't be written isn't made
+        assert!(journal.record(&mut assembly,Edit::Name("wall".into())).is_err());
+        assert_eq!(assembly.name(),"");
+        assert!(journal.is_empty());
+
+        // nor is one that can't be applied
+        assert!(journal.record(&mut assembly,Edit::Mate { first: (0,0), second: (1,0) }).is_err());
+        assert!(!path.exists());
+    }
+
+}
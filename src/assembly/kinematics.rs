@@ -0,0 +1,471 @@
+use crate::assembly::Assembly;
+use crate::geometry::{Geometry,Matrix,Transform,Vertex,Bounds};
+use crate::errors::{Error,Context};
+use crate::constant::Index;
+
+// A value of the joint being swept and every joint's value with it
+type Sample = (f64,Vec<f64>);
+
+/// An instance hung off another one, or off the ground, by the
+/// joint at one of its connections
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq)]
+pub struct Mount {
+    pub instance: Index,
+    pub connection: Index,
+    pub parent: Option<Index>,
+}
+
+/// Two instances that overlap with a joint at a value
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct Collision {
+    pub value: f64,
+    pub a: Index,
+    pub b: Index,
+}
+
+/// The space the instances moved by a joint pass through over its
+/// range, as overlapping convex pieces in assembly space. Each piece
+/// is the hull of a moving instance at two neighbouring samples, so
+/// the pieces bulge a little inside the path of a turning part.
+#[derive(Default,Debug,Clone)]
+pub struct Swept {
+    pieces: Vec<(Index,Geometry)>,
+}
+
+/// The joints that connect the instances of an assembly, so it can
+/// be posed by joint value. Each mounted instance moves with its
+/// parent, and anything that isn't mounted stays where it is.
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Mechanism {
+    mounts: Vec<Mount>,
+}
+
+// Whether the motion of an instance is being found or is known
+#[derive(Clone)]
+enum Visit {
+    Open,
+    Started,
+    Done(Matrix),
+}
+
+impl Mechanism {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Mounts an instance by the joint at one of its connections,
+    // to another instance or to the ground if there's no parent
+    pub fn with_mount(mut self, instance: Index, connection: Index, parent: Option<Index>) -> Self {
+        self.mounts.push(Mount { instance, connection, parent });
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn mounts(&self) -> &[Mount] {
+        &self.mounts
+    }
+
+    // The transform of every instance with each joint at a value,
+    // given in the order they were mounted. Values are clamped to
+    // the range of their joint.
+    pub fn transforms(&self, assembly: &Assembly, values: &[f64]) -> Result<Vec<Matrix>,Error> {
+        span!("assembly.kinematics", mounts = self.mounts.len());
+        if values.len() != self.mounts.len() {
+            return Err(Error::JointCount { expected: self.mounts.len(), found: values.len() });
+        }
+
+        let count = assembly.instances().len();
+        let mut mounted = vec![None;count];
+        for (index,mount) in self.mounts.iter().enumerate() {
+            let check = |i: Index| match i < count {
+                true => Ok(()),
+                false => Err(Error::IndexOutOfRange { index: i, len: count }),
+            };
+            check(mount.instance).in_item(index)?;
+            mount.parent.map_or(Ok(()),check).in_item(index)?;
+
+            let part = assembly.instances()[mount.instance].part();
+            let connections = part.connections();
+            if mount.connection >= connections.len() {
+                return Err(Error::IndexOutOfRange { index: mount.connection, len: connections.len() })
+                    .in_part(part.name())
+                    .in_item(index);
+            }
+            mounted[mount.instance] = Some(index);
+        }
+
+        let mut visits = vec![Visit::Open;count];
+        (0..count)
+            .map(|i| Ok(self.motion(assembly,values,&mounted,&mut visits,i)? * *assembly.instances()[i].transform()))
+            .collect()
+    }
+
+    // A copy of the assembly with each joint at a value
+    pub fn pose(&self, assembly: &Assembly, values: &[f64]) -> Result<Assembly,Error> {
+        let transforms = self.transforms(assembly,values)?;
+        let mut posed = assembly.clone();
+        for (instance,transform) in posed.instances_mut().iter_mut().zip(transforms) {
+            instance.set_transform(transform)?;
+        }
+        Ok(posed)
+    }
+
+    // Pairs of instances that overlap with each joint at a value,
+    // leaving out instances and the parent they're mounted to
+    pub fn collisions(&self, assembly: &Assembly, values: &[f64]) -> Result<Vec<(Index,Index)>,Error> {
+        let geometries = self.geometries(assembly,values)?;
+        let count = geometries.len();
+        Ok((0..count)
+            .flat_map(|a| (a + 1..count).map(move |b| (a,b)))
+            .filter(|(a,b)| !self.joined(*a,*b))
+            .filter(|(a,b)| geometries[*a].intersects(&geometries[*b]))
+            .collect())
+    }
+
+    // Moves one joint through its range in `steps` even steps, with
+    // the others at zero or the nearest value in their range, and
+    // finds where the instances it moves run into anything else
+    pub fn sweep(&self, assembly: &Assembly, mount: Index, steps: usize) -> Result<Vec<Collision>,Error> {
+        let (moving,samples) = self.samples(assembly,mount,steps)?;
+
+        let mut collisions = Vec::new();
+        for (value,values) in samples {
+            let geometries = self.geometries(assembly,&values)?;
+
+            for a in (0..geometries.len()).filter(|a| moving[*a]) {
+                for b in (0..geometries.len()).filter(|b| !moving[*b]) {
+                    if !self.joined(a,b) && geometries[a].intersects(&geometries[b]) {
+                        collisions.push(Collision { value, a: a.min(b), b: a.max(b) });
+                    }
+                }
+            }
+        }
+        Ok(collisions)
+    }
+
+    // The space swept by the instances one joint moves, sampled at
+    // `steps` even steps through its range with the other joints at
+    // rest. Each moving instance is replaced by its convex hull.
+    pub fn swept(&self, assembly: &Assembly, mount: Index, steps: usize) -> Result<Swept,Error> {
+        span!("assembly.swept", mount = mount, steps = steps);
+        let (moving,samples) = self.samples(assembly,mount,steps)?;
+        let poses = samples
+            .iter()
+            .map(|(_,values)| self.transforms(assembly,values))
+            .collect::<Result<Vec<Vec<Matrix>>,Error>>()?;
+
+        let mut pieces = Vec::new();
+        for (index,instance) in assembly.instances().iter().enumerate().filter(|(i,_)| moving[*i]) {
+            let hull = instance.part().geometry().convex_hull();
+            for pair in poses.windows(2) {
+                let points = pair
+                    .iter()
+                    .flat_map(|pose| hull.vertices().iter().map(|v| {
+                        let mut v = *v;
+                        v.transform(&pose[index]);
+                        v
+                    }))
+                    .collect::<Vec<Vertex>>();
+                pieces.push((index,Geometry::hull(&points)));
+            }
+        }
+        Ok(Swept { pieces })
+    }
+
+    // Which instances a joint moves, and the joint values at each
+    // step through its range with the other joints at zero or the
+    // nearest value in their range
+    fn samples(&self, assembly: &Assembly, mount: Index, steps: usize) -> Result<(Vec<bool>,Vec<Sample>),Error> {
+        let item = self.mounts
+            .get(mount)
+            .ok_or(Error::IndexOutOfRange { index: mount, len: self.mounts.len() })?;
+
+        // the joints at rest, which also checks the mounts
+        let mut values = self.mounts
+            .iter()
+            .map(|m| assembly
+                .get(m.instance)
+                .and_then(|i| i.part().connections().get(m.connection))
+                .map(|c| c.joint().clamp(0.0))
+                .unwrap_or(0.0))
+            .collect::<Vec<f64>>();
+        self.transforms(assembly,&values)?;
+
+        let joint = *assembly.instances()[item.instance].part().connections()[item.connection].joint();
+        let moving = (0..assembly.instances().len())
+            .map(|i| self.moved_by(i,item.instance))
+            .collect::<Vec<bool>>();
+
+        let (min,max) = joint.range();
+        let steps = steps.max(1);
+        let samples = (0..=steps)
+            .map(|step| {
+                let value = min + (max - min) * step as f64 / steps as f64;
+                values[mount] = value;
+                (value,values.clone())
+            })
+            .collect();
+        Ok((moving,samples))
+    }
+
+    // Every instance's geometry in assembly space at a pose
+    fn geometries(&self, assembly: &Assembly, values: &[f64]) -> Result<Vec<Geometry>,Error> {
+        let transforms = self.transforms(assembly,values)?;
+        Ok(assembly
+            .instances()
+            .iter()
+            .zip(transforms.iter())
+            .map(|(instance,transform)| {
+                let mut geometry = Geometry::default();
+                geometry.append(instance.part().geometry(),transform);
+                geometry
+            })
+            .collect())
+    }
+
+    // The motion of an instance away from where it is in the
+    // assembly, which includes the motion of its parents
+    fn motion(&self, assembly: &Assembly, values: &[f64], mounted: &[Option<Index>], visits: &mut [Visit], instance: Index) -> Result<Matrix,Error> {
+        match visits[instance] {
+            Visit::Done(motion) => return Ok(motion),
+            Visit::Started => return Err(Error::JointCycle(instance)),
+            Visit::Open => visits[instance] = Visit::Started,
+        }
+
+        let motion = match mounted[instance] {
+            Some(index) => {
+                let mount = self.mounts[index];
+                let parent = match mount.parent {
+                    Some(parent) => self.motion(assembly,values,mounted,visits,parent)?,
+                    None => Matrix::identity(),
+                };
+
+                // the joint moved to where the instance is
+                let placed = &assembly.instances()[instance];
+                let connection = &placed.part().connections()[mount.connection];
+                let mut joint = *connection.joint();
+                let mut point = connection.point();
+                joint.transform(placed.transform());
+                point.transform(placed.transform());
+                parent * joint.motion(point,values[index])
+            },
+            None => Matrix::identity(),
+        };
+
+        visits[instance] = Visit::Done(motion);
+        Ok(motion)
+    }
+
+    // True if `instance` moves with the joint of `root`
+    fn moved_by(&self, instance: Index, root: Index) -> bool {
+        let mut current = Some(instance);
+        let mut seen = 0;
+        while let Some(i) = current {
+            if i == root {
+                return true;
+            }
+            seen += 1;
+            if seen > self.mounts.len() {
+                return false;
+            }
+            current = self.mounts
+                .iter()
+                .rev()
+                .find(|m| m.instance == i)
+                .and_then(|m| m.parent);
+        }
+        false
+    }
+
+    // True if one instance is mounted to the other
+    fn joined(&self, a: Index, b: Index) -> bool {
+        self.mounts
+            .iter()
+            .any(|m| (m.instance,m.parent) == (a,Some(b)) || (m.instance,m.parent) == (b,Some(a)))
+    }
+
+}
+
+impl Swept {
+
+    // The pieces with the instance each one came from
+    pub fn pieces(&self) -> &[(Index,Geometry)] {
+        &self.pieces
+    }
+
+    // Every piece in one mesh, which overlaps itself
+    pub fn geometry(&self) -> Geometry {
+        let mut geometry = Geometry::default();
+        for (_,piece) in self.pieces.iter() {
+            geometry.append(piece,&Matrix::identity());
+        }
+        geometry
+    }
+
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.pieces
+            .iter()
+            .filter_map(|(_,p)| p.bounds())
+            .reduce(|a,b| a.union(&b))
+    }
+
+    // True if anything moving passes through a closed mesh
+    pub fn intersects(&self, other: &Geometry) -> bool {
+        self.pieces
+            .iter()
+            .any(|(_,p)| p.intersects(other))
+    }
+
+    // The instances in a static assembly that something moving
+    // passes through, leaving out the moving instances themselves
+    pub fn interference(&self, assembly: &Assembly) -> Vec<Index> {
+        assembly
+            .instances()
+            .iter()
+            .enumerate()
+            .filter(|(i,_)| !self.pieces.iter().any(|(p,_)| p == i))
+            .filter(|(_,instance)| self.intersects(&instance.geometry()))
+            .map(|(i,_)| i)
+            .collect()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::geometry::Direction;
+    use crate::part::{Part,Connection,Joint};
+    use std::f64::consts::{PI,FRAC_PI_2};
+
+    // A box between two corners
+    fn block(min: (f64,f64,f64), max: (f64,f64,f64)) -> Geometry {
+        let (a,b) = (min,max);
+        Geometry::make(
+            vec![
+                a.0,a.1,a.2, b.0,a.1,a.2, b.0,b.1,a.2, a.0,b.1,a.2,
+                a.0,a.1,b.2, b.0,a.1,b.2, b.0,b.1,b.2, a.0,b.1,b.2,
+            ],
+            vec![
+                1,3,2, 1,4,3, 5,6,7, 5,7,8,
+                1,2,6, 1,6,5, 2,3,7, 2,7,6,
+                3,4,8, 3,8,7, 4,1,5, 4,5,8,
+            ])
+    }
+
+    // A cabinet side with a door hinged on it and a drawer that
+    // slides out of it, with a wall behind the door
+    fn cabinet() -> Assembly {
+        let side = Part::new("side")
+            .with_geometry(block((0.0,0.0,0.0),(0.02,0.5,1.0)))
+            .build()
+            .unwrap();
+
+        // hinged along its left edge, swinging out towards -Y
+        let door = Part::new("door")
+            .with_geometry(block((0.0,0.0,0.0),(0.5,0.02,1.0)))
+            .with_connection(Connection::new(Vertex::new(0.0,0.0,0.5),0.01)
+                .with_joint(Joint::revolute(Direction::new(0.0,0.0,-1.0),0.0,PI)))
+            .build()
+            .unwrap();
+
+        let drawer = Part::new("drawer")
+            .with_geometry(block((0.0,0.0,0.0),(0.3,0.3,0.1)))
+            .with_connection(Connection::new(Vertex::new(0.15,0.0,0.05),0.01)
+                .with_joint(Joint::prismatic(Direction::new(0.0,-1.0,0.0),0.0,0.25)))
+            .build()
+            .unwrap();
+
+        let wall = Part::new("wall")
+            .with_geometry(block((-0.5,-0.5,0.0),(-0.4,0.5,1.0)))
+            .build()
+            .unwrap();
+
+        Assembly::new("cabinet")
+            .with_part(side,Matrix::identity())
+            .with_part(door,Matrix::translate(0.02,-0.02,0.0))
+            .with_part(drawer,Matrix::translate(0.1,0.1,0.2))
+            .with_part(wall,Matrix::identity())
+    }
+
+    #[test]
+    fn test_mechanism_pose() {
+        let assembly = cabinet();
+        let mechanism = Mechanism::new()
+            .with_mount(1,0,Some(0))
+            .with_mount(2,0,Some(0));
+
+        // a quarter turn swings the door's far edge out to -Y
+        let posed = mechanism.pose(&assembly,&[FRAC_PI_2,1.0]).unwrap();
+        let bounds = posed.instances()[1].geometry().bounds().unwrap();
+        assert_relative_eq!(bounds.min.y,-0.52,epsilon = 1e-9);
+        assert_relative_eq!(bounds.max.x,0.04,epsilon = 1e-9);
+
+        // the drawer stops at the end of its slide
+        let bounds = posed.instances()[2].geometry().bounds().unwrap();
+        assert_relative_eq!(bounds.min.y,-0.15,epsilon = 1e-9);
+
+        // the side has no connection to mount it by
+        let carried = Mechanism::new()
+            .with_mount(0,0,None)
+            .with_mount(1,0,Some(0));
+        assert!(matches!(carried.transforms(&assembly,&[0.0,0.0]),Err(Error::InItem(0,_))));
+
+        let cycle = Mechanism::new()
+            .with_mount(1,0,Some(2))
+            .with_mount(2,0,Some(1));
+        assert!(matches!(cycle.transforms(&assembly,&[0.0,0.0]),Err(Error::JointCycle(_))));
+        assert!(matches!(mechanism.transforms(&assembly,&[0.0]),Err(Error::JointCount { expected: 2, found: 1 })));
+    }
+
+    #[test]
+    fn test_mechanism_sweep() {
+        let assembly = cabinet();
+        let mechanism = Mechanism::new()
+            .with_mount(1,0,Some(0))
+            .with_mount(2,0,Some(0));
+
+        // closed, nothing overlaps
+        assert!(mechanism.collisions(&assembly,&[0.0,0.0]).unwrap().is_empty());
+
+        // the door hits the wall when it's opened far enough,
+        // and the drawer hits the closed door
+        let hits = mechanism.sweep(&assembly,0,8).unwrap();
+        assert!(!hits.is_empty());
+        assert!(hits.iter().all(|c| (c.a,c.b) == (1,3) && c.value > FRAC_PI_2));
+
+        let hits = mechanism.sweep(&assembly,1,4).unwrap();
+        assert!(hits.iter().all(|c| (c.a,c.b) == (1,2) && c.value > 0.0));
+        assert_eq!(hits.len(),3);
+        assert!(mechanism.sweep(&assembly,2,4).is_err());
+    }
+
+    #[test]
+    fn test_mechanism_swept() {
+        let assembly = cabinet();
+        let mechanism = Mechanism::new()
+            .with_mount(1,0,Some(0))
+            .with_mount(2,0,Some(0));
+
+        // the door sweeps a half disc in front of the cabinet
+        let swept = mechanism.swept(&assembly,0,12).unwrap();
+        assert_eq!(swept.pieces().len(),12);
+        let bounds = swept.bounds().unwrap();
+        assert_relative_eq!(bounds.min.x,-0.48,epsilon = 1e-9);
+        assert_relative_eq!(bounds.min.y,-0.52,epsilon = 1e-9);
+        assert_eq!(swept.interference(&assembly),vec![3]);
+
+        // the drawer only runs into the closed door
+        let swept = mechanism.swept(&assembly,1,1).unwrap();
+        assert_eq!(swept.pieces().len(),1);
+        assert_relative_eq!(swept.pieces()[0].1.volume(),0.3 * 0.55 * 0.1,epsilon = 1e-9);
+        assert_eq!(swept.interference(&assembly),vec![1]);
+        assert!(!swept.intersects(&block((2.0,2.0,2.0),(3.0,3.0,3.0))));
+    }
+
+}
@@ -0,0 +1,376 @@
+use std::collections::BTreeMap;
+
+use crate::assembly::{Assembly,Edit};
+use crate::geometry::Matrix;
+use crate::errors::Error;
+use crate::constant::Index;
+
+/// Which of the two histories made a change
+#[derive(Debug,Copy,Clone,PartialEq,Eq,PartialOrd,Ord)]
+pub enum Side {
+    Ours,
+    Theirs,
+}
+
+/// A change made in both histories that can't be kept automatically.
+/// Instances are given by their index in the common base assembly.
+#[derive(Debug,Clone)]
+pub enum Conflict {
+    /// the assembly was renamed differently
+    Name { ours: String, theirs: String },
+    /// an attribute was set to different values
+    Attribute { instance: Index, attribute: String, ours: f64, theirs: f64 },
+    /// an instance was moved to different places
+    Transform { instance: Index, ours: Box<Matrix>, theirs: Box<Matrix> },
+    /// both histories removed the same instance
+    Removed { instance: Index },
+    /// one history removed an instance the other changed
+    RemovedAndChanged { instance: Index, removed: Side },
+}
+
+/// The result of reconciling two histories that started from the
+/// same assembly. The edits are ours followed by theirs, renumbered
+/// to apply after ours, leaving out any edit of theirs that is part
+/// of a conflict.
+#[derive(Debug,Clone)]
+pub struct Merge {
+    pub edits: Vec<Edit>,
+    pub conflicts: Vec<Conflict>,
+}
+
+// Where an instance came from, which stays the same however the
+// instances around it are added or removed
+#[derive(Debug,Copy,Clone,PartialEq,Eq,PartialOrd,Ord)]
+enum Origin {
+    Base(Index),
+    Added(Side,Index),
+}
+
+// What a single edit changes
+#[derive(Debug,Clone,PartialEq,Eq,PartialOrd,Ord)]
+enum Key {
+    Name,
+    Attribute(Origin,String),
+    Transform(Origin),
+    Removed(Origin),
+}
+
+// The final value of every change made by one history
+#[derive(Default)]
+struct Changes {
+    targets: Vec<Option<Origin>>,
+    names: Option<String>,
+    values: BTreeMap<Key,f64>,
+    transforms: BTreeMap<Origin,Matrix>,
+    removed: Vec<Origin>,
+    mates: BTreeMap<Index,(Origin,Origin)>,
+}
+
+impl Changes {
+
+    // Replays the edits against the instance origins, recording
+    // what each one changes. Fails if an edit refers to an instance
+    // that doesn't exist at that point.
+    fn new(count: usize, edits: &[Edit], side: Side) -> Result<Self,Error> {
+        let mut ids = (0..count).map(Origin::Base).collect::<Vec<Origin>>();
+        let mut changes = Changes::default();
+
+        for (number,edit) in edits.iter().enumerate() {
+            let len = ids.len();
+            let target = |index: Index| ids
+                .get(index)
+                .copied()
+                .ok_or(Error::IndexOutOfRange { index, len })
+                .map_err(|e| Error::InItem(number,Box::new(e)));
+
+            let origin = match edit {
+                Edit::Name(name) => {
+                    changes.names = Some(name.clone());
+                    None
+                },
+                Edit::Add { .. } => {
+                    ids.push(Origin::Added(side,number));
+                    ids.last().copied()
+                },
+                Edit::Remove(index) => {
+                    let origin = target(*index)?;
+                    ids.remove(*index);
+                    changes.removed.push(origin);
+                    Some(origin)
+                },
+                Edit::Set { index, attribute, value } => {
+                    let origin = target(*index)?;
+                    changes.values.insert(Key::Attribute(origin,attribute.clone()),*value);
+                    Some(origin)
+                },
+                Edit::Transform { index, transform } => {
+                    let origin = target(*index)?;
+                    changes.transforms.insert(origin,*transform);
+                    Some(origin)
+                },
+                Edit::Mate { first, second } => {
+                    let ends = (target(first.0)?,target(second.0)?);
+                    changes.mates.insert(number,ends);
+                    None
+                },
+            };
+            changes.targets.push(origin);
+        }
+
+        Ok(changes)
+    }
+
+    fn touches(&self, origin: Origin) -> bool {
+        self.transforms.contains_key(&origin) ||
+        self.values.keys().any(|k| matches!(k,Key::Attribute(o,_) if *o == origin)) ||
+        self.mates.values().any(|(a,b)| *a == origin || *b == origin)
+    }
+
+}
+
+impl Merge {
+
+    // Reconciles two lists of edits made to copies of `base`
+    pub fn new(base: &Assembly, ours: &[Edit], theirs: &[Edit]) -> Result<Self,Error> {
+        let count = base.instances().len();
+        let mine = Changes::new(count,ours,Side::Ours)?;
+        let other = Changes::new(count,theirs,Side::Theirs)?;
+
+        let mut conflicts = Vec::new();
+        let mut skipped = Vec::new();
+
+        let index = |origin: &Origin| match origin {
+            Origin::Base(i) => *i,
+            Origin::Added(_,i) => *i,
+        };
+
+        if let (Some(a),Some(b)) = (&mine.names,&other.names) {
+            if a != b {
+                conflicts.push(Conflict::Name { ours: a.clone(), theirs: b.clone() });
+                skipped.push(Key::Name);
+            }
+        }
+
+        for (key,theirs) in other.values.iter() {
+            if let (Some(ours),Key::Attribute(origin,attribute)) = (mine.values.get(key),key) {
+                if ours != theirs {
+                    conflicts.push(Conflict::Attribute {
+                        instance: index(origin),
+                        attribute: attribute.clone(),
+                        ours: *ours,
+                        theirs: *theirs,
+                    });
+                    skipped.push(key.clone());
+                }
+            }
+        }
+
+        for (origin,theirs) in other.transforms.iter() {
+            if let Some(ours) = mine.transforms.get(origin) {
+                if ours.unpack() != theirs.unpack() {
+                    conflicts.push(Conflict::Transform {
+                        instance: index(origin),
+                        ours: Box::new(*ours),
+                        theirs: Box::new(*theirs),
+                    });
+                    skipped.push(Key::Transform(*origin));
+                }
+            }
+        }
+
+        for origin in other.removed.iter() {
+            if mine.removed.contains(origin) {
+                conflicts.push(Conflict::Removed { instance: index(origin) });
+                skipped.push(Key::Removed(*origin));
+            } else if mine.touches(*origin) {
+                conflicts.push(Conflict::RemovedAndChanged { instance: index(origin), removed: Side::Theirs });
+                skipped.push(Key::Removed(*origin));
+            }
+        }
+
+        for origin in mine.removed.iter() {
+            if !other.removed.contains(origin) && other.touches(*origin) {
+                conflicts.push(Conflict::RemovedAndChanged { instance: index(origin), removed: Side::Ours });
+            }
+        }
+
+        // theirs is renumbered to follow ours, and anything that
+        // conflicts or touches an instance we removed is left out
+        let mut ids = (0..count).map(Origin::Base).collect::<Vec<Origin>>();
+        for (number,edit) in ours.iter().enumerate() {
+            match edit {
+                Edit::Add { .. } => ids.push(Origin::Added(Side::Ours,number)),
+                Edit::Remove(i) => { ids.remove(*i); },
+                _ => (),
+            }
+        }
+
+        let mut edits = ours.to_vec();
+        for (number,(edit,target)) in theirs.iter().zip(other.targets.iter()).enumerate() {
+            let key = match (edit,target) {
+                (Edit::Name(_),_) => Some(Key::Name),
+                (Edit::Set { attribute, .. },Some(o)) => Some(Key::Attribute(*o,attribute.clone())),
+                (Edit::Transform { .. },Some(o)) => Some(Key::Transform(*o)),
+                (Edit::Remove(_),Some(o)) => Some(Key::Removed(*o)),
+                _ => None,
+            };
+
+            if key.is_some_and(|k| skipped.contains(&k)) {
+                continue;
+            }
+
+            // a mate is kept only if both of its instances are
+            if let (Edit::Mate { first, second },Some((a,b))) = (edit,other.mates.get(&number)) {
+                let position = |origin: &Origin| ids.iter().position(|i| i == origin);
+                if let (Some(i),Some(j)) = (position(a),position(b)) {
+                    edits.push(Edit::Mate { first: (i,first.1), second: (j,second.1) });
+                }
+                continue;
+            }
+
+            let current = match target {
+                Some(origin) => match ids.iter().position(|i| i == origin) {
+                    Some(i) => Some(i),
+                    None if matches!(edit,Edit::Add { .. }) => None,
+                    None => continue,
+                },
+                None => None,
+            };
+
+            let edit = match (edit.clone(),current) {
+                (Edit::Remove(_),Some(i)) => {
+                    ids.remove(i);
+                    Edit::Remove(i)
+                },
+                (Edit::Set { attribute, value, .. },Some(i)) => Edit::Set { index: i, attribute, value },
+                (Edit::Transform { transform, .. },Some(i)) => Edit::Transform { index: i, transform },
+                (edit,_) => {
+                    if let Some(origin) = target.filter(|_| matches!(edit,Edit::Add { .. })) {
+                        ids.push(origin);
+                    }
+                    edit
+                },
+            };
+            edits.push(edit);
+        }
+
+        Ok(Merge { edits, conflicts })
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+
+    // Applies the merged edits to a copy of the base assembly
+    pub fn apply(&self, base: &Assembly) -> Result<Assembly,Error> {
+        let mut assembly = base.clone();
+        for edit in self.edits.iter() {
+            edit.apply(&mut assembly)?;
+        }
+        Ok(assembly)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::models;
+    use crate::assembly::Instance;
+    use crate::part::{Part,Attribute,AttributeItem,Connection};
+    use crate::geometry::Vector;
+    use crate::assembly::Mate;
+
+    fn base() -> Assembly {
+        let stud = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_attribute(Attribute::new("Length".into(),vec![
+                AttributeItem::translate_specific(Vector::new(1.0,0.0,0.0),vec![4,5,6,7])
+            ]))
+            .with_connection(Connection::new(models::M2X4.geometry().vertices()[0],0.01))
+            .build()
+            .unwrap();
+
+        Assembly::new("wall")
+            .with_instance(Instance::new(stud.clone(),Matrix::identity()).with_name("a"))
+            .with_instance(Instance::new(stud.clone(),Matrix::identity()).with_name("b"))
+            .with_instance(Instance::new(stud,Matrix::identity()).with_name("c"))
+    }
+
+    fn set(index: Index, value: f64) -> Edit {
+        Edit::Set { index, attribute: "Length".into(), value }
+    }
+
+    fn length(assembly: &Assembly, name: &str) -> f64 {
+        assembly.find(name).unwrap().part().attribute("Length").unwrap().value()
+    }
+
+    #[test]
+    fn test_merge_clean() {
+        let base = base();
+
+        // we remove `a`, they change `c`, which is at index 1 for us
+        let ours = [Edit::Remove(0),set(0,1.0)];
+        let theirs = [set(2,2.0),Edit::Add { name: "d".into(), part: Box::new(base.instances()[0].part().clone()), transform: Matrix::identity() }];
+
+        let merge = Merge::new(&base,&ours,&theirs).unwrap();
+        assert!(merge.is_clean());
+
+        let result = merge.apply(&base).unwrap();
+        let names = result.instances().iter().map(|i| i.name()).collect::<Vec<_>>();
+        assert_eq!(names,["b","c","d"]);
+        assert_eq!(length(&result,"b"),1.0);
+        assert_eq!(length(&result,"c"),2.0);
+    }
+
+    #[test]
+    fn test_merge_conflicts() {
+        let base = base();
+
+        let ours = [set(0,1.0),Edit::Remove(2),Edit::Remove(1),Edit::Name("north".into())];
+        let theirs = [set(0,2.0),set(2,3.0),Edit::Remove(1),Edit::Name("south".into())];
+
+        let merge = Merge::new(&base,&ours,&theirs).unwrap();
+        assert_eq!(merge.conflicts.len(),4);
+        assert!(matches!(merge.conflicts[0],Conflict::Name { .. }));
+        assert!(matches!(merge.conflicts[1],Conflict::Attribute { instance: 0, ours: 1.0, theirs: 2.0, .. }));
+        assert!(matches!(merge.conflicts[2],Conflict::Removed { instance: 1 }));
+        assert!(matches!(merge.conflicts[3],Conflict::RemovedAndChanged { instance: 2, removed: Side::Ours }));
+
+        // ours is kept wherever there's a conflict
+        let result = merge.apply(&base).unwrap();
+        assert_eq!(result.name(),"north");
+        assert_eq!(result.instances().len(),1);
+        assert_eq!(length(&result,"a"),1.0);
+    }
+
+    #[test]
+    fn test_merge_mates() {
+        let base = base();
+
+        // we remove `a`, which they mated, so only their other mate
+        // is kept, renumbered to follow the removal
+        let ours = [Edit::Remove(0)];
+        let theirs = [Edit::Mate { first: (1,0), second: (2,0) },Edit::Mate { first: (0,0), second: (2,0) }];
+
+        let merge = Merge::new(&base,&ours,&theirs).unwrap();
+        assert_eq!(merge.conflicts.len(),1);
+        assert!(matches!(merge.conflicts[0],Conflict::RemovedAndChanged { instance: 0, removed: Side::Ours }));
+
+        let result = merge.apply(&base).unwrap();
+        assert_eq!(result.mates(),&[Mate { first: (0,0), second: (1,0) }]);
+        assert!(Merge::new(&base,&[],&[Edit::Mate { first: (3,0), second: (0,0) }]).is_err());
+    }
+
+    #[test]
+    fn test_merge_same_change() {
+        let base = base();
+        let merge = Merge::new(&base,&[set(1,1.0)],&[set(1,1.0)]).unwrap();
+        assert!(merge.is_clean());
+
+        let invalid = Merge::new(&base,&[Edit::Remove(5)],&[]);
+        assert!(matches!(invalid.unwrap_err().root(),Error::IndexOutOfRange { index: 5, len: 3 }));
+    }
+
+}
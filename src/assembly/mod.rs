@@ -0,0 +1,26 @@
+#[allow(clippy::module_inception)]
+mod assembly;
+mod instance;
+mod query;
+mod transaction;
+mod journal;
+mod merge;
+mod kinematics;
+mod routing;
+mod detail;
+mod template;
+mod graph;
+mod array;
+
+pub use assembly::{Assembly,Mate};
+pub use instance::Instance;
+pub use query::Query;
+pub use transaction::Transaction;
+pub use journal::{Journal,Edit};
+pub use merge::{Merge,Conflict,Side};
+pub use kinematics::{Mechanism,Mount,Collision,Swept};
+pub use routing::{Router,Route,Anchor};
+pub use detail::{Detail,LevelOfDetail};
+pub use template::{Template,Slot,Requirement};
+pub use graph::Graph;
+pub use array::{Array,Arrangement,Jitter,Spacing};
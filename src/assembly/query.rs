@@ -0,0 +1,177 @@
+use crate::assembly::{Assembly,Instance};
+use crate::part::Filter;
+use crate::constant::Index;
+
+/// One test that an instance has to pass to match a query
+#[derive(Debug,Clone,PartialEq)]
+enum Condition {
+    Filter(Filter),
+    NameContains(String),
+    Part(String),
+    Attribute(String),
+    LongerThan(f64),
+    ShorterThan(f64),
+}
+
+/// Finds instances in an assembly by their metadata, names, size
+/// and attributes. Every condition added has to match.
+#[derive(Debug,Clone)]
+pub struct Query<'a> {
+    assembly: &'a Assembly,
+    conditions: Vec<Condition>,
+}
+
+impl<'a> Query<'a> {
+
+    pub fn new(assembly: &'a Assembly) -> Self {
+        Self {
+            assembly,
+            conditions: Vec::new(),
+        }
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.conditions.push(Condition::Filter(filter));
+        self
+    }
+
+    pub fn tag<T: Into<String>>(self, name: T) -> Self {
+        self.filter(Filter::tag(name))
+    }
+
+    pub fn layer<T: Into<String>>(self, name: T) -> Self {
+        self.filter(Filter::layer(name))
+    }
+
+    // Instances with the text somewhere in their name
+    pub fn name_contains<T: Into<String>>(mut self, text: T) -> Self {
+        self.conditions.push(Condition::NameContains(text.into()));
+        self
+    }
+
+    // Instances of parts with exactly this name
+    pub fn part<T: Into<String>>(mut self, name: T) -> Self {
+        self.conditions.push(Condition::Part(name.into()));
+        self
+    }
+
+    pub fn has_attribute<T: Into<String>>(mut self, name: T) -> Self {
+        self.conditions.push(Condition::Attribute(name.into()));
+        self
+    }
+
+    // Instances where the longest side of the part's bounding box
+    // is longer than `length`
+    pub fn longer_than(mut self, length: f64) -> Self {
+        self.conditions.push(Condition::LongerThan(length));
+        self
+    }
+
+    pub fn shorter_than(mut self, length: f64) -> Self {
+        self.conditions.push(Condition::ShorterThan(length));
+        self
+    }
+
+    pub fn matches(&self, instance: &Instance) -> bool {
+        self.conditions.iter().all(|c| test(c,instance))
+    }
+
+    // The index of each matching instance in the assembly
+    pub fn indices(&self) -> Vec<Index> {
+        self.assembly
+            .instances()
+            .iter()
+            .enumerate()
+            .filter(|(_,i)| self.matches(i))
+            .map(|(i,_)| i)
+            .collect()
+    }
+
+    pub fn instances(&self) -> Vec<&'a Instance> {
+        let assembly = self.assembly;
+        assembly
+            .instances()
+            .iter()
+            .filter(|i| self.matches(i))
+            .collect()
+    }
+
+    pub fn first(&self) -> Option<&'a Instance> {
+        self.instances().into_iter().next()
+    }
+
+    pub fn count(&self) -> usize {
+        self.indices().len()
+    }
+
+}
+
+fn test(condition: &Condition, instance: &Instance) -> bool {
+    let part = instance.part();
+    match condition {
+        Condition::Filter(filter) => filter.matches(part.metadata()),
+        Condition::NameContains(text) => instance.name().contains(text.as_str()),
+        Condition::Part(name) => part.name() == name,
+        Condition::Attribute(name) => part.attribute(name).is_some(),
+        Condition::LongerThan(length) => longest(instance) > *length,
+        Condition::ShorterThan(length) => longest(instance) < *length,
+    }
+}
+
+// The longest side of the part's bounding box
+fn longest(instance: &Instance) -> f64 {
+    match instance.part().geometry().bounds() {
+        Some(bounds) => {
+            let size = bounds.size();
+            size.x.max(size.y).max(size.z)
+        },
+        None => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::models;
+    use crate::part::{Part,Metadata,Attribute,AttributeItem};
+    use crate::geometry::{Matrix,Vector};
+
+    fn assembly() -> Assembly {
+        let stud = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_metadata(Metadata::new().with_tag("framing"))
+            .with_attribute(Attribute::new("Length".into(),vec![
+                AttributeItem::translate_specific(Vector::new(1.0,0.0,0.0),vec![4,5,6,7])
+            ]))
+            .build()
+            .unwrap();
+
+        let mut short = stud.clone();
+        short.set("Length",-2.0).unwrap();
+
+        let block = Part::new("block")
+            .with_geometry(models::M2X4.geometry())
+            .build()
+            .unwrap();
+
+        Assembly::new("wall")
+            .with_instance(Instance::new(stud.clone(),Matrix::identity()).with_name("stud 1"))
+            .with_instance(Instance::new(short,Matrix::identity()).with_name("stud 2"))
+            .with_instance(Instance::new(block,Matrix::identity()).with_name("header"))
+    }
+
+    #[test]
+    fn test_query_conditions() {
+        let assembly = assembly();
+
+        assert_eq!(assembly.query().count(),3);
+        assert_eq!(assembly.query().tag("framing").indices(),vec![0,1]);
+        assert_eq!(assembly.query().name_contains("stud").longer_than(2.0).indices(),vec![0]);
+        assert_eq!(assembly.query().shorter_than(1.0).indices(),vec![1]);
+        assert_eq!(assembly.query().part("block").first().unwrap().name(),"header");
+        assert_eq!(assembly.query().has_attribute("Length").count(),2);
+        assert!(assembly.query().tag("framing").part("block").first().is_none());
+    }
+
+}
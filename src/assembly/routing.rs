@@ -0,0 +1,558 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::assembly::Assembly;
+use crate::geometry::{Geometry,Matrix,Transform,Vertex,Vector,Bounds};
+use crate::errors::{Error,Context};
+use crate::constant::Index;
+
+// the most cells in the search grid before it's made coarser
+const MAX_CELLS: usize = 1 << 21;
+
+// the number of straight pieces each bend is drawn with
+const ARC_STEPS: usize = 8;
+
+/// One end of a route
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub enum Anchor {
+    /// a point in assembly space
+    Point(Vertex),
+    /// a connection on the part of an instance
+    Connection {
+        instance: Index,
+        connection: Index,
+    },
+}
+
+/// Settings for finding a path for a cable, pipe or conduit through
+/// an assembly. Paths are searched for on a grid of cubes, then
+/// straightened and rounded off at each bend.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Router {
+    resolution: f64,
+    clearance: f64,
+    bend: f64,
+    ignored: Vec<Index>,
+}
+
+/// A path found between two anchors
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Route {
+    points: Vec<Vertex>,
+    bends: usize,
+}
+
+// A cell waiting to be searched, ordered so the heap gives back
+// the one with the shortest estimated path first
+#[derive(Debug,Copy,Clone,PartialEq)]
+struct Step(f64,Index);
+
+// The parts of the assembly that a route has to go around,
+// in assembly space. The search keeps further away than the
+// clearance, so that rounding off a corner that hugs a part
+// doesn't cut into the part.
+struct Space {
+    obstacles: Vec<(Geometry,Bounds)>,
+    anchors: [Vertex;2],
+    clearance: f64,
+    search: f64,
+    near: f64,
+}
+
+// The cubes the space is split into while searching
+struct Grid {
+    origin: Vertex,
+    size: f64,
+    counts: [usize;3],
+}
+
+impl From<Vertex> for Anchor {
+    fn from(point: Vertex) -> Self {
+        Anchor::Point(point)
+    }
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self {
+            resolution: 0.05,
+            clearance: 0.0,
+            bend: 0.0,
+            ignored: Vec::new(),
+        }
+    }
+}
+
+impl Eq for Step {}
+
+impl Ord for Step {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.total_cmp(&self.0).then(other.1.cmp(&self.1))
+    }
+}
+
+impl PartialOrd for Step {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Anchor {
+
+    // The anchor in assembly space
+    fn point(&self, assembly: &Assembly) -> Result<Vertex,Error> {
+        match *self {
+            Anchor::Point(point) => Ok(point),
+            Anchor::Connection { instance, connection } => {
+                let count = assembly.instances().len();
+                let instance = assembly
+                    .get(instance)
+                    .ok_or(Error::IndexOutOfRange { index: instance, len: count })?;
+                let part = instance.part();
+                let mut point = part
+                    .connections()
+                    .get(connection)
+                    .map(|c| c.point())
+                    .ok_or(Error::IndexOutOfRange { index: connection, len: part.connections().len() })
+                    .in_part(part.name())?;
+                point.transform(instance.transform());
+                Ok(point)
+            },
+        }
+    }
+
+}
+
+impl Router {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The size of the cubes searched through, in meters. Smaller
+    // cubes find paths through tighter gaps but take longer.
+    pub fn with_resolution(mut self, resolution: f64) -> Self {
+        self.resolution = resolution.abs();
+        self
+    }
+
+    // The closest the middle of the route can come to any part,
+    // which is at least the radius of the cable or pipe
+    pub fn with_clearance(mut self, clearance: f64) -> Self {
+        self.clearance = clearance.abs();
+        self
+    }
+
+    // The tightest radius the route can bend around
+    pub fn with_bend_radius(mut self, radius: f64) -> Self {
+        self.bend = radius.abs();
+        self
+    }
+
+    // Lets the route pass through an instance, like a cabinet
+    // the cable is run inside of
+    pub fn with_ignored(mut self, instance: Index) -> Self {
+        self.ignored.push(instance);
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn resolution(&self) -> f64 {
+        self.resolution
+    }
+
+    pub fn clearance(&self) -> f64 {
+        self.clearance
+    }
+
+    pub fn bend_radius(&self) -> f64 {
+        self.bend
+    }
+
+    // Finds a path between two anchors that keeps clear of every part
+    // that isn't ignored. The route can pass close to the parts at each
+    // anchor on its way out, since a connection is on a part's surface.
+    pub fn route<A: Into<Anchor>, B: Into<Anchor>>(&self, assembly: &Assembly, from: A, to: B) -> Result<Route,Error> {
+        span!("assembly.route", instances = assembly.instances().len());
+        let start = from.into().point(assembly).in_item(0)?;
+        let end = to.into().point(assembly).in_item(1)?;
+
+        // a right angle bend comes in from its corner by this much
+        let search = self.clearance + self.bend * (std::f64::consts::SQRT_2 - 1.0);
+
+        let obstacles = assembly
+            .instances()
+            .iter()
+            .enumerate()
+            .filter(|(i,_)| !self.ignored.contains(i))
+            .map(|(_,instance)| instance.geometry())
+            .filter_map(|geometry| {
+                let mut bounds = geometry.bounds()?;
+                let pad = Vector::new(search,search,search);
+                bounds.min = bounds.min - pad;
+                bounds.max = bounds.max + pad;
+                Some((geometry,bounds))
+            })
+            .collect::<Vec<(Geometry,Bounds)>>();
+
+        let grid = Grid::new(&obstacles,[start,end],self.resolution.max(f64::EPSILON),search);
+        let space = Space {
+            obstacles,
+            anchors: [start,end],
+            clearance: self.clearance,
+            search,
+            near: self.clearance + grid.size * 2.0,
+        };
+
+        let cells = grid.search(&space,start,end).ok_or(Error::NoRoute)?;
+        let mut points = vec![start];
+        points.extend(cells.iter().skip(1).take(cells.len().saturating_sub(2)).map(|c| grid.center(*c)));
+        points.push(end);
+
+        let points = straighten(&space,&points,grid.size / 4.0);
+        self.round(&space,points,grid.size / 4.0)
+    }
+
+    // Replaces every corner with an arc of the bend radius, which
+    // has to fit in the straight pieces either side of it
+    fn round(&self, space: &Space, points: Vec<Vertex>, step: f64) -> Result<Route,Error> {
+        let last = points.len() - 1;
+        let mut result = vec![points[0]];
+        let mut bends = 0;
+
+        for i in 1..last {
+            let (before,corner,after) = (points[i - 1],points[i],points[i + 1]);
+            let (incoming,outgoing) = ((corner - before).normalize(),(after - corner).normalize());
+            let angle = incoming.angle(&outgoing);
+            if angle <= 1e-9 {
+                continue;
+            }
+            bends += 1;
+
+            if self.bend == 0.0 {
+                result.push(corner);
+                continue;
+            }
+
+            // corners share the straight piece between them
+            let share = |n: Index| if n == 0 || n == last { 1.0 } else { 0.5 };
+            let tangent = self.bend * (angle / 2.0).tan();
+            let room = (corner.distance(&before) * share(i - 1)).min(corner.distance(&after) * share(i + 1));
+            if !tangent.is_finite() || tangent > room {
+                return Err(Error::NoRoute);
+            }
+
+            let middle = (outgoing - incoming).normalize();
+            let center = corner + middle * (self.bend / (angle / 2.0).cos());
+            let axis = incoming.cross(&outgoing);
+            let begin = corner - incoming * tangent;
+            for k in 0..=ARC_STEPS {
+                let mut offset = begin - center;
+                offset.transform_direction(&Matrix::rotate_axis(axis,angle * k as f64 / ARC_STEPS as f64));
+                let point = center + offset;
+                if !space.clear(result.last().unwrap_or(&point),&point,step,space.clearance) {
+                    return Err(Error::NoRoute);
+                }
+                result.push(point);
+            }
+        }
+
+        result.push(points[last]);
+        Ok(Route { points: result, bends })
+    }
+
+}
+
+impl Route {
+
+    // The points along the route, from the first anchor to the second,
+    // with each bend drawn as a number of short straight pieces
+    pub fn points(&self) -> &[Vertex] {
+        &self.points
+    }
+
+    // The length of cable or pipe needed to follow the route
+    pub fn length(&self) -> f64 {
+        self.points
+            .windows(2)
+            .map(|w| w[0].distance(&w[1]))
+            .sum()
+    }
+
+    // The number of places the route changes direction
+    pub fn bends(&self) -> usize {
+        self.bends
+    }
+
+}
+
+impl Space {
+
+    // True if a point is at least `clearance` from every part,
+    // or close to one of the anchors
+    fn free(&self, point: &Vertex, clearance: f64) -> bool {
+        if self.anchors.iter().any(|a| a.distance(point) <= self.near) {
+            return true;
+        }
+        self.obstacles
+            .iter()
+            .filter(|(_,bounds)| bounds.contains(point))
+            .all(|(geometry,_)| !geometry.encloses(point) && geometry.distance(point) >= clearance)
+    }
+
+    // True if every point along a line is free, checked at
+    // steps no longer than `step`
+    fn clear(&self, a: &Vertex, b: &Vertex, step: f64, clearance: f64) -> bool {
+        let count = (a.distance(b) / step).ceil().max(1.0) as usize;
+        (0..=count).all(|k| self.free(&(*a + (*b - *a) * (k as f64 / count as f64)),clearance))
+    }
+
+}
+
+impl Grid {
+
+    // A grid around everything with room to go around the outside,
+    // made coarser until it isn't too big to search
+    fn new(obstacles: &[(Geometry,Bounds)], anchors: [Vertex;2], size: f64, clearance: f64) -> Self {
+        let mut bounds = Bounds::new(anchors[0],anchors[0]);
+        bounds.expand(&anchors[1]);
+        for (_,b) in obstacles.iter() {
+            bounds = bounds.union(b);
+        }
+
+        let mut size = size;
+        loop {
+            let pad = clearance + size * 2.0;
+            let pad = Vector::new(pad,pad,pad);
+            let (min,max) = (bounds.min - pad,bounds.max + pad);
+            let extent = max - min;
+            let counts = [extent.x,extent.y,extent.z].map(|e| (e / size).ceil() as usize + 1);
+            if counts.iter().product::<usize>() <= MAX_CELLS {
+                return Self { origin: min, size, counts };
+            }
+            size *= 2.0;
+        }
+    }
+
+    fn index(&self, [x,y,z]: [usize;3]) -> Index {
+        (z * self.counts[1] + y) * self.counts[0] + x
+    }
+
+    fn cell(&self, index: Index) -> [usize;3] {
+        let x = index % self.counts[0];
+        let y = (index / self.counts[0]) % self.counts[1];
+        let z = index / (self.counts[0] * self.counts[1]);
+        [x,y,z]
+    }
+
+    fn locate(&self, point: &Vertex) -> Index {
+        let offset = *point - self.origin;
+        let [x,y,z] = [offset.x,offset.y,offset.z].map(|v| (v / self.size).floor().max(0.0) as usize);
+        self.index([
+            x.min(self.counts[0] - 1),
+            y.min(self.counts[1] - 1),
+            z.min(self.counts[2] - 1),
+        ])
+    }
+
+    fn center(&self, index: Index) -> Vertex {
+        let [x,y,z] = self.cell(index).map(|v| (v as f64 + 0.5) * self.size);
+        self.origin + Vector::new(x,y,z)
+    }
+
+    // The cells from the one with `start` to the one with `end`, by
+    // searching outwards in every direction including diagonals and
+    // trying the cells that head towards the end first
+    fn search(&self, space: &Space, start: Vertex, end: Vertex) -> Option<Vec<Index>> {
+        let total = self.counts.iter().product::<usize>();
+        let (first,last) = (self.locate(&start),self.locate(&end));
+        let goal = self.center(last);
+
+        // cells are only checked against the parts when they're reached
+        let mut free: Vec<Option<bool>> = vec![None;total];
+        let mut distances = vec![f64::INFINITY;total];
+        let mut previous: Vec<Option<Index>> = vec![None;total];
+        let mut heap = BinaryHeap::new();
+
+        distances[first] = 0.0;
+        heap.push(Step(self.center(first).distance(&goal),first));
+
+        while let Some(Step(_,index)) = heap.pop() {
+            if index == last {
+                let mut cells = vec![last];
+                while let Some(cell) = previous[*cells.last()?] {
+                    cells.push(cell);
+                }
+                cells.reverse();
+                return Some(cells);
+            }
+
+            let [x,y,z] = self.cell(index);
+            let center = self.center(index);
+            for dz in -1..=1_isize {
+                for dy in -1..=1_isize {
+                    for dx in -1..=1_isize {
+                        let cell = [x.checked_add_signed(dx),y.checked_add_signed(dy),z.checked_add_signed(dz)];
+                        let [Some(nx),Some(ny),Some(nz)] = cell else {
+                            continue;
+                        };
+                        if nx >= self.counts[0] || ny >= self.counts[1] || nz >= self.counts[2] {
+                            continue;
+                        }
+
+                        let next = self.index([nx,ny,nz]);
+                        let point = self.center(next);
+                        let distance = distances[index] + center.distance(&point);
+                        if distance >= distances[next] {
+                            continue;
+                        }
+                        if !*free[next].get_or_insert_with(|| space.free(&point,space.search)) {
+                            continue;
+                        }
+
+                        distances[next] = distance;
+                        previous[next] = Some(index);
+                        heap.push(Step(distance + point.distance(&goal),next));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+}
+
+// Skips every point it can, going straight from each point to the
+// furthest one after it that can be reached without hitting anything
+fn straighten(space: &Space, points: &[Vertex], step: f64) -> Vec<Vertex> {
+    let mut result = vec![points[0]];
+    let mut current = 0;
+    while current < points.len() - 1 {
+        let next = (current + 1..points.len())
+            .rev()
+            .find(|j| space.clear(&points[current],&points[*j],step,space.search))
+            .unwrap_or(current + 1);
+        result.push(points[next]);
+        current = next;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::models;
+    use crate::part::{Part,Connection};
+    use crate::assembly::Instance;
+
+    // a block lying across the straight line between the ends
+    // of a route, with a connection on top of it
+    fn wall() -> Assembly {
+        let mut geometry = models::M2X4.geometry();
+        geometry.transform(&Matrix::scale(0.5,5.0,12.0));
+
+        let part = Part::new("wall")
+            .with_geometry(geometry)
+            .with_connection(Connection::new(Vertex::new(0.0,0.0,0.2286),0.01))
+            .build()
+            .unwrap();
+
+        Assembly::new("room")
+            .with_instance(Instance::new(part.clone(),Matrix::rotate_z(std::f64::consts::FRAC_PI_2)))
+            .with_instance(Instance::new(part,Matrix::translate(3.0,0.0,0.0)).with_name("far"))
+    }
+
+    fn turn(route: &Route) -> f64 {
+        route.points()
+            .windows(3)
+            .map(|w| (w[1] - w[0]).angle(&(w[2] - w[1])))
+            .fold(0.0,f64::max)
+    }
+
+    #[test]
+    fn test_router_around_wall() {
+        let assembly = wall();
+        let (start,end) = (Vertex::new(0.0,-1.0,0.0),Vertex::new(0.0,1.0,0.0));
+
+        let route = Router::new()
+            .with_resolution(0.1)
+            .with_clearance(0.05)
+            .build()
+            .route(&assembly,start,end)
+            .unwrap();
+
+        let points = route.points();
+        assert_eq!(points.first(),Some(&start));
+        assert_eq!(points.last(),Some(&end));
+        assert!(route.length() > 2.0);
+        assert!(route.bends() > 0);
+
+        // nothing goes through the wall
+        let wall = assembly.instances()[0].geometry();
+        for pair in points.windows(2) {
+            for k in 0..=20 {
+                let point = pair[0] + (pair[1] - pair[0]) * (k as f64 / 20.0);
+                assert!(!wall.encloses(&point));
+            }
+        }
+
+        // without the wall it's straight across
+        let route = Router::new()
+            .with_ignored(0)
+            .route(&assembly,start,end)
+            .unwrap();
+        assert_eq!(route.points().len(),2);
+        assert_relative_eq!(route.length(),2.0,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_router_bend_radius() {
+        let assembly = wall();
+        let (start,end) = (Vertex::new(0.0,-1.0,0.0),Vertex::new(0.0,1.0,0.0));
+        let router = Router::new()
+            .with_resolution(0.1)
+            .with_clearance(0.05);
+
+        let sharp = router.clone().route(&assembly,start,end).unwrap();
+        let round = router.with_bend_radius(0.1).route(&assembly,start,end).unwrap();
+
+        // each bend is split into small turns, keeping the same
+        // distance from the wall
+        assert!(round.bends() > 0);
+        assert!(turn(&sharp) > 0.5);
+        assert!(turn(&round) < turn(&sharp) / 4.0);
+        let wall = assembly.instances()[0].geometry();
+        assert!(round.points().iter().all(|p| wall.distance(p) >= 0.05 - 1e-9));
+
+        // too tight to bend around
+        let result = Router::new()
+            .with_resolution(0.1)
+            .with_clearance(0.05)
+            .with_bend_radius(1.0)
+            .route(&assembly,start,end);
+        assert!(matches!(result,Err(Error::NoRoute)));
+    }
+
+    #[test]
+    fn test_router_connections() {
+        let assembly = wall();
+
+        // up and away from the top of the wall
+        let route = Router::new()
+            .with_resolution(0.1)
+            .route(&assembly,Anchor::Connection { instance: 0, connection: 0 },Vertex::new(1.0,0.0,0.5))
+            .unwrap();
+        assert_relative_eq!(route.points()[0].z,0.2286,epsilon = 1e-9);
+        assert_relative_eq!(route.length(),(1.0 + 0.2714 * 0.2714f64).sqrt(),epsilon = 1e-9);
+
+        let missing = Router::new().route(&assembly,Anchor::Connection { instance: 1, connection: 2 },Vertex::default());
+        assert!(matches!(missing.unwrap_err().root(),Error::IndexOutOfRange { index: 2, len: 1 }));
+        let missing = Router::new().route(&assembly,Vertex::default(),Anchor::Connection { instance: 5, connection: 0 });
+        assert!(matches!(missing,Err(Error::InItem(1,_))));
+    }
+
+}
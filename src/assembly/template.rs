@@ -0,0 +1,302 @@
+use std::mem::discriminant;
+
+use crate::assembly::{Assembly,Instance};
+use crate::geometry::Matrix;
+use crate::part::{Part,Catalog,Connection,Joint};
+use crate::errors::Error;
+use crate::constant::TOLERANCE;
+
+/// A connection that a part needs to have to fill a slot
+#[derive(Debug,Clone,PartialEq)]
+pub struct Requirement {
+    /// the kind of joint, whatever its axis and range, or None
+    /// for any joint
+    pub joint: Option<Joint>,
+    /// the smallest and largest radius, in meters
+    pub radius: (f64,f64),
+}
+
+/// A place in a template that any part with the right size and
+/// connections can be put in
+#[derive(Debug,Clone)]
+pub struct Slot {
+    name: String,
+    transform: Matrix,
+    size: Option<([f64;3],[f64;3])>,
+    requirements: Vec<Requirement>,
+}
+
+/// An assembly with slots left open, so that one design can be
+/// built from different parts, like a bookshelf that takes any
+/// 3/4in panel for its shelves
+#[derive(Debug,Clone)]
+pub struct Template {
+    name: String,
+    instances: Vec<Instance>,
+    slots: Vec<Slot>,
+}
+
+impl Requirement {
+
+    // A connection with any joint and a radius between `min` and `max`
+    pub fn new(min: f64, max: f64) -> Self {
+        Self { joint: None, radius: (min.min(max),min.max(max)) }
+    }
+
+    // The same kind of joint as `joint`
+    pub fn with_joint(mut self, joint: Joint) -> Self {
+        self.joint = Some(joint);
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn matches(&self, connection: &Connection) -> bool {
+        let (min,max) = self.radius;
+        let radius = connection.radius();
+        let joint = self.joint.is_none_or(|j| discriminant(&j) == discriminant(connection.joint()));
+        joint && radius >= min - TOLERANCE && radius <= max + TOLERANCE
+    }
+
+}
+
+impl Slot {
+
+    // A slot that puts its part at `transform` in the assembly
+    pub fn new<T: Into<String>>(name: T, transform: Matrix) -> Self {
+        Self {
+            name: name.into(),
+            transform,
+            size: None,
+            requirements: Vec::new(),
+        }
+    }
+
+    // The smallest and largest sides of the part's bounding box,
+    // longest first, so a panel is limited in thickness by the
+    // last side
+    pub fn with_size(mut self, min: [f64;3], max: [f64;3]) -> Self {
+        self.size = Some((min,max));
+        self
+    }
+
+    // Adds a connection the part needs to have. Each requirement
+    // needs a different connection.
+    pub fn with_requirement(mut self, requirement: Requirement) -> Self {
+        self.requirements.push(requirement);
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    pub fn requirements(&self) -> &[Requirement] {
+        &self.requirements
+    }
+
+    // True if the part is the right size and has a connection for
+    // every requirement
+    pub fn accepts(&self, part: &Part) -> bool {
+        let size = part
+            .geometry()
+            .bounds()
+            .map(|b| b.size())
+            .map(|s| {
+                let mut size = [s.x,s.y,s.z];
+                size.sort_by(|a,b| b.total_cmp(a));
+                size
+            });
+
+        let sized = match (self.size,size) {
+            (None,_) => true,
+            (Some(_),None) => false,
+            (Some((min,max)),Some(size)) => (0..3).all(|i| {
+                size[i] >= min[i] - TOLERANCE && size[i] <= max[i] + TOLERANCE
+            }),
+        };
+
+        sized && assign(&self.requirements,part.connections(),&mut vec![false;part.connections().len()])
+    }
+
+}
+
+impl Template {
+
+    pub fn new<T: Into<String>>(name: T) -> Self {
+        Self {
+            name: name.into(),
+            instances: Vec::new(),
+            slots: Vec::new(),
+        }
+    }
+
+    // A part that's always in the assembly
+    pub fn with_instance(mut self, instance: Instance) -> Self {
+        self.instances.push(instance);
+        self
+    }
+
+    pub fn with_slot(mut self, slot: Slot) -> Self {
+        self.slots.push(slot);
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn instances(&self) -> &[Instance] {
+        &self.instances
+    }
+
+    pub fn slots(&self) -> &[Slot] {
+        &self.slots
+    }
+
+    pub fn slot(&self, name: &str) -> Option<&Slot> {
+        self.slots.iter().find(|s| s.name == name)
+    }
+
+    // The parts in the catalog that fit a slot, by name
+    pub fn candidates<'a>(&self, slot: &str, catalog: &'a Catalog) -> Result<Vec<&'a Part>,Error> {
+        let slot = self.slot(slot).ok_or_else(|| Error::UnknownSlot(slot.into()))?;
+        Ok(catalog.parts().filter(|p| slot.accepts(p)).collect())
+    }
+
+    // Builds the assembly with the named part from the catalog in
+    // each slot, given as pairs of slot and part names. Every slot
+    // needs a part, and each instance is named after its slot.
+    pub fn fill(&self, catalog: &Catalog, choices: &[(&str,&str)]) -> Result<Assembly,Error> {
+        span!("template.fill", slots = self.slots.len());
+        if let Some((slot,_)) = choices.iter().find(|(s,_)| self.slot(s).is_none()) {
+            return Err(Error::UnknownSlot(slot.to_string()));
+        }
+
+        let mut assembly = Assembly::new(self.name.clone());
+        for instance in self.instances.iter() {
+            assembly.add(instance.clone());
+        }
+
+        for slot in self.slots.iter() {
+            let (_,name) = choices
+                .iter()
+                .find(|(s,_)| *s == slot.name)
+                .ok_or_else(|| Error::EmptySlot(slot.name.clone()))?;
+
+            let part = catalog
+                .get(name)
+                .ok_or_else(|| Error::UnknownPart(name.to_string()))?;
+
+            if !slot.accepts(part) {
+                return Err(Error::SlotMismatch { slot: slot.name.clone(), part: name.to_string() });
+            }
+
+            assembly.add(Instance::new(part.clone(),slot.transform).with_name(slot.name.clone()));
+        }
+        Ok(assembly)
+    }
+
+    // Builds the assembly with the first part by name that fits
+    // each slot
+    pub fn fill_first(&self, catalog: &Catalog) -> Result<Assembly,Error> {
+        let mut choices = Vec::new();
+        for slot in self.slots.iter() {
+            let part = catalog
+                .parts()
+                .find(|p| slot.accepts(p))
+                .ok_or_else(|| Error::EmptySlot(slot.name.clone()))?;
+            choices.push((slot.name.as_str(),part.name()));
+        }
+        self.fill(catalog,&choices)
+    }
+
+}
+
+// True if every requirement can be given its own connection, trying
+// each free connection for the first requirement in turn
+fn assign(requirements: &[Requirement], connections: &[Connection], used: &mut Vec<bool>) -> bool {
+    let Some((first,rest)) = requirements.split_first() else {
+        return true;
+    };
+    for (index,connection) in connections.iter().enumerate() {
+        if used[index] || !first.matches(connection) {
+            continue;
+        }
+        used[index] = true;
+        if assign(rest,connections,used) {
+            return true;
+        }
+        used[index] = false;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::models::{Lumber,SheetGoods};
+    use crate::geometry::Direction;
+
+    #[test]
+    fn test_template_fill() {
+        let catalog = Catalog::new()
+            .with_part(SheetGoods::Plywood.part())
+            .with_part(SheetGoods::Mdf.part())
+            .with_part(SheetGoods::Osb.part())
+            .with_part(Lumber::TwoByFour.part());
+
+        // a 3/4in panel with a connection at each end
+        let shelf = |i: usize| Slot::new(format!("shelf {}",i),Matrix::translate(0.0,0.0,i as f64 * 0.3))
+            .with_size([1.0,0.5,0.018],[3.0,2.0,0.02])
+            .with_requirement(Requirement::new(0.009,0.01).with_joint(Joint::Fixed))
+            .with_requirement(Requirement::new(0.009,0.01))
+            .build();
+
+        let template = Template::new("bookshelf")
+            .with_instance(Instance::new(Lumber::FourByFour.part(),Matrix::identity()))
+            .with_slot(shelf(1))
+            .with_slot(shelf(2))
+            .build();
+
+        let names = template
+            .candidates("shelf 1",&catalog)
+            .unwrap()
+            .iter()
+            .map(|p| p.name())
+            .collect::<Vec<&str>>();
+        assert_eq!(names,vec!["mdf","plywood"]);
+
+        let assembly = template.fill(&catalog,&[("shelf 1","plywood"),("shelf 2","mdf")]).unwrap();
+        assert_eq!(assembly.instances().len(),3);
+        assert_eq!(assembly.instances()[2].name(),"shelf 2");
+        assert_eq!(assembly.instances()[2].part().name(),"mdf");
+
+        assert_eq!(template.fill_first(&catalog).unwrap().instances()[1].part().name(),"mdf");
+        assert!(matches!(template.fill(&catalog,&[("shelf 1","osb")]),Err(Error::SlotMismatch { .. })));
+        assert!(matches!(template.fill(&catalog,&[("shelf 1","mdf")]),Err(Error::EmptySlot(s)) if s == "shelf 2"));
+        assert!(matches!(template.fill(&catalog,&[("shelf 3","mdf")]),Err(Error::UnknownSlot(_))));
+        assert!(matches!(template.candidates("top",&catalog),Err(Error::UnknownSlot(_))));
+
+        // a hinge needs a revolute connection, which none of the parts have
+        let door = Slot::new("door",Matrix::identity())
+            .with_requirement(Requirement::new(0.0,1.0).with_joint(Joint::revolute(Direction::new(0.0,0.0,1.0),0.0,1.0)));
+        assert!(Template::new("cabinet").with_slot(door).fill_first(&catalog).is_err());
+    }
+
+}
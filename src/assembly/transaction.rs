@@ -0,0 +1,189 @@
+use crate::assembly::{Assembly,Query};
+use crate::geometry::Matrix;
+use crate::errors::Error;
+use crate::constant::Index;
+
+type Check<'a> = Box<dyn Fn(&Assembly) -> Result<(),Error> + 'a>;
+
+/// A set of edits to an assembly that are kept together or not at
+/// all. Edits are made in place, and the assembly is put back the
+/// way it was if the transaction is rolled back, fails validation
+/// when committed or is dropped without being committed.
+pub struct Transaction<'a> {
+    assembly: &'a mut Assembly,
+    snapshot: Option<Assembly>,
+    checks: Vec<Check<'a>>,
+}
+
+impl<'a> Transaction<'a> {
+
+    pub fn new(assembly: &'a mut Assembly) -> Self {
+        let snapshot = Some(assembly.clone());
+        Self {
+            assembly,
+            snapshot,
+            checks: Vec::new(),
+        }
+    }
+
+    // Adds a test the assembly has to pass before the
+    // transaction can be committed
+    pub fn with_check<F>(mut self, check: F) -> Self
+    where
+        F: Fn(&Assembly) -> Result<(),Error> + 'a
+    {
+        self.checks.push(Box::new(check));
+        self
+    }
+
+    pub fn assembly(&self) -> &Assembly {
+        self.assembly
+    }
+
+    // Gives access to the assembly for edits that don't have
+    // their own method here
+    pub fn assembly_mut(&mut self) -> &mut Assembly {
+        self.assembly
+    }
+
+    pub fn set(&mut self, index: Index, name: &str, value: f64) -> Result<(),Error> {
+        let len = self.assembly.instances().len();
+        self.assembly
+            .instances_mut()
+            .get_mut(index)
+            .ok_or(Error::IndexOutOfRange { index, len })?
+            .part_mut()
+            .set(name,value)
+    }
+
+    pub fn set_all<F>(&mut self, query: F, name: &str, value: f64) -> Vec<(Index,Result<(),Error>)>
+    where
+        F: Fn(Query) -> Query
+    {
+        self.assembly.set_all(query,name,value)
+    }
+
+    pub fn set_transform(&mut self, index: Index, transform: Matrix) -> Result<(),Error> {
+        let len = self.assembly.instances().len();
+        self.assembly
+            .instances_mut()
+            .get_mut(index)
+            .ok_or(Error::IndexOutOfRange { index, len })?
+            .set_transform(transform)
+    }
+
+    // Validates every part and then runs the checks
+    pub fn validate(&self) -> Result<(),Error> {
+        for instance in self.assembly.instances().iter() {
+            instance.part().validate()?;
+        }
+        for check in self.checks.iter() {
+            check(self.assembly)?;
+        }
+        Ok(())
+    }
+
+    // Keeps the edits if the assembly is valid, and otherwise
+    // rolls them back and returns the reason.
+    pub fn commit(mut self) -> Result<(),Error> {
+        let result = self.validate();
+        if result.is_ok() {
+            self.snapshot = None;
+        }
+        result
+    }
+
+    pub fn rollback(self) {
+        // dropping restores the snapshot
+    }
+
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            *self.assembly = snapshot;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::models;
+    use crate::assembly::Instance;
+    use crate::part::{Part,Attribute,AttributeItem};
+    use crate::geometry::Vector;
+
+    fn assembly() -> Assembly {
+        let stud = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_attribute(Attribute::new("Length".into(),vec![
+                AttributeItem::translate_specific(Vector::new(1.0,0.0,0.0),vec![4,5,6,7])
+            ]))
+            .build()
+            .unwrap();
+
+        Assembly::new("wall")
+            .with_instance(Instance::new(stud.clone(),Matrix::identity()).with_name("left"))
+            .with_instance(Instance::new(stud,Matrix::translate(0.0,1.0,0.0)).with_name("right"))
+    }
+
+    fn x(assembly: &Assembly, index: Index) -> f64 {
+        assembly.instances()[index].part().geometry().vertices()[4].x
+    }
+
+    #[test]
+    fn test_transaction_commit() {
+        let mut assembly = assembly();
+
+        let mut transaction = assembly.transaction();
+        transaction.set(0,"Length",1.0).unwrap();
+        transaction.set_transform(1,Matrix::translate(0.0,2.0,0.0)).unwrap();
+        assert!(transaction.set(5,"Length",1.0).is_err());
+        transaction.commit().unwrap();
+
+        assert_relative_eq!(x(&assembly,0),2.2192,epsilon = 1e-9);
+        assert_eq!(assembly.instances()[1].geometry().vertices()[0].y,2.0 - 0.04445);
+    }
+
+    #[test]
+    fn test_transaction_rollback() {
+        let mut assembly = assembly();
+
+        let mut transaction = assembly.transaction();
+        transaction.set(0,"Length",1.0).unwrap();
+        transaction.assembly_mut().remove(1);
+        transaction.rollback();
+
+        assert_relative_eq!(x(&assembly,0),1.2192,epsilon = 1e-9);
+        assert_eq!(assembly.instances().len(),2);
+
+        // dropping without committing also rolls back
+        {
+            let mut transaction = assembly.transaction();
+            transaction.set(1,"Length",1.0).unwrap();
+        }
+        assert_relative_eq!(x(&assembly,1),1.2192,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_transaction_failed_check() {
+        let mut assembly = assembly();
+
+        // both studs have to stay the same length
+        let mut transaction = assembly
+            .transaction()
+            .with_check(|a| match x(a,0) == x(a,1) {
+                true => Ok(()),
+                false => Err(Error::FixedAttribute),
+            });
+
+        transaction.set(0,"Length",1.0).unwrap();
+        assert!(transaction.validate().is_err());
+        assert!(matches!(transaction.commit(),Err(Error::FixedAttribute)));
+        assert_relative_eq!(x(&assembly,0),1.2192,epsilon = 1e-9);
+    }
+
+}
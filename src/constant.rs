@@ -2,4 +2,21 @@
 pub type Index = usize;
 
 pub const VERTEX_TAG: char = 'v';
-pub const FACE_TAG: char = 'f';
\ No newline at end of file
+pub const FACE_TAG: char = 'f';
+pub const NORMAL_TAG: &str = "vn";
+pub const UV_TAG: &str = "vt";
+pub const VERTEX_NORMAL_TAG: &str = "vs";
+pub const VERTEX_GROUP_TAG: &str = "vg";
+pub const CHANNEL_TAG: &str = "vc";
+pub const ATTRIBUTE_TAG: char = 'a';
+pub const OBJECT_TAG: char = 'o';
+pub const GROUP_TAG: char = 'g';
+pub const FEATURE_TAG: &str = "feature";
+pub const SUPPRESS_TAG: &str = "suppress";
+pub const ROLLBACK_TAG: &str = "rollback";
+pub const CONNECTION_TAG: &str = "connection";
+pub const METADATA_TAG: &str = "meta";
+pub const COMMENT_TAG: char = '#';
+
+// distance below which two points are considered coincident
+pub const TOLERANCE: f64 = 1e-6;
\ No newline at end of file
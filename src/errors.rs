@@ -6,6 +6,9 @@ pub enum Error {
     #[error("Could not parse string to geometry")]
     ParseError,
 
+    #[error("Line {line} is not valid: '{text}'")]
+    InvalidLine { line: usize, text: String },
+
     #[error("Attribute scaling value is 0.0")]
     FixedAttribute,
 
@@ -15,9 +18,210 @@ pub enum Error {
     #[error("Attribute doesn't change any vertices")]
     EmptyAttribute,
 
+    #[error("Part doesn't have an attribute named '{0}'")]
+    UnknownAttribute(String),
+
+    #[error("Catalog doesn't have a part named '{0}'")]
+    UnknownPart(String),
+
+    #[error("Template doesn't have a slot named '{0}'")]
+    UnknownSlot(String),
+
+    #[error("Slot '{0}' doesn't have a part in it")]
+    EmptySlot(String),
+
+    #[error("Part '{part}' doesn't fit slot '{slot}'")]
+    SlotMismatch { slot: String, part: String },
+
+    #[error("Geometry doesn't have a vertex group named '{0}'")]
+    UnknownGroup(String),
+
+    #[error("Geometry doesn't have a channel named '{0}'")]
+    UnknownChannel(String),
+
+    #[error("Channel has {found} values but the geometry has {expected} vertices")]
+    ChannelLength { expected: usize, found: usize },
+
+    #[error("Got {found} colors but expected {expected}")]
+    ColorCount { expected: usize, found: usize },
+
+    #[error("Selection needs a geometry to be resolved")]
+    UnresolvedSelection,
+
+    #[error("Geometry is flat along axis {0}, so it can't be scaled to a size")]
+    FlatAxis(usize),
+
+    #[error("Can't make a part {0} long, it needs to be more than 0")]
+    InvalidSize(f64),
+
+    #[error("Part doesn't have any geometry")]
+    EmptyGeometry,
+
+    #[error("Profile doesn't have a closed outline")]
+    EmptyProfile,
+
+    #[error("Could not tessellate profile: {0}")]
+    TessellationError(String),
+
+    #[error("Part '{0}' doesn't have a flat face to nest")]
+    NotFlat(String),
+
+    #[error("Part '{0}' doesn't fit on the sheet")]
+    DoesNotFit(String),
+
+    #[error("Could not read font: {0}")]
+    InvalidFont(String),
+
+    #[error("Could not read materials: {0}")]
+    InvalidMaterial(String),
+
+    #[error("Connection {index} is {distance} away from the part surface")]
+    DetachedConnection { index: usize, distance: f64 },
+
+    #[error("Got {found} joint values for {expected} joints")]
+    JointCount { expected: usize, found: usize },
+
+    #[error("{0} cannot mate with {1}")]
+    Incompatible(String,String),
+
+    #[error("Instance {0} is attached to itself through its joints")]
+    JointCycle(usize),
+
+    #[error("Instance {0} can't be moved in or out along any of its connections")]
+    Blocked(usize),
+
+    #[error("Could not find a route between the anchors")]
+    NoRoute,
+
+    #[error("Could not fit a shape to {0} vertices")]
+    InvalidFit(usize),
+
+    #[error("Index {index} is out of range for {len} items")]
+    IndexOutOfRange { index: usize, len: usize },
+
+    #[error("Range {start}..{end} is invalid for {len} items")]
+    InvalidRange { start: usize, end: usize, len: usize },
+
+    #[error("Could not encode data: {0}")]
+    EncodeError(String),
+
+    #[error("Could not decode image: {0}")]
+    DecodeError(String),
+
+    #[error("File '{0}' doesn't match the hash in its manifest")]
+    ChecksumMismatch(String),
+
+    #[error("File '{0}' is listed in the manifest but missing")]
+    MissingFile(String),
+
+    #[error("File '{0}' in the manifest isn't a path inside the bundle")]
+    OutsideBundle(String),
+
+    #[error("Can't tell which way datum '{0}' faces into the part")]
+    UnknownDirection(String),
+
+    #[error("Part doesn't have a feature named '{0}'")]
+    UnknownFeature(String),
+
+    #[error("Part already has a feature named '{0}'")]
+    DuplicateFeature(String),
+
+    #[error("Could not read or write a file")]
+    IoError(#[from] std::io::Error),
+
+    #[error("In part '{0}': {1}")]
+    InPart(String, #[source] Box<Error>),
+
+    #[error("In attribute '{0}': {1}")]
+    InAttribute(String, #[source] Box<Error>),
+
+    #[error("In feature '{0}': {1}")]
+    InFeature(String, #[source] Box<Error>),
+
+    #[error("In item {0}: {1}")]
+    InItem(usize, #[source] Box<Error>),
+
+    #[error("On line {0}: {1}")]
+    InLine(usize, #[source] Box<Error>),
+
     #[error("Could not parse a float from string")]
     ParseFloatError(#[from] std::num::ParseFloatError),
 
     #[error("Could not parse an integer from string")]
     ParseIntError(#[from] std::num::ParseIntError),
-}
\ No newline at end of file
+}
+
+impl Error {
+
+    // The error underneath any context wrappers
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::InPart(_,e) |
+            Error::InAttribute(_,e) |
+            Error::InFeature(_,e) |
+            Error::InItem(_,e) |
+            Error::InLine(_,e) => e.root(),
+            e => e,
+        }
+    }
+
+}
+
+/// Adds the location of a failure to an error as it's returned
+pub trait Context<T> {
+    fn in_part(self, name: &str) -> Result<T,Error>;
+    fn in_attribute(self, name: &str) -> Result<T,Error>;
+    fn in_feature(self, name: &str) -> Result<T,Error>;
+    fn in_item(self, index: usize) -> Result<T,Error>;
+    fn in_line(self, line: usize) -> Result<T,Error>;
+}
+
+impl<T> Context<T> for Result<T,Error> {
+
+    fn in_part(self, name: &str) -> Result<T,Error> {
+        self.map_err(|e| Error::InPart(name.into(),Box::new(e)))
+    }
+
+    fn in_attribute(self, name: &str) -> Result<T,Error> {
+        self.map_err(|e| Error::InAttribute(name.into(),Box::new(e)))
+    }
+
+    fn in_feature(self, name: &str) -> Result<T,Error> {
+        self.map_err(|e| Error::InFeature(name.into(),Box::new(e)))
+    }
+
+    fn in_item(self, index: usize) -> Result<T,Error> {
+        self.map_err(|e| Error::InItem(index,Box::new(e)))
+    }
+
+    fn in_line(self, line: usize) -> Result<T,Error> {
+        self.map_err(|e| Error::InLine(line,Box::new(e)))
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_error_context() {
+        let result: Result<(),Error> = Err(Error::UnknownGroup("front".into()));
+        let error = result
+            .in_item(1)
+            .in_attribute("Length")
+            .in_part("2x4")
+            .unwrap_err();
+
+        assert!(matches!(error.root(),Error::UnknownGroup(n) if n == "front"));
+        assert_eq!(
+            error.to_string(),
+            "In part '2x4': In attribute 'Length': In item 1: \
+            Geometry doesn't have a vertex group named 'front'");
+
+        let source = std::error::Error::source(&error).unwrap();
+        assert!(source.to_string().starts_with("In attribute 'Length'"));
+    }
+
+}
@@ -0,0 +1,325 @@
+use std::fs;
+use std::path::{Path,Component};
+use sha2::{Sha256,Digest};
+
+use crate::assembly::Assembly;
+use crate::analysis::Bom;
+use crate::export::{Mesh,csv,stl};
+use crate::materials::Database;
+use crate::part::Part;
+use crate::errors::Error;
+
+// the file in a bundle that lists every other file
+pub const MANIFEST: &str = "manifest.txt";
+
+// the folder in a bundle that parts are written to
+const PARTS: &str = "parts";
+
+// the extension of part files, which are in the text format that
+// `Part` reads and not plain OBJ
+const PART_EXTENSION: &str = "part";
+
+// the files in a bundle for the whole assembly
+const MESH: &str = "assembly.stl";
+const BOM: &str = "bom.csv";
+
+/// A file in a bundle with its size and SHA-256 hash
+#[derive(Debug,Clone,PartialEq)]
+pub struct Entry {
+    path: String,
+    size: u64,
+    hash: String,
+}
+
+/// The files in an exported bundle, so that a copy can be checked
+/// for missing or changed files before it's loaded
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Manifest {
+    entries: Vec<Entry>,
+}
+
+impl Entry {
+
+    // An entry for the contents of a file at `path`, relative to
+    // the bundle and separated by '/'
+    pub fn new<T: Into<String>>(path: T, data: &[u8]) -> Self {
+        Self {
+            path: path.into(),
+            size: data.len() as u64,
+            hash: hash(data),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+
+    // Checks the contents of the file against the size and hash
+    pub fn matches(&self, data: &[u8]) -> bool {
+        data.len() as u64 == self.size && hash(data) == self.hash
+    }
+
+}
+
+impl Manifest {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Adds an entry for a file, replacing any with the same path
+    pub fn add<T: Into<String>>(&mut self, path: T, data: &[u8]) {
+        let entry = Entry::new(path,data);
+        self.entries.retain(|e| e.path != entry.path);
+        self.entries.push(entry);
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn get(&self, path: &str) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.path == path)
+    }
+
+    // Reads the manifest file of the bundle in `directory`
+    pub fn read<P: AsRef<Path>>(directory: P) -> Result<Self,Error> {
+        let text = fs::read_to_string(directory.as_ref().join(MANIFEST))?;
+        Self::try_from(text.as_str())
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, directory: P) -> Result<(),Error> {
+        fs::write(directory.as_ref().join(MANIFEST),String::from(self))?;
+        Ok(())
+    }
+
+    // Checks every file in the manifest against the files in
+    // `directory`, failing on the first that's missing or changed.
+    // Files that aren't in the manifest are ignored.
+    pub fn verify<P: AsRef<Path>>(&self, directory: P) -> Result<(),Error> {
+        span!("bundle.verify", files = self.entries.len());
+        for entry in self.entries.iter() {
+            let data = match fs::read(locate(directory.as_ref(),&entry.path)) {
+                Ok(data) => data,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    return Err(Error::MissingFile(entry.path.clone()));
+                },
+                Err(e) => return Err(e.into()),
+            };
+            if !entry.matches(&data) {
+                return Err(Error::ChecksumMismatch(entry.path.clone()));
+            }
+        }
+        Ok(())
+    }
+
+}
+
+// The SHA-256 hash of some data as lowercase hex
+pub fn hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}",b))
+        .collect()
+}
+
+// Writes an assembly to `directory` as a part file for each instance,
+// a mesh of the whole assembly, a bill of materials from `database`
+// and a manifest of all of them, creating the directory if it doesn't
+// exist. Part files are numbered in the order of the instances so that
+// instances with the same name don't clash.
+pub fn write<P: AsRef<Path>>(directory: P, assembly: &Assembly, database: &Database) -> Result<Manifest,Error> {
+    span!("bundle.write", instances = assembly.instances().len());
+    let directory = directory.as_ref();
+    fs::create_dir_all(directory.join(PARTS))?;
+
+    let mut files = Vec::new();
+    for (index,instance) in assembly.instances().iter().enumerate() {
+        let path = format!("{}/{}-{}.{}",PARTS,index,filename(instance.name()),PART_EXTENSION);
+        files.push((path,String::from(instance.part()).into_bytes()));
+    }
+    files.push((MESH.into(),stl::encode(&Mesh::from_assembly(assembly))));
+    files.push((BOM.into(),csv::encode_bom(&Bom::new(assembly,database)).into_bytes()));
+
+    let mut manifest = Manifest::new();
+    for (path,data) in files.iter() {
+        fs::write(locate(directory,path),data)?;
+        manifest.add(path.as_str(),data);
+    }
+    manifest.write(directory)?;
+    Ok(manifest)
+}
+
+// Verifies the bundle in `directory` and reads the parts in it, in
+// the order they were written
+pub fn read<P: AsRef<Path>>(directory: P) -> Result<Vec<Part>,Error> {
+    let directory = directory.as_ref();
+    let manifest = Manifest::read(directory)?;
+    manifest.verify(directory)?;
+
+    let mut parts = Vec::new();
+    for entry in manifest.entries().iter().filter(|e| e.path.starts_with(PARTS)) {
+        let text = fs::read_to_string(locate(directory,&entry.path))?;
+        parts.push(Part::try_from(text)?);
+    }
+    Ok(parts)
+}
+
+// The path of a file in the bundle on this system
+fn locate(directory: &Path, path: &str) -> std::path::PathBuf {
+    path.split('/').fold(directory.to_path_buf(),|p,c| p.join(c))
+}
+
+// Whether a path from a manifest stays inside the bundle, so every
+// part of it between the '/'s is a plain name on this system
+fn inside(path: &str) -> bool {
+    path.split('/').all(|c| {
+        let mut components = Path::new(c).components();
+        matches!((components.next(),components.next()),(Some(Component::Normal(_)),None))
+    })
+}
+
+// A name with anything that isn't safe in a file name replaced
+fn filename(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+// A manifest is written as one line per file with the hash, size and
+// path separated by spaces, like the output of `sha256sum` with sizes
+impl From<&Manifest> for String {
+    fn from(manifest: &Manifest) -> Self {
+        manifest.entries
+            .iter()
+            .map(|e| format!("{} {} {}\n",e.hash,e.size,e.path))
+            .collect()
+    }
+}
+
+impl TryFrom<&str> for Manifest {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut entries = Vec::new();
+        for (index,line) in value.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || Error::InvalidLine { line: index + 1, text: line.into() };
+            let mut fields = line.splitn(3,' ');
+            let (Some(hash),Some(size),Some(path)) = (fields.next(),fields.next(),fields.next()) else {
+                return Err(invalid());
+            };
+            if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(invalid());
+            }
+            if !inside(path) {
+                return Err(Error::OutsideBundle(path.into()));
+            }
+
+            entries.push(Entry {
+                path: path.into(),
+                size: size.parse()?,
+                hash: hash.to_ascii_lowercase(),
+            });
+        }
+        Ok(Self { entries })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::assembly::Instance;
+    use crate::geometry::{Matrix,Vector};
+    use crate::part::{Attribute,AttributeItem,Alteration,Selection};
+    use crate::models::M2X4;
+
+    // the attribute picks its vertices by group, which is written
+    // with the part and read back from the bundle
+    fn stud() -> Part {
+        let mut geometry = M2X4.geometry();
+        geometry.add_group("end",[4,5,6,7]).unwrap();
+        Part::new("2x4")
+            .with_geometry(geometry)
+            .with_attribute(Attribute::new("Length".into(),vec![
+                AttributeItem::new(Selection::group("end"),Alteration::translate(Vector::new(1.0,0.0,0.0)))
+            ]))
+    }
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let directory = std::env::temp_dir().join(format!("construct-bundle-{}",std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+
+        let assembly = Assembly::new("shelf")
+            .with_instance(Instance::new(stud(),Matrix::identity()).with_name("left leg"))
+            .with_instance(Instance::new(stud(),Matrix::translate(1.0,0.0,0.0)).with_name("left leg"))
+            .with_instance(Instance::new(Part::new("shelf").with_geometry(M2X4.geometry()),Matrix::identity()));
+
+        let database = Database::builtin();
+        let manifest = write(&directory,&assembly,&database).unwrap();
+        assert_eq!(manifest.entries().len(),5);
+        assert_eq!(manifest.entries()[1].path(),"parts/1-left_leg.part");
+        assert_eq!(Manifest::read(&directory).unwrap(),manifest);
+
+        // the bill of materials is the same as a csv export
+        let bom = fs::read_to_string(directory.join(BOM)).unwrap();
+        assert_eq!(bom,csv::encode_bom(&Bom::new(&assembly,&database)));
+        assert!(manifest.get(BOM).unwrap().matches(bom.as_bytes()));
+
+        let parts = read(&directory).unwrap();
+        assert_eq!(parts.len(),3);
+        assert_eq!(parts[2].name(),"shelf");
+        assert_eq!(parts[0].attributes().len(),1);
+
+        // a changed file is caught before it's loaded
+        let path = directory.join("parts").join("0-left_leg.part");
+        let mut text = fs::read_to_string(&path).unwrap();
+        text.push_str("v 0 0 0\n");
+        fs::write(&path,text).unwrap();
+        assert!(matches!(read(&directory),Err(Error::ChecksumMismatch(p)) if p == "parts/0-left_leg.part"));
+
+        fs::remove_file(directory.join("assembly.stl")).unwrap();
+        manifest.write(&directory).unwrap();
+        fs::write(&path,String::from(assembly.instances()[0].part())).unwrap();
+        assert!(matches!(read(&directory),Err(Error::MissingFile(p)) if p == "assembly.stl"));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_manifest_string() {
+        let mut manifest = Manifest::new();
+        manifest.add("a.txt",b"abc");
+        manifest.add("b/c.txt",b"");
+        manifest.add("a.txt",b"abc");
+        assert_eq!(manifest.entries().len(),2);
+        assert_eq!(manifest.get("a.txt").unwrap().hash(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+        let text = String::from(&manifest);
+        assert_eq!(Manifest::try_from(text.as_str()).unwrap(),manifest);
+        assert!(matches!(Manifest::try_from("abc 3 a.txt"),Err(Error::InvalidLine { line: 1, .. })));
+
+        // paths that would reach outside the bundle aren't read
+        let hash = hash(b"");
+        for path in ["../a.txt","parts/../../a.txt","/etc/passwd","parts//a.txt","./a.txt","parts/"] {
+            let text = format!("{} 0 {}",hash,path);
+            assert!(matches!(Manifest::try_from(text.as_str()),Err(Error::OutsideBundle(p)) if p == path));
+        }
+    }
+
+}
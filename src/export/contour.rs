@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+
+use crate::geometry::{Polyline,Profile,Point};
+use crate::part::Part;
+use crate::errors::{Error,Context};
+use crate::constant::{Index,TOLERANCE};
+
+// faces within this of facing straight up or down are floors
+const FLAT: f64 = 1e-3;
+
+/// What a contour cuts. Contours are machined in this order, so
+/// the outline is cut last while the rest of the sheet still
+/// holds the part in place.
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq,PartialOrd,Ord)]
+pub enum Cut {
+    /// the edge of a pocket that stops above the bottom
+    Pocket,
+    /// a hole all the way through the part
+    Hole,
+    /// the outside edge of the part
+    #[default]
+    Outline,
+}
+
+/// A closed path in the XY plane of a part for a tool to follow,
+/// cutting down from the top of the part. Outlines wind
+/// counter-clockwise and holes and pockets clockwise.
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Contour {
+    pub cut: Cut,
+    /// the height of the floor of the cut in the part
+    pub z: f64,
+    /// how far below the top of the part the floor is
+    pub depth: f64,
+    pub path: Polyline,
+}
+
+impl Contour {
+
+    // Finds the contours to cut a part out of stock along its Z
+    // axis. The outline and through holes come from slicing just
+    // above the bottom, and pockets from the edges of each floor
+    // between the top and the bottom. They're ordered by cut and
+    // then depth, with each one starting near where the last one
+    // finished.
+    pub fn from_part(part: &Part) -> Result<Vec<Contour>,Error> {
+        Self::contours(part).in_part(part.name())
+    }
+
+    fn contours(part: &Part) -> Result<Vec<Contour>,Error> {
+        span!("export.contours", part = part.name());
+        let geometry = part.geometry();
+        let bounds = geometry.bounds().ok_or(Error::EmptyGeometry)?;
+        let (top,bottom) = (bounds.max.z,bounds.min.z);
+        let thickness = top - bottom;
+        let level = TOLERANCE.max(thickness * FLAT);
+
+        let mut contours = Vec::new();
+
+        // faces that face straight up or down, by their height
+        let mut floors: BTreeMap<i64,(f64,Vec<Index>)> = BTreeMap::new();
+        let vertices = geometry.vertices();
+        for (index,face) in geometry.faces().iter().enumerate().filter(|(_,f)| f.is_valid(vertices)) {
+            let triangle = face.triangle(vertices);
+            if triangle.normal().z.abs() > 1.0 - FLAT {
+                let z = triangle.centroid().z;
+                floors.entry((z / level).round() as i64)
+                    .or_insert((z,Vec::new()))
+                    .1
+                    .push(index);
+            }
+        }
+
+        let inside = floors
+            .values()
+            .filter(|(z,_)| *z > bottom + level && *z < top - level);
+
+        for (z,faces) in inside {
+            for path in geometry.boundary(faces).into_iter().filter(|l| l.is_closed() && l.len() > 2) {
+                contours.push(Contour::new(Cut::Pocket,*z,top,path));
+            }
+        }
+
+        let loops = geometry
+            .slice(bottom + level)
+            .into_iter()
+            .filter(|l| l.is_closed() && l.len() > 2)
+            .collect::<Vec<Polyline>>();
+
+        if loops.is_empty() {
+            return Err(Error::EmptyProfile);
+        }
+
+        for profile in Profile::nest(loops) {
+            contours.push(Contour::new(Cut::Outline,bottom,top,profile.outer().clone()));
+            for hole in profile.holes() {
+                contours.push(Contour::new(Cut::Hole,bottom,top,hole.clone()));
+            }
+        }
+
+        Ok(order(contours))
+    }
+
+    // A contour wound the way its cut needs
+    fn new(cut: Cut, z: f64, top: f64, mut path: Polyline) -> Self {
+        let outside = cut == Cut::Outline;
+        if (path.signed_area() > 0.0) != outside {
+            path.reverse();
+        }
+        Self { cut, z, depth: top - z, path }
+    }
+
+    // Where the tool starts and finishes the contour
+    pub fn start(&self) -> Option<Point> {
+        self.path.points().first().copied()
+    }
+
+}
+
+// Sorts contours by cut and depth, picking the closest next
+// contour within each group, starting from the origin
+fn order(mut contours: Vec<Contour>) -> Vec<Contour> {
+    contours.sort_by(|a,b| a.cut.cmp(&b.cut).then(a.depth.total_cmp(&b.depth)));
+
+    let distance = |a: Point, b: Point| (a.0 - b.0).hypot(a.1 - b.1);
+    let same = |a: &Contour, b: &Contour| a.cut == b.cut && (a.depth - b.depth).abs() < TOLERANCE;
+
+    let mut result: Vec<Contour> = Vec::with_capacity(contours.len());
+    let mut position = (0.0,0.0);
+    while !contours.is_empty() {
+        let group = contours
+            .iter()
+            .take_while(|c| same(c,&contours[0]))
+            .count();
+
+        let next = (0..group)
+            .min_by(|a,b| {
+                let a = contours[*a].start().map(|p| distance(p,position)).unwrap_or(f64::MAX);
+                let b = contours[*b].start().map(|p| distance(p,position)).unwrap_or(f64::MAX);
+                a.total_cmp(&b)
+            })
+            .unwrap_or(0);
+
+        let contour = contours.remove(next);
+        position = contour.start().unwrap_or(position);
+        result.push(contour);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::geometry::{Geometry,ParseMode};
+
+    // A 4 x 3 x 1 plate with a 1 x 1 pocket half way down
+    // and a 0.5 x 1 hole through it
+    const PLATE: &str = "
+        v 0 0 0\nv 4 0 0\nv 4 3 0\nv 0 3 0
+        v 0 0 1\nv 4 0 1\nv 4 3 1\nv 0 3 1
+        v 1 1 1\nv 2 1 1\nv 2 2 1\nv 1 2 1
+        v 1 1 0.5\nv 2 1 0.5\nv 2 2 0.5\nv 1 2 0.5
+        v 3 1 0\nv 3.5 1 0\nv 3.5 2 0\nv 3 2 0
+        v 3 1 1\nv 3.5 1 1\nv 3.5 2 1\nv 3 2 1
+    ";
+
+    fn plate() -> Part {
+        // the top is left open, which slicing doesn't need
+        let faces = "
+            f 1 3 2\nf 1 4 3
+            f 1 2 6\nf 1 6 5\nf 2 3 7\nf 2 7 6
+            f 3 4 8\nf 3 8 7\nf 4 1 5\nf 4 5 8
+            f 13 14 15\nf 13 15 16
+            f 9 13 14\nf 9 14 10\nf 10 14 15\nf 10 15 11
+            f 11 15 16\nf 11 16 12\nf 12 16 13\nf 12 13 9
+            f 17 21 22\nf 17 22 18\nf 18 22 23\nf 18 23 19
+            f 19 23 24\nf 19 24 20\nf 20 24 21\nf 20 21 17
+        ";
+        let geometry = Geometry::parse(&format!("{}{}",PLATE,faces),ParseMode::Lenient).unwrap();
+        Part::new("plate").with_geometry(geometry).build().unwrap()
+    }
+
+    #[test]
+    fn test_contour_from_part() {
+        let contours = Contour::from_part(&plate()).unwrap();
+        let cuts = contours.iter().map(|c| c.cut).collect::<Vec<Cut>>();
+        assert_eq!(cuts,vec![Cut::Pocket,Cut::Hole,Cut::Outline]);
+
+        let pocket = &contours[0];
+        assert_relative_eq!(pocket.depth,0.5);
+        assert_relative_eq!(pocket.path.signed_area(),-1.0);
+
+        assert_relative_eq!(contours[1].depth,1.0);
+        assert_relative_eq!(contours[1].path.signed_area(),-0.5);
+
+        let outline = &contours[2];
+        assert_relative_eq!(outline.z,0.0);
+        assert_relative_eq!(outline.path.signed_area(),12.0);
+
+        let error = Contour::from_part(&Part::new("empty")).unwrap_err();
+        assert!(matches!(error.root(),Error::EmptyGeometry));
+    }
+
+}
@@ -0,0 +1,109 @@
+use std::path::Path;
+
+use crate::analysis::{Bom,CutList};
+use crate::export::table::{Table,Cell};
+use crate::errors::Error;
+
+// the decimal places numbers are rounded to, which is a micrometer
+// for sizes and well under a cent for prices
+const PLACES: i32 = 6;
+
+// Writes a bill of materials with a row for each line, grouped by
+// material with subtotals and a total at the end
+pub fn encode_bom(bom: &Bom) -> String {
+    span!("export.csv.bom", lines = bom.len());
+    encode(&Table::bom(bom))
+}
+
+// Writes a cut list with a row for each piece and the offcut of
+// each board, in meters
+pub fn encode_cuts(list: &CutList) -> String {
+    span!("export.csv.cuts", boards = list.boards().len());
+    encode(&Table::cuts(list))
+}
+
+pub fn write_bom<P: AsRef<Path>>(path: P, bom: &Bom) -> Result<(),Error> {
+    std::fs::write(path,encode_bom(bom))?;
+    Ok(())
+}
+
+pub fn write_cuts<P: AsRef<Path>>(path: P, list: &CutList) -> Result<(),Error> {
+    std::fs::write(path,encode_cuts(list))?;
+    Ok(())
+}
+
+fn encode(table: &Table) -> String {
+    let mut result = table.header.join(",");
+    result.push('\n');
+    for row in table.rows.iter() {
+        let cells = row.cells
+            .iter()
+            .map(cell)
+            .collect::<Vec<String>>();
+        result.push_str(&cells.join(","));
+        result.push('\n');
+    }
+    result
+}
+
+// Quotes text with commas, quotes or line breaks in it
+fn cell(cell: &Cell) -> String {
+    match cell {
+        Cell::Empty => String::new(),
+        Cell::Number(v) => {
+            // adding zero turns -0 from an empty sum into 0
+            let scale = 10f64.powi(PLACES);
+            ((v * scale).round() / scale + 0.0).to_string()
+        },
+        Cell::Text(t) if t.contains([',','"','\n','\r']) => format!("\"{}\"",t.replace('"',"\"\"")),
+        Cell::Text(t) => t.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::assembly::Assembly;
+    use crate::geometry::Matrix;
+    use crate::materials::Database;
+    use crate::models::Lumber;
+
+    #[test]
+    fn test_csv_encode() {
+        let mut stud = Lumber::TwoByFour.part();
+        stud.metadata_mut().set_material(Some("2x4".into()));
+        let mut short = stud.clone();
+        short.set("Length",-1.4384).unwrap();
+
+        let assembly = Assembly::new("wall")
+            .with_part(stud.clone(),Matrix::identity())
+            .with_part(stud,Matrix::identity())
+            .with_part(short.clone(),Matrix::identity())
+            .with_part(crate::part::Part::new("shelf, \"upper\""),Matrix::identity());
+
+        let database = Database::builtin();
+        let bom = encode_bom(&Bom::new(&assembly,&database));
+        let lines = bom.lines().collect::<Vec<&str>>();
+        assert_eq!(lines[0],"material,part,sku,quantity,length,width,thickness,mass,price,total mass,total price");
+        assert!(lines[1].starts_with("2x4,2x4,,1,1,0.0889,0.0381,"));
+        assert!(lines[2].starts_with("2x4,2x4,,2,2.4384,"));
+        assert!(lines[3].starts_with("2x4,subtotal,,3,,,,,,"));
+        assert_eq!(lines[4],",\"shelf, \"\"upper\"\"\",,1,0,0,0,,,,");
+        assert_eq!(lines[5],",subtotal,,1,,,,,,0,0");
+        assert!(lines[6].starts_with(",total,,4,"));
+
+        let cuts = encode_cuts(&CutList::new(&assembly,&database,0.0));
+        assert_eq!(cuts.lines().collect::<Vec<&str>>(),vec![
+            "material,board,stock,part,length,waste",
+            "2x4,1,2.4384,2x4,2.4384,",
+            "2x4,1,2.4384,offcut,,0",
+            "2x4,2,2.4384,2x4,2.4384,",
+            "2x4,2,2.4384,offcut,,0",
+            "2x4,3,2.4384,2x4,1,",
+            "2x4,3,2.4384,offcut,,1.4384",
+            ",total,3,,5.8768,1.4384",
+        ]);
+    }
+
+}
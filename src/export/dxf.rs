@@ -0,0 +1,158 @@
+use std::path::Path;
+
+use crate::nesting::Layout;
+use crate::export::{Contour,Cut};
+use crate::geometry::Polyline;
+use crate::errors::Error;
+
+// space between sheets in millimeters
+const GAP: f64 = 100.0;
+
+// Draws a layout as an ASCII DXF in millimeters, with the sheets
+// stacked along Y. Sheet edges are on the `SHEET` layer, part
+// outlines on `OUTLINE` and holes on `HOLE`, all as closed
+// polylines so that older readers can open it too.
+pub fn encode(layout: &Layout) -> String {
+    span!("export.dxf", sheets = layout.sheets());
+    let mm = |v: f64| v * 1000.0;
+    let (length,width) = (mm(layout.sheet().length()),mm(layout.sheet().width()));
+
+    let mut result = start();
+
+    for sheet in 0..layout.sheets() {
+        let offset = sheet as f64 * (width + GAP);
+        let edge = Polyline::closed(vec![(0.0,0.0),(length,0.0),(length,width),(0.0,width)]);
+        polyline(&mut result,"SHEET",&edge.map(|(x,y)| (x,y + offset)),0.0);
+
+        for placement in layout.on(sheet) {
+            let point = |(x,y): (f64,f64)| (mm(x),mm(y) + offset);
+            polyline(&mut result,"OUTLINE",&placement.profile.outer().map(point),0.0);
+            for hole in placement.profile.holes() {
+                polyline(&mut result,"HOLE",&hole.map(point),0.0);
+            }
+        }
+    }
+
+    finish(result)
+}
+
+pub fn write<P: AsRef<Path>>(path: P, layout: &Layout) -> Result<(),Error> {
+    std::fs::write(path,encode(layout))?;
+    Ok(())
+}
+
+// Writes contours as an ASCII DXF in millimeters for CAM, in the
+// order they should be cut. The top of the part is at zero and each
+// polyline sits at the depth of its floor below it, on a `POCKET`,
+// `HOLE` or `OUTLINE` layer.
+pub fn encode_contours(contours: &[Contour]) -> String {
+    span!("export.dxf.contours", contours = contours.len());
+    let mut result = start();
+    for contour in contours.iter() {
+        let layer = match contour.cut {
+            Cut::Pocket => "POCKET",
+            Cut::Hole => "HOLE",
+            Cut::Outline => "OUTLINE",
+        };
+        let path = contour.path.map(|(x,y)| (x * 1000.0,y * 1000.0));
+        polyline(&mut result,layer,&path,-contour.depth * 1000.0);
+    }
+    finish(result)
+}
+
+pub fn write_contours<P: AsRef<Path>>(path: P, contours: &[Contour]) -> Result<(),Error> {
+    std::fs::write(path,encode_contours(contours))?;
+    Ok(())
+}
+
+// The header, in millimeters, up to the start of the entities
+fn start() -> String {
+    let mut result = String::new();
+    group(&mut result,0,"SECTION");
+    group(&mut result,2,"HEADER");
+    group(&mut result,9,"$INSUNITS");
+    group(&mut result,70,"4");
+    group(&mut result,0,"ENDSEC");
+    group(&mut result,0,"SECTION");
+    group(&mut result,2,"ENTITIES");
+    result
+}
+
+// Ends the entities and the file
+fn finish(mut result: String) -> String {
+    group(&mut result,0,"ENDSEC");
+    group(&mut result,0,"EOF");
+    result
+}
+
+// Adds a group code and its value
+fn group(result: &mut String, code: u32, value: &str) {
+    result.push_str(&format!("{code}\n{value}\n"));
+}
+
+// Adds a closed polyline with its vertices on a layer, at a height
+fn polyline(result: &mut String, layer: &str, line: &Polyline, z: f64) {
+    group(result,0,"POLYLINE");
+    group(result,8,layer);
+    group(result,66,"1");
+    group(result,10,"0.0");
+    group(result,20,"0.0");
+    group(result,30,&format!("{z:.3}"));
+    group(result,70,"1");
+    for (x,y) in line.points() {
+        group(result,0,"VERTEX");
+        group(result,8,layer);
+        group(result,10,&format!("{x:.3}"));
+        group(result,20,&format!("{y:.3}"));
+        group(result,30,&format!("{z:.3}"));
+    }
+    group(result,0,"SEQEND");
+    group(result,8,layer);
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::nesting::{Nesting,Sheet};
+    use crate::part::Part;
+    use crate::models;
+
+    #[test]
+    fn test_dxf_encode() {
+        let parts = [Part::new("stud").with_geometry(models::M2X4.geometry()).build().unwrap()];
+        let layout = Nesting::new(Sheet::default()).layout(&parts).unwrap();
+        let text = encode(&layout);
+
+        assert!(text.ends_with("0\nEOF\n"));
+        assert_eq!(text.matches("POLYLINE").count(),2);
+        assert_eq!(text.matches("\nOUTLINE\n").count(),6);
+        assert!(text.contains("\n0\nVERTEX\n8\nSHEET\n10\n2438.400\n"));
+    }
+
+    #[test]
+    fn test_dxf_encode_contours() {
+        let contours = [
+            Contour {
+                cut: Cut::Pocket,
+                z: 0.01,
+                depth: 0.008,
+                path: Polyline::closed(vec![(0.01,0.01),(0.01,0.02),(0.02,0.02)]),
+            },
+            Contour {
+                cut: Cut::Outline,
+                z: 0.0,
+                depth: 0.018,
+                path: Polyline::closed(vec![(0.0,0.0),(0.1,0.0),(0.1,0.1),(0.0,0.1)]),
+            },
+        ];
+        let text = encode_contours(&contours);
+        let pocket = text.find("POCKET").unwrap();
+        let outline = text.find("OUTLINE").unwrap();
+
+        assert!(pocket < outline);
+        assert!(text.contains("8\nPOCKET\n10\n10.000\n20\n20.000\n30\n-8.000\n"));
+        assert!(text.contains("8\nOUTLINE\n10\n100.000\n20\n0.000\n30\n-18.000\n"));
+    }
+
+}
@@ -0,0 +1,334 @@
+use std::path::Path;
+
+use crate::export::{Mesh,Colors};
+use crate::geometry::{Bounds,Vertex,Vector,Direction};
+use crate::errors::Error;
+
+const MAGIC: u32 = 0x4654_6C67;
+const VERSION: u32 = 2;
+const JSON: u32 = 0x4E4F_534A;
+const BIN: u32 = 0x004E_4942;
+
+// accessor component types and buffer view targets
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// The JSON arrays of a glTF file, built up one mesh at a time
+/// along with the binary buffer they point into.
+#[derive(Default)]
+struct Document {
+    buffer: Vec<u8>,
+    views: Vec<String>,
+    accessors: Vec<String>,
+    materials: Vec<String>,
+    meshes: Vec<String>,
+    nodes: Vec<String>,
+}
+
+impl Document {
+
+    // Adds a buffer view and an accessor over it, returning
+    // the accessor index.
+    fn accessor(&mut self, data: &[u8], target: u32, kind: &str, component: u32, count: usize, bounds: Option<Bounds>) -> usize {
+        while !self.buffer.len().is_multiple_of(4) {
+            self.buffer.push(0);
+        }
+
+        self.views.push(format!(
+            r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}"#,
+            self.buffer.len(),data.len(),target));
+        self.buffer.extend_from_slice(data);
+
+        let bounds = match bounds {
+            Some(b) => format!(
+                r#","min":[{},{},{}],"max":[{},{},{}]"#,
+                b.min.x as f32,b.min.y as f32,b.min.z as f32,
+                b.max.x as f32,b.max.y as f32,b.max.z as f32),
+            None => String::new(),
+        };
+
+        self.accessors.push(format!(
+            r#"{{"bufferView":{},"componentType":{},"count":{},"type":"{}"{}}}"#,
+            self.views.len() - 1,component,count,kind,bounds));
+        self.accessors.len() - 1
+    }
+
+    fn material(&mut self, color: [f64;4], blend: bool) -> usize {
+        let [r,g,b,a] = color;
+        let mode = if blend { r#","alphaMode":"BLEND""# } else { "" };
+        self.materials.push(format!(
+            r#"{{"pbrMetallicRoughness":{{"baseColorFactor":[{},{},{},{}],"metallicFactor":0}}{}}}"#,
+            r,g,b,a,mode));
+        self.materials.len() - 1
+    }
+
+    // Texture coordinates with U running along the grain, so wood
+    // textures line up with it. Empty if there's no grain.
+    fn texcoords(&mut self, points: &[Vertex], grain: Option<Direction>) -> String {
+        let Some(grain) = grain else {
+            return String::new();
+        };
+
+        // any axis that isn't close to the grain gives a V direction
+        let axis = match grain.x.abs() < 0.9 {
+            true => Vector::new(1.0,0.0,0.0),
+            false => Vector::new(0.0,1.0,0.0),
+        };
+        let across = grain.cross(&axis).normalize();
+
+        let texcoord = self.accessor(
+            &floats(points.iter().flat_map(|p| [p.dot(&grain),p.dot(&across)])),
+            ARRAY_BUFFER,"VEC2",FLOAT,points.len(),None);
+        format!(r#","TEXCOORD_0":{}"#,texcoord)
+    }
+
+    fn add(&mut self, mesh: &Mesh) {
+        let geometry = mesh.geometry();
+        let vertices = geometry.vertices();
+        let faces = geometry
+            .faces()
+            .iter()
+            .enumerate()
+            .filter(|(_,f)| f.is_valid(vertices))
+            .collect::<Vec<_>>();
+
+        let primitive = match mesh.colors() {
+            // every corner gets its own vertex so that the color
+            // doesn't bleed into neighbouring faces
+            Colors::Faces(_) => {
+                let points = faces
+                    .iter()
+                    .flat_map(|(_,f)| [vertices[f.a],vertices[f.b],vertices[f.c]])
+                    .collect::<Vec<_>>();
+                let colors = faces
+                    .iter()
+                    .flat_map(|(i,_)| [mesh.color(*i).unwrap_or_default().linear();3])
+                    .collect::<Vec<_>>();
+
+                let position = self.accessor(
+                    &floats(points.iter().flat_map(|v| [v.x,v.y,v.z])),
+                    ARRAY_BUFFER,"VEC3",FLOAT,points.len(),Bounds::from_points(&points));
+                let color = self.accessor(
+                    &floats(colors.iter().flatten().copied()),
+                    ARRAY_BUFFER,"VEC4",FLOAT,colors.len(),None);
+                let texcoords = self.texcoords(&points,mesh.grain());
+                let blend = faces.iter().any(|(i,_)| mesh.color(*i).is_some_and(|c| !c.is_opaque()));
+                let material = self.material([1.0;4],blend);
+
+                format!(r#"{{"attributes":{{"POSITION":{},"COLOR_0":{}{}}},"material":{}}}"#,position,color,texcoords,material)
+            },
+            colors => {
+                let position = self.accessor(
+                    &floats(vertices.iter().flat_map(|v| [v.x,v.y,v.z])),
+                    ARRAY_BUFFER,"VEC3",FLOAT,vertices.len(),Bounds::from_points(vertices));
+                let indices = faces
+                    .iter()
+                    .flat_map(|(_,f)| [f.a,f.b,f.c])
+                    .flat_map(|i| (i as u32).to_le_bytes())
+                    .collect::<Vec<u8>>();
+                let indices = self.accessor(
+                    &indices,ELEMENT_ARRAY_BUFFER,"SCALAR",UNSIGNED_INT,faces.len() * 3,None);
+                let texcoords = self.texcoords(vertices,mesh.grain());
+
+                match colors {
+                    Colors::Vertices(_) => {
+                        let colors = (0..vertices.len())
+                            .map(|i| mesh.vertex_color(i).unwrap_or_default().linear())
+                            .collect::<Vec<_>>();
+                        let color = self.accessor(
+                            &floats(colors.iter().flatten().copied()),
+                            ARRAY_BUFFER,"VEC4",FLOAT,colors.len(),None);
+                        format!(r#"{{"attributes":{{"POSITION":{},"COLOR_0":{}{}}},"indices":{}}}"#,position,color,texcoords,indices)
+                    },
+                    Colors::Part(color) => {
+                        let material = self.material(color.linear(),!color.is_opaque());
+                        format!(r#"{{"attributes":{{"POSITION":{}{}}},"indices":{},"material":{}}}"#,position,texcoords,indices,material)
+                    },
+                    _ => format!(r#"{{"attributes":{{"POSITION":{}{}}},"indices":{}}}"#,position,texcoords,indices),
+                }
+            },
+        };
+
+        let name = escape(mesh.name());
+        self.meshes.push(format!(r#"{{"name":"{}","primitives":[{}]}}"#,name,primitive));
+        self.nodes.push(format!(r#"{{"name":"{}","mesh":{}}}"#,name,self.meshes.len() - 1));
+    }
+
+    fn json(&self) -> String {
+        let list = |items: &[String]| items.join(",");
+        let scene = (0..self.nodes.len()).map(|i| i.to_string()).collect::<Vec<_>>();
+        let mut json = format!(
+            r#"{{"asset":{{"version":"2.0","generator":"construct"}},"scene":0,"scenes":[{{"nodes":[{}]}}],"nodes":[{}],"meshes":[{}]"#,
+            scene.join(","),list(&self.nodes),list(&self.meshes));
+
+        if !self.materials.is_empty() {
+            json.push_str(&format!(r#","materials":[{}]"#,list(&self.materials)));
+        }
+        if !self.buffer.is_empty() {
+            json.push_str(&format!(
+                r#","buffers":[{{"byteLength":{}}}],"bufferViews":[{}],"accessors":[{}]"#,
+                self.buffer.len(),list(&self.views),list(&self.accessors)));
+        }
+        json.push('}');
+        json
+    }
+
+}
+
+// Writes every mesh as a node of one binary glTF (`.glb`) file. Part
+// colors become materials, and face and vertex colors become vertex
+// colors.
+pub fn encode(meshes: &[Mesh]) -> Vec<u8> {
+    span!("export.gltf", meshes = meshes.len());
+    let mut document = Document::default();
+    for mesh in meshes.iter() {
+        document.add(mesh);
+    }
+
+    let mut json = document.json().into_bytes();
+    while !json.len().is_multiple_of(4) {
+        json.push(b' ');
+    }
+
+    let mut buffer = document.buffer;
+    while !buffer.len().is_multiple_of(4) {
+        buffer.push(0);
+    }
+
+    let mut length = 12 + 8 + json.len();
+    if !buffer.is_empty() {
+        length += 8 + buffer.len();
+    }
+
+    let mut result = Vec::with_capacity(length);
+    for value in [MAGIC,VERSION,length as u32,json.len() as u32,JSON] {
+        result.extend_from_slice(&value.to_le_bytes());
+    }
+    result.extend_from_slice(&json);
+
+    if !buffer.is_empty() {
+        result.extend_from_slice(&(buffer.len() as u32).to_le_bytes());
+        result.extend_from_slice(&BIN.to_le_bytes());
+        result.extend_from_slice(&buffer);
+    }
+
+    result
+}
+
+pub fn write<P: AsRef<Path>>(path: P, meshes: &[Mesh]) -> Result<(),Error> {
+    std::fs::write(path,encode(meshes))?;
+    Ok(())
+}
+
+fn floats<I: Iterator<Item = f64>>(values: I) -> Vec<u8> {
+    values.flat_map(|v| (v as f32).to_le_bytes()).collect()
+}
+
+// Makes a string safe to put between quotes in JSON
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}",c as u32)),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::models;
+    use crate::part::Color;
+
+    // The JSON chunk and the length of the binary chunk
+    fn chunks(data: &[u8]) -> (String,usize) {
+        let word = |i: usize| u32::from_le_bytes(data[i..i + 4].try_into().unwrap()) as usize;
+        assert_eq!(word(0),MAGIC as usize);
+        assert_eq!(word(8),data.len());
+
+        let length = word(12);
+        let json = String::from_utf8(data[20..20 + length].to_vec()).unwrap();
+        let binary = match data.len() > 20 + length {
+            true => word(20 + length),
+            false => 0,
+        };
+        (json,binary)
+    }
+
+    #[test]
+    fn test_gltf_encode() {
+        let meshes = [
+            Mesh::new("plain \"stud\"",models::M2X4.geometry()),
+            Mesh::new("red",models::M2X4.geometry()).with_color(Color::new(255,0,0).with_alpha(128)),
+        ];
+
+        let data = encode(&meshes);
+        assert_eq!(data.len() % 4,0);
+
+        let (json,binary) = chunks(&data);
+        assert!(json.contains(r#""name":"plain \"stud\"""#));
+        assert!(json.contains(r#""baseColorFactor":[1,0,0,"#));
+        assert!(json.contains(r#""alphaMode":"BLEND""#));
+
+        // positions and indices for both meshes
+        assert_eq!(binary,2 * (8 * 12 + 36 * 4));
+    }
+
+    #[test]
+    fn test_gltf_encode_face_colors() {
+        let colors = (0..12).map(|i| Color::new(i * 20,0,0)).collect();
+        let mesh = Mesh::new("stud",models::M2X4.geometry())
+            .with_face_colors(colors)
+            .unwrap();
+
+        let (json,binary) = chunks(&encode(&[mesh]));
+        assert!(json.contains(r#""COLOR_0":1"#));
+        assert!(!json.contains("indices"));
+        assert_eq!(binary,36 * 12 + 36 * 16);
+    }
+
+    #[test]
+    fn test_gltf_encode_vertex_colors() {
+        let colors = (0..8).map(|i| Color::ramp(i as f64 / 7.0)).collect();
+        let mesh = Mesh::new("stud",models::M2X4.geometry())
+            .with_vertex_colors(colors)
+            .unwrap();
+
+        let (json,binary) = chunks(&encode(&[mesh]));
+        assert!(json.contains(r#""COLOR_0":2"#));
+        assert!(json.contains(r#""indices":1"#));
+        assert_eq!(binary,8 * 12 + 36 * 4 + 8 * 16);
+    }
+
+    #[test]
+    fn test_gltf_encode_grain() {
+        use crate::geometry::Direction;
+
+        let mesh = Mesh::new("stud",models::M2X4.geometry())
+            .with_grain(Direction::new(0.0,0.0,2.0));
+
+        let (json,binary) = chunks(&encode(&[mesh]));
+        assert!(json.contains(r#""TEXCOORD_0":2"#));
+        assert_eq!(binary,8 * 12 + 36 * 4 + 8 * 8);
+    }
+
+    #[test]
+    fn test_gltf_encode_empty() {
+        let (json,binary) = chunks(&encode(&[]));
+        assert!(!json.contains("buffers"));
+        assert_eq!(binary,0);
+    }
+
+    #[test]
+    fn test_gltf_escape() {
+        assert_eq!(escape("a\"b\\c\n"),"a\\\"b\\\\c\\u000a");
+    }
+
+}
@@ -0,0 +1,115 @@
+use std::path::Path;
+
+use crate::assembly::Assembly;
+use crate::errors::Error;
+
+// Writes the instances of an assembly and the mates between them as
+// an undirected Graphviz graph. Nodes are numbered by instance and
+// labelled with its name and part, and each edge is labelled with
+// the connection it joins at each end.
+pub fn encode_dot(assembly: &Assembly) -> String {
+    span!("export.dot", instances = assembly.instances().len());
+    let quote = |text: &str| format!("\"{}\"",text.replace('\\',"\\\\").replace('"',"\\\""));
+
+    let mut result = format!("graph {} {{\n",quote(assembly.name()));
+    for (index,instance) in assembly.instances().iter().enumerate() {
+        let label = format!("{}\n{}",instance.name(),instance.part().name());
+        result.push_str(&format!("    {} [label={}];\n",index,quote(&label).replace('\n',"\\n")));
+    }
+    for mate in assembly.mates().iter() {
+        result.push_str(&format!("    {} -- {} [taillabel=\"{}\",headlabel=\"{}\"];\n",
+            mate.first.0,mate.second.0,mate.first.1,mate.second.1));
+    }
+    result.push_str("}\n");
+    result
+}
+
+// Writes the same graph as GraphML, with the names of the instance
+// and part on each node and the connections on each edge as data
+pub fn encode_graphml(assembly: &Assembly) -> String {
+    span!("export.graphml", instances = assembly.instances().len());
+    let mut result = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+        "  <key id=\"name\" for=\"node\" attr.name=\"name\" attr.type=\"string\"/>\n",
+        "  <key id=\"part\" for=\"node\" attr.name=\"part\" attr.type=\"string\"/>\n",
+        "  <key id=\"source\" for=\"edge\" attr.name=\"source connection\" attr.type=\"int\"/>\n",
+        "  <key id=\"target\" for=\"edge\" attr.name=\"target connection\" attr.type=\"int\"/>\n",
+    ));
+
+    result.push_str(&format!("  <graph id=\"{}\" edgedefault=\"undirected\">\n",escape(assembly.name())));
+    for (index,instance) in assembly.instances().iter().enumerate() {
+        result.push_str(&format!(concat!(
+            "    <node id=\"n{}\">\n",
+            "      <data key=\"name\">{}</data>\n",
+            "      <data key=\"part\">{}</data>\n",
+            "    </node>\n"),
+            index,escape(instance.name()),escape(instance.part().name())));
+    }
+    for (index,mate) in assembly.mates().iter().enumerate() {
+        result.push_str(&format!(concat!(
+            "    <edge id=\"e{}\" source=\"n{}\" target=\"n{}\">\n",
+            "      <data key=\"source\">{}</data>\n",
+            "      <data key=\"target\">{}</data>\n",
+            "    </edge>\n"),
+            index,mate.first.0,mate.second.0,mate.first.1,mate.second.1));
+    }
+    result.push_str("  </graph>\n</graphml>\n");
+    result
+}
+
+pub fn write_dot<P: AsRef<Path>>(path: P, assembly: &Assembly) -> Result<(),Error> {
+    std::fs::write(path,encode_dot(assembly))?;
+    Ok(())
+}
+
+pub fn write_graphml<P: AsRef<Path>>(path: P, assembly: &Assembly) -> Result<(),Error> {
+    std::fs::write(path,encode_graphml(assembly))?;
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&',"&amp;")
+        .replace('<',"&lt;")
+        .replace('>',"&gt;")
+        .replace('"',"&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::assembly::Instance;
+    use crate::geometry::{Matrix,Vertex};
+    use crate::part::{Part,Connection};
+
+    fn assembly() -> Assembly {
+        let part = Part::new("2x4")
+            .with_connection(Connection::new(Vertex::new(0.0,0.0,0.0),0.01))
+            .with_connection(Connection::new(Vertex::new(1.0,0.0,0.0),0.01));
+        let mut assembly = Assembly::new("frame \"A\"")
+            .with_instance(Instance::new(part.clone(),Matrix::identity()).with_name("top & bottom"))
+            .with_instance(Instance::new(part,Matrix::identity()).with_name("side"));
+        assembly.mate((0,1),(1,0)).unwrap();
+        assembly
+    }
+
+    #[test]
+    fn test_graph_encode() {
+        let dot = encode_dot(&assembly());
+        assert_eq!(dot,concat!(
+            "graph \"frame \\\"A\\\"\" {\n",
+            "    0 [label=\"top & bottom\\n2x4\"];\n",
+            "    1 [label=\"side\\n2x4\"];\n",
+            "    0 -- 1 [taillabel=\"1\",headlabel=\"0\"];\n",
+            "}\n",
+        ));
+
+        let xml = encode_graphml(&assembly());
+        assert!(xml.contains("<graph id=\"frame &quot;A&quot;\" edgedefault=\"undirected\">"));
+        assert!(xml.contains("<data key=\"name\">top &amp; bottom</data>"));
+        assert!(xml.contains("<edge id=\"e0\" source=\"n0\" target=\"n1\">"));
+        assert_eq!(xml.matches("<node ").count(),2);
+    }
+
+}
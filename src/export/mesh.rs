@@ -0,0 +1,245 @@
+use crate::geometry::{Geometry,Direction};
+use crate::part::{Part,Color,Filter};
+use crate::assembly::{Assembly,LevelOfDetail,Detail};
+use crate::errors::Error;
+
+/// The colors to draw a mesh in
+#[derive(Default,Debug,Clone,PartialEq)]
+pub enum Colors {
+    /// left to the viewer
+    #[default]
+    None,
+    /// one color for the whole mesh
+    Part(Color),
+    /// one color for each face
+    Faces(Vec<Color>),
+    /// one color for each vertex, blended across faces
+    Vertices(Vec<Color>),
+}
+
+/// A named geometry to export, along with its colors
+#[derive(Default,Debug,Clone)]
+pub struct Mesh {
+    name: String,
+    geometry: Geometry,
+    colors: Colors,
+    grain: Option<Direction>,
+}
+
+impl Mesh {
+
+    pub fn new<T: Into<String>>(name: T, geometry: Geometry) -> Self {
+        Self {
+            name: name.into(),
+            geometry,
+            colors: Colors::None,
+            grain: None,
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.colors = Colors::Part(color);
+        self
+    }
+
+    // The direction wood textures should run in
+    pub fn with_grain(mut self, grain: Direction) -> Self {
+        self.grain = Some(grain.normalize());
+        self
+    }
+
+    // Fails unless there is exactly one color for each face
+    pub fn with_face_colors(mut self, colors: Vec<Color>) -> Result<Self,Error> {
+        if colors.len() != self.geometry.size() {
+            return Err(Error::ColorCount {
+                expected: self.geometry.size(),
+                found: colors.len(),
+            });
+        }
+        self.colors = Colors::Faces(colors);
+        Ok(self)
+    }
+
+    // Fails unless there is exactly one color for each vertex
+    pub fn with_vertex_colors(mut self, colors: Vec<Color>) -> Result<Self,Error> {
+        if colors.len() != self.geometry.vertices().len() {
+            return Err(Error::ColorCount {
+                expected: self.geometry.vertices().len(),
+                found: colors.len(),
+            });
+        }
+        self.colors = Colors::Vertices(colors);
+        Ok(self)
+    }
+
+    // Colors each vertex by its value in a channel, from blue at
+    // `low` through green to red at `high`, like a heatmap of how
+    // far a scan deviates from its design
+    pub fn with_channel_colors(self, name: &str, low: f64, high: f64) -> Result<Self,Error> {
+        let values = self.geometry
+            .channel(name)
+            .ok_or_else(|| Error::UnknownChannel(name.into()))?;
+        let colors = values
+            .iter()
+            .map(|v| Color::ramp((v - low) / (high - low)))
+            .collect();
+        self.with_vertex_colors(colors)
+    }
+
+    // The evaluated geometry of a part, in the part's color
+    pub fn from_part(part: &Part) -> Self {
+        let mut mesh = Self::new(part.name(),part.geometry().clone());
+        if let Some(color) = part.metadata().color() {
+            mesh = mesh.with_color(color);
+        }
+        mesh.grain = part.metadata().grain();
+        mesh
+    }
+
+    // One mesh for each instance, moved into assembly space
+    pub fn from_assembly(assembly: &Assembly) -> Vec<Self> {
+        Self::from_assembly_filtered(assembly,&Filter::All)
+    }
+
+    // Like `from_assembly`, skipping parts that don't match the
+    // filter, so that only some layers or tags are exported.
+    pub fn from_assembly_filtered(assembly: &Assembly, filter: &Filter) -> Vec<Self> {
+        Self::from_assembly_with(assembly,filter,&LevelOfDetail::default())
+    }
+
+    // Like `from_assembly_filtered`, with small parts swapped for
+    // simpler stand-ins and hidden parts left out
+    pub fn from_assembly_with(assembly: &Assembly, filter: &Filter, detail: &LevelOfDetail) -> Vec<Self> {
+        assembly
+            .matching(filter)
+            .filter(|i| detail.detail_of(i) != Detail::Hidden)
+            .map(|i| {
+                let mut geometry = Geometry::default();
+                geometry.append(&detail.geometry(i),i.transform());
+                let mut mesh = Self::new(i.name(),geometry);
+                if let Some(color) = i.part().metadata().color() {
+                    mesh = mesh.with_color(color);
+                }
+                mesh.grain = i.grain();
+                mesh
+            })
+            .collect()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn geometry(&self) -> &Geometry {
+        &self.geometry
+    }
+
+    pub fn colors(&self) -> &Colors {
+        &self.colors
+    }
+
+    pub fn grain(&self) -> Option<Direction> {
+        self.grain
+    }
+
+    // The color of one face, if there is one
+    pub fn color(&self, face: usize) -> Option<Color> {
+        match &self.colors {
+            Colors::None | Colors::Vertices(_) => None,
+            Colors::Part(color) => Some(*color),
+            Colors::Faces(colors) => colors.get(face).copied(),
+        }
+    }
+
+    // The color of one vertex, if vertices are colored
+    pub fn vertex_color(&self, vertex: usize) -> Option<Color> {
+        match &self.colors {
+            Colors::Vertices(colors) => colors.get(vertex).copied(),
+            _ => None,
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::models;
+    use crate::geometry::Matrix;
+    use crate::part::Metadata;
+
+    #[test]
+    fn test_mesh_from_assembly() {
+        let red = Part::new("red")
+            .with_geometry(models::M2X4.geometry())
+            .with_metadata(Metadata::new().with_color(Color::new(255,0,0)))
+            .build()
+            .unwrap();
+
+        let plain = Part::new("plain")
+            .with_geometry(models::M2X4.geometry())
+            .build()
+            .unwrap();
+
+        let assembly = Assembly::new("pair")
+            .with_part(red,Matrix::identity())
+            .with_part(plain,Matrix::translate(0.0,1.0,0.0));
+
+        let meshes = Mesh::from_assembly(&assembly);
+        assert_eq!(meshes.len(),2);
+        assert_eq!(meshes[0].color(3),Some(Color::new(255,0,0)));
+        assert_eq!(meshes[1].color(3),None);
+        assert_eq!(meshes[1].name(),"plain");
+        assert!(meshes[1].geometry().vertices()[0].y > 0.5);
+
+        assert_eq!(meshes[0].grain(),None);
+
+        let meshes = Mesh::from_assembly_filtered(&assembly,&Filter::Not(Box::new(Filter::All)));
+        assert!(meshes.is_empty());
+
+        // the small part as a box in place, and the other hidden
+        let detail = LevelOfDetail::new(5.0)
+            .with_detail(Detail::Bounds)
+            .with_override("red",Detail::Hidden);
+        let meshes = Mesh::from_assembly_with(&assembly,&Filter::All,&detail);
+        assert_eq!(meshes.len(),1);
+        assert_eq!(meshes[0].geometry().size(),12);
+        assert!(meshes[0].geometry().vertices()[0].y > 0.5);
+    }
+
+    #[test]
+    fn test_mesh_face_colors() {
+        let mesh = Mesh::new("stud",models::M2X4.geometry());
+        assert!(matches!(
+            mesh.clone().with_face_colors(vec![Color::default()]),
+            Err(Error::ColorCount { expected: 12, found: 1 })));
+
+        let colors = (0..12).map(|i| Color::new(i,0,0)).collect();
+        let mesh = mesh.with_face_colors(colors).unwrap();
+        assert_eq!(mesh.color(5),Some(Color::new(5,0,0)));
+        assert_eq!(mesh.color(12),None);
+    }
+
+    #[test]
+    fn test_mesh_vertex_colors() {
+        let mut geometry = models::M2X4.geometry();
+        let values = (0..8).map(|i| i as f64 / 7.0).collect();
+        geometry.set_channel("error",values).unwrap();
+
+        let mesh = Mesh::new("stud",geometry);
+        assert!(matches!(
+            mesh.clone().with_vertex_colors(vec![Color::default()]),
+            Err(Error::ColorCount { expected: 8, found: 1 })));
+        assert!(matches!(
+            mesh.clone().with_channel_colors("missing",0.0,1.0),
+            Err(Error::UnknownChannel(n)) if n == "missing"));
+
+        let mesh = mesh.with_channel_colors("error",0.0,1.0).unwrap();
+        assert_eq!(mesh.vertex_color(0),Some(Color::new(0,0,255)));
+        assert_eq!(mesh.vertex_color(7),Some(Color::new(255,0,0)));
+        assert_eq!(mesh.vertex_color(8),None);
+        assert_eq!(mesh.color(0),None);
+    }
+
+}
@@ -0,0 +1,18 @@
+mod mesh;
+mod contour;
+mod table;
+pub mod stl;
+pub mod ply;
+pub mod gltf;
+pub mod svg;
+pub mod dxf;
+pub mod bundle;
+pub mod csv;
+pub mod graph;
+#[cfg(feature = "xlsx")]
+pub mod xlsx;
+#[cfg(feature = "rapier")]
+pub mod rapier;
+
+pub use mesh::{Mesh,Colors};
+pub use contour::{Contour,Cut};
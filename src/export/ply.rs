@@ -0,0 +1,117 @@
+use std::path::Path;
+
+use crate::export::{Mesh,Colors};
+use crate::export::stl::valid;
+use crate::errors::Error;
+
+// faces and vertices without a color get this if any others have one
+const DEFAULT: [u8;4] = [180,180,180,255];
+
+// Writes every mesh into one ASCII PLY. If any mesh has part or face
+// colors then every face gets red, green, blue and alpha properties,
+// and the same for every vertex if any mesh has vertex colors. Faces
+// that refer to vertices the mesh doesn't have are left out.
+pub fn encode(meshes: &[Mesh]) -> String {
+    span!("export.ply", meshes = meshes.len());
+    let colored = meshes.iter().any(|m| matches!(m.colors(),Colors::Part(_) | Colors::Faces(_)));
+    let shaded = meshes.iter().any(|m| matches!(m.colors(),Colors::Vertices(_)));
+    let properties = "property uchar red\nproperty uchar green\nproperty uchar blue\nproperty uchar alpha\n";
+    let vertices = meshes.iter().map(|m| m.geometry().vertices().len()).sum::<usize>();
+    let faces = meshes.iter().map(|m| valid(m).count()).sum::<usize>();
+
+    let mut result = String::new();
+    result.push_str("ply\nformat ascii 1.0\ncomment construct\n");
+    result.push_str(&format!("element vertex {}\n",vertices));
+    result.push_str("property double x\nproperty double y\nproperty double z\n");
+    if shaded {
+        result.push_str(properties);
+    }
+    result.push_str(&format!("element face {}\n",faces));
+    result.push_str("property list uchar uint vertex_indices\n");
+    if colored {
+        result.push_str(properties);
+    }
+    result.push_str("end_header\n");
+
+    for mesh in meshes.iter() {
+        for (index,v) in mesh.geometry().vertices().iter().enumerate() {
+            result.push_str(&format!("{} {} {}",v.x,v.y,v.z));
+            if shaded {
+                let [r,g,b,a] = mesh.vertex_color(index).map(|c| c.rgba()).unwrap_or(DEFAULT);
+                result.push_str(&format!(" {} {} {} {}",r,g,b,a));
+            }
+            result.push('\n');
+        }
+    }
+
+    let mut offset = 0;
+    for mesh in meshes.iter() {
+        for (index,face) in valid(mesh) {
+            result.push_str(&format!("3 {} {} {}",face.a + offset,face.b + offset,face.c + offset));
+            if colored {
+                let [r,g,b,a] = mesh.color(index).map(|c| c.rgba()).unwrap_or(DEFAULT);
+                result.push_str(&format!(" {} {} {} {}",r,g,b,a));
+            }
+            result.push('\n');
+        }
+        offset += mesh.geometry().vertices().len();
+    }
+
+    result
+}
+
+pub fn write<P: AsRef<Path>>(path: P, meshes: &[Mesh]) -> Result<(),Error> {
+    std::fs::write(path,encode(meshes))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::geometry::{Geometry,Face};
+    use crate::part::Color;
+
+    fn triangle() -> Geometry {
+        geometry! { v 0 0 0; v 1 0 0; v 0 1 0; f 1 2 3; }
+    }
+
+    #[test]
+    fn test_ply_encode() {
+        let data = encode(&[Mesh::new("a",triangle())]);
+        assert!(!data.contains("property uchar red"));
+        assert!(data.ends_with("end_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n"));
+
+        let data = encode(&[
+            Mesh::new("a",triangle()),
+            Mesh::new("b",triangle()).with_face_colors(vec![Color::new(1,2,3)]).unwrap(),
+        ]);
+        assert!(data.contains("element vertex 6\n"));
+        assert!(data.contains("element face 2\n"));
+        assert!(data.contains("property uchar alpha\nend_header\n"));
+        assert!(data.ends_with("3 0 1 2 180 180 180 255\n3 3 4 5 1 2 3 255\n"));
+
+        // a face past the last vertex isn't written or counted
+        let dangling = Geometry::new(triangle().vertices().to_vec(),vec![Face::new(1,2,6),Face::new(1,2,3)]);
+        let data = encode(&[Mesh::new("a",dangling).with_face_colors(vec![Color::new(1,2,3),Color::new(4,5,6)]).unwrap()]);
+        assert!(data.contains("element face 1\n"));
+        assert!(data.ends_with("end_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2 4 5 6 255\n"));
+    }
+
+    #[test]
+    fn test_ply_vertex_colors() {
+        let data = encode(&[
+            Mesh::new("a",triangle()),
+            Mesh::new("b",triangle()).with_vertex_colors(vec![Color::new(1,2,3);3]).unwrap(),
+        ]);
+        assert!(data.contains("property double z\nproperty uchar red\n"));
+        assert!(data.contains("property list uchar uint vertex_indices\nend_header\n"));
+        assert!(data.contains("\n0 0 0 180 180 180 255\n"));
+        assert!(data.contains("\n0 1 0 1 2 3 255\n3 0 1 2\n"));
+
+        // and it reads back
+        let cloud = crate::import::PointCloud::parse_ply(data.as_bytes()).unwrap();
+        assert_eq!(cloud.len(),6);
+    }
+
+}
@@ -0,0 +1,264 @@
+use rapier3d::prelude::{ColliderBuilder,RigidBodyBuilder,Collider,RigidBody,ColliderSet,RigidBodySet,RigidBodyHandle,SharedShape,Isometry,Real};
+use rapier3d::na::{Point3,Vector3};
+
+use crate::assembly::{Assembly,Instance};
+use crate::geometry::{Geometry,Vertex,Decomposition};
+use crate::part::Filter;
+use crate::materials::Database;
+use crate::errors::{Error,Context};
+use crate::constant::Index;
+
+/// The shape of the collider made for each part
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Shape {
+    /// the surface itself, which is exact but slow for moving parts
+    #[default]
+    Trimesh,
+    /// the convex hull, which is fast but fills in any hollows
+    Hull,
+    /// convex pieces from `Geometry::decompose`, which follow
+    /// the surface more closely than the hull
+    Decomposition,
+}
+
+/// Settings for turning the instances of an assembly into rigid
+/// bodies for a rapier simulation, in meters and kilograms
+#[derive(Debug,Clone,PartialEq)]
+pub struct Physics {
+    shape: Shape,
+    decomposition: Decomposition,
+    density: f64,
+    fixed: Option<Filter>,
+}
+
+/// A rigid body and its collider for one instance. The body sits at
+/// the centroid of the instance in assembly space and the user data
+/// of both is the index of the instance.
+#[derive(Debug,Clone)]
+pub struct Body {
+    pub instance: Index,
+    pub body: RigidBody,
+    pub collider: Collider,
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Self {
+            shape: Shape::Trimesh,
+            decomposition: Decomposition::default(),
+            density: 1000.0,
+            fixed: None,
+        }
+    }
+}
+
+impl Physics {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_shape(mut self, shape: Shape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    // How parts are split up for `Shape::Decomposition`
+    pub fn with_decomposition(mut self, decomposition: Decomposition) -> Self {
+        self.decomposition = decomposition;
+        self
+    }
+
+    // The density in kg/m³ of parts without a known material,
+    // which is the density of water by default
+    pub fn with_density(mut self, density: f64) -> Self {
+        self.density = density;
+        self
+    }
+
+    // Parts matching the filter don't move, like the floor
+    pub fn with_fixed(mut self, filter: Filter) -> Self {
+        self.fixed = Some(filter);
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    // A body for every instance, with a mass from the database
+    pub fn bodies(&self, assembly: &Assembly, database: &Database) -> Result<Vec<Body>,Error> {
+        span!("export.rapier", instances = assembly.instances().len());
+        assembly
+            .instances()
+            .iter()
+            .enumerate()
+            .map(|(index,instance)| self.body(index,instance,database).in_item(index))
+            .collect()
+    }
+
+    // Adds a body for every instance to a simulation, returning the
+    // handles in the same order as the instances
+    pub fn insert(&self, assembly: &Assembly, database: &Database, bodies: &mut RigidBodySet, colliders: &mut ColliderSet) -> Result<Vec<RigidBodyHandle>,Error> {
+        Ok(self.bodies(assembly,database)?
+            .into_iter()
+            .map(|item| {
+                let handle = bodies.insert(item.body);
+                colliders.insert_with_parent(item.collider,handle,bodies);
+                handle
+            })
+            .collect())
+    }
+
+    fn body(&self, index: Index, instance: &Instance, database: &Database) -> Result<Body,Error> {
+        let part = instance.part();
+        let mut geometry = instance.geometry();
+        let center = geometry.centroid().ok_or(Error::EmptyGeometry).in_part(part.name())?;
+
+        // colliders need outward facing triangles to find a volume
+        if geometry.volume() < 0.0 {
+            geometry.flip();
+        }
+
+        let mut collider = self.collider(&geometry,center).in_part(part.name())?;
+        collider = match database.mass(part) {
+            Some(mass) => collider.mass(mass as Real),
+            None => collider.density(self.density as Real),
+        };
+
+        let fixed = self.fixed
+            .as_ref()
+            .is_some_and(|f| f.matches(part.metadata()));
+
+        let body = match fixed {
+            true => RigidBodyBuilder::fixed(),
+            false => RigidBodyBuilder::dynamic(),
+        };
+
+        Ok(Body {
+            instance: index,
+            body: body
+                .translation(Vector3::new(center.x as Real,center.y as Real,center.z as Real))
+                .user_data(index as u128)
+                .build(),
+            collider: collider
+                .user_data(index as u128)
+                .build(),
+        })
+    }
+
+    // A collider for the geometry around a point, which becomes
+    // the origin of the collider
+    fn collider(&self, geometry: &Geometry, center: Vertex) -> Result<ColliderBuilder,Error> {
+        let points = |geometry: &Geometry| geometry
+            .vertices()
+            .iter()
+            .map(|v| *v - center)
+            .map(|v| Point3::new(v.x as Real,v.y as Real,v.z as Real))
+            .collect::<Vec<Point3<Real>>>();
+
+        let indices = geometry
+            .faces()
+            .iter()
+            .filter(|f| f.is_valid(geometry.vertices()))
+            .map(|f| [f.a as u32,f.b as u32,f.c as u32])
+            .collect::<Vec<[u32;3]>>();
+
+        let hull = |points: &[Point3<Real>]| SharedShape::convex_hull(points)
+            .ok_or(Error::EncodeError("points don't have a convex hull".into()));
+
+        match self.shape {
+            Shape::Trimesh => ColliderBuilder::trimesh(points(geometry),indices)
+                .map_err(|e| Error::EncodeError(e.to_string())),
+            Shape::Hull => Ok(ColliderBuilder::new(hull(&points(geometry))?)),
+            Shape::Decomposition => Ok(ColliderBuilder::compound(geometry
+                .decompose(&self.decomposition)
+                .iter()
+                .map(|piece| Ok((Isometry::identity(),hull(&points(piece))?)))
+                .collect::<Result<Vec<_>,Error>>()?)),
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::geometry::Matrix;
+    use crate::part::{Part,Metadata};
+    use rapier3d::prelude::{PhysicsPipeline,IslandManager,DefaultBroadPhase,NarrowPhase,ImpulseJointSet,MultibodyJointSet,CCDSolver,IntegrationParameters};
+
+    // A 2 x 0.2 x 0.1 board with outward facing triangles
+    fn board(name: &str, material: &str) -> Part {
+        let geometry = Geometry::make(
+            vec![
+                -1.0,-0.1,-0.05, 1.0,-0.1,-0.05, 1.0,0.1,-0.05, -1.0,0.1,-0.05,
+                -1.0,-0.1, 0.05, 1.0,-0.1, 0.05, 1.0,0.1, 0.05, -1.0,0.1, 0.05,
+            ],
+            vec![
+                1,3,2, 1,4,3, 5,6,7, 5,7,8,
+                1,2,6, 1,6,5, 2,3,7, 2,7,6,
+                3,4,8, 3,8,7, 4,1,5, 4,5,8,
+            ]);
+        Part::new(name)
+            .with_geometry(geometry)
+            .with_metadata(Metadata::new().with_tag(name).with_material(material))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_rapier_bodies() {
+        let assembly = Assembly::new("pile")
+            .with_part(board("floor","unknown"),Matrix::scale(1.0,20.0,1.0))
+            .with_part(board("board","steel"),Matrix::translate(0.0,0.0,0.5));
+
+        let database = Database::builtin();
+        for shape in [Shape::Trimesh,Shape::Hull,Shape::Decomposition] {
+            let physics = Physics::new()
+                .with_shape(shape)
+                .with_fixed(Filter::tag("floor"));
+
+            let bodies = physics.bodies(&assembly,&database).unwrap();
+            assert!(bodies[0].body.is_fixed());
+            assert!(bodies[1].body.is_dynamic());
+            assert_eq!(bodies[1].collider.user_data,1);
+
+            let expected = database.mass(assembly.instances()[1].part()).unwrap();
+            assert_relative_eq!(bodies[1].collider.mass() as f64,expected,max_relative = 1e-3);
+        }
+
+        // dropped onto the floor, the board comes to rest on top
+        let mut set = RigidBodySet::new();
+        let mut colliders = ColliderSet::new();
+        let handles = Physics::new()
+            .with_shape(Shape::Hull)
+            .with_fixed(Filter::tag("floor"))
+            .insert(&assembly,&database,&mut set,&mut colliders)
+            .unwrap();
+
+        let mut pipeline = PhysicsPipeline::new();
+        let mut islands = IslandManager::new();
+        let mut broad = DefaultBroadPhase::new();
+        let mut narrow = NarrowPhase::new();
+        let mut impulses = ImpulseJointSet::new();
+        let mut multibody = MultibodyJointSet::new();
+        let mut ccd = CCDSolver::new();
+        for _ in 0..120 {
+            pipeline.step(&Vector3::new(0.0,0.0,-9.81),&IntegrationParameters::default(),&mut islands,&mut broad,&mut narrow,&mut set,&mut colliders,&mut impulses,&mut multibody,&mut ccd,None,&(),&());
+        }
+
+        let z = set[handles[1]].translation().z as f64;
+        assert_relative_eq!(z,0.1,epsilon = 5e-3);
+    }
+
+    #[test]
+    fn test_rapier_empty() {
+        let assembly = Assembly::new("empty").with_part(Part::new("nothing"),Matrix::identity());
+        let error = Physics::new().bodies(&assembly,&Database::builtin()).unwrap_err();
+        assert!(matches!(error,Error::InItem(0,_)));
+        assert!(matches!(error.root(),Error::EmptyGeometry));
+    }
+
+}
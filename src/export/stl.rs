@@ -0,0 +1,81 @@
+use std::path::Path;
+
+use crate::export::Mesh;
+use crate::errors::Error;
+
+// text at the start of the 80 byte header
+const HEADER: &[u8] = b"construct";
+
+// Writes every mesh into one binary STL. Faces with a color store it
+// in the attribute byte count as 15-bit RGB with the top bit set, the
+// way VisCAM and SolidView read it; other faces leave it at zero.
+pub fn encode(meshes: &[Mesh]) -> Vec<u8> {
+    span!("export.stl", meshes = meshes.len());
+    let count = meshes.iter().map(|m| valid(m).count()).sum::<usize>();
+
+    let mut result = Vec::with_capacity(84 + count * 50);
+    let mut header = [0u8;80];
+    header[..HEADER.len()].copy_from_slice(HEADER);
+    result.extend_from_slice(&header);
+    result.extend_from_slice(&(count as u32).to_le_bytes());
+
+    for mesh in meshes.iter() {
+        let vertices = mesh.geometry().vertices();
+        for (index,face) in valid(mesh) {
+            let triangle = face.triangle(vertices);
+            for v in [*triangle.normal(),triangle.p1,triangle.p2,triangle.p3] {
+                for c in [v.x,v.y,v.z] {
+                    result.extend_from_slice(&(c as f32).to_le_bytes());
+                }
+            }
+
+            let attribute = mesh.color(index).map(|c| c.rgb555()).unwrap_or(0);
+            result.extend_from_slice(&attribute.to_le_bytes());
+        }
+    }
+
+    result
+}
+
+pub fn write<P: AsRef<Path>>(path: P, meshes: &[Mesh]) -> Result<(),Error> {
+    std::fs::write(path,encode(meshes))?;
+    Ok(())
+}
+
+// Faces that refer to real vertices, with their index
+pub(crate) fn valid(mesh: &Mesh) -> impl Iterator<Item = (usize,&crate::geometry::Face)> {
+    let vertices = mesh.geometry().vertices();
+    mesh.geometry()
+        .faces()
+        .iter()
+        .enumerate()
+        .filter(|(_,f)| f.is_valid(vertices))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::models;
+    use crate::part::Color;
+
+    #[test]
+    fn test_stl_encode() {
+        let plain = Mesh::new("plain",models::M2X4.geometry());
+        let red = plain.clone().with_color(Color::new(255,0,0));
+        let data = encode(&[plain,red]);
+
+        assert_eq!(data.len(),84 + 24 * 50);
+        assert_eq!(&data[..9],b"construct");
+        assert_eq!(u32::from_le_bytes(data[80..84].try_into().unwrap()),24);
+
+        // the attribute bytes of the first face of each mesh
+        let attribute = |face: usize| {
+            let i = 84 + face * 50 + 48;
+            u16::from_le_bytes([data[i],data[i + 1]])
+        };
+        assert_eq!(attribute(0),0);
+        assert_eq!(attribute(12),0x8000 | (31 << 10));
+    }
+
+}
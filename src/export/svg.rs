@@ -0,0 +1,100 @@
+use std::path::Path;
+
+use crate::nesting::Layout;
+use crate::geometry::Polyline;
+use crate::errors::Error;
+
+// space between sheets in millimeters
+const GAP: f64 = 100.0;
+
+// Draws a layout as an SVG in millimeters, with the sheets stacked
+// from the top down. Each part is a path titled with its name and
+// holes are cut out with the even-odd rule.
+pub fn encode(layout: &Layout) -> String {
+    span!("export.svg", sheets = layout.sheets());
+    let mm = |v: f64| v * 1000.0;
+    let (length,width) = (mm(layout.sheet().length()),mm(layout.sheet().width()));
+    let height = (layout.sheets() as f64 * (width + GAP) - GAP).max(0.0);
+
+    let mut result = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{length}mm\" height=\"{height}mm\" viewBox=\"0 0 {length} {height}\">\n"
+    );
+
+    for sheet in 0..layout.sheets() {
+        // SVG counts down from the top, so flip each sheet
+        let offset = sheet as f64 * (width + GAP);
+        let point = |(x,y): (f64,f64)| (mm(x),offset + width - mm(y));
+
+        result.push_str(&format!("<g id=\"sheet-{sheet}\">\n"));
+        result.push_str(&format!(
+            "<rect x=\"0\" y=\"{offset}\" width=\"{length}\" height=\"{width}\" fill=\"none\" stroke=\"black\"/>\n"
+        ));
+
+        for placement in layout.on(sheet) {
+            let data = placement.profile
+                .loops()
+                .map(|l| path(&l.map(point)))
+                .collect::<Vec<String>>()
+                .join(" ");
+
+            result.push_str(&format!(
+                "<path d=\"{}\" fill=\"none\" stroke=\"red\" fill-rule=\"evenodd\"><title>{}</title></path>\n",
+                data,escape(&placement.name)
+            ));
+        }
+
+        result.push_str("</g>\n");
+    }
+
+    result.push_str("</svg>\n");
+    result
+}
+
+pub fn write<P: AsRef<Path>>(path: P, layout: &Layout) -> Result<(),Error> {
+    std::fs::write(path,encode(layout))?;
+    Ok(())
+}
+
+// Path data for one closed loop
+fn path(line: &Polyline) -> String {
+    let points = line
+        .points()
+        .iter()
+        .map(|(x,y)| format!("{x:.3} {y:.3}"))
+        .collect::<Vec<String>>();
+    format!("M {} Z",points.join(" L "))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&',"&amp;")
+        .replace('<',"&lt;")
+        .replace('>',"&gt;")
+        .replace('"',"&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::nesting::{Nesting,Sheet};
+    use crate::part::Part;
+    use crate::models;
+
+    #[test]
+    fn test_svg_encode() {
+        let parts = [
+            Part::new("stud <a>").with_geometry(models::M2X4.geometry()).build().unwrap(),
+            Part::new("stud <b>").with_geometry(models::M2X4.geometry()).build().unwrap(),
+        ];
+        let layout = Nesting::new(Sheet::new(2.5,0.1)).layout(&parts).unwrap();
+        let text = encode(&layout);
+
+        assert_eq!(layout.sheets(),2);
+        assert!(text.starts_with("<svg"));
+        assert_eq!(text.matches("<path").count(),2);
+        assert_eq!(text.matches("<rect").count(),2);
+        assert!(text.contains("<title>stud &lt;a&gt;</title>"));
+        assert!(text.contains("height=\"300mm\""));
+    }
+
+}
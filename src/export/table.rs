@@ -0,0 +1,171 @@
+use crate::analysis::{Bom,CutList,Line};
+
+const BOM_COLUMNS: [&str;11] = [
+    "material","part","sku","quantity","length","width","thickness",
+    "mass","price","total mass","total price",
+];
+
+const CUT_COLUMNS: [&str;6] = [
+    "material","board","stock","part","length","waste",
+];
+
+// One cell of a spreadsheet
+#[derive(Debug,Clone,PartialEq)]
+pub(crate) enum Cell {
+    Empty,
+    Text(String),
+    Number(f64),
+}
+
+// A row of cells, which may be a subtotal or total of the rows
+// before it
+#[derive(Debug,Clone,PartialEq)]
+pub(crate) struct Row {
+    pub cells: Vec<Cell>,
+    pub total: bool,
+}
+
+// The rows of a spreadsheet, shared by every format it's written in
+// so that they all lay out a BOM or cut list the same way
+#[derive(Debug,Clone,PartialEq)]
+pub(crate) struct Table {
+    pub header: &'static [&'static str],
+    pub rows: Vec<Row>,
+}
+
+impl Table {
+
+    // A line for each part grouped by material, with a subtotal
+    // after each material and a total at the end. Sizes are in
+    // meters and masses in kilograms.
+    pub fn bom(bom: &Bom) -> Self {
+        let mut rows = Vec::new();
+        for (material,lines) in bom.groups() {
+            let material = Cell::text(material.unwrap_or_default());
+            for line in lines.iter() {
+                rows.push(Row::new(vec![
+                    material.clone(),
+                    Cell::text(&line.name),
+                    Cell::text(line.sku.as_deref().unwrap_or_default()),
+                    Cell::Number(line.quantity as f64),
+                    Cell::Number(line.size[0]),
+                    Cell::Number(line.size[1]),
+                    Cell::Number(line.size[2]),
+                    Cell::from(line.mass),
+                    Cell::from(line.price),
+                    Cell::from(line.total_mass()),
+                    Cell::from(line.total_price()),
+                ]));
+            }
+
+            let sum = |f: fn(&Line) -> Option<f64>| lines.iter().filter_map(f).sum::<f64>();
+            rows.push(Row::total(vec![
+                material,
+                Cell::text("subtotal"),
+                Cell::Empty,
+                Cell::Number(lines.iter().map(|l| l.quantity).sum::<usize>() as f64),
+                Cell::Empty, Cell::Empty, Cell::Empty, Cell::Empty, Cell::Empty,
+                Cell::Number(sum(|l| l.total_mass())),
+                Cell::Number(sum(|l| l.total_price())),
+            ]));
+        }
+
+        rows.push(Row::total(vec![
+            Cell::Empty,
+            Cell::text("total"),
+            Cell::Empty,
+            Cell::Number(bom.quantity() as f64),
+            Cell::Empty, Cell::Empty, Cell::Empty, Cell::Empty, Cell::Empty,
+            Cell::Number(bom.mass()),
+            Cell::Number(bom.price()),
+        ]));
+
+        Self { header: &BOM_COLUMNS, rows }
+    }
+
+    // A row for each piece, with boards numbered for each material
+    // and the offcut of each after its pieces, then any pieces that
+    // are too long for their stock and a total of the boards, the
+    // length of the pieces and the waste
+    pub fn cuts(list: &CutList) -> Self {
+        let mut rows = Vec::new();
+        let mut number = 0;
+        for (index,board) in list.boards().iter().enumerate() {
+            let first = index == 0 || list.boards()[index - 1].material != board.material;
+            number = if first { 1 } else { number + 1 };
+
+            let start = vec![
+                Cell::text(&board.material),
+                Cell::Number(number as f64),
+                Cell::Number(board.length),
+            ];
+            for piece in board.pieces.iter() {
+                let mut cells = start.clone();
+                cells.extend([Cell::text(&piece.name),Cell::Number(piece.length),Cell::Empty]);
+                rows.push(Row::new(cells));
+            }
+
+            let mut cells = start;
+            cells.extend([Cell::text("offcut"),Cell::Empty,Cell::Number(board.waste())]);
+            rows.push(Row::total(cells));
+        }
+
+        for piece in list.oversized().iter() {
+            rows.push(Row::new(vec![
+                Cell::text(&piece.material),
+                Cell::text("oversized"),
+                Cell::Empty,
+                Cell::text(&piece.name),
+                Cell::Number(piece.length),
+                Cell::Empty,
+            ]));
+        }
+
+        let length = list.boards()
+            .iter()
+            .flat_map(|b| b.pieces.iter())
+            .map(|p| p.length)
+            .sum::<f64>();
+
+        rows.push(Row::total(vec![
+            Cell::Empty,
+            Cell::text("total"),
+            Cell::Number(list.boards().len() as f64),
+            Cell::Empty,
+            Cell::Number(length),
+            Cell::Number(list.waste()),
+        ]));
+
+        Self { header: &CUT_COLUMNS, rows }
+    }
+
+}
+
+impl Row {
+
+    fn new(cells: Vec<Cell>) -> Self {
+        Self { cells, total: false }
+    }
+
+    fn total(cells: Vec<Cell>) -> Self {
+        Self { cells, total: true }
+    }
+
+}
+
+impl Cell {
+
+    fn text(value: &str) -> Self {
+        match value.is_empty() {
+            true => Cell::Empty,
+            false => Cell::Text(value.into()),
+        }
+    }
+
+}
+
+impl From<Option<f64>> for Cell {
+    fn from(value: Option<f64>) -> Self {
+        value.map(Cell::Number).unwrap_or(Cell::Empty)
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::Path;
+use rust_xlsxwriter::{Workbook,Worksheet,Format,XlsxError};
+
+use crate::analysis::{Bom,CutList};
+use crate::export::table::{Table,Cell};
+use crate::errors::Error;
+
+// Writes a workbook with the bill of materials on one sheet and
+// the cut list on another, laid out the same as the CSV files
+// with the header, subtotals and totals in bold
+pub fn encode(bom: &Bom, list: &CutList) -> Result<Vec<u8>,Error> {
+    span!("export.xlsx", lines = bom.len(), boards = list.boards().len());
+    let mut workbook = Workbook::new();
+    sheet(workbook.add_worksheet(),"BOM",&Table::bom(bom))?;
+    sheet(workbook.add_worksheet(),"Cut list",&Table::cuts(list))?;
+    workbook.save_to_buffer().map_err(error)
+}
+
+pub fn write<P: AsRef<Path>>(path: P, bom: &Bom, list: &CutList) -> Result<(),Error> {
+    std::fs::write(path,encode(bom,list)?)?;
+    Ok(())
+}
+
+fn sheet(worksheet: &mut Worksheet, name: &str, table: &Table) -> Result<(),Error> {
+    let bold = Format::new().set_bold();
+    let plain = Format::new();
+    worksheet.set_name(name).map_err(error)?;
+
+    for (column,title) in table.header.iter().enumerate() {
+        worksheet.write_string_with_format(0,column as u16,*title,&bold).map_err(error)?;
+    }
+
+    for (index,row) in table.rows.iter().enumerate() {
+        let format = if row.total { &bold } else { &plain };
+        for (column,cell) in row.cells.iter().enumerate() {
+            let (row,column) = (index as u32 + 1,column as u16);
+            match cell {
+                Cell::Empty => continue,
+                Cell::Text(t) => worksheet.write_string_with_format(row,column,t,format),
+                Cell::Number(v) => worksheet.write_number_with_format(row,column,*v,format),
+            }.map_err(error)?;
+        }
+    }
+    Ok(())
+}
+
+fn error(error: XlsxError) -> Error {
+    Error::EncodeError(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::assembly::Assembly;
+    use crate::materials::Database;
+
+    #[test]
+    fn test_xlsx_encode() {
+        let assembly = Assembly::new("empty");
+        let database = Database::builtin();
+        let data = encode(&Bom::new(&assembly,&database),&CutList::new(&assembly,&database,0.0)).unwrap();
+
+        // workbooks are zip files
+        assert_eq!(&data[..4],b"PK\x03\x04");
+        assert!(data.windows(8).any(|w| w == b"workbook"));
+    }
+
+}
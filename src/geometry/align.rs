@@ -0,0 +1,186 @@
+use crate::geometry::*;
+use crate::geometry::nearest::Nearest;
+use crate::geometry::fit::{solve,rms};
+
+/// What is made as small as possible when aligning one
+/// geometry to another
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Metric {
+    /// the distance from each point to the closest point
+    /// on the other surface
+    Point,
+    /// the distance from each point to the plane of the face
+    /// it's closest to, which lets flat faces slide along
+    /// each other and usually settles in fewer steps
+    #[default]
+    Plane,
+}
+
+/// Settings for lining one geometry up with another by the
+/// iterative closest point method. The geometries should already
+/// be roughly in place, because each step only moves towards the
+/// nearest surface.
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub struct Alignment {
+    metric: Metric,
+    iterations: usize,
+    tolerance: f64,
+    limit: Option<f64>,
+}
+
+impl Default for Alignment {
+    fn default() -> Self {
+        Self {
+            metric: Metric::default(),
+            iterations: 50,
+            tolerance: 1e-9,
+            limit: None,
+        }
+    }
+}
+
+impl Alignment {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_metric(mut self, metric: Metric) -> Self {
+        self.metric = metric;
+        self
+    }
+
+    // The most steps taken before giving up
+    pub fn with_iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    // Stops once a step improves the root mean square distance
+    // by less than this
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    // Ignores points further than this from the other surface,
+    // like stray points in a scan that aren't part of the object
+    pub fn with_limit(mut self, distance: f64) -> Self {
+        self.limit = Some(distance);
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    pub fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+
+    pub fn limit(&self) -> Option<f64> {
+        self.limit
+    }
+
+}
+
+// The transform that moves `points` onto the surface of `target`,
+// which is the identity if there's nothing to match against
+pub(crate) fn align(points: &[Vertex], target: &Geometry, settings: &Alignment) -> Matrix {
+    let mut matrix = Matrix::identity();
+    let Some(nearest) = Nearest::new(target) else {
+        return matrix;
+    };
+
+    let mut moved = points.to_vec();
+    let mut last = f64::MAX;
+    for _ in 0..settings.iterations {
+        let pairs = moved
+            .iter()
+            .map(|p| {
+                let (closest,face) = nearest.closest(p);
+                (*p,closest,target.get(face).normal().vector())
+            })
+            .filter(|(p,q,_)| settings.limit.is_none_or(|l| p.distance(q) <= l))
+            .collect::<Vec<(Vertex,Vertex,Vector)>>();
+
+        if pairs.len() < 3 {
+            break;
+        }
+
+        let error = rms(pairs.iter().map(|(p,q,n)| match settings.metric {
+            Metric::Point => p.distance(q),
+            Metric::Plane => (*q - *p).dot(n),
+        }));
+        if last - error <= settings.tolerance {
+            break;
+        }
+        last = error;
+
+        let Some(step) = step(&pairs,settings.metric) else {
+            break;
+        };
+        moved.iter_mut().for_each(|p| p.transform(&step));
+        matrix = step * matrix;
+    }
+    matrix
+}
+
+// One small move of the points towards their matches, from the least
+// squares solution for a rotation and translation about the middle of
+// the points, treating the rotation as small enough to be linear
+fn step(pairs: &[(Vertex,Vertex,Vector)], metric: Metric) -> Option<Matrix> {
+    let middle = pairs.iter().fold(Vertex::default(),|s,(p,_,_)| s + *p) * (1.0 / pairs.len() as f64);
+
+    let mut a = [[0.0;6];6];
+    let mut b = [0.0;6];
+    let mut add = |row: [f64;6], value: f64| {
+        for i in 0..6 {
+            for j in 0..6 {
+                a[i][j] += row[i] * row[j];
+            }
+            b[i] += row[i] * value;
+        }
+    };
+
+    for (p,q,n) in pairs.iter() {
+        let (p,q) = (*p - middle,*q - middle);
+        match metric {
+            Metric::Point => {
+                add([0.0,p.z,-p.y,1.0,0.0,0.0],q.x - p.x);
+                add([-p.z,0.0,p.x,0.0,1.0,0.0],q.y - p.y);
+                add([p.y,-p.x,0.0,0.0,0.0,1.0],q.z - p.z);
+            },
+            Metric::Plane => {
+                let c = p.cross(n);
+                add([c.x,c.y,c.z,n.x,n.y,n.z],(q - p).dot(n));
+            },
+        }
+    }
+
+    // a little damping keeps directions that nothing holds in
+    // place, like sliding along a single flat face, from moving
+    let damping = (0..6).map(|i| a[i][i]).fold(0.0,f64::max) * 1e-9 + f64::MIN_POSITIVE;
+    for (i,row) in a.iter_mut().enumerate() {
+        row[i] += damping;
+    }
+
+    let [x,y,z,tx,ty,tz] = solve(a,b)?;
+    let turn = Vector::new(x,y,z);
+    let rotation = match turn.magnitude() > 0.0 {
+        true => Matrix::rotate_axis(turn,turn.magnitude()),
+        false => Matrix::identity(),
+    };
+
+    Some(Matrix::translate(middle.x + tx,middle.y + ty,middle.z + tz) *
+        rotation *
+        Matrix::translate(-middle.x,-middle.y,-middle.z))
+}
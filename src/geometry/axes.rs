@@ -0,0 +1,151 @@
+use crate::geometry::{Vector,Matrix};
+
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Up {
+    Y,
+    Z,
+}
+
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Handedness {
+    Right,
+    Left,
+}
+
+/// Describes which axis points up and whether the coordinate
+/// system is right or left handed. The X axis always points
+/// to the right.
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct AxisConvention {
+    pub up: Up,
+    pub handedness: Handedness,
+}
+
+impl AxisConvention {
+
+    // OpenGL, glTF and most OBJ files
+    pub const Y_UP_RIGHT: Self = Self::new(Up::Y,Handedness::Right);
+
+    // Unity and DirectX
+    pub const Y_UP_LEFT: Self = Self::new(Up::Y,Handedness::Left);
+
+    // Blender, most CAD tools and construct's own models
+    pub const Z_UP_RIGHT: Self = Self::new(Up::Z,Handedness::Right);
+
+    // Unreal
+    pub const Z_UP_LEFT: Self = Self::new(Up::Z,Handedness::Left);
+
+    pub const fn new(up: Up, handedness: Handedness) -> Self {
+        Self { up, handedness }
+    }
+
+    // The right, up and back (towards the viewer) directions
+    // expressed in this convention's coordinates.
+    fn basis(&self) -> (Vector,Vector,Vector) {
+        let right = Vector::new(1.0,0.0,0.0);
+        let up = match self.up {
+            Up::Y => Vector::new(0.0,1.0,0.0),
+            Up::Z => Vector::new(0.0,0.0,1.0),
+        };
+
+        // cross products are mirrored in left-handed coordinates
+        let back = match self.handedness {
+            Handedness::Right => right.cross(&up),
+            Handedness::Left => right.cross(&up) * -1.0,
+        };
+
+        (right,up,back)
+    }
+
+    // True if converting between the conventions mirrors the
+    // geometry, which reverses the winding of every face.
+    pub fn mirrors(&self, other: &AxisConvention) -> bool {
+        self.handedness != other.handedness
+    }
+
+    // A matrix that converts coordinates in this convention
+    // into coordinates in the `other` convention.
+    pub fn matrix_to(&self, other: &AxisConvention) -> Matrix {
+        let (r1,u1,b1) = self.basis();
+        let (r2,u2,b2) = other.basis();
+
+        let a = [r1.unpack(),u1.unpack(),b1.unpack()];
+        let b = [r2.unpack(),u2.unpack(),b2.unpack()];
+
+        let c = |v: (f64,f64,f64), i: usize| match i {
+            0 => v.0,
+            1 => v.1,
+            _ => v.2,
+        };
+
+        let mut data = [0.0;16];
+        for i in 0..3 {
+            for j in 0..3 {
+                data[i * 4 + j] = (0..3)
+                    .map(|k| c(b[k],i) * c(a[k],j))
+                    .sum();
+            }
+        }
+        data[15] = 1.0;
+
+        Matrix::new(data)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::geometry::{Vertex,Transform};
+
+    fn convert(from: AxisConvention, to: AxisConvention, v: Vertex) -> Vertex {
+        let mut v = v;
+        v.transform(&from.matrix_to(&to));
+        v
+    }
+
+    #[test]
+    fn test_axes_y_up_to_z_up() {
+        let v = convert(
+            AxisConvention::Y_UP_RIGHT,
+            AxisConvention::Z_UP_RIGHT,
+            Vertex::new(1.0,2.0,3.0));
+
+        // up stays up, and +Z (towards the viewer) becomes -Y
+        assert_eq!(v,Vertex::new(1.0,-3.0,2.0));
+    }
+
+    #[test]
+    fn test_axes_z_up_to_y_up() {
+        let v = convert(
+            AxisConvention::Z_UP_RIGHT,
+            AxisConvention::Y_UP_RIGHT,
+            Vertex::new(1.0,-3.0,2.0));
+
+        assert_eq!(v,Vertex::new(1.0,2.0,3.0));
+    }
+
+    #[test]
+    fn test_axes_handedness() {
+        let from = AxisConvention::Y_UP_RIGHT;
+        let to = AxisConvention::Y_UP_LEFT;
+
+        let v = convert(from,to,Vertex::new(1.0,2.0,3.0));
+
+        assert_eq!(v,Vertex::new(1.0,2.0,-3.0));
+        assert!(from.mirrors(&to));
+        assert!(!from.mirrors(&AxisConvention::Z_UP_RIGHT));
+    }
+
+    #[test]
+    fn test_axes_identity() {
+        let v = convert(
+            AxisConvention::Z_UP_LEFT,
+            AxisConvention::Z_UP_LEFT,
+            Vertex::new(1.0,2.0,3.0));
+
+        assert_eq!(v,Vertex::new(1.0,2.0,3.0));
+    }
+
+}
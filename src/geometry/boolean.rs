@@ -0,0 +1,488 @@
+use std::collections::{HashMap,HashSet,BTreeSet};
+
+use crate::geometry::*;
+use crate::constant::{Index,TOLERANCE};
+
+// points closer to a plane than this are on it
+const EPSILON: f64 = 1e-9;
+
+/// The ways two closed meshes can be combined
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub(crate) enum Operation {
+    /// everything inside either mesh
+    Union,
+    /// everything inside the first mesh but not the second
+    Difference,
+    /// everything inside both meshes
+    Intersection,
+}
+
+// A flat convex polygon with the plane it's on
+#[derive(Debug,Clone)]
+struct Polygon {
+    points: Vec<Vertex>,
+    normal: Vector,
+    offset: f64,
+}
+
+// A node of a tree that splits space by the planes of polygons,
+// with the polygons on its plane, and the nodes for the space in
+// front of it and behind it
+#[derive(Default,Debug)]
+struct Node {
+    plane: Option<(Vector,f64)>,
+    front: Option<Index>,
+    back: Option<Index>,
+    polygons: Vec<Polygon>,
+}
+
+// A tree of nodes kept in a list, with the root first. The tree of a
+// convex mesh is as deep as it has faces, so it's built and walked
+// with a stack of nodes to visit rather than by recursion.
+#[derive(Debug)]
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+// Where a polygon is compared to a plane
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+enum Side {
+    Coplanar,
+    Front,
+    Back,
+    Spanning,
+}
+
+impl Polygon {
+
+    fn new(points: Vec<Vertex>) -> Option<Self> {
+        let normal = (points[1] - points[0]).cross(&(points[2] - points[0]));
+        if normal.magnitude() <= EPSILON {
+            return None;
+        }
+        let normal = normal.normalize();
+        Some(Self { offset: normal.dot(&points[0]), points, normal })
+    }
+
+    fn flip(&mut self) {
+        self.points.reverse();
+        self.normal = self.normal * -1.0;
+        self.offset = -self.offset;
+    }
+
+    // Sorts the polygon into the lists for a plane, cutting it in
+    // two if it crosses the plane. Polygons on the plane go in front
+    // or behind depending on which way they face.
+    fn split(
+        self,
+        (normal,offset): (Vector,f64),
+        coplanar_front: &mut Vec<Polygon>,
+        coplanar_back: &mut Vec<Polygon>,
+        front: &mut Vec<Polygon>,
+        back: &mut Vec<Polygon>,
+    ) {
+        let sides = self.points
+            .iter()
+            .map(|p| {
+                let t = normal.dot(p) - offset;
+                if t < -EPSILON {
+                    Side::Back
+                } else if t > EPSILON {
+                    Side::Front
+                } else {
+                    Side::Coplanar
+                }
+            })
+            .collect::<Vec<Side>>();
+
+        let side = sides.iter().fold(Side::Coplanar,|a,b| match (a,*b) {
+            (a,Side::Coplanar) => a,
+            (Side::Coplanar,b) => b,
+            (a,b) if a == b => a,
+            _ => Side::Spanning,
+        });
+
+        match side {
+            Side::Coplanar if self.normal.dot(&normal) > 0.0 => coplanar_front.push(self),
+            Side::Coplanar => coplanar_back.push(self),
+            Side::Front => front.push(self),
+            Side::Back => back.push(self),
+            Side::Spanning => {
+                let (mut f,mut b) = (Vec::new(),Vec::new());
+                let count = self.points.len();
+                for i in 0..count {
+                    let j = (i + 1) % count;
+                    let (si,sj) = (sides[i],sides[j]);
+                    let (pi,pj) = (self.points[i],self.points[j]);
+                    if si != Side::Back {
+                        f.push(pi);
+                    }
+                    if si != Side::Front {
+                        b.push(pi);
+                    }
+                    if matches!((si,sj),(Side::Front,Side::Back) | (Side::Back,Side::Front)) {
+                        let t = (offset - normal.dot(&pi)) / normal.dot(&(pj - pi));
+                        let point = pi + (pj - pi) * t;
+                        f.push(point);
+                        b.push(point);
+                    }
+                }
+                // the pieces keep the plane of the whole polygon
+                let (normal,offset) = (self.normal,self.offset);
+                if f.len() >= 3 {
+                    front.push(Polygon { points: f, normal, offset });
+                }
+                if b.len() >= 3 {
+                    back.push(Polygon { points: b, normal, offset });
+                }
+            },
+        }
+    }
+
+}
+
+impl Tree {
+
+    fn new(polygons: Vec<Polygon>) -> Self {
+        let mut tree = Self { nodes: vec![Node::default()] };
+        tree.build(polygons);
+        tree
+    }
+
+    // Turns the solid inside out
+    fn invert(&mut self) {
+        for node in self.nodes.iter_mut() {
+            for polygon in node.polygons.iter_mut() {
+                polygon.flip();
+            }
+            if let Some((normal,offset)) = node.plane.as_mut() {
+                *normal = *normal * -1.0;
+                *offset = -*offset;
+            }
+            std::mem::swap(&mut node.front,&mut node.back);
+        }
+    }
+
+    // The parts of the polygons that are outside this solid
+    fn clip_polygons(&self, polygons: Vec<Polygon>) -> Vec<Polygon> {
+        let mut result = Vec::new();
+        let mut stack = vec![(0,polygons)];
+        while let Some((index,polygons)) = stack.pop() {
+            let node = &self.nodes[index];
+            let Some(plane) = node.plane else {
+                result.extend(polygons);
+                continue;
+            };
+            let (mut front,mut back) = (Vec::new(),Vec::new());
+            for polygon in polygons {
+                let (mut coplanar_front,mut coplanar_back) = (Vec::new(),Vec::new());
+                polygon.split(plane,&mut coplanar_front,&mut coplanar_back,&mut front,&mut back);
+                front.append(&mut coplanar_front);
+                back.append(&mut coplanar_back);
+            }
+            match node.front {
+                Some(child) => stack.push((child,front)),
+                None => result.extend(front),
+            }
+            if let Some(child) = node.back {
+                stack.push((child,back));
+            }
+        }
+        result
+    }
+
+    // Takes out the parts of this tree's polygons inside another
+    fn clip_to(&mut self, other: &Tree) {
+        for node in self.nodes.iter_mut() {
+            node.polygons = other.clip_polygons(std::mem::take(&mut node.polygons));
+        }
+    }
+
+    fn all_polygons(&self) -> Vec<Polygon> {
+        self.nodes
+            .iter()
+            .flat_map(|n| n.polygons.iter().cloned())
+            .collect()
+    }
+
+    // Adds polygons to the tree, splitting each node by the plane of
+    // the first polygon to reach it when it doesn't have one yet
+    fn build(&mut self, polygons: Vec<Polygon>) {
+        let mut stack = vec![(0,polygons)];
+        while let Some((index,polygons)) = stack.pop() {
+            let Some(first) = polygons.first() else {
+                continue;
+            };
+            let plane = *self.nodes[index].plane.get_or_insert((first.normal,first.offset));
+            let (mut front,mut back) = (Vec::new(),Vec::new());
+            let mut coplanar = Vec::new();
+            for polygon in polygons {
+                let mut behind = Vec::new();
+                polygon.split(plane,&mut coplanar,&mut behind,&mut front,&mut back);
+                coplanar.append(&mut behind);
+            }
+            self.nodes[index].polygons.extend(coplanar);
+            if !front.is_empty() {
+                let child = self.child(index,true);
+                stack.push((child,front));
+            }
+            if !back.is_empty() {
+                let child = self.child(index,false);
+                stack.push((child,back));
+            }
+        }
+    }
+
+    // The node in front of or behind a node, added if there isn't one
+    fn child(&mut self, index: Index, front: bool) -> Index {
+        let count = self.nodes.len();
+        let node = &mut self.nodes[index];
+        let child = match front {
+            true => &mut node.front,
+            false => &mut node.back,
+        };
+        match *child {
+            Some(child) => child,
+            None => {
+                *child = Some(count);
+                self.nodes.push(Node::default());
+                count
+            },
+        }
+    }
+
+}
+
+// Combines two closed meshes into one. The result is made of the
+// pieces of the faces of each mesh, split where they cross the
+// other, so it keeps no groups, channels or normals.
+pub(crate) fn combine(a: &Geometry, b: &Geometry, operation: Operation) -> Geometry {
+    let mut a = Tree::new(polygons(a));
+    let mut b = Tree::new(polygons(b));
+
+    match operation {
+        Operation::Union => {
+            a.clip_to(&b);
+            b.clip_to(&a);
+            b.invert();
+            b.clip_to(&a);
+            b.invert();
+            a.build(b.all_polygons());
+        },
+        Operation::Difference => {
+            a.invert();
+            a.clip_to(&b);
+            b.clip_to(&a);
+            b.invert();
+            b.clip_to(&a);
+            b.invert();
+            a.build(b.all_polygons());
+            a.invert();
+        },
+        Operation::Intersection => {
+            a.invert();
+            b.clip_to(&a);
+            b.invert();
+            a.clip_to(&b);
+            b.clip_to(&a);
+            a.build(b.all_polygons());
+            a.invert();
+        },
+    }
+
+    mesh(a.all_polygons())
+}
+
+fn polygons(geometry: &Geometry) -> Vec<Polygon> {
+    geometry
+        .faces()
+        .iter()
+        .filter(|f| f.is_valid(geometry.vertices()))
+        .map(|f| f.triangle(geometry.vertices()))
+        .filter_map(|t| Polygon::new(vec![t.p1,t.p2,t.p3]))
+        .collect()
+}
+
+// Splits the polygons into triangles, sharing vertices that are in
+// the same place. Splitting leaves corners of some pieces part way
+// along an edge of a neighbour, so those are added to the edge to
+// keep every edge shared by two faces.
+fn mesh(polygons: Vec<Polygon>) -> Geometry {
+    let mut vertices = Vec::new();
+    let mut indices: HashMap<(i64,i64,i64),Index> = HashMap::new();
+    let mut index = |point: Vertex| {
+        let key = (point.x / EPSILON,point.y / EPSILON,point.z / EPSILON);
+        let key = (key.0.round() as i64,key.1.round() as i64,key.2.round() as i64);
+        *indices.entry(key).or_insert_with(|| {
+            vertices.push(point);
+            vertices.len() - 1
+        })
+    };
+
+    let mut loops = polygons
+        .into_iter()
+        .map(|p| {
+            let mut points = p.points.into_iter().map(&mut index).collect::<Vec<Index>>();
+            points.dedup();
+            while points.len() > 1 && points.first() == points.last() {
+                points.pop();
+            }
+            points
+        })
+        .filter(|p| p.len() >= 3)
+        .collect::<Vec<Vec<Index>>>();
+
+    let split = weld(&vertices,&mut loops);
+
+    let mut faces = Vec::new();
+    for (points,split) in loops.into_iter().zip(split) {
+        // a corner added to an edge is in line with its neighbours,
+        // so those pieces are fanned from their middle instead
+        let (apex,ring) = match split {
+            true => {
+                let middle = points.iter().fold(Vertex::default(),|a,i| a + vertices[*i]) / points.len();
+                vertices.push(middle);
+                (vertices.len() - 1,&points[..])
+            },
+            false => (points[0],&points[1..]),
+        };
+        let last = split.then(|| [points[points.len() - 1],points[0]]);
+        for pair in ring.windows(2).chain(last.as_ref().map(|l| &l[..])) {
+            let (a,b,c) = (apex,pair[0],pair[1]);
+            if a != b && b != c && a != c {
+                faces.push(Face::make(a + 1,b + 1,c + 1));
+            }
+        }
+    }
+    Geometry::new(vertices,faces)
+}
+
+// Adds the corners of neighbouring polygons that lie along an edge
+// to it, for edges that aren't matched by an edge running the other
+// way. Returns whether each polygon had corners added.
+fn weld(vertices: &[Vertex], loops: &mut [Vec<Index>]) -> Vec<bool> {
+    let edges = loops
+        .iter()
+        .flat_map(|p| (0..p.len()).map(move |i| (p[i],p[(i + 1) % p.len()])))
+        .collect::<HashSet<(Index,Index)>>();
+
+    let unmatched = |a: Index, b: Index| !edges.contains(&(b,a));
+    // sorted along x so only the ones across from an edge are checked
+    let mut corners = edges
+        .iter()
+        .filter(|(a,b)| unmatched(*a,*b))
+        .flat_map(|(a,b)| [*a,*b])
+        .collect::<BTreeSet<Index>>()
+        .into_iter()
+        .map(|i| (vertices[i].x,i))
+        .collect::<Vec<(f64,Index)>>();
+    corners.sort_by(|x,y| x.0.total_cmp(&y.0));
+
+    let mut split = vec![false;loops.len()];
+    if corners.is_empty() {
+        return split;
+    }
+
+    for (points,split) in loops.iter_mut().zip(split.iter_mut()) {
+        let mut result = Vec::with_capacity(points.len());
+        for i in 0..points.len() {
+            let (a,b) = (points[i],points[(i + 1) % points.len()]);
+            result.push(a);
+            if !unmatched(a,b) {
+                continue;
+            }
+
+            let (start,direction) = (vertices[a],vertices[b] - vertices[a]);
+            let length = direction.magnitude();
+            let low = start.x.min(vertices[b].x) - TOLERANCE;
+            let high = start.x.max(vertices[b].x) + TOLERANCE;
+            let first = corners.partition_point(|c| c.0 < low);
+            let mut between = corners[first..]
+                .iter()
+                .take_while(|c| c.0 <= high)
+                .map(|(_,c)| *c)
+                .filter(|c| *c != a && *c != b)
+                .filter_map(|c| {
+                    let t = (vertices[c] - start).dot(&direction) / (length * length);
+                    let off = (start + direction * t).distance(&vertices[c]);
+                    let inside = t * length > TOLERANCE && (1.0 - t) * length > TOLERANCE;
+                    (inside && off < TOLERANCE).then_some((t,c))
+                })
+                .collect::<Vec<(f64,Index)>>();
+            between.sort_by(|x,y| x.0.total_cmp(&y.0));
+            *split |= !between.is_empty();
+            result.extend(between.into_iter().map(|(_,c)| c));
+        }
+        *points = result;
+    }
+    split
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::testing;
+
+    fn block(min: f64, max: f64) -> Geometry {
+        Geometry::from_bounds(&Bounds::new(Vertex::new(min,min,min),Vertex::new(max,max,max)))
+    }
+
+    #[test]
+    fn test_boolean_boxes() {
+        let a = block(0.0,2.0);
+        let b = block(1.0,3.0);
+
+        assert_relative_eq!(a.union(&b).volume(),15.0,epsilon = 1e-9);
+        assert_relative_eq!(a.difference(&b).volume(),7.0,epsilon = 1e-9);
+        assert_relative_eq!(a.intersection(&b).volume(),1.0,epsilon = 1e-9);
+
+        // a box cut right through leaves a hole
+        let bar = Geometry::from_bounds(&Bounds::new(Vertex::new(0.5,0.5,-1.0),Vertex::new(1.5,1.5,3.0)));
+        let cut = a.difference(&bar);
+        assert_relative_eq!(cut.volume(),6.0,epsilon = 1e-9);
+        assert!(!cut.encloses(&Vertex::new(1.0,1.0,1.0)));
+        assert!(cut.encloses(&Vertex::new(0.25,1.0,1.0)));
+
+        // nothing is cut by a mesh that doesn't reach
+        assert_relative_eq!(a.difference(&block(5.0,6.0)).volume(),8.0,epsilon = 1e-9);
+        assert!(a.intersection(&block(5.0,6.0)).is_empty());
+    }
+
+    // Every edge of a closed mesh is shared by exactly two faces
+    fn assert_closed(geometry: &Geometry) {
+        assert!(!geometry.is_empty());
+        assert!(geometry.edge_faces().values().all(|f| f.len() == 2));
+    }
+
+    #[test]
+    fn test_boolean_closed() {
+        let sphere = testing::sphere(1.0,400);
+        let cut = sphere.difference(&block(0.2,2.0));
+        assert_closed(&cut);
+        assert!(cut.volume() < sphere.volume());
+        assert!(!cut.encloses(&Vertex::new(0.5,0.5,0.5)));
+        assert!(cut.encloses(&Vertex::new(-0.5,-0.5,-0.5)));
+
+        assert_closed(&block(0.0,2.0).union(&block(1.0,3.0)));
+        assert_closed(&block(0.0,2.0).difference(&block(1.0,3.0)));
+        assert_closed(&sphere.intersection(&block(0.0,2.0)));
+    }
+
+    #[test]
+    fn test_boolean_deep_tree() {
+        // the tree of a convex mesh has a node for every face, which
+        // can't take a frame of the stack each
+        let result = std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(|| {
+                let sphere = testing::sphere(1.0,1500);
+                sphere.difference(&block(0.0,2.0))
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_closed(&result);
+    }
+
+}
@@ -0,0 +1,117 @@
+use crate::geometry::*;
+
+/// An axis-aligned bounding box
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct Bounds {
+    pub min: Vertex,
+    pub max: Vertex,
+}
+
+impl Bounds {
+
+    pub const fn new(min: Vertex, max: Vertex) -> Self {
+        Self { min, max }
+    }
+
+    // The smallest box containing every point, or None
+    // if there are no points.
+    pub fn from_points(points: &[Vertex]) -> Option<Self> {
+        let first = points.first()?;
+        let mut bounds = Self::new(*first,*first);
+        for point in points.iter().skip(1) {
+            bounds.expand(point);
+        }
+        Some(bounds)
+    }
+
+    pub fn expand(&mut self, point: &Vertex) {
+        self.min.x = self.min.x.min(point.x);
+        self.min.y = self.min.y.min(point.y);
+        self.min.z = self.min.z.min(point.z);
+        self.max.x = self.max.x.max(point.x);
+        self.max.y = self.max.y.max(point.y);
+        self.max.z = self.max.z.max(point.z);
+    }
+
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        let mut result = *self;
+        result.expand(&other.min);
+        result.expand(&other.max);
+        result
+    }
+
+    pub fn contains(&self, point: &Vertex) -> bool {
+        self.min.x <= point.x && point.x <= self.max.x &&
+        self.min.y <= point.y && point.y <= self.max.y &&
+        self.min.z <= point.z && point.z <= self.max.z
+    }
+
+    pub fn intersects(&self, other: &Bounds) -> bool {
+        self.min.x <= other.max.x && other.min.x <= self.max.x &&
+        self.min.y <= other.max.y && other.min.y <= self.max.y &&
+        self.min.z <= other.max.z && other.min.z <= self.max.z
+    }
+
+    pub fn size(&self) -> Vector {
+        self.max - self.min
+    }
+
+    pub fn center(&self) -> Vertex {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn corners(&self) -> [Vertex;8] {
+        let (a,b) = (self.min,self.max);
+        [
+            Vertex::new(a.x,a.y,a.z), Vertex::new(b.x,a.y,a.z),
+            Vertex::new(a.x,b.y,a.z), Vertex::new(b.x,b.y,a.z),
+            Vertex::new(a.x,a.y,b.z), Vertex::new(b.x,a.y,b.z),
+            Vertex::new(a.x,b.y,b.z), Vertex::new(b.x,b.y,b.z),
+        ]
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_bounds_from_points() {
+        let bounds = Bounds::from_points(&[
+            Vertex::new(1.0,-2.0,0.5),
+            Vertex::new(-1.0,3.0,0.0),
+            Vertex::new(0.0,0.0,2.0),
+        ]).unwrap();
+
+        assert_eq!(bounds.min,Vertex::new(-1.0,-2.0,0.0));
+        assert_eq!(bounds.max,Vertex::new(1.0,3.0,2.0));
+        assert_eq!(bounds.size(),Vector::new(2.0,5.0,2.0));
+        assert_eq!(bounds.center(),Vertex::new(0.0,0.5,1.0));
+        assert!(Bounds::from_points(&[]).is_none());
+    }
+
+    #[test]
+    fn test_bounds_contains() {
+        let bounds = Bounds::new(
+            Vertex::new(0.0,0.0,0.0),
+            Vertex::new(1.0,1.0,1.0));
+
+        assert!(bounds.contains(&Vertex::new(0.5,0.5,0.5)));
+        assert!(bounds.contains(&Vertex::new(1.0,1.0,1.0)));
+        assert!(!bounds.contains(&Vertex::new(1.5,0.5,0.5)));
+    }
+
+    #[test]
+    fn test_bounds_intersects() {
+        let a = Bounds::new(Vertex::new(0.0,0.0,0.0),Vertex::new(1.0,1.0,1.0));
+        let b = Bounds::new(Vertex::new(0.5,0.5,0.5),Vertex::new(2.0,2.0,2.0));
+        let c = Bounds::new(Vertex::new(1.5,1.5,1.5),Vertex::new(2.0,2.0,2.0));
+
+        assert!(a.intersects(&b));
+        assert!(!a.intersects(&c));
+        assert_eq!(a.union(&c),Bounds::new(Vertex::new(0.0,0.0,0.0),Vertex::new(2.0,2.0,2.0)));
+    }
+
+}
@@ -0,0 +1,120 @@
+use crate::constant::Index;
+
+/// A sorted, non-overlapping set of vertex ranges that
+/// have been modified since the last time it was cleared.
+/// Ranges are half-open (start..end).
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Changes {
+    ranges: Vec<(Index,Index)>,
+}
+
+impl Changes {
+
+    pub const fn new() -> Self {
+        Self { ranges: Vec::new() }
+    }
+
+    pub fn mark(&mut self, start: Index, end: Index) {
+        if start >= end {
+            return;
+        }
+
+        // find the first range that could touch the new one
+        let position = self.ranges
+            .iter()
+            .position(|&(_,e)| e >= start)
+            .unwrap_or(self.ranges.len());
+
+        let mut start = start;
+        let mut end = end;
+
+        // absorb every range that overlaps or is adjacent
+        while position < self.ranges.len() && self.ranges[position].0 <= end {
+            let (s,e) = self.ranges.remove(position);
+            start = start.min(s);
+            end = end.max(e);
+        }
+
+        self.ranges.insert(position,(start,end));
+    }
+
+    pub fn mark_index(&mut self, index: Index) {
+        self.mark(index,index.saturating_add(1));
+    }
+
+    pub fn mark_all(&mut self, count: usize) {
+        self.ranges.clear();
+        self.mark(0,count);
+    }
+
+    pub fn ranges(&self) -> &[(Index,Index)] {
+        &self.ranges
+    }
+
+    pub fn contains(&self, index: Index) -> bool {
+        self.ranges
+            .iter()
+            .any(|&(s,e)| s <= index && index < e)
+    }
+
+    pub fn count(&self) -> usize {
+        self.ranges
+            .iter()
+            .map(|(s,e)| e - s)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_changes_merge_overlapping() {
+        let mut changes = Changes::new();
+        changes.mark(0,3);
+        changes.mark(2,5);
+        assert_eq!(changes.ranges(),&[(0,5)]);
+    }
+
+    #[test]
+    fn test_changes_merge_adjacent() {
+        let mut changes = Changes::new();
+        changes.mark(4,6);
+        changes.mark(0,2);
+        changes.mark(2,4);
+        assert_eq!(changes.ranges(),&[(0,6)]);
+    }
+
+    #[test]
+    fn test_changes_keep_disjoint() {
+        let mut changes = Changes::new();
+        changes.mark(8,10);
+        changes.mark_index(5);
+        changes.mark(0,2);
+
+        assert_eq!(changes.ranges(),&[(0,2),(5,6),(8,10)]);
+        assert_eq!(changes.count(),5);
+        assert!(changes.contains(5));
+        assert!(!changes.contains(6));
+    }
+
+    #[test]
+    fn test_changes_ignore_empty() {
+        let mut changes = Changes::new();
+        changes.mark(3,3);
+        changes.mark(4,2);
+        assert!(changes.is_empty());
+    }
+
+}
@@ -0,0 +1,109 @@
+use crate::geometry::*;
+use crate::geometry::fit::spread;
+use crate::geometry::nearest::Nearest;
+use crate::geometry::symmetry::Lookup;
+
+// The rigid transform that moves `a` onto `b`, if every vertex lands
+// within `tolerance` of a vertex of `b` and every face lands on its
+// surface. Two vertices of `a` that pin down a frame are matched to
+// every pair of vertices in `b` at the same distances, so shapes
+// that look the same several ways around still find one of them.
+pub(crate) fn congruent(a: &Geometry, b: &Geometry, tolerance: f64) -> Option<Matrix> {
+    let (from,to) = (a.vertices(),b.vertices());
+    if from.len() != to.len() || a.size() != b.size() {
+        return None;
+    }
+
+    // spreads are sums of squares, so they're compared as the
+    // distances they work out to for each vertex
+    let (ca,sa,_) = spread(from)?;
+    let (cb,sb,_) = spread(to)?;
+    let count = from.len() as f64;
+    let rms = |s: f64| (s / count).max(0.0).sqrt();
+    if sa.iter().zip(sb.iter()).any(|(x,y)| (rms(*x) - rms(*y)).abs() > tolerance) {
+        return None;
+    }
+
+    let lookup = Lookup::new(to,tolerance);
+    let surface = Nearest::new(b);
+    let fits = |matrix: &Matrix| {
+        let moved = from
+            .iter()
+            .map(|v| {
+                let mut v = *v;
+                v.transform(matrix);
+                v
+            })
+            .collect::<Vec<Vertex>>();
+        moved.iter().all(|v| lookup.find(v).is_some()) && surface.as_ref().is_none_or(|s| a
+            .faces()
+            .iter()
+            .filter(|f| f.is_valid(&moved))
+            .map(|f| (moved[f.a] + moved[f.b] + moved[f.c]) * (1.0 / 3.0))
+            .all(|c| s.closest(&c).0.distance(&c) <= tolerance))
+    };
+
+    // the vertex furthest from the middle, and the one furthest
+    // from the line through it
+    let first = *from.iter().max_by(|p,q| p.distance(&ca).total_cmp(&q.distance(&ca)))?;
+    let axis = (first - ca).normalize();
+    let off = |v: &Vertex| {
+        let d = *v - ca;
+        (d - axis * d.dot(&axis)).magnitude()
+    };
+    let second = *from.iter().max_by(|p,q| off(p).total_cmp(&off(q)))?;
+
+    let Some(anchor) = frame(ca,first,second,tolerance) else {
+        // everything is on a line or at one point
+        let matrix = Matrix::translate(cb.x - ca.x,cb.y - ca.y,cb.z - ca.z);
+        return fits(&matrix).then_some(matrix);
+    };
+
+    let near = |x: f64, y: f64| (x - y).abs() <= tolerance * 2.0;
+    let (reach,width,gap) = (first.distance(&ca),second.distance(&ca),first.distance(&second));
+    for p in to.iter().filter(|p| near(p.distance(&cb),reach)) {
+        for q in to.iter().filter(|q| near(q.distance(&cb),width) && near(q.distance(p),gap)) {
+            let Some(other) = frame(cb,*p,*q,tolerance) else {
+                continue;
+            };
+            let matrix = moving(ca,&anchor,cb,&other);
+            if fits(&matrix) {
+                return Some(matrix);
+            }
+        }
+    }
+    None
+}
+
+// Three directions at right angles, along `p` from the center and
+// towards `q` from that line
+fn frame(center: Vertex, p: Vertex, q: Vertex, tolerance: f64) -> Option<[Vector;3]> {
+    let u = p - center;
+    if u.magnitude() <= tolerance {
+        return None;
+    }
+    let u = u.normalize();
+    let d = q - center;
+    let w = d - u * d.dot(&u);
+    if w.magnitude() <= tolerance {
+        return None;
+    }
+    let w = w.normalize();
+    Some([u,w,u.cross(&w)])
+}
+
+// The rotation that turns one frame into the other, about the
+// center of the first, then moved to the center of the second
+fn moving(ca: Vertex, fa: &[Vector;3], cb: Vertex, fb: &[Vector;3]) -> Matrix {
+    let component = |v: &Vector, i: usize| [v.x,v.y,v.z][i];
+    let r = |row: usize, column: usize| (0..3)
+        .map(|i| component(&fb[i],row) * component(&fa[i],column))
+        .sum::<f64>();
+    let rotation = Matrix::new([
+        r(0,0), r(0,1), r(0,2), 0.0,
+        r(1,0), r(1,1), r(1,2), 0.0,
+        r(2,0), r(2,1), r(2,2), 0.0,
+        0.0,    0.0,    0.0,    1.0,
+    ]);
+    Matrix::translate(cb.x,cb.y,cb.z) * rotation * Matrix::translate(-ca.x,-ca.y,-ca.z)
+}
@@ -0,0 +1,170 @@
+use std::collections::BTreeSet;
+
+use crate::geometry::*;
+use crate::constant::{Index,TOLERANCE};
+
+/// A measure of how much a surface bends at a vertex. Curvatures are
+/// positive where the surface bulges out, like the outside of a sphere,
+/// and negative where it dips in.
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Curvature {
+    /// the product of the principal curvatures, which is zero
+    /// wherever the surface only bends one way, like a cylinder
+    Gaussian,
+    /// the average of the principal curvatures
+    #[default]
+    Mean,
+    /// the principal curvature that bends the most
+    Maximum,
+    /// the principal curvature that bends the least
+    Minimum,
+}
+
+/// A rounded edge, found as connected vertices that bend one way
+/// by about the same amount
+#[derive(Debug,Clone,PartialEq)]
+pub struct Fillet {
+    /// the average radius of the vertices
+    pub radius: f64,
+    pub vertices: Vec<Index>,
+}
+
+impl Curvature {
+
+    // This measure from the Gaussian and mean curvature of a vertex
+    pub fn value(&self, gaussian: f64, mean: f64) -> f64 {
+        let spread = (mean * mean - gaussian).max(0.0).sqrt();
+        match self {
+            Curvature::Gaussian => gaussian,
+            Curvature::Mean => mean,
+            Curvature::Maximum => mean + spread,
+            Curvature::Minimum => mean - spread,
+        }
+    }
+
+}
+
+// The Gaussian and mean curvature at every vertex. Gaussian curvature
+// is the angle left over around a vertex and mean curvature is from the
+// cotangent weighted edges, both spread over the area closest to the
+// vertex. Vertices on an open edge, or that aren't used by any faces,
+// are zero because the surface doesn't go all the way around them.
+pub(crate) fn curvatures(geometry: &Geometry) -> Vec<(f64,f64)> {
+    let vertices = geometry.vertices();
+    let count = vertices.len();
+
+    let mut angles = vec![0.0;count];
+    let mut areas = vec![0.0;count];
+    let mut normals = vec![Vector::default();count];
+    let mut laplace = vec![Vector::default();count];
+
+    for face in geometry.faces().iter().filter(|f| f.is_valid(vertices)) {
+        let corners = [face.a,face.b,face.c];
+        let points = corners.map(|i| vertices[i]);
+        let normal = (points[1] - points[0]).cross(&(points[2] - points[0]));
+        let area = normal.magnitude() / 2.0;
+        if area <= 0.0 {
+            continue;
+        }
+
+        // the angle and its cotangent at each corner
+        let corner = |i: usize| {
+            let a = points[(i + 1) % 3] - points[i];
+            let b = points[(i + 2) % 3] - points[i];
+            (a.angle(&b),a.dot(&b) / a.cross(&b).magnitude())
+        };
+        let corner = [0,1,2].map(corner);
+        let obtuse = corner.iter().position(|(a,_)| *a > std::f64::consts::FRAC_PI_2);
+
+        for i in 0..3 {
+            let (j,k) = ((i + 1) % 3,(i + 2) % 3);
+            let index = corners[i];
+            angles[index] += corner[i].0;
+            normals[index] = normals[index] + normal;
+
+            // the edge from i to j is across from k, and from i to k across from j
+            let (to_j,to_k) = (points[i] - points[j],points[i] - points[k]);
+            laplace[index] = laplace[index] + to_j * corner[k].1 + to_k * corner[j].1;
+
+            // the part of the face closest to the vertex, or a share
+            // of the area if the face is obtuse and that is outside it
+            areas[index] += match obtuse {
+                None => (to_j.dot(&to_j) * corner[k].1 + to_k.dot(&to_k) * corner[j].1) / 8.0,
+                Some(o) if o == i => area / 2.0,
+                Some(_) => area / 4.0,
+            };
+        }
+    }
+
+    let open = geometry
+        .edge_faces()
+        .into_iter()
+        .filter(|(_,faces)| faces.len() == 1)
+        .flat_map(|((a,b),_)| [a,b])
+        .collect::<BTreeSet<Index>>();
+
+    (0..count)
+        .map(|i| {
+            if open.contains(&i) || areas[i] <= 0.0 {
+                return (0.0,0.0);
+            }
+            let gaussian = (std::f64::consts::TAU - angles[i]) / areas[i];
+            let bend = laplace[i] * (1.0 / (4.0 * areas[i]));
+            let mean = match bend.dot(&normals[i]) < 0.0 {
+                true => -bend.magnitude(),
+                false => bend.magnitude(),
+            };
+            (gaussian,mean)
+        })
+        .collect()
+}
+
+// Groups of connected vertices that bend one way, where the Gaussian
+// curvature is small next to the square of the mean curvature, and the
+// radius stays within `tolerance` times the radius where the group
+// started. Sharp edges on a finely divided mesh look like very tight
+// fillets, so small radii should be checked before trusting them.
+pub(crate) fn fillets(geometry: &Geometry, tolerance: f64) -> Vec<Fillet> {
+    let values = curvatures(geometry);
+    let radius = |i: Index| {
+        let (gaussian,mean) = values[i];
+        let round = mean.abs() > TOLERANCE && gaussian.abs() <= tolerance * mean * mean;
+        round.then(|| 1.0 / (2.0 * mean.abs()))
+    };
+
+    let mut neighbours = vec![Vec::new();values.len()];
+    for (a,b) in geometry.edges() {
+        neighbours[a].push(b);
+        neighbours[b].push(a);
+    }
+
+    let mut seen = vec![false;values.len()];
+    let mut result = Vec::new();
+    for start in 0..values.len() {
+        let Some(size) = radius(start).filter(|_| !seen[start]) else {
+            continue;
+        };
+
+        seen[start] = true;
+        let mut vertices = vec![start];
+        let mut next = 0;
+        while next < vertices.len() {
+            let vertex = vertices[next];
+            next += 1;
+            for &other in neighbours[vertex].iter() {
+                let close = radius(other).is_some_and(|r| (r - size).abs() <= tolerance * size);
+                if !seen[other] && close {
+                    seen[other] = true;
+                    vertices.push(other);
+                }
+            }
+        }
+
+        if vertices.len() > 1 {
+            vertices.sort_unstable();
+            let radius = vertices.iter().filter_map(|v| radius(*v)).sum::<f64>() / vertices.len() as f64;
+            result.push(Fillet { radius, vertices });
+        }
+    }
+    result
+}
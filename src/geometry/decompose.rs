@@ -0,0 +1,321 @@
+use crate::geometry::*;
+
+// rows of samples are nudged off the grid by this much of a cell,
+// so they don't run exactly along edges of the mesh
+const NUDGE: (f64,f64) = (0.000_618,0.000_377);
+
+/// Settings for splitting a closed mesh into convex pieces
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub struct Decomposition {
+    concavity: f64,
+    depth: usize,
+    resolution: usize,
+}
+
+impl Default for Decomposition {
+    fn default() -> Self {
+        Self {
+            concavity: 0.05,
+            depth: 6,
+            resolution: 32,
+        }
+    }
+}
+
+impl Decomposition {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // How much of a piece's hull can be empty, as a fraction of
+    // the hull's volume, before the piece is split again
+    pub fn with_concavity(mut self, concavity: f64) -> Self {
+        self.concavity = concavity.max(0.0);
+        self
+    }
+
+    // How many times a piece can be split in half, so there are
+    // never more than 2^depth pieces
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    // How many samples are taken along the longest side to
+    // measure the volume of each piece
+    pub fn with_resolution(mut self, resolution: usize) -> Self {
+        self.resolution = resolution.max(1);
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn concavity(&self) -> f64 {
+        self.concavity
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn resolution(&self) -> usize {
+        self.resolution
+    }
+
+}
+
+// Whether samples at the middle of each cell of a grid are inside
+// a closed mesh, found a row at a time along X
+struct Grid {
+    origin: Vertex,
+    cell: f64,
+    size: [usize;3],
+    inside: Vec<bool>,
+}
+
+impl Grid {
+
+    fn new(triangles: &[Triangle], bounds: &Bounds, resolution: usize) -> Self {
+        let extent = bounds.size();
+        let cell = extent.x.max(extent.y).max(extent.z) / resolution as f64;
+        let count = |v: f64| ((v / cell).ceil() as usize).max(1);
+        let size = [count(extent.x),count(extent.y),count(extent.z)];
+        let mut grid = Self { origin: bounds.min, cell, size, inside: vec![false;size[0] * size[1] * size[2]] };
+
+        for k in 0..size[2] {
+            for j in 0..size[1] {
+                let y = bounds.min.y + (j as f64 + 0.5 + NUDGE.0) * cell;
+                let z = bounds.min.z + (k as f64 + 0.5 + NUDGE.1) * cell;
+
+                // where the row passes through the surface
+                let mut crossings = triangles
+                    .iter()
+                    .filter_map(|t| row(t,y,z))
+                    .collect::<Vec<f64>>();
+                crossings.sort_by(f64::total_cmp);
+
+                for i in 0..size[0] {
+                    let x = bounds.min.x + (i as f64 + 0.5) * cell;
+                    let before = crossings.iter().take_while(|c| **c < x).count();
+                    let index = grid.index(i,j,k);
+                    grid.inside[index] = before % 2 == 1;
+                }
+            }
+        }
+        grid
+    }
+
+    fn index(&self, i: usize, j: usize, k: usize) -> usize {
+        i + self.size[0] * (j + self.size[1] * k)
+    }
+
+    // The number of samples inside in a range of cells
+    fn count(&self, region: &Region) -> usize {
+        let mut count = 0;
+        for k in region.min[2]..region.max[2] {
+            for j in region.min[1]..region.max[1] {
+                for i in region.min[0]..region.max[0] {
+                    count += self.inside[self.index(i,j,k)] as usize;
+                }
+            }
+        }
+        count
+    }
+
+    // The smallest range of cells around the samples inside
+    // a region, or None if there aren't any
+    fn shrink(&self, region: &Region) -> Option<Region> {
+        let mut result: Option<Region> = None;
+        for k in region.min[2]..region.max[2] {
+            for j in region.min[1]..region.max[1] {
+                for i in region.min[0]..region.max[0] {
+                    if self.inside[self.index(i,j,k)] {
+                        let r = result.get_or_insert(Region { min: [i,j,k], max: [i + 1,j + 1,k + 1] });
+                        for (axis,v) in [i,j,k].into_iter().enumerate() {
+                            r.min[axis] = r.min[axis].min(v);
+                            r.max[axis] = r.max[axis].max(v + 1);
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    // The box a range of cells covers
+    fn bounds(&self, region: &Region) -> Bounds {
+        let point = |c: [usize;3]| self.origin + Vector::new(c[0] as f64,c[1] as f64,c[2] as f64) * self.cell;
+        Bounds::new(point(region.min),point(region.max))
+    }
+
+}
+
+// A range of cells in a grid
+#[derive(Debug,Copy,Clone,PartialEq)]
+struct Region {
+    min: [usize;3],
+    max: [usize;3],
+}
+
+impl Region {
+
+    // The two halves of the region split before a cell on an axis
+    fn split(&self, axis: usize, at: usize) -> (Region,Region) {
+        let (mut a,mut b) = (*self,*self);
+        a.max[axis] = at;
+        b.min[axis] = at;
+        (a,b)
+    }
+
+}
+
+// Where a line along X at a height and depth passes through a
+// triangle, going by which side of each edge it's on
+fn row(t: &Triangle, y: f64, z: f64) -> Option<f64> {
+    let side = |a: &Vertex, b: &Vertex| (b.y - a.y) * (z - a.z) - (b.z - a.z) * (y - a.y);
+    let (u,v,w) = (side(&t.p2,&t.p3),side(&t.p3,&t.p1),side(&t.p1,&t.p2));
+    let total = u + v + w;
+    let inside = (u >= 0.0 && v >= 0.0 && w >= 0.0) || (u <= 0.0 && v <= 0.0 && w <= 0.0);
+    match inside && total.abs() > f64::EPSILON {
+        true => Some((t.p1.x * u + t.p2.x * v + t.p3.x * w) / total),
+        false => None,
+    }
+}
+
+// The points whose hull is the hull of the part of a mesh inside a
+// box: the corners of each triangle cut down to the box, the box
+// corners inside the mesh and where the box edges cross the mesh
+fn points(triangles: &[Triangle], grid: &Grid, bounds: &Bounds) -> Vec<Vertex> {
+    let mut points = Vec::new();
+    for triangle in triangles {
+        let mut polygon = vec![triangle.p1,triangle.p2,triangle.p3];
+        for axis in 0..3 {
+            let (min,max) = (axes(&bounds.min)[axis],axes(&bounds.max)[axis]);
+            polygon = clip(&polygon,axis,min,1.0);
+            polygon = clip(&polygon,axis,max,-1.0);
+        }
+        points.extend(polygon);
+    }
+
+    let corners = (0..8)
+        .map(|c| Vertex::new(
+            if c & 1 == 0 { bounds.min.x } else { bounds.max.x },
+            if c & 2 == 0 { bounds.min.y } else { bounds.max.y },
+            if c & 4 == 0 { bounds.min.z } else { bounds.max.z }))
+        .collect::<Vec<Vertex>>();
+
+    // corners are inside if the samples in every cell
+    // around them are, which is close enough at the
+    // resolution of the grid
+    for corner in corners.iter() {
+        let lattice = |axis: usize| ((axes(corner)[axis] - axes(&grid.origin)[axis]) / grid.cell).round() as usize;
+        let (i,j,k) = (lattice(0),lattice(1),lattice(2));
+        let inside = (0..8).all(|n| {
+            let cell = [i + (n & 1),j + ((n >> 1) & 1),k + ((n >> 2) & 1)];
+            cell.iter().zip(grid.size).all(|(c,s)| *c >= 1 && *c <= s) &&
+            grid.inside[grid.index(cell[0] - 1,cell[1] - 1,cell[2] - 1)]
+        });
+        if inside {
+            points.push(*corner);
+        }
+    }
+
+    for (a,b) in [(0,1),(2,3),(4,5),(6,7),(0,2),(1,3),(4,6),(5,7),(0,4),(1,5),(2,6),(3,7)] {
+        points.extend(triangles.iter().filter_map(|t| t.crossing(&corners[a],&corners[b])));
+    }
+    points
+}
+
+// The part of a polygon on the positive side of a plane across an
+// axis, where `sign` picks which side is kept
+fn clip(polygon: &[Vertex], axis: usize, value: f64, sign: f64) -> Vec<Vertex> {
+    let distance = |v: &Vertex| (axes(v)[axis] - value) * sign;
+    let mut result = Vec::with_capacity(polygon.len() + 1);
+    for (i,current) in polygon.iter().enumerate() {
+        let next = &polygon[(i + 1) % polygon.len()];
+        let (dc,dn) = (distance(current),distance(next));
+        if dc >= 0.0 {
+            result.push(*current);
+        }
+        if (dc >= 0.0) != (dn >= 0.0) {
+            result.push(*current + (*next - *current) * (dc / (dc - dn)));
+        }
+    }
+    result
+}
+
+fn axes(v: &Vector) -> [f64;3] {
+    [v.x,v.y,v.z]
+}
+
+// Splits a closed mesh into convex hulls by cutting it in half
+// across whichever axis-aligned plane leaves the least empty space
+// in the hulls of the two halves, until each hull is full enough.
+// Volumes are measured by sampling a grid inside the mesh.
+pub(crate) fn decompose(vertices: &[Vertex], faces: &[Face], settings: &Decomposition) -> Vec<Geometry> {
+    let triangles = faces
+        .iter()
+        .filter(|f| f.is_valid(vertices))
+        .map(|f| f.triangle(vertices))
+        .collect::<Vec<Triangle>>();
+
+    let Some(bounds) = Bounds::from_points(vertices) else {
+        return Vec::new();
+    };
+
+    let grid = Grid::new(&triangles,&bounds,settings.resolution);
+    let volume = grid.cell.powi(3);
+    let all = Region { min: [0,0,0], max: grid.size };
+
+    // the hull of a region and how much of it is empty
+    let piece = |region: &Region| {
+        let bounds = grid.bounds(region);
+        let nearby = triangles
+            .iter()
+            .filter(|t| Bounds::from_points(&[t.p1,t.p2,t.p3]).is_some_and(|b| b.intersects(&bounds)))
+            .cloned()
+            .collect::<Vec<Triangle>>();
+        let hull = Geometry::hull(&points(&nearby,&grid,&bounds));
+        let empty = (hull.volume() - grid.count(region) as f64 * volume).max(0.0);
+        (hull,empty)
+    };
+
+    let mut pieces = Vec::new();
+    let mut open = grid.shrink(&all).map(|r| (r,0)).into_iter().collect::<Vec<_>>();
+    while let Some((region,depth)) = open.pop() {
+        let (hull,empty) = piece(&region);
+        let full = hull.volume() <= 0.0 || empty <= settings.concavity * hull.volume();
+        if full || depth >= settings.depth {
+            if !hull.is_empty() {
+                pieces.push(hull);
+            }
+            continue;
+        }
+
+        // a few evenly spaced cuts across each axis
+        let best = (0..3)
+            .flat_map(|axis| {
+                let (min,max) = (region.min[axis],region.max[axis]);
+                (1..4)
+                    .map(move |q| min + (max - min) * q / 4)
+                    .filter(move |at| *at > min && *at < max)
+                    .map(move |at| (axis,at))
+            })
+            .map(|(axis,at)| {
+                let (a,b) = region.split(axis,at);
+                let (a,b) = (grid.shrink(&a),grid.shrink(&b));
+                let cost = [a,b].iter().flatten().map(|r| piece(r).1).sum::<f64>();
+                (cost,a,b)
+            })
+            .min_by(|x,y| x.0.total_cmp(&y.0));
+
+        match best {
+            Some((_,a,b)) => open.extend([a,b].into_iter().flatten().map(|r| (r,depth + 1))),
+            None => pieces.push(hull),
+        }
+    }
+    pieces
+}
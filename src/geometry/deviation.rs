@@ -0,0 +1,64 @@
+use crate::geometry::*;
+use crate::geometry::nearest::Nearest;
+use crate::geometry::fit::rms;
+
+/// A summary of how far the vertices of a geometry are from a
+/// reference surface. Distances are positive outside the reference,
+/// like extra material, and negative inside it.
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct Deviation {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// the root mean square of the distances
+    pub rms: f64,
+    /// the number of vertices measured
+    pub count: usize,
+}
+
+impl Deviation {
+
+    // The summary of some distances, which is all zeros if
+    // there aren't any
+    pub fn new(values: &[f64]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        Self {
+            min: values.iter().copied().fold(f64::MAX,f64::min),
+            max: values.iter().copied().fold(f64::MIN,f64::max),
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            rms: rms(values.iter().copied()),
+            count: values.len(),
+        }
+    }
+
+    // The largest distance either side of the reference
+    pub fn worst(&self) -> f64 {
+        self.min.abs().max(self.max.abs())
+    }
+
+    // True if every vertex is within `tolerance` of the reference
+    pub fn within(&self, tolerance: f64) -> bool {
+        self.worst() <= tolerance
+    }
+
+}
+
+// The signed distance of every point from the surface of the reference,
+// using the normal of the closest face to tell inside from outside.
+// None if the reference doesn't have any faces.
+pub(crate) fn deviations(points: &[Vertex], reference: &Geometry) -> Option<Vec<f64>> {
+    let nearest = Nearest::new(reference)?;
+    Some(points
+        .iter()
+        .map(|p| {
+            let (closest,face) = nearest.closest(p);
+            let distance = p.distance(&closest);
+            match (*p - closest).dot(&reference.get(face).normal().vector()) < 0.0 {
+                true => -distance,
+                false => distance,
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,104 @@
+use std::ops::{Deref,DerefMut,Neg};
+
+use crate::geometry::{Vector,Transform,Matrix};
+
+/// A direction in space. Unlike a `Vector`, which is treated as a
+/// point (w = 1), a direction has w = 0 and so isn't moved by the
+/// translation part of a matrix.
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct Direction(Vector);
+
+pub type Normal = Direction;
+
+impl Direction {
+
+    pub const fn new(x: f64, y: f64, z: f64) -> Self {
+        Self(Vector::new(x,y,z))
+    }
+
+    pub fn vector(&self) -> Vector {
+        self.0
+    }
+
+    pub fn normalize(&self) -> Self {
+        Self(self.0.normalize())
+    }
+
+    // The angle between a vector and the line this direction runs
+    // along, so it's never more than PI/2
+    pub fn axis_angle(&self, other: &Vector) -> f64 {
+        let angle = self.0.angle(other);
+        angle.min(std::f64::consts::PI - angle)
+    }
+
+}
+
+impl From<Vector> for Direction {
+    fn from(v: Vector) -> Self {
+        Self(v)
+    }
+}
+
+impl From<Direction> for Vector {
+    fn from(d: Direction) -> Self {
+        d.0
+    }
+}
+
+impl Deref for Direction {
+    type Target = Vector;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Direction {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Neg for Direction {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self(self.0 * -1.0)
+    }
+}
+
+impl Transform for Direction {
+    fn transform(&mut self, matrix: &Matrix) {
+        self.0.transform_direction(matrix);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_direction_ignores_translation() {
+        let mut point = Vector::new(0.0,0.0,1.0);
+        let mut direction = Direction::new(0.0,0.0,1.0);
+
+        let m = Matrix::translate(1.0,2.0,3.0) * Matrix::scale(2.0,2.0,2.0);
+        point.transform(&m);
+        direction.transform(&m);
+
+        assert_eq!(point,Vector::new(1.0,2.0,5.0));
+        assert_eq!(direction,Direction::new(0.0,0.0,2.0));
+    }
+
+    #[test]
+    fn test_direction_rotates() {
+        let mut direction = Direction::new(1.0,0.0,0.0);
+        direction.transform(&Matrix::rotate_z(std::f64::consts::FRAC_PI_2));
+
+        assert_relative_eq!(direction.x,0.0,epsilon = 1e-12);
+        assert_relative_eq!(direction.y,1.0,epsilon = 1e-12);
+        assert_eq!((-direction).y,-direction.y);
+    }
+
+}
@@ -1,15 +1,21 @@
 use std::convert::TryFrom;
 
-use crate::utilities;
 use crate::geometry::*;
 use crate::errors::Error;
 use crate::constant::{FACE_TAG,Index};
 
-#[derive(Default,Debug,Clone)]
+// A texture coordinate
+pub type Uv = (f64,f64);
+
+/// A triangle of vertex indices. Each corner can also refer to
+/// a normal and a texture coordinate stored on the geometry.
+#[derive(Default,Debug,Clone,PartialEq)]
 pub struct Face {
     pub a: Index,
     pub b: Index,
     pub c: Index,
+    pub normals: Option<[Index;3]>,
+    pub uvs: Option<[Index;3]>,
 }
 
 impl Face {
@@ -20,6 +26,8 @@ impl Face {
             a: a.saturating_sub(1),
             b: b.saturating_sub(1),
             c: c.saturating_sub(1),
+            normals: None,
+            uvs: None,
         }
     }
 
@@ -34,24 +42,75 @@ impl Face {
             a: a.into().saturating_sub(1),
             b: b.into().saturating_sub(1),
             c: c.into().saturating_sub(1),
+            normals: None,
+            uvs: None,
+        }
+    }
+
+    // Assumes that values given are 1-indexed
+    pub fn with_normals(mut self, [a,b,c]: [Index;3]) -> Self {
+        self.normals = Some([a.saturating_sub(1),b.saturating_sub(1),c.saturating_sub(1)]);
+        self
+    }
+
+    // Assumes that values given are 1-indexed
+    pub fn with_uvs(mut self, [a,b,c]: [Index;3]) -> Self {
+        self.uvs = Some([a.saturating_sub(1),b.saturating_sub(1),c.saturating_sub(1)]);
+        self
+    }
+
+    // Checks that the corner normals and uvs exist, if there are any
+    pub fn is_valid_corners(&self, normals: usize, uvs: usize) -> bool {
+        let valid = |v: Option<[Index;3]>, l: usize| v.is_none_or(|v| v.iter().all(|i| *i < l));
+        valid(self.normals,normals) && valid(self.uvs,uvs)
+    }
+
+    // Reverses the winding, keeping every corner's data with it
+    pub fn flip(&mut self) {
+        std::mem::swap(&mut self.b,&mut self.c);
+        if let Some(n) = self.normals.as_mut() {
+            n.swap(1,2);
+        }
+        if let Some(t) = self.uvs.as_mut() {
+            t.swap(1,2);
         }
     }
 
-    pub fn is_valid(&self, data: &Vec<Vertex>) -> bool {
+    // Maps every index through the given functions
+    pub fn remap<V,N,T>(&self, vertex: V, normal: N, uv: T) -> Face
+    where
+        V: Fn(Index) -> Index,
+        N: Fn(Index) -> Index,
+        T: Fn(Index) -> Index,
+    {
+        Face {
+            a: vertex(self.a),
+            b: vertex(self.b),
+            c: vertex(self.c),
+            normals: self.normals.map(|n| n.map(&normal)),
+            uvs: self.uvs.map(|t| t.map(&uv)),
+        }
+    }
+
+    pub fn is_valid(&self, data: &[Vertex]) -> bool {
         let l = data.len();
         self.a < l && 
         self.b < l &&
         self.c < l
     }
 
-    pub fn normal(&self, data: &Vec<Vertex>) -> Normal {
+    pub fn edges(&self) -> [(Index,Index);3] {
+        [(self.a,self.b),(self.b,self.c),(self.c,self.a)]
+    }
+
+    pub fn normal(&self, data: &[Vertex]) -> Normal {
         self.triangle(data).normal()
     }
 
-    pub fn triangle(&self, data: &Vec<Vertex>) -> Triangle {
-        let p1 = data[self.a].clone();
-        let p2 = data[self.b].clone();
-        let p3 = data[self.c].clone();
+    pub fn triangle(&self, data: &[Vertex]) -> Triangle {
+        let p1 = data[self.a];
+        let p2 = data[self.b];
+        let p3 = data[self.c];
         let indices = (self.a,self.b,self.c);
 
         Triangle {
@@ -62,13 +121,71 @@ impl Face {
         }
     }
 
+    // Reads an `f` line with three or more corners and fans it into
+    // triangles around the first corner. Corners can be `v`, `v/t`,
+    // `v//n` or `v/t/n`, but every corner must have the same form, and
+    // indices start at 1.
+    pub fn polygon(value: &str) -> Result<Vec<Face>,Error> {
+        let corners = value
+            .trim_start_matches([FACE_TAG,' '])
+            .split_whitespace()
+            .map(corner)
+            .collect::<Result<Vec<_>,Error>>()?;
+
+        let (first,rest) = corners.split_first().ok_or(Error::ParseError)?;
+        let form = |(_,t,n): &(usize,Option<usize>,Option<usize>)| (t.is_some(),n.is_some());
+        if rest.len() < 2 || rest.iter().any(|c| form(c) != form(first)) {
+            return Err(Error::ParseError);
+        }
+
+        Ok(rest.windows(2).map(|pair| {
+            let [(a,ta,na),(b,tb,nb),(c,tc,nc)] = [*first,pair[0],pair[1]];
+            let mut face = Face::new(a,b,c);
+            if let (Some(a),Some(b),Some(c)) = (ta,tb,tc) {
+                face = face.with_uvs([a,b,c]);
+            }
+            if let (Some(a),Some(b),Some(c)) = (na,nb,nc) {
+                face = face.with_normals([a,b,c]);
+            }
+            face
+        }).collect())
+    }
+
 }
 
 impl TryFrom<&str> for Face {
     type Error = Error;
 
+    // The first triangle of an `f` line, see `Face::polygon`
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        Ok(Face::from(utilities::extract::<usize>(FACE_TAG,value)?))
+        Face::polygon(value)?
+            .into_iter()
+            .next()
+            .ok_or(Error::ParseError)
+    }
+}
+
+// Reads one `v/t/n` corner, where `t` and `n` may be missing. Indices
+// start at 1, so a 0 is an error.
+fn corner(value: &str) -> Result<(usize,Option<usize>,Option<usize>),Error> {
+    let mut parts = value.split('/');
+    let index = |v: &str| match v.parse::<usize>() {
+        Ok(0) => Err(Error::ParseError),
+        Ok(i) => Ok(i),
+        Err(e) => Err(e.into()),
+    };
+    let optional = |v: Option<&str>| match v {
+        None | Some("") => Ok(None),
+        Some(v) => index(v).map(Some),
+    };
+
+    let vertex = index(parts.next().ok_or(Error::ParseError)?)?;
+    let uv = optional(parts.next())?;
+    let normal = optional(parts.next())?;
+
+    match parts.next() {
+        Some(_) => Err(Error::ParseError),
+        None => Ok((vertex,uv,normal)),
     }
 }
 
@@ -88,11 +205,19 @@ impl From<(usize,usize,usize)> for Face {
 
 impl From<Face> for String {
     fn from(v: Face) -> Self {
-        let a = v.a.saturating_add(1);
-        let b = v.b.saturating_add(1);
-        let c = v.c.saturating_add(1);
+        let corner = |i: usize| {
+            let vertex = [v.a,v.b,v.c][i].saturating_add(1);
+            let uv = v.uvs.map(|t| t[i].saturating_add(1));
+            let normal = v.normals.map(|n| n[i].saturating_add(1));
+            match (uv,normal) {
+                (None,None) => format!("{}",vertex),
+                (Some(t),None) => format!("{}/{}",vertex,t),
+                (None,Some(n)) => format!("{}//{}",vertex,n),
+                (Some(t),Some(n)) => format!("{}/{}/{}",vertex,t,n),
+            }
+        };
         format!("{} {} {} {}",
-            FACE_TAG, a, b, c
+            FACE_TAG, corner(0), corner(1), corner(2)
         )
     }
 }
@@ -101,6 +226,25 @@ impl From<Face> for String {
 mod tests {
 
     use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+
+        #[test]
+        fn test_face_parse_never_panics(data in "\\PC*") {
+            let _ = Face::try_from(data);
+        }
+
+        #[test]
+        fn test_face_string_round_trip(a in 1usize..1_000_000, b in 1usize..1_000_000, c in 1usize..1_000_000) {
+            let face = Face::new(a,b,c);
+            let result = Face::try_from(String::from(face.clone())).unwrap();
+            prop_assert_eq!(result.a,face.a);
+            prop_assert_eq!(result.b,face.b);
+            prop_assert_eq!(result.c,face.c);
+        }
+
+    }
 
     // TODO: add negative numbers check
 
@@ -141,4 +285,53 @@ mod tests {
         assert_eq!(normal.z,1.0);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_face_corners_from_string() {
+        let face = Face::try_from("f 1/4/7 2/5/8 3/6/9").unwrap();
+        assert_eq!(face.uvs,Some([3,4,5]));
+        assert_eq!(face.normals,Some([6,7,8]));
+
+        let face = Face::try_from("f 1//7 2//8 3//9").unwrap();
+        assert_eq!(face.uvs,None);
+        assert_eq!(face.normals,Some([6,7,8]));
+
+        let face = Face::try_from("f 1/4 2/5 3/6").unwrap();
+        assert_eq!(face.uvs,Some([3,4,5]));
+        assert_eq!(face.normals,None);
+
+        // corners have to agree
+        assert!(Face::try_from("f 1/4 2 3/6").is_err());
+        assert!(Face::try_from("f 1/4/7/1 2/5/8 3/6/9").is_err());
+        assert!(Face::try_from("f 1/x 2/5 3/6").is_err());
+    }
+
+    #[test]
+    fn test_face_polygon() {
+        let faces = Face::polygon("f 1/1 2/2 3/3 4/4 5/5").unwrap();
+        assert_eq!(faces,vec![
+            Face::new(1,2,3).with_uvs([1,2,3]),
+            Face::new(1,3,4).with_uvs([1,3,4]),
+            Face::new(1,4,5).with_uvs([1,4,5]),
+        ]);
+
+        assert!(Face::polygon("f 1 2").is_err());
+        assert!(Face::polygon("f 1 2 3 4/4").is_err());
+        assert!(Face::polygon("f 0 1 2").is_err());
+        assert!(Face::polygon("f 1//0 2//1 3//1").is_err());
+    }
+
+    #[test]
+    fn test_face_corners_round_trip() {
+        for d in ["f 1 2 3","f 1/4 2/5 3/6","f 1//7 2//8 3//9","f 1/4/7 2/5/8 3/6/9"] {
+            assert_eq!(String::from(Face::try_from(d).unwrap()),d);
+        }
+    }
+
+    #[test]
+    fn test_face_flip() {
+        let mut face = Face::new(1,2,3).with_normals([4,5,6]).with_uvs([7,8,9]);
+        face.flip();
+        assert_eq!(String::from(face),"f 1/7/4 3/9/6 2/8/5");
+    }
+
+}
@@ -0,0 +1,592 @@
+use std::f64::consts::TAU;
+
+use crate::geometry::*;
+
+// the number of sides on the mesh of a fitted cylinder
+const SEGMENTS: usize = 32;
+
+// the most steps taken to refine a fit
+const ITERATIONS: usize = 200;
+
+/// A plane fitted through points, with the root mean square
+/// distance of the points from it
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct PlaneFit {
+    pub plane: Plane,
+    /// the middle of the points, which is on the plane
+    pub center: Vertex,
+    pub residual: f64,
+}
+
+/// A line fitted through points, with the root mean square
+/// distance of the points from it
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct LineFit {
+    /// the middle of the points, which is on the line
+    pub center: Vertex,
+    pub direction: Direction,
+    /// the distance between the furthest points along the line
+    pub length: f64,
+    pub residual: f64,
+}
+
+/// A sphere fitted to points on its surface, with the root mean
+/// square distance of the points from that surface
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct SphereFit {
+    pub center: Vertex,
+    pub radius: f64,
+    pub residual: f64,
+}
+
+/// A box fitted around points along the directions they spread
+/// out in the most, with the root mean square distance of the
+/// points from its surface
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct BoxFit {
+    pub center: Vertex,
+    /// the directions of the sides, longest first
+    pub axes: [Direction;3],
+    /// the length of the box along each axis
+    pub size: Vector,
+    pub residual: f64,
+}
+
+/// A cylinder fitted to points on its curved side, with the root
+/// mean square distance of the points from that side
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct CylinderFit {
+    /// the middle of the axis between the ends
+    pub center: Vertex,
+    pub axis: Direction,
+    pub radius: f64,
+    pub length: f64,
+    pub residual: f64,
+}
+
+impl PlaneFit {
+
+    // The plane that the points are closest to on average, or
+    // None if there are fewer than three points
+    pub fn new(points: &[Vertex]) -> Option<Self> {
+        if points.len() < 3 {
+            return None;
+        }
+        let (center,_,vectors) = spread(points)?;
+        let plane = Plane::from_point(&center,vectors[2].into());
+        Some(Self {
+            plane,
+            center,
+            residual: rms(points.iter().map(|p| plane.distance(p))),
+        })
+    }
+
+}
+
+impl LineFit {
+
+    // The line that the points are closest to on average, or None
+    // if there are fewer than two points
+    pub fn new(points: &[Vertex]) -> Option<Self> {
+        if points.len() < 2 {
+            return None;
+        }
+        let (center,_,vectors) = spread(points)?;
+        let heights = points.iter().map(|p| (*p - center).dot(&vectors[0]));
+        let (low,high) = heights.fold((f64::MAX,f64::MIN),|(l,h),d| (l.min(d),h.max(d)));
+
+        let mut fit = Self { center, direction: vectors[0].into(), length: high - low, residual: 0.0 };
+        fit.residual = rms(points.iter().map(|p| fit.distance(p)));
+        Some(fit)
+    }
+
+    // How far a point is from the line
+    pub fn distance(&self, point: &Vertex) -> f64 {
+        let offset = *point - self.center;
+        let along = offset.dot(&self.direction.vector());
+        (offset - self.direction.vector() * along).magnitude()
+    }
+
+}
+
+impl SphereFit {
+
+    // The sphere whose surface the points are closest to, or None
+    // if there are fewer than four points or they're all in a plane
+    pub fn new(points: &[Vertex]) -> Option<Self> {
+        if points.len() < 4 {
+            return None;
+        }
+        let (middle,_,_) = spread(points)?;
+        let offsets = points.iter().map(|p| *p - middle).collect::<Vec<Vector>>();
+        let squared = offsets.iter().map(|o| o.dot(o)).sum::<f64>() / points.len() as f64;
+
+        // the algebraic fit of |p - c|² = r² around the middle
+        let mut a = [[0.0;3];3];
+        let mut b = [0.0;3];
+        for offset in offsets.iter() {
+            let row = [offset.x,offset.y,offset.z];
+            let rhs = (offset.dot(offset) - squared) / 2.0;
+            for i in 0..3 {
+                for j in 0..3 {
+                    a[i][j] += row[i] * row[j];
+                }
+                b[i] += row[i] * rhs;
+            }
+        }
+
+        let [x,y,z] = solve(a,b)?;
+        let mut center = middle + Vector::new(x,y,z);
+
+        // then move towards the geometric fit, which noise doesn't
+        // pull off to one side
+        let count = points.len() as f64;
+        for _ in 0..ITERATIONS {
+            let radius = points.iter().map(|p| p.distance(&center)).sum::<f64>() / count;
+            let pull = points
+                .iter()
+                .filter(|p| p.distance(&center) > 0.0)
+                .fold(Vector::default(),|s,p| s + (center - *p).normalize());
+            let next = middle + pull * (radius / count);
+            let moved = next.distance(&center);
+            center = next;
+            if moved <= radius * 1e-12 {
+                break;
+            }
+        }
+
+        let radius = points.iter().map(|p| p.distance(&center)).sum::<f64>() / count;
+
+        let mut fit = Self { center, radius, residual: 0.0 };
+        fit.residual = rms(points.iter().map(|p| fit.distance(p)));
+        Some(fit)
+    }
+
+    // How far a point is from the surface of the sphere, which
+    // is negative inside it
+    pub fn distance(&self, point: &Vertex) -> f64 {
+        point.distance(&self.center) - self.radius
+    }
+
+}
+
+impl BoxFit {
+
+    // The box around the points, lined up with the directions
+    // they spread out in, or None if there aren't any points
+    pub fn new(points: &[Vertex]) -> Option<Self> {
+        let (middle,_,vectors) = spread(points)?;
+
+        let mut min = [f64::MAX;3];
+        let mut max = [f64::MIN;3];
+        for point in points {
+            let offset = *point - middle;
+            for axis in 0..3 {
+                let d = offset.dot(&vectors[axis]);
+                min[axis] = min[axis].min(d);
+                max[axis] = max[axis].max(d);
+            }
+        }
+
+        let center = (0..3).fold(middle,|c,a| c + vectors[a] * ((min[a] + max[a]) / 2.0));
+        let size = Vector::new(max[0] - min[0],max[1] - min[1],max[2] - min[2]);
+        let axes = vectors.map(Direction::from);
+
+        let mut fit = Self { center, axes, size, residual: 0.0 };
+        fit.residual = rms(points.iter().map(|p| fit.distance(p)));
+        Some(fit)
+    }
+
+    // How far a point is from the surface of the box
+    pub fn distance(&self, point: &Vertex) -> f64 {
+        let offset = *point - self.center;
+        let half = [self.size.x / 2.0,self.size.y / 2.0,self.size.z / 2.0];
+        let local = self.axes.map(|a| offset.dot(&a.vector()));
+
+        let outside = (0..3)
+            .map(|i| (local[i].abs() - half[i]).max(0.0))
+            .map(|d| d * d)
+            .sum::<f64>()
+            .sqrt();
+
+        match outside > 0.0 {
+            true => outside,
+            false => (0..3)
+                .map(|i| half[i] - local[i].abs())
+                .fold(f64::MAX,f64::min),
+        }
+    }
+
+    // A mesh of the box with its faces pointing out
+    pub fn geometry(&self) -> Geometry {
+        let [a,b,c] = self.axes.map(|a| a.vector());
+        let (x,y,z) = (a * (self.size.x / 2.0),b * (self.size.y / 2.0),c * (self.size.z / 2.0));
+
+        // flip one side if the axes are left handed
+        let z = match a.cross(&b).dot(&c) < 0.0 {
+            true => z * -1.0,
+            false => z,
+        };
+
+        let corners = [
+            self.center - x - y - z, self.center + x - y - z,
+            self.center + x + y - z, self.center - x + y - z,
+            self.center - x - y + z, self.center + x - y + z,
+            self.center + x + y + z, self.center - x + y + z,
+        ];
+
+        let values = corners.iter().flat_map(|v| [v.x,v.y,v.z]).collect();
+        Geometry::make(values,vec![
+            1,3,2, 1,4,3, 5,6,7, 5,7,8,
+            1,2,6, 1,6,5, 2,3,7, 2,7,6,
+            3,4,8, 3,8,7, 4,1,5, 4,5,8,
+        ])
+    }
+
+}
+
+impl CylinderFit {
+
+    // The cylinder whose side the points are closest to, starting
+    // from whichever direction the points spread out in makes the
+    // best axis. None if there are fewer than three points or
+    // they're in a line.
+    pub fn new(points: &[Vertex]) -> Option<Self> {
+        if points.len() < 3 {
+            return None;
+        }
+        let (_,_,vectors) = spread(points)?;
+        let mut best = vectors
+            .iter()
+            .filter_map(|axis| Self::around(points,*axis))
+            .min_by(|a,b| a.residual.total_cmp(&b.residual))?;
+
+        // then tilt the axis a little at a time while it helps
+        let mut step = 0.1;
+        while step > 1e-9 {
+            let axis = best.axis.vector();
+            let (u,v) = basis(&axis);
+            let better = [u,u * -1.0,v,v * -1.0]
+                .into_iter()
+                .filter_map(|d| Self::around(points,axis + d * step))
+                .filter(|f| f.residual < best.residual)
+                .min_by(|a,b| a.residual.total_cmp(&b.residual));
+            match better {
+                Some(fit) => best = fit,
+                None => step /= 2.0,
+            }
+        }
+        Some(best)
+    }
+
+    // The cylinder with a known axis direction, from the circle that
+    // best fits the points seen down the axis
+    pub fn around(points: &[Vertex], axis: Vector) -> Option<Self> {
+        let axis = axis.normalize();
+        let (u,v) = basis(&axis);
+        let flat = points
+            .iter()
+            .map(|p| (p.dot(&u),p.dot(&v)))
+            .collect::<Vec<(f64,f64)>>();
+
+        let (cx,cy,radius) = circle(&flat)?;
+        let heights = points.iter().map(|p| p.dot(&axis));
+        let (low,high) = heights.fold((f64::MAX,f64::MIN),|(l,h),d| (l.min(d),h.max(d)));
+        let center = u * cx + v * cy + axis * ((low + high) / 2.0);
+
+        let mut fit = Self { center, axis: axis.into(), radius, length: high - low, residual: 0.0 };
+        fit.residual = rms(points.iter().map(|p| fit.distance(p)));
+        Some(fit)
+    }
+
+    // How far a point is from the curved side of the cylinder
+    pub fn distance(&self, point: &Vertex) -> f64 {
+        let offset = *point - self.center;
+        let along = offset.dot(&self.axis.vector());
+        (offset - self.axis.vector() * along).magnitude() - self.radius
+    }
+
+    // A closed mesh of the cylinder with its faces pointing out
+    pub fn geometry(&self) -> Geometry {
+        let axis = self.axis.vector();
+        let (u,v) = basis(&axis);
+        let half = axis * (self.length / 2.0);
+
+        let mut vertices = Vec::with_capacity(SEGMENTS * 2 + 2);
+        for end in [self.center - half,self.center + half] {
+            for i in 0..SEGMENTS {
+                let angle = TAU * i as f64 / SEGMENTS as f64;
+                vertices.push(end + u * (self.radius * angle.cos()) + v * (self.radius * angle.sin()));
+            }
+        }
+        vertices.push(self.center - half);
+        vertices.push(self.center + half);
+
+        // one based, going around counter-clockwise seen from the top
+        let (bottom,top) = (SEGMENTS * 2 + 1,SEGMENTS * 2 + 2);
+        let mut indices = Vec::with_capacity(SEGMENTS * 12);
+        for i in 0..SEGMENTS {
+            let j = (i + 1) % SEGMENTS;
+            let (a,b,c,d) = (i + 1,j + 1,SEGMENTS + i + 1,SEGMENTS + j + 1);
+            indices.extend([a,b,d, a,d,c, bottom,b,a, top,c,d]);
+        }
+
+        let values = vertices.iter().flat_map(|p| [p.x,p.y,p.z]).collect();
+        Geometry::make(values,indices)
+    }
+
+}
+
+// The middle of the points and how far they spread out along each
+// of three directions at right angles, largest first, from the
+// eigenvectors of their covariance
+pub(crate) fn spread(points: &[Vertex]) -> Option<(Vertex,[f64;3],[Vector;3])> {
+    if points.is_empty() {
+        return None;
+    }
+    let center = points.iter().fold(Vertex::default(),|s,p| s + *p) * (1.0 / points.len() as f64);
+
+    let mut covariance = [[0.0;3];3];
+    for point in points {
+        let d = *point - center;
+        let d = [d.x,d.y,d.z];
+        for i in 0..3 {
+            for j in 0..3 {
+                covariance[i][j] += d[i] * d[j];
+            }
+        }
+    }
+
+    let (values,vectors) = eigen(covariance);
+    Some((center,values,vectors))
+}
+
+// The eigenvalues and eigenvectors of a symmetric matrix from
+// Jacobi rotations, sorted from largest to smallest value
+pub(crate) fn eigen(mut m: [[f64;3];3]) -> ([f64;3],[Vector;3]) {
+    let mut v = [[1.0,0.0,0.0],[0.0,1.0,0.0],[0.0,0.0,1.0]];
+    for _ in 0..50 {
+        let off = m[0][1].abs() + m[0][2].abs() + m[1][2].abs();
+        if off <= f64::EPSILON * (m[0][0].abs() + m[1][1].abs() + m[2][2].abs()) {
+            break;
+        }
+        for (p,q) in [(0,1),(0,2),(1,2)] {
+            if m[p][q] == 0.0 {
+                continue;
+            }
+            let theta = (m[q][q] - m[p][p]) / (2.0 * m[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let t = if theta == 0.0 { 1.0 } else { t };
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+
+            for row in m.iter_mut() {
+                let (a,b) = (row[p],row[q]);
+                row[p] = c * a - s * b;
+                row[q] = s * a + c * b;
+            }
+            let (a,b) = (m[p],m[q]);
+            m[p] = [0,1,2].map(|k| c * a[k] - s * b[k]);
+            m[q] = [0,1,2].map(|k| s * a[k] + c * b[k]);
+            for row in v.iter_mut() {
+                let (a,b) = (row[p],row[q]);
+                row[p] = c * a - s * b;
+                row[q] = s * a + c * b;
+            }
+        }
+    }
+
+    let mut order = [0,1,2];
+    order.sort_by(|a,b| m[*b][*b].total_cmp(&m[*a][*a]));
+    (order.map(|i| m[i][i]),order.map(|i| Vector::new(v[0][i],v[1][i],v[2][i]).normalize()))
+}
+
+// Two directions at right angles to each other and to `axis`
+pub(crate) fn basis(axis: &Vector) -> (Vector,Vector) {
+    let other = match axis.x.abs() < 0.9 {
+        true => Vector::new(1.0,0.0,0.0),
+        false => Vector::new(0.0,1.0,0.0),
+    };
+    let u = axis.cross(&other).normalize();
+    (u,axis.cross(&u).normalize())
+}
+
+// The circle closest to points in a plane, from the algebraic fit
+// of x² + y² + Dx + Ey + F = 0, as its centre and radius
+pub(crate) fn circle(points: &[(f64,f64)]) -> Option<(f64,f64,f64)> {
+    let mut a = [[0.0;3];3];
+    let mut b = [0.0;3];
+    for (x,y) in points {
+        let row = [*x,*y,1.0];
+        let rhs = -(x * x + y * y);
+        for i in 0..3 {
+            for j in 0..3 {
+                a[i][j] += row[i] * row[j];
+            }
+            b[i] += row[i] * rhs;
+        }
+    }
+
+    let [d,e,f] = solve(a,b)?;
+    let (cx,cy) = (-d / 2.0,-e / 2.0);
+    let squared = cx * cx + cy * cy - f;
+    match squared > 0.0 {
+        true => Some((cx,cy,squared.sqrt())),
+        false => None,
+    }
+}
+
+// Solves a small square system with Gaussian elimination, or
+// None if it's singular
+pub(crate) fn solve<const N: usize>(mut a: [[f64;N];N], mut b: [f64;N]) -> Option<[f64;N]> {
+    let scale = a.iter().flatten().fold(0.0,|m: f64,v| m.max(v.abs()));
+    for col in 0..N {
+        let pivot = (col..N).max_by(|i,j| a[*i][col].abs().total_cmp(&a[*j][col].abs()))?;
+        if a[pivot][col].abs() <= scale * 1e-12 {
+            return None;
+        }
+        a.swap(col,pivot);
+        b.swap(col,pivot);
+        for row in col + 1..N {
+            let k = a[row][col] / a[col][col];
+            let top = a[col];
+            for (value,above) in a[row].iter_mut().zip(top).skip(col) {
+                *value -= k * above;
+            }
+            b[row] -= k * b[col];
+        }
+    }
+
+    let mut x = [0.0;N];
+    for row in (0..N).rev() {
+        let sum = (row + 1..N).map(|c| a[row][c] * x[c]).sum::<f64>();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    Some(x)
+}
+
+// The root mean square of some distances
+pub(crate) fn rms<I: Iterator<Item = f64>>(values: I) -> f64 {
+    let (sum,count) = values.fold((0.0,0),|(s,n),v| (s + v * v,n + 1));
+    match count {
+        0 => 0.0,
+        n => (sum / n as f64).sqrt(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // points on a tilted plane with a little noise
+    fn noisy(count: usize) -> Vec<Vertex> {
+        (0..count)
+            .map(|i| {
+                let (x,y) = ((i % 10) as f64,(i / 10) as f64);
+                let noise = if i % 2 == 0 { 0.01 } else { -0.01 };
+                Vertex::new(x,y,0.5 * x + 2.0 + noise)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fit_plane() {
+        let fit = PlaneFit::new(&noisy(100)).unwrap();
+        let normal = fit.plane.normal.vector();
+        let expected = Vector::new(-0.5,0.0,1.0).normalize();
+        assert_relative_eq!(normal.dot(&expected).abs(),1.0,epsilon = 1e-6);
+        assert_relative_eq!(fit.residual,0.01 * expected.z,epsilon = 1e-3);
+        assert!(PlaneFit::new(&noisy(2)).is_none());
+    }
+
+    #[test]
+    fn test_fit_line() {
+        let direction = Vector::new(1.0,2.0,-1.0).normalize();
+        let points = (0..11)
+            .map(|i| Vertex::new(1.0,0.0,0.0) + direction * (i as f64 * 0.1))
+            .collect::<Vec<Vertex>>();
+
+        let fit = LineFit::new(&points).unwrap();
+        assert_relative_eq!(fit.direction.vector().dot(&direction).abs(),1.0,epsilon = 1e-9);
+        assert_relative_eq!(fit.length,1.0,epsilon = 1e-9);
+        assert_relative_eq!(fit.distance(&Vertex::new(1.0,0.0,0.0)),0.0,epsilon = 1e-9);
+        assert!(fit.residual < 1e-9);
+        assert!(LineFit::new(&points[..1]).is_none());
+    }
+
+    #[test]
+    fn test_fit_sphere() {
+        // points spread over the top of a sphere, with some rings a
+        // little inside and outside it
+        let center = Vertex::new(1.0,-2.0,0.5);
+        let sphere = |noise: f64| (0..60)
+            .map(|i| {
+                let (a,b) = (i as f64 * 0.37,(i % 6) as f64 * 0.25);
+                let radius = if (i / 6) % 2 == 0 { 2.0 + noise } else { 2.0 - noise };
+                center + Vector::new(b.cos() * a.cos(),b.cos() * a.sin(),b.sin()) * radius
+            })
+            .collect::<Vec<Vertex>>();
+
+        let fit = SphereFit::new(&sphere(0.0)).unwrap();
+        assert_relative_eq!(fit.center.distance(&center),0.0,epsilon = 1e-9);
+        assert_relative_eq!(fit.radius,2.0,epsilon = 1e-9);
+        assert_relative_eq!(fit.distance(&center),-fit.radius,epsilon = 1e-9);
+
+        let fit = SphereFit::new(&sphere(0.01)).unwrap();
+        assert_relative_eq!(fit.radius,2.0,epsilon = 1e-2);
+        assert_relative_eq!(fit.residual,0.01,epsilon = 1e-3);
+
+        // flat points don't make a sphere
+        assert!(SphereFit::new(&noisy(20).iter().map(|p| Vertex::new(p.x,p.y,0.0)).collect::<Vec<Vertex>>()).is_none());
+    }
+
+    #[test]
+    fn test_fit_box() {
+        let mut points = Vec::new();
+        for i in 0..=4 {
+            for j in 0..=2 {
+                for k in 0..=1 {
+                    points.push(Vertex::new(i as f64,j as f64 * 0.5,k as f64 * 0.25));
+                }
+            }
+        }
+        let rotation = Matrix::rotate_z(0.3);
+        points.iter_mut().for_each(|p| p.transform(&rotation));
+
+        let fit = BoxFit::new(&points).unwrap();
+        assert_relative_eq!(fit.size.x,4.0,epsilon = 1e-9);
+        assert_relative_eq!(fit.size.y,1.0,epsilon = 1e-9);
+        assert_relative_eq!(fit.size.z,0.25,epsilon = 1e-9);
+
+        let geometry = fit.geometry();
+        assert_relative_eq!(geometry.volume(),1.0,epsilon = 1e-9);
+        assert_relative_eq!(fit.distance(&fit.center),0.125,epsilon = 1e-9);
+        assert!(BoxFit::new(&[]).is_none());
+    }
+
+    #[test]
+    fn test_fit_cylinder() {
+        // rings of points around a tilted axis
+        let axis = Vector::new(1.0,1.0,0.0).normalize();
+        let (u,v) = basis(&axis);
+        let points = (0..200)
+            .map(|i| {
+                let angle = i as f64 * 0.7;
+                let height = (i % 20) as f64 * 0.1;
+                Vertex::new(1.0,2.0,3.0) + axis * height + u * (0.5 * angle.cos()) + v * (0.5 * angle.sin())
+            })
+            .collect::<Vec<Vertex>>();
+
+        let fit = CylinderFit::new(&points).unwrap();
+        assert_relative_eq!(fit.radius,0.5,epsilon = 1e-9);
+        assert_relative_eq!(fit.length,1.9,epsilon = 1e-9);
+        assert_relative_eq!(fit.axis.vector().dot(&axis).abs(),1.0,epsilon = 1e-9);
+        assert!(fit.residual < 1e-9);
+
+        let geometry = fit.geometry();
+        let expected = 0.5 * 0.5 * (TAU / SEGMENTS as f64).sin() * SEGMENTS as f64 / 2.0 * 1.9;
+        assert_relative_eq!(geometry.volume(),expected,epsilon = 1e-9);
+    }
+
+}
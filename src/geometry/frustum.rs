@@ -0,0 +1,104 @@
+use crate::geometry::*;
+
+/// The space a camera can see, as six planes facing inwards. Anything
+/// in front of every plane is inside.
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct Frustum {
+    planes: [Plane;6],
+}
+
+impl Frustum {
+
+    // The frustum of a camera with view and projection matrices
+    // like the ones the renderer takes
+    pub fn new(view: &Matrix, projection: &Matrix) -> Self {
+        Self::from_matrix(&(*projection * *view))
+    }
+
+    // The frustum of a matrix that takes points into clip space,
+    // where everything visible is between -1 and 1 on every axis
+    pub fn from_matrix(matrix: &Matrix) -> Self {
+        let m = matrix.unpack();
+        let row = |i: usize| [m[i * 4],m[i * 4 + 1],m[i * 4 + 2],m[i * 4 + 3]];
+        let (x,y,z,w) = (row(0),row(1),row(2),row(3));
+
+        // each plane is where the clip space value on one axis
+        // equals w, either side
+        let plane = |sign: f64, r: [f64;4]| {
+            let [a,b,c,d] = [0,1,2,3].map(|i| w[i] + sign * r[i]);
+            let length = Vector::new(a,b,c).magnitude();
+            Plane::new(Normal::new(a / length,b / length,c / length),-d / length)
+        };
+
+        Self {
+            planes: [
+                plane(1.0,x),
+                plane(-1.0,x),
+                plane(1.0,y),
+                plane(-1.0,y),
+                plane(1.0,z),
+                plane(-1.0,z),
+            ],
+        }
+    }
+
+    // The left, right, bottom, top, near and far planes
+    pub fn planes(&self) -> &[Plane;6] {
+        &self.planes
+    }
+
+    pub fn contains(&self, point: &Vertex) -> bool {
+        self.planes
+            .iter()
+            .all(|p| p.distance(point) >= 0.0)
+    }
+
+    // True unless the box is completely behind one of the planes.
+    // Boxes near the corners of the frustum can be outside it but
+    // still count, which is fine for deciding what to draw.
+    pub fn intersects(&self, bounds: &Bounds) -> bool {
+        self.planes.iter().all(|plane| {
+            let n = plane.normal;
+            let furthest = Vertex::new(
+                if n.x >= 0.0 { bounds.max.x } else { bounds.min.x },
+                if n.y >= 0.0 { bounds.max.y } else { bounds.min.y },
+                if n.z >= 0.0 { bounds.max.z } else { bounds.min.z });
+            plane.distance(&furthest) >= 0.0
+        })
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_frustum_perspective() {
+        let view = Matrix::look_at(
+            Vertex::new(0.0,0.0,5.0),
+            Vertex::new(0.0,0.0,0.0),
+            Vector::new(0.0,1.0,0.0));
+        let projection = Matrix::perspective(std::f64::consts::FRAC_PI_2,1.0,1.0,10.0);
+        let frustum = Frustum::new(&view,&projection);
+
+        assert!(frustum.contains(&Vertex::new(0.0,0.0,0.0)));
+        assert!(frustum.contains(&Vertex::new(4.9,0.0,0.0)));
+        assert!(!frustum.contains(&Vertex::new(5.1,0.0,0.0)));
+        assert!(!frustum.contains(&Vertex::new(0.0,0.0,4.5)));
+        assert!(!frustum.contains(&Vertex::new(0.0,0.0,-5.5)));
+
+        // the near plane faces away from the camera
+        assert_relative_eq!(frustum.planes()[4].normal.z,-1.0,epsilon = 1e-9);
+        assert_relative_eq!(frustum.planes()[4].offset,-4.0,epsilon = 1e-9);
+
+        let inside = Bounds::new(Vertex::new(-1.0,-1.0,-1.0),Vertex::new(1.0,1.0,1.0));
+        let across = Bounds::new(Vertex::new(4.0,-1.0,-1.0),Vertex::new(8.0,1.0,1.0));
+        let behind = Bounds::new(Vertex::new(-1.0,-1.0,6.0),Vertex::new(1.0,1.0,8.0));
+        assert!(frustum.intersects(&inside));
+        assert!(frustum.intersects(&across));
+        assert!(!frustum.intersects(&behind));
+    }
+
+}
@@ -0,0 +1,113 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::geometry::*;
+use crate::geometry::nearest::Nearest;
+use crate::constant::Index;
+
+// A vertex waiting to be visited, ordered so the heap gives
+// back the closest first
+#[derive(Debug,Copy,Clone,PartialEq)]
+struct Step(f64,Index);
+
+// The distance along the edges from a point on the surface to every
+// vertex, with the vertex before each on the way there
+pub(crate) struct Geodesic {
+    start: Vertex,
+    face: Index,
+    distances: Vec<f64>,
+    previous: Vec<Option<Index>>,
+    nearest: Nearest,
+}
+
+impl Eq for Step {}
+
+impl Ord for Step {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.total_cmp(&self.0).then(other.1.cmp(&self.1))
+    }
+}
+
+impl PartialOrd for Step {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Geodesic {
+
+    // Searches outwards from the point on the surface closest to
+    // `from`, crossing the face it's on to reach the first vertices.
+    // None if the geometry doesn't have any faces.
+    pub(crate) fn new(geometry: &Geometry, from: &Vertex) -> Option<Self> {
+        let nearest = Nearest::new(geometry)?;
+        let vertices = geometry.vertices();
+        let (start,face) = nearest.closest(from);
+
+        let mut neighbours = vec![Vec::new();vertices.len()];
+        for (a,b) in geometry.edges() {
+            let length = vertices[a].distance(&vertices[b]);
+            neighbours[a].push((b,length));
+            neighbours[b].push((a,length));
+        }
+
+        let mut distances = vec![f64::INFINITY;vertices.len()];
+        let mut previous = vec![None;vertices.len()];
+        let mut heap = BinaryHeap::new();
+
+        let corners = &geometry.faces()[face];
+        for index in [corners.a,corners.b,corners.c] {
+            distances[index] = start.distance(&vertices[index]);
+            heap.push(Step(distances[index],index));
+        }
+
+        while let Some(Step(distance,index)) = heap.pop() {
+            if distance > distances[index] {
+                continue;
+            }
+            for &(next,length) in neighbours[index].iter() {
+                let total = distance + length;
+                if total < distances[next] {
+                    distances[next] = total;
+                    previous[next] = Some(index);
+                    heap.push(Step(total,next));
+                }
+            }
+        }
+
+        Some(Self { start, face, distances, previous, nearest })
+    }
+
+    pub(crate) fn distances(self) -> Vec<f64> {
+        self.distances
+    }
+
+    // The shortest way to the point on the surface closest to `to`,
+    // crossing the last face straight to it. None if it isn't
+    // connected to the start.
+    pub(crate) fn path(&self, geometry: &Geometry, to: &Vertex) -> Option<Vec<Vertex>> {
+        let vertices = geometry.vertices();
+        let (end,face) = self.nearest.closest(to);
+        if face == self.face {
+            return Some(vec![self.start,end]);
+        }
+
+        let corners = &geometry.faces()[face];
+        let (_,last) = [corners.a,corners.b,corners.c]
+            .into_iter()
+            .map(|i| (self.distances[i] + vertices[i].distance(&end),i))
+            .filter(|(d,_)| d.is_finite())
+            .min_by(|a,b| a.0.total_cmp(&b.0))?;
+
+        let mut points = vec![end];
+        let mut current = Some(last);
+        while let Some(index) = current {
+            points.push(vertices[index]);
+            current = self.previous[index];
+        }
+        points.push(self.start);
+        points.reverse();
+        Some(points)
+    }
+
+}
@@ -1,13 +1,96 @@
 use std::convert::TryFrom;
+use std::collections::{BTreeMap,BTreeSet};
+use std::sync::atomic::{AtomicU64,Ordering};
 use itertools::Itertools;
 
 use crate::errors::Error;
 use crate::geometry::*;
+use crate::geometry::remesh;
+use crate::geometry::hull;
+use crate::geometry::decompose;
+use crate::geometry::align;
+use crate::geometry::deviation;
+use crate::geometry::congruence;
+use crate::geometry::support;
+use crate::geometry::lattice;
+use crate::geometry::boolean::{self,Operation};
+use crate::geometry::symmetry;
+use crate::geometry::curvature;
+use crate::geometry::geodesic::Geodesic;
+use crate::utilities;
+use crate::constant::{Index,TOLERANCE,VERTEX_TAG,FACE_TAG,NORMAL_TAG,UV_TAG,VERTEX_GROUP_TAG,CHANNEL_TAG,VERTEX_NORMAL_TAG,OBJECT_TAG,GROUP_TAG,ATTRIBUTE_TAG,FEATURE_TAG,SUPPRESS_TAG,ROLLBACK_TAG,CONNECTION_TAG,METADATA_TAG};
+
+// Source of revision numbers shared by every geometry, so that a
+// revision identifies one particular state of one geometry.
+static REVISION: AtomicU64 = AtomicU64::new(1);
 
 #[derive(Default,Debug,Clone)]
 pub struct Geometry {
     vertices: Vec<Vertex>,
     faces: Vec<Face>,
+    normals: Vec<Normal>,
+    corner_normals: Vec<Normal>,
+    uvs: Vec<Uv>,
+    groups: BTreeMap<String,Vec<Index>>,
+    channels: BTreeMap<String,Vec<f64>>,
+    changes: Changes,
+    revision: u64,
+}
+
+/// How to treat lines that aren't understood when parsing text
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq)]
+pub enum ParseMode {
+    /// skip anything that isn't valid data
+    #[default]
+    Lenient,
+    /// fail on the first line that isn't valid data, a comment,
+    /// an object name or blank
+    Strict,
+}
+
+/// The index ranges that data was appended at
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Appended {
+    pub vertices: (Index,Index),
+    pub faces: (Index,Index),
+}
+
+/// Settings for Laplacian smoothing
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Smoothing {
+    iterations: usize,
+    lambda: f64,
+    boundary: bool,
+    groups: Vec<String>,
+}
+
+impl Smoothing {
+
+    pub fn new(iterations: usize, lambda: f64) -> Self {
+        Self {
+            iterations,
+            lambda,
+            boundary: false,
+            groups: Vec::new(),
+        }
+    }
+
+    // Keeps vertices on open edges where they are
+    pub fn with_pinned_boundary(mut self, value: bool) -> Self {
+        self.boundary = value;
+        self
+    }
+
+    // Keeps the vertices of the named group where they are
+    pub fn with_pinned_group<T: Into<String>>(mut self, name: T) -> Self {
+        self.groups.push(name.into());
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
 }
 
 impl Geometry {
@@ -26,17 +109,225 @@ impl Geometry {
             .map(|k| Face::new(k[0],k[1],k[2]) )
             .collect();
 
-        Self::new(vertices,faces)
+        let mut geometry = Self::new(vertices,faces);
+        geometry.touch();
+        geometry
+    }
+
+    // A closed box with outward facing triangles
+    pub fn from_bounds(bounds: &Bounds) -> Self {
+        let mut geometry = Self::new(bounds.corners().to_vec(),BOX_FACES.to_vec());
+        geometry.touch();
+        geometry
     }
 
     pub const fn new(vertices: Vec<Vertex>, faces: Vec<Face>) -> Self {
-        Self { vertices, faces }
+        Self { 
+            vertices, 
+            faces, 
+            normals: Vec::new(),
+            corner_normals: Vec::new(),
+            uvs: Vec::new(),
+            groups: BTreeMap::new(),
+            channels: BTreeMap::new(),
+            changes: Changes::new(),
+            revision: 0,
+        }
+    }
+
+    // A number that changes whenever the geometry is modified. Zero
+    // means the geometry hasn't been assigned a revision yet.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn touch(&mut self) {
+        self.revision = REVISION.fetch_add(1,Ordering::Relaxed);
     }
 
     pub fn size(&self) -> usize {
         self.faces.len()
     }
 
+    // Reads `v`, `vn`, `vt` and `f` lines, `vs` normals at vertices,
+    // and `vg` groups and `vc` channels of vertices. Faces with more
+    // than three corners are split into triangles. Blank lines, `#`
+    // comments (including at the end of a line), `o`/`g` names and the
+    // lines a part is written with, like `a` attributes and features,
+    // are allowed in either mode, but are not part of the geometry.
+    pub fn parse(text: &str, mode: ParseMode) -> Result<Self,Error> {
+        span!("geometry.parse", bytes = text.len());
+        let mut geometry = Geometry::default();
+
+        // normals, groups and channels are added once every vertex
+        // is read
+        let mut normals = Vec::new();
+        let mut groups = Vec::new();
+        let mut channels = Vec::new();
+
+        for (number,line) in text.lines().enumerate() {
+            let data = utilities::strip_comment(line);
+            let tag = data.split_whitespace().next();
+
+            let valid = match (tag,tag.map(|t| t.parse::<char>().ok())) {
+                (None,_) => true,
+                (Some(VERTEX_GROUP_TAG),_) => match Self::parse_named::<Index>(data) {
+                    Ok(group) => { groups.push((number,line,group)); true },
+                    Err(_) => false,
+                },
+                (Some(CHANNEL_TAG),_) => match Self::parse_named::<f64>(data) {
+                    Ok(channel) => { channels.push((number,line,channel)); true },
+                    Err(_) => false,
+                },
+                (Some(FEATURE_TAG | SUPPRESS_TAG | ROLLBACK_TAG | CONNECTION_TAG | METADATA_TAG),_) => true,
+                (Some(VERTEX_NORMAL_TAG),_) => match utilities::values::<f64>(data).as_deref() {
+                    Ok(&[x,y,z]) => { normals.push((number,line,Normal::new(x,y,z))); true },
+                    _ => false,
+                },
+                (Some(NORMAL_TAG),_) => match utilities::values::<f64>(data).as_deref() {
+                    Ok(&[x,y,z]) => { geometry.corner_normals.push(Normal::new(x,y,z)); true },
+                    Ok(&[x,y,z,..]) => { geometry.corner_normals.push(Normal::new(x,y,z)); false },
+                    _ => false,
+                },
+                (Some(UV_TAG),_) => match utilities::values::<f64>(data).as_deref() {
+                    Ok(&[u,v]) => { geometry.uvs.push((u,v)); true },
+                    Ok(&[u,v,..]) => { geometry.uvs.push((u,v)); false },
+                    _ => false,
+                },
+                (_,Some(Some(VERTEX_TAG))) => match Vertex::try_from(data) {
+                    Ok(v) => { geometry.vertices.push(v); utilities::fields(data) == 3 },
+                    Err(_) => false,
+                },
+                (_,Some(Some(FACE_TAG))) => match Face::polygon(data) {
+                    Ok(f) => { geometry.faces.extend(f); true },
+                    Err(_) => false,
+                },
+                (_,Some(Some(OBJECT_TAG | GROUP_TAG | ATTRIBUTE_TAG))) => true,
+                _ => false,
+            };
+
+            if !valid && mode == ParseMode::Strict {
+                return Err(Error::InvalidLine { line: number + 1, text: line.into() });
+            }
+        }
+
+        if let Some((number,line,_)) = normals.first().copied() {
+            let normals = normals.into_iter().map(|(_,_,n)| n).collect();
+            if geometry.set_normals(normals).is_err() && mode == ParseMode::Strict {
+                return Err(Error::InvalidLine { line: number + 1, text: line.into() });
+            }
+        }
+        for (number,line,(name,indices)) in groups {
+            if geometry.add_group(name,indices).is_err() && mode == ParseMode::Strict {
+                return Err(Error::InvalidLine { line: number + 1, text: line.into() });
+            }
+        }
+        for (number,line,(name,values)) in channels {
+            if geometry.set_channel(name,values).is_err() && mode == ParseMode::Strict {
+                return Err(Error::InvalidLine { line: number + 1, text: line.into() });
+            }
+        }
+
+        geometry.touch();
+        geometry.validated()
+    }
+
+    // Reads a `vg` or `vc` line, which is a name followed by values
+    fn parse_named<T: std::str::FromStr>(line: &str) -> Result<(String,Vec<T>),Error> {
+        let (_,rest) = utilities::token(line);
+        let (name,rest) = utilities::unquote(rest)?;
+        let values = rest
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<T>,_>>()
+            .or(Err(Error::ParseError))?;
+        Ok((name,values))
+    }
+
+    // Splits text into one geometry per `o` or `g` section. Face
+    // indices count vertices, normals and uvs across the whole text,
+    // so each section takes the vertices it declares or uses and the
+    // normals and uvs it uses, renumbered from zero. Unreadable lines
+    // and faces that reference missing data are skipped.
+    pub fn parse_multi(text: &str) -> Vec<(String,Geometry)> {
+        span!("geometry.parse_multi", bytes = text.len());
+        let mut vertices = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        let mut sections: Vec<(String,Vec<Index>,Vec<Face>)> = vec![(String::new(),Vec::new(),Vec::new())];
+
+        for line in text.lines().map(utilities::strip_comment) {
+            let mut tokens = line.split_whitespace();
+            let tag = tokens.next();
+            match (tag,tag.map(|t| t.parse::<char>().ok())) {
+                (Some(NORMAL_TAG),_) => if let Ok(&[x,y,z,..]) = utilities::values::<f64>(line).as_deref() {
+                    normals.push(Normal::new(x,y,z));
+                },
+                (Some(UV_TAG),_) => if let Ok(&[u,v,..]) = utilities::values::<f64>(line).as_deref() {
+                    uvs.push((u,v));
+                },
+                (_,Some(Some(OBJECT_TAG | GROUP_TAG))) => {
+                    let name = utilities::section_name(&line[1..]);
+                    sections.push((name,Vec::new(),Vec::new()));
+                },
+                (_,Some(Some(VERTEX_TAG))) => if let Ok(v) = Vertex::try_from(line) {
+                    if let Some((_,declared,_)) = sections.last_mut() {
+                        declared.push(vertices.len());
+                    }
+                    vertices.push(v);
+                },
+                (_,Some(Some(FACE_TAG))) => if let Ok(f) = Face::polygon(line) {
+                    if let Some((_,_,faces)) = sections.last_mut() {
+                        faces.extend(f);
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        // the sorted, unique indices that a set of faces refer to
+        let used = |faces: &[Face], get: fn(&Face) -> Option<[Index;3]>| {
+            let mut used = faces.iter().filter_map(get).flatten().collect::<Vec<Index>>();
+            used.sort_unstable();
+            used.dedup();
+            used
+        };
+
+        sections
+            .into_iter()
+            .filter(|(_,declared,faces)| !declared.is_empty() || !faces.is_empty())
+            .map(|(name,declared,faces)| {
+                let faces = faces
+                    .into_iter()
+                    .filter(|f| f.is_valid(&vertices))
+                    .filter(|f| f.is_valid_corners(normals.len(),uvs.len()))
+                    .collect::<Vec<Face>>();
+
+                let mut used_vertices = declared;
+                used_vertices.extend(faces.iter().flat_map(|f| [f.a,f.b,f.c]));
+                used_vertices.sort_unstable();
+                used_vertices.dedup();
+
+                let used_normals = used(&faces,|f| f.normals);
+                let used_uvs = used(&faces,|f| f.uvs);
+
+                let local = |used: &[Index], i: Index| used.binary_search(&i).unwrap_or_default();
+                let mut geometry = Geometry::new(
+                    used_vertices.iter().map(|i| vertices[*i]).collect(),
+                    faces.iter().map(|f| f.remap(
+                        |i| local(&used_vertices,i),
+                        |i| local(&used_normals,i),
+                        |i| local(&used_uvs,i),
+                    )).collect());
+
+                geometry.corner_normals = used_normals.iter().map(|i| normals[*i]).collect();
+                geometry.uvs = used_uvs.iter().map(|i| uvs[*i]).collect();
+                geometry.touch();
+                (name,geometry)
+            })
+            .collect()
+    }
+
     pub fn get(&self, i: usize) -> Triangle {
         let face = &self.faces[i];
         face.triangle(&self.vertices)
@@ -44,151 +335,2403 @@ impl Geometry {
 
     pub fn validated(self) -> Result<Self,Error> {
         for face in self.faces.iter() {
-            if !face.is_valid(&self.vertices) {
+            if !face.is_valid(&self.vertices) ||
+               !face.is_valid_corners(self.corner_normals.len(),self.uvs.len()) {
                 return Err(Error::ParseError);
             }
         }
         Ok(self)
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty() || self.faces.is_empty()
+    }
+
+    // The shortest distance from `point` to any face, or
+    // infinity if the geometry has no faces. Faces with indices
+    // past the last vertex are skipped.
+    pub fn distance(&self, point: &Vertex) -> f64 {
+        self.faces
+            .iter()
+            .filter(|f| f.is_valid(&self.vertices))
+            .map(|f| f.triangle(&self.vertices).distance(point))
+            .fold(f64::INFINITY,f64::min)
+    }
+
+    // True if `point` is inside a closed mesh, found by counting how
+    // many times a ray from it crosses the surface. Points on the
+    // surface aren't inside.
+    pub fn encloses(&self, point: &Vertex) -> bool {
+        let Some(bounds) = self.bounds() else {
+            return false;
+        };
+        if !bounds.contains(point) || self.distance(point) <= TOLERANCE {
+            return false;
+        }
+
+        // an uneven direction is unlikely to run along an edge
+        let reach = bounds.size().magnitude() * 2.0 + 1.0;
+        let end = *point + Vector::new(0.5772,0.6180,0.5320).normalize() * reach;
+        self.faces
+            .iter()
+            .filter(|f| f.is_valid(&self.vertices))
+            .filter(|f| f.triangle(&self.vertices).crossing(point,&end).is_some())
+            .count() % 2 == 1
+    }
+
+    // True if two closed meshes overlap, because an edge of one
+    // passes through a face of the other or one is inside the
+    // other. Meshes that only touch don't intersect.
+    pub fn intersects(&self, other: &Geometry) -> bool {
+        let (Some(a),Some(b)) = (self.bounds(),other.bounds()) else {
+            return false;
+        };
+        if !a.intersects(&b) {
+            return false;
+        }
+
+        let crosses = |edges: &Geometry, faces: &Geometry, limit: &Bounds| {
+            let triangles = faces.faces
+                .iter()
+                .filter(|f| f.is_valid(&faces.vertices))
+                .map(|f| f.triangle(&faces.vertices))
+                .filter(|t| Bounds::from_points(&[t.p1,t.p2,t.p3]).is_some_and(|b| b.intersects(limit)))
+                .collect::<Vec<Triangle>>();
+
+            edges.edges().into_iter().any(|(i,j)| {
+                let (p,q) = (edges.vertices[i],edges.vertices[j]);
+                triangles.iter().any(|t| t.crossing(&p,&q).is_some())
+            })
+        };
+
+        crosses(self,other,&a) ||
+        crosses(other,self,&b) ||
+        self.vertices.iter().any(|v| other.encloses(v)) ||
+        other.vertices.iter().any(|v| self.encloses(v))
+    }
+
     pub fn vertices(&self) -> &Vec<Vertex> {
         &self.vertices
     }
 
+    // Marks every vertex as changed because the caller may
+    // touch any of them. Use `alter` to report a narrower set.
     pub fn vertices_mut(&mut self) -> &mut Vec<Vertex> {
+        self.touch();
+        self.changes.mark_all(self.vertices.len());
         &mut self.vertices
     }
 
-}
+    pub fn faces(&self) -> &Vec<Face> {
+        &self.faces
+    }
 
-impl IntoIterator for Geometry {
-    type Item = Triangle;
-    type IntoIter = std::vec::IntoIter<Self::Item>;
+    // Every unique edge, as (low,high) vertex indices, mapped to
+    // the faces that use it
+    pub fn edge_faces(&self) -> BTreeMap<(Index,Index),Vec<Index>> {
+        let mut edges: BTreeMap<(Index,Index),Vec<Index>> = BTreeMap::new();
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.faces
+        for (index,face) in self.faces.iter().enumerate() {
+            for (a,b) in face.edges() {
+                edges.entry((a.min(b),a.max(b)))
+                    .or_default()
+                    .push(index);
+            }
+        }
+
+        edges
+    }
+
+    // Every unique edge in the geometry
+    pub fn edges(&self) -> Vec<(Index,Index)> {
+        self.edge_faces()
+            .into_keys()
+            .collect()
+    }
+
+    // Edges where the surface bends by more than `angle` radians,
+    // along with open and non-manifold edges.
+    pub fn feature_edges(&self, angle: f64) -> Vec<(Index,Index)> {
+        let normals = self.face_normals();
+        self.edge_faces()
             .into_iter()
-            .map(|f| f.triangle(&self.vertices))
-            .collect::<Vec<Triangle>>()
+            .filter(|(_,faces)| match faces.as_slice() {
+                [a,b] => normals[*a].angle(&normals[*b]) > angle,
+                _ => true,
+            })
+            .map(|(edge,_)| edge)
+            .collect()
+    }
+
+    // Edges that outline the geometry when seen from `eye`: those
+    // between a face pointing towards it and one pointing away, along
+    // with open edges.
+    pub fn silhouette_edges(&self, eye: &Vertex) -> Vec<(Index,Index)> {
+        let facing = self.faces
+            .iter()
+            .map(|f| match f.is_valid(&self.vertices) {
+                true => f.normal(&self.vertices).dot(&(*eye - self.vertices[f.a])) > 0.0,
+                false => false,
+            })
+            .collect::<Vec<bool>>();
+
+        self.edge_faces()
             .into_iter()
+            .filter(|(_,faces)| match faces.as_slice() {
+                [a] => facing[*a],
+                [a,b] => facing[*a] != facing[*b],
+                _ => faces.iter().any(|f| facing[*f]),
+            })
+            .map(|(edge,_)| edge)
+            .collect()
     }
-}
 
-impl TryFrom<String> for Geometry {
-    type Error = Error;
+    fn face_normals(&self) -> Vec<Normal> {
+        self.faces
+            .iter()
+            .map(|f| match f.is_valid(&self.vertices) {
+                true => f.normal(&self.vertices),
+                false => Normal::default(),
+            })
+            .collect()
+    }
 
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        let mut geometry = Geometry::default();
+    // For every face, the faces that share an edge with it
+    pub fn face_neighbours(&self) -> Vec<Vec<Index>> {
+        let edges = self.edge_faces();
 
-        for line in value.lines() {
-            if let Ok(v) = Vertex::try_from(line) {
-                geometry.vertices.push(v);
-                continue;
+        let mut result = vec![Vec::new(); self.faces.len()];
+        for shared in edges.values() {
+            for &a in shared.iter() {
+                for &b in shared.iter().filter(|b| **b != a) {
+                    if !result[a].contains(&b) {
+                        result[a].push(b);
+                    }
+                }
             }
-            if let Ok(f) = Face::try_from(line) {
-                geometry.faces.push(f);
-                continue;
+        }
+
+        for neighbours in result.iter_mut() {
+            neighbours.sort_unstable();
+        }
+
+        result
+    }
+
+    // Per-vertex normals, if they have been set or computed
+    pub fn normals(&self) -> Option<&[Normal]> {
+        if self.normals.is_empty() {
+            None
+        } else {
+            Some(&self.normals)
+        }
+    }
+
+    pub fn set_normals(&mut self, normals: Vec<Normal>) -> Result<(),Error> {
+        if normals.len() != self.vertices.len() {
+            return Err(Error::ChannelLength {
+                expected: self.vertices.len(),
+                found: normals.len(),
+            });
+        }
+        self.normals = normals;
+        self.touch();
+        Ok(())
+    }
+
+    pub fn remove_normals(&mut self) -> Option<Vec<Normal>> {
+        if self.normals.is_empty() {
+            return None;
+        }
+        self.touch();
+        Some(std::mem::take(&mut self.normals))
+    }
+
+    // Stores smooth per-vertex normals, averaging the normals of the
+    // faces around each vertex weighted by their area.
+    pub fn compute_normals(&mut self) {
+        self.normals = self.smooth_normals();
+        self.touch();
+    }
+
+    fn smooth_normals(&self) -> Vec<Normal> {
+        let mut normals = vec![Vector::default(); self.vertices.len()];
+        for face in self.faces.iter().filter(|f| f.is_valid(&self.vertices)) {
+            let t = face.triangle(&self.vertices);
+
+            // the cross product's length is twice the area
+            let n = (t.p2 - t.p1).cross(&(t.p3 - t.p1));
+            for i in [face.a,face.b,face.c] {
+                normals[i] = normals[i] + n;
             }
         }
+        normals
+            .into_iter()
+            .map(|n| Normal::from(n.normalize()))
+            .collect()
+    }
 
-        geometry.validated()
+    // Normals referred to by the corners of faces, kept apart from
+    // the per-vertex normals so hard edges can be described.
+    pub fn corner_normals(&self) -> &[Normal] {
+        &self.corner_normals
     }
-}
 
-impl From<Geometry> for String {
-    fn from(geometry: Geometry) -> Self {
-        let mut result = String::new();
+    pub fn set_corner_normals(&mut self, normals: Vec<Normal>) -> Result<(),Error> {
+        Self::check_corners(&self.faces,|f| f.normals,normals.len())?;
+        self.corner_normals = normals;
+        self.touch();
+        Ok(())
+    }
 
-        let vertices = Itertools::intersperse(
-            geometry.vertices
-                .into_iter()
-                .map(String::from),
-            "\n".into()
-        ).collect::<String>();
+    // Texture coordinates referred to by the corners of faces
+    pub fn uvs(&self) -> &[Uv] {
+        &self.uvs
+    }
 
-        let faces = Itertools::intersperse(
-            geometry.faces
-                .into_iter()
-                .map(String::from),
-            "\n".into()
-        ).collect::<String>();
+    pub fn set_uvs(&mut self, uvs: Vec<Uv>) -> Result<(),Error> {
+        Self::check_corners(&self.faces,|f| f.uvs,uvs.len())?;
+        self.uvs = uvs;
+        self.touch();
+        Ok(())
+    }
 
-        result.push_str(&vertices);
-        result.push_str("\n");
-        result.push_str(&faces);
-        result
+    // Fails if any face refers to a corner value past `len`
+    fn check_corners(faces: &[Face], get: fn(&Face) -> Option<[Index;3]>, len: usize) -> Result<(),Error> {
+        match faces.iter().filter_map(get).flatten().find(|i| *i >= len) {
+            Some(index) => Err(Error::IndexOutOfRange { index, len }),
+            None => Ok(()),
+        }
     }
-}
 
-impl Transform for Geometry {
-    fn transform(&mut self, matrix: &Matrix) {
-        self.vertices.transform(matrix);
+    // Moves the geometry from one axis convention into another,
+    // reversing face winding if the conversion mirrors it so that
+    // normals keep pointing outwards.
+    pub fn convert_axes(&mut self, from: AxisConvention, to: AxisConvention) {
+        self.transform(&from.matrix_to(&to));
+        if from.mirrors(&to) {
+            self.flip();
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
+    // Reverses the winding of every face
+    pub fn flip(&mut self) {
+        for face in self.faces.iter_mut() {
+            face.flip();
+        }
+        self.touch();
+    }
 
-    use super::*;
+    // Faces that are too small to be useful: those with an edge
+    // shorter than `threshold` or with less area than a square of
+    // that size, including faces that repeat a vertex.
+    pub fn find_degenerate(&self, threshold: f64) -> Vec<Index> {
+        self.faces
+            .iter()
+            .enumerate()
+            .filter(|(_,f)| f.is_valid(&self.vertices))
+            .filter(|(_,f)| {
+                let triangle = f.triangle(&self.vertices);
+                let shortest = triangle.edge_lengths().into_iter().fold(f64::INFINITY,f64::min);
+                shortest < threshold || triangle.area() < threshold * threshold
+            })
+            .map(|(i,_)| i)
+            .collect()
+    }
 
-    #[test]
-    fn test_string_from_geometry() {
-        let d = "\
-            v 0.1 0.2 0.3\n\
-            v 0.4 0.5 0.6\n\
-            v 0.7 0.8 0.9\n\
-            v 1.1 1.2 1.3\n\
-            v 1.4 1.5 1.6\n\
-            v 1.7 1.8 1.9\n\
-            f 1 2 3\n\
-            f 4 5 6\n"
-        .to_string();
+    // Faces that are long and thin, with an aspect ratio above
+    // `limit` (an equilateral triangle has an aspect ratio of 1).
+    pub fn find_slivers(&self, limit: f64) -> Vec<Index> {
+        self.faces
+            .iter()
+            .enumerate()
+            .filter(|(_,f)| f.is_valid(&self.vertices))
+            .filter(|(_,f)| f.triangle(&self.vertices).aspect_ratio() > limit)
+            .map(|(i,_)| i)
+            .collect()
+    }
 
-        let g = Geometry::try_from(d.clone()).unwrap();
-        let s = String::from(g.clone());
+    // Merges the ends of edges shorter than `threshold` and removes
+    // the faces that collapse as a result. Vertices are never moved or
+    // removed, so groups and channels stay valid, but merged vertices
+    // are no longer used by any face. Returns the number of faces
+    // removed.
+    pub fn collapse_degenerate(&mut self, threshold: f64) -> usize {
+        span!("geometry.collapse_degenerate", faces = self.faces.len());
+        let count = self.faces.len();
 
-        assert_eq!(g.size(),2);
-        assert_eq!(d.trim(),s.trim());
+        // each pass only merges vertices that haven't been touched
+        // yet, so chains of short edges don't all fold into one point
+        loop {
+            let mut target = (0..self.vertices.len()).collect::<Vec<Index>>();
+            let mut locked = vec![false; self.vertices.len()];
+            let mut merged = false;
+
+            for face in self.faces.iter().filter(|f| f.is_valid(&self.vertices)) {
+                for (a,b) in face.edges() {
+                    if a == b || locked[a] || locked[b] {
+                        continue;
+                    }
+                    if self.vertices[a].distance(&self.vertices[b]) < threshold {
+                        target[a.max(b)] = a.min(b);
+                        locked[a] = true;
+                        locked[b] = true;
+                        merged = true;
+                    }
+                }
+            }
+
+            if !merged {
+                break;
+            }
+
+            for face in self.faces.iter_mut().filter(|f| f.is_valid(&self.vertices)) {
+                *face = face.remap(|i| target[i],|i| i,|i| i);
+            }
+            self.faces.retain(|f| f.a != f.b && f.b != f.c && f.c != f.a);
+        }
+
+        let removed = count - self.faces.len();
+        if removed > 0 {
+            self.touch();
+            self.changes.mark_all(self.vertices.len());
+        }
+        removed
     }
 
-    #[test]
-    fn test_geometry_from_string() {
-        let d = "\
-            v 0.1 0.2 0.3\n\
-            v 0.4 0.5 0.6\n\
-            v 0.7 0.8 0.9\n\
-            v 1.1 1.2 1.3\n\
-            v 1.4 1.5 1.6\n\
-            v 1.7 1.8 1.9\n\
-            f 1 2 3\n\
-            f 4 5 6\n"
-        .to_string();
+    // Replaces the longest edge of each sliver with the other diagonal
+    // of the quad it makes with its neighbour, where that leaves both
+    // faces less stretched. Returns the number of edges flipped.
+    pub fn flip_slivers(&mut self, limit: f64) -> usize {
+        span!("geometry.flip_slivers", faces = self.faces.len());
+        let mut flipped = 0;
 
-        let g = Geometry::try_from(d).unwrap();
+        // every flip lowers the worst aspect ratio of the pair, so
+        // this runs out of flips eventually
+        loop {
+            let edges = self.edge_faces();
+            let mut touched = vec![false; self.faces.len()];
+            let mut count = 0;
 
-        assert_eq!(g.size(),2);
+            for index in self.find_slivers(limit) {
+                if touched[index] {
+                    continue;
+                }
 
-        let a = g.get(0);
-        let b = g.get(1);
+                let face = &self.faces[index];
+                let triangle = face.triangle(&self.vertices);
+                let lengths = triangle.edge_lengths();
+                let longest = (0..3).fold(0,|m,i| if lengths[i] > lengths[m] { i } else { m });
+                let (u,v) = face.edges()[longest];
 
-        assert_eq!(a.p1.x,0.1);
-        assert_eq!(a.p1.y,0.2);
-        assert_eq!(a.p1.z,0.3);
-        assert_eq!(a.p2.x,0.4);
-        assert_eq!(a.p2.y,0.5);
-        assert_eq!(a.p2.z,0.6);
-        assert_eq!(a.p3.x,0.7);
-        assert_eq!(a.p3.y,0.8);
-        assert_eq!(a.p3.z,0.9);
-        assert_eq!(b.p1.x,1.1);
-        assert_eq!(b.p1.y,1.2);
-        assert_eq!(b.p1.z,1.3);
-        assert_eq!(b.p2.x,1.4);
-        assert_eq!(b.p2.y,1.5);
-        assert_eq!(b.p2.z,1.6);
-        assert_eq!(b.p3.x,1.7);
-        assert_eq!(b.p3.y,1.8);
-        assert_eq!(b.p3.z,1.9);
+                let other = match edges.get(&(u.min(v),u.max(v))).map(Vec::as_slice) {
+                    Some(&[x,y]) if x == index => y,
+                    Some(&[x,y]) if y == index => x,
+                    _ => continue,
+                };
+
+                if touched[other] || !self.faces[other].edges().contains(&(v,u)) {
+                    continue;
+                }
+
+                let w = opposite(face,u,v);
+                let x = opposite(&self.faces[other],v,u);
+                if w == x || edges.contains_key(&(w.min(x),w.max(x))) {
+                    continue;
+                }
+
+                let first = corners(face,&self.faces[other],[u,x,w]);
+                let second = corners(face,&self.faces[other],[x,v,w]);
+
+                let before = triangle.aspect_ratio().max(self.get(other).aspect_ratio());
+                let (t1,t2) = (first.triangle(&self.vertices),second.triangle(&self.vertices));
+                let after = t1.aspect_ratio().max(t2.aspect_ratio());
+
+                // the new faces have to face the same way as the old ones
+                let facing = *triangle.normal() + *self.get(other).normal();
+                if after >= before || t1.normal().dot(&facing) <= 0.0 || t2.normal().dot(&facing) <= 0.0 {
+                    continue;
+                }
+
+                self.faces[index] = first;
+                self.faces[other] = second;
+                touched[index] = true;
+                touched[other] = true;
+                count += 1;
+            }
+
+            if count == 0 {
+                break;
+            }
+            flipped += count;
+        }
+
+        if flipped > 0 {
+            self.touch();
+            self.changes.mark_all(self.vertices.len());
+        }
+        flipped
+    }
+
+    // Collapses degenerate faces and then flips slivers, returning
+    // the number of faces removed and edges flipped.
+    pub fn clean(&mut self, threshold: f64, limit: f64) -> (usize,usize) {
+        let removed = self.collapse_degenerate(threshold);
+        let flipped = self.flip_slivers(limit);
+        (removed,flipped)
+    }
+
+    // Moves each vertex `lambda` of the way towards the middle of its
+    // neighbours, `iterations` times.
+    pub fn smooth(&mut self, iterations: usize, lambda: f64) {
+        // only fails for missing groups, and there are none
+        let _ = self.smooth_with(&Smoothing::new(iterations,lambda));
+    }
+
+    // Like `smooth`, but can leave the boundary and some groups in
+    // place. Fails if a pinned group doesn't exist.
+    pub fn smooth_with(&mut self, smoothing: &Smoothing) -> Result<(),Error> {
+        span!("geometry.smooth", vertices = self.vertices.len(), iterations = smoothing.iterations);
+        let mut pinned = vec![false; self.vertices.len()];
+
+        for name in smoothing.groups.iter() {
+            let group = self.group(name).ok_or(Error::UnknownGroup(name.clone()))?;
+            for i in group.iter() {
+                if let Some(pin) = pinned.get_mut(*i) {
+                    *pin = true;
+                }
+            }
+        }
+
+        let edges = self.edge_faces();
+        let mut neighbours = vec![Vec::new(); self.vertices.len()];
+        for (&(a,b),faces) in edges.iter() {
+            neighbours[a].push(b);
+            neighbours[b].push(a);
+            if smoothing.boundary && faces.len() == 1 {
+                pinned[a] = true;
+                pinned[b] = true;
+            }
+        }
+
+        for _ in 0..smoothing.iterations {
+            self.vertices = self.vertices
+                .iter()
+                .enumerate()
+                .map(|(i,v)| match (pinned[i],neighbours[i].len()) {
+                    (true,_) | (_,0) => *v,
+                    (false,n) => {
+                        let sum = neighbours[i]
+                            .iter()
+                            .fold(Vector::default(),|s,j| s + self.vertices[*j]);
+                        *v + (sum / n - *v) * smoothing.lambda
+                    },
+                })
+                .collect();
+        }
+
+        if !self.normals.is_empty() {
+            self.normals = self.smooth_normals();
+        }
+
+        self.touch();
+        self.changes.mark_all(self.vertices.len());
+        Ok(())
+    }
+
+    // A rougher copy with the vertices in each cube of size `cell`
+    // merged into one at their average, leaving out faces that
+    // collapse. Only vertices and faces are kept, and vertices
+    // that aren't used by any face are dropped.
+    pub fn decimate(&self, cell: f64) -> Geometry {
+        span!("geometry.decimate", faces = self.faces.len(), cell = cell);
+        let cell = cell.abs().max(f64::EPSILON);
+        let origin = self.bounds().map(|b| b.min).unwrap_or_default();
+
+        let mut cells: BTreeMap<[i64;3],Index> = BTreeMap::new();
+        let mut sums: Vec<(Vertex,usize)> = Vec::new();
+        let mut target = vec![None;self.vertices.len()];
+        for face in self.faces.iter().filter(|f| f.is_valid(&self.vertices)) {
+            for index in [face.a,face.b,face.c] {
+                if target[index].is_some() {
+                    continue;
+                }
+                let vertex = self.vertices[index];
+                let offset = vertex - origin;
+                let key = [offset.x,offset.y,offset.z].map(|v| (v / cell).floor() as i64);
+                let merged = *cells.entry(key).or_insert_with(|| {
+                    sums.push((Vertex::default(),0));
+                    sums.len() - 1
+                });
+                sums[merged].0 = sums[merged].0 + vertex;
+                sums[merged].1 += 1;
+                target[index] = Some(merged);
+            }
+        }
+
+        let mut seen = BTreeSet::new();
+        let corners = self.faces
+            .iter()
+            .filter_map(|f| Some([target[f.a]?,target[f.b]?,target[f.c]?]))
+            .filter(|[a,b,c]| a != b && b != c && c != a)
+            .filter(|[a,b,c]| {
+                // the same three corners in any order is the same face
+                let mut key = [*a,*b,*c];
+                key.sort_unstable();
+                seen.insert(key)
+            })
+            .collect::<Vec<[Index;3]>>();
+
+        // merged vertices that only had faces that collapsed are left
+        // out, and the rest are numbered from one for the faces
+        let mut used = vec![None;sums.len()];
+        let mut vertices = Vec::new();
+        let faces = corners
+            .into_iter()
+            .map(|corners| {
+                let [a,b,c] = corners.map(|i| *used[i].get_or_insert_with(|| {
+                    let (sum,count) = sums[i];
+                    vertices.push(sum * (1.0 / count as f64));
+                    vertices.len()
+                }));
+                Face::new(a,b,c)
+            })
+            .collect();
+
+        let mut geometry = Geometry::new(vertices,faces);
+        geometry.touch();
+        geometry
+    }
+
+    // Rebuilds the surface with edges close to `target` in length,
+    // keeping boundaries and sharp edges. Channels are interpolated
+    // from the nearest point on the old surface, and vertices join a
+    // group if they fall between members of it. Normals are
+    // recomputed if there were any, and corner normals and uvs
+    // are dropped.
+    pub fn remesh(&mut self, target: f64, iterations: usize) {
+        span!("geometry.remesh", faces = self.faces.len(), target = target);
+        if target <= 0.0 || !target.is_finite() {
+            return;
+        }
+
+        let (vertices,faces) = remesh::isotropic(&self.vertices,&self.faces,target,iterations);
+
+        // the corners and weights of the nearest old face
+        let weights = vertices
+            .iter()
+            .map(|v| self.faces
+                .iter()
+                .filter(|f| f.is_valid(&self.vertices))
+                .map(|f| (f,f.triangle(&self.vertices)))
+                .min_by(|(_,a),(_,b)| a.distance(v).total_cmp(&b.distance(v)))
+                .map(|(f,t)| ([f.a,f.b,f.c],t.barycentric(v).unwrap_or((1.0,0.0,0.0)))))
+            .collect::<Vec<_>>();
+
+        for values in self.channels.values_mut() {
+            *values = weights
+                .iter()
+                .map(|w| match w {
+                    Some(([a,b,c],(u,v,w))) => values[*a] * u + values[*b] * v + values[*c] * w,
+                    None => 0.0,
+                })
+                .collect();
+        }
+
+        for indices in self.groups.values_mut() {
+            let members = indices.iter().collect::<BTreeSet<&Index>>();
+            *indices = weights
+                .iter()
+                .enumerate()
+                .filter_map(|(i,w)| {
+                    let ([a,b,c],(u,v,w)) = w.as_ref()?;
+                    [(a,u),(b,v),(c,w)]
+                        .iter()
+                        .all(|(k,x)| **x <= TOLERANCE || members.contains(k))
+                        .then_some(i)
+                })
+                .collect();
+        }
+
+        self.vertices = vertices;
+        self.faces = faces;
+        self.corner_normals.clear();
+        self.uvs.clear();
+
+        if !self.normals.is_empty() {
+            self.normals = self.smooth_normals();
+        }
+
+        self.touch();
+        self.changes.mark_all(self.vertices.len());
+    }
+
+    pub fn bounds(&self) -> Option<Bounds> {
+        Bounds::from_points(&self.vertices)
+    }
+
+    // Scales the geometry evenly so it's as large as it can be inside
+    // the target box, and centres it there. Returns the scale factors,
+    // or None if there's nothing to scale.
+    pub fn fit_within(&mut self, target: &Bounds) -> Option<Vector> {
+        let bounds = self.bounds()?;
+        let (size,goal) = (bounds.size(),target.size());
+        let factor = [(size.x,goal.x),(size.y,goal.y),(size.z,goal.z)]
+            .into_iter()
+            .filter(|(s,_)| *s > f64::EPSILON)
+            .map(|(s,g)| g / s)
+            .reduce(f64::min)
+            .unwrap_or(1.0);
+
+        let factors = Vector::new(factor,factor,factor);
+        self.fit(&bounds,target,factors);
+        Some(factors)
+    }
+
+    // Scales the geometry on each axis so its bounds are the target
+    // box. Sides that are flat stay flat and are moved to the middle
+    // of the box. Returns the scale factors, or None if there's
+    // nothing to scale.
+    pub fn fit_to(&mut self, target: &Bounds) -> Option<Vector> {
+        let bounds = self.bounds()?;
+        let (size,goal) = (bounds.size(),target.size());
+        let factor = |s: f64, g: f64| match s > f64::EPSILON {
+            true => g / s,
+            false => 1.0,
+        };
+
+        let factors = Vector::new(
+            factor(size.x,goal.x),
+            factor(size.y,goal.y),
+            factor(size.z,goal.z));
+        self.fit(&bounds,target,factors);
+        Some(factors)
+    }
+
+    // Scales the geometry around the middle of its bounds and moves
+    // that to the middle of the target
+    fn fit(&mut self, bounds: &Bounds, target: &Bounds, factors: Vector) {
+        let (from,to) = (bounds.center(),target.center());
+        self.transform(&(
+            Matrix::translate(to.x,to.y,to.z) *
+            Matrix::scale(factors.x,factors.y,factors.z) *
+            Matrix::translate(-from.x,-from.y,-from.z)));
+    }
+
+    // The smallest convex mesh around a set of points, which is
+    // empty if they're all in one plane
+    pub fn hull(points: &[Vertex]) -> Self {
+        span!("geometry.hull", points = points.len());
+        let (vertices,faces) = hull::convex(points);
+        let mut geometry = Self::new(vertices,faces);
+        geometry.touch();
+        geometry
+    }
+
+    // The convex hull of the geometry's vertices
+    pub fn convex_hull(&self) -> Self {
+        Self::hull(&self.vertices)
+    }
+
+    // Approximately splits a closed mesh into convex hulls that
+    // together cover it, which are simpler to check for collisions
+    // than the mesh itself. A convex mesh comes back as its hull.
+    pub fn decompose(&self, settings: &Decomposition) -> Vec<Geometry> {
+        span!("geometry.decompose", faces = self.faces.len());
+        decompose::decompose(&self.vertices,&self.faces,settings)
+    }
+
+    // Faces that overhang by more than `angle` radians from vertical
+    // when printed with +Z up, and so need something under them.
+    // Faces on the bed at the bottom of the mesh are left out.
+    pub fn overhangs(&self, angle: f64) -> Vec<Index> {
+        support::overhangs(&self.vertices,&self.faces,angle)
+    }
+
+    // Pillars or trees under the overhangs of the mesh as it sits, each
+    // standing on the bed or the part of the mesh below it, to be
+    // printed alongside it and broken off afterwards
+    pub fn supports(&self, settings: &Supports) -> Geometry {
+        span!("geometry.supports", faces = self.faces.len());
+        support::supports(&self.vertices,&self.faces,settings)
+    }
+
+    // A lattice of square struts filling the inside of a closed mesh,
+    // cut off where they reach its surface, for making a part lighter
+    // while keeping it stiff
+    pub fn lattice(&self, settings: &Lattice) -> Geometry {
+        span!("geometry.lattice", faces = self.faces.len());
+        let (mut result,offsets) = lattice::lattice(self,settings);
+        let width = settings.width();
+        let vertices = result.vertices_mut();
+        for (offset,indices) in offsets.iter() {
+            for index in indices.iter() {
+                vertices[*index] = vertices[*index] + *offset * width;
+            }
+        }
+        result
+    }
+
+    // Everything inside this mesh or `other`. Both have to be closed,
+    // and the result is made of new faces so it has no groups,
+    // channels or normals.
+    pub fn union(&self, other: &Geometry) -> Geometry {
+        span!("geometry.union", faces = self.faces.len(), other = other.faces.len());
+        boolean::combine(self,other,Operation::Union)
+    }
+
+    // Everything inside this mesh that isn't inside `other`, like
+    // cutting a hole with a tool shaped like `other`
+    pub fn difference(&self, other: &Geometry) -> Geometry {
+        span!("geometry.difference", faces = self.faces.len(), other = other.faces.len());
+        boolean::combine(self,other,Operation::Difference)
+    }
+
+    // Everything inside both this mesh and `other`
+    pub fn intersection(&self, other: &Geometry) -> Geometry {
+        span!("geometry.intersection", faces = self.faces.len(), other = other.faces.len());
+        boolean::combine(self,other,Operation::Intersection)
+    }
+
+    // The transform that best lines this geometry's vertices up with
+    // the surface of `target`, like moving a scan of a built part onto
+    // its design. It only corrects small misalignments, so the two
+    // should already be roughly in place.
+    pub fn align_to(&self, target: &Geometry) -> Matrix {
+        self.align_with(target,&Alignment::default())
+    }
+
+    pub fn align_with(&self, target: &Geometry, settings: &Alignment) -> Matrix {
+        span!("geometry.align", vertices = self.vertices.len(), faces = target.faces.len());
+        align::align(&self.vertices,target,settings)
+    }
+
+    // Measures how far each vertex is from the surface of `reference`
+    // into the named channel, positive outside it and negative inside,
+    // like the error in a scan of a built part aligned to its design.
+    pub fn deviation_from<T: Into<String>>(&mut self, reference: &Geometry, name: T) -> Result<Deviation,Error> {
+        span!("geometry.deviation", vertices = self.vertices.len(), faces = reference.faces.len());
+        let values = deviation::deviations(&self.vertices,reference).ok_or(Error::EmptyGeometry)?;
+        let summary = Deviation::new(&values);
+        self.set_channel(name,values)?;
+        Ok(summary)
+    }
+
+    // The mirror planes and rotation axes that leave the geometry
+    // looking the same, where every vertex lands within `tolerance`
+    // of another. Each comes with the vertex every vertex lands on.
+    pub fn symmetries(&self, tolerance: f64) -> Vec<Symmetry> {
+        span!("geometry.symmetries", vertices = self.vertices.len());
+        symmetry::symmetries(self,tolerance)
+    }
+
+    // The rigid transform that moves this geometry onto `other` if
+    // they're the same shape within `tolerance`, like two copies of
+    // a part modelled in different places. Mirror images don't count.
+    pub fn congruent_to(&self, other: &Geometry, tolerance: f64) -> Option<Matrix> {
+        span!("geometry.congruent", vertices = self.vertices.len());
+        congruence::congruent(self,other,tolerance)
+    }
+
+    // How much the surface bends at every vertex, which is zero on
+    // open edges. Curvature is one over the radius, so it scales with
+    // the size of the geometry.
+    pub fn curvature(&self, measure: Curvature) -> Vec<f64> {
+        span!("geometry.curvature", vertices = self.vertices.len());
+        curvature::curvatures(self)
+            .into_iter()
+            .map(|(gaussian,mean)| measure.value(gaussian,mean))
+            .collect()
+    }
+
+    // Measures the curvature at every vertex into the named channel,
+    // for coloring or for picking out sharp and rounded areas
+    pub fn curvature_into<T: Into<String>>(&mut self, measure: Curvature, name: T) -> Result<(),Error> {
+        let values = self.curvature(measure);
+        self.set_channel(name,values)
+    }
+
+    // Rounded edges, as groups of connected vertices that bend one way
+    // with radii within `tolerance` times each other
+    pub fn fillets(&self, tolerance: f64) -> Vec<Fillet> {
+        span!("geometry.fillets", vertices = self.vertices.len());
+        curvature::fillets(self,tolerance)
+    }
+
+    // The distance from the point on the surface closest to `from` to
+    // every vertex, travelling over the surface along edges, so it's a
+    // little longer than the true shortest distance on coarse meshes.
+    // Vertices that can't be reached are infinitely far away.
+    pub fn surface_distances(&self, from: &Vertex) -> Vec<f64> {
+        span!("geometry.surface_distances", vertices = self.vertices.len());
+        Geodesic::new(self,from)
+            .map(Geodesic::distances)
+            .unwrap_or_else(|| vec![f64::INFINITY;self.vertices.len()])
+    }
+
+    // The distance between two points over the surface, like the length
+    // of a wire laid along it. None if they aren't on connected faces.
+    pub fn surface_distance(&self, from: &Vertex, to: &Vertex) -> Option<f64> {
+        self.surface_path(from,to).map(|p| p.windows(2).map(|w| w[0].distance(&w[1])).sum())
+    }
+
+    // The points of the shortest way between two points over the surface,
+    // starting and ending on the surface closest to them
+    pub fn surface_path(&self, from: &Vertex, to: &Vertex) -> Option<Vec<Vertex>> {
+        span!("geometry.surface_path", vertices = self.vertices.len());
+        Geodesic::new(self,from)?
+            .path(self,to)
+    }
+
+    // The enclosed volume, which is only meaningful for a closed mesh
+    // with outward facing triangles. Inside-out meshes are negative.
+    pub fn volume(&self) -> f64 {
+        self.faces
+            .iter()
+            .filter(|f| f.is_valid(&self.vertices))
+            .map(|f| {
+                let t = f.triangle(&self.vertices);
+                t.p1.dot(&t.p2.cross(&t.p3))
+            })
+            .sum::<f64>() / 6.0
+    }
+
+    // The centre of the enclosed volume, which is where the centre of
+    // mass is for an even density. None if the mesh has no volume.
+    pub fn centroid(&self) -> Option<Vertex> {
+        let (volume,sum) = self.faces
+            .iter()
+            .filter(|f| f.is_valid(&self.vertices))
+            .map(|f| f.triangle(&self.vertices))
+            .fold((0.0,Vertex::default()),|(volume,sum),t| {
+                let v = t.p1.dot(&t.p2.cross(&t.p3)) / 6.0;
+                (volume + v,sum + (t.p1 + t.p2 + t.p3) * (v / 4.0))
+            });
+
+        match volume.abs() > f64::EPSILON {
+            true => Some(sum * (1.0 / volume)),
+            false => None,
+        }
+    }
+
+    // The outline where a plane at a height cuts through the mesh,
+    // as polylines in XY. Loops around outward facing triangles wind
+    // counter-clockwise around solid and clockwise around holes.
+    pub fn slice(&self, z: f64) -> Vec<Polyline> {
+        let segments = self.faces
+            .iter()
+            .filter(|f| f.is_valid(&self.vertices))
+            .filter_map(|f| {
+                let t = f.triangle(&self.vertices);
+                let points = [t.p1,t.p2,t.p3];
+
+                // points on the plane count as above it, so an edge
+                // is only crossed once
+                let crossings = (0..3)
+                    .map(|i| (points[i],points[(i + 1) % 3]))
+                    .filter(|(a,b)| (a.z >= z) != (b.z >= z))
+                    .map(|(a,b)| {
+                        let t = (z - a.z) / (b.z - a.z);
+                        (a.x + (b.x - a.x) * t,a.y + (b.y - a.y) * t)
+                    })
+                    .collect::<Vec<Point>>();
+
+                // run the segment with the solid on its left
+                let normal = t.normal();
+                match crossings[..] {
+                    [a,b] if (b.0 - a.0) * -normal.y + (b.1 - a.1) * normal.x >= 0.0 => Some((a,b)),
+                    [a,b] => Some((b,a)),
+                    _ => None,
+                }
+            })
+            .collect::<Vec<(Point,Point)>>();
+
+        Polyline::chain(&segments)
+    }
+
+    // Cuts the mesh into layers `layer_height` apart from the bottom
+    // up, each sliced through its middle, like a printer would build
+    // it. Loops that don't close, from holes in the mesh, are left
+    // out, and the rest are sorted into islands with their holes.
+    // There are no layers if the height isn't positive.
+    pub fn slice_layers(&self, layer_height: f64) -> Vec<Layer> {
+        span!("geometry.slice_layers", faces = self.faces.len());
+        let Some(bounds) = self.bounds().filter(|_| layer_height > 0.0) else {
+            return Vec::new();
+        };
+
+        let count = ((bounds.size().z / layer_height).ceil() as usize).max(1);
+        (0..count)
+            .map(|i| bounds.min.z + layer_height * (i as f64 + 0.5))
+            .map(|z| {
+                let loops = self
+                    .slice(z)
+                    .into_iter()
+                    .filter(|l| l.is_closed() && l.len() > 2)
+                    .collect::<Vec<Polyline>>();
+                Layer::new(z,Profile::nest(loops))
+            })
+            .collect()
+    }
+
+    // The boundary of a set of faces seen from above, as polylines in
+    // XY. Edges between two of the faces are left out.
+    pub fn boundary(&self, faces: &[Index]) -> Vec<Polyline> {
+        let edges = faces
+            .iter()
+            .filter_map(|i| self.faces.get(*i))
+            .filter(|f| f.is_valid(&self.vertices))
+            .flat_map(|f| f.edges())
+            .map(|(a,b)| {
+                let (a,b) = (self.vertices[a],self.vertices[b]);
+                ((a.x,a.y),(b.x,b.y))
+            })
+            .collect::<Vec<(Point,Point)>>();
+
+        let inner = edges
+            .iter()
+            .map(|(a,b)| (profile::weld(*a),profile::weld(*b)))
+            .collect::<BTreeSet<_>>();
+
+        let outer = edges
+            .into_iter()
+            .filter(|(a,b)| !inner.contains(&(profile::weld(*b),profile::weld(*a))))
+            .collect::<Vec<(Point,Point)>>();
+
+        Polyline::chain(&outer)
+    }
+
+    // Names a set of vertices so that selections can refer
+    // to them, replacing any group with the same name.
+    pub fn add_group<T, I>(&mut self, name: T, indices: I) -> Result<(),Error>
+    where
+        T: Into<String>,
+        I: Into<Vec<Index>>
+    {
+        let indices = indices.into();
+        let len = self.vertices.len();
+        if let Some(&index) = indices.iter().find(|i| **i >= len) {
+            return Err(Error::IndexOutOfRange { index, len });
+        }
+        self.groups.insert(name.into(),indices);
+        self.touch();
+        Ok(())
+    }
+
+    pub fn remove_group(&mut self, name: &str) -> Option<Vec<Index>> {
+        let result = self.groups.remove(name);
+        if result.is_some() {
+            self.touch();
+        }
+        result
+    }
+
+    pub fn group(&self, name: &str) -> Option<&Vec<Index>> {
+        self.groups.get(name)
+    }
+
+    pub fn groups(&self) -> &BTreeMap<String,Vec<Index>> {
+        &self.groups
+    }
+
+    // Stores a named scalar value for every vertex, replacing 
+    // any channel with the same name.
+    pub fn set_channel<T: Into<String>>(&mut self, name: T, values: Vec<f64>) -> Result<(),Error> {
+        if values.len() != self.vertices.len() {
+            return Err(Error::ChannelLength { 
+                expected: self.vertices.len(), 
+                found: values.len() 
+            });
+        }
+        self.channels.insert(name.into(),values);
+        self.touch();
+        Ok(())
+    }
+
+    pub fn remove_channel(&mut self, name: &str) -> Option<Vec<f64>> {
+        let result = self.channels.remove(name);
+        if result.is_some() {
+            self.touch();
+        }
+        result
+    }
+
+    pub fn channel(&self, name: &str) -> Option<&Vec<f64>> {
+        self.channels.get(name)
+    }
+
+    pub fn channels(&self) -> &BTreeMap<String,Vec<f64>> {
+        &self.channels
+    }
+
+    // Transforms a copy of `other` and adds it to this geometry. Groups
+    // and channels with the same name are merged, and channels missing
+    // from either side are filled with zeros. Faces of a mirrored copy
+    // are wound the other way so they still face out.
+    pub fn append(&mut self, other: &Geometry, matrix: &Matrix) -> Appended {
+        self.append_named(other,matrix,|n| n.to_string())
+    }
+
+    // Like `append`, but the groups and channels from `other` are
+    // renamed to `<prefix>.<name>` so they stay separate.
+    pub fn append_prefixed(&mut self, prefix: &str, other: &Geometry, matrix: &Matrix) -> Appended {
+        self.append_named(other,matrix,|n| format!("{}.{}",prefix,n))
+    }
+
+    fn append_named<F>(&mut self, other: &Geometry, matrix: &Matrix, rename: F) -> Appended
+    where
+        F: Fn(&str) -> String
+    {
+        span!("geometry.append", vertices = other.vertices.len(), faces = other.faces.len());
+        let offset = self.vertices.len();
+        let start = self.faces.len();
+        let count = other.vertices.len();
+
+        // normals are kept if either side has them
+        if !self.normals.is_empty() || !other.normals.is_empty() {
+            if self.normals.is_empty() {
+                self.normals = self.smooth_normals();
+            }
+            let mut normals = match other.normals.is_empty() {
+                true => other.smooth_normals(),
+                false => other.normals.clone(),
+            };
+            let normal = matrix.normal();
+            for n in normals.iter_mut() {
+                n.transform_direction(&normal);
+                *n = n.normalize();
+            }
+            self.normals.extend(normals);
+        }
+
+        let mut vertices = other.vertices.clone();
+        vertices.transform(matrix);
+        self.vertices.extend(vertices);
+
+        // corner data is appended as-is, with faces pointing past
+        // what was already here
+        let normal_offset = self.corner_normals.len();
+        let uv_offset = self.uvs.len();
+
+        let mut corner_normals = other.corner_normals.clone();
+        let normal = matrix.normal();
+        for n in corner_normals.iter_mut() {
+            n.transform_direction(&normal);
+            *n = n.normalize();
+        }
+        self.corner_normals.extend(corner_normals);
+        self.uvs.extend_from_slice(&other.uvs);
+
+        // a transform that mirrors turns the faces inside out
+        let mirrored = matrix.determinant() < 0.0;
+        self.faces.extend(other.faces.iter().map(|f| {
+            let mut face = f.remap(
+                |i| i + offset,
+                |i| i + normal_offset,
+                |i| i + uv_offset,
+            );
+            if mirrored {
+                face.flip();
+            }
+            face
+        }));
+
+        for (name,indices) in other.groups.iter() {
+            self.groups
+                .entry(rename(name))
+                .or_default()
+                .extend(indices.iter().map(|i| i + offset));
+        }
+
+        // channels that only exist here get zeros for the new vertices
+        for values in self.channels.values_mut() {
+            values.resize(offset + count,0.0);
+        }
+
+        for (name,values) in other.channels.iter() {
+            let channel = self.channels
+                .entry(rename(name))
+                .or_default();
+            channel.resize(offset,0.0);
+            channel.extend_from_slice(values);
+        }
+
+        self.touch();
+        self.changes.mark(offset,offset + count);
+
+        Appended {
+            vertices: (offset,offset + count),
+            faces: (start,self.faces.len()),
+        }
+    }
+
+    // Gives mutable access to the vertices and records only the
+    // given ranges as changed.
+    pub fn alter<F>(&mut self, ranges: &[(Index,Index)], f: F) 
+    where
+        F: FnOnce(&mut Vec<Vertex>)
+    {
+        f(&mut self.vertices);
+        self.touch();
+        for &(start,end) in ranges.iter() {
+            self.changes.mark(start,end.min(self.vertices.len()));
+        }
+    }
+
+    pub fn changes(&self) -> &Changes {
+        &self.changes
+    }
+
+    pub fn mark_changed(&mut self, start: Index, end: Index) {
+        self.changes.mark(start,end.min(self.vertices.len()));
+    }
+
+    pub fn clear_changes(&mut self) {
+        self.changes.clear();
+    }
+
+    pub fn take_changes(&mut self) -> Changes {
+        std::mem::take(&mut self.changes)
+    }
+
+}
+
+// The vertex of `face` that isn't on the edge from `u` to `v`
+fn opposite(face: &Face, u: Index, v: Index) -> Index {
+    [face.a,face.b,face.c]
+        .into_iter()
+        .find(|i| *i != u && *i != v)
+        .unwrap_or(face.a)
+}
+
+// A face through `vertices`, taking the corner data for each
+// vertex from whichever of the two faces has that vertex. Corner
+// data is only kept if both faces have it.
+fn corners(first: &Face, second: &Face, vertices: [Index;3]) -> Face {
+    let corner = |vertex: Index, data: fn(&Face) -> Option<[Index;3]>| {
+        [first,second].into_iter().find_map(|f| {
+            let position = [f.a,f.b,f.c].iter().position(|i| *i == vertex)?;
+            data(f).map(|d| d[position])
+        })
+    };
+
+    let data = |get: fn(&Face) -> Option<[Index;3]>| {
+        match (get(first),get(second)) {
+            (Some(_),Some(_)) => Some(vertices.map(|v| corner(v,get).unwrap_or_default())),
+            _ => None,
+        }
+    };
+
+    Face {
+        a: vertices[0],
+        b: vertices[1],
+        c: vertices[2],
+        normals: data(|f| f.normals),
+        uvs: data(|f| f.uvs),
+    }
+}
+
+impl IntoIterator for Geometry {
+    type Item = Triangle;
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.faces
+            .into_iter()
+            .map(|f| f.triangle(&self.vertices))
+            .collect::<Vec<Triangle>>()
+            .into_iter()
+    }
+}
+
+impl TryFrom<String> for Geometry {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Geometry::parse(&value,ParseMode::Lenient)
+    }
+}
+
+impl From<&Primitive> for Geometry {
+    fn from(primitive: &Primitive) -> Self {
+        let mut geometry = Geometry::new(primitive.vertices().to_vec(),primitive.faces().to_vec());
+        geometry.touch();
+        geometry
+    }
+}
+
+impl From<Geometry> for String {
+    fn from(geometry: Geometry) -> Self {
+        span!("geometry.export", vertices = geometry.vertices.len(), faces = geometry.faces.len());
+        let mut result = String::new();
+
+        let vertices = Itertools::intersperse(
+            geometry.vertices
+                .into_iter()
+                .map(String::from),
+            "\n".into()
+        ).collect::<String>();
+
+        let faces = Itertools::intersperse(
+            geometry.faces
+                .into_iter()
+                .map(String::from),
+            "\n".into()
+        ).collect::<String>();
+
+        result.push_str(&vertices);
+        result.push('\n');
+
+        for (u,v) in geometry.uvs.iter() {
+            result.push_str(&format!("{} {} {}\n",UV_TAG,u,v));
+        }
+
+        for n in geometry.corner_normals.iter() {
+            result.push_str(&format!("{} {} {} {}\n",NORMAL_TAG,n.x,n.y,n.z));
+        }
+
+        for n in geometry.normals.iter() {
+            result.push_str(&format!("{} {} {} {}\n",VERTEX_NORMAL_TAG,n.x,n.y,n.z));
+        }
+
+        result.push_str(&faces);
+
+        // groups and channels are named lists of vertex indices and
+        // values, with the name quoted if it has spaces
+        let named = |tag: &str, name: &str, values: Vec<String>| {
+            let mut line = format!("\n{} {}",tag,utilities::quote(name,&[]));
+            for value in values {
+                line.push(' ');
+                line.push_str(&value);
+            }
+            line
+        };
+        for (name,indices) in geometry.groups.iter() {
+            result.push_str(&named(VERTEX_GROUP_TAG,name,indices.iter().map(|i| i.to_string()).collect()));
+        }
+        for (name,values) in geometry.channels.iter() {
+            result.push_str(&named(CHANNEL_TAG,name,values.iter().map(|v| v.to_string()).collect()));
+        }
+        result
+    }
+}
+
+impl Transform for Geometry {
+    fn transform(&mut self, matrix: &Matrix) {
+        span!("geometry.transform", vertices = self.vertices.len());
+        self.vertices.transform(matrix);
+
+        // normals need the inverse-transpose to survive
+        // non-uniform scaling
+        let normal = matrix.normal();
+        for n in self.normals.iter_mut().chain(self.corner_normals.iter_mut()) {
+            n.transform_direction(&normal);
+            *n = n.normalize();
+        }
+
+        self.touch();
+        self.changes.mark_all(self.vertices.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::testing;
+    use proptest::prelude::*;
+
+    // lines that look roughly like geometry data
+    fn line() -> impl Strategy<Value = String> {
+        "(v|f|vn|o|#)?[ 0-9.eE+-]{0,24}"
+    }
+
+    proptest! {
+
+        #[test]
+        fn test_geometry_parse_never_panics(data in "\\PC*") {
+            let _ = Geometry::try_from(data);
+        }
+
+        #[test]
+        fn test_geometry_parse_lines_valid(lines in prop::collection::vec(line(),0..32)) {
+            if let Ok(g) = Geometry::try_from(lines.join("\n")) {
+                for triangle in g.clone().into_iter() {
+                    let (a,b,c) = triangle.indices;
+                    prop_assert!(a < g.vertices().len());
+                    prop_assert!(b < g.vertices().len());
+                    prop_assert!(c < g.vertices().len());
+                }
+            }
+        }
+
+        #[test]
+        fn test_geometry_string_round_trip(
+            values in prop::collection::vec(-1e6f64..1e6,3..60),
+            indices in prop::collection::vec(1usize..20,0..30))
+        {
+            let count = values.len() / 3;
+            let indices = indices
+                .into_iter()
+                .map(|i| (i - 1) % count + 1)
+                .collect::<Vec<usize>>();
+
+            let g = Geometry::make(values,indices);
+            let s = String::from(g.clone());
+            let r = Geometry::try_from(s).unwrap();
+
+            prop_assert_eq!(r.size(),g.size());
+            prop_assert_eq!(r.vertices().len(),g.vertices().len());
+        }
+
+    }
+
+    #[test]
+    fn test_geometry_parse_comments() {
+        let d = "\
+            # a single triangle\n\
+            o triangle\n\
+            \n\
+            v 0 0 0\n\
+            v 1 0 0 # the corner on x\n\
+               v 0 1 0\n\
+            \n\
+            f 1 2 3";
+
+        for mode in [ParseMode::Lenient,ParseMode::Strict] {
+            let g = Geometry::parse(d,mode).unwrap();
+            assert_eq!(g.vertices().len(),3);
+            assert_eq!(g.size(),1);
+        }
+    }
+
+    #[test]
+    fn test_geometry_parse_groups() {
+        let mut g = Geometry::make(vec![0.0,0.0,0.0,1.0,0.0,0.0,0.0,1.0,0.0],vec![1,2,3]);
+        g.add_group("base edge",[0,1]).unwrap();
+        g.set_channel("heat",vec![0.5,1.0,-2.0]).unwrap();
+        g.set_normals(vec![Normal::new(0.0,0.0,1.0),Normal::new(0.0,0.6,0.8),Normal::new(0.0,0.0,1.0)]).unwrap();
+
+        let text = String::from(g.clone());
+        assert!(text.ends_with("vg \"base edge\" 0 1\nvc heat 0.5 1 -2"));
+        assert!(text.contains("vs 0 0.6 0.8\n"));
+
+        let r = Geometry::parse(&text,ParseMode::Strict).unwrap();
+        assert_eq!(r.groups(),g.groups());
+        assert_eq!(r.channels(),g.channels());
+        assert_eq!(r.normals(),g.normals());
+        assert!(Geometry::parse(&text.replacen("vs 0 0.6 0.8\n","",1),ParseMode::Strict).is_err());
+
+        // groups past the last vertex and short channels are invalid
+        let result = Geometry::parse(&(text.clone() + "\nvg far 3"),ParseMode::Strict);
+        assert!(matches!(result,Err(Error::InvalidLine { line: 10, .. })));
+        assert!(Geometry::parse(&(text.clone() + "\nvc cold 1"),ParseMode::Strict).is_err());
+        assert!(Geometry::parse(&(text + "\nvg far 3"),ParseMode::Lenient).unwrap().group("far").is_none());
+    }
+
+    #[test]
+    fn test_geometry_parse_multi() {
+        let d = "\
+            o first\n\
+            v 0 0 0\n\
+            v 1 0 0\n\
+            v 0 1 0\n\
+            f 1 2 3\n\
+            g second part\n\
+            v 0 0 1\n\
+            v 1 0 1\n\
+            f 4 5 3\n\
+            f 4 5 9\n\
+            o empty\n";
+
+        let result = Geometry::parse_multi(d);
+        assert_eq!(result.len(),2);
+
+        let (name,first) = &result[0];
+        assert_eq!(name,"first");
+        assert_eq!(first.vertices().len(),3);
+        assert_eq!(first.size(),1);
+
+        // the shared vertex is copied and the bad face dropped
+        let (name,second) = &result[1];
+        assert_eq!(name,"second part");
+        assert_eq!(second.vertices().len(),3);
+        assert_eq!(second.vertices()[0],Vertex::new(0.0,1.0,0.0));
+        assert_eq!(second.faces()[0].edges(),[(1,2),(2,0),(0,1)]);
+    }
+
+    #[test]
+    fn test_geometry_parse_multi_unnamed() {
+        let d = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3";
+        let result = Geometry::parse_multi(d);
+        assert_eq!(result.len(),1);
+        assert_eq!(result[0].0,"");
+        assert!(Geometry::parse_multi("# nothing").is_empty());
+    }
+
+    #[test]
+    fn test_geometry_corners() {
+        let d = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 0 1\nvn 0 0 1\nf 1/1/1 2/2/1 3/3/1";
+        let mut g = Geometry::parse(d,ParseMode::Strict).unwrap();
+        assert_eq!(g.uvs(),&[(0.0,0.0),(1.0,0.0),(0.0,1.0)]);
+        assert_eq!(g.corner_normals(),&[Normal::new(0.0,0.0,1.0)]);
+        assert_eq!(String::from(g.clone()),d);
+
+        // corner data follows the face when it's flipped or scaled
+        g.flip();
+        g.transform(&Matrix::scale(1.0,1.0,-1.0));
+        assert_eq!(g.faces()[0],Face::new(1,3,2).with_uvs([1,3,2]).with_normals([1,1,1]));
+        assert_eq!(g.corner_normals(),&[Normal::new(0.0,0.0,-1.0)]);
+
+        assert!(g.set_uvs(Vec::new()).is_err());
+        assert!(g.set_corner_normals(vec![Normal::default();2]).is_ok());
+        assert!(Geometry::parse("v 0 0 0\nf 1//2 1//2 1//2",ParseMode::Lenient).is_err());
+    }
+
+    #[test]
+    fn test_geometry_append_corners() {
+        let d = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2//1 3//1";
+        let mut a = Geometry::try_from(d.to_string()).unwrap();
+        let b = a.clone();
+        a.append(&b,&Matrix::identity());
+        assert_eq!(a.corner_normals().len(),2);
+        assert_eq!(a.faces()[1],Face::new(4,5,6).with_normals([2,2,2]));
+    }
+
+    #[test]
+    fn test_geometry_parse_multi_corners() {
+        let d = "vn 1 0 0\nvn 0 0 1\no A\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1//2 2//2 3//2\no B\nf 1 2 3";
+        let result = Geometry::parse_multi(d);
+        assert_eq!(result.len(),2);
+        assert_eq!(result[0].1.corner_normals(),&[Normal::new(0.0,0.0,1.0)]);
+        assert_eq!(result[0].1.faces()[0].normals,Some([0,0,0]));
+        assert!(result[1].1.corner_normals().is_empty());
+    }
+
+    #[test]
+    fn test_geometry_parse_strict() {
+        let d = "v 0 0 0\nv 1 0 0\nvp 0 0 1\nv 0 1 0\nf 1 2 3";
+
+        // lenient parsing skips the parameter vertex
+        assert_eq!(Geometry::parse(d,ParseMode::Lenient).unwrap().vertices().len(),3);
+
+        let result = Geometry::parse(d,ParseMode::Strict);
+        assert!(matches!(result,Err(Error::InvalidLine { line: 3, text }) if text == "vp 0 0 1"));
+
+        // trailing values are an error too
+        let result = Geometry::parse("v 0 0 0 1",ParseMode::Strict);
+        assert!(matches!(result,Err(Error::InvalidLine { line: 1, .. })));
+    }
+
+    #[test]
+    fn test_geometry_parse_polygons() {
+        let d = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4";
+
+        // a quad is split into two triangles in either mode
+        for mode in [ParseMode::Lenient,ParseMode::Strict] {
+            let g = Geometry::parse(d,mode).unwrap();
+            assert_eq!(g.faces(),&[Face::new(1,2,3),Face::new(1,3,4)]);
+        }
+        let result = Geometry::parse_multi(&format!("o quad\n{}",d));
+        assert_eq!(result[0].1.faces().len(),2);
+
+        // indices start at 1
+        let d = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 0 1 2";
+        let result = Geometry::parse(d,ParseMode::Strict);
+        assert!(matches!(result,Err(Error::InvalidLine { line: 4, text }) if text == "f 0 1 2"));
+        assert!(Geometry::parse(d,ParseMode::Lenient).unwrap().faces().is_empty());
+    }
+
+    #[test]
+    fn test_string_from_geometry() {
+        let d = "\
+            v 0.1 0.2 0.3\n\
+            v 0.4 0.5 0.6\n\
+            v 0.7 0.8 0.9\n\
+            v 1.1 1.2 1.3\n\
+            v 1.4 1.5 1.6\n\
+            v 1.7 1.8 1.9\n\
+            f 1 2 3\n\
+            f 4 5 6\n"
+        .to_string();
+
+        let g = Geometry::try_from(d.clone()).unwrap();
+        let s = String::from(g.clone());
+
+        assert_eq!(g.size(),2);
+        assert_eq!(d.trim(),s.trim());
+    }
+
+    #[test]
+    fn test_geometry_from_string() {
+        let d = "\
+            v 0.1 0.2 0.3\n\
+            v 0.4 0.5 0.6\n\
+            v 0.7 0.8 0.9\n\
+            v 1.1 1.2 1.3\n\
+            v 1.4 1.5 1.6\n\
+            v 1.7 1.8 1.9\n\
+            f 1 2 3\n\
+            f 4 5 6\n"
+        .to_string();
+
+        let g = Geometry::try_from(d).unwrap();
+
+        assert_eq!(g.size(),2);
+
+        let a = g.get(0);
+        let b = g.get(1);
+
+        assert_eq!(a.p1.x,0.1);
+        assert_eq!(a.p1.y,0.2);
+        assert_eq!(a.p1.z,0.3);
+        assert_eq!(a.p2.x,0.4);
+        assert_eq!(a.p2.y,0.5);
+        assert_eq!(a.p2.z,0.6);
+        assert_eq!(a.p3.x,0.7);
+        assert_eq!(a.p3.y,0.8);
+        assert_eq!(a.p3.z,0.9);
+        assert_eq!(b.p1.x,1.1);
+        assert_eq!(b.p1.y,1.2);
+        assert_eq!(b.p1.z,1.3);
+        assert_eq!(b.p2.x,1.4);
+        assert_eq!(b.p2.y,1.5);
+        assert_eq!(b.p2.z,1.6);
+        assert_eq!(b.p3.x,1.7);
+        assert_eq!(b.p3.y,1.8);
+        assert_eq!(b.p3.z,1.9);
+    }
+
+    #[test]
+    fn test_geometry_alter_marks_changes() {
+        let mut g = Geometry::make(
+            vec![0.0; 18],
+            vec![1, 2, 3, 4, 5, 6]);
+
+        assert!(g.changes().is_empty());
+
+        g.alter(&[(1,2),(4,10)],|v| {
+            v[1].x = 1.0;
+            v[4].x = 1.0;
+            v[5].x = 1.0;
+        });
+
+        assert_eq!(g.changes().ranges(),&[(1,2),(4,6)]);
+
+        let changes = g.take_changes();
+        assert_eq!(changes.count(),3);
+        assert!(g.changes().is_empty());
+    }
+
+    #[test]
+    fn test_geometry_revision() {
+        let mut g = Geometry::make(
+            vec![0.0; 9],
+            vec![1, 2, 3]);
+
+        let a = g.revision();
+        assert_ne!(a,0);
+        assert_eq!(g.clone().revision(),a);
+
+        g.vertices_mut()[0].x = 1.0;
+        let b = g.revision();
+        assert_ne!(a,b);
+
+        g.add_group("corner",[0]).unwrap();
+        assert_ne!(g.revision(),b);
+    }
+
+    #[test]
+    fn test_geometry_face_neighbours() {
+        // two triangles making a square, plus one detached triangle
+        let g = Geometry::make(
+            vec![0.0; 21],
+            vec![1, 2, 3, 1, 3, 4, 5, 6, 7]);
+
+        let neighbours = g.face_neighbours();
+        assert_eq!(neighbours,vec![vec![1],vec![0],vec![]]);
+    }
+
+    #[test]
+    fn test_geometry_macro() {
+        let g = geometry! {
+            v 0 0 0;
+            v 1.5 0 0;
+            v 0 -1.5 0.25;
+            f 1 2 3;
+        };
+        let h = Geometry::make(vec![0.0,0.0,0.0, 1.5,0.0,0.0, 0.0,-1.5,0.25],vec![1,2,3]);
+        assert_eq!(g.vertices(),h.vertices());
+        assert_eq!(g.faces(),h.faces());
+        assert_eq!(testing::cube().volume(),1.0);
+    }
+
+    #[test]
+    fn test_geometry_find_degenerate() {
+        let g = geometry! {
+            v 0 0 0; v 1 0 0; v 0 1 0;
+            v 1 0.001 0; v 2 0 0;
+            f 1 2 3; f 1 2 4; f 2 4 3; f 1 2 5;
+        };
+
+        // a short edge, and a face along a line
+        assert_eq!(g.find_degenerate(0.01),vec![1,2,3]);
+        assert_eq!(g.find_slivers(4.0),vec![1,2,3]);
+        assert!(g.find_slivers(1e6).contains(&3));
+        assert!(g.find_slivers(f64::INFINITY).is_empty());
+    }
+
+    #[test]
+    fn test_geometry_collapse_degenerate() {
+        let mut g = Geometry::make(
+            vec![
+                0.0,0.0,0.0, 1.0,0.0,0.0, 1.0,0.001,0.0, 0.0,1.0,0.0,
+            ],
+            vec![1,2,4, 2,3,4]);
+
+        // the second face loses its short edge and disappears
+        assert_eq!(g.collapse_degenerate(0.01),1);
+        assert_eq!(g.faces(),&vec![Face::new(1,2,4)]);
+        assert_eq!(g.vertices().len(),4);
+        assert_eq!(g.collapse_degenerate(0.01),0);
+    }
+
+    #[test]
+    fn test_geometry_flip_slivers() {
+        // a thin diamond split along its long diagonal
+        let mut g = Geometry::make(
+            vec![
+                0.0,0.0,0.0, 10.0,0.0,0.0, 5.0,1.0,0.0, 5.0,-1.0,0.0,
+            ],
+            vec![1,2,3, 2,1,4]);
+
+        let before = g.find_slivers(2.0).len();
+        assert_eq!(before,2);
+        assert_eq!(g.flip_slivers(2.0),1);
+
+        // now split along the short diagonal, still facing up
+        assert!(g.edges().contains(&(2,3)));
+        assert!(!g.edges().contains(&(0,1)));
+        for face in g.faces() {
+            assert!(face.normal(g.vertices()).z > 0.0);
+        }
+        assert_eq!(g.clean(0.01,2.0),(0,0));
+    }
+
+    #[test]
+    fn test_geometry_remesh() {
+        // a long box, where every side is two skinny faces
+        let mut g = testing::cube();
+        g.transform(&Matrix::scale(1.0,1.0,10.0));
+        let z = g.vertices().iter().map(|v| v.z).collect();
+        g.set_channel("Height",z).unwrap();
+        g.add_group("Top",[4,5,6,7]).unwrap();
+        g.compute_normals();
+
+        g.remesh(0.5,8);
+        assert!(g.size() > 12);
+        assert!(g.find_slivers(4.0).is_empty());
+
+        // still a closed box of the same size
+        assert!(g.edge_faces().values().all(|f| f.len() == 2));
+        let bounds = g.bounds().unwrap();
+        assert_eq!(bounds.min,Vertex::new(0.0,0.0,0.0));
+        assert_eq!(bounds.max,Vertex::new(1.0,1.0,10.0));
+
+        for (i,v) in g.vertices().iter().enumerate() {
+            assert_relative_eq!(g.channel("Height").unwrap()[i],v.z,epsilon = 1e-9);
+        }
+
+        let top = g.group("Top").unwrap();
+        assert!(!top.is_empty());
+        assert!(top.iter().all(|i| g.vertices()[*i].z > 9.0));
+        assert_eq!(g.normals().unwrap().len(),g.vertices().len());
+    }
+
+    #[test]
+    fn test_geometry_fit() {
+        let block = || Geometry::from_bounds(&Bounds::new(Vertex::new(10.0,10.0,10.0),Vertex::new(14.0,12.0,11.0)));
+        let target = Bounds::new(Vertex::new(0.0,0.0,0.0),Vertex::new(2.0,2.0,2.0));
+
+        // the longest side is what limits an even scale
+        let mut within = block();
+        assert_eq!(within.fit_within(&target),Some(Vector::new(0.5,0.5,0.5)));
+        let bounds = within.bounds().unwrap();
+        assert_eq!(bounds.min,Vertex::new(0.0,0.5,0.75));
+        assert_eq!(bounds.max,Vertex::new(2.0,1.5,1.25));
+
+        let mut stretched = block();
+        assert_eq!(stretched.fit_to(&target),Some(Vector::new(0.5,1.0,2.0)));
+        assert_eq!(stretched.bounds(),Some(target));
+
+        // a flat square keeps its thickness
+        let mut flat = Geometry::make(vec![0.0,0.0,0.0, 1.0,0.0,0.0, 1.0,1.0,0.0],vec![1,2,3]);
+        assert_eq!(flat.fit_to(&target),Some(Vector::new(2.0,2.0,1.0)));
+        assert_eq!(flat.bounds().unwrap().min.z,1.0);
+        assert_eq!(Geometry::default().fit_within(&target),None);
+    }
+
+    #[test]
+    fn test_geometry_smooth() {
+        // a square of four faces with a raised middle
+        let mut g = Geometry::make(
+            vec![
+                0.0,0.0,0.0, 2.0,0.0,0.0, 2.0,2.0,0.0, 0.0,2.0,0.0, 1.0,1.0,1.0,
+            ],
+            vec![1,2,5, 2,3,5, 3,4,5, 4,1,5]);
+
+        let mut pinned = g.clone();
+        pinned.smooth_with(&Smoothing::new(1,0.5).with_pinned_boundary(true)).unwrap();
+        assert_eq!(pinned.vertices()[0],Vertex::new(0.0,0.0,0.0));
+        assert_eq!(pinned.vertices()[4],Vertex::new(1.0,1.0,0.5));
+
+        let mut grouped = g.clone();
+        grouped.add_group("Peak",[4]).unwrap();
+        grouped.smooth_with(&Smoothing::new(3,0.5).with_pinned_group("Peak")).unwrap();
+        assert_eq!(grouped.vertices()[4],Vertex::new(1.0,1.0,1.0));
+        assert_ne!(grouped.vertices()[0],Vertex::new(0.0,0.0,0.0));
+
+        let result = grouped.smooth_with(&Smoothing::new(1,0.5).with_pinned_group("Missing"));
+        assert!(matches!(result,Err(Error::UnknownGroup(_))));
+
+        // everything drifts towards the middle
+        g.smooth(10,0.5);
+        let bounds = g.bounds().unwrap();
+        assert!(bounds.size().x < 2.0);
+        assert!(bounds.size().z < 1.0);
+    }
+
+    #[test]
+    fn test_geometry_volume() {
+        let mut g = testing::cube();
+        g.transform(&Matrix::scale(2.0,3.0,4.0));
+        assert_relative_eq!(g.volume(),24.0);
+
+        g.flip();
+        assert_relative_eq!(g.volume(),-24.0);
+        assert_eq!(Geometry::default().volume(),0.0);
+
+        // flipping doesn't move the centroid
+        let centroid = g.centroid().unwrap();
+        assert_relative_eq!(centroid.x,1.0);
+        assert_relative_eq!(centroid.y,1.5);
+        assert_relative_eq!(centroid.z,2.0);
+        assert_eq!(Geometry::default().centroid(),None);
+    }
+
+    #[test]
+    fn test_geometry_hull() {
+        // a cube with points inside and on its faces
+        let mut points = testing::cube().vertices().clone();
+        points.push(Vertex::new(0.5,0.5,0.5));
+        points.push(Vertex::new(0.5,0.5,1.0));
+        points.push(Vertex::new(0.2,0.7,0.1));
+
+        let hull = Geometry::hull(&points);
+        assert_eq!(hull.vertices().len(),8);
+        assert_eq!(hull.faces().len(),12);
+        assert_relative_eq!(hull.volume(),1.0,epsilon = 1e-12);
+        assert_eq!(hull.edge_faces().values().filter(|f| f.len() != 2).count(),0);
+
+        // a tetrahedron around a cube corner cut off
+        let mut g = testing::cube();
+        g.vertices_mut()[6] = Vertex::new(0.5,0.5,0.5);
+        assert_relative_eq!(g.convex_hull().volume(),1.0 - 1.0 / 6.0,epsilon = 1e-12);
+
+        // flat points don't have a hull
+        let flat = [Vertex::new(0.0,0.0,0.0),Vertex::new(1.0,0.0,0.0),Vertex::new(0.0,1.0,0.0),Vertex::new(1.0,1.0,0.0)];
+        assert!(Geometry::hull(&flat).is_empty());
+        assert!(Geometry::hull(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_geometry_align() {
+        let target = {
+            let mut g = testing::cube();
+            g.transform(&Matrix::scale(2.0,1.0,0.5));
+            g
+        };
+
+        // points spread over every side of the box, as if scanned
+        let mut points = Vec::new();
+        for i in 0..=8 {
+            for j in 0..=8 {
+                let (u,v) = (i as f64 / 8.0,j as f64 / 8.0);
+                for side in [0.0,1.0] {
+                    points.push(Vertex::new(u * 2.0,v,side * 0.5));
+                    points.push(Vertex::new(u * 2.0,side,v * 0.5));
+                    points.push(Vertex::new(side * 2.0,u,v * 0.5));
+                }
+            }
+        }
+
+        let misplaced = Matrix::translate(0.05,-0.03,0.02) * Matrix::rotate(0.05,-0.03,0.1);
+        let mut scan = Geometry::new(points,vec![]);
+        scan.transform(&misplaced);
+
+        for settings in [Alignment::new(),Alignment::new().with_metric(Metric::Point).with_iterations(500)] {
+            let matrix = scan.align_with(&target,&settings);
+            let mut aligned = scan.clone();
+            aligned.transform(&matrix);
+
+            let worst = aligned.vertices().iter().map(|v| target.distance(v)).fold(0.0,f64::max);
+            assert!(worst < 1e-6,"{:?} is {} away",settings.metric(),worst);
+
+            let undone = (matrix * misplaced).unpack();
+            for (value,expected) in undone.iter().zip(Matrix::identity().unpack()) {
+                assert_relative_eq!(*value,expected,epsilon = 1e-6);
+            }
+        }
+
+        // stray points are left out when there's a limit
+        let mut noisy = scan.clone();
+        noisy.vertices_mut().push(Vertex::new(5.0,5.0,5.0));
+        let matrix = noisy.align_with(&target,&Alignment::new().with_limit(0.5));
+        assert_relative_eq!((matrix * misplaced).unpack()[3],0.0,epsilon = 1e-6);
+
+        assert_eq!(scan.align_to(&Geometry::default()).unpack(),Matrix::identity().unpack());
+    }
+
+    #[test]
+    fn test_geometry_deviation() {
+        let reference = testing::cube();
+        let mut scan = Geometry::new(vec![
+            Vertex::new(0.5,0.5,1.02),
+            Vertex::new(0.5,0.5,0.99),
+            Vertex::new(-0.01,0.5,0.5),
+            Vertex::new(1.0,0.3,0.2),
+        ],vec![]);
+
+        let summary = scan.deviation_from(&reference,"error").unwrap();
+        let values = scan.channel("error").unwrap();
+        for (value,expected) in values.iter().zip([0.02,-0.01,0.01,0.0]) {
+            assert_relative_eq!(*value,expected,epsilon = 1e-12);
+        }
+
+        assert_eq!(summary.count,4);
+        assert_relative_eq!(summary.min,-0.01,epsilon = 1e-12);
+        assert_relative_eq!(summary.max,0.02,epsilon = 1e-12);
+        assert_relative_eq!(summary.mean,0.005,epsilon = 1e-12);
+        assert_relative_eq!(summary.rms,(0.0006f64 / 4.0).sqrt(),epsilon = 1e-12);
+        assert!(summary.within(0.02 + 1e-12));
+        assert!(!summary.within(0.015));
+
+        assert!(matches!(scan.deviation_from(&Geometry::default(),"error"),Err(Error::EmptyGeometry)));
+        assert_eq!(Deviation::new(&[]),Deviation::default());
+    }
+
+    #[test]
+    fn test_geometry_surface_distance() {
+        let block = testing::cube();
+        let distances = block.surface_distances(&Vertex::new(0.0,0.0,0.0));
+        assert_relative_eq!(distances[0],0.0);
+        assert_relative_eq!(distances[7],1.0 + 2f64.sqrt(),epsilon = 1e-9);
+
+        // the opposite corner is further over the surface than straight through
+        let far = block.surface_distance(&Vertex::new(0.0,0.0,0.0),&Vertex::new(1.0,1.0,1.0)).unwrap();
+        assert!(far > 3f64.sqrt());
+
+        // points on the same face are straight across, and points
+        // off the surface start from the closest point on it
+        let near = block.surface_distance(&Vertex::new(0.1,0.3,0.0),&Vertex::new(0.2,0.4,0.0)).unwrap();
+        assert_relative_eq!(near,0.02f64.sqrt(),epsilon = 1e-9);
+        let path = block.surface_path(&Vertex::new(0.5,0.5,-3.0),&Vertex::new(0.5,0.5,4.0)).unwrap();
+        assert_relative_eq!(path[0].z,0.0);
+        assert_relative_eq!(path.last().unwrap().z,1.0);
+
+        // nothing to travel over
+        let empty = Geometry::default();
+        assert!(empty.surface_distance(&Vertex::default(),&Vertex::default()).is_none());
+        assert!(empty.surface_distances(&Vertex::default()).is_empty());
+    }
+
+    #[test]
+    fn test_geometry_decimate() {
+        let sphere = crate::testing::sphere(1.0,400);
+
+        let rough = sphere.decimate(0.5);
+        assert!(rough.size() < sphere.size() / 4);
+        assert!(rough.size() > 0);
+        assert!(rough.faces().iter().all(|f| f.is_valid(rough.vertices())));
+
+        // every vertex is used, and stays near the surface
+        let used = rough.faces().iter().flat_map(|f| [f.a,f.b,f.c]).collect::<BTreeSet<Index>>();
+        assert_eq!(used.len(),rough.vertices().len());
+        assert!(rough.vertices().iter().all(|v| (v.magnitude() - 1.0).abs() < 0.5));
+
+        // one cube around everything leaves nothing
+        assert!(testing::cube().decimate(10.0).is_empty());
+        assert_eq!(testing::cube().decimate(0.1).size(),12);
+    }
+
+    #[test]
+    fn test_geometry_from_bounds() {
+        let bounds = Bounds::new(Vertex::new(-1.0,0.0,2.0),Vertex::new(1.0,3.0,2.5));
+        let block = Geometry::from_bounds(&bounds);
+        assert_eq!(block.size(),12);
+        assert_relative_eq!(block.volume(),3.0,epsilon = 1e-9);
+        assert_eq!(block.bounds(),Some(bounds));
+        assert_eq!(block.edge_faces().values().filter(|f| f.len() != 2).count(),0);
+    }
+
+    #[test]
+    fn test_geometry_curvature() {
+        // points spread evenly over a sphere of radius 2
+        let count = 400;
+        let sphere = crate::testing::sphere(2.0,count);
+
+        let mean = sphere.curvature(Curvature::Mean);
+        let gaussian = sphere.curvature(Curvature::Gaussian);
+        assert_eq!(mean.len(),count);
+        for (h,k) in mean.iter().zip(gaussian.iter()) {
+            assert_relative_eq!(*h,0.5,max_relative = 0.05);
+            assert_relative_eq!(*k,0.25,max_relative = 0.1);
+        }
+
+        // turned inside out it bends the other way
+        let mut inside = sphere.clone();
+        inside.flip();
+        assert!(inside.curvature(Curvature::Mean).iter().all(|h| *h < 0.0));
+
+        // a flat cube only bends at the corners, which are all
+        // the same, and it goes into a channel
+        let mut block = testing::cube();
+        block.curvature_into(Curvature::Gaussian,"bend").unwrap();
+        let values = block.channel("bend").unwrap();
+        assert!(values.iter().all(|k| *k > 0.0));
+        assert!(values.iter().all(|k| (k - values[0]).abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_geometry_fillets() {
+        // an open tube of radius 0.5 around Z in five rings
+        let sides = 24;
+        let mut values = Vec::new();
+        for ring in 0..5 {
+            for side in 0..sides {
+                let angle = side as f64 * std::f64::consts::TAU / sides as f64;
+                values.extend([angle.cos() * 0.5,angle.sin() * 0.5,ring as f64 * 0.2]);
+            }
+        }
+        let mut indices = Vec::new();
+        for ring in 0..4 {
+            for side in 0..sides {
+                let a = ring * sides + side + 1;
+                let b = ring * sides + (side + 1) % sides + 1;
+                indices.extend([a,b,b + sides,a,b + sides,a + sides]);
+            }
+        }
+        let tube = Geometry::make(values,indices);
+
+        // only bends around, and not along
+        let minimum = tube.curvature(Curvature::Minimum);
+        let maximum = tube.curvature(Curvature::Maximum);
+        for i in sides..sides * 4 {
+            assert_relative_eq!(minimum[i],0.0,epsilon = 1e-6);
+            assert_relative_eq!(maximum[i],2.0,max_relative = 0.02);
+        }
+
+        // the open rings at each end are left out
+        let fillets = tube.fillets(0.05);
+        assert_eq!(fillets.len(),1);
+        assert_relative_eq!(fillets[0].radius,0.5,max_relative = 0.02);
+        assert_eq!(fillets[0].vertices,(sides..sides * 4).collect::<Vec<Index>>());
+
+        // a cube bends both ways at its corners, so it has none
+        assert!(testing::cube().fillets(0.05).is_empty());
+    }
+
+    #[test]
+    fn test_geometry_symmetries() {
+        let mut block = testing::cube();
+        block.transform(&Matrix::scale(2.0,1.0,0.5));
+
+        let found = block.symmetries(1e-9);
+        let mirrors = found.iter().filter(|s| matches!(s,Symmetry::Mirror { .. })).collect::<Vec<_>>();
+        let turns = found.iter().filter(|s| matches!(s,Symmetry::Rotation { order: 2, .. })).count();
+        assert_eq!(mirrors.len(),3);
+        assert_eq!(turns,3);
+
+        // the plane across the length swaps the ends
+        let across = mirrors
+            .iter()
+            .find(|s| matches!(s,Symmetry::Mirror { plane, .. } if plane.normal.x.abs() > 0.9))
+            .unwrap();
+        assert_eq!(across.map()[0],1);
+        assert_eq!(across.pairs(),vec![(0,1),(2,3),(4,5),(6,7)]);
+
+        // each symmetry moves the vertices onto the ones it maps them to
+        for symmetry in found.iter() {
+            let mut moved = block.clone();
+            moved.transform(&symmetry.matrix());
+            for (vertex,index) in moved.vertices().iter().zip(symmetry.map()) {
+                assert_relative_eq!(vertex.distance(&block.vertices()[*index]),0.0,epsilon = 1e-9);
+            }
+        }
+
+        // a hexagonal prism turns six ways around its length
+        let prism = Geometry::hull(&(0..12)
+            .map(|i| {
+                let angle = (i % 6) as f64 * std::f64::consts::TAU / 6.0;
+                Vertex::new(angle.cos(),angle.sin(),(i / 6) as f64 * 3.0)
+            })
+            .collect::<Vec<Vertex>>());
+        let found = prism.symmetries(1e-9);
+        assert_eq!(found.iter().filter(|s| matches!(s,Symmetry::Mirror { .. })).count(),7);
+        assert!(found.iter().any(|s| matches!(s,Symmetry::Rotation { order: 6, axis, .. } if axis.z.abs() > 0.99)));
+        assert_eq!(found.iter().filter(|s| matches!(s,Symmetry::Rotation { order: 2, .. })).count(),6);
+
+        // moving one corner breaks every symmetry
+        block.vertices_mut()[6] = Vertex::new(2.0,1.0,0.6);
+        assert!(block.symmetries(1e-9).is_empty());
+        assert!(Geometry::default().symmetries(1e-9).is_empty());
+    }
+
+    #[test]
+    fn test_geometry_congruent_to() {
+        // a lopsided tetrahedron, which has no symmetries to hide behind
+        let shape = Geometry::hull(&[
+            Vertex::new(0.0,0.0,0.0),
+            Vertex::new(2.0,0.0,0.0),
+            Vertex::new(0.5,1.5,0.0),
+            Vertex::new(0.3,0.4,1.2),
+        ]);
+
+        let matrix = Matrix::translate(1.0,2.0,3.0) * Matrix::rotate(0.4,-0.2,2.5);
+        let mut moved = shape.clone();
+        moved.transform(&matrix);
+
+        let found = shape.congruent_to(&moved,1e-9).unwrap();
+        let mut back = shape.clone();
+        back.transform(&found);
+        for (a,b) in back.vertices().iter().zip(moved.vertices()) {
+            assert_relative_eq!(a.distance(b),0.0,epsilon = 1e-9);
+        }
+
+        // a mirror image is a different shape
+        let mut mirrored = shape.clone();
+        mirrored.transform(&Matrix::scale(-1.0,1.0,1.0));
+        assert!(shape.congruent_to(&mirrored,1e-9).is_none());
+
+        // and so is one that's been stretched a little
+        let mut stretched = moved.clone();
+        stretched.transform(&Matrix::scale(1.001,1.0,1.0));
+        assert!(shape.congruent_to(&stretched,1e-9).is_none());
+        assert!(testing::cube().congruent_to(&testing::cube(),1e-9).is_some());
+    }
+
+    #[test]
+    fn test_geometry_decompose() {
+        let g = testing::cube();
+        let pieces = g.decompose(&Decomposition::new());
+        assert_eq!(pieces.len(),1);
+        assert_relative_eq!(pieces[0].volume(),1.0,epsilon = 1e-9);
+
+        // an L shape of three cubes splits into two convex pieces
+        let mut outline = Geometry::parse("
+            v 0 0 0\nv 2 0 0\nv 2 0 1\nv 1 0 1\nv 1 0 2\nv 0 0 2
+            v 0 1 0\nv 2 1 0\nv 2 1 1\nv 1 1 1\nv 1 1 2\nv 0 1 2
+            f 1 2 3\nf 1 3 4\nf 1 4 6\nf 4 5 6
+            f 7 9 8\nf 7 10 9\nf 7 12 10\nf 10 12 11
+            f 1 7 8\nf 1 8 2\nf 2 8 9\nf 2 9 3\nf 3 9 10\nf 3 10 4
+            f 4 10 11\nf 4 11 5\nf 5 11 12\nf 5 12 6\nf 6 12 7\nf 6 7 1
+        ",ParseMode::Strict).unwrap();
+        if outline.volume() < 0.0 {
+            outline.flip();
+        }
+        assert_relative_eq!(outline.volume(),3.0,epsilon = 1e-9);
+
+        let pieces = outline.decompose(&Decomposition::new().with_resolution(16));
+        assert_eq!(pieces.len(),2);
+        let total = pieces.iter().map(|p| p.volume()).sum::<f64>();
+        assert_relative_eq!(total,3.0,epsilon = 1e-6);
+        for piece in pieces.iter() {
+            assert_relative_eq!(piece.volume(),piece.convex_hull().volume(),epsilon = 1e-9);
+        }
+
+        // without any splits it's just the hull
+        let pieces = outline.decompose(&Decomposition::new().with_depth(0));
+        assert_eq!(pieces.len(),1);
+        assert_relative_eq!(pieces[0].volume(),3.5,epsilon = 1e-9);
+        assert!(Geometry::default().decompose(&Decomposition::new()).is_empty());
+    }
+
+    #[test]
+    fn test_geometry_slice_layers() {
+        // two towers on a shared base, with a hole through the base
+        let mut g = Geometry::from_bounds(&Bounds::new(Vertex::new(0.0,0.0,0.0),Vertex::new(5.0,1.0,1.0)));
+        g.append(&Geometry::from_bounds(&Bounds::new(Vertex::new(0.0,0.0,1.0),Vertex::new(1.0,1.0,3.0))),&Matrix::identity());
+        g.append(&Geometry::from_bounds(&Bounds::new(Vertex::new(4.0,0.0,1.0),Vertex::new(5.0,1.0,3.0))),&Matrix::identity());
+
+        let layers = g.slice_layers(0.5);
+        assert_eq!(layers.len(),6);
+        assert_relative_eq!(layers[0].z(),0.25);
+        assert_eq!(layers[0].islands().len(),1);
+        assert_relative_eq!(layers[0].area(),5.0);
+        assert_eq!(layers[5].islands().len(),2);
+        assert_relative_eq!(layers[5].area(),2.0);
+
+        let mut ring = Geometry::from_bounds(&Bounds::new(Vertex::new(0.0,0.0,0.0),Vertex::new(3.0,3.0,1.0)));
+        let mut hole = Geometry::from_bounds(&Bounds::new(Vertex::new(1.0,1.0,0.0),Vertex::new(2.0,2.0,1.0)));
+        hole.flip();
+        ring.append(&hole,&Matrix::identity());
+        let layers = ring.slice_layers(1.0);
+        assert_eq!(layers.len(),1);
+        assert_eq!(layers[0].islands()[0].holes().len(),1);
+        assert_relative_eq!(layers[0].area(),8.0);
+
+        assert!(g.slice_layers(0.0).is_empty());
+        assert!(Geometry::default().slice_layers(1.0).is_empty());
+    }
+
+    #[test]
+    fn test_geometry_slice() {
+        let mut g = testing::cube();
+        g.transform(&Matrix::scale(2.0,3.0,4.0));
+
+        let lines = g.slice(1.5);
+        assert_eq!(lines.len(),1);
+        assert!(lines[0].is_closed());
+        assert_relative_eq!(lines[0].signed_area(),6.0);
+        assert_relative_eq!(lines[0].length(),10.0);
+
+        g.flip();
+        assert_relative_eq!(g.slice(1.5)[0].signed_area(),-6.0);
+        assert!(g.slice(5.0).is_empty());
+    }
+
+    #[test]
+    fn test_geometry_intersects() {
+        let g = testing::cube();
+        assert!(g.encloses(&Vertex::new(0.5,0.5,0.5)));
+        assert!(!g.encloses(&Vertex::new(0.5,0.5,1.0)));
+        assert!(!g.encloses(&Vertex::new(1.5,0.5,0.5)));
+
+        let moved = |x: f64, y: f64, z: f64| {
+            let mut other = testing::cube();
+            other.transform(&Matrix::translate(x,y,z));
+            other
+        };
+
+        // overlapping corners, touching sides and apart
+        assert!(g.intersects(&moved(0.5,0.5,0.5)));
+        assert!(!g.intersects(&moved(1.0,0.0,0.0)));
+        assert!(!g.intersects(&moved(2.0,0.0,0.0)));
+
+        // a small cube entirely inside a big one
+        let mut small = testing::cube();
+        small.transform(&(Matrix::translate(0.25,0.25,0.25) * Matrix::scale(0.5,0.5,0.5)));
+        assert!(g.intersects(&small));
+        assert!(small.intersects(&g));
+    }
+
+    #[test]
+    fn test_geometry_boundary() {
+        let g = testing::cube();
+
+        // the two triangles on top share an edge
+        let lines = g.boundary(&[2,3]);
+        assert_eq!(lines.len(),1);
+        assert_eq!(lines[0].len(),4);
+        assert_relative_eq!(lines[0].signed_area(),1.0);
+        assert!(g.boundary(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_geometry_edges() {
+        let g = testing::cube();
+
+        // 12 cube edges plus a diagonal across each side
+        assert_eq!(g.edges().len(),18);
+        assert_eq!(g.feature_edges(0.1).len(),12);
+        assert_eq!(g.feature_edges(std::f64::consts::PI).len(),0);
+
+        // an open edge is always a feature
+        let g = Geometry::make(
+            vec![0.0,0.0,0.0, 1.0,0.0,0.0, 0.0,1.0,0.0],
+            vec![1, 2, 3]);
+        assert_eq!(g.feature_edges(std::f64::consts::PI).len(),3);
+    }
+
+    #[test]
+    fn test_geometry_silhouette_edges() {
+        let g = testing::cube();
+
+        // looking straight down only the top square is visible
+        let edges = g.silhouette_edges(&Vertex::new(0.5,0.5,10.0));
+        assert_eq!(edges,vec![(4,5),(4,6),(5,7),(6,7)]);
+
+        // from a corner the outline is a hexagon
+        let edges = g.silhouette_edges(&Vertex::new(10.0,10.0,10.0));
+        assert_eq!(edges.len(),6);
+    }
+
+    #[test]
+    fn test_geometry_convert_axes() {
+        let mut g = Geometry::make(
+            vec![0.0,0.0,0.0, 1.0,0.0,0.0, 0.0,1.0,0.0],
+            vec![1, 2, 3]);
+
+        // facing +Z, which is towards the viewer in Y-up
+        assert_eq!(g.get(0).normal(),Normal::new(0.0,0.0,1.0));
+
+        g.convert_axes(AxisConvention::Y_UP_RIGHT,AxisConvention::Z_UP_RIGHT);
+        assert_eq!(g.vertices()[2],Vertex::new(0.0,0.0,1.0));
+        assert_eq!(g.get(0).normal(),Normal::new(0.0,-1.0,0.0));
+
+        // mirroring reverses the winding so the normal still 
+        // points towards the viewer
+        g.convert_axes(AxisConvention::Z_UP_RIGHT,AxisConvention::Z_UP_LEFT);
+        assert_eq!(g.vertices()[0],Vertex::new(0.0,0.0,0.0));
+        assert_eq!(g.get(0).normal(),Normal::new(0.0,1.0,0.0));
+    }
+
+    #[test]
+    fn test_geometry_normals() {
+        let mut g = Geometry::make(
+            vec![0.0,0.0,0.0, 1.0,0.0,0.0, 0.0,1.0,0.0],
+            vec![1, 2, 3]);
+
+        assert!(g.normals().is_none());
+        assert!(g.set_normals(vec![Normal::default()]).is_err());
+
+        g.compute_normals();
+        assert_eq!(g.normals().unwrap(),&[Normal::new(0.0,0.0,1.0);3]);
+        assert!(g.remove_normals().is_some());
+        assert!(g.normals().is_none());
+    }
+
+    #[test]
+    fn test_geometry_normals_scaled() {
+        // a face tilted 45 degrees around the z axis
+        let mut g = Geometry::make(
+            vec![1.0,0.0,0.0, 0.0,1.0,0.0, 0.0,1.0,1.0],
+            vec![1, 2, 3]);
+
+        g.compute_normals();
+        g.transform(&(Matrix::scale(3.0,1.0,1.0) * Matrix::translate(1.0,2.0,3.0)));
+
+        // stored normals match the normal of the transformed face
+        let expected = g.get(0).normal();
+        let normal = g.normals().unwrap()[0];
+        assert_relative_eq!(normal.x,expected.x,epsilon = 1e-12);
+        assert_relative_eq!(normal.y,expected.y,epsilon = 1e-12);
+        assert_relative_eq!(normal.z,expected.z,epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_geometry_append_normals() {
+        let mut a = Geometry::make(
+            vec![0.0,0.0,0.0, 1.0,0.0,0.0, 0.0,1.0,0.0],
+            vec![1, 2, 3]);
+        let b = a.clone();
+        a.compute_normals();
+
+        a.append(&b,&Matrix::rotate_x(std::f64::consts::PI));
+
+        let normals = a.normals().unwrap();
+        assert_eq!(normals.len(),6);
+        assert_relative_eq!(normals[5].z,-1.0,epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_geometry_channels() {
+        let mut g = Geometry::make(
+            vec![0.0; 9],
+            vec![1, 2, 3]);
+
+        g.set_channel("weight",vec![1.0,2.0,3.0]).unwrap();
+        assert_eq!(g.channel("weight"),Some(&vec![1.0,2.0,3.0]));
+
+        let result = g.set_channel("bad",vec![1.0]);
+        assert!(matches!(result,Err(Error::ChannelLength { expected: 3, found: 1 })));
+
+        assert!(g.remove_channel("weight").is_some());
+        assert!(g.channels().is_empty());
+    }
+
+    #[test]
+    fn test_geometry_append() {
+        let mut a = Geometry::make(
+            vec![0.0,0.0,0.0, 1.0,0.0,0.0, 0.0,1.0,0.0],
+            vec![1, 2, 3]);
+        a.add_group("corner",[0]).unwrap();
+        a.set_channel("heat",vec![1.0,1.0,1.0]).unwrap();
+        a.clear_changes();
+
+        let mut b = a.clone();
+        b.remove_channel("heat");
+        b.set_channel("cold",vec![2.0,2.0,2.0]).unwrap();
+
+        let appended = a.append(&b,&Matrix::translate(0.0,0.0,1.0));
+
+        assert_eq!(appended.vertices,(3,6));
+        assert_eq!(appended.faces,(1,2));
+        assert_eq!(a.vertices().len(),6);
+        assert_eq!(a.vertices()[3].z,1.0);
+
+        let face = &a.faces()[1];
+        assert_eq!((face.a,face.b,face.c),(3,4,5));
+
+        assert_eq!(a.group("corner"),Some(&vec![0,3]));
+        assert_eq!(a.channel("heat"),Some(&vec![1.0,1.0,1.0,0.0,0.0,0.0]));
+        assert_eq!(a.channel("cold"),Some(&vec![0.0,0.0,0.0,2.0,2.0,2.0]));
+        assert_eq!(a.changes().ranges(),&[(3,6)]);
+    }
+
+    #[test]
+    fn test_geometry_append_mirrored() {
+        let cube = testing::cube();
+        let mut geometry = Geometry::default();
+        geometry.append(&cube,&Matrix::scale(-1.0,1.0,1.0));
+        assert_relative_eq!(geometry.volume(),cube.volume(),epsilon = 1e-12);
+
+        let face = &geometry.faces()[0];
+        assert_eq!((face.a,face.b,face.c),(cube.faces()[0].a,cube.faces()[0].c,cube.faces()[0].b));
+    }
+
+    #[test]
+    fn test_geometry_append_prefixed() {
+        let mut a = Geometry::make(
+            vec![0.0; 9],
+            vec![1, 2, 3]);
+        a.add_group("corner",[0]).unwrap();
+
+        let b = a.clone();
+        a.append_prefixed("copy",&b,&Matrix::translate(0.0,0.0,0.0));
+
+        assert_eq!(a.group("corner"),Some(&vec![0]));
+        assert_eq!(a.group("copy.corner"),Some(&vec![3]));
+    }
+
+    #[test]
+    fn test_geometry_groups() {
+        let mut g = Geometry::make(
+            vec![0.0; 9],
+            vec![1, 2, 3]);
+
+        g.add_group("ends",[0,2]).unwrap();
+        assert_eq!(g.group("ends"),Some(&vec![0,2]));
+
+        let result = g.add_group("bad",[3]);
+        assert!(matches!(result,Err(Error::IndexOutOfRange { index: 3, len: 3 })));
+        assert!(g.group("bad").is_none());
+
+        assert_eq!(g.remove_group("ends"),Some(vec![0,2]));
+        assert!(g.groups().is_empty());
+    }
+
+    #[test]
+    fn test_geometry_transform_marks_all() {
+        let mut g = Geometry::make(
+            vec![0.0; 9],
+            vec![1, 2, 3]);
+
+        g.transform(&Matrix::translate(1.0,0.0,0.0));
+        assert_eq!(g.changes().ranges(),&[(0,3)]);
+
+        g.clear_changes();
+        assert!(g.changes().is_empty());
     }
 
 }
\ No newline at end of file
@@ -0,0 +1,130 @@
+use std::collections::BTreeSet;
+
+use crate::geometry::*;
+use crate::constant::Index;
+
+// points closer to a face than this, relative to the size of the
+// point cloud, count as on it
+const EPSILON: f64 = 1e-9;
+
+// A face of the hull being built, with its outward plane
+struct Side {
+    corners: [Index;3],
+    normal: Vector,
+    offset: f64,
+}
+
+impl Side {
+
+    fn new(points: &[Vertex], corners: [Index;3]) -> Self {
+        let [a,b,c] = corners.map(|i| points[i]);
+        let normal = (b - a).cross(&(c - a)).normalize();
+        Self { corners, normal, offset: normal.dot(&a) }
+    }
+
+    // How far a point is in front of the face
+    fn height(&self, point: &Vertex) -> f64 {
+        self.normal.dot(point) - self.offset
+    }
+
+}
+
+// The index of the point that scores highest
+fn furthest<F: Fn(&Vertex) -> f64>(points: &[Vertex], score: F) -> Index {
+    points
+        .iter()
+        .enumerate()
+        .max_by(|(_,a),(_,b)| score(a).total_cmp(&score(b)))
+        .map(|(i,_)| i)
+        .unwrap_or_default()
+}
+
+// The smallest convex surface around a set of points, built by
+// adding one point at a time and replacing the faces it can see.
+// Faces wind counter-clockwise seen from outside. Returns nothing
+// if the points don't span a volume.
+pub(crate) fn convex(points: &[Vertex]) -> (Vec<Vertex>,Vec<Face>) {
+    let Some(bounds) = Bounds::from_points(points) else {
+        return (Vec::new(),Vec::new());
+    };
+    let epsilon = EPSILON * (1.0 + bounds.size().magnitude());
+
+    // a starting tetrahedron from points far apart
+    let a = furthest(points,|p| -p.x);
+    let b = furthest(points,|p| p.distance(&points[a]));
+    let line = (points[b] - points[a]).normalize();
+    let c = furthest(points,|p| {
+        let v = *p - points[a];
+        (v - line * v.dot(&line)).magnitude()
+    });
+    let normal = (points[b] - points[a]).cross(&(points[c] - points[a])).normalize();
+    let d = furthest(points,|p| normal.dot(&(*p - points[a])).abs());
+
+    if normal.dot(&(points[d] - points[a])).abs() <= epsilon || normal.magnitude() == 0.0 {
+        return (Vec::new(),Vec::new());
+    }
+
+    let center = (points[a] + points[b] + points[c] + points[d]) * 0.25;
+    let mut sides = [[a,b,c],[a,c,d],[a,d,b],[b,d,c]]
+        .into_iter()
+        .map(|corners| {
+            let side = Side::new(points,corners);
+            match side.height(&center) > 0.0 {
+                true => Side::new(points,[corners[0],corners[2],corners[1]]),
+                false => side,
+            }
+        })
+        .collect::<Vec<Side>>();
+
+    for (index,point) in points.iter().enumerate() {
+        let visible = sides
+            .iter()
+            .map(|s| s.height(point) > epsilon)
+            .collect::<Vec<bool>>();
+
+        if !visible.iter().any(|v| *v) {
+            continue;
+        }
+
+        // the edges around the visible faces, which the
+        // new faces fan out from
+        let edges = sides
+            .iter()
+            .zip(visible.iter())
+            .filter(|(_,v)| **v)
+            .flat_map(|(s,_)| {
+                let [p,q,r] = s.corners;
+                [(p,q),(q,r),(r,p)]
+            })
+            .collect::<BTreeSet<(Index,Index)>>();
+
+        let horizon = edges
+            .iter()
+            .filter(|(p,q)| !edges.contains(&(*q,*p)))
+            .copied()
+            .collect::<Vec<(Index,Index)>>();
+
+        let mut kept = Vec::with_capacity(sides.len());
+        for (side,visible) in sides.into_iter().zip(visible) {
+            if !visible {
+                kept.push(side);
+            }
+        }
+        sides = kept;
+        sides.extend(horizon.into_iter().map(|(p,q)| Side::new(points,[p,q,index])));
+    }
+
+    // only keep the points the faces use
+    let mut map = vec![None;points.len()];
+    let mut vertices = Vec::new();
+    let faces = sides
+        .iter()
+        .map(|s| s.corners.map(|i| *map[i].get_or_insert_with(|| {
+            vertices.push(points[i]);
+            vertices.len()
+        })))
+        .map(|[a,b,c]| Face::new(a,b,c))
+        .collect();
+
+    (vertices,faces)
+}
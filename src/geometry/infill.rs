@@ -0,0 +1,270 @@
+use std::f64::consts::{PI,FRAC_PI_2,FRAC_PI_4};
+
+use crate::geometry::*;
+
+/// The pattern of paths that fill the inside of a layer
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq)]
+pub enum InfillPattern {
+    /// parallel lines in one direction
+    #[default]
+    Rectilinear,
+    /// parallel lines in two directions at right angles
+    Grid,
+    /// waves that stack into a gyroid from layer to layer
+    Gyroid,
+}
+
+/// Settings for filling the islands of a sliced layer with paths
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub struct Infill {
+    pattern: InfillPattern,
+    density: f64,
+    width: f64,
+    angle: f64,
+}
+
+impl Default for Infill {
+    fn default() -> Self {
+        Self {
+            pattern: InfillPattern::Rectilinear,
+            density: 0.2,
+            width: 0.0004,
+            angle: FRAC_PI_4,
+        }
+    }
+}
+
+impl Infill {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_pattern(mut self, pattern: InfillPattern) -> Self {
+        self.pattern = pattern;
+        self
+    }
+
+    // How much of the inside is filled, from 0 for nothing to 1 for
+    // paths laid right next to each other
+    pub fn with_density(mut self, density: f64) -> Self {
+        self.density = density.clamp(0.0,1.0);
+        self
+    }
+
+    // The width of the line each path lays down
+    pub fn with_width(mut self, width: f64) -> Self {
+        self.width = width.abs();
+        self
+    }
+
+    // The direction of the lines in radians from +X, which slicers
+    // usually turn a quarter turn every layer
+    pub fn with_angle(mut self, angle: f64) -> Self {
+        self.angle = angle;
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn pattern(&self) -> InfillPattern {
+        self.pattern
+    }
+
+    pub fn density(&self) -> f64 {
+        self.density
+    }
+
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+
+    pub fn angle(&self) -> f64 {
+        self.angle
+    }
+
+    // The distance between neighbouring paths for the density
+    pub fn spacing(&self) -> f64 {
+        let spacing = self.width / self.density;
+        match self.pattern {
+            InfillPattern::Grid => spacing * 2.0,
+            _ => spacing,
+        }
+    }
+
+}
+
+pub(crate) fn fill(layer: &Layer, settings: &Infill) -> Vec<Polyline> {
+    if settings.density <= 0.0 || settings.width <= 0.0 {
+        return Vec::new();
+    }
+    let spacing = settings.spacing();
+    layer
+        .islands()
+        .iter()
+        .flat_map(|island| match settings.pattern {
+            InfillPattern::Rectilinear => lines(island,spacing,settings.angle),
+            InfillPattern::Grid => {
+                let mut result = lines(island,spacing,settings.angle);
+                result.extend(lines(island,spacing,settings.angle + FRAC_PI_2));
+                result
+            },
+            InfillPattern::Gyroid => gyroid(island,spacing * 2.0,layer.z(),settings.width),
+        })
+        .collect()
+}
+
+// True if a point is inside the outline of an island and outside
+// all of its holes
+fn inside(island: &Profile, point: Point) -> bool {
+    island.outer().contains(point) && !island.holes().iter().any(|h| h.contains(point))
+}
+
+// Straight lines across an island at an angle, lined up on a grid
+// through the origin so the lines of one layer sit on the next.
+// The island is turned so the lines run along X and each line is
+// cut where it crosses the loops, then turned back.
+fn lines(island: &Profile, spacing: f64, angle: f64) -> Vec<Polyline> {
+    let (sin,cos) = angle.sin_cos();
+    let turned = island.map(|(x,y)| (x * cos + y * sin,y * cos - x * sin));
+    let Some(((_,y0),(_,y1))) = turned.bounds() else {
+        return Vec::new();
+    };
+
+    let mut result = Vec::new();
+    let first = (y0 / spacing - 0.5).ceil() as i64;
+    let last = (y1 / spacing - 0.5).floor() as i64;
+    for row in first..=last {
+        let y = spacing * (row as f64 + 0.5);
+        let mut crossings = turned
+            .loops()
+            .flat_map(|l| l.segments())
+            .filter(|((_,ay),(_,by))| (*ay > y) != (*by > y))
+            .map(|((ax,ay),(bx,by))| ax + (y - ay) * (bx - ax) / (by - ay))
+            .collect::<Vec<f64>>();
+        crossings.sort_by(f64::total_cmp);
+
+        for pair in crossings.chunks_exact(2) {
+            let line = Polyline::new(vec![(pair[0],y),(pair[1],y)]);
+            result.push(line.map(|(x,y)| (x * cos - y * sin,x * sin + y * cos)));
+        }
+    }
+    result
+}
+
+// The curves where a gyroid with a period crosses the height of
+// the layer, in the island. At a height the gyroid is
+//
+//     sin(x)cos(y) + sin(y)cos(z) + sin(z)cos(x) = 0
+//
+// which for each x has a pair of answers for y in every period,
+// so each curve is traced along X a step at a time and broken off
+// wherever there's no answer or it leaves the island.
+fn gyroid(island: &Profile, period: f64, z: f64, step: f64) -> Vec<Polyline> {
+    let Some(((x0,y0),(x1,y1))) = island.bounds() else {
+        return Vec::new();
+    };
+
+    let scale = 2.0 * PI / period;
+    let (sin_z,cos_z) = (z * scale).sin_cos();
+    let step = step.max(period / 32.0);
+    let columns = ((x1 - x0) / step).ceil() as usize + 1;
+    let first = (y0 * scale / (2.0 * PI)).floor() as i64 - 1;
+    let last = (y1 * scale / (2.0 * PI)).ceil() as i64 + 1;
+
+    let mut result = Vec::new();
+    for branch in [1.0,-1.0] {
+        for k in first..=last {
+            let mut current: Vec<Point> = Vec::new();
+            for column in 0..columns {
+                let x = (x0 + step * column as f64).min(x1);
+                let (sin_x,cos_x) = (x * scale).sin_cos();
+
+                // sin(x)cos(y) + cos(z)sin(y) = r.cos(y - phase)
+                let r = sin_x.hypot(cos_z);
+                let c = -sin_z * cos_x;
+                let point = match r > f64::EPSILON && c.abs() <= r {
+                    true => {
+                        let y = cos_z.atan2(sin_x) + branch * (c / r).acos() + 2.0 * PI * k as f64;
+                        Some((x,y / scale)).filter(|p| inside(island,*p))
+                    },
+                    false => None,
+                };
+
+                match point {
+                    Some(point) => current.push(point),
+                    None if current.len() > 1 => result.push(Polyline::new(std::mem::take(&mut current))),
+                    None => current.clear(),
+                }
+            }
+            if current.len() > 1 {
+                result.push(Polyline::new(current));
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // A square 10 across with a square hole 2 across in the middle
+    fn layer(z: f64) -> Layer {
+        let outer = Polyline::closed(vec![(0.0,0.0),(10.0,0.0),(10.0,10.0),(0.0,10.0)]);
+        let hole = Polyline::closed(vec![(4.0,4.0),(6.0,4.0),(6.0,6.0),(4.0,6.0)]);
+        Layer::new(z,vec![Profile::new(outer).with_hole(hole)])
+    }
+
+    fn length(paths: &[Polyline]) -> f64 {
+        paths.iter().map(Polyline::length).sum()
+    }
+
+    #[test]
+    fn test_infill_lines() {
+        let settings = Infill::new()
+            .with_width(1.0)
+            .with_density(0.5)
+            .with_angle(0.0)
+            .build();
+        let paths = layer(0.0).infill(&settings);
+
+        // lines at 1, 3, 5, 7 and 9 with the one at 5 cut by the hole
+        assert_eq!(paths.len(),6);
+        assert_relative_eq!(length(&paths),48.0,epsilon = 1e-9);
+        assert!(paths.iter().all(|p| p.points()[0].1 == p.points()[1].1));
+
+        // the same amount of material in two directions
+        let grid = layer(0.0).infill(&settings.with_pattern(InfillPattern::Grid));
+        assert_relative_eq!(length(&grid),length(&paths),epsilon = 2.0);
+
+        // turned lines stay inside the island
+        let turned = layer(0.0).infill(&settings.with_angle(FRAC_PI_4));
+        assert!(turned.iter().flat_map(|p| p.points().iter()).all(|p| (-1e-9..=10.0 + 1e-9).contains(&p.0)));
+        assert!(layer(0.0).infill(&settings.with_density(0.0)).is_empty());
+    }
+
+    #[test]
+    fn test_infill_gyroid() {
+        let settings = Infill::new()
+            .with_width(0.25)
+            .with_density(0.1)
+            .with_pattern(InfillPattern::Gyroid)
+            .build();
+
+        let first = layer(0.3);
+        let paths = first.infill(&settings);
+        assert!(!paths.is_empty());
+        assert!(paths.iter().flat_map(|p| p.points().iter()).all(|p| inside(&first.islands()[0],*p)));
+
+        // roughly as much path as lines at the same density, and a
+        // different pattern on the next layer up
+        let lines = length(&layer(0.3).infill(&settings.with_pattern(InfillPattern::Rectilinear)));
+        assert!((length(&paths) - lines).abs() < lines * 0.5);
+        assert_ne!(layer(1.3).infill(&settings),paths);
+    }
+
+}
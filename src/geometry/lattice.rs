@@ -0,0 +1,264 @@
+use std::f64::consts::SQRT_2;
+
+use crate::geometry::*;
+use crate::constant::{Index,TOLERANCE};
+
+/// The arrangement of struts repeated in every cell of a lattice
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq)]
+pub enum LatticeKind {
+    /// struts along the edges of cubes
+    #[default]
+    Grid,
+    /// struts between each corner and face centre of the cubes and
+    /// their nearest neighbours, which is stiffer for its weight
+    Octet,
+}
+
+/// Settings for filling the inside of a closed mesh with a lattice
+/// of square struts
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub struct Lattice {
+    kind: LatticeKind,
+    cell: f64,
+    density: f64,
+}
+
+impl Default for Lattice {
+    fn default() -> Self {
+        Self {
+            kind: LatticeKind::Grid,
+            cell: 0.01,
+            density: 0.2,
+        }
+    }
+}
+
+impl Lattice {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_kind(mut self, kind: LatticeKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    // The size of the cube the pattern repeats in
+    pub fn with_cell(mut self, cell: f64) -> Self {
+        self.cell = cell.abs().max(TOLERANCE);
+        self
+    }
+
+    // How much of each cell is filled by struts, from 0 to 1, which
+    // sets how wide they are
+    pub fn with_density(mut self, density: f64) -> Self {
+        self.density = density.clamp(0.0,1.0);
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn kind(&self) -> LatticeKind {
+        self.kind
+    }
+
+    pub fn cell(&self) -> f64 {
+        self.cell
+    }
+
+    pub fn density(&self) -> f64 {
+        self.density
+    }
+
+    // The width of the struts for the density, ignoring where they
+    // overlap at the nodes. A grid has three struts a cell long in
+    // each cell, and an octet has twenty four half a diagonal long.
+    pub fn width(&self) -> f64 {
+        let length = match self.kind {
+            LatticeKind::Grid => 3.0,
+            LatticeKind::Octet => 24.0 / SQRT_2,
+        };
+        self.cell * (self.density / length).sqrt()
+    }
+
+}
+
+// The lattice inside a mesh with struts of no width, along with the
+// vertices that move out from the middle of the struts together and
+// the way they go for each unit of width
+pub(crate) fn lattice(geometry: &Geometry, settings: &Lattice) -> (Geometry,Vec<(Vector,Vec<Index>)>) {
+    let mut result = Geometry::default();
+    let mut offsets: Vec<(Vector,Vec<Index>)> = Vec::new();
+    for (a,b) in struts(geometry,settings) {
+        let start = result.vertices().len();
+        let section = section(b - a);
+        result.append(&strut(a,b,0.0),&Matrix::identity());
+        for (corner,offset) in section.iter().chain(section.iter()).enumerate() {
+            match offsets.iter_mut().find(|(o,_)| (*o - *offset).magnitude() <= TOLERANCE) {
+                Some((_,indices)) => indices.push(start + corner),
+                None => offsets.push((*offset,vec![start + corner])),
+            }
+        }
+    }
+    (result,offsets)
+}
+
+// The struts of the lattice, cut off where they leave the mesh. The
+// nodes are half a cell in from the corner of the mesh's bounds, so
+// that the struts of a box end on its walls rather than along them.
+fn struts(geometry: &Geometry, settings: &Lattice) -> Vec<(Vertex,Vertex)> {
+    let Some(bounds) = geometry.bounds() else {
+        return Vec::new();
+    };
+
+    let cell = settings.cell;
+    let steps: &[(f64,f64,f64)] = match settings.kind {
+        LatticeKind::Grid => &[(1.0,0.0,0.0),(0.0,1.0,0.0),(0.0,0.0,1.0)],
+        LatticeKind::Octet => &[
+            (0.5,0.5,0.0),(0.5,-0.5,0.0),(0.5,0.0,0.5),
+            (0.5,0.0,-0.5),(0.0,0.5,0.5),(0.0,0.5,-0.5),
+        ],
+    };
+    let nodes: &[(f64,f64,f64)] = match settings.kind {
+        LatticeKind::Grid => &[(0.0,0.0,0.0)],
+        LatticeKind::Octet => &[(0.0,0.0,0.0),(0.5,0.5,0.0),(0.5,0.0,0.5),(0.0,0.5,0.5)],
+    };
+
+    // one cell more than the bounds on each side, so struts coming
+    // in from outside are cut at the surface too
+    let size = bounds.size();
+    let counts = [size.x,size.y,size.z].map(|s| (s / cell).ceil() as i64 + 1);
+    let origin = bounds.min + Vector::new(0.5,0.5,0.5) * cell;
+
+    let mut result = Vec::new();
+    for i in -1..counts[0] {
+        for j in -1..counts[1] {
+            for k in -1..counts[2] {
+                for node in nodes.iter() {
+                    let a = origin + Vector::new(i as f64 + node.0,j as f64 + node.1,k as f64 + node.2) * cell;
+                    for step in steps.iter() {
+                        let b = a + Vector::new(step.0,step.1,step.2) * cell;
+                        result.extend(clip(geometry,a,b));
+                    }
+                }
+            }
+        }
+    }
+    result
+}
+
+// The parts of the segment from `a` to `b` that are inside the mesh.
+// A segment with one end inside is cut where it leaves, found by
+// halving the segment since it can leave through an edge. One with
+// both ends inside is cut either side of any gap it crosses, and one
+// with both ends outside is left out even if it passes through.
+fn clip(geometry: &Geometry, a: Vertex, b: Vertex) -> Vec<(Vertex,Vertex)> {
+    let result = match (geometry.encloses(&a),geometry.encloses(&b)) {
+        (true,true) => {
+            let mut crossings = geometry
+                .faces()
+                .iter()
+                .filter(|f| f.is_valid(geometry.vertices()))
+                .filter_map(|f| f.triangle(geometry.vertices()).crossing(&a,&b))
+                .collect::<Vec<Vertex>>();
+            crossings.sort_by(|p,q| p.distance(&a).total_cmp(&q.distance(&a)));
+            match (crossings.first(),crossings.last()) {
+                (Some(first),Some(last)) => vec![(a,*first),(*last,b)],
+                _ => vec![(a,b)],
+            }
+        },
+        (true,false) => vec![(a,surface(geometry,a,b))],
+        (false,true) => vec![(surface(geometry,b,a),b)],
+        (false,false) => Vec::new(),
+    };
+    result
+        .into_iter()
+        .filter(|(a,b)| a.distance(b) > TOLERANCE)
+        .collect()
+}
+
+// Where a segment from a point inside the mesh to one outside it
+// crosses the surface, to within the tolerance
+fn surface(geometry: &Geometry, mut inside: Vertex, mut outside: Vertex) -> Vertex {
+    while inside.distance(&outside) > TOLERANCE {
+        let middle = (inside + outside) / 2;
+        match geometry.encloses(&middle) {
+            true => inside = middle,
+            false => outside = middle,
+        }
+    }
+    (inside + outside) / 2
+}
+
+// The corners of a square a unit across around a line along `axis`,
+// in the order `strut` puts them at each end
+fn section(axis: Vector) -> [Vector;4] {
+    let axis = axis.normalize();
+    let up = match axis.z.abs() < 0.9 {
+        true => Vector::new(0.0,0.0,1.0),
+        false => Vector::new(1.0,0.0,0.0),
+    };
+    let u = axis.cross(&up).normalize() * 0.5;
+    let v = axis.cross(&u).normalize() * 0.5;
+    [
+        (u * -1.0) - v, u - v,
+        v - u, u + v,
+    ]
+}
+
+// A square bar of a width from one point to another
+pub(crate) fn strut(a: Vertex, b: Vertex, width: f64) -> Geometry {
+    let section = section(b - a);
+    let vertices = [a,b]
+        .into_iter()
+        .flat_map(|end| section.map(|s| end + s * width))
+        .collect::<Vec<Vertex>>();
+    Geometry::new(vertices,BOX_FACES.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::testing::cube;
+
+    #[test]
+    fn test_lattice_grid() {
+        let settings = Lattice::new()
+            .with_cell(0.25)
+            .with_density(0.03)
+            .build();
+        assert_relative_eq!(settings.width(),0.025);
+
+        // four nodes a side, each with a strut to the next one and
+        // the ones on the far sides cut off at the walls
+        let lattice = cube().lattice(&settings);
+        assert_eq!(lattice.size(),(3 * 4 * 4 * 4 + 3 * 4 * 4) * 12);
+        let bounds = lattice.bounds().unwrap();
+        assert_relative_eq!(bounds.min.x,0.0,epsilon = 1e-5);
+        assert_relative_eq!(bounds.max.z,1.0,epsilon = 1e-5);
+
+        // the struts along X all reach from wall to wall
+        let length = 0.25 * 4.0;
+        assert_relative_eq!(lattice.volume(),3.0 * 16.0 * length * 0.025 * 0.025,epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_lattice_octet() {
+        let settings = Lattice::new()
+            .with_kind(LatticeKind::Octet)
+            .with_cell(0.5)
+            .build();
+        let lattice = cube().lattice(&settings);
+        assert!(!lattice.is_empty());
+
+        // every strut is inside, give or take its width
+        let bounds = Bounds::new(Vertex::new(-0.1,-0.1,-0.1),Vertex::new(1.1,1.1,1.1));
+        assert!(lattice.vertices().iter().all(|v| bounds.contains(v)));
+        assert!(Geometry::default().lattice(&settings).is_empty());
+    }
+
+}
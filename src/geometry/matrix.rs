@@ -1,9 +1,10 @@
-use crate::geometry::*;
 use std::{fmt,ops::Mul};
 
+use crate::geometry::Vector;
+
 type Data = [f64;16];
 
-#[derive(Default,Copy,Clone)]
+#[derive(Default,Copy,Clone,PartialEq)]
 pub struct Matrix {
     data: Data,
 }
@@ -25,11 +26,11 @@ impl fmt::Debug for Matrix {
             m41, m42, m43, m44
         ] = self.unpack();
 
-        write!(f, "Matrix {{\n")?;
-        write!(f, "    {} {} {} {}\n",m11,m12,m13,m14);
-        write!(f, "    {} {} {} {}\n",m21,m22,m23,m24);
-        write!(f, "    {} {} {} {}\n",m31,m32,m33,m34);
-        write!(f, "    {} {} {} {}\n",m41,m42,m43,m44);
+        writeln!(f, "Matrix {{")?;
+        writeln!(f, "    {} {} {} {}",m11,m12,m13,m14)?;
+        writeln!(f, "    {} {} {} {}",m21,m22,m23,m24)?;
+        writeln!(f, "    {} {} {} {}",m31,m32,m33,m34)?;
+        writeln!(f, "    {} {} {} {}",m41,m42,m43,m44)?;
         write!(f, "}}")
     }
 }
@@ -47,6 +48,10 @@ impl Matrix {
         }
     }
 
+    pub fn identity() -> Self {
+        Self::scale(1.0,1.0,1.0)
+    }
+
     pub fn unpack(&self) -> [f64;16] {
         self.data
     }
@@ -108,6 +113,156 @@ impl Matrix {
         Self::rotate_z(z)
     }
 
+    // A rotation by `angle` radians around an axis through the
+    // origin, counter-clockwise looking back down the axis
+    pub fn rotate_axis(axis: Vector, angle: f64) -> Self {
+        let (x,y,z) = axis.normalize().unpack();
+        let (s,c) = angle.sin_cos();
+        let t = 1.0 - c;
+        Self::new([
+            t * x * x + c,     t * x * y - s * z, t * x * z + s * y, 0.0,
+            t * x * y + s * z, t * y * y + c,     t * y * z - s * x, 0.0,
+            t * x * z - s * y, t * y * z + s * x, t * z * z + c,     0.0,
+            0.0,               0.0,               0.0,               1.0,
+        ])
+    }
+
+    // A right-handed view matrix for a camera at `eye` looking
+    // towards `target`, which maps the camera to the origin looking
+    // down -Z.
+    pub fn look_at(eye: Vector, target: Vector, up: Vector) -> Self {
+        let f = (target - eye).normalize();
+        let s = f.cross(&up).normalize();
+        let u = s.cross(&f);
+
+        Self::new([
+             s.x,  s.y,  s.z, -s.dot(&eye),
+             u.x,  u.y,  u.z, -u.dot(&eye),
+            -f.x, -f.y, -f.z,  f.dot(&eye),
+             0.0,  0.0,  0.0,  1.0,
+        ])
+    }
+
+    // A perspective projection into clip space (-1 to 1 on every
+    // axis) with a vertical field of view in radians.
+    pub fn perspective(fov: f64, aspect: f64, near: f64, far: f64) -> Self {
+        let f = 1.0 / (fov / 2.0).tan();
+        let d = near - far;
+
+        Self::new([
+            f / aspect, 0.0,               0.0,                     0.0,
+                   0.0,   f,               0.0,                     0.0,
+                   0.0, 0.0, (far + near) / d, (2.0 * far * near) / d,
+                   0.0, 0.0,              -1.0,                     0.0,
+        ])
+    }
+
+    // An orthographic projection of the given box into clip space
+    pub fn orthographic(left: f64, right: f64, bottom: f64, top: f64, near: f64, far: f64) -> Self {
+        let w = right - left;
+        let h = top - bottom;
+        let d = far - near;
+
+        Self::new([
+            2.0 / w,     0.0,      0.0, -(right + left) / w,
+                0.0, 2.0 / h,      0.0, -(top + bottom) / h,
+                0.0,     0.0, -2.0 / d,   -(far + near) / d,
+                0.0,     0.0,      0.0,                 1.0,
+        ])
+    }
+
+    pub fn transpose(&self) -> Self {
+        let d = self.data;
+        let mut data = [0.0;16];
+        for i in 0..4 {
+            for j in 0..4 {
+                data[j * 4 + i] = d[i * 4 + j];
+            }
+        }
+        Self { data }
+    }
+
+    // The determinant of the upper 3x3 (linear) part, which is
+    // negative if the matrix mirrors geometry.
+    pub fn determinant(&self) -> f64 {
+        let [
+            m11, m12, m13, _,
+            m21, m22, m23, _,
+            m31, m32, m33, _,
+            _, _, _, _
+        ] = self.data;
+
+        m11 * (m22 * m33 - m23 * m32) -
+        m12 * (m21 * m33 - m23 * m31) +
+        m13 * (m21 * m32 - m22 * m31)
+    }
+
+    // The matrix to transform normals with: the inverse-transpose of
+    // the linear part, without translation. It's built from the
+    // cofactors so that it still gives usable (if degenerate)
+    // normals when the matrix flattens geometry.
+    pub fn normal(&self) -> Self {
+        let [
+            m11, m12, m13, _,
+            m21, m22, m23, _,
+            m31, m32, m33, _,
+            _, _, _, _
+        ] = self.data;
+
+        let c11 = m22 * m33 - m23 * m32;
+        let c12 = m23 * m31 - m21 * m33;
+        let c13 = m21 * m32 - m22 * m31;
+        let c21 = m13 * m32 - m12 * m33;
+        let c22 = m11 * m33 - m13 * m31;
+        let c23 = m12 * m31 - m11 * m32;
+        let c31 = m12 * m23 - m13 * m22;
+        let c32 = m13 * m21 - m11 * m23;
+        let c33 = m11 * m22 - m12 * m21;
+
+        // cofactors equal the inverse-transpose scaled by the
+        // determinant, so only the sign needs correcting
+        let s = if self.determinant() < 0.0 { -1.0 } else { 1.0 };
+
+        Self::new([
+            c11 * s, c12 * s, c13 * s, 0.0,
+            c21 * s, c22 * s, c23 * s, 0.0,
+            c31 * s, c32 * s, c33 * s, 0.0,
+                0.0,     0.0,     0.0, 1.0,
+        ])
+    }
+
+    // The matrix that undoes this one, for matrices that scale,
+    // turn and move geometry without perspective. None if the
+    // matrix flattens geometry, which can't be undone.
+    pub fn inverse(&self) -> Option<Self> {
+        let determinant = self.determinant();
+        if determinant.abs() <= f64::EPSILON {
+            return None;
+        }
+
+        // the normal matrix is the inverse-transpose up to the
+        // size of the determinant
+        let scale = 1.0 / determinant.abs();
+        let [
+            a11, a12, a13, _,
+            a21, a22, a23, _,
+            a31, a32, a33, _,
+            _, _, _, _
+        ] = self.normal().transpose().unpack();
+
+        let (a11,a12,a13) = (a11 * scale,a12 * scale,a13 * scale);
+        let (a21,a22,a23) = (a21 * scale,a22 * scale,a23 * scale);
+        let (a31,a32,a33) = (a31 * scale,a32 * scale,a33 * scale);
+        let (x,y,z) = (self.data[3],self.data[7],self.data[11]);
+
+        Some(Self::new([
+            a11, a12, a13, -(a11 * x + a12 * y + a13 * z),
+            a21, a22, a23, -(a21 * x + a22 * y + a23 * z),
+            a31, a32, a33, -(a31 * x + a32 * y + a33 * z),
+            0.0, 0.0, 0.0, 1.0,
+        ]))
+    }
+
     pub fn matching(v: MatrixType, x: f64, y: f64, z: f64) -> Self {
         match v {
             MatrixType::Scale => Self::scale(x,y,z),
@@ -209,6 +364,117 @@ mod tests {
 
     }
 
+    #[test]
+    fn test_rotate_axis_matrix() {
+        let a = Matrix::rotate_axis(Vector::new(0.0,0.0,2.0),1.2);
+        let b = Matrix::rotate_z(1.2);
+        for (a,b) in a.unpack().iter().zip(b.unpack().iter()) {
+            assert_relative_eq!(a,b,epsilon = 1e-12);
+        }
+
+        // a third of a turn around the diagonal swaps the axes
+        let mut v = Vertex::new(1.0,0.0,0.0);
+        v.transform(&Matrix::rotate_axis(Vector::new(1.0,1.0,1.0),std::f64::consts::TAU / 3.0));
+        assert_relative_eq!(v.x,0.0,epsilon = 1e-12);
+        assert_relative_eq!(v.y,1.0,epsilon = 1e-12);
+        assert_relative_eq!(v.z,0.0,epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_look_at_matrix() {
+        let m = Matrix::look_at(
+            Vertex::new(0.0,0.0,5.0),
+            Vertex::new(0.0,0.0,0.0),
+            Vector::new(0.0,1.0,0.0));
+
+        // the target ends up straight ahead of the camera
+        let mut target = Vertex::new(0.0,0.0,0.0);
+        target.transform(&m);
+        fassert_eq!(target.x, 0.0);
+        fassert_eq!(target.y, 0.0);
+        fassert_eq!(target.z, -5.0);
+
+        // and up stays up
+        let mut above = Vertex::new(0.0,1.0,0.0);
+        above.transform(&m);
+        fassert_eq!(above.y, 1.0);
+    }
+
+    #[test]
+    fn test_perspective_matrix() {
+        let m = Matrix::perspective(std::f64::consts::FRAC_PI_2,2.0,1.0,10.0);
+
+        let mut near = Vertex::new(0.0,0.0,-1.0);
+        let mut far = Vertex::new(0.0,0.0,-10.0);
+        let mut edge = Vertex::new(2.0,1.0,-1.0);
+        near.transform(&m);
+        far.transform(&m);
+        edge.transform(&m);
+
+        assert_relative_eq!(near.z, -1.0, epsilon = 1e-12);
+        assert_relative_eq!(far.z, 1.0, epsilon = 1e-12);
+        assert_relative_eq!(edge.x, 1.0, epsilon = 1e-12);
+        assert_relative_eq!(edge.y, 1.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_orthographic_matrix() {
+        let m = Matrix::orthographic(-2.0,2.0,-1.0,1.0,1.0,3.0);
+
+        let mut a = Vertex::new(-2.0,-1.0,-1.0);
+        let mut b = Vertex::new(2.0,1.0,-3.0);
+        a.transform(&m);
+        b.transform(&m);
+
+        assert_eq!(a,Vertex::new(-1.0,-1.0,-1.0));
+        assert_eq!(b,Vertex::new(1.0,1.0,1.0));
+    }
+
+    #[test]
+    fn test_transpose_matrix() {
+        let a = Matrix::translate(1.0,2.0,3.0).transpose();
+        let [
+            _, _, _, m14,
+            _, _, _, _,
+            _, _, _, _,
+            m41, m42, m43, _
+        ] = a.unpack();
+
+        fassert_eq!(m14, 0.0);
+        fassert_eq!(m41, 1.0);
+        fassert_eq!(m42, 2.0);
+        fassert_eq!(m43, 3.0);
+    }
+
+    #[test]
+    fn test_determinant_matrix() {
+        fassert_eq!(Matrix::scale(2.0,3.0,4.0).determinant(), 24.0);
+        fassert_eq!(Matrix::scale(-1.0,1.0,1.0).determinant(), -1.0);
+        fassert_eq!(Matrix::translate(5.0,6.0,7.0).determinant(), 1.0);
+    }
+
+    #[test]
+    fn test_normal_matrix() {
+        // a plane tilted 45 degrees between x and y
+        let mut normal = Normal::new(1.0,1.0,0.0).normalize();
+        let mut tangent = Vector::new(1.0,-1.0,0.0);
+
+        let m = Matrix::scale(2.0,1.0,1.0) * Matrix::translate(5.0,5.0,5.0);
+        normal.transform_normal(&m);
+        tangent.transform_direction(&m);
+
+        // the normal stays perpendicular to the stretched surface
+        fassert_eq!(normal.dot(&tangent), 0.0);
+        fassert_eq!(normal.magnitude(), 1.0);
+    }
+
+    #[test]
+    fn test_normal_matrix_mirror() {
+        let mut normal = Normal::new(1.0,0.0,0.0);
+        normal.transform_normal(&Matrix::scale(-1.0,1.0,1.0));
+        assert_eq!(normal,Normal::new(-1.0,0.0,0.0));
+    }
+
     #[test]
     fn test_mul_matrices() {
         let a = Matrix::new([
@@ -298,7 +564,7 @@ mod tests {
         ];
 
         // a face that references the data
-        let mut face = Face::new(1,2,3);
+        let face = Face::new(1,2,3);
 
         // x, y, and z scaling factors
         let x = 1.123;
@@ -423,4 +689,17 @@ mod tests {
         fassert_eq!(vertex.z,3.0126502432958917);
     }
 
+    #[test]
+    fn test_matrix_inverse() {
+        let m = Matrix::translate(1.0,-2.0,3.0) * Matrix::rotate(0.3,1.1,-0.7) * Matrix::scale(2.0,0.5,-1.0);
+        let mut vertex = Vertex::new(1.0,2.0,3.0);
+        vertex.transform(&m);
+        vertex.transform(&m.inverse().unwrap());
+
+        assert_relative_eq!(vertex.x,1.0,epsilon = 1e-12);
+        assert_relative_eq!(vertex.y,2.0,epsilon = 1e-12);
+        assert_relative_eq!(vertex.z,3.0,epsilon = 1e-12);
+        assert!(Matrix::scale(1.0,0.0,1.0).inverse().is_none());
+    }
+
 }
\ No newline at end of file
@@ -2,13 +2,58 @@
 pub mod matrix;
 pub mod face;
 pub mod vector;
+pub mod direction;
 pub mod triangle;
+#[allow(clippy::module_inception)]
 pub mod geometry;
 pub mod transform;
+pub mod changes;
+pub mod bounds;
+pub mod plane;
+pub mod axes;
+pub mod profile;
+pub mod fit;
+#[cfg(feature = "text")]
+pub mod text;
+mod remesh;
+mod hull;
+mod decompose;
+mod nearest;
+mod align;
+mod deviation;
+mod symmetry;
+mod curvature;
+mod geodesic;
+mod frustum;
+mod primitive;
+mod congruence;
+mod support;
+mod infill;
+mod lattice;
+mod boolean;
 
-pub use face::Face;
-pub use vector::{Vector,Vertex,Normal};
+pub use face::{Face,Uv};
+pub use vector::{Vector,Vertex};
+pub use direction::{Direction,Normal};
 pub use triangle::Triangle;
-pub use geometry::Geometry;
+pub use geometry::{Geometry,Appended,ParseMode,Smoothing};
 pub use transform::Transform;
-pub use matrix::{Matrix,MatrixType};
\ No newline at end of file
+pub use matrix::{Matrix,MatrixType};
+pub use changes::Changes;
+pub use bounds::Bounds;
+pub use plane::Plane;
+pub use frustum::Frustum;
+pub use primitive::Primitive;
+pub(crate) use primitive::{BOX_FACES,centered};
+pub(crate) use lattice::lattice;
+pub use profile::{Profile,Polyline,Point,SectionProperties,Layer};
+pub use axes::{AxisConvention,Up,Handedness};
+pub use decompose::Decomposition;
+pub use align::{Alignment,Metric};
+pub use deviation::Deviation;
+pub use symmetry::Symmetry;
+pub use curvature::{Curvature,Fillet};
+pub use support::{Supports,SupportStyle};
+pub use infill::{Infill,InfillPattern};
+pub use lattice::{Lattice,LatticeKind};
+pub use fit::{PlaneFit,LineFit,SphereFit,BoxFit,CylinderFit};
\ No newline at end of file
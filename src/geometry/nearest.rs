@@ -0,0 +1,174 @@
+use crate::geometry::*;
+use crate::constant::{Index,TOLERANCE};
+
+// the most cells the grid is split into
+const MAX_CELLS: usize = 1 << 20;
+
+// Finds the closest point on the surface of a mesh without checking
+// every face, by sorting the faces into a grid of cubes and searching
+// outwards from the cube a point is in
+pub(crate) struct Nearest {
+    triangles: Vec<(Index,Triangle)>,
+    origin: Vertex,
+    cell: f64,
+    counts: [usize;3],
+    cells: Vec<Vec<usize>>,
+}
+
+impl Nearest {
+
+    // None if the geometry doesn't have any valid faces
+    pub(crate) fn new(geometry: &Geometry) -> Option<Self> {
+        let vertices = geometry.vertices();
+        let triangles = geometry
+            .faces()
+            .iter()
+            .enumerate()
+            .filter(|(_,f)| f.is_valid(vertices))
+            .map(|(i,f)| (i,f.triangle(vertices)))
+            .collect::<Vec<(Index,Triangle)>>();
+
+        let corners = triangles
+            .iter()
+            .flat_map(|(_,t)| [t.p1,t.p2,t.p3])
+            .collect::<Vec<Vertex>>();
+        let bounds = Bounds::from_points(&corners)?;
+
+        // about one face in each cell, padded so flat meshes still
+        // have some depth
+        let size = bounds.size();
+        let pad = size.x.max(size.y).max(size.z) * 0.01 + TOLERANCE;
+        let volume = (size.x + pad) * (size.y + pad) * (size.z + pad);
+        let mut cell = (volume / triangles.len() as f64).cbrt();
+        let count = |cell: f64| [size.x,size.y,size.z].map(|s| ((s / cell).floor() as usize + 1).max(1));
+        while count(cell).iter().product::<usize>() > MAX_CELLS {
+            cell *= 2.0;
+        }
+
+        let counts = count(cell);
+        let mut nearest = Self {
+            triangles: Vec::new(),
+            origin: bounds.min,
+            cell,
+            counts,
+            cells: vec![Vec::new();counts.iter().product()],
+        };
+
+        for (index,corners) in corners.chunks(3).enumerate() {
+            let Some(around) = Bounds::from_points(corners) else {
+                continue;
+            };
+            let (low,high) = (nearest.locate(&around.min),nearest.locate(&around.max));
+            for x in low[0]..=high[0] {
+                for y in low[1]..=high[1] {
+                    for z in low[2]..=high[2] {
+                        let cell = nearest.index([x,y,z]);
+                        nearest.cells[cell].push(index);
+                    }
+                }
+            }
+        }
+
+        nearest.triangles = triangles;
+        Some(nearest)
+    }
+
+    // The closest point on the surface to `point` and the index of
+    // the face it's on
+    pub(crate) fn closest(&self, point: &Vertex) -> (Vertex,Index) {
+        let center = self.locate(point);
+        let mut best = (f64::MAX,Vertex::default(),0);
+
+        let reach = self.counts.iter().max().copied().unwrap_or(1);
+        for ring in 0..=reach {
+            for cell in self.ring(center,ring) {
+                for index in self.cells[cell].iter() {
+                    let (face,triangle) = &self.triangles[*index];
+                    let closest = triangle.closest_point(point);
+                    let distance = closest.distance(point);
+                    if distance < best.0 {
+                        best = (distance,closest,*face);
+                    }
+                }
+            }
+
+            // anything in the next ring is at least this far away
+            if best.0 <= ring as f64 * self.cell {
+                break;
+            }
+        }
+        (best.1,best.2)
+    }
+
+    // The cell a point is in, or the nearest cell if it's outside
+    fn locate(&self, point: &Vertex) -> [usize;3] {
+        let offset = *point - self.origin;
+        let place = |value: f64, count: usize| ((value / self.cell).floor().max(0.0) as usize).min(count - 1);
+        [
+            place(offset.x,self.counts[0]),
+            place(offset.y,self.counts[1]),
+            place(offset.z,self.counts[2]),
+        ]
+    }
+
+    fn index(&self, [x,y,z]: [usize;3]) -> usize {
+        (x * self.counts[1] + y) * self.counts[2] + z
+    }
+
+    // The cells exactly `ring` cells away from `center` in any
+    // direction, that are inside the grid
+    fn ring(&self, center: [usize;3], ring: usize) -> Vec<usize> {
+        let span = |axis: usize| {
+            let low = center[axis].saturating_sub(ring);
+            let high = (center[axis] + ring).min(self.counts[axis] - 1);
+            low..=high
+        };
+
+        let mut result = Vec::new();
+        for x in span(0) {
+            for y in span(1) {
+                for z in span(2) {
+                    let away = [x,y,z]
+                        .iter()
+                        .zip(center)
+                        .map(|(a,b)| a.abs_diff(b))
+                        .max()
+                        .unwrap_or(0);
+                    if away == ring {
+                        result.push(self.index([x,y,z]));
+                    }
+                }
+            }
+        }
+        result
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_nearest_matches_every_face() {
+        let geometry = Geometry::hull(&(0..40)
+            .map(|i| {
+                let (a,b) = (i as f64 * 0.9,i as f64 * 0.37);
+                Vertex::new(a.cos() * b.cos() * 2.0,a.sin() * b.cos(),b.sin() * 0.5)
+            })
+            .collect::<Vec<Vertex>>());
+
+        let nearest = Nearest::new(&geometry).unwrap();
+        for i in 0..50 {
+            let t = i as f64;
+            let point = Vertex::new((t * 0.71).sin() * 4.0,(t * 1.3).cos() * 2.0,(t * 0.2).sin() * 3.0);
+            let (closest,face) = nearest.closest(&point);
+            assert_relative_eq!(closest.distance(&point),geometry.distance(&point),epsilon = 1e-12);
+            assert_relative_eq!(geometry.get(face).distance(&point),geometry.distance(&point),epsilon = 1e-12);
+        }
+
+        assert!(Nearest::new(&Geometry::default()).is_none());
+    }
+
+}
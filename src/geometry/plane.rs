@@ -0,0 +1,49 @@
+use crate::geometry::*;
+
+/// An infinite plane, holding the points `p` where
+/// `normal.dot(p) == offset`.
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct Plane {
+    pub normal: Normal,
+    pub offset: f64,
+}
+
+impl Plane {
+
+    pub const fn new(normal: Normal, offset: f64) -> Self {
+        Self { normal, offset }
+    }
+
+    // The plane through `point` facing along `normal`
+    pub fn from_point(point: &Vertex, normal: Normal) -> Self {
+        let normal = normal.normalize();
+        Self::new(normal,normal.dot(point))
+    }
+
+    // Positive in front of the plane, negative behind it
+    pub fn distance(&self, point: &Vertex) -> f64 {
+        self.normal.dot(point) - self.offset
+    }
+
+    // The point on the plane nearest to `point`
+    pub fn project(&self, point: &Vertex) -> Vertex {
+        *point - *self.normal * self.distance(point)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_plane_distance() {
+        let plane = Plane::from_point(&Vertex::new(0.0,0.0,2.0),Normal::new(0.0,0.0,4.0));
+        assert_eq!(plane.offset,2.0);
+        assert_eq!(plane.distance(&Vertex::new(5.0,1.0,3.0)),1.0);
+        assert_eq!(plane.distance(&Vertex::new(5.0,1.0,0.0)),-2.0);
+        assert_eq!(plane.project(&Vertex::new(5.0,1.0,0.0)),Vertex::new(5.0,1.0,2.0));
+    }
+
+}
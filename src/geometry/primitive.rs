@@ -0,0 +1,81 @@
+use crate::geometry::{Geometry,Vertex,Face};
+
+/// The vertices and faces of a geometry that's known ahead of time,
+/// built at compile time so that it can be kept in a `static` and
+/// turned into a full geometry whenever it's needed
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct Primitive {
+    vertices: &'static [Vertex],
+    faces: &'static [Face],
+}
+
+// The faces of a box with corners in the order of `Bounds::corners`
+pub(crate) static BOX_FACES: [Face;12] = [
+    Face::new(1,3,4), Face::new(1,4,2), Face::new(5,6,8), Face::new(5,8,7),
+    Face::new(1,2,6), Face::new(1,6,5), Face::new(2,4,8), Face::new(2,8,6),
+    Face::new(4,3,7), Face::new(4,7,8), Face::new(3,1,5), Face::new(3,5,7),
+];
+
+impl Primitive {
+
+    pub const fn new(vertices: &'static [Vertex], faces: &'static [Face]) -> Self {
+        Self { vertices, faces }
+    }
+
+    pub const fn vertices(&self) -> &'static [Vertex] {
+        self.vertices
+    }
+
+    pub const fn faces(&self) -> &'static [Face] {
+        self.faces
+    }
+
+    // A new geometry with the same vertices and faces
+    pub fn geometry(&self) -> Geometry {
+        Geometry::from(self)
+    }
+
+}
+
+// The corners of a box centered on the origin, in the same order
+// as `Bounds::corners`, for building box primitives
+pub(crate) const fn centered(x: f64, y: f64, z: f64) -> [Vertex;8] {
+    let (x,y,z) = (x / 2.0,y / 2.0,z / 2.0);
+    [
+        Vertex::new(-x,-y,-z), Vertex::new(x,-y,-z),
+        Vertex::new(-x,y,-z), Vertex::new(x,y,-z),
+        Vertex::new(-x,-y,z), Vertex::new(x,-y,z),
+        Vertex::new(-x,y,z), Vertex::new(x,y,z),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    static SQUARE: Primitive = primitive! {
+        v 0 0 0;
+        v 1 0 0;
+        v 1 1 0;
+        v 0 1 0;
+        f 1 2 3;
+        f 1 3 4
+    };
+
+    #[test]
+    fn test_primitive_geometry() {
+        assert_eq!(SQUARE.vertices().len(),4);
+        assert_eq!(SQUARE.faces()[1],Face::new(1,3,4));
+
+        let g = SQUARE.geometry();
+        assert_eq!(g.vertices().as_slice(),SQUARE.vertices());
+        assert_eq!(g.size(),2);
+        assert_ne!(g.revision(),0);
+        assert_ne!(SQUARE.geometry().revision(),g.revision());
+
+        static BOX: Primitive = Primitive::new(&centered(2.0,2.0,2.0),&BOX_FACES);
+        assert_eq!(BOX.geometry().volume(),8.0);
+    }
+
+}
@@ -0,0 +1,755 @@
+use std::collections::{BTreeMap,BTreeSet};
+
+use crate::geometry::*;
+use crate::geometry::infill;
+use crate::errors::Error;
+use crate::constant::{Index,TOLERANCE};
+
+// A point on a 2D profile
+pub type Point = (f64,f64);
+
+// A point rounded so that points closer than the
+// tolerance usually compare equal
+pub(crate) fn weld((x,y): Point) -> (i64,i64) {
+    ((x / TOLERANCE).round() as i64,(y / TOLERANCE).round() as i64)
+}
+
+/// A chain of straight segments in 2D, optionally closed back
+/// to the first point.
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Polyline {
+    points: Vec<Point>,
+    closed: bool,
+}
+
+/// A 2D cross-section made of a closed outline and any number of
+/// closed holes inside of it.
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Profile {
+    outer: Polyline,
+    holes: Vec<Polyline>,
+}
+
+/// The section properties of a profile, with the second moments
+/// of area taken about axes through the centroid parallel to X
+/// and Y. The section moduli divide those by the distance to the
+/// furthest point of the outline.
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct SectionProperties {
+    pub area: f64,
+    pub centroid: Point,
+    /// resisting bending about the X axis, from the spread in Y
+    pub ixx: f64,
+    /// resisting bending about the Y axis, from the spread in X
+    pub iyy: f64,
+    pub ixy: f64,
+    pub sxx: f64,
+    pub syy: f64,
+}
+
+/// A slice through a mesh at a height, as the separate islands of
+/// solid it cuts through, each with its holes
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Layer {
+    z: f64,
+    islands: Vec<Profile>,
+}
+
+impl Polyline {
+
+    pub fn new(points: Vec<Point>) -> Self {
+        Self { points, closed: false }
+    }
+
+    pub fn closed(points: Vec<Point>) -> Self {
+        Self { points, closed: true }
+    }
+
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    // Each segment as a pair of points, including the closing one
+    pub fn segments(&self) -> impl Iterator<Item = (Point,Point)> + '_ {
+        let count = match self.closed {
+            true if self.points.len() > 1 => self.points.len(),
+            _ => self.points.len().saturating_sub(1),
+        };
+        (0..count).map(|i| (self.points[i],self.points[(i + 1) % self.points.len()]))
+    }
+
+    pub fn length(&self) -> f64 {
+        self.segments()
+            .map(|(a,b)| (b.0 - a.0).hypot(b.1 - a.1))
+            .sum()
+    }
+
+    // Positive when the points wind counter-clockwise, and
+    // always zero for an open polyline
+    pub fn signed_area(&self) -> f64 {
+        if !self.closed {
+            return 0.0;
+        }
+        self.segments()
+            .map(|(a,b)| a.0 * b.1 - b.0 * a.1)
+            .sum::<f64>() * 0.5
+    }
+
+    pub fn area(&self) -> f64 {
+        self.signed_area().abs()
+    }
+
+    pub fn reverse(&mut self) {
+        self.points.reverse();
+    }
+
+    // Whether a point is inside the closed polyline, using the
+    // even-odd rule. Always false for an open polyline.
+    pub fn contains(&self, (x,y): Point) -> bool {
+        self.closed && self.segments()
+            .filter(|((_,ay),(_,by))| (*ay > y) != (*by > y))
+            .filter(|((ax,ay),(bx,by))| x < ax + (y - ay) * (bx - ax) / (by - ay))
+            .count() % 2 == 1
+    }
+
+    // The smallest convex loop around a set of points, wound
+    // counter-clockwise, leaving out points along its edges
+    pub fn hull(points: &[Point]) -> Polyline {
+        let mut points = points.to_vec();
+        points.sort_by(|a,b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        points.dedup_by(|a,b| weld(*a) == weld(*b));
+        if points.len() < 3 {
+            return Polyline::closed(points);
+        }
+
+        let turn = |o: Point, a: Point, b: Point| (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0);
+
+        // the lower half left to right and the upper half back
+        let mut hull: Vec<Point> = Vec::with_capacity(points.len() * 2);
+        for pass in [points.clone(),points.into_iter().rev().collect()] {
+            let start = hull.len();
+            for p in pass.into_iter() {
+                while hull.len() >= start + 2 && turn(hull[hull.len() - 2],hull[hull.len() - 1],p) <= 0.0 {
+                    hull.pop();
+                }
+                hull.push(p);
+            }
+            hull.pop();
+        }
+        Polyline::closed(hull)
+    }
+
+    // Joins segments that meet end to start into polylines, which
+    // are closed if they come back around to where they started.
+    // Chains with a loose end are started from it.
+    pub fn chain(segments: &[(Point,Point)]) -> Vec<Polyline> {
+        let mut next = BTreeMap::new();
+        let mut ends = BTreeSet::new();
+        for (a,b) in segments.iter().filter(|(a,b)| weld(*a) != weld(*b)) {
+            next.insert(weld(*a),(weld(*b),*a,*b));
+            ends.insert(weld(*b));
+        }
+
+        let mut lines = Vec::new();
+        loop {
+            let start = next
+                .keys()
+                .find(|k| !ends.contains(*k))
+                .or_else(|| next.keys().next())
+                .copied();
+
+            let Some(start) = start else { break };
+
+            let mut points = Vec::new();
+            let mut last = None;
+            let mut current = start;
+            while let Some((to,a,b)) = next.remove(&current) {
+                ends.remove(&to);
+                points.push(a);
+                last = Some(b);
+                current = to;
+            }
+
+            match current == start {
+                true => lines.push(Polyline::closed(points)),
+                false => {
+                    points.extend(last);
+                    lines.push(Polyline::new(points));
+                },
+            }
+        }
+        lines
+    }
+
+    // A copy with every point moved by a function
+    pub fn map<F: Fn(Point) -> Point>(&self, f: F) -> Self {
+        Self {
+            points: self.points.iter().map(|p| f(*p)).collect(),
+            closed: self.closed,
+        }
+    }
+
+    // The lower left and upper right corners around the
+    // points, or None if there aren't any
+    pub fn bounds(&self) -> Option<(Point,Point)> {
+        let first = *self.points.first()?;
+        Some(self.points.iter().fold((first,first),|(a,b),p| (
+            (a.0.min(p.0),a.1.min(p.1)),
+            (b.0.max(p.0),b.1.max(p.1)),
+        )))
+    }
+
+    // Points in the XY plane at the given height
+    pub fn vertices(&self, z: f64) -> Vec<Vertex> {
+        self.points
+            .iter()
+            .map(|(x,y)| Vertex::new(*x,*y,z))
+            .collect()
+    }
+
+}
+
+impl Profile {
+
+    // The outline is always treated as closed
+    pub fn new(mut outer: Polyline) -> Self {
+        outer.closed = true;
+        Self { outer, holes: Vec::new() }
+    }
+
+    // Holes are always treated as closed
+    pub fn with_hole(mut self, mut hole: Polyline) -> Self {
+        hole.closed = true;
+        self.holes.push(hole);
+        self
+    }
+
+    pub fn outer(&self) -> &Polyline {
+        &self.outer
+    }
+
+    pub fn holes(&self) -> &[Polyline] {
+        &self.holes
+    }
+
+    // Every closed loop, starting with the outline
+    pub fn loops(&self) -> impl Iterator<Item = &Polyline> {
+        std::iter::once(&self.outer).chain(self.holes.iter())
+    }
+
+    // A copy with every point of every loop moved by a function
+    pub fn map<F: Fn(Point) -> Point>(&self, f: F) -> Self {
+        Self {
+            outer: self.outer.map(&f),
+            holes: self.holes.iter().map(|h| h.map(&f)).collect(),
+        }
+    }
+
+    // The corners around the outline, since the holes are inside it
+    pub fn bounds(&self) -> Option<(Point,Point)> {
+        self.outer.bounds()
+    }
+
+    // The area of the outline less the area of the holes
+    pub fn area(&self) -> f64 {
+        self.holes
+            .iter()
+            .fold(self.outer.area(),|a,h| a - h.area())
+    }
+
+    // The area, centroid, second moments of area and section
+    // moduli, with holes taken away
+    pub fn properties(&self) -> SectionProperties {
+        let mut profile = self.clone();
+        profile.outer.closed = true;
+        profile.orient();
+
+        // sums over the edges of every loop, by Green's theorem,
+        // as moments about the origin
+        let (mut a,mut sx,mut sy,mut ix,mut iy,mut ixy) = (0.0,0.0,0.0,0.0,0.0,0.0);
+        for ((x0,y0),(x1,y1)) in profile.loops().flat_map(|l| l.segments()) {
+            let c = x0 * y1 - x1 * y0;
+            a += c / 2.0;
+            sx += (x0 + x1) * c / 6.0;
+            sy += (y0 + y1) * c / 6.0;
+            ix += (y0 * y0 + y0 * y1 + y1 * y1) * c / 12.0;
+            iy += (x0 * x0 + x0 * x1 + x1 * x1) * c / 12.0;
+            ixy += (x0 * y1 + 2.0 * x0 * y0 + 2.0 * x1 * y1 + x1 * y0) * c / 24.0;
+        }
+
+        if a.abs() < f64::EPSILON {
+            return SectionProperties::default();
+        }
+
+        // moved to the centroid by the parallel axis theorem
+        let (cx,cy) = (sx / a,sy / a);
+        let ixx = ix - a * cy * cy;
+        let iyy = iy - a * cx * cx;
+
+        let (dx,dy) = profile.outer.points.iter().fold((0.0f64,0.0f64),|(dx,dy),(x,y)| {
+            (dx.max((x - cx).abs()),dy.max((y - cy).abs()))
+        });
+
+        SectionProperties {
+            area: a,
+            centroid: (cx,cy),
+            ixx,
+            iyy,
+            ixy: ixy - a * cx * cy,
+            sxx: if dy > 0.0 { ixx / dy } else { 0.0 },
+            syy: if dx > 0.0 { iyy / dx } else { 0.0 },
+        }
+    }
+
+    // Winds the outline counter-clockwise and the holes clockwise
+    pub fn orient(&mut self) {
+        if self.outer.signed_area() < 0.0 {
+            self.outer.reverse();
+        }
+        for hole in self.holes.iter_mut() {
+            if hole.signed_area() > 0.0 {
+                hole.reverse();
+            }
+        }
+    }
+
+    // Finds the outline of a set of closed loops, which is
+    // the one with the largest area, and makes the rest holes
+    pub fn from_loops(mut loops: Vec<Polyline>) -> Result<Self,Error> {
+        let outer = loops
+            .iter()
+            .enumerate()
+            .filter(|(_,l)| l.len() > 2)
+            .max_by(|(_,a),(_,b)| a.area().total_cmp(&b.area()))
+            .map(|(i,_)| i)
+            .ok_or(Error::EmptyProfile)?;
+
+        let mut profile = Profile::new(loops.remove(outer));
+        for hole in loops.into_iter().filter(|l| l.len() > 2) {
+            profile = profile.with_hole(hole);
+        }
+        Ok(profile)
+    }
+
+    // Sorts closed loops into separate profiles by how deeply they
+    // are nested, so a loop inside one other loop is a hole and a
+    // loop inside a hole starts a new profile.
+    pub fn nest(loops: Vec<Polyline>) -> Vec<Profile> {
+        let loops = loops
+            .into_iter()
+            .filter(|l| l.len() > 2)
+            .collect::<Vec<Polyline>>();
+
+        let parents = loops
+            .iter()
+            .map(|l| loops
+                .iter()
+                .enumerate()
+                .filter(|(_,o)| !std::ptr::eq(*o,l) && o.contains(l.points[0]))
+                .map(|(i,_)| i)
+                .collect::<Vec<Index>>())
+            .collect::<Vec<Vec<Index>>>();
+
+        let mut profiles = BTreeMap::new();
+        for (i,line) in loops.iter().enumerate() {
+            if parents[i].len() % 2 == 0 {
+                profiles.insert(i,Profile::new(line.clone()));
+            }
+        }
+
+        // a hole belongs to the smallest outline around it
+        for (i,line) in loops.iter().enumerate() {
+            let owner = parents[i]
+                .iter()
+                .filter(|p| profiles.contains_key(p))
+                .min_by(|a,b| loops[**a].area().total_cmp(&loops[**b].area()));
+            if let (Some(owner),true) = (owner,parents[i].len() % 2 == 1) {
+                if let Some(profile) = profiles.get_mut(owner) {
+                    profile.holes.push(Polyline::closed(line.points.clone()));
+                }
+            }
+        }
+
+        profiles.into_values().collect()
+    }
+
+}
+
+impl Layer {
+
+    pub fn new(z: f64, islands: Vec<Profile>) -> Self {
+        Self { z, islands }
+    }
+
+    // The height the layer was cut at
+    pub fn z(&self) -> f64 {
+        self.z
+    }
+
+    pub fn islands(&self) -> &[Profile] {
+        &self.islands
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.islands.is_empty()
+    }
+
+    // The area of solid in the layer
+    pub fn area(&self) -> f64 {
+        self.islands.iter().map(Profile::area).sum()
+    }
+
+    // Paths that fill the inside of each island with a pattern
+    pub fn infill(&self, settings: &Infill) -> Vec<Polyline> {
+        infill::fill(self,settings)
+    }
+
+}
+
+impl From<Polyline> for Profile {
+    fn from(value: Polyline) -> Self {
+        Profile::new(value)
+    }
+}
+
+#[cfg(feature = "lyon")]
+mod interop {
+
+    use lyon::math::point;
+    use lyon::path::{Path,PathEvent};
+    use lyon::path::iterator::PathIterator;
+    use lyon::tessellation::{FillTessellator,FillOptions,FillRule,FillVertex,BuffersBuilder,VertexBuffers};
+
+    use super::*;
+
+    fn build(builder: &mut lyon::path::Builder, line: &Polyline) {
+        let mut points = line.points.iter();
+        if let Some((x,y)) = points.next() {
+            builder.begin(point(*x as f32,*y as f32));
+            for (x,y) in points {
+                builder.line_to(point(*x as f32,*y as f32));
+            }
+            builder.end(line.closed);
+        }
+    }
+
+    impl From<&Polyline> for Path {
+        fn from(value: &Polyline) -> Self {
+            let mut builder = Path::builder();
+            build(&mut builder,value);
+            builder.build()
+        }
+    }
+
+    impl From<&Profile> for Path {
+        fn from(value: &Profile) -> Self {
+            let mut builder = Path::builder();
+            for line in value.loops() {
+                build(&mut builder,line);
+            }
+            builder.build()
+        }
+    }
+
+    impl Polyline {
+
+        // Reads every sub-path of a lyon path, flattening curves
+        // so they are no further than `tolerance` from the result
+        pub fn from_path(path: &Path, tolerance: f64) -> Vec<Polyline> {
+            let mut lines = Vec::new();
+            let mut current = Vec::new();
+
+            for event in path.iter().flattened(tolerance as f32) {
+                match event {
+                    PathEvent::Begin { at } => {
+                        current = vec![(at.x as f64,at.y as f64)];
+                    },
+                    PathEvent::Line { to, .. } => {
+                        current.push((to.x as f64,to.y as f64));
+                    },
+                    PathEvent::End { close, .. } => {
+                        let points = std::mem::take(&mut current);
+                        lines.push(match close {
+                            true => Polyline::closed(points),
+                            false => Polyline::new(points),
+                        });
+                    },
+                    _ => (),
+                }
+            }
+            lines
+        }
+
+    }
+
+    impl Profile {
+
+        // Builds a profile from the closed sub-paths of a lyon
+        // path. Open sub-paths are ignored.
+        pub fn from_path(path: &Path, tolerance: f64) -> Result<Self,Error> {
+            let loops = Polyline::from_path(path,tolerance)
+                .into_iter()
+                .filter(|l| l.is_closed())
+                .collect();
+            Profile::from_loops(loops)
+        }
+
+        // Triangulates the profile into a flat geometry in the XY
+        // plane, with every face pointing up the Z axis
+        pub fn tessellate(&self) -> Result<Geometry,Error> {
+            let path = Path::from(self);
+            let mut buffers: VertexBuffers<Vertex,u32> = VertexBuffers::new();
+
+            FillTessellator::new()
+                .tessellate_path(
+                    &path,
+                    &FillOptions::default().with_fill_rule(FillRule::EvenOdd),
+                    &mut BuffersBuilder::new(&mut buffers,|v: FillVertex| {
+                        let p = v.position();
+                        Vertex::new(p.x as f64,p.y as f64,0.0)
+                    }))
+                .map_err(|e| Error::TessellationError(e.to_string()))?;
+
+            let faces = buffers.indices
+                .chunks_exact(3)
+                .map(|c| {
+                    let mut face = Face {
+                        a: c[0] as usize,
+                        b: c[1] as usize,
+                        c: c[2] as usize,
+                        ..Default::default()
+                    };
+                    if face.normal(&buffers.vertices).z < 0.0 {
+                        face.flip();
+                    }
+                    face
+                })
+                .collect();
+
+            Ok(Geometry::new(buffers.vertices,faces))
+        }
+
+        // Extrudes the profile up the Z axis into a closed solid,
+        // with the vertices at each end in the `top` and `bottom`
+        // groups so they can be moved by attributes
+        pub fn extrude(&self, depth: f64) -> Result<Geometry,Error> {
+            let mut profile = self.clone();
+            profile.orient();
+
+            let cap = profile.tessellate()?;
+            let mut bottom = cap.clone();
+            bottom.flip();
+
+            let mut geometry = Geometry::default();
+            let mut top = Vec::new();
+            let mut base = Vec::new();
+
+            let (start,end) = geometry.append(&bottom,&Matrix::identity()).vertices;
+            base.extend(start..end);
+
+            let (start,end) = geometry.append(&cap,&Matrix::translate(0.0,0.0,depth)).vertices;
+            top.extend(start..end);
+
+            // each loop gets its own ring of wall vertices, so the
+            // caps and walls don't share smoothed normals
+            for line in profile.loops() {
+                let count = line.len();
+                let lower = line.vertices(0.0);
+                let upper = line.vertices(depth);
+                let faces = (0..count)
+                    .flat_map(|i| {
+                        let j = (i + 1) % count;
+                        [
+                            Face::new(i + 1,j + 1,count + j + 1),
+                            Face::new(i + 1,count + j + 1,count + i + 1),
+                        ]
+                    })
+                    .collect();
+
+                let wall = Geometry::new([lower,upper].concat(),faces);
+                let (start,end) = geometry.append(&wall,&Matrix::identity()).vertices;
+                base.extend(start..start + count);
+                top.extend(start + count..end);
+            }
+
+            geometry.add_group("bottom",base)?;
+            geometry.add_group("top",top)?;
+            Ok(geometry)
+        }
+
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn square(size: f64) -> Polyline {
+        Polyline::closed(vec![(0.0,0.0),(size,0.0),(size,size),(0.0,size)])
+    }
+
+    #[test]
+    fn test_polyline_measure() {
+        let line = square(2.0);
+        assert_eq!(line.length(),8.0);
+        assert_eq!(line.signed_area(),4.0);
+
+        let open = Polyline::new(line.points().to_vec());
+        assert_eq!(open.length(),6.0);
+        assert_eq!(open.area(),0.0);
+
+        let moved = line.map(|(x,y)| (x + 1.0,y * 2.0));
+        assert_eq!(moved.bounds(),Some(((1.0,0.0),(3.0,4.0))));
+        assert_eq!(Polyline::default().bounds(),None);
+    }
+
+    #[test]
+    fn test_polyline_hull() {
+        let points = [(0.0,0.0),(1.0,1.0),(2.0,0.0),(1.0,0.0),(2.0,2.0),(0.5,1.5),(0.0,2.0),(2.0,2.0)];
+        let hull = Polyline::hull(&points);
+        assert_eq!(hull.points(),&[(0.0,0.0),(2.0,0.0),(2.0,2.0),(0.0,2.0)]);
+        assert!(hull.is_closed());
+        assert_eq!(Polyline::hull(&[(1.0,1.0)]).len(),1);
+        assert_eq!(Polyline::hull(&[(0.0,0.0),(1.0,0.0),(2.0,0.0)]).len(),2);
+        assert!(Polyline::hull(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_polyline_chain() {
+        let square = square(1.0);
+        let mut segments = square.segments().collect::<Vec<(Point,Point)>>();
+        segments.swap(0,2);
+        segments.push(((5.0,5.0),(6.0,5.0)));
+        segments.push(((4.0,5.0),(5.0,5.0)));
+
+        let lines = Polyline::chain(&segments);
+        assert_eq!(lines.len(),2);
+        assert_eq!(lines[0],Polyline::new(vec![(4.0,5.0),(5.0,5.0),(6.0,5.0)]));
+        assert!(lines[1].is_closed());
+        assert_eq!(lines[1].len(),4);
+        assert_eq!(lines[1].signed_area(),1.0);
+    }
+
+    #[test]
+    fn test_profile_properties() {
+        // a 2 x 4 rectangle away from the origin
+        let rectangle = Profile::new(square(1.0).map(|(x,y)| (x * 2.0 + 3.0,y * 4.0 - 1.0)));
+        let properties = rectangle.properties();
+        assert_relative_eq!(properties.area,8.0);
+        assert_relative_eq!(properties.centroid.0,4.0);
+        assert_relative_eq!(properties.centroid.1,1.0);
+        assert_relative_eq!(properties.ixx,2.0 * 64.0 / 12.0,epsilon = 1e-9);
+        assert_relative_eq!(properties.iyy,4.0 * 8.0 / 12.0,epsilon = 1e-9);
+        assert_relative_eq!(properties.ixy,0.0,epsilon = 1e-9);
+        assert_relative_eq!(properties.sxx,2.0 * 16.0 / 6.0,epsilon = 1e-9);
+        assert_relative_eq!(properties.syy,4.0 * 4.0 / 6.0,epsilon = 1e-9);
+
+        // a square tube, either way around
+        let mut outer = square(4.0);
+        outer.reverse();
+        let tube = Profile::new(outer)
+            .with_hole(square(2.0).map(|(x,y)| (x + 1.0,y + 1.0)));
+        let properties = tube.properties();
+        assert_relative_eq!(properties.area,12.0);
+        assert_relative_eq!(properties.centroid.0,2.0);
+        assert_relative_eq!(properties.ixx,(256.0 - 16.0) / 12.0,epsilon = 1e-9);
+        assert_relative_eq!(properties.sxx,(256.0 - 16.0) / 24.0,epsilon = 1e-9);
+
+        assert_eq!(Profile::default().properties(),SectionProperties::default());
+    }
+
+    #[test]
+    fn test_profile_from_loops() {
+        let mut hole = Polyline::closed(vec![(1.0,1.0),(1.0,2.0),(2.0,2.0),(2.0,1.0)]);
+        hole.reverse();
+
+        let mut profile = Profile::from_loops(vec![hole,square(3.0)]).unwrap();
+        assert_eq!(profile.outer(),&square(3.0));
+        assert_eq!(profile.holes().len(),1);
+        assert_eq!(profile.area(),8.0);
+
+        profile.orient();
+        assert!(profile.holes()[0].signed_area() < 0.0);
+
+        assert!(matches!(Profile::from_loops(vec![]),Err(Error::EmptyProfile)));
+    }
+
+    #[test]
+    fn test_profile_nest() {
+        let offset = |l: &Polyline, d: f64| Polyline::closed(l.points().iter().map(|(x,y)| (x + d,y + d)).collect());
+
+        // a ring with an island inside it, and a separate square
+        let loops = vec![
+            square(10.0),
+            offset(&square(6.0),2.0),
+            offset(&square(2.0),4.0),
+            offset(&square(1.0),20.0),
+        ];
+
+        let profiles = Profile::nest(loops);
+        assert_eq!(profiles.len(),3);
+        assert_eq!(profiles[0].area(),64.0);
+        assert_eq!(profiles[1].area(),4.0);
+        assert_eq!(profiles[2].area(),1.0);
+        assert!(square(1.0).contains((0.5,0.5)));
+        assert!(!square(1.0).contains((1.5,0.5)));
+    }
+
+    #[cfg(feature = "lyon")]
+    #[test]
+    fn test_profile_extrude() {
+        let hole = Polyline::closed(vec![(1.0,1.0),(2.0,1.0),(2.0,2.0),(1.0,2.0)]);
+        let geometry = Profile::new(square(3.0)).with_hole(hole).extrude(2.0).unwrap();
+
+        // every face points away from the middle of the solid, so
+        // the signed volume is positive
+        let volume = geometry.faces()
+            .iter()
+            .map(|f| {
+                let t = f.triangle(geometry.vertices());
+                t.p1.dot(&t.p2.cross(&t.p3)) / 6.0
+            })
+            .sum::<f64>();
+
+        assert!((volume - 16.0).abs() < 1e-6);
+        assert!(geometry.group("top").unwrap().iter().all(|i| geometry.vertices()[*i].z == 2.0));
+        assert!(geometry.group("bottom").unwrap().iter().all(|i| geometry.vertices()[*i].z == 0.0));
+    }
+
+    #[cfg(feature = "lyon")]
+    #[test]
+    fn test_profile_path_round_trip() {
+        use lyon::path::Path;
+
+        let hole = Polyline::closed(vec![(1.0,1.0),(2.0,1.0),(2.0,2.0),(1.0,2.0)]);
+        let profile = Profile::new(square(3.0)).with_hole(hole);
+
+        let path = Path::from(&profile);
+        let result = Profile::from_path(&path,0.01).unwrap();
+        assert_eq!(result,profile);
+
+        let geometry = result.tessellate().unwrap();
+        let area = geometry.faces()
+            .iter()
+            .map(|f| f.triangle(geometry.vertices()).area())
+            .sum::<f64>();
+
+        assert!((area - 8.0).abs() < 1e-6);
+        assert!(geometry.faces().iter().all(|f| f.normal(geometry.vertices()).z > 0.0));
+    }
+
+}
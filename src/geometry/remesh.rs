@@ -0,0 +1,406 @@
+use std::collections::{BTreeMap,BTreeSet};
+use std::f64::consts::PI;
+
+use crate::geometry::*;
+use crate::constant::Index;
+
+// edges with a sharper dihedral angle than this are kept in place
+const FEATURE_ANGLE: f64 = PI / 4.0;
+
+type Edge = (Index,Index);
+
+fn key(a: Index, b: Index) -> Edge {
+    (a.min(b),a.max(b))
+}
+
+// The corners of `face` rotated so that the edge between `a` and
+// `b` comes first, in the face's own winding.
+fn rotate(face: [Index;3], a: Index, b: Index) -> [Index;3] {
+    let [p,q,r] = face;
+    match (p,q,r) {
+        _ if key(p,q) == key(a,b) => [p,q,r],
+        _ if key(q,r) == key(a,b) => [q,r,p],
+        _ => [r,p,q],
+    }
+}
+
+/// A triangle mesh stripped down to positions and corners, which
+/// the remesher can split, collapse and flip without having to keep
+/// any other data in sync.
+struct Mesh {
+    vertices: Vec<Vertex>,
+    faces: Vec<[Index;3]>,
+}
+
+impl Mesh {
+
+    fn edge_faces(&self) -> BTreeMap<Edge,Vec<Index>> {
+        let mut edges: BTreeMap<Edge,Vec<Index>> = BTreeMap::new();
+        for (i,[a,b,c]) in self.faces.iter().enumerate() {
+            for (u,v) in [(*a,*b),(*b,*c),(*c,*a)] {
+                edges.entry(key(u,v)).or_default().push(i);
+            }
+        }
+        edges
+    }
+
+    fn neighbours(&self) -> Vec<BTreeSet<Index>> {
+        let mut result = vec![BTreeSet::new(); self.vertices.len()];
+        for [a,b,c] in self.faces.iter() {
+            for (u,v) in [(*a,*b),(*b,*c),(*c,*a)] {
+                result[u].insert(v);
+                result[v].insert(u);
+            }
+        }
+        result
+    }
+
+    // Twice the area, pointing out of the front of the face
+    fn area_normal(&self, [a,b,c]: [Index;3]) -> Vector {
+        let (a,b,c) = (self.vertices[a],self.vertices[b],self.vertices[c]);
+        (b - a).cross(&(c - a))
+    }
+
+    fn length(&self, (a,b): Edge) -> f64 {
+        self.vertices[a].distance(&self.vertices[b])
+    }
+
+    // Edges on the boundary, shared by more than two faces or
+    // between faces meeting at a sharp angle.
+    fn is_feature(&self, shared: &[Index]) -> bool {
+        match shared {
+            [a,b] => {
+                let n1 = self.area_normal(self.faces[*a]);
+                let n2 = self.area_normal(self.faces[*b]);
+                n1.angle(&n2) > FEATURE_ANGLE
+            },
+            _ => true,
+        }
+    }
+
+    // Vertices that lie on a feature edge and shouldn't move
+    fn fixed(&self, edges: &BTreeMap<Edge,Vec<Index>>) -> Vec<bool> {
+        let mut fixed = vec![false; self.vertices.len()];
+        for ((a,b),shared) in edges.iter() {
+            if self.is_feature(shared) {
+                fixed[*a] = true;
+                fixed[*b] = true;
+            }
+        }
+        fixed
+    }
+
+    // Splits every edge longer than `high` at its midpoint
+    fn split(&mut self, high: f64) {
+        loop {
+            let edges = self.edge_faces();
+            let mut touched = vec![false; self.faces.len()];
+            let mut count = 0;
+
+            for (&(a,b),shared) in edges.iter() {
+                if self.length((a,b)) <= high || shared.iter().any(|f| touched[*f]) {
+                    continue;
+                }
+
+                let m = self.vertices.len();
+                self.vertices.push((self.vertices[a] + self.vertices[b]) * 0.5);
+
+                for &f in shared.iter() {
+                    let [p,q,r] = rotate(self.faces[f],a,b);
+                    self.faces[f] = [p,m,r];
+                    self.faces.push([m,q,r]);
+                    touched[f] = true;
+                    touched.push(true);
+                }
+                count += 1;
+            }
+
+            if count == 0 {
+                break;
+            }
+        }
+    }
+
+    // Merges the ends of edges shorter than `low`, as long as that
+    // doesn't create edges longer than `high`, fold any faces over or
+    // pinch the surface.
+    fn collapse(&mut self, low: f64, high: f64) {
+        loop {
+            let edges = self.edge_faces();
+            let fixed = self.fixed(&edges);
+            let neighbours = self.neighbours();
+
+            let mut around = vec![Vec::new(); self.vertices.len()];
+            for (i,face) in self.faces.iter().enumerate() {
+                for v in face.iter() {
+                    around[*v].push(i);
+                }
+            }
+
+            let mut target = (0..self.vertices.len()).collect::<Vec<Index>>();
+            let mut locked = vec![false; self.vertices.len()];
+            let mut count = 0;
+
+            for (&(a,b),shared) in edges.iter() {
+                if self.length((a,b)) >= low || locked[a] || locked[b] || shared.len() != 2 {
+                    continue;
+                }
+
+                // the fixed end stays where it is
+                let (keep,drop,point) = match (fixed[a],fixed[b]) {
+                    (true,true) => continue,
+                    (true,false) => (a,b,self.vertices[a]),
+                    (false,true) => (b,a,self.vertices[b]),
+                    (false,false) => (a,b,(self.vertices[a] + self.vertices[b]) * 0.5),
+                };
+
+                // both ends share exactly the two opposite vertices, and
+                // those keep at least three neighbours
+                let common = neighbours[a].intersection(&neighbours[b]).collect::<Vec<_>>();
+                if common.len() != 2 || common.iter().any(|c| neighbours[**c].len() <= 3) {
+                    continue;
+                }
+
+                let ring = neighbours[a].union(&neighbours[b]).filter(|n| **n != a && **n != b);
+                if ring.clone().any(|n| self.vertices[*n].distance(&point) > high) {
+                    continue;
+                }
+
+                let position = |v: Index| if v == a || v == b { point } else { self.vertices[v] };
+                let folds = around[a].iter().chain(around[b].iter())
+                    .filter(|f| !shared.contains(f))
+                    .any(|f| {
+                        let face = self.faces[*f];
+                        let [x,y,z] = face.map(position);
+                        let after = (y - x).cross(&(z - x));
+                        self.area_normal(face).dot(&after) <= 0.0
+                    });
+
+                if folds {
+                    continue;
+                }
+
+                self.vertices[keep] = point;
+                target[drop] = keep;
+                locked[a] = true;
+                locked[b] = true;
+                for n in ring {
+                    locked[*n] = true;
+                }
+                count += 1;
+            }
+
+            if count == 0 {
+                break;
+            }
+
+            for face in self.faces.iter_mut() {
+                *face = face.map(|v| target[v]);
+            }
+            self.faces.retain(|[a,b,c]| a != b && b != c && c != a);
+        }
+    }
+
+    // Flips edges where that brings the number of neighbours of the
+    // four vertices involved closer to six.
+    fn flip(&mut self) {
+        // fixed once up front, so every flip lowers the total and
+        // this has to stop
+        let fixed = self.fixed(&self.edge_faces());
+        loop {
+            let edges = self.edge_faces();
+            let mut existing = edges.keys().copied().collect::<BTreeSet<Edge>>();
+            let mut valence = self.neighbours().iter().map(BTreeSet::len).collect::<Vec<usize>>();
+            let mut touched = vec![false; self.faces.len()];
+            let mut count = 0;
+
+            let deviation = |valence: &[usize], v: Index| {
+                let target = if fixed[v] { 4 } else { 6 };
+                valence[v].abs_diff(target)
+            };
+
+            for (&(a,b),shared) in edges.iter() {
+                let [f1,f2] = shared[..] else { continue };
+                if touched[f1] || touched[f2] || self.is_feature(shared) {
+                    continue;
+                }
+
+                let [p,q,c] = rotate(self.faces[f1],a,b);
+                let [_,_,d] = rotate(self.faces[f2],a,b);
+                if c == d || existing.contains(&key(c,d)) {
+                    continue;
+                }
+
+                let before: usize = [a,b,c,d].iter().map(|v| deviation(&valence,*v)).sum();
+                let mut after = valence.clone();
+                after[a] -= 1;
+                after[b] -= 1;
+                after[c] += 1;
+                after[d] += 1;
+                let improved: usize = [a,b,c,d].iter().map(|v| deviation(&after,*v)).sum();
+                if improved >= before {
+                    continue;
+                }
+
+                let facing = self.area_normal(self.faces[f1]) + self.area_normal(self.faces[f2]);
+                let first = [p,d,c];
+                let second = [d,q,c];
+                if self.area_normal(first).dot(&facing) <= 0.0 || self.area_normal(second).dot(&facing) <= 0.0 {
+                    continue;
+                }
+
+                self.faces[f1] = first;
+                self.faces[f2] = second;
+                existing.remove(&key(a,b));
+                existing.insert(key(c,d));
+                valence = after;
+                touched[f1] = true;
+                touched[f2] = true;
+                count += 1;
+            }
+
+            if count == 0 {
+                break;
+            }
+        }
+    }
+
+    // Moves each free vertex towards the middle of its neighbours,
+    // but only along the surface.
+    fn smooth(&mut self) {
+        let edges = self.edge_faces();
+        let fixed = self.fixed(&edges);
+        let neighbours = self.neighbours();
+
+        let mut normals = vec![Vector::default(); self.vertices.len()];
+        for face in self.faces.iter() {
+            let n = self.area_normal(*face);
+            for v in face.iter() {
+                normals[*v] = normals[*v] + n;
+            }
+        }
+
+        let moved = (0..self.vertices.len())
+            .map(|v| {
+                let point = self.vertices[v];
+                if fixed[v] || neighbours[v].is_empty() {
+                    return point;
+                }
+
+                let sum = neighbours[v]
+                    .iter()
+                    .fold(Vector::default(),|s,n| s + self.vertices[*n]);
+                let offset = sum / neighbours[v].len() - point;
+                let normal = normals[v].normalize();
+                point + offset - normal * normal.dot(&offset)
+            })
+            .collect();
+
+        self.vertices = moved;
+    }
+
+    // Moves every vertex back onto the nearest original face
+    fn project(&mut self, surface: &[Triangle]) {
+        for vertex in self.vertices.iter_mut() {
+            let nearest = surface
+                .iter()
+                .map(|t| t.closest_point(vertex))
+                .min_by(|a,b| a.distance(vertex).total_cmp(&b.distance(vertex)));
+            if let Some(point) = nearest {
+                *vertex = point;
+            }
+        }
+    }
+
+    // Drops vertices that no face uses
+    fn compact(self) -> (Vec<Vertex>,Vec<Face>) {
+        let mut index = vec![None; self.vertices.len()];
+        let mut vertices = Vec::new();
+
+        for face in self.faces.iter() {
+            for v in face.iter() {
+                if index[*v].is_none() {
+                    index[*v] = Some(vertices.len());
+                    vertices.push(self.vertices[*v]);
+                }
+            }
+        }
+
+        let faces = self.faces
+            .iter()
+            .map(|f| f.map(|v| index[v].unwrap_or_default() + 1))
+            .map(|[a,b,c]| Face::new(a,b,c))
+            .collect();
+
+        (vertices,faces)
+    }
+
+}
+
+// Rebuilds a surface with edges close to `target` in length, by
+// repeatedly splitting long edges, collapsing short ones, flipping
+// edges to even out the number of neighbours of each vertex and then
+// relaxing vertices along the original surface. Boundaries and sharp
+// edges are kept. Vertices no face uses are dropped.
+pub(crate) fn isotropic(vertices: &[Vertex], faces: &[Face], target: f64, iterations: usize) -> (Vec<Vertex>,Vec<Face>) {
+    let faces = faces
+        .iter()
+        .filter(|f| f.is_valid(vertices))
+        .collect::<Vec<&Face>>();
+
+    let surface = faces
+        .iter()
+        .map(|f| f.triangle(vertices))
+        .collect::<Vec<Triangle>>();
+
+    let mut mesh = Mesh {
+        vertices: vertices.to_vec(),
+        faces: faces.iter().map(|f| [f.a,f.b,f.c]).collect(),
+    };
+
+    let low = target * 4.0 / 5.0;
+    let high = target * 4.0 / 3.0;
+
+    for _ in 0..iterations {
+        mesh.split(high);
+        mesh.collapse(low,high);
+        mesh.flip();
+        mesh.smooth();
+        mesh.project(&surface);
+    }
+
+    mesh.compact()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_remesh_rotate() {
+        assert_eq!(rotate([1,2,3],1,2),[1,2,3]);
+        assert_eq!(rotate([1,2,3],3,2),[2,3,1]);
+        assert_eq!(rotate([1,2,3],1,3),[3,1,2]);
+    }
+
+    #[test]
+    fn test_remesh_split() {
+        let mut mesh = Mesh {
+            vertices: vec![
+                Vertex::new(0.0,0.0,0.0),
+                Vertex::new(4.0,0.0,0.0),
+                Vertex::new(0.0,1.0,0.0),
+            ],
+            faces: vec![[0,1,2]],
+        };
+
+        mesh.split(1.5);
+        let edges = mesh.edge_faces();
+        assert!(edges.keys().all(|e| mesh.length(*e) <= 1.5));
+        for face in mesh.faces.iter() {
+            assert!(mesh.area_normal(*face).z > 0.0);
+        }
+    }
+
+}
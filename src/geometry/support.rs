@@ -0,0 +1,286 @@
+use crate::geometry::*;
+use crate::geometry::lattice::strut;
+use crate::constant::{Index,TOLERANCE};
+
+// rows of support points are nudged off the grid by this much of
+// the spacing, so they don't land exactly on edges of the mesh
+const NUDGE: (f64,f64) = (0.000_618,0.000_377);
+
+/// The shape of the supports put under overhangs
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq)]
+pub enum SupportStyle {
+    /// a straight pillar from each point down to whatever is below it
+    #[default]
+    Pillar,
+    /// points that are close together branch off a shared trunk
+    Tree,
+}
+
+/// Settings for building supports under the overhangs of a mesh
+/// that's printed with +Z up
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub struct Supports {
+    angle: f64,
+    spacing: f64,
+    width: f64,
+    style: SupportStyle,
+}
+
+impl Default for Supports {
+    fn default() -> Self {
+        Self {
+            angle: std::f64::consts::FRAC_PI_4,
+            spacing: 0.005,
+            width: 0.001,
+            style: SupportStyle::Pillar,
+        }
+    }
+}
+
+impl Supports {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The steepest overhang that prints without supports, in radians
+    // from vertical, so that walls are 0 and flat ceilings are pi/2
+    pub fn with_angle(mut self, angle: f64) -> Self {
+        self.angle = angle.clamp(0.0,std::f64::consts::FRAC_PI_2);
+        self
+    }
+
+    // The distance between support points under an overhang
+    pub fn with_spacing(mut self, spacing: f64) -> Self {
+        self.spacing = spacing.abs().max(TOLERANCE);
+        self
+    }
+
+    // The size across each pillar or branch
+    pub fn with_width(mut self, width: f64) -> Self {
+        self.width = width.abs().max(TOLERANCE);
+        self
+    }
+
+    pub fn with_style(mut self, style: SupportStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn angle(&self) -> f64 {
+        self.angle
+    }
+
+    pub fn spacing(&self) -> f64 {
+        self.spacing
+    }
+
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+
+    pub fn style(&self) -> SupportStyle {
+        self.style
+    }
+
+}
+
+// Faces that point down more steeply than `angle` from vertical,
+// leaving out the ones resting on the bed at the bottom of the mesh
+pub(crate) fn overhangs(vertices: &[Vertex], faces: &[Face], angle: f64) -> Vec<Index> {
+    let Some(bed) = vertices.iter().map(|v| v.z).reduce(f64::min) else {
+        return Vec::new();
+    };
+    let limit = angle.sin();
+    faces
+        .iter()
+        .enumerate()
+        .filter(|(_,f)| f.is_valid(vertices))
+        .filter(|(_,f)| -f.normal(vertices).z > limit + TOLERANCE)
+        .filter(|(_,f)| {
+            let t = f.triangle(vertices);
+            [t.p1,t.p2,t.p3].iter().any(|p| p.z > bed + TOLERANCE)
+        })
+        .map(|(i,_)| i)
+        .collect()
+}
+
+pub(crate) fn supports(vertices: &[Vertex], faces: &[Face], settings: &Supports) -> Geometry {
+    let mut result = Geometry::default();
+    let Some(bounds) = Bounds::from_points(vertices) else {
+        return result;
+    };
+
+    let triangles = faces
+        .iter()
+        .filter(|f| f.is_valid(vertices))
+        .map(|f| f.triangle(vertices))
+        .collect::<Vec<Triangle>>();
+
+    // where a vertical line through a point first meets the mesh or
+    // the bed going down
+    let (top,bed) = (bounds.max.z + 1.0,bounds.min.z);
+    let below = |point: &Vertex| {
+        let bottom = Vertex::new(point.x,point.y,bed - 1.0);
+        triangles
+            .iter()
+            .filter_map(|t| t.crossing(point,&bottom))
+            .map(|p| p.z)
+            .fold(bed,f64::max)
+    };
+
+    let points = points(vertices,faces,settings,&bounds,top);
+    let struts = match settings.style {
+        SupportStyle::Pillar => points
+            .iter()
+            .map(|p| (Vertex::new(p.x,p.y,below(p)),*p))
+            .collect(),
+        SupportStyle::Tree => trees(&points,settings,below),
+    };
+
+    for (a,b) in struts.into_iter() {
+        if b.z - a.z > TOLERANCE {
+            result.append(&strut(a,b,settings.width),&Matrix::identity());
+        }
+    }
+    result
+}
+
+// Points on a grid under the overhangs, with one at the middle of
+// any overhang too small for the grid to land on
+fn points(vertices: &[Vertex], faces: &[Face], settings: &Supports, bounds: &Bounds, top: f64) -> Vec<Vertex> {
+    let spacing = settings.spacing;
+    let mut result: Vec<Vertex> = Vec::new();
+    for index in overhangs(vertices,faces,settings.angle) {
+        let triangle = faces[index].triangle(vertices);
+        let (xs,ys) = ([triangle.p1.x,triangle.p2.x,triangle.p3.x],[triangle.p1.y,triangle.p2.y,triangle.p3.y]);
+
+        // the grid lines that fall between the lowest and highest
+        // value of each axis
+        let range = |values: [f64;3], min: f64, nudge: f64| {
+            let cell = |v: f64| (v - min) / spacing - 0.5 - nudge;
+            let low = values.into_iter().map(cell).fold(f64::MAX,f64::min).ceil().max(0.0) as i64;
+            let high = values.into_iter().map(cell).fold(f64::MIN,f64::max).floor() as i64;
+            (low..=high).map(move |i| min + spacing * (i as f64 + 0.5 + nudge))
+        };
+
+        let mut found = Vec::new();
+        for x in range(xs,bounds.min.x,NUDGE.0) {
+            for y in range(ys,bounds.min.y,NUDGE.1) {
+                let from = Vertex::new(x,y,top);
+                let to = Vertex::new(x,y,bounds.min.z - 1.0);
+                found.extend(triangle.crossing(&from,&to));
+            }
+        }
+
+        if found.is_empty() {
+            found.push(triangle.centroid());
+        }
+        for point in found.into_iter() {
+            if !result.iter().any(|p| p.distance(&point) <= TOLERANCE) {
+                result.push(point);
+            }
+        }
+    }
+    result
+}
+
+// Gathers points that share a cell of a coarser grid onto a trunk
+// under the middle of them. Each branch leans at 45 degrees at most,
+// so the trunk stops below the lowest branch. Points the trunk can't
+// reach that way get pillars of their own.
+fn trees<F>(points: &[Vertex], settings: &Supports, below: F) -> Vec<(Vertex,Vertex)>
+    where F: Fn(&Vertex) -> f64
+{
+    let cell = settings.spacing * 3.0;
+    let mut clusters: Vec<((i64,i64),Vec<Vertex>)> = Vec::new();
+    for point in points.iter() {
+        let key = ((point.x / cell).floor() as i64,(point.y / cell).floor() as i64);
+        match clusters.iter_mut().find(|(k,_)| *k == key) {
+            Some((_,cluster)) => cluster.push(*point),
+            None => clusters.push((key,vec![*point])),
+        }
+    }
+
+    let mut result = Vec::new();
+    for (_,cluster) in clusters.into_iter() {
+        let sum = cluster.iter().fold(Vector::default(),|s,p| s + *p);
+        let middle = sum / cluster.len();
+        let height = cluster
+            .iter()
+            .map(|p| p.z - ((p.x - middle.x).powi(2) + (p.y - middle.y).powi(2)).sqrt())
+            .fold(f64::MAX,f64::min);
+
+        let joint = Vertex::new(middle.x,middle.y,height);
+        let ground = below(&Vertex::new(middle.x,middle.y,cluster.iter().map(|p| p.z).fold(f64::MAX,f64::min)));
+        if cluster.len() < 2 || height <= ground + TOLERANCE {
+            result.extend(cluster.iter().map(|p| (Vertex::new(p.x,p.y,below(p)),*p)));
+            continue;
+        }
+
+        result.push((Vertex::new(joint.x,joint.y,ground),joint));
+        result.extend(cluster.into_iter().map(|p| (joint,p)));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::testing;
+
+    // A table: a slab held up on one leg in the middle
+    fn table() -> Geometry {
+        let mut geometry = Geometry::from_bounds(&Bounds::new(Vertex::new(0.0,0.0,0.1),Vertex::new(0.04,0.02,0.11)));
+        geometry.append(&Geometry::from_bounds(&Bounds::new(Vertex::new(0.018,0.008,0.0),Vertex::new(0.022,0.012,0.1))),&Matrix::identity());
+        geometry
+    }
+
+    #[test]
+    fn test_support_overhangs() {
+        let geometry = table();
+        let overhangs = geometry.overhangs(std::f64::consts::FRAC_PI_4);
+        assert_eq!(overhangs.len(),2);
+        assert!(overhangs.iter().all(|i| geometry.get(*i).normal().z < -0.99));
+
+        // nothing overhangs a box sitting on the bed
+        let block = testing::cube();
+        assert!(block.overhangs(0.0).is_empty());
+    }
+
+    #[test]
+    fn test_support_pillars() {
+        let geometry = table();
+        let settings = Supports::new().with_spacing(0.01).with_width(0.001);
+        let supports = geometry.supports(&settings);
+
+        // a 4x2 grid under the slab, each a box of 12 faces
+        assert_eq!(supports.size(),8 * 12);
+        let bounds = supports.bounds().unwrap();
+        assert_relative_eq!(bounds.min.z,0.0,epsilon = 1e-9);
+        assert_relative_eq!(bounds.max.z,0.1,epsilon = 1e-9);
+        assert_relative_eq!(supports.volume(),8.0 * 0.001 * 0.001 * 0.1,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_support_trees() {
+        let geometry = table();
+        let settings = Supports::new()
+            .with_spacing(0.01)
+            .with_style(SupportStyle::Tree)
+            .build();
+        let supports = geometry.supports(&settings);
+
+        // fewer trunks reach the bed than there are points
+        let pillars = geometry.supports(&Supports::new().with_spacing(0.01));
+        let footprint = |g: &Geometry| g.vertices().iter().filter(|v| v.z.abs() < 1e-9).count();
+        assert!(footprint(&supports) < footprint(&pillars));
+        assert_relative_eq!(supports.bounds().unwrap().max.z,0.1,epsilon = 1e-3);
+    }
+
+}
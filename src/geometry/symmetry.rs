@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+
+use crate::geometry::*;
+use crate::geometry::fit::spread;
+use crate::geometry::nearest::Nearest;
+use crate::constant::Index;
+
+// the most vertices used to suggest planes and axes
+const CANDIDATES: usize = 16;
+
+/// A way of moving a geometry that leaves it looking the same, with
+/// the vertex that each vertex lands on
+#[derive(Debug,Clone,PartialEq)]
+pub enum Symmetry {
+    /// a reflection across a plane
+    Mirror {
+        plane: Plane,
+        map: Vec<Index>,
+    },
+    /// a turn of a whole fraction of a circle around an axis through
+    /// `center`, which is the smallest turn that works
+    Rotation {
+        center: Vertex,
+        axis: Direction,
+        /// the number of turns to go all the way around
+        order: usize,
+        map: Vec<Index>,
+    },
+}
+
+// Finds the vertex at a point, within a tolerance, by sorting the
+// vertices into cubes the size of the tolerance
+pub(crate) struct Lookup<'a> {
+    vertices: &'a [Vertex],
+    size: f64,
+    cells: HashMap<[i64;3],Vec<Index>>,
+}
+
+impl Symmetry {
+
+    // The vertex that each vertex is moved onto
+    pub fn map(&self) -> &[Index] {
+        match self {
+            Symmetry::Mirror { map, .. } |
+            Symmetry::Rotation { map, .. } => map,
+        }
+    }
+
+    // Vertices that swap places, each once with the lower index first,
+    // which is what a mirrored edit needs to move together
+    pub fn pairs(&self) -> Vec<(Index,Index)> {
+        self.map()
+            .iter()
+            .enumerate()
+            .filter(|(i,j)| i < j)
+            .map(|(i,j)| (i,*j))
+            .collect()
+    }
+
+    // The transform that moves the geometry onto itself
+    pub fn matrix(&self) -> Matrix {
+        match self {
+            Symmetry::Mirror { plane, .. } => {
+                let n = plane.normal.vector();
+                let d = plane.offset;
+                Matrix::new([
+                    1.0 - 2.0 * n.x * n.x, -2.0 * n.x * n.y,       -2.0 * n.x * n.z,       2.0 * d * n.x,
+                    -2.0 * n.y * n.x,       1.0 - 2.0 * n.y * n.y, -2.0 * n.y * n.z,       2.0 * d * n.y,
+                    -2.0 * n.z * n.x,       -2.0 * n.z * n.y,       1.0 - 2.0 * n.z * n.z, 2.0 * d * n.z,
+                    0.0,                    0.0,                    0.0,                    1.0,
+                ])
+            },
+            Symmetry::Rotation { center, axis, order, .. } => {
+                Matrix::translate(center.x,center.y,center.z) *
+                Matrix::rotate_axis(axis.vector(),TAU / *order as f64) *
+                Matrix::translate(-center.x,-center.y,-center.z)
+            },
+        }
+    }
+
+}
+
+impl<'a> Lookup<'a> {
+
+    pub(crate) fn new(vertices: &'a [Vertex], tolerance: f64) -> Self {
+        let size = tolerance.max(f64::EPSILON) * 2.0;
+        let mut lookup = Self { vertices, size, cells: HashMap::new() };
+        for (index,vertex) in vertices.iter().enumerate() {
+            let cell = lookup.cell(vertex);
+            lookup.cells.entry(cell).or_default().push(index);
+        }
+        lookup
+    }
+
+    fn cell(&self, point: &Vertex) -> [i64;3] {
+        [point.x,point.y,point.z].map(|v| (v / self.size).floor() as i64)
+    }
+
+    // The closest vertex within the tolerance of `point`
+    pub(crate) fn find(&self, point: &Vertex) -> Option<Index> {
+        let [x,y,z] = self.cell(point);
+        let mut best: Option<(f64,Index)> = None;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    for index in self.cells.get(&[x + dx,y + dy,z + dz]).into_iter().flatten() {
+                        let distance = self.vertices[*index].distance(point);
+                        if distance <= self.size / 2.0 && best.is_none_or(|(d,_)| distance < d) {
+                            best = Some((distance,*index));
+                        }
+                    }
+                }
+            }
+        }
+        best.map(|(_,i)| i)
+    }
+
+}
+
+// Every mirror plane and rotation axis of a geometry, where vertices
+// land within `tolerance` of other vertices and faces land on the
+// surface, however it happens to be split into triangles.
+// Planes and axes all pass through the middle of the vertices, so
+// they're found by trying the directions between vertices that are
+// the same distance from it, and the directions the vertices spread
+// out in the most and least.
+pub(crate) fn symmetries(geometry: &Geometry, tolerance: f64) -> Vec<Symmetry> {
+    let vertices = geometry.vertices();
+    let Some((center,_,spreads)) = spread(vertices) else {
+        return Vec::new();
+    };
+
+    let lookup = Lookup::new(vertices,tolerance);
+    let surface = Nearest::new(geometry);
+
+    // the vertices furthest from the middle, which any symmetry
+    // has to move between each other
+    let reach = vertices.iter().map(|v| v.distance(&center)).fold(0.0,f64::max);
+    let outer = vertices
+        .iter()
+        .filter(|v| reach - v.distance(&center) <= tolerance)
+        .take(CANDIDATES)
+        .copied()
+        .collect::<Vec<Vertex>>();
+
+    let mut directions = spreads.to_vec();
+    for (i,a) in outer.iter().enumerate() {
+        directions.push(*a - center);
+        for b in outer.iter().skip(i + 1) {
+            directions.push(*a - *b);
+            directions.push((*a + *b) * 0.5 - center);
+        }
+    }
+    let directions = unique(directions,tolerance / reach.max(f64::EPSILON));
+
+    let moves = |f: &dyn Fn(&Vertex) -> Vertex| -> Option<Vec<Index>> {
+        let map = vertices
+            .iter()
+            .map(|v| lookup.find(&f(v)))
+            .collect::<Option<Vec<Index>>>()?;
+        if map.iter().enumerate().all(|(i,j)| i == *j) {
+            return None;
+        }
+        let kept = surface.as_ref().is_none_or(|s| geometry
+            .faces()
+            .iter()
+            .filter(|f| f.is_valid(vertices))
+            .map(|f| (vertices[map[f.a]] + vertices[map[f.b]] + vertices[map[f.c]]) * (1.0 / 3.0))
+            .all(|c| s.closest(&c).0.distance(&c) <= tolerance));
+        kept.then_some(map)
+    };
+
+    let mut result = Vec::new();
+    for normal in directions.iter() {
+        let plane = Plane::from_point(&center,(*normal).into());
+        let n = plane.normal.vector();
+        if let Some(map) = moves(&|v| *v - n * (2.0 * plane.distance(v))) {
+            result.push(Symmetry::Mirror { plane, map });
+        }
+    }
+
+    for axis in directions.iter() {
+        let axis = axis.normalize();
+
+        // a vertex off the axis has to turn onto another vertex
+        // at the same height and distance from the axis
+        let offset = |v: &Vertex| {
+            let d = *v - center;
+            d - axis * d.dot(&axis)
+        };
+        let Some(start) = vertices.iter().find(|v| offset(v).magnitude() > tolerance) else {
+            continue;
+        };
+        let (from,height) = (offset(start),(*start - center).dot(&axis));
+
+        let mut angles = vertices
+            .iter()
+            .filter(|v| ((**v - center).dot(&axis) - height).abs() <= tolerance)
+            .map(offset)
+            .filter(|o| (o.magnitude() - from.magnitude()).abs() <= tolerance)
+            .map(|o| from.cross(&o).dot(&axis).atan2(from.dot(&o)).rem_euclid(TAU))
+            .filter(|a| *a * from.magnitude() > tolerance)
+            .collect::<Vec<f64>>();
+        angles.sort_by(f64::total_cmp);
+
+        // the smallest turn that works, if it goes evenly around
+        for angle in angles {
+            let order = (TAU / angle).round();
+            if order < 2.0 || (TAU / order - angle).abs() * from.magnitude() > tolerance {
+                continue;
+            }
+            let turn = Matrix::translate(center.x,center.y,center.z) *
+                Matrix::rotate_axis(axis,TAU / order) *
+                Matrix::translate(-center.x,-center.y,-center.z);
+            let turned = |v: &Vertex| {
+                let mut v = *v;
+                v.transform(&turn);
+                v
+            };
+            if let Some(map) = moves(&turned) {
+                let order = order as usize;
+                result.push(Symmetry::Rotation { center, axis: axis.into(), order, map });
+                break;
+            }
+        }
+    }
+
+    result
+}
+
+// The directions without any that are within an angle of pointing
+// the same way or straight back, or that are too short to point
+fn unique(directions: Vec<Vector>, angle: f64) -> Vec<Vector> {
+    let mut result: Vec<Vector> = Vec::new();
+    for direction in directions.into_iter().filter(|d| d.magnitude() > f64::EPSILON) {
+        let direction = direction.normalize();
+        if !result.iter().any(|d| d.cross(&direction).magnitude() <= angle.max(1e-9)) {
+            result.push(direction);
+        }
+    }
+    result
+}
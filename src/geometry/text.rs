@@ -0,0 +1,161 @@
+use std::fs;
+use std::fmt;
+use std::path::Path as FilePath;
+
+use lyon::math::point;
+use lyon::path::Path;
+use ttf_parser::{Face as FontFace,OutlineBuilder};
+
+use crate::geometry::*;
+use crate::errors::Error;
+
+// How far flattened glyph curves may stray, as a fraction of the text size
+const FLATNESS: f64 = 0.005;
+
+/// A TrueType or OpenType font used to turn text into geometry
+#[derive(Clone)]
+pub struct Font {
+    data: Vec<u8>,
+}
+
+// Collects glyph outlines into a lyon path, offset along the line
+struct Outline {
+    builder: lyon::path::Builder,
+    offset: f32,
+    open: bool,
+}
+
+impl fmt::Debug for Font {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f,"Font({} bytes)",self.data.len())
+    }
+}
+
+impl OutlineBuilder for Outline {
+
+    fn move_to(&mut self, x: f32, y: f32) {
+        if self.open {
+            self.builder.end(true);
+        }
+        self.builder.begin(point(x + self.offset,y));
+        self.open = true;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.builder.line_to(point(x + self.offset,y));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.builder.quadratic_bezier_to(
+            point(x1 + self.offset,y1),
+            point(x + self.offset,y));
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.builder.cubic_bezier_to(
+            point(x1 + self.offset,y1),
+            point(x2 + self.offset,y2),
+            point(x + self.offset,y));
+    }
+
+    fn close(&mut self) {
+        if self.open {
+            self.builder.end(true);
+            self.open = false;
+        }
+    }
+
+}
+
+impl Font {
+
+    // Fails if the data isn't a font that can be read
+    pub fn new(data: Vec<u8>) -> Result<Self,Error> {
+        FontFace::parse(&data,0).map_err(|e| Error::InvalidFont(e.to_string()))?;
+        Ok(Self { data })
+    }
+
+    pub fn load<P: AsRef<FilePath>>(path: P) -> Result<Self,Error> {
+        Self::new(fs::read(path)?)
+    }
+
+    fn face(&self) -> FontFace<'_> {
+        // checked when the font was created
+        FontFace::parse(&self.data,0).expect("font was validated")
+    }
+
+    // The outlines of a line of text with its baseline on the X axis,
+    // starting at the origin, scaled so the font's em is `size` high.
+    // Characters that aren't in the font are skipped.
+    pub fn profiles(&self, text: &str, size: f64) -> Vec<Profile> {
+        let face = self.face();
+        let scale = size / face.units_per_em() as f64;
+
+        let mut outline = Outline {
+            builder: Path::builder(),
+            offset: 0.0,
+            open: false,
+        };
+
+        for glyph in text.chars().filter_map(|c| face.glyph_index(c)) {
+            face.outline_glyph(glyph,&mut outline);
+            outline.close();
+            outline.offset += face.glyph_hor_advance(glyph).unwrap_or(0) as f32;
+        }
+
+        let path = outline.builder.build();
+        let loops = Polyline::from_path(&path,FLATNESS / scale * size)
+            .into_iter()
+            .map(|l| Polyline::closed(l
+                .points()
+                .iter()
+                .map(|(x,y)| (x * scale,y * scale))
+                .collect()))
+            .collect();
+
+        Profile::nest(loops)
+    }
+
+    // Text extruded `depth` up the Z axis, with the ends of every
+    // glyph in the `top` and `bottom` groups
+    pub fn extrude(&self, text: &str, size: f64, depth: f64) -> Result<Geometry,Error> {
+        let mut geometry = Geometry::default();
+        for profile in self.profiles(text,size) {
+            geometry.append(&profile.extrude(depth)?,&Matrix::identity());
+        }
+        Ok(geometry)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // only some systems have this font, so tests that need a real
+    // font are skipped without it
+    const FONT: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf";
+
+    #[test]
+    fn test_font_invalid() {
+        assert!(matches!(Font::new(vec![0,1,2,3]),Err(Error::InvalidFont(_))));
+    }
+
+    #[test]
+    fn test_font_profiles() {
+        let Ok(font) = Font::load(FONT) else { return };
+
+        // 'o' has a hole, 'i' is two separate shapes
+        let profiles = font.profiles("oi",10.0);
+        assert_eq!(profiles.len(),3);
+        assert_eq!(profiles.iter().map(|p| p.holes().len()).sum::<usize>(),1);
+
+        let geometry = font.extrude("oi",10.0,1.0).unwrap();
+        let bounds = Bounds::from_points(geometry.vertices()).unwrap();
+        assert!((bounds.max.z - 1.0).abs() < 1e-6);
+        assert!(bounds.max.y < 10.0);
+        assert!(geometry.group("top").is_some());
+    }
+
+}
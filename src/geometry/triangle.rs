@@ -1,6 +1,6 @@
 use crate::geometry::*;
 use crate::geometry::{Transform,Matrix};
-use crate::constant::Index;
+use crate::constant::{Index,TOLERANCE};
 
 #[derive(Default,Debug,Clone)]
 pub struct Triangle {
@@ -13,11 +13,11 @@ pub struct Triangle {
 impl Triangle {
 
     pub fn normal(&self) -> Normal {
-        let p1 = self.p1.clone();
-        let p2 = self.p2.clone();
-        let p3 = self.p3.clone();
+        let p1 = self.p1;
+        let p2 = self.p2;
+        let p3 = self.p3;
 
-        let a = p2 - p1.clone();
+        let a = p2 - p1;
         let b = p3 - p1;
 
         let x = a.y * b.z - a.z * b.y;
@@ -26,12 +26,162 @@ impl Triangle {
 
         Normal::new(x,y,z).normalize()
     }
-    
+
+    pub fn centroid(&self) -> Vertex {
+        (self.p1 + self.p2 + self.p3) / 3
+    }
+
+    pub fn area(&self) -> f64 {
+        (self.p2 - self.p1).cross(&(self.p3 - self.p1)).magnitude() / 2.0
+    }
+
+    // Edge lengths, starting with the edge from p1 to p2
+    pub fn edge_lengths(&self) -> [f64;3] {
+        [
+            self.p1.distance(&self.p2),
+            self.p2.distance(&self.p3),
+            self.p3.distance(&self.p1),
+        ]
+    }
+
+    // How stretched the triangle is: 1 for an equilateral triangle,
+    // growing without limit as it flattens into a line.
+    pub fn aspect_ratio(&self) -> f64 {
+        let longest = self.edge_lengths().into_iter().fold(0.0,f64::max);
+        let area = self.area();
+        if area == 0.0 {
+            return f64::INFINITY;
+        }
+        longest * longest * 3f64.sqrt() / (4.0 * area)
+    }
+
+    pub fn plane(&self) -> Plane {
+        Plane::from_point(&self.p1,self.normal())
+    }
+
+    // The weights of each corner for the point on the triangle's
+    // plane nearest `point`, or None for degenerate triangles. The
+    // weights sum to one and are all positive inside the triangle.
+    pub fn barycentric(&self, point: &Vertex) -> Option<(f64,f64,f64)> {
+        let ab = self.p2 - self.p1;
+        let ac = self.p3 - self.p1;
+        let ap = *point - self.p1;
+
+        let d00 = ab.dot(&ab);
+        let d01 = ab.dot(&ac);
+        let d11 = ac.dot(&ac);
+        let d20 = ap.dot(&ab);
+        let d21 = ap.dot(&ac);
+
+        let denom = d00 * d11 - d01 * d01;
+        if denom.abs() <= f64::EPSILON * d00 * d11 {
+            return None;
+        }
+
+        let v = (d11 * d20 - d01 * d21) / denom;
+        let w = (d00 * d21 - d01 * d20) / denom;
+        Some((1.0 - v - w,v,w))
+    }
+
+    // True if `point` lies on the triangle, within tolerance
+    pub fn contains(&self, point: &Vertex) -> bool {
+        self.distance(point) <= TOLERANCE
+    }
+
+    // The point on the triangle nearest to `point`, found by
+    // checking which vertex, edge or face region it projects into.
+    pub fn closest_point(&self, point: &Vertex) -> Vertex {
+        let (a,b,c) = (self.p1,self.p2,self.p3);
+        let p = *point;
+
+        let ab = b - a;
+        let ac = c - a;
+        let ap = p - a;
+
+        let d1 = ab.dot(&ap);
+        let d2 = ac.dot(&ap);
+        if d1 <= 0.0 && d2 <= 0.0 {
+            return a;
+        }
+
+        let bp = p - b;
+        let d3 = ab.dot(&bp);
+        let d4 = ac.dot(&bp);
+        if d3 >= 0.0 && d4 <= d3 {
+            return b;
+        }
+
+        let vc = d1 * d4 - d3 * d2;
+        if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+            return a + ab * (d1 / (d1 - d3));
+        }
+
+        let cp = p - c;
+        let d5 = ab.dot(&cp);
+        let d6 = ac.dot(&cp);
+        if d6 >= 0.0 && d5 <= d6 {
+            return c;
+        }
+
+        let vb = d5 * d2 - d1 * d6;
+        if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+            return a + ac * (d2 / (d2 - d6));
+        }
+
+        let va = d3 * d6 - d5 * d4;
+        if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+            return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+        }
+
+        let denom = va + vb + vc;
+
+        // degenerate triangles have no interior
+        if denom == 0.0 {
+            return a;
+        }
+
+        let v = vb / denom;
+        let w = vc / denom;
+        a + ab * v + ac * w
+    }
+
+    pub fn distance(&self, point: &Vertex) -> f64 {
+        self.closest_point(point).distance(point)
+    }
+
+    // Where the segment from `a` to `b` passes through the inside
+    // of the triangle, or None if it misses, runs along the plane
+    // or only touches an edge or the triangle at one of its ends.
+    pub fn crossing(&self, a: &Vertex, b: &Vertex) -> Option<Vertex> {
+        let direction = *b - *a;
+        let e1 = self.p2 - self.p1;
+        let e2 = self.p3 - self.p1;
+        let p = direction.cross(&e2);
+        let det = e1.dot(&p);
+        if det.abs() <= f64::EPSILON * e1.magnitude() * e2.magnitude() * direction.magnitude() {
+            return None;
+        }
+
+        let s = *a - self.p1;
+        let u = s.dot(&p) / det;
+        let q = s.cross(&e1);
+        let v = direction.dot(&q) / det;
+        let t = e2.dot(&q) / det;
+
+        let inside = u > TOLERANCE && v > TOLERANCE && u + v < 1.0 - TOLERANCE;
+        let between = t > TOLERANCE && t < 1.0 - TOLERANCE;
+        match inside && between {
+            true => Some(*a + direction * t),
+            false => None,
+        }
+    }
+
     pub fn as_face(self) -> Face {
         Face {
             a: self.indices.0,
             b: self.indices.1,
             c: self.indices.2,
+            ..Default::default()
         }
     }
 
@@ -49,7 +199,12 @@ impl Transform for Triangle {
 mod tests {
 
     use super::*;
-    use crate::geometry::*;
+
+    macro_rules! fassert_eq {
+        ( $v: expr, $e: expr ) => {
+            assert_relative_eq!($v,$e, epsilon = f64::EPSILON);
+        }
+    }
 
     #[test]
     fn test_triangle_normal() {
@@ -68,4 +223,105 @@ mod tests {
         assert_eq!(normal.z,1.0);
     }
 
+    #[test]
+    fn test_triangle_closest_point() {
+        let data = vec![
+            Vertex::new(0.0,0.0,0.0),
+            Vertex::new(1.0,0.0,0.0),
+            Vertex::new(0.0,1.0,0.0),
+        ];
+
+        let t = Face::new(1,2,3).triangle(&data);
+
+        // above the interior
+        let p = t.closest_point(&Vertex::new(0.25,0.25,2.0));
+        assert_eq!(p.x,0.25);
+        assert_eq!(p.y,0.25);
+        assert_eq!(p.z,0.0);
+
+        // past a corner
+        let p = t.closest_point(&Vertex::new(-1.0,-1.0,0.0));
+        assert_eq!(p.x,0.0);
+        assert_eq!(p.y,0.0);
+
+        // beside the hypotenuse
+        let p = t.closest_point(&Vertex::new(1.0,1.0,0.0));
+        assert_eq!(p.x,0.5);
+        assert_eq!(p.y,0.5);
+
+        assert_eq!(t.distance(&Vertex::new(0.25,0.25,2.0)),2.0);
+    }
+
+    #[test]
+    fn test_triangle_centroid_and_plane() {
+        let data = vec![
+            Vertex::new(0.0,0.0,1.0),
+            Vertex::new(3.0,0.0,1.0),
+            Vertex::new(0.0,3.0,1.0),
+        ];
+
+        let t = Face::new(1,2,3).triangle(&data);
+        assert_eq!(t.centroid(),Vertex::new(1.0,1.0,1.0));
+
+        let plane = t.plane();
+        assert_eq!(plane.normal,Normal::new(0.0,0.0,1.0));
+        assert_eq!(plane.offset,1.0);
+    }
+
+    #[test]
+    fn test_triangle_aspect_ratio() {
+        let data = vec![
+            Vertex::new(0.0,0.0,0.0),
+            Vertex::new(2.0,0.0,0.0),
+            Vertex::new(1.0,3f64.sqrt(),0.0),
+            Vertex::new(1.0,0.01,0.0),
+        ];
+
+        let t = Face::new(1,2,3).triangle(&data);
+        assert_relative_eq!(t.area(),3f64.sqrt(),epsilon = 1e-12);
+        assert_relative_eq!(t.aspect_ratio(),1.0,epsilon = 1e-12);
+
+        let sliver = Face::new(1,2,4).triangle(&data);
+        assert!(sliver.aspect_ratio() > 100.0);
+
+        let line = Face::new(1,2,2).triangle(&data);
+        assert_eq!(line.aspect_ratio(),f64::INFINITY);
+        assert_eq!(line.edge_lengths(),[2.0,0.0,2.0]);
+    }
+
+    #[test]
+    fn test_triangle_barycentric() {
+        let data = vec![
+            Vertex::new(0.0,0.0,0.0),
+            Vertex::new(1.0,0.0,0.0),
+            Vertex::new(0.0,1.0,0.0),
+        ];
+
+        let t = Face::new(1,2,3).triangle(&data);
+        assert_eq!(t.barycentric(&Vertex::new(0.0,0.0,0.0)),Some((1.0,0.0,0.0)));
+        assert_eq!(t.barycentric(&Vertex::new(0.5,0.0,5.0)),Some((0.5,0.5,0.0)));
+
+        let (u,v,w) = t.barycentric(&Vertex::new(2.0,2.0,0.0)).unwrap();
+        assert!(u < 0.0);
+        fassert_eq!(u + v + w,1.0);
+
+        let line = Face::new(1,2,2).triangle(&data);
+        assert_eq!(line.barycentric(&Vertex::new(0.0,0.0,0.0)),None);
+    }
+
+    #[test]
+    fn test_triangle_contains() {
+        let data = vec![
+            Vertex::new(0.0,0.0,0.0),
+            Vertex::new(1.0,0.0,0.0),
+            Vertex::new(0.0,1.0,0.0),
+        ];
+
+        let t = Face::new(1,2,3).triangle(&data);
+        assert!(t.contains(&Vertex::new(0.25,0.25,0.0)));
+        assert!(t.contains(&Vertex::new(0.5,0.5,0.0)));
+        assert!(!t.contains(&Vertex::new(0.25,0.25,0.1)));
+        assert!(!t.contains(&Vertex::new(0.6,0.6,0.0)));
+    }
+
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
 use std::fmt;
-use std::ops::{Div,Sub,Mul};
+use std::ops::{Div,Add,Sub,Mul};
 use std::convert::TryFrom;
 
 use crate::utilities;
@@ -7,7 +7,7 @@ use crate::geometry::{Transform,Matrix};
 use crate::errors::Error;
 use crate::constant::VERTEX_TAG;
 
-#[derive(Default,Debug,Copy,Clone)]
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
 pub struct Vector {
     pub x: f64,
     pub y: f64,
@@ -15,7 +15,6 @@ pub struct Vector {
 }
 
 pub type Vertex = Vector;
-pub type Normal = Vertex;
 
 impl Div<usize> for Vector {
     type Output = Self;
@@ -29,6 +28,18 @@ impl Div<usize> for Vector {
     }
 }
 
+impl Add for Vector {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self::Output {
+        Self {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
 impl Sub for Vector {
     type Output = Self;
 
@@ -56,11 +67,7 @@ impl Mul<f64> for Vector {
 impl Vector {
 
     pub const fn new(x: f64, y: f64, z: f64) -> Self {
-        Self { 
-            x: x, 
-            y: y, 
-            z: z
-        }
+        Self { x, y, z }
     }
 
     pub fn with<T: Into<f64>>((x,y,z): (T,T,T)) -> Self {
@@ -83,7 +90,7 @@ impl Vector {
     }
 
     pub fn normalize(&self) -> Vector {
-        let mut v = self.clone();
+        let mut v = *self;
         let m = v.magnitude();
         if m > 0.0 {
             v.x /= m;
@@ -93,6 +100,29 @@ impl Vector {
         v
     }
 
+    pub fn dot(&self, other: &Vector) -> f64 {
+        self.x * other.x +
+        self.y * other.y +
+        self.z * other.z
+    }
+
+    pub fn cross(&self, other: &Vector) -> Vector {
+        Vector::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    // The angle between two vectors in radians
+    pub fn angle(&self, other: &Vector) -> f64 {
+        let m = self.magnitude() * other.magnitude();
+        if m == 0.0 {
+            return 0.0;
+        }
+        (self.dot(other) / m).clamp(-1.0,1.0).acos()
+    }
+
     pub fn distance(&self, other: &Vector) -> f64 {
         let (x1,y1,z1) = self.unpack();
         let (x2,y2,z2) = other.unpack();
@@ -108,6 +138,29 @@ impl Vector {
         (self.x,self.y,self.z)
     }
 
+    // Transforms the vector as a direction, ignoring translation
+    pub fn transform_direction(&mut self, matrix: &Matrix) {
+        let [
+            m11, m12, m13, _,
+            m21, m22, m23, _,
+            m31, m32, m33, _,
+            _, _, _, _
+        ] = matrix.unpack();
+
+        let (x,y,z) = self.unpack();
+
+        self.x = m11 * x + m12 * y + m13 * z;
+        self.y = m21 * x + m22 * y + m23 * z;
+        self.z = m31 * x + m32 * y + m33 * z;
+    }
+
+    // Transforms the vector as a surface normal, so that it stays
+    // perpendicular to the surface under non-uniform scaling.
+    pub fn transform_normal(&mut self, matrix: &Matrix) {
+        self.transform_direction(&matrix.normal());
+        *self = self.normalize();
+    }
+
 }
 
 impl TryFrom<&str> for Vector {
@@ -190,6 +243,25 @@ impl<T: Transform> Transform for Vec<T> {
 mod tests {
 
     use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+
+        #[test]
+        fn test_vector_parse_never_panics(data in "\\PC*") {
+            let _ = Vector::try_from(data);
+        }
+
+        #[test]
+        fn test_vector_string_round_trip(x in -1e9f64..1e9, y in -1e9f64..1e9, z in -1e9f64..1e9) {
+            let vector = Vector::new(x,y,z);
+            let result = Vector::try_from(String::from(vector)).unwrap();
+            prop_assert_eq!(result.x,x);
+            prop_assert_eq!(result.y,y);
+            prop_assert_eq!(result.z,z);
+        }
+
+    }
 
     #[test]
     fn test_string_from_vector_int() {
@@ -255,6 +327,51 @@ mod tests {
         assert_eq!(v2,6667.0);
     }
 
+    #[test]
+    fn test_vector_dot_cross() {
+        let a = Vector::new(1.0,0.0,0.0);
+        let b = Vector::new(0.0,1.0,0.0);
+        let c = a.cross(&b);
+
+        assert_eq!(a.dot(&b),0.0);
+        assert_eq!(c.x,0.0);
+        assert_eq!(c.y,0.0);
+        assert_eq!(c.z,1.0);
+    }
+
+    #[test]
+    fn test_vector_angle() {
+        let a = Vector::new(1.0,0.0,0.0);
+        let b = Vector::new(0.0,2.0,0.0);
+        let c = Vector::new(-3.0,0.0,0.0);
+
+        assert_eq!(a.angle(&a),0.0);
+        assert_eq!(a.angle(&b),std::f64::consts::FRAC_PI_2);
+        assert_eq!(a.angle(&c),std::f64::consts::PI);
+    }
+
+    #[test]
+    fn test_vector_transform_direction() {
+        let mut point = Vector::new(1.0,2.0,3.0);
+        let mut direction = Vector::new(1.0,2.0,3.0);
+
+        let m = Matrix::translate(1.0,1.0,1.0);
+        point.transform(&m);
+        direction.transform_direction(&m);
+
+        assert_eq!(point,Vector::new(2.0,3.0,4.0));
+        assert_eq!(direction,Vector::new(1.0,2.0,3.0));
+    }
+
+    #[test]
+    fn test_vector_add() {
+        let vector = Vector::new(1.0,2.0,3.0) + Vector::new(1.0,1.0,1.0);
+
+        assert_eq!(vector.x,2.0);
+        assert_eq!(vector.y,3.0);
+        assert_eq!(vector.z,4.0);
+    }
+
     #[test]
     fn test_vector_sub() {
         let vector1 = Vector::new(2.0,2.0,2.0);
@@ -0,0 +1,186 @@
+use std::fs;
+use std::path::Path;
+
+use crate::assembly::{Assembly,Instance};
+use crate::part::Catalog;
+use crate::geometry::Matrix;
+use crate::errors::{Error,Context};
+
+// the column with the name of the part in the catalog
+const PART: &str = "part";
+
+// the column with the name of the instance, if there is one
+const NAME: &str = "name";
+
+// A row of the file with the line it starts on
+struct Record {
+    line: usize,
+    cells: Vec<String>,
+}
+
+// Reads a CSV file into an assembly, see `parse`
+pub fn load<P: AsRef<Path>>(path: P, catalog: &Catalog) -> Result<Assembly,Error> {
+    let path = path.as_ref();
+    let name = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    parse(name,&fs::read_to_string(path)?,catalog)
+}
+
+// Reads a cut list with one part on each row into an assembly. The
+// first row names the columns: "part" is the name of a part in the
+// catalog to copy, an optional "name" column names the instance, and
+// every other column sets the attribute with that name. Empty cells
+// leave the attribute as it is in the catalog. Every instance is
+// placed at the origin. Errors are given for the line the row is on.
+pub fn parse(name: &str, text: &str, catalog: &Catalog) -> Result<Assembly,Error> {
+    span!("import.csv", bytes = text.len());
+    let mut records = records(text)?.into_iter();
+    let mut assembly = Assembly::new(name);
+
+    let Some(header) = records.next() else {
+        return Ok(assembly);
+    };
+
+    let columns = header.cells
+        .iter()
+        .map(|c| c.trim())
+        .collect::<Vec<&str>>();
+
+    let column = |name: &str| columns.iter().position(|c| c.eq_ignore_ascii_case(name));
+    let part = column(PART).ok_or_else(|| Error::InvalidLine {
+        line: header.line,
+        text: header.cells.join(","),
+    })?;
+    let label = column(NAME);
+
+    for record in records {
+        let cell = |index: usize| record.cells
+            .get(index)
+            .map(|c| c.trim())
+            .unwrap_or_default();
+
+        let template = cell(part);
+        let mut instance = catalog
+            .get(template)
+            .cloned()
+            .ok_or_else(|| Error::UnknownPart(template.into()))
+            .in_line(record.line)?;
+
+        for (index,attribute) in columns.iter().enumerate() {
+            if index == part || Some(index) == label || cell(index).is_empty() {
+                continue;
+            }
+            cell(index)
+                .parse::<f64>()
+                .map_err(Error::from)
+                .and_then(|v| instance.set(attribute,v))
+                .in_attribute(attribute)
+                .in_line(record.line)?;
+        }
+
+        let mut instance = Instance::new(instance,Matrix::identity());
+        if let Some(name) = label.map(cell).filter(|n| !n.is_empty()) {
+            instance = instance.with_name(name);
+        }
+        assembly.add(instance);
+    }
+
+    Ok(assembly)
+}
+
+// Splits the text into rows of cells. Cells can be quoted to hold
+// commas, line breaks or quotes, which are written twice. Blank
+// lines are skipped.
+fn records(text: &str) -> Result<Vec<Record>,Error> {
+    let mut result = Vec::new();
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let (mut line,mut start) = (1,1);
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quoted,c) {
+            (true,'"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            },
+            (true,'"') => quoted = false,
+            (false,'"') if cell.trim().is_empty() => {
+                cell.clear();
+                quoted = true;
+            },
+            (false,',') => cells.push(std::mem::take(&mut cell)),
+            (false,'\r') => (),
+            (false,'\n') => {
+                cells.push(std::mem::take(&mut cell));
+                if cells.iter().any(|c| !c.trim().is_empty()) {
+                    result.push(Record { line: start, cells: std::mem::take(&mut cells) });
+                }
+                cells.clear();
+                line += 1;
+                start = line;
+            },
+            (_,c) => {
+                if c == '\n' {
+                    line += 1;
+                }
+                cell.push(c);
+            },
+        }
+    }
+
+    if quoted {
+        return Err(Error::InvalidLine { line: start, text: cells.join(",") + &cell });
+    }
+
+    cells.push(cell);
+    if cells.iter().any(|c| !c.trim().is_empty()) {
+        result.push(Record { line: start, cells });
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::models::{Lumber,SheetGoods};
+
+    #[test]
+    fn test_csv_parse() {
+        let catalog = Catalog::new()
+            .with_part(Lumber::TwoByFour.part())
+            .with_part(SheetGoods::Plywood.part());
+
+        let text = "Part, Name , Length,Width\r\n\
+            2x4,left stud,0.5,\r\n\
+            \r\n\
+            plywood,\"back, upper\",-1.2,-0.6\r\n\
+            2x4,,,";
+        let assembly = parse("shelf",text,&catalog).unwrap();
+        assert_eq!(assembly.name(),"shelf");
+        assert_eq!(assembly.instances().len(),3);
+
+        let stud = &assembly.instances()[0];
+        assert_eq!(stud.name(),"left stud");
+        assert_eq!(stud.part().attribute("Length").unwrap().value(),0.5);
+        assert_eq!(assembly.instances()[1].name(),"back, upper");
+        assert_eq!(assembly.instances()[1].part().attribute("Width").unwrap().value(),-0.6);
+        assert_eq!(assembly.instances()[2].name(),"2x4");
+        assert_eq!(assembly.instances()[2].part().attribute("Length").unwrap().value(),0.0);
+
+        // errors are given for the line the row is on
+        let error = parse("",&(text.to_owned() + "\n2x4,,1.0,1.0"),&catalog).unwrap_err();
+        assert!(matches!(error,Error::InLine(6,_)));
+        assert!(error.to_string().starts_with("On line 6: In attribute 'Width'"));
+        assert!(matches!(error.root(),Error::UnknownAttribute(n) if n == "Width"));
+        assert!(matches!(parse("","part\nbench",&catalog),Err(Error::InLine(2,_))));
+        assert!(matches!(parse("","part\n2x4,\"open",&catalog),Err(Error::InvalidLine { line: 2, .. })));
+        assert!(matches!(parse("","name,Length\n",&catalog),Err(Error::InvalidLine { line: 1, .. })));
+        assert!(parse("","",&catalog).unwrap().instances().is_empty());
+    }
+
+}
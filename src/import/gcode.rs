@@ -0,0 +1,363 @@
+use std::fs;
+use std::path::Path;
+use std::f64::consts::TAU;
+
+use crate::geometry::{Vertex,Bounds,Polyline};
+use crate::part::Units;
+use crate::errors::Error;
+
+// the furthest a flattened arc strays from the true arc, in meters
+const ARC_TOLERANCE: f64 = 1e-5;
+
+// the most points an arc is flattened into, enough for a full
+// circle a few kilometers across
+const ARC_SEGMENTS: f64 = 1e5;
+
+/// How the tool moves along a stroke
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Motion {
+    /// moving as fast as possible without cutting (G0)
+    Rapid,
+    /// cutting at the feed rate (G1, G2 and G3)
+    #[default]
+    Feed,
+}
+
+/// An unbroken run of moves of the same kind, in meters
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Stroke {
+    pub motion: Motion,
+    pub points: Vec<Vertex>,
+}
+
+/// The moves of a G-code program, read for previewing. Only
+/// straight moves and arcs in the XY plane are understood, and
+/// anything else, like spindle and coolant codes, is ignored.
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Toolpath {
+    strokes: Vec<Stroke>,
+}
+
+// The modal state of the machine while reading
+struct Machine {
+    position: Vertex,
+    motion: u8,
+    scale: f64,
+    absolute: bool,
+}
+
+impl Stroke {
+
+    // The stroke seen from above
+    pub fn polyline(&self) -> Polyline {
+        Polyline::new(self.points.iter().map(|p| (p.x,p.y)).collect())
+    }
+
+    pub fn length(&self) -> f64 {
+        self.points
+            .windows(2)
+            .map(|w| w[0].distance(&w[1]))
+            .sum()
+    }
+
+}
+
+impl Toolpath {
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self,Error> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    // Reads G0, G1, G2 and G3 moves in absolute (G90) or relative
+    // (G91) coordinates, in millimeters (G21) or inches (G20).
+    // Arcs are flattened into short straight moves.
+    pub fn parse(text: &str) -> Result<Self,Error> {
+        span!("import.gcode");
+        let mut machine = Machine {
+            position: Vertex::default(),
+            motion: 0,
+            scale: 1.0 / Units::Millimeters.per_meter(),
+            absolute: true,
+        };
+
+        let mut path = Self::default();
+        for (index,line) in text.lines().enumerate() {
+            let invalid = || Error::InvalidLine { line: index + 1, text: line.into() };
+            let words = words(line).ok_or_else(invalid)?;
+            machine.run(&words,&mut path).map_err(|_| invalid())?;
+        }
+        Ok(path)
+    }
+
+    pub fn strokes(&self) -> &[Stroke] {
+        &self.strokes
+    }
+
+    // Every stroke of one kind seen from above
+    pub fn polylines(&self, motion: Motion) -> Vec<Polyline> {
+        self.strokes
+            .iter()
+            .filter(|s| s.motion == motion)
+            .map(Stroke::polyline)
+            .collect()
+    }
+
+    // The distance travelled by one kind of move
+    pub fn length(&self, motion: Motion) -> f64 {
+        self.strokes
+            .iter()
+            .filter(|s| s.motion == motion)
+            .map(Stroke::length)
+            .sum()
+    }
+
+    // The box around every point the tool visits, or
+    // None if it never moves
+    pub fn bounds(&self) -> Option<Bounds> {
+        Bounds::from_points(&self.strokes
+            .iter()
+            .flat_map(|s| s.points.iter().copied())
+            .collect::<Vec<Vertex>>())
+    }
+
+    // Adds a point, starting a new stroke from the last
+    // position if the kind of move changed
+    fn push(&mut self, motion: Motion, from: Vertex, point: Vertex) {
+        match self.strokes.last_mut() {
+            Some(stroke) if stroke.motion == motion => stroke.points.push(point),
+            _ => self.strokes.push(Stroke { motion, points: vec![from,point] }),
+        }
+    }
+
+}
+
+impl Machine {
+
+    // Carries out the words on one line
+    fn run(&mut self, words: &[(char,f64)], path: &mut Toolpath) -> Result<(),Error> {
+        let value = |letter: char| words
+            .iter()
+            .find(|(l,_)| *l == letter)
+            .map(|(_,v)| *v);
+
+        for (letter,value) in words.iter().copied() {
+            match (letter,value as u32) {
+                ('G',code @ 0..=3) if value.fract() == 0.0 => self.motion = code as u8,
+                ('G',17) => {},
+                ('G',18 | 19) => return Err(Error::ParseError),
+                ('G',20) => self.scale = 1.0 / Units::Inches.per_meter(),
+                ('G',21) => self.scale = 1.0 / Units::Millimeters.per_meter(),
+                ('G',90) => self.absolute = true,
+                ('G',91) => self.absolute = false,
+                _ => {},
+            }
+        }
+
+        // codes on their own set a mode without moving
+        if !words.iter().any(|(l,_)| matches!(l,'X' | 'Y' | 'Z')) {
+            return Ok(());
+        }
+
+        let axis = |letter: char, current: f64| match (value(letter),self.absolute) {
+            (Some(v),true) => v * self.scale,
+            (Some(v),false) => current + v * self.scale,
+            (None,_) => current,
+        };
+
+        let start = self.position;
+        let end = Vertex::new(
+            axis('X',start.x),
+            axis('Y',start.y),
+            axis('Z',start.z));
+
+        match self.motion {
+            0 => path.push(Motion::Rapid,start,end),
+            1 => path.push(Motion::Feed,start,end),
+            code => {
+                // an arc needs a centre or a radius to go around
+                let radius = value('R').map(|r| r * self.scale);
+                let offset = match (value('I'),value('J')) {
+                    (None,None) if radius.is_none() => return Err(Error::ParseError),
+                    (i,j) => (i.unwrap_or(0.0) * self.scale,j.unwrap_or(0.0) * self.scale),
+                };
+                for point in arc(start,end,offset,radius,code == 2)? {
+                    path.push(Motion::Feed,start,point);
+                }
+            },
+        }
+
+        self.position = end;
+        Ok(())
+    }
+
+}
+
+// The words on a line as letters and numbers, without comments
+// in parentheses or after a semicolon. None if a word is invalid
+// or the parentheses don't match.
+fn words(line: &str) -> Option<Vec<(char,f64)>> {
+    let mut text = String::new();
+    let mut depth = 0usize;
+    for c in line.chars() {
+        match c {
+            ';' if depth == 0 => break,
+            '(' => depth += 1,
+            ')' => depth = depth.checked_sub(1)?,
+            c if depth == 0 => text.push(c),
+            _ => {},
+        }
+    }
+    if depth != 0 {
+        return None;
+    }
+
+    let mut result = Vec::new();
+    let mut chars = text.chars().filter(|c| !c.is_whitespace()).peekable();
+    while let Some(letter) = chars.next() {
+        let mut number = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || matches!(c,'.' | '-' | '+')) {
+            number.push(c);
+        }
+        match letter.to_ascii_uppercase() {
+            '%' => {},
+            l if l.is_ascii_alphabetic() => result.push((l,number.parse().ok()?)),
+            _ => return None,
+        }
+    }
+    Some(result)
+}
+
+// The points along an arc in the XY plane from `start` to `end`,
+// not including the start, with any change in Z spread along it.
+// The centre is either offset from the start or found from a
+// radius, which is negative for the longer way around.
+fn arc(start: Vertex, end: Vertex, offset: (f64,f64), radius: Option<f64>, clockwise: bool) -> Result<Vec<Vertex>,Error> {
+    let center = match radius {
+        Some(r) => {
+            let (dx,dy) = (end.x - start.x,end.y - start.y);
+            let chord = dx.hypot(dy);
+            if chord == 0.0 || chord > 2.0 * r.abs() + ARC_TOLERANCE {
+                return Err(Error::ParseError);
+            }
+            let height = (r * r - chord * chord / 4.0).max(0.0).sqrt();
+            let side = if clockwise == (r > 0.0) { -1.0 } else { 1.0 };
+            (start.x + dx / 2.0 - side * height * dy / chord,
+             start.y + dy / 2.0 + side * height * dx / chord)
+        },
+        None => (start.x + offset.0,start.y + offset.1),
+    };
+
+    let radius = (start.x - center.0).hypot(start.y - center.1);
+    let from = (start.y - center.1).atan2(start.x - center.0);
+    let to = (end.y - center.1).atan2(end.x - center.0);
+
+    // the same start and end makes a full circle
+    let mut sweep = to - from;
+    if clockwise {
+        while sweep >= 0.0 { sweep -= TAU; }
+    } else {
+        while sweep <= 0.0 { sweep += TAU; }
+    }
+
+    let step = match radius > ARC_TOLERANCE {
+        true => 2.0 * (1.0 - ARC_TOLERANCE / radius).acos(),
+        false => TAU,
+    };
+    let count = (sweep.abs() / step).ceil();
+    if count.is_nan() || count > ARC_SEGMENTS {
+        return Err(Error::ParseError);
+    }
+    let count = (count as usize).max(1);
+
+    Ok((1..=count)
+        .map(|i| {
+            let t = i as f64 / count as f64;
+            match i == count {
+                true => end,
+                false => {
+                    let angle = from + sweep * t;
+                    Vertex::new(
+                        center.0 + radius * angle.cos(),
+                        center.1 + radius * angle.sin(),
+                        start.z + (end.z - start.z) * t)
+                },
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_gcode_lines() {
+        let path = Toolpath::parse("
+            %
+            G21 G90 (metric, absolute)
+            G0 Z5
+            X10 Y0 ; move over
+            G1 Z-1 F300
+            Y10
+            X0
+            G91 Y-10
+            G0 Z6
+        ").unwrap();
+
+        let strokes = path.strokes();
+        assert_eq!(strokes.len(),3);
+        assert_eq!(strokes[0].motion,Motion::Rapid);
+        assert_eq!(strokes[1].points.len(),5);
+        assert_eq!(strokes[1].points[4],Vertex::new(0.0,0.0,-0.001));
+        assert_relative_eq!(path.length(Motion::Feed),0.036);
+        assert_eq!(path.polylines(Motion::Feed)[0].points()[2],(0.01,0.01));
+
+        let bounds = path.bounds().unwrap();
+        assert_relative_eq!(bounds.max.z,0.005);
+        assert_relative_eq!(bounds.min.z,-0.001);
+    }
+
+    #[test]
+    fn test_gcode_arcs() {
+        // half circles of radius 10mm, with I and J and with R
+        for text in ["G1 X10\nG3 X-10 I-10 J0","G1 X10\nG3 X-10 R10"] {
+            let path = Toolpath::parse(text).unwrap();
+            let points = &path.strokes()[0].points;
+            assert!(points.len() > 10);
+            assert!(points.iter().skip(1).all(|p| (p.x.hypot(p.y) - 0.01).abs() < 1e-9));
+            assert!(points.iter().all(|p| p.y > -1e-9));
+            assert_relative_eq!(path.length(Motion::Feed),0.01 + std::f64::consts::PI * 0.01,epsilon = 1e-4);
+        }
+
+        // a full clockwise circle in inches
+        let path = Toolpath::parse("G20 G0 X1\nG2 X1 Y0 I-1").unwrap();
+        let circle = &path.strokes()[1];
+        assert_eq!(circle.motion,Motion::Feed);
+        assert!(circle.points.iter().any(|p| p.y < -0.025));
+        assert_relative_eq!(circle.length(),std::f64::consts::TAU * 0.0254,epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_gcode_invalid() {
+        assert!(matches!(Toolpath::parse("G0 X1\nG1 X?"),Err(Error::InvalidLine { line: 2, .. })));
+        assert!(matches!(Toolpath::parse("G18\nG2 X1 I1"),Err(Error::InvalidLine { line: 1, .. })));
+        assert!(Toolpath::parse("G2 X50 R1").is_err());
+
+        // arcs without a centre or radius aren't straight lines
+        assert!(matches!(Toolpath::parse("G0 X1\nG2 X2 Y1"),Err(Error::InvalidLine { line: 2, .. })));
+        assert!(matches!(Toolpath::parse("G3 X1\nX2"),Err(Error::InvalidLine { line: 1, .. })));
+        assert!(Toolpath::parse("G0 X1\nG2 X2 Y1 J1").is_ok());
+
+        // comments have to be closed, and can't close what isn't open
+        assert!(matches!(Toolpath::parse("G1 X1 ) Y2"),Err(Error::InvalidLine { line: 1, .. })));
+        assert!(matches!(Toolpath::parse("G0 X1\nG1 (cut X2"),Err(Error::InvalidLine { line: 2, .. })));
+        assert_eq!(Toolpath::parse("G1 (a (b) c) X1 ; done)").unwrap().strokes().len(),1);
+
+        // arcs too big to flatten
+        assert!(matches!(Toolpath::parse("R018446744073709551615G2X1G3G91"),Err(Error::InvalidLine { line: 1, .. })));
+        assert!(matches!(Toolpath::parse("G0 X1\nG2 X1 I-100000000"),Err(Error::InvalidLine { line: 2, .. })));
+        assert!(Toolpath::parse("M3 S1000\nT1 M6").unwrap().strokes().is_empty());
+    }
+
+}
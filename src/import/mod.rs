@@ -0,0 +1,6 @@
+pub mod gcode;
+pub mod csv;
+mod points;
+pub mod parametric;
+
+pub use points::PointCloud;
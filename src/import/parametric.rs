@@ -0,0 +1,128 @@
+use crate::geometry::{Geometry,Vector,BoxFit};
+use crate::part::{Part,Attribute,AttributeItem,Alteration,Selection};
+use crate::errors::{Error,Context};
+
+// The attribute for each side of the fitted box, longest first, and
+// the groups at the low and high end of it. The attribute moves the
+// high end.
+const SIDES: [(&str,&str,&str);3] = [
+    ("Length","back","front"),
+    ("Width","left","right"),
+    ("Height","bottom","top"),
+];
+
+// Turns a box-like mesh, like a board drawn in another program, into
+// a part with "Length", "Width" and "Height" attributes along the
+// sides of the box around it, longest first. The vertices within
+// `tolerance` of each end of the box are grouped, so each attribute
+// moves one end cap. The mesh is left where it is, and fails to
+// convert if its vertices are further than `tolerance` from the box
+// on average or there isn't a vertex at every corner of the box.
+pub fn parameterize(name: &str, mut geometry: Geometry, tolerance: f64) -> Result<Part,Error> {
+    span!("import.parameterize", vertices = geometry.vertices().len());
+    let fit = BoxFit::new(geometry.vertices())
+        .ok_or(Error::EmptyGeometry)
+        .in_part(name)?;
+
+    let half = [fit.size.x / 2.0,fit.size.y / 2.0,fit.size.z / 2.0];
+    let cornered = (0..8).all(|i: usize| {
+        let corner = (0..3).fold(fit.center,|c,a| {
+            let sign = if i & (1 << a) == 0 { -1.0 } else { 1.0 };
+            c + fit.axes[a].vector() * (half[a] * sign)
+        });
+        geometry.vertices().iter().any(|v| v.distance(&corner) <= tolerance)
+    });
+
+    if fit.residual > tolerance || !cornered {
+        return Err(Error::InvalidFit(geometry.vertices().len())).in_part(name);
+    }
+
+    let mut part = Part::new(name);
+    for (axis,(attribute,low,high)) in SIDES.into_iter().enumerate() {
+        let direction = facing(fit.axes[axis].vector());
+        let distances = geometry
+            .vertices()
+            .iter()
+            .map(|v| (*v - fit.center).dot(&direction))
+            .collect::<Vec<f64>>();
+
+        let near = |end: f64| distances
+            .iter()
+            .enumerate()
+            .filter(|(_,d)| (*d - end).abs() <= tolerance)
+            .map(|(i,_)| i)
+            .collect::<Vec<usize>>();
+
+        geometry.add_group(low,near(-half[axis])).in_part(name)?;
+        geometry.add_group(high,near(half[axis])).in_part(name)?;
+
+        part = part.with_attribute(Attribute::new(attribute.into(),vec![
+            AttributeItem::new(Selection::group(high),Alteration::translate(direction))
+        ]));
+    }
+
+    part.with_geometry(geometry).build()
+}
+
+// The axis pointing the way its largest component is positive, so a
+// mesh along +X gets its front at +X whichever way the fit found
+fn facing(axis: Vector) -> Vector {
+    let largest = [axis.x,axis.y,axis.z]
+        .into_iter()
+        .max_by(|a,b| a.abs().total_cmp(&b.abs()))
+        .unwrap_or_default();
+    match largest < 0.0 {
+        true => axis * -1.0,
+        false => axis,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::geometry::{Matrix,Transform,Bounds,Vertex};
+    use crate::models;
+
+    #[test]
+    fn test_parameterize_box() {
+        // a 2x4 from another program, turned and a long way out
+        let mut geometry = models::M2X4.geometry();
+        geometry.transform(&(Matrix::translate(10.0,-4.0,2.0) * Matrix::rotate_z(0.5)));
+
+        let mut part = parameterize("stud",geometry,1e-6).unwrap();
+        assert_eq!(part.attributes().len(),3);
+        assert_eq!(part.base().group("front").map(Vec::len),Some(4));
+        assert_eq!(part.base().group("top").map(Vec::len),Some(4));
+
+        // a longer board, with the back end where it was
+        part.set("Length",0.5).unwrap();
+        let before = BoxFit::new(part.base().vertices()).unwrap();
+        let after = BoxFit::new(part.geometry().vertices()).unwrap();
+        assert_relative_eq!(after.size.x - before.size.x,0.5,epsilon = 1e-9);
+        assert_relative_eq!(after.size.y,before.size.y,epsilon = 1e-9);
+
+        part.set("Height",0.01).unwrap();
+        let after = BoxFit::new(part.geometry().vertices()).unwrap();
+        assert_relative_eq!(after.size.z - before.size.z,0.01,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_parameterize_not_a_box() {
+        // the corners of a wedge are on the box around it, but most of
+        // the corners of the box are missing
+        let wedge = Geometry::make(
+            vec![0.0,0.0,0.0, 2.0,0.0,0.0, 0.0,1.0,0.0, 0.0,0.0,1.0],
+            vec![1,3,2, 1,2,4, 1,4,3, 2,3,4]);
+        let error = parameterize("wedge",wedge,1e-3).unwrap_err();
+        assert!(matches!(error.root(),Error::InvalidFit(4)));
+
+        let error = parameterize("empty",Geometry::default(),1e-3).unwrap_err();
+        assert!(matches!(error.root(),Error::EmptyGeometry));
+
+        let block = Geometry::from_bounds(&Bounds::new(Vertex::new(0.0,0.0,0.0),Vertex::new(1.0,3.0,2.0)));
+        let part = parameterize("block",block,1e-6).unwrap();
+        assert_eq!(part.attributes()[0].items()[0].alteration().dimension(),Vector::new(0.0,1.0,0.0));
+    }
+
+}
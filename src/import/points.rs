@@ -0,0 +1,411 @@
+use std::fs;
+use std::path::Path;
+
+use crate::geometry::{Vertex,Normal,Bounds,Matrix,Transform};
+use crate::geometry::{PlaneFit,LineFit,SphereFit,BoxFit,CylinderFit};
+use crate::errors::Error;
+use crate::constant::COMMENT_TAG;
+
+/// A set of measured points, like a scan of a site, along with
+/// their normals if the file had them
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct PointCloud {
+    points: Vec<Vertex>,
+    normals: Vec<Normal>,
+}
+
+// How the body of a PLY file is stored
+#[derive(Debug,Copy,Clone,PartialEq)]
+enum Format {
+    Ascii,
+    Little,
+    Big,
+}
+
+// The type of a PLY property, by its size in bytes
+#[derive(Debug,Copy,Clone,PartialEq)]
+enum Kind {
+    Int(usize),
+    Uint(usize),
+    Float(usize),
+}
+
+// A property of a PLY element, which may be a list with a count
+// of one kind followed by values of another
+#[derive(Debug,Clone,PartialEq)]
+struct Property {
+    name: String,
+    kind: Kind,
+    count: Option<Kind>,
+}
+
+#[derive(Debug,Clone,PartialEq)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+// Reads values out of the body of a PLY file
+struct Body<'a> {
+    format: Format,
+    bytes: &'a [u8],
+    words: std::str::SplitAsciiWhitespace<'a>,
+}
+
+impl PointCloud {
+
+    pub fn new(points: Vec<Vertex>) -> Self {
+        Self { points, normals: Vec::new() }
+    }
+
+    // Reads a `.ply` file, or a text file of points for any
+    // other extension
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self,Error> {
+        let path = path.as_ref();
+        let ply = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("ply"));
+
+        match ply {
+            true => Self::parse_ply(&fs::read(path)?),
+            false => Self::parse_xyz(&fs::read_to_string(path)?),
+        }
+    }
+
+    // Reads points from text with one point on each line, as X, Y
+    // and Z separated by spaces or commas. Anything after those,
+    // like a color, is ignored, as are blank lines and comments.
+    pub fn parse_xyz(text: &str) -> Result<Self,Error> {
+        span!("import.xyz");
+        let mut points = Vec::new();
+        for (index,line) in text.lines().enumerate() {
+            let invalid = || Error::InvalidLine { line: index + 1, text: line.into() };
+            let data = line.split(COMMENT_TAG).next().unwrap_or_default();
+            let values = data
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|w| !w.is_empty())
+                .take(3)
+                .map(|w| w.parse::<f64>().map_err(|_| invalid()))
+                .collect::<Result<Vec<f64>,Error>>()?;
+
+            match values[..] {
+                [] => continue,
+                [x,y,z] => points.push(Vertex::new(x,y,z)),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(Self::new(points))
+    }
+
+    // Reads the vertices of an ASCII or binary PLY file, with
+    // normals if every vertex has them
+    pub fn parse_ply(data: &[u8]) -> Result<Self,Error> {
+        span!("import.ply", bytes = data.len());
+        let (format,elements,body) = header(data)?;
+        let mut body = Body::new(format,body);
+        let mut cloud = Self::default();
+
+        for element in elements.iter() {
+            let find = |name: &str| element.properties.iter().position(|p| p.name == name);
+            let position = [find("x"),find("y"),find("z")];
+            let normal = [find("nx"),find("ny"),find("nz")];
+
+            for _ in 0..element.count {
+                let mut values = Vec::with_capacity(element.properties.len());
+                for property in element.properties.iter() {
+                    match property.count {
+                        Some(count) => {
+                            let count = body.read(count)? as usize;
+                            for _ in 0..count {
+                                body.read(property.kind)?;
+                            }
+                            values.push(0.0);
+                        },
+                        None => values.push(body.read(property.kind)?),
+                    }
+                }
+
+                if element.name != "vertex" {
+                    continue;
+                }
+                if let [Some(x),Some(y),Some(z)] = position {
+                    cloud.points.push(Vertex::new(values[x],values[y],values[z]));
+                }
+                if let [Some(x),Some(y),Some(z)] = normal {
+                    cloud.normals.push(Normal::new(values[x],values[y],values[z]));
+                }
+            }
+        }
+
+        if cloud.normals.len() != cloud.points.len() {
+            cloud.normals.clear();
+        }
+        Ok(cloud)
+    }
+
+    pub fn points(&self) -> &[Vertex] {
+        &self.points
+    }
+
+    // The normal of each point, or nothing if they aren't known
+    pub fn normals(&self) -> &[Normal] {
+        &self.normals
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn bounds(&self) -> Option<Bounds> {
+        Bounds::from_points(&self.points)
+    }
+
+    // The plane the points are closest to, like a floor or wall
+    pub fn fit_plane(&self) -> Option<PlaneFit> {
+        PlaneFit::new(&self.points)
+    }
+
+    pub fn fit_line(&self) -> Option<LineFit> {
+        LineFit::new(&self.points)
+    }
+
+    pub fn fit_sphere(&self) -> Option<SphereFit> {
+        SphereFit::new(&self.points)
+    }
+
+    // A box around the points, lined up with the way they spread
+    pub fn fit_box(&self) -> Option<BoxFit> {
+        BoxFit::new(&self.points)
+    }
+
+    // The cylinder the points are closest to, like a pipe or post
+    pub fn fit_cylinder(&self) -> Option<CylinderFit> {
+        CylinderFit::new(&self.points)
+    }
+
+}
+
+impl Transform for PointCloud {
+    fn transform(&mut self, matrix: &Matrix) {
+        self.points.transform(matrix);
+        let normal = matrix.normal();
+        for n in self.normals.iter_mut() {
+            n.transform_direction(&normal);
+            *n = n.normalize();
+        }
+    }
+}
+
+// Reads the format and elements from the header of a PLY file,
+// returning them with the data after the header
+fn header(data: &[u8]) -> Result<(Format,Vec<Element>,&[u8]),Error> {
+    const END: &[u8] = b"end_header";
+    let end = data
+        .windows(END.len())
+        .position(|w| w == END)
+        .ok_or(Error::ParseError)?;
+    let start = data[end..]
+        .iter()
+        .position(|b| *b == b'\n')
+        .map(|p| end + p + 1)
+        .unwrap_or(data.len());
+
+    let text = std::str::from_utf8(&data[..end]).map_err(|_| Error::ParseError)?;
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+
+    for (index,line) in text.lines().enumerate() {
+        let invalid = || Error::InvalidLine { line: index + 1, text: line.into() };
+        let words = line.split_whitespace().collect::<Vec<&str>>();
+        match words[..] {
+            ["ply"] if index == 0 => {},
+            _ if index == 0 => return Err(invalid()),
+            [] | ["comment",..] | ["obj_info",..] => {},
+            ["format",name,_] => format = Some(match name {
+                "ascii" => Format::Ascii,
+                "binary_little_endian" => Format::Little,
+                "binary_big_endian" => Format::Big,
+                _ => return Err(invalid()),
+            }),
+            ["element",name,count] => elements.push(Element {
+                name: name.into(),
+                count: count.parse().map_err(|_| invalid())?,
+                properties: Vec::new(),
+            }),
+            ["property","list",count,kind,name] => elements
+                .last_mut()
+                .ok_or_else(invalid)?
+                .properties
+                .push(Property {
+                    name: name.into(),
+                    kind: Kind::parse(kind).ok_or_else(invalid)?,
+                    count: Some(Kind::parse(count).ok_or_else(invalid)?),
+                }),
+            ["property",kind,name] => elements
+                .last_mut()
+                .ok_or_else(invalid)?
+                .properties
+                .push(Property {
+                    name: name.into(),
+                    kind: Kind::parse(kind).ok_or_else(invalid)?,
+                    count: None,
+                }),
+            _ => return Err(invalid()),
+        }
+    }
+
+    Ok((format.ok_or(Error::ParseError)?,elements,&data[start..]))
+}
+
+impl Kind {
+
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Kind::Int(1),
+            "uchar" | "uint8" => Kind::Uint(1),
+            "short" | "int16" => Kind::Int(2),
+            "ushort" | "uint16" => Kind::Uint(2),
+            "int" | "int32" => Kind::Int(4),
+            "uint" | "uint32" => Kind::Uint(4),
+            "float" | "float32" => Kind::Float(4),
+            "double" | "float64" => Kind::Float(8),
+            _ => return None,
+        })
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Kind::Int(n) | Kind::Uint(n) | Kind::Float(n) => *n,
+        }
+    }
+
+}
+
+impl<'a> Body<'a> {
+
+    fn new(format: Format, bytes: &'a [u8]) -> Self {
+        let text = match format {
+            Format::Ascii => std::str::from_utf8(bytes).unwrap_or_default(),
+            _ => "",
+        };
+        Self { format, bytes, words: text.split_ascii_whitespace() }
+    }
+
+    fn read(&mut self, kind: Kind) -> Result<f64,Error> {
+        if self.format == Format::Ascii {
+            return Ok(self.words.next().ok_or(Error::ParseError)?.parse()?);
+        }
+
+        let size = kind.size();
+        if self.bytes.len() < size {
+            return Err(Error::ParseError);
+        }
+        let (head,rest) = self.bytes.split_at(size);
+        self.bytes = rest;
+
+        let mut buffer = [0u8;8];
+        buffer[..size].copy_from_slice(head);
+        if self.format == Format::Big {
+            buffer[..size].reverse();
+        }
+
+        Ok(match kind {
+            Kind::Int(1) => i8::from_le_bytes([buffer[0]]) as f64,
+            Kind::Int(2) => i16::from_le_bytes([buffer[0],buffer[1]]) as f64,
+            Kind::Int(_) => i32::from_le_bytes([buffer[0],buffer[1],buffer[2],buffer[3]]) as f64,
+            Kind::Uint(1) => buffer[0] as f64,
+            Kind::Uint(2) => u16::from_le_bytes([buffer[0],buffer[1]]) as f64,
+            Kind::Uint(_) => u32::from_le_bytes([buffer[0],buffer[1],buffer[2],buffer[3]]) as f64,
+            Kind::Float(4) => f32::from_le_bytes([buffer[0],buffer[1],buffer[2],buffer[3]]) as f64,
+            Kind::Float(_) => f64::from_le_bytes(buffer),
+        })
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_points_xyz() {
+        let cloud = PointCloud::parse_xyz("
+            # x y z r g b
+            0 0 0 255 0 0
+            1.5,2,3
+            -1 -2 -3.25 # last
+        ").unwrap();
+        assert_eq!(cloud.len(),3);
+        assert_eq!(cloud.points()[2],Vertex::new(-1.0,-2.0,-3.25));
+        assert!(cloud.normals().is_empty());
+
+        assert!(matches!(PointCloud::parse_xyz("0 0 0\n1 2"),Err(Error::InvalidLine { line: 2, .. })));
+        assert!(matches!(PointCloud::parse_xyz("1 2 z"),Err(Error::InvalidLine { line: 1, .. })));
+    }
+
+    #[test]
+    fn test_points_ply() {
+        let ascii = "ply\nformat ascii 1.0\ncomment scan\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nproperty float nx\nproperty float ny\nproperty float nz\nproperty uchar red\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0 0 0 1 9\n1 0 0 0 0 1 9\n0 1 0 0 0 1 9\n3 0 1 2\n";
+        let cloud = PointCloud::parse_ply(ascii.as_bytes()).unwrap();
+        assert_eq!(cloud.len(),3);
+        assert_eq!(cloud.points()[1],Vertex::new(1.0,0.0,0.0));
+        assert_eq!(cloud.normals()[2],Normal::new(0.0,0.0,1.0));
+
+        // the same points with a face first, in both byte orders
+        for (name,big) in [("binary_little_endian",false),("binary_big_endian",true)] {
+            let mut data = format!("ply\nformat {} 1.0\nelement face 1\nproperty list uchar int vertex_indices\nelement vertex 2\nproperty double x\nproperty float y\nproperty short z\nend_header\n",name).into_bytes();
+            let mut push = |bytes: &[u8]| match big {
+                true => data.extend(bytes.iter().rev()),
+                false => data.extend(bytes),
+            };
+            push(&[3]);
+            for i in [0i32,1,2] {
+                push(&i.to_le_bytes());
+            }
+            for (x,y,z) in [(1.5f64,2.5f32,-3i16),(4.0,5.0,6)] {
+                push(&x.to_le_bytes());
+                push(&y.to_le_bytes());
+                push(&z.to_le_bytes());
+            }
+
+            let cloud = PointCloud::parse_ply(&data).unwrap();
+            assert_eq!(cloud.points(),&[Vertex::new(1.5,2.5,-3.0),Vertex::new(4.0,5.0,6.0)]);
+            assert!(cloud.normals().is_empty());
+
+            data.truncate(data.len() - 1);
+            assert!(matches!(PointCloud::parse_ply(&data),Err(Error::ParseError)));
+        }
+
+        assert!(matches!(PointCloud::parse_ply(b"ply\nformat ascii 1.0\nproperty float x\nend_header\n"),Err(Error::InvalidLine { line: 3, .. })));
+        assert!(PointCloud::parse_ply(b"solid\n").is_err());
+    }
+
+    #[test]
+    fn test_points_fit() {
+        // a floor measured in millimeters
+        let text = (0..50)
+            .map(|i| format!("{} {} {}",(i % 10) * 100,(i / 10) * 100,if i % 3 == 0 { 2 } else { 0 }))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let mut cloud = PointCloud::parse_xyz(&text).unwrap();
+        cloud.transform(&Matrix::scale(0.001,0.001,0.001));
+
+        let plane = cloud.fit_plane().unwrap();
+        assert_relative_eq!(plane.plane.normal.z.abs(),1.0,epsilon = 1e-3);
+        assert!(plane.residual < 0.001);
+
+        let fit = cloud.fit_box().unwrap();
+        assert_relative_eq!(fit.size.x,0.9,epsilon = 1e-3);
+        assert_relative_eq!(fit.size.y,0.4,epsilon = 1e-3);
+        assert!(cloud.fit_cylinder().is_some());
+    }
+
+}
@@ -1,11 +1,21 @@
 
 #[cfg(test)]
 #[macro_use] extern crate approx;
-#[macro_use] extern crate lazy_static;
+
+#[macro_use] pub mod utilities;
 
 pub mod errors;
 pub mod part;
+pub mod assembly;
 pub mod geometry;
 pub mod constant;
-pub mod utilities;
-pub mod models;
\ No newline at end of file
+pub mod models;
+pub mod render;
+pub mod export;
+pub mod materials;
+pub mod nesting;
+pub mod import;
+pub mod analysis;
+
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing;
\ No newline at end of file
@@ -0,0 +1,375 @@
+use std::fs;
+use std::path::Path;
+use std::convert::TryFrom;
+use std::collections::BTreeMap;
+
+use crate::materials::{Material,Kind,Section,Cost,nominal};
+use crate::part::{Part,Units};
+use crate::errors::Error;
+
+// The columns of a material CSV file, in the order they're written
+const COLUMNS: [&str;12] = [
+    "name","kind","density","modulus","units",
+    "nominal_thickness","nominal_width",
+    "actual_thickness","actual_width",
+    "length","cost","cost_unit",
+];
+
+// Softwood lumber by nominal size
+const LUMBER: [&str;9] = ["1x4","1x6","2x2","2x4","2x6","2x8","2x10","2x12","4x4"];
+
+// The modulus of softwood framing lumber along the grain, in pascals
+const LUMBER_MODULUS: f64 = 9.6e9;
+
+// Sheet goods as (name, density, modulus, nominal and actual thickness in inches)
+const SHEETS: [(&str,f64,f64,f64,f64);5] = [
+    ("plywood 1/2",600.0,8.0e9,0.5,0.46875),
+    ("plywood 3/4",600.0,8.0e9,0.75,0.71875),
+    ("osb 7/16",650.0,5.0e9,0.4375,0.4375),
+    ("mdf 3/4",750.0,3.5e9,0.75,0.75),
+    ("drywall 1/2",700.0,2.0e9,0.5,0.5),
+];
+
+// Metals as (name, density, modulus)
+const METALS: [(&str,f64,f64);5] = [
+    ("steel",7850.0,200.0e9),
+    ("stainless steel",8000.0,193.0e9),
+    ("aluminum",2700.0,69.0e9),
+    ("brass",8500.0,100.0e9),
+    ("copper",8960.0,117.0e9),
+];
+
+/// A set of stock materials looked up by name
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Database {
+    materials: BTreeMap<String,Material>,
+}
+
+impl Database {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Common lumber, sheet goods and metals, without prices. Lumber
+    // is named like `2x4` and comes in 8 foot lengths, sheets are 4
+    // by 8 feet.
+    pub fn builtin() -> Self {
+        let mut database = Self::new();
+        let inch = |v: f64| v / Units::Inches.per_meter();
+        let foot = |v: f64| v / Units::Feet.per_meter();
+
+        for name in LUMBER.into_iter() {
+            // the sizes are known to be valid
+            let (thickness,width) = nominal::parse(name).unwrap_or_default();
+            let actual = nominal::section(name).unwrap_or_default();
+            database.add(Material::new(name,Kind::Lumber,450.0)
+                .with_modulus(LUMBER_MODULUS)
+                .with_nominal(Section::new(inch(thickness),inch(width)))
+                .with_actual(actual)
+                .with_length(foot(8.0)));
+        }
+
+        for (name,density,modulus,nominal,actual) in SHEETS.into_iter() {
+            database.add(Material::new(name,Kind::Sheet,density)
+                .with_modulus(modulus)
+                .with_nominal(Section::new(inch(nominal),foot(4.0)))
+                .with_actual(Section::new(inch(actual),foot(4.0)))
+                .with_length(foot(8.0)));
+        }
+
+        for (name,density,modulus) in METALS.into_iter() {
+            database.add(Material::new(name,Kind::Metal,density).with_modulus(modulus));
+        }
+
+        database
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.add(material);
+        self
+    }
+
+    // Adds a material, returning any it replaced with the same name
+    pub fn add(&mut self, material: Material) -> Option<Material> {
+        self.materials.insert(material.name().into(),material)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Material> {
+        self.materials.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Material> {
+        self.materials.get(name)
+    }
+
+    pub fn materials(&self) -> impl Iterator<Item = &Material> {
+        self.materials.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+
+    // Adds every material from another database, replacing any
+    // with the same name, so user materials override built-in ones
+    pub fn extend(&mut self, other: Database) {
+        self.materials.extend(other.materials);
+    }
+
+    // The material named in a part's metadata
+    pub fn material(&self, part: &Part) -> Option<&Material> {
+        part.metadata()
+            .material()
+            .and_then(|n| self.get(n))
+    }
+
+    // The mass in kilograms of a part modelled in meters, or None if
+    // its material isn't known
+    pub fn mass(&self, part: &Part) -> Option<f64> {
+        self.material(part).map(|m| m.mass(part.geometry().volume().abs()))
+    }
+
+    // The price of the stock needed for a part modelled in meters
+    pub fn price(&self, part: &Part) -> Option<f64> {
+        self.material(part)?.price(part.geometry().volume().abs())
+    }
+
+    // Reads a database from a `.csv` or `.toml` file, going by
+    // the extension
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self,Error> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::parse_toml(&text),
+            _ => Self::parse_csv(&text),
+        }
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(),Error> {
+        fs::write(path,String::from(self))?;
+        Ok(())
+    }
+
+    // Reads a CSV file with a header row naming its columns, which
+    // can be in any order. Sizes are in the `units` column's units,
+    // or meters, the modulus is in pascals, and empty cells are left
+    // unset. Values can't contain commas.
+    pub fn parse_csv(text: &str) -> Result<Self,Error> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_,l)| !l.trim().is_empty());
+
+        let header = lines
+            .next()
+            .map(|(_,l)| l.split(',').map(|c| c.trim().to_string()).collect::<Vec<String>>())
+            .unwrap_or_default();
+
+        let mut database = Self::new();
+        for (index,line) in lines {
+            let cells = line.split(',').map(str::trim).collect::<Vec<&str>>();
+            let invalid = || Error::InvalidLine { line: index + 1, text: line.into() };
+
+            let row = header
+                .iter()
+                .zip(cells.iter())
+                .filter(|(_,v)| !v.is_empty())
+                .map(|(k,v)| (k.as_str(),v.to_string()))
+                .collect::<BTreeMap<&str,String>>();
+
+            database.add(material(&row).map_err(|_| invalid())?);
+        }
+        Ok(database)
+    }
+
+    // Reads a TOML file with a `[[material]]` table for each material,
+    // using the same keys as the CSV columns
+    #[cfg(feature = "toml")]
+    pub fn parse_toml(text: &str) -> Result<Self,Error> {
+        let table = text
+            .parse::<toml::Table>()
+            .map_err(|e| Error::InvalidMaterial(e.message().into()))?;
+
+        let entries = match table.get("material") {
+            Some(toml::Value::Array(v)) => v.as_slice(),
+            Some(_) => return Err(Error::InvalidMaterial("'material' should be an array of tables".into())),
+            None => &[],
+        };
+
+        let mut database = Self::new();
+        for (index,entry) in entries.iter().enumerate() {
+            let table = entry
+                .as_table()
+                .ok_or_else(|| Error::InvalidMaterial(format!("material {} isn't a table",index)))?;
+
+            let row = table
+                .iter()
+                .filter_map(|(k,v)| match v {
+                    toml::Value::String(s) => Some((k.as_str(),s.clone())),
+                    toml::Value::Float(f) => Some((k.as_str(),f.to_string())),
+                    toml::Value::Integer(i) => Some((k.as_str(),i.to_string())),
+                    _ => None,
+                })
+                .collect::<BTreeMap<&str,String>>();
+
+            database.add(material(&row).map_err(|_| Error::InvalidMaterial(format!("material {} is invalid",index)))?);
+        }
+        Ok(database)
+    }
+
+}
+
+// Builds a material from named values, which only needs a name
+fn material(row: &BTreeMap<&str,String>) -> Result<Material,Error> {
+    let number = |key: &str| row.get(key).map(|v| v.parse::<f64>()).transpose();
+    let text = |key: &str| row.get(key).cloned();
+
+    let name = text("name").ok_or(Error::ParseError)?;
+    let kind = Kind::try_from(text("kind").unwrap_or_default().as_str())?;
+    let units = match text("units") {
+        Some(u) => Units::try_from(u.as_str())?,
+        None => Units::Meters,
+    };
+    let scale = units.per_meter();
+
+    let mut material = Material::new(name,kind,number("density")?.unwrap_or(0.0));
+
+    if let Some(modulus) = number("modulus")? {
+        material = material.with_modulus(modulus);
+    }
+    if let (Some(t),Some(w)) = (number("nominal_thickness")?,number("nominal_width")?) {
+        material = material.with_nominal(Section::with_units(t,w,units));
+    }
+    if let (Some(t),Some(w)) = (number("actual_thickness")?,number("actual_width")?) {
+        material = material.with_actual(Section::with_units(t,w,units));
+    }
+    if let Some(length) = number("length")? {
+        material = material.with_length(length / scale);
+    }
+    if let Some(cost) = number("cost")? {
+        material = material.with_cost(Cost::new(cost,&text("cost_unit").unwrap_or("piece".into()))?);
+    }
+    Ok(material.build())
+}
+
+// Databases are written as CSV in meters, with only the
+// nominal and actual sections that were set
+impl From<&Database> for String {
+    fn from(database: &Database) -> Self {
+        let mut result = COLUMNS.join(",");
+        let value = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+
+        for m in database.materials() {
+            let cells = [
+                m.name().to_string(),
+                m.kind().name().to_string(),
+                m.density().to_string(),
+                value(m.modulus()),
+                Units::Meters.symbol().to_string(),
+                value(m.nominal().map(|s| s.thickness)),
+                value(m.nominal().map(|s| s.width)),
+                value(m.actual().map(|s| s.thickness)),
+                value(m.actual().map(|s| s.width)),
+                value(m.length()),
+                value(m.cost().map(|c| c.amount())),
+                m.cost().map(|c| c.unit().to_string()).unwrap_or_default(),
+            ];
+            result.push('\n');
+            result.push_str(&cells.join(","));
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::models;
+    use crate::part::Metadata;
+
+    #[test]
+    fn test_database_builtin() {
+        let database = Database::builtin();
+        let stud = database.get("2x4").unwrap();
+        assert_eq!(stud.kind(),Kind::Lumber);
+        assert_relative_eq!(stud.actual().unwrap().width,0.0889);
+        assert_relative_eq!(stud.nominal().unwrap().width,0.1016);
+        assert!(database.get("plywood 3/4").is_some());
+        assert!(database.get("steel").unwrap().actual().is_none());
+        assert_eq!(database.get("steel").unwrap().modulus(),Some(200.0e9));
+    }
+
+    #[test]
+    fn test_database_csv() {
+        let text = "
+            name,kind,units,density,modulus,actual_thickness,actual_width,length,cost,cost_unit
+            2x4,lumber,in,500,1.1e10,1.5,3.5,96,4.5,piece
+            steel,metal,,7850,,,,,1.2,kg
+        ";
+
+        let mut database = Database::builtin();
+        database.extend(Database::parse_csv(text).unwrap());
+
+        let stud = database.get("2x4").unwrap();
+        assert_eq!(stud.density(),500.0);
+        assert_eq!(stud.modulus(),Some(1.1e10));
+        assert_eq!(stud.cost(),Some(Cost::Piece(4.5)));
+        assert_relative_eq!(stud.length().unwrap(),2.4384);
+        assert_eq!(database.get("steel").unwrap().cost(),Some(Cost::Mass(1.2)));
+
+        let again = Database::parse_csv(&String::from(&database)).unwrap();
+        assert_eq!(again,database);
+
+        let invalid = Database::parse_csv("name,density\nstud,heavy");
+        assert!(matches!(invalid,Err(Error::InvalidLine { line: 2, .. })));
+    }
+
+    #[test]
+    fn test_database_mass() {
+        let database = Database::builtin();
+        let part = Part::new("block")
+            .with_geometry(models::M2X4.geometry())
+            .with_metadata(Metadata::new().with_material("steel"))
+            .build()
+            .unwrap();
+
+        let volume = part.geometry().volume().abs();
+        assert_relative_eq!(database.mass(&part).unwrap(),volume * 7850.0);
+        assert_eq!(database.price(&part),None);
+        assert_eq!(Database::new().mass(&part),None);
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_database_toml() {
+        let text = r#"
+            [[material]]
+            name = "2x6"
+            kind = "lumber"
+            units = "in"
+            density = 450
+            nominal_thickness = 2
+            nominal_width = 6
+            actual_thickness = 1.5
+            actual_width = 5.5
+            cost = 3.25
+            cost_unit = "m"
+        "#;
+
+        let database = Database::parse_toml(text).unwrap();
+        let joist = database.get("2x6").unwrap();
+        assert_relative_eq!(joist.actual().unwrap().thickness,0.0381);
+        assert_eq!(joist.cost(),Some(Cost::Length(3.25)));
+
+        assert!(Database::parse_toml("[[material]]\nkind = \"lumber\"").is_err());
+        assert!(Database::parse_toml("material = 1").is_err());
+    }
+
+}
@@ -0,0 +1,289 @@
+use std::convert::TryFrom;
+
+use crate::part::Units;
+use crate::errors::Error;
+
+/// The broad kind of stock a material comes as
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Kind {
+    /// sawn sections sold by the piece, like a 2x4
+    Lumber,
+    /// flat panels, like plywood
+    Sheet,
+    /// metals, sold by weight or as bar and plate
+    Metal,
+    #[default]
+    Other,
+}
+
+/// The cross-section of a piece of stock, in meters. Sheet goods
+/// use the thickness of the panel and the width of the sheet.
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct Section {
+    pub thickness: f64,
+    pub width: f64,
+}
+
+/// What the price of a material is quoted for. Amounts are per
+/// meter, square meter or kilogram regardless of the units that
+/// the dimensions were given in.
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub enum Cost {
+    /// each stock piece, which needs a stock length
+    Piece(f64),
+    /// each meter of length
+    Length(f64),
+    /// each square meter of face
+    Area(f64),
+    /// each kilogram
+    Mass(f64),
+}
+
+/// A stock material with its density, stiffness, sizes and cost
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Material {
+    name: String,
+    kind: Kind,
+    density: f64,
+    modulus: Option<f64>,
+    nominal: Option<Section>,
+    actual: Option<Section>,
+    length: Option<f64>,
+    cost: Option<Cost>,
+}
+
+impl Kind {
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::Lumber => "lumber",
+            Kind::Sheet => "sheet",
+            Kind::Metal => "metal",
+            Kind::Other => "other",
+        }
+    }
+
+}
+
+impl TryFrom<&str> for Kind {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.trim() {
+            "lumber" => Ok(Kind::Lumber),
+            "sheet" => Ok(Kind::Sheet),
+            "metal" => Ok(Kind::Metal),
+            "other" | "" => Ok(Kind::Other),
+            _ => Err(Error::ParseError),
+        }
+    }
+}
+
+impl Section {
+
+    pub const fn new(thickness: f64, width: f64) -> Self {
+        Self { thickness, width }
+    }
+
+    // A section given in other units, converted to meters
+    pub fn with_units(thickness: f64, width: f64, units: Units) -> Self {
+        let scale = units.per_meter();
+        Self::new(thickness / scale,width / scale)
+    }
+
+    pub fn area(&self) -> f64 {
+        self.thickness * self.width
+    }
+
+    // The second moment of area for bending across the width,
+    // like a joist standing on its edge
+    pub fn second_moment(&self) -> f64 {
+        self.thickness * self.width.powi(3) / 12.0
+    }
+
+}
+
+impl Cost {
+
+    // Reads an amount and what it's for: `piece`, `m`, `m2` or `kg`
+    pub fn new(amount: f64, unit: &str) -> Result<Self,Error> {
+        match unit.trim() {
+            "piece" => Ok(Cost::Piece(amount)),
+            "m" => Ok(Cost::Length(amount)),
+            "m2" => Ok(Cost::Area(amount)),
+            "kg" => Ok(Cost::Mass(amount)),
+            _ => Err(Error::ParseError),
+        }
+    }
+
+    pub fn amount(&self) -> f64 {
+        match self {
+            Cost::Piece(v) | Cost::Length(v) | Cost::Area(v) | Cost::Mass(v) => *v,
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Cost::Piece(_) => "piece",
+            Cost::Length(_) => "m",
+            Cost::Area(_) => "m2",
+            Cost::Mass(_) => "kg",
+        }
+    }
+
+}
+
+impl Material {
+
+    // A material with a density in kilograms per cubic meter
+    pub fn new<T: Into<String>>(name: T, kind: Kind, density: f64) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            density,
+            ..Default::default()
+        }
+    }
+
+    // The elastic (Young's) modulus in pascals, along the
+    // grain for wood
+    pub fn with_modulus(mut self, modulus: f64) -> Self {
+        self.modulus = Some(modulus);
+        self
+    }
+
+    // The size the stock is sold as, like 2x4 inches
+    pub fn with_nominal(mut self, section: Section) -> Self {
+        self.nominal = Some(section);
+        self
+    }
+
+    // The size the stock really is, like 1.5x3.5 inches
+    pub fn with_actual(mut self, section: Section) -> Self {
+        self.actual = Some(section);
+        self
+    }
+
+    // The length of one stock piece, in meters
+    pub fn with_length(mut self, length: f64) -> Self {
+        self.length = Some(length);
+        self
+    }
+
+    pub fn with_cost(mut self, cost: Cost) -> Self {
+        self.cost = Some(cost);
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    pub fn density(&self) -> f64 {
+        self.density
+    }
+
+    pub fn modulus(&self) -> Option<f64> {
+        self.modulus
+    }
+
+    pub fn nominal(&self) -> Option<Section> {
+        self.nominal
+    }
+
+    pub fn actual(&self) -> Option<Section> {
+        self.actual
+    }
+
+    pub fn length(&self) -> Option<f64> {
+        self.length
+    }
+
+    pub fn cost(&self) -> Option<Cost> {
+        self.cost
+    }
+
+    // The mass in kilograms of a volume in cubic meters
+    pub fn mass(&self, volume: f64) -> f64 {
+        self.density * volume
+    }
+
+    // The price of a volume in cubic meters of this material. Whole
+    // stock pieces are bought, and prices by length or area need a
+    // section, preferring the actual one. None if the price can't be
+    // worked out.
+    pub fn price(&self, volume: f64) -> Option<f64> {
+        let section = self.actual.or(self.nominal);
+        match self.cost? {
+            Cost::Mass(v) => Some(self.mass(volume) * v),
+            Cost::Length(v) => Some(volume / section?.area() * v),
+            Cost::Area(v) => Some(volume / section?.thickness * v),
+            Cost::Piece(v) => {
+                let piece = section?.area() * self.length?;
+                Some((volume / piece).ceil().max(1.0) * v)
+            },
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn stud() -> Material {
+        Material::new("2x4",Kind::Lumber,400.0)
+            .with_nominal(Section::with_units(2.0,4.0,Units::Inches))
+            .with_actual(Section::with_units(1.5,3.5,Units::Inches))
+            .with_length(2.4384)
+            .build()
+    }
+
+    #[test]
+    fn test_material_sections() {
+        let stud = stud();
+        assert_relative_eq!(stud.actual().unwrap().thickness,0.0381);
+        assert_relative_eq!(stud.nominal().unwrap().width,0.1016);
+        assert_relative_eq!(Section::new(2.0,3.0).second_moment(),4.5);
+
+        let steel = Material::new("steel",Kind::Metal,7850.0).with_cost(Cost::Length(1.0));
+        assert_eq!(steel.nominal(),None);
+        assert_eq!(steel.price(1.0),None);
+        assert_relative_eq!(steel.with_nominal(Section::new(0.01,0.1)).price(0.01).unwrap(),10.0);
+    }
+
+    #[test]
+    fn test_material_price() {
+        let stud = stud();
+        let volume = stud.actual().unwrap().area() * 3.0;
+        assert_relative_eq!(stud.mass(volume),400.0 * volume);
+        assert_eq!(stud.price(volume),None);
+
+        // 3 meters needs two 8 foot pieces
+        assert_relative_eq!(stud.clone().with_cost(Cost::Piece(4.0)).price(volume).unwrap(),8.0);
+        assert_relative_eq!(stud.clone().with_cost(Cost::Length(2.0)).price(volume).unwrap(),6.0);
+        assert_relative_eq!(stud.clone().with_cost(Cost::Mass(0.5)).price(volume).unwrap(),200.0 * volume);
+
+        let bare = Material::new("bare",Kind::Other,100.0).with_cost(Cost::Area(1.0));
+        assert_eq!(bare.price(1.0),None);
+    }
+
+    #[test]
+    fn test_cost_units() {
+        assert_eq!(Cost::new(2.0,"m2").unwrap(),Cost::Area(2.0));
+        assert_eq!(Cost::new(2.0,"kg").unwrap().unit(),"kg");
+        assert!(Cost::new(2.0,"ton").is_err());
+        assert_eq!(Kind::try_from("sheet").unwrap(),Kind::Sheet);
+        assert!(Kind::try_from("cheese").is_err());
+    }
+
+}
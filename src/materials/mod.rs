@@ -0,0 +1,8 @@
+mod material;
+mod database;
+mod pricing;
+pub mod nominal;
+
+pub use material::{Material,Kind,Section,Cost};
+pub use database::Database;
+pub use pricing::{PricingProvider,PriceTable,Item};
@@ -0,0 +1,76 @@
+use crate::materials::Section;
+use crate::part::Units;
+use crate::errors::Error;
+
+// Dimensional lumber is sold by a nominal size in inches that is
+// bigger than the dressed size, by an amount that depends on the
+// size. These are the standard softwood reductions.
+fn reduction(nominal: f64) -> f64 {
+    match nominal {
+        n if n <= 1.0 => 0.25,
+        n if n < 8.0 => 0.5,
+        _ => 0.75,
+    }
+}
+
+// The actual size in inches of a nominal size, like 4 to 3.5
+pub fn actual(nominal: f64) -> f64 {
+    (nominal - reduction(nominal)).max(0.0)
+}
+
+// The nominal size of an actual size in inches, like 3.5 to 4
+pub fn nominal(actual: f64) -> f64 {
+    match actual {
+        a if a <= 0.75 => a + 0.25,
+        a if a < 7.25 => a + 0.5,
+        a => a + 0.75,
+    }
+}
+
+// The actual size in meters of a nominal size in inches
+pub fn actual_meters(nominal: f64) -> f64 {
+    actual(nominal) / Units::Inches.per_meter()
+}
+
+// Reads a nominal size like `2x4` into its two numbers
+pub fn parse(text: &str) -> Result<(f64,f64),Error> {
+    let (a,b) = text
+        .trim()
+        .split_once(['x','X'])
+        .ok_or(Error::ParseError)?;
+    Ok((a.trim().parse()?,b.trim().parse()?))
+}
+
+// The actual section in meters of a nominal size like `2x4`
+pub fn section(text: &str) -> Result<Section,Error> {
+    let (thickness,width) = parse(text)?;
+    Ok(Section::with_units(actual(thickness),actual(width),Units::Inches))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_nominal_sizes() {
+        for (n,a) in [(1.0,0.75),(2.0,1.5),(4.0,3.5),(6.0,5.5),(8.0,7.25),(12.0,11.25)] {
+            assert_eq!(actual(n),a);
+            assert_eq!(nominal(a),n);
+        }
+        assert_relative_eq!(actual_meters(4.0),0.0889);
+    }
+
+    #[test]
+    fn test_nominal_parse() {
+        assert_eq!(parse("2x4").unwrap(),(2.0,4.0));
+        assert_eq!(parse(" 4 X 4 ").unwrap(),(4.0,4.0));
+        assert!(parse("2by4").is_err());
+        assert!(parse("2xfour").is_err());
+
+        let section = section("2x6").unwrap();
+        assert_relative_eq!(section.thickness,0.0381);
+        assert_relative_eq!(section.width,0.1397);
+    }
+
+}
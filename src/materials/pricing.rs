@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+
+use crate::materials::Database;
+use crate::part::Part;
+
+/// A part as a supplier prices it: by the code it's sold under, the
+/// material it's cut from or how much of it there is
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct Item<'a> {
+    /// the code in the part's metadata
+    pub sku: Option<&'a str>,
+    /// the name of the material in the part's metadata
+    pub material: Option<&'a str>,
+    /// the longest side of the part, in meters
+    pub length: f64,
+    /// the volume of the part, in cubic meters
+    pub volume: f64,
+}
+
+/// Looks up the price of parts, so that a BOM can be priced from a
+/// supplier's catalog or a live feed without the crate knowing where
+/// prices come from
+pub trait PricingProvider {
+
+    // The price of one of the item, or None if it isn't known
+    fn price(&self, item: &Item) -> Option<f64>;
+
+}
+
+/// Fixed prices by SKU, or by the meter for a material. A price for
+/// the SKU is used before one for the material.
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct PriceTable {
+    skus: BTreeMap<String,f64>,
+    materials: BTreeMap<String,f64>,
+}
+
+impl<'a> Item<'a> {
+
+    // The item for a part modelled in meters
+    pub fn new(part: &'a Part) -> Self {
+        let geometry = part.geometry();
+        let length = geometry
+            .bounds()
+            .map(|b| b.size())
+            .map(|s| s.x.max(s.y).max(s.z))
+            .unwrap_or_default();
+
+        Self {
+            sku: part.metadata().sku(),
+            material: part.metadata().material(),
+            length,
+            volume: geometry.volume().abs(),
+        }
+    }
+
+}
+
+impl PriceTable {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The price of each part sold under a code
+    pub fn with_sku<T: Into<String>>(mut self, sku: T, price: f64) -> Self {
+        self.skus.insert(sku.into(),price);
+        self
+    }
+
+    // The price of each meter of a material
+    pub fn with_material<T: Into<String>>(mut self, material: T, price: f64) -> Self {
+        self.materials.insert(material.into(),price);
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+}
+
+impl PricingProvider for PriceTable {
+
+    fn price(&self, item: &Item) -> Option<f64> {
+        let sku = item.sku.and_then(|s| self.skus.get(s)).copied();
+        let material = || item.material.and_then(|m| self.materials.get(m)).map(|p| p * item.length);
+        sku.or_else(material)
+    }
+
+}
+
+// A database prices the stock needed for each part by the cost of
+// its material
+impl PricingProvider for Database {
+
+    fn price(&self, item: &Item) -> Option<f64> {
+        self.get(item.material?)?.price(item.volume)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::models::Lumber;
+    use crate::materials::{Material,Kind,Cost};
+
+    #[test]
+    fn test_price_table() {
+        let mut stud = Lumber::TwoByFour.part();
+        stud.metadata_mut().set_material(Some("2x4".into()));
+
+        let table = PriceTable::new()
+            .with_sku("SPF-248",5.25)
+            .with_material("2x4",2.0)
+            .build();
+
+        let item = Item::new(&stud);
+        assert_relative_eq!(item.length,2.4384,epsilon = 1e-9);
+        assert_relative_eq!(table.price(&item).unwrap(),4.8768,epsilon = 1e-9);
+
+        stud.metadata_mut().set_sku(Some("SPF-248".into()));
+        assert_eq!(table.price(&Item::new(&stud)),Some(5.25));
+        stud.metadata_mut().set_material(None);
+        stud.metadata_mut().set_sku(Some("SPF-296".into()));
+        assert_eq!(table.price(&Item::new(&stud)),None);
+
+        let database = Database::new()
+            .with_material(Material::new("2x4",Kind::Lumber,500.0).with_cost(Cost::Mass(2.0)));
+        let item = Item { material: Some("2x4"), volume: 0.5, ..Default::default() };
+        assert_eq!(PricingProvider::price(&database,&item),Some(500.0));
+    }
+
+}
@@ -0,0 +1,139 @@
+use std::f64::consts::TAU;
+
+use crate::geometry::{Geometry,Vertex,Vector,Matrix};
+use crate::part::{Part,Attribute,Connection};
+use crate::models::stretch;
+
+/// Standard fasteners, standing with the top of the shank on the
+/// origin, the head above it and the shank running down along -Z
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub enum Fastener {
+    /// a 16d common nail
+    #[default]
+    Nail,
+    /// a #8 wood screw, 2-1/2" long
+    Screw,
+    /// a 3/8" hex bolt, 4" long
+    Bolt,
+}
+
+impl Fastener {
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Nail => "nail",
+            Self::Screw => "screw",
+            Self::Bolt => "bolt",
+        }
+    }
+
+    // The length of the shank in meters
+    pub fn length(&self) -> f64 {
+        match self {
+            Self::Nail => 0.0889,
+            Self::Screw => 0.0635,
+            Self::Bolt => 0.1016,
+        }
+    }
+
+    // The diameter of the shank in meters
+    pub fn diameter(&self) -> f64 {
+        match self {
+            Self::Nail => 0.0041,
+            Self::Screw => 0.0042,
+            Self::Bolt => 0.009525,
+        }
+    }
+
+    // The diameter and height of the head in meters
+    pub fn head(&self) -> (f64,f64) {
+        match self {
+            Self::Nail => (0.0087,0.0015),
+            Self::Screw => (0.0084,0.0030),
+            Self::Bolt => (0.0165,0.0059),
+        }
+    }
+
+    // The shank and head as two closed prisms, round except for the
+    // hex head of the bolt, with the end of the shank named "tip"
+    pub fn geometry(&self) -> Geometry {
+        let (head,height) = self.head();
+        let sides = match self {
+            Self::Bolt => 6,
+            _ => 12,
+        };
+
+        let mut geometry = prism(self.diameter() / 2.0,-self.length(),0.0,12);
+        geometry
+            .add_group("tip",(0..12).collect::<Vec<usize>>())
+            .expect("the shank has twelve sides");
+        geometry.append(&prism(head / 2.0,0.0,height,sides),&Matrix::identity());
+        geometry
+    }
+
+    // The fastener as a part, with a "Length" attribute that moves
+    // the tip and a connection on the top of the head and the tip
+    pub fn part(&self) -> Part {
+        let (_,height) = self.head();
+        let radius = self.diameter() / 2.0;
+        Part::new(self.name())
+            .with_geometry(self.geometry())
+            .with_attribute(Attribute::new("Length".into(),vec![
+                stretch("tip",Vector::new(0.0,0.0,-1.0))
+            ]))
+            .with_connection(Connection::new(Vertex::new(0.0,0.0,height),radius))
+            .with_connection(Connection::new(Vertex::new(0.0,0.0,-self.length()),radius))
+    }
+
+}
+
+// A closed prism around the Z axis between two heights, with the
+// bottom ring of vertices first and then the top one
+fn prism(radius: f64, bottom: f64, top: f64, sides: usize) -> Geometry {
+    let mut values = Vec::with_capacity(sides * 6);
+    for z in [bottom,top] {
+        for i in 0..sides {
+            let angle = i as f64 * TAU / sides as f64;
+            values.extend([angle.cos() * radius,angle.sin() * radius,z]);
+        }
+    }
+
+    let mut indices = Vec::with_capacity(sides * 12);
+    for i in 0..sides {
+        let (a,b) = (i + 1,(i + 1) % sides + 1);
+        let (c,d) = (a + sides,b + sides);
+        indices.extend([a,b,d,a,d,c]);
+    }
+    for i in 1..sides - 1 {
+        indices.extend([1,i + 2,i + 1]);
+        indices.extend([sides + 1,sides + i + 1,sides + i + 2]);
+    }
+    Geometry::make(values,indices)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_fastener_parts() {
+        let nail = Fastener::Nail.geometry();
+        assert_eq!(nail.vertices().len(),48);
+        assert_eq!(nail.edge_faces().values().filter(|f| f.len() != 2).count(),0);
+
+        // a twelve sided shank is a little smaller than a round one
+        let shank = prism(0.5,0.0,1.0,12);
+        assert_relative_eq!(shank.volume(),PI * 0.25,max_relative = 0.05);
+        assert!(shank.volume() > 0.0);
+
+        let mut bolt = Fastener::Bolt.part().build().unwrap();
+        assert_eq!(bolt.connections().len(),2);
+        bolt.set("Length",0.0254).unwrap();
+        let bounds = bolt.geometry().bounds().unwrap();
+        assert_relative_eq!(bounds.min.z,-0.127,epsilon = 1e-9);
+        assert_relative_eq!(bounds.max.z,0.0059,epsilon = 1e-9);
+    }
+
+}
@@ -0,0 +1,105 @@
+use std::f64::consts::PI;
+
+use crate::geometry::{Geometry,Vertex,Vector,Direction,Matrix};
+use crate::part::{Part,Attribute,Connection,Joint};
+use crate::models::{block,stretch};
+
+/// Hardware for joining parts, with connections that move the
+/// way the joint does
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub enum Hardware {
+    /// a 2" steel angle bracket, holding two parts square
+    #[default]
+    Bracket,
+    /// a 3" butt hinge, turning around a pin along Z
+    Hinge,
+    /// a 16" drawer slide, running along X
+    Slide,
+}
+
+impl Hardware {
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Bracket => "bracket",
+            Self::Hinge => "hinge",
+            Self::Slide => "slide",
+        }
+    }
+
+    // The plates of the hardware as boxes, with their sides
+    // named like the lumber
+    pub fn geometry(&self) -> Geometry {
+        let mut geometry = Geometry::default();
+        let plates = match self {
+            Self::Bracket => vec![
+                (Vertex::new(0.0,-0.02,0.0),Vertex::new(0.05,0.02,0.003)),
+                (Vertex::new(0.0,-0.02,0.0),Vertex::new(0.003,0.02,0.05)),
+            ],
+            Self::Hinge => vec![
+                (Vertex::new(-0.0254,0.0,0.0),Vertex::new(0.0,0.002,0.0762)),
+                (Vertex::new(0.0,0.0,0.0),Vertex::new(0.0254,0.002,0.0762)),
+            ],
+            Self::Slide => vec![
+                (Vertex::new(0.0,0.0,0.0),Vertex::new(0.4064,0.0127,0.0455)),
+            ],
+        };
+        for (min,max) in plates {
+            geometry.append(&block(min,max),&Matrix::identity());
+        }
+        geometry
+    }
+
+    // The hardware as a part. The bracket can be made wider, the
+    // hinge taller and the slide longer, and in each the second
+    // connection is the one that moves.
+    pub fn part(&self) -> Part {
+        let part = Part::new(self.name()).with_geometry(self.geometry());
+        match self {
+            Self::Bracket => part
+                .with_attribute(Attribute::new("Width".into(),vec![
+                    stretch("right",Vector::new(0.0,1.0,0.0))
+                ]))
+                .with_connection(Connection::new(Vertex::new(0.0265,0.0,0.0),0.0025))
+                .with_connection(Connection::new(Vertex::new(0.0,0.0,0.0265),0.0025)),
+            Self::Hinge => part
+                .with_attribute(Attribute::new("Height".into(),vec![
+                    stretch("top",Vector::new(0.0,0.0,1.0))
+                ]))
+                .with_connection(Connection::new(Vertex::new(-0.0127,0.0,0.0381),0.0025))
+                .with_connection(Connection::new(Vertex::new(0.0,0.0,0.0381),0.0025)
+                    .with_joint(Joint::revolute(Direction::new(0.0,0.0,1.0),0.0,PI))),
+            Self::Slide => part
+                .with_attribute(Attribute::new("Length".into(),vec![
+                    stretch("front",Vector::new(1.0,0.0,0.0))
+                ]))
+                .with_connection(Connection::new(Vertex::new(0.0,0.00635,0.02275),0.003))
+                .with_connection(Connection::new(Vertex::new(0.4064,0.00635,0.02275),0.003)
+                    .with_joint(Joint::prismatic(Direction::new(1.0,0.0,0.0),0.0,0.35))),
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_hardware_parts() {
+        for hardware in [Hardware::Bracket,Hardware::Hinge,Hardware::Slide] {
+            let part = hardware.part().build().unwrap();
+            assert_eq!(part.connections().len(),2);
+            assert_eq!(part.connections()[0].joint(),&Joint::Fixed);
+        }
+
+        // both leaves of the hinge get taller
+        let mut hinge = Hardware::Hinge.part().build().unwrap();
+        assert_eq!(hinge.geometry().vertices().len(),16);
+        hinge.set("Height",0.0254).unwrap();
+        assert_eq!(hinge.geometry().vertices().iter().filter(|v| v.z > 0.1).count(),8);
+        assert_eq!(hinge.connections()[1].joint().freedom(),1);
+    }
+
+}
@@ -0,0 +1,104 @@
+use crate::geometry::{Geometry,Primitive,Vertex,Vector,BOX_FACES,centered};
+use crate::part::{Part,Attribute,Connection};
+use crate::models::{M2X4,sides,stretch};
+
+// eight feet, the length most lumber is sold in
+const LENGTH: f64 = 2.4384;
+
+pub static M2X6: Primitive = Primitive::new(&centered(LENGTH,0.1397,0.0381),&BOX_FACES);
+pub static M2X8: Primitive = Primitive::new(&centered(LENGTH,0.1842,0.0381),&BOX_FACES);
+pub static M4X4: Primitive = Primitive::new(&centered(LENGTH,0.0889,0.0889),&BOX_FACES);
+
+/// Common sizes of dimensional lumber, eight feet long
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub enum Lumber {
+    #[default]
+    TwoByFour,
+    TwoBySix,
+    TwoByEight,
+    FourByFour,
+}
+
+impl Lumber {
+
+    // The nominal size in inches, like 2x4
+    pub fn nominal(&self) -> (usize,usize) {
+        match self {
+            Self::TwoByFour => (2,4),
+            Self::TwoBySix => (2,6),
+            Self::TwoByEight => (2,8),
+            Self::FourByFour => (4,4),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        let (a,b) = self.nominal();
+        format!("{}x{}",a,b)
+    }
+
+    // The actual width and height in meters
+    pub fn size(&self) -> (f64,f64) {
+        let size = self.geometry().bounds().unwrap_or_default().size();
+        (size.y,size.z)
+    }
+
+    // The board along X with its sides named, like "front" for
+    // the end at +X and "top" for the face at +Z
+    pub fn geometry(&self) -> Geometry {
+        match self {
+            Self::TwoByFour => sides(M2X4.geometry()),
+            Self::TwoBySix => sides(M2X6.geometry()),
+            Self::TwoByEight => sides(M2X8.geometry()),
+            Self::FourByFour => sides(M4X4.geometry()),
+        }
+    }
+
+    // The board as a part, with a "Length" attribute that adds to
+    // the front end and a connection in the middle of each end
+    pub fn part(&self) -> Part {
+        let (width,height) = self.size();
+        let x = LENGTH / 2.0;
+        let radius = width.min(height) / 2.0;
+        Part::new(self.name())
+            .with_geometry(self.geometry())
+            .with_attribute(Attribute::new("Length".into(),vec![
+                stretch("front",Vector::new(1.0,0.0,0.0))
+            ]))
+            .with_connection(Connection::new(Vertex::new(-x,0.0,0.0),radius))
+            .with_connection(Connection::new(Vertex::new(x,0.0,0.0),radius))
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_lumber_parts() {
+        let sizes = [Lumber::TwoByFour,Lumber::TwoBySix,Lumber::TwoByEight,Lumber::FourByFour]
+            .map(|l| l.size());
+        assert_relative_eq!(sizes[0].0,0.0889,epsilon = 1e-9);
+        assert_relative_eq!(sizes[1].0,0.1397,epsilon = 1e-9);
+        assert_relative_eq!(sizes[2].0,0.1842,epsilon = 1e-9);
+        assert_relative_eq!(sizes[3].1,0.0889,epsilon = 1e-9);
+
+        let mut board = Lumber::TwoBySix.part().build().unwrap();
+        assert_eq!(board.name(),"2x6");
+        assert_eq!(board.connections().len(),2);
+
+        // only the front end moves
+        board.set("Length",0.5).unwrap();
+        let bounds = board.geometry().bounds().unwrap();
+        assert_relative_eq!(bounds.min.x,-1.2192,epsilon = 1e-9);
+        assert_relative_eq!(bounds.max.x,1.7192,epsilon = 1e-9);
+
+        // the 2x4 keeps the layout of M2X4
+        let stud = Lumber::TwoByFour.geometry();
+        assert_eq!(stud.vertices(),M2X4.vertices());
+        assert_eq!(stud.group("front"),Some(&vec![4,5,6,7]));
+        assert_eq!(stud.group("top"),Some(&vec![1,2,5,6]));
+    }
+
+}
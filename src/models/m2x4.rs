@@ -1,42 +1,36 @@
-use crate::geometry::{Vertex,Face,Geometry};
+use crate::geometry::Primitive;
 
-lazy_static! {
-    pub static ref M2X4: Geometry = {
-        Geometry::make(
-            vec![
-                -1.2192, -0.04445, -0.01905, // 1 b-l
-                -1.2192, -0.04445,  0.01905, // 2 t-l
-                -1.2192,  0.04445,  0.01905, // 3 t-r
-                -1.2192,  0.04445, -0.01905, // 4 b-r
-                 1.2192, -0.04445, -0.01905, // 5 b-l 
-                 1.2192, -0.04445,  0.01905, // 6 t-l
-                 1.2192,  0.04445,  0.01905, // 7 t-r
-                 1.2192,  0.04445, -0.01905, // 8 b-r
-            ],
-            vec![
-                // back end
-                3, 1, 2,
-                1, 3, 4,
-                
-                // front end
-                7, 6, 5,
-                5, 8, 7,
-                
-                // top
-                3, 7, 6,
-                6, 2, 3,
-                
-                // bottom 
-                4, 5, 8,
-                4, 1, 5,
-                
-                // left 
-                1, 2, 6,
-                6, 5, 1,
-                
-                // right
-                8, 7, 3, 
-                3, 4, 8,    
-            ]) 
-    };
-}
\ No newline at end of file
+pub static M2X4: Primitive = primitive! {
+    v -1.2192 -0.04445 -0.01905; // 1 b-l
+    v -1.2192 -0.04445  0.01905; // 2 t-l
+    v -1.2192  0.04445  0.01905; // 3 t-r
+    v -1.2192  0.04445 -0.01905; // 4 b-r
+    v  1.2192 -0.04445 -0.01905; // 5 b-l
+    v  1.2192 -0.04445  0.01905; // 6 t-l
+    v  1.2192  0.04445  0.01905; // 7 t-r
+    v  1.2192  0.04445 -0.01905; // 8 b-r
+
+    // back end
+    f 3 1 2;
+    f 1 3 4;
+
+    // front end
+    f 7 6 5;
+    f 5 8 7;
+
+    // top
+    f 3 7 6;
+    f 6 2 3;
+
+    // bottom
+    f 4 5 8;
+    f 4 1 5;
+
+    // left
+    f 1 2 6;
+    f 6 5 1;
+
+    // right
+    f 8 7 3;
+    f 3 4 8;
+};
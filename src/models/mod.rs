@@ -1,4 +1,58 @@
-
 mod m2x4;
+mod lumber;
+mod sheet;
+mod fastener;
+mod hardware;
+
+pub use m2x4::M2X4;
+pub use lumber::{Lumber,M2X6,M2X8,M4X4};
+pub use sheet::SheetGoods;
+pub use fastener::Fastener;
+pub use hardware::Hardware;
+
+use crate::geometry::{Geometry,Vertex,Vector,Bounds};
+use crate::part::{AttributeItem,Alteration,Selection};
+
+// Names the vertices on each side of the box around a geometry
+// "back", "front", "left", "right", "bottom" and "top", looking
+// along +X with +Z up, so attributes can move whole sides at once
+pub(crate) fn sides(mut geometry: Geometry) -> Geometry {
+    let Some(bounds) = geometry.bounds() else {
+        return geometry;
+    };
+
+    let (min,max) = (bounds.min,bounds.max);
+    let sides = [
+        ("back",0,min.x),
+        ("front",0,max.x),
+        ("left",1,min.y),
+        ("right",1,max.y),
+        ("bottom",2,min.z),
+        ("top",2,max.z),
+    ];
+
+    for (name,axis,side) in sides {
+        let indices = geometry
+            .vertices()
+            .iter()
+            .enumerate()
+            .filter(|(_,v)| ([v.x,v.y,v.z][axis] - side).abs() < 1e-9)
+            .map(|(i,_)| i)
+            .collect::<Vec<usize>>();
+        geometry
+            .add_group(name,indices)
+            .expect("indices come from the geometry");
+    }
+    geometry
+}
+
+// A box between two corners with its sides named
+pub(crate) fn block(min: Vertex, max: Vertex) -> Geometry {
+    sides(Geometry::from_bounds(&Bounds::new(min,max)))
+}
 
-pub use m2x4::M2X4;
\ No newline at end of file
+// Moves a named group of vertices along a direction by the
+// value of the attribute
+pub(crate) fn stretch(group: &str, direction: Vector) -> AttributeItem {
+    AttributeItem::new(Selection::group(group),Alteration::translate(direction))
+}
@@ -0,0 +1,96 @@
+use crate::geometry::{Geometry,Vertex,Vector};
+use crate::part::{Part,Attribute,Connection};
+use crate::models::{block,stretch};
+
+// four by eight feet, the size most sheets are sold in
+const LENGTH: f64 = 2.4384;
+const WIDTH: f64 = 1.2192;
+
+/// Common sheet goods, four by eight feet
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub enum SheetGoods {
+    /// 3/4" plywood
+    #[default]
+    Plywood,
+    /// 7/16" oriented strand board
+    Osb,
+    /// 1/2" drywall
+    Drywall,
+    /// 3/4" medium density fiberboard
+    Mdf,
+}
+
+impl SheetGoods {
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Plywood => "plywood",
+            Self::Osb => "osb",
+            Self::Drywall => "drywall",
+            Self::Mdf => "mdf",
+        }
+    }
+
+    // The actual thickness in meters
+    pub fn thickness(&self) -> f64 {
+        match self {
+            Self::Plywood | Self::Mdf => 0.01905,
+            Self::Osb => 0.0111125,
+            Self::Drywall => 0.0127,
+        }
+    }
+
+    // The sheet lying flat with its long side along X, centered on
+    // the origin, with its sides named like the lumber
+    pub fn geometry(&self) -> Geometry {
+        let (x,y,z) = (LENGTH / 2.0,WIDTH / 2.0,self.thickness() / 2.0);
+        block(Vertex::new(-x,-y,-z),Vertex::new(x,y,z))
+    }
+
+    // The sheet as a part, with "Length", "Width" and "Thickness"
+    // attributes that move the front, right and top sides, and a
+    // connection in the middle of each edge
+    pub fn part(&self) -> Part {
+        let (x,y) = (LENGTH / 2.0,WIDTH / 2.0);
+        let radius = self.thickness() / 2.0;
+        Part::new(self.name())
+            .with_geometry(self.geometry())
+            .with_attribute(Attribute::new("Length".into(),vec![
+                stretch("front",Vector::new(1.0,0.0,0.0))
+            ]))
+            .with_attribute(Attribute::new("Width".into(),vec![
+                stretch("right",Vector::new(0.0,1.0,0.0))
+            ]))
+            .with_attribute(Attribute::new("Thickness".into(),vec![
+                stretch("top",Vector::new(0.0,0.0,1.0))
+            ]))
+            .with_connection(Connection::new(Vertex::new(-x,0.0,0.0),radius))
+            .with_connection(Connection::new(Vertex::new(x,0.0,0.0),radius))
+            .with_connection(Connection::new(Vertex::new(0.0,-y,0.0),radius))
+            .with_connection(Connection::new(Vertex::new(0.0,y,0.0),radius))
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_sheet_parts() {
+        let mut sheet = SheetGoods::Osb.part().build().unwrap();
+        assert_eq!(sheet.name(),"osb");
+        assert_eq!(sheet.connections().len(),4);
+        assert_relative_eq!(sheet.geometry().volume(),2.4384 * 1.2192 * 0.0111125,epsilon = 1e-9);
+
+        // cut down to two feet wide and planed thinner
+        sheet.set("Width",-0.6096).unwrap();
+        sheet.set("Thickness",-0.001).unwrap();
+        let size = sheet.geometry().bounds().unwrap().size();
+        assert_relative_eq!(size.x,2.4384,epsilon = 1e-9);
+        assert_relative_eq!(size.y,0.6096,epsilon = 1e-9);
+        assert_relative_eq!(size.z,0.0101125,epsilon = 1e-9);
+    }
+
+}
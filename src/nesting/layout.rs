@@ -0,0 +1,367 @@
+use std::f64::consts::FRAC_PI_2;
+
+use crate::nesting::{Sheet,Outline};
+use crate::geometry::{Profile,Point};
+use crate::part::Part;
+use crate::errors::{Error,Context};
+use crate::constant::{Index,TOLERANCE};
+
+/// Settings for arranging flat parts onto sheets
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub struct Nesting {
+    sheet: Sheet,
+    kerf: f64,
+    margin: f64,
+}
+
+/// A part outline placed on one of the sheets of a layout
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Placement {
+    /// the index of the part in the nested slice
+    pub part: Index,
+    pub name: String,
+    pub sheet: Index,
+    /// the rotation in radians applied to the outline
+    pub rotation: f64,
+    /// the outline in meters from the corner of its sheet
+    pub profile: Profile,
+}
+
+/// Parts arranged onto as many sheets as they need
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Layout {
+    sheet: Sheet,
+    sheets: usize,
+    placements: Vec<Placement>,
+}
+
+// A free or used rectangle on a sheet
+#[derive(Debug,Copy,Clone,PartialEq)]
+struct Rect {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+// The free space left on one sheet, as overlapping rectangles
+struct Bin {
+    free: Vec<Rect>,
+}
+
+impl Nesting {
+
+    pub fn new(sheet: Sheet) -> Self {
+        Self { sheet, ..Default::default() }
+    }
+
+    // The width of material the saw or cutter removes, which
+    // is left between neighbouring parts
+    pub fn with_kerf(mut self, kerf: f64) -> Self {
+        self.kerf = kerf;
+        self
+    }
+
+    // Space left empty around the edges of each sheet
+    pub fn with_margin(mut self, margin: f64) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn sheet(&self) -> &Sheet {
+        &self.sheet
+    }
+
+    pub fn kerf(&self) -> f64 {
+        self.kerf
+    }
+
+    pub fn margin(&self) -> f64 {
+        self.margin
+    }
+
+    // Places the outline of every part onto sheets, largest first,
+    // trying to use as few sheets as it can. Parts with a grain
+    // are turned to run along the grain of the sheet, and others
+    // can be turned a quarter turn to fit better.
+    pub fn layout(&self, parts: &[Part]) -> Result<Layout,Error> {
+        span!("nesting.layout", parts = parts.len());
+        let mut outlines = parts
+            .iter()
+            .enumerate()
+            .map(|(i,p)| self.outline(p).in_part(p.name()).map(|o| (i,o)))
+            .collect::<Result<Vec<(Index,Outline)>,Error>>()?;
+
+        outlines.sort_by(|(_,a),(_,b)| b.profile.area().total_cmp(&a.profile.area()));
+
+        let length = self.sheet.length() - 2.0 * self.margin + self.kerf;
+        let width = self.sheet.width() - 2.0 * self.margin + self.kerf;
+
+        let mut bins: Vec<Bin> = Vec::new();
+        let mut placements = Vec::new();
+
+        for (index,outline) in outlines.into_iter() {
+            let name = parts[index].name();
+            let options = self.rotations(&outline)
+                .into_iter()
+                .map(|r| (r,outline.profile.map(|p| rotate(p,r))))
+                .filter_map(|(r,p)| p.bounds().map(|b| (r,p,b)))
+                .collect::<Vec<_>>();
+
+            let fit = |bin: &Bin| options
+                .iter()
+                .filter_map(|(r,p,(a,b))| bin
+                    .find(b.0 - a.0 + self.kerf,b.1 - a.1 + self.kerf)
+                    .map(|(s,rect)| (s,rect,*r,p,*a)))
+                .min_by(|a,b| a.0.total_cmp(&b.0));
+
+            let found = bins
+                .iter()
+                .enumerate()
+                .find_map(|(i,b)| fit(b).map(|f| (i,f)));
+
+            let (sheet,(_,rect,rotation,profile,corner)) = match found {
+                Some(found) => found,
+                None => {
+                    let bin = Bin::new(length,width);
+                    let found = fit(&bin).ok_or_else(|| Error::DoesNotFit(name.into()))?;
+                    bins.push(bin);
+                    (bins.len() - 1,found)
+                },
+            };
+
+            bins[sheet].place(rect);
+
+            let (dx,dy) = (self.margin + rect.x - corner.0,self.margin + rect.y - corner.1);
+            placements.push(Placement {
+                part: index,
+                name: name.into(),
+                sheet,
+                rotation,
+                profile: profile.map(|(x,y)| (x + dx,y + dy)),
+            });
+        }
+
+        placements.sort_by_key(|p| (p.sheet,p.part));
+        Ok(Layout {
+            sheet: self.sheet,
+            sheets: bins.len(),
+            placements,
+        })
+    }
+
+    // The outline of a part, checking that it's thin enough
+    fn outline(&self, part: &Part) -> Result<Outline,Error> {
+        let outline = Outline::new(part)?;
+        match self.sheet.thickness() {
+            Some(t) if outline.thickness > t + TOLERANCE => Err(Error::NotFlat(part.name().into())),
+            _ => Ok(outline),
+        }
+    }
+
+    // The rotations a part can be placed at
+    fn rotations(&self, outline: &Outline) -> Vec<f64> {
+        match (outline.grain,self.sheet.has_grain()) {
+            (Some(angle),true) => vec![-angle],
+            _ => vec![0.0,FRAC_PI_2],
+        }
+    }
+
+}
+
+impl Layout {
+
+    pub fn sheet(&self) -> &Sheet {
+        &self.sheet
+    }
+
+    // The number of sheets used
+    pub fn sheets(&self) -> usize {
+        self.sheets
+    }
+
+    // Every placement, ordered by sheet
+    pub fn placements(&self) -> &[Placement] {
+        &self.placements
+    }
+
+    // The placements on one sheet
+    pub fn on(&self, sheet: Index) -> impl Iterator<Item = &Placement> {
+        self.placements.iter().filter(move |p| p.sheet == sheet)
+    }
+
+    // The fraction of the used sheets covered by parts
+    pub fn utilization(&self) -> f64 {
+        match self.sheets {
+            0 => 0.0,
+            n => self.placements
+                .iter()
+                .map(|p| p.profile.area())
+                .sum::<f64>() / (n as f64 * self.sheet.area()),
+        }
+    }
+
+}
+
+impl Rect {
+
+    fn right(&self) -> f64 {
+        self.x + self.w
+    }
+
+    fn top(&self) -> f64 {
+        self.y + self.h
+    }
+
+    fn overlaps(&self, other: &Rect) -> bool {
+        self.x < other.right() && other.x < self.right() &&
+        self.y < other.top() && other.y < self.top()
+    }
+
+    fn contains(&self, other: &Rect) -> bool {
+        self.x <= other.x && other.right() <= self.right() &&
+        self.y <= other.y && other.top() <= self.top()
+    }
+
+}
+
+impl Bin {
+
+    fn new(w: f64, h: f64) -> Self {
+        Self { free: vec![Rect { x: 0.0, y: 0.0, w, h }] }
+    }
+
+    // The best place for a rectangle, which is the free space it
+    // leaves the least room in along its shorter side, with that
+    // leftover as a score.
+    fn find(&self, w: f64, h: f64) -> Option<(f64,Rect)> {
+        self.free
+            .iter()
+            .filter(|f| w <= f.w + TOLERANCE && h <= f.h + TOLERANCE)
+            .map(|f| ((f.w - w).min(f.h - h),Rect { x: f.x, y: f.y, w, h }))
+            .min_by(|a,b| a.0.total_cmp(&b.0))
+    }
+
+    // Takes a rectangle out of the free space, splitting any free
+    // rectangle it overlaps into the space left around it
+    fn place(&mut self, used: Rect) {
+        let mut free = Vec::with_capacity(self.free.len() * 2);
+        for f in self.free.iter() {
+            if !used.overlaps(f) {
+                free.push(*f);
+                continue;
+            }
+            if used.x > f.x {
+                free.push(Rect { w: used.x - f.x, ..*f });
+            }
+            if used.right() < f.right() {
+                free.push(Rect { x: used.right(), w: f.right() - used.right(), ..*f });
+            }
+            if used.y > f.y {
+                free.push(Rect { h: used.y - f.y, ..*f });
+            }
+            if used.top() < f.top() {
+                free.push(Rect { y: used.top(), h: f.top() - used.top(), ..*f });
+            }
+        }
+
+        // drop free rectangles that are inside another one
+        let mut kept: Vec<Rect> = Vec::with_capacity(free.len());
+        for (i,r) in free.iter().enumerate() {
+            let inside = free
+                .iter()
+                .enumerate()
+                .any(|(j,o)| i != j && o.contains(r) && (o != r || j < i));
+            if !inside {
+                kept.push(*r);
+            }
+        }
+        self.free = kept;
+    }
+
+}
+
+fn rotate((x,y): Point, angle: f64) -> Point {
+    let (sin,cos) = angle.sin_cos();
+    (x * cos - y * sin,x * sin + y * cos)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::geometry::{Geometry,ParseMode,Direction};
+
+    // A box with its top face at the given thickness
+    fn panel(name: &str, length: f64, width: f64) -> Part {
+        let (l,w,t) = (length,width,0.018);
+        let text = format!("
+            v 0 0 0\nv {l} 0 0\nv {l} {w} 0\nv 0 {w} 0
+            v 0 0 {t}\nv {l} 0 {t}\nv {l} {w} {t}\nv 0 {w} {t}
+            f 1 3 2\nf 1 4 3\nf 5 6 7\nf 5 7 8
+            f 1 2 6\nf 1 6 5\nf 2 3 7\nf 2 7 6
+            f 3 4 8\nf 3 8 7\nf 4 1 5\nf 4 5 8
+        ");
+        Part::new(name)
+            .with_geometry(Geometry::parse(&text,ParseMode::Lenient).unwrap())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_nesting_sheet_count() {
+        let parts = (0..5)
+            .map(|i| panel(&format!("shelf {}",i),1.2,0.6))
+            .collect::<Vec<Part>>();
+
+        let layout = Nesting::new(Sheet::new(2.44,1.22))
+            .with_kerf(0.003)
+            .build()
+            .layout(&parts)
+            .unwrap();
+
+        // four 1.2 x 0.6 panels fit on a sheet with a 3mm kerf
+        assert_eq!(layout.sheets(),2);
+        assert_eq!(layout.on(0).count(),4);
+        assert_eq!(layout.placements().len(),5);
+
+        for (i,a) in layout.placements().iter().enumerate() {
+            let (lo,hi) = a.profile.bounds().unwrap();
+            assert!(lo.0 > -TOLERANCE && hi.0 < 2.44 + TOLERANCE);
+            assert!(lo.1 > -TOLERANCE && hi.1 < 1.22 + TOLERANCE);
+            for b in layout.placements().iter().skip(i + 1).filter(|b| b.sheet == a.sheet) {
+                let (blo,bhi) = b.profile.bounds().unwrap();
+                let gap = (blo.0 - hi.0).max(lo.0 - bhi.0).max(blo.1 - hi.1).max(lo.1 - bhi.1);
+                assert!(gap > 0.003 - TOLERANCE);
+            }
+        }
+    }
+
+    #[test]
+    fn test_nesting_grain() {
+        let mut part = panel("side",0.5,1.1);
+        part.metadata_mut().set_grain(Some(Direction::new(0.0,1.0,0.0)));
+
+        let nesting = Nesting::new(Sheet::new(2.44,1.22)).with_margin(0.01);
+        let layout = nesting.layout(std::slice::from_ref(&part)).unwrap();
+        let (lo,hi) = layout.placements()[0].profile.bounds().unwrap();
+
+        // turned so the grain runs along the sheet
+        assert_relative_eq!(hi.0 - lo.0,1.1,epsilon = 1e-9);
+        assert_relative_eq!(lo.0,0.01,epsilon = 1e-9);
+
+        // without a sheet grain it's placed as it is
+        let plain = Nesting::new(Sheet::new(2.44,1.22).with_grain(false));
+        let layout = plain.layout(&[panel("big",1.0,2.0)]).unwrap();
+        let (lo,hi) = layout.placements()[0].profile.bounds().unwrap();
+        assert_relative_eq!(hi.0 - lo.0,2.0,epsilon = 1e-9);
+
+        let huge = Nesting::new(Sheet::new(1.0,1.0)).layout(&[panel("huge",2.0,2.0)]);
+        assert!(matches!(huge,Err(Error::DoesNotFit(n)) if n == "huge"));
+    }
+
+}
@@ -0,0 +1,7 @@
+mod sheet;
+mod outline;
+mod layout;
+
+pub use sheet::Sheet;
+pub use outline::Outline;
+pub use layout::{Nesting,Layout,Placement};
@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+
+use crate::geometry::{Vector,Profile,Polyline,Point};
+use crate::part::Part;
+use crate::errors::Error;
+
+// faces within this of facing straight along the thickness
+// axis count as part of the flat face
+const FLAT: f64 = 1e-3;
+
+// points closer than this in meters are joined into one corner
+const WELD: f64 = 1e-6;
+
+/// The shape of a flat part seen down its thinnest axis. The
+/// outline is in meters in the plane of the other two axes.
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Outline {
+    pub profile: Profile,
+    pub thickness: f64,
+    /// the angle of the grain in the outline's plane, if the part
+    /// has a grain that doesn't run through the thickness
+    pub grain: Option<f64>,
+}
+
+impl Outline {
+
+    // Finds the outline of a part from the boundary of its top face,
+    // the faces at the top of its thinnest axis that point up it.
+    pub fn new(part: &Part) -> Result<Self,Error> {
+        let geometry = part.geometry();
+        let bounds = geometry.bounds().ok_or(Error::EmptyGeometry)?;
+        let size = axes(&bounds.size());
+
+        // the thinnest axis, with the other two in order so
+        // that they wind the same way looking down it
+        let axis = (0..3)
+            .min_by(|a,b| size[*a].total_cmp(&size[*b]))
+            .unwrap_or(2);
+        let (u,v) = ((axis + 1) % 3,(axis + 2) % 3);
+        let top = axes(&bounds.max)[axis];
+
+        let vertices = geometry.vertices();
+        let point = |i: usize| {
+            let p = axes(&vertices[i]);
+            (p[u],p[v])
+        };
+        let key = |(x,y): Point| ((x / WELD).round() as i64,(y / WELD).round() as i64);
+
+        // edges of the faces on top keyed by their welded corners,
+        // ignoring which way the faces wind
+        let mut corners = BTreeMap::new();
+        let mut edges = BTreeMap::new();
+        let level = WELD.max(FLAT * size[axis]);
+        for face in geometry.faces().iter().filter(|f| f.is_valid(vertices)) {
+            let normal = axes(&face.normal(vertices).vector());
+            let height = face.edges().iter().all(|(a,_)| (axes(&vertices[*a])[axis] - top).abs() <= level);
+            if normal[axis].abs() > 1.0 - FLAT && height {
+                for (a,b) in face.edges() {
+                    let (a,b) = ((key(point(a)),point(a)),(key(point(b)),point(b)));
+                    corners.insert(a.0,a.1);
+                    corners.insert(b.0,b.1);
+                    *edges.entry((a.0.min(b.0),a.0.max(b.0))).or_insert(0) += 1;
+                }
+            }
+        }
+
+        // edges that aren't shared with a neighbour are on the boundary
+        let mut boundary: BTreeMap<_,Vec<_>> = BTreeMap::new();
+        for (a,b) in edges.into_iter().filter(|(_,n)| *n == 1).map(|(e,_)| e) {
+            boundary.entry(a).or_default().push(b);
+            boundary.entry(b).or_default().push(a);
+        }
+
+        let mut loops = Vec::new();
+        while let Some(start) = boundary.keys().next().copied() {
+            let mut points = Vec::new();
+            let mut current = start;
+            while let Some(next) = boundary.get_mut(&current).and_then(|n| n.pop()) {
+                if let Some(back) = boundary.get_mut(&next) {
+                    back.retain(|k| *k != current);
+                }
+                points.push(corners[&current]);
+                current = next;
+            }
+            boundary.retain(|_,n| !n.is_empty());
+            loops.push(Polyline::closed(points));
+        }
+
+        let mut profile = Profile::from_loops(loops)
+            .map_err(|_| Error::NotFlat(part.name().into()))?;
+        profile.orient();
+
+        let grain = part.metadata().grain().map(|g| axes(&g.vector()));
+        Ok(Self {
+            profile,
+            thickness: size[axis],
+            grain: grain
+                .filter(|g| g[u].hypot(g[v]) > FLAT)
+                .map(|g| g[v].atan2(g[u])),
+        })
+    }
+
+}
+
+fn axes(v: &Vector) -> [f64;3] {
+    [v.x,v.y,v.z]
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::models;
+    use crate::geometry::Direction;
+    use crate::part::Metadata;
+
+    #[test]
+    fn test_outline_board() {
+        let part = Part::new("board")
+            .with_geometry(models::M2X4.geometry())
+            .with_metadata(Metadata::new().with_grain(Direction::new(1.0,0.0,0.0)))
+            .build()
+            .unwrap();
+
+        let outline = Outline::new(&part).unwrap();
+        assert_relative_eq!(outline.thickness,0.0381,epsilon = 1e-9);
+        assert_relative_eq!(outline.profile.area(),2.4384 * 0.0889,epsilon = 1e-9);
+        assert!(outline.profile.outer().signed_area() > 0.0);
+        assert_relative_eq!(outline.grain.unwrap(),0.0);
+    }
+
+}
@@ -0,0 +1,94 @@
+use crate::materials::{Material,Kind};
+use crate::part::Units;
+
+/// A piece of sheet stock that parts are nested onto, in meters.
+/// The length runs along X in a layout, and so does the grain.
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub struct Sheet {
+    length: f64,
+    width: f64,
+    thickness: Option<f64>,
+    grain: bool,
+}
+
+impl Default for Sheet {
+    fn default() -> Self {
+        let foot = |v: f64| v / Units::Feet.per_meter();
+        Self::new(foot(8.0),foot(4.0))
+    }
+}
+
+impl Sheet {
+
+    // A sheet with the grain running along its length
+    pub fn new(length: f64, width: f64) -> Self {
+        Self { length, width, thickness: None, grain: true }
+    }
+
+    // A sheet cut from a sheet material, or None if the material
+    // isn't a sheet or doesn't have both a length and a width
+    pub fn from_material(material: &Material) -> Option<Self> {
+        let section = material.actual().or(material.nominal())?;
+        match material.kind() {
+            Kind::Sheet => Some(Self::new(material.length()?,section.width)
+                .with_thickness(section.thickness)),
+            _ => None,
+        }
+    }
+
+    // The thickest part that can be cut from the sheet
+    pub fn with_thickness(mut self, thickness: f64) -> Self {
+        self.thickness = Some(thickness);
+        self
+    }
+
+    // Whether the sheet has a grain, like plywood, that parts
+    // with a grain have to line up with
+    pub fn with_grain(mut self, grain: bool) -> Self {
+        self.grain = grain;
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn length(&self) -> f64 {
+        self.length
+    }
+
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+
+    pub fn thickness(&self) -> Option<f64> {
+        self.thickness
+    }
+
+    pub fn has_grain(&self) -> bool {
+        self.grain
+    }
+
+    pub fn area(&self) -> f64 {
+        self.length * self.width
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::materials::Database;
+
+    #[test]
+    fn test_sheet_from_material() {
+        let database = Database::builtin();
+        let sheet = Sheet::from_material(database.get("plywood 3/4").unwrap()).unwrap();
+        assert_relative_eq!(sheet.length(),Sheet::default().length());
+        assert_relative_eq!(sheet.width(),1.2192);
+        assert_relative_eq!(sheet.thickness().unwrap(),0.018256,epsilon = 1e-6);
+        assert!(Sheet::from_material(database.get("2x4").unwrap()).is_none());
+    }
+
+}
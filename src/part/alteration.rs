@@ -1,3 +1,5 @@
+use log::trace;
+
 use crate::geometry::{
     Vertex,
     Vector,
@@ -6,6 +8,31 @@ use crate::geometry::{
     Matrix
 };
 
+/// How the magnitude and dimension of a scale are turned
+/// into scaling factors.
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Scaling {
+    /// factor = dimension * magnitude, so 0 flattens
+    #[default]
+    Absolute,
+    /// factor = 1 + dimension * magnitude, so 0 is no change
+    Relative,
+}
+
+/// The frame an alteration's matrix is expressed in
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub enum Space {
+    /// the part's own space
+    #[default]
+    Local,
+    /// the assembly, through the transform of the instance the
+    /// part is placed by
+    World,
+    /// the part's own axes moved to a point on the part, so that
+    /// rotations and scales happen around it
+    Pivot(Vertex),
+}
+
 /// An Alteration will apply a matrix transformation 
 /// of the specified type to a set of points. 
 #[derive(Debug,Copy,Clone)]
@@ -13,6 +40,9 @@ pub struct Alteration {
     magnitude: f64,        // the multiplier for the change
     dimension: Vector,     // the dimension of the change
     operation: MatrixType, // the type of change to make
+    scaling: Scaling,      // how scale factors are derived
+    space: Space,          // the frame the change is made in
+    frame: Matrix,         // where the part is placed, for world changes
 }
 
 impl Alteration {
@@ -21,7 +51,10 @@ impl Alteration {
         Self {
             magnitude: 0.0,
             dimension: Vector::default(),
-            operation: operation,
+            operation,
+            scaling: Scaling::Absolute,
+            space: Space::Local,
+            frame: Matrix::identity(),
         }
     }
 
@@ -35,6 +68,24 @@ impl Alteration {
         self
     }
 
+    pub fn with_scaling(mut self, value: Scaling) -> Self {
+        self.scaling = value;
+        self
+    }
+
+    pub fn with_space(mut self, value: Space) -> Self {
+        self.space = value;
+        self
+    }
+
+    // The transform of the instance the part is placed by, which
+    // changes in world space are made through. It's set when a part
+    // is evaluated for an instance, and ignored in any other space.
+    pub fn with_frame(mut self, value: Matrix) -> Self {
+        self.frame = value;
+        self
+    }
+
     pub fn build(self) -> Self {
         self
     }
@@ -45,6 +96,15 @@ impl Alteration {
             .build()
     }
 
+    // A scale that grows from the original size, so an unset
+    // magnitude of 0 leaves the geometry unchanged.
+    pub fn scale_relative(dimension: Vector) -> Self {
+        Self::new(MatrixType::Scale)
+            .with_dimension(dimension)
+            .with_scaling(Scaling::Relative)
+            .build()
+    }
+
     pub fn rotate(dimension: Vector) -> Self {
         Self::new(MatrixType::Rotate)
             .with_dimension(dimension)
@@ -57,6 +117,36 @@ impl Alteration {
             .build()
     }
 
+    pub fn operation(&self) -> MatrixType {
+        self.operation
+    }
+
+    pub fn magnitude(&self) -> f64 {
+        self.magnitude
+    }
+
+    pub fn dimension(&self) -> Vector {
+        self.dimension
+    }
+
+    pub fn scaling(&self) -> Scaling {
+        self.scaling
+    }
+
+    pub fn space(&self) -> Space {
+        self.space
+    }
+
+    // The values passed to the matrix constructor: scale factors,
+    // angles or offsets depending on the operation.
+    pub fn vector(&self) -> Vector {
+        let vector = self.dimension * self.magnitude;
+        match (self.operation,self.scaling) {
+            (MatrixType::Scale,Scaling::Relative) => Vector::new(1.0,1.0,1.0) + vector,
+            _ => vector,
+        }
+    }
+
     pub fn update_magnitude(&mut self, value: f64) {
         self.magnitude = value;
     }
@@ -65,31 +155,101 @@ impl Alteration {
         self.dimension = value;
     }
 
-    pub fn apply(&self, vertices: &mut Vec<Vertex>) {
+    // True for a scale with a zero factor on any axis, which
+    // flattens the vertices it's applied to.
+    pub fn collapses(&self) -> bool {
+        let vector = self.vector();
+        matches!(self.operation,MatrixType::Scale) &&
+            (vector.x == 0.0 || vector.y == 0.0 || vector.z == 0.0)
+    }
+
+    // True if the alteration leaves vertices where they are, so
+    // applying it can be skipped.
+    pub fn is_identity(&self) -> bool {
+        let vector = self.vector();
+        match self.operation {
+            MatrixType::Scale => vector == Vector::new(1.0,1.0,1.0),
+            _ => vector == Vector::default(),
+        }
+    }
+
+    pub fn apply(&self, vertices: &mut [Vertex]) {
+        if self.is_identity() {
+            return;
+        }
         let matrix = self.matrix();
+        trace!("{:?} applied to {} vertices with {:?}",self.operation,vertices.len(),matrix);
         for vertex in vertices.iter_mut() {
             vertex.transform(&matrix);
         }
     }
 
+    // The matrix for this alteration with its effect scaled by
+    // `weight`, where 0 leaves vertices unchanged and 1 is the
+    // full alteration.
+    pub fn weighted(&self, weight: f64) -> Matrix {
+        let vector = self.vector();
+        let vector = match self.operation {
+            MatrixType::Scale => {
+                let unit = Vector::new(1.0,1.0,1.0);
+                unit + (vector - unit) * weight
+            },
+            _ => vector * weight,
+        };
+
+        self.framed(Matrix::matching(
+            self.operation,
+            vector.x,
+            vector.y,
+            vector.z,
+        ))
+    }
+
     pub fn matrix(&self) -> Matrix {
-        let vector = self.dimension * self.magnitude;
+        let vector = self.vector();
 
-        Matrix::matching(
+        self.framed(Matrix::matching(
             self.operation,
             vector.x,
             vector.y,
             vector.z,
-        )
+        ))
+    }
+
+    // The matrix moved from the space the alteration is made in
+    // into the part's own space. A frame that can't be undone
+    // leaves the matrix as it is.
+    fn framed(&self, matrix: Matrix) -> Matrix {
+        match self.space {
+            Space::Local => matrix,
+            Space::Pivot(p) => {
+                Matrix::translate(p.x,p.y,p.z) *
+                matrix *
+                Matrix::translate(-p.x,-p.y,-p.z)
+            },
+            Space::World => match self.frame.inverse() {
+                Some(inverse) => inverse * matrix * self.frame,
+                None => matrix,
+            },
+        }
     }
 
 }
 
+// Moves the pivot along with the part's geometry. The change itself
+// stays in the part's own axes.
+impl Transform for Alteration {
+    fn transform(&mut self, matrix: &Matrix) {
+        if let Space::Pivot(point) = &mut self.space {
+            point.transform(matrix);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
-    use crate::models;
 
     macro_rules! fassert_eq {
         ( $v: expr, $e: expr ) => {
@@ -97,6 +257,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_alteration_collapses() {
+        assert!(Alteration::scale(Vector::new(1.0,1.0,1.0)).collapses());
+        assert!(!Alteration::scale(Vector::new(1.0,1.0,1.0)).with_magnitude(2.0).collapses());
+        assert!(Alteration::scale(Vector::new(1.0,0.0,1.0)).with_magnitude(2.0).collapses());
+        assert!(!Alteration::translate(Vector::new(1.0,1.0,1.0)).collapses());
+    }
+
+    #[test]
+    fn test_alteration_scale_relative() {
+        let mut data = vec![Vector::new(1.0,2.0,3.0)];
+
+        // unset, so nothing changes
+        let change = Alteration::scale_relative(Vector::new(1.0,0.0,0.0));
+        assert!(change.is_identity());
+        assert!(!change.collapses());
+
+        // half as wide again along x
+        change.with_magnitude(0.5).apply(&mut data);
+        assert_eq!(data[0],Vector::new(1.5,2.0,3.0));
+
+        // shrinking all the way still collapses
+        assert!(change.with_magnitude(-1.0).collapses());
+        assert_eq!(change.with_magnitude(2.0).vector(),Vector::new(3.0,1.0,1.0));
+    }
+
+    #[test]
+    fn test_alteration_identity() {
+        let mut data = vec![Vector::new(1.0,2.0,3.0)];
+
+        let change = Alteration::translate(Vector::new(1.0,1.0,1.0));
+        assert!(change.is_identity());
+        change.apply(&mut data);
+        assert_eq!(data[0],Vector::new(1.0,2.0,3.0));
+
+        assert!(Alteration::rotate(Vector::new(0.0,0.0,0.0)).with_magnitude(5.0).is_identity());
+        assert!(Alteration::scale(Vector::new(1.0,1.0,1.0)).with_magnitude(1.0).is_identity());
+        assert!(!Alteration::scale(Vector::new(1.0,1.0,1.0)).is_identity());
+        assert!(!Alteration::translate(Vector::new(0.0,1.0,0.0)).with_magnitude(1.0).is_identity());
+    }
+
     #[test]
     fn test_alteration_translate() {
         let change = Alteration::new(MatrixType::Translate)
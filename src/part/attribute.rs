@@ -1,28 +1,49 @@
-use std::iter::zip;
-use log;
-
-use crate::geometry::{Matrix,Vector,Vertex,Transform,Geometry};
-use crate::constant::Index;
-use crate::errors::Error;
-use crate::part::Alteration;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::ops::{Bound,RangeBounds};
+use std::sync::{Arc,Mutex};
+use log::{debug,trace,warn};
+
+use crate::utilities;
+use crate::geometry::{Vector,Vertex,Normal,Transform,Geometry,Bounds,Matrix,MatrixType};
+use crate::geometry::{PlaneFit,LineFit,SphereFit,CylinderFit};
+use crate::constant::{Index,ATTRIBUTE_TAG};
+use crate::errors::{Error,Context};
+use crate::part::{Alteration,Scaling,Space};
+use crate::materials::nominal;
 
 #[derive(Debug,Clone)]
 pub enum Selection {
     Specific(Vec<Index>),
-    Range((Index,Index)),
+    // half-open, with no end meaning "to the last vertex"
+    Range((Index,Option<Index>)),
     All,
+    Group(String),
+    Within(Bounds),
+    Connected { face: Index, angle: f64 },
+    Surface { from: Vertex, distance: f64 },
+    Falloff { core: Box<Selection>, radius: f64 },
+    Weighted(Vec<(Index,f64)>),
 }
 
+// The last resolution of a selection, along with the
+// revision of the geometry it was resolved against.
+#[derive(Default,Debug)]
+struct Cache(Mutex<Option<(u64,Arc<Selection>)>>);
+
 #[derive(Debug,Clone)]
 pub struct AttributeItem {
     selection:  Selection,
     alteration: Alteration,
+    cache:      Cache,
 }
 
 #[derive(Debug,Clone)]
 pub struct Attribute {
     name:  String,
+    value: f64,
     items: Vec<AttributeItem>,
+    nominal: bool,
 }
 
 impl Selection {
@@ -31,7 +52,18 @@ impl Selection {
         Self::Specific(indices.into())
     }
 
-    pub fn range(start: usize, end: usize) -> Self {
+    // Accepts any range syntax: `0..3`, `0..=2`, `4..` or `..`
+    pub fn range<R: RangeBounds<Index>>(range: R) -> Self {
+        let start = match range.start_bound() {
+            Bound::Included(s) => *s,
+            Bound::Excluded(s) => s.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(e) => e.checked_add(1),
+            Bound::Excluded(e) => Some(*e),
+            Bound::Unbounded => None,
+        };
         Self::Range((start,end))
     }
 
@@ -39,44 +71,427 @@ impl Selection {
         Self::All
     }
 
-    pub fn apply(&self, alteration: &Alteration, vertices: &mut Vec<Vertex>) {
-        match self.clone() {
+    pub fn group<T: Into<String>>(name: T) -> Self {
+        Self::Group(name.into())
+    }
+
+    pub fn within(bounds: Bounds) -> Self {
+        Self::Within(bounds)
+    }
+
+    // Every vertex of the surface reachable from the seed face by
+    // crossing edges where neighbouring faces differ by no more 
+    // than `max_angle` radians.
+    pub fn connected_from(seed_face: Index, max_angle: f64) -> Self {
+        Self::Connected { face: seed_face, angle: max_angle }
+    }
+
+    // Every vertex within `distance` of a point when measured over
+    // the surface, like everything within 10cm of a mounting point
+    // without reaching through the part to the other side.
+    pub fn along_surface(from: Vertex, distance: f64) -> Self {
+        Self::Surface { from, distance }
+    }
+
+    // A soft selection that fully includes the `core` selection and
+    // partially includes vertices within `radius` of it, with less
+    // influence the further away they are.
+    pub fn falloff(core: Selection, radius: f64) -> Self {
+        Self::Falloff { core: Box::new(core), radius }
+    }
+
+    // Specific, range and all selections can be applied without
+    // looking at the geometry. Others need to be resolved first.
+    pub fn is_resolved(&self) -> bool {
+        matches!(self,
+            Selection::Specific(_) | 
+            Selection::Range(_) | 
+            Selection::All |
+            Selection::Weighted(_))
+    }
+
+    // Converts the selection into an equivalent resolved selection
+    // for the given geometry, checking that every index exists.
+    pub fn resolve(&self, geometry: &Geometry) -> Result<Selection,Error> {
+        let result = match self {
+            Selection::Group(name) => geometry
+                .group(name)
+                .map(|v| Selection::Specific(v.clone()))
+                .ok_or_else(|| Error::UnknownGroup(name.clone()))?,
+            Selection::Connected { face, angle } => {
+                Selection::Specific(Self::connected(geometry,*face,*angle)?)
+            },
+            Selection::Surface { from, distance } => Selection::Specific(geometry
+                .surface_distances(from)
+                .into_iter()
+                .enumerate()
+                .filter(|(_,d)| d <= distance)
+                .map(|(i,_)| i)
+                .collect()),
+            Selection::Falloff { core, radius } => {
+                core.resolve(geometry)?.soften(*radius,geometry.vertices())
+            },
+            _ => self.local(geometry.vertices())?.into_owned(),
+        };
+        result.validate(geometry.vertices().len())?;
+
+        let count = result.count(geometry.vertices().len());
+        debug!("resolved {} selection to {} vertices",self.kind(),count);
+        if count == 0 {
+            warn!("{} selection doesn't contain any vertices",self.kind());
+        }
+
+        Ok(result)
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Selection::Specific(_) => "specific",
+            Selection::Range(_) => "range",
+            Selection::All => "all",
+            Selection::Group(_) => "group",
+            Selection::Within(_) => "within",
+            Selection::Connected { .. } => "connected",
+            Selection::Surface { .. } => "surface",
+            Selection::Falloff { .. } => "falloff",
+            Selection::Weighted(_) => "weighted",
+        }
+    }
+
+    // The number of vertices touched by a resolved selection
+    fn count(&self, count: usize) -> usize {
+        self.ranges(count)
+            .iter()
+            .map(|(s,e)| e.saturating_sub(*s))
+            .sum()
+    }
+
+    // Flood-fills across shared edges from the seed face
+    fn connected(geometry: &Geometry, seed: Index, angle: f64) -> Result<Vec<Index>,Error> {
+        let faces = geometry.faces();
+        let vertices = geometry.vertices();
+
+        if seed >= faces.len() {
+            return Err(Error::IndexOutOfRange { index: seed, len: faces.len() });
+        }
+
+        let neighbours = geometry.face_neighbours();
+        let normals = faces
+            .iter()
+            .map(|f| f.normal(vertices))
+            .collect::<Vec<Normal>>();
+
+        let mut visited = vec![false; faces.len()];
+        let mut stack = vec![seed];
+        let mut result = Vec::new();
+        visited[seed] = true;
+
+        while let Some(index) = stack.pop() {
+            let face = &faces[index];
+            result.extend_from_slice(&[face.a,face.b,face.c]);
+
+            for &next in neighbours[index].iter() {
+                if visited[next] {
+                    continue;
+                }
+                if normals[index].angle(&normals[next]) <= angle {
+                    visited[next] = true;
+                    stack.push(next);
+                }
+            }
+        }
+
+        result.sort_unstable();
+        result.dedup();
+        Ok(result)
+    }
+
+    // Resolves the selection using only the vertices. Groups, connected
+    // and surface selections can't be resolved this way because they
+    // need data stored on the geometry.
+    fn local(&self, vertices: &[Vertex]) -> Result<Cow<'_,Selection>,Error> {
+        match self {
+            Selection::Group(_) | 
+            Selection::Connected { .. } |
+            Selection::Surface { .. } => Err(Error::UnresolvedSelection),
+            Selection::Within(bounds) => Ok(Cow::Owned(Selection::Specific(
+                vertices
+                    .iter()
+                    .enumerate()
+                    .filter(|(_,v)| bounds.contains(v))
+                    .map(|(i,_)| i)
+                    .collect()))),
+            Selection::Falloff { core, radius } => {
+                let core = core.local(vertices)?;
+                core.validate(vertices.len())?;
+                Ok(Cow::Owned(core.soften(*radius,vertices)))
+            },
+            _ => Ok(Cow::Borrowed(self)),
+        }
+    }
+
+    // Moves the positions the selection is made by along with
+    // `vertices`, which are about to be moved by `matrix`, so that
+    // it picks the same vertices once they are. A box that `matrix`
+    // would turn isn't a box along the axes any more, so it's swapped
+    // for the vertices in it.
+    pub(crate) fn follow(&mut self, matrix: &Matrix, vertices: &[Vertex]) {
+        match self {
+            Selection::Within(bounds) if aligned(matrix) => {
+                let mut corners = bounds.corners();
+                for corner in corners.iter_mut() {
+                    corner.transform(matrix);
+                }
+                if let Some(moved) = Bounds::from_points(&corners) {
+                    *bounds = moved;
+                }
+            },
+            Selection::Within(_) => {
+                if let Ok(selection) = self.local(vertices) {
+                    *self = selection.into_owned();
+                }
+            },
+            Selection::Surface { from, .. } => from.transform(matrix),
+            Selection::Falloff { core, .. } => core.follow(matrix,vertices),
+            _ => (),
+        }
+    }
+
+    // Spreads a resolved selection out to nearby vertices, with weights
+    // falling smoothly from 1 at the selection to 0 at `radius`.
+    fn soften(&self, radius: f64, vertices: &[Vertex]) -> Selection {
+        let core = self.weighted(vertices.len());
+        let mut result = Vec::new();
+
+        for (index,vertex) in vertices.iter().enumerate() {
+            let weight = core
+                .iter()
+                .map(|(i,w)| w * falloff(vertices[*i].distance(vertex),radius))
+                .fold(0.0,f64::max);
+
+            if weight > 0.0 {
+                result.push((index,weight));
+            }
+        }
+
+        Selection::Weighted(result)
+    }
+
+    // The indices and weights of a resolved selection
+    fn weighted(&self, count: usize) -> Vec<(Index,f64)> {
+        match self {
+            Selection::Specific(v) => v.iter().map(|i| (*i,1.0)).collect(),
+            Selection::Range(r) => {
+                let (start,end) = span(r,count);
+                (start..end).map(|i| (i,1.0)).collect()
+            },
+            Selection::All => (0..count).map(|i| (i,1.0)).collect(),
+            Selection::Weighted(v) => v.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    // The influence of the selection on every vertex of the geometry,
+    // from 0 (unaffected) to 1 (fully affected).
+    pub fn weights(&self, geometry: &Geometry) -> Result<Vec<f64>,Error> {
+        Ok(self.resolve(geometry)?.weight_map(geometry.vertices().len()))
+    }
+
+    // The positions of the vertices the selection touches, leaving
+    // out any that are weighted down to nothing
+    pub fn points(&self, geometry: &Geometry) -> Result<Vec<Vertex>,Error> {
+        let vertices = geometry.vertices();
+        let mut indices = self
+            .resolve(geometry)?
+            .weighted(vertices.len())
+            .into_iter()
+            .filter(|(_,w)| *w > 0.0)
+            .map(|(i,_)| i)
+            .collect::<Vec<Index>>();
+        indices.sort_unstable();
+        indices.dedup();
+        Ok(indices.into_iter().map(|i| vertices[i]).collect())
+    }
+
+    // The plane through the selected vertices, like a datum
+    // taken from a flat face of a scan
+    pub fn fit_plane(&self, geometry: &Geometry) -> Result<PlaneFit,Error> {
+        self.fit(geometry,PlaneFit::new)
+    }
+
+    // The line through the selected vertices, like a datum
+    // taken from an edge
+    pub fn fit_line(&self, geometry: &Geometry) -> Result<LineFit,Error> {
+        self.fit(geometry,LineFit::new)
+    }
+
+    pub fn fit_sphere(&self, geometry: &Geometry) -> Result<SphereFit,Error> {
+        self.fit(geometry,SphereFit::new)
+    }
+
+    // The cylinder through the selected vertices, like the
+    // axis of a hole or a pipe
+    pub fn fit_cylinder(&self, geometry: &Geometry) -> Result<CylinderFit,Error> {
+        self.fit(geometry,CylinderFit::new)
+    }
+
+    fn fit<T>(&self, geometry: &Geometry, method: fn(&[Vertex]) -> Option<T>) -> Result<T,Error> {
+        let points = self.points(geometry)?;
+        method(&points).ok_or(Error::InvalidFit(points.len()))
+    }
+
+    // The weight of every vertex in a resolved selection, which is
+    // zero for vertices it leaves out
+    pub(crate) fn weight_map(&self, count: usize) -> Vec<f64> {
+        let mut result = vec![0.0; count];
+        for (index,weight) in self.weighted(count) {
+            if let Some(w) = result.get_mut(index) {
+                *w = weight;
+            }
+        }
+        result
+    }
+
+    // Checks that every index in the selection exists
+    // in a geometry with `count` vertices.
+    pub fn validate(&self, count: usize) -> Result<(),Error> {
+        match self {
+            Selection::Specific(v) => match v.iter().find(|i| **i >= count) {
+                Some(&index) => Err(Error::IndexOutOfRange { index, len: count }),
+                None => Ok(()),
+            },
+            Selection::Range(r) => match span(r,count) {
+                (start,end) if start > end || end > count => {
+                    Err(Error::InvalidRange { start, end, len: count })
+                },
+                _ => Ok(()),
+            },
+            Selection::Weighted(v) => match v.iter().find(|(i,_)| *i >= count) {
+                Some(&(index,_)) => Err(Error::IndexOutOfRange { index, len: count }),
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    pub fn apply(&self, alteration: &Alteration, vertices: &mut [Vertex]) -> Result<(),Error> {
+        let selection = self.local(vertices)?;
+        selection.validate(vertices.len())?;
+
+        if alteration.is_identity() {
+            trace!("skipping {:?} alteration with no effect",alteration.operation());
+            return Ok(());
+        }
+
+        let count = selection.count(vertices.len());
+        debug!("{:?} by {:?} applied to {} vertices",
+            alteration.operation(),
+            alteration.vector(),
+            count);
+        trace!("altered vertex ranges: {:?}",selection.ranges(vertices.len()));
+
+        if count == 0 {
+            warn!("{:?} alteration selects no vertices",alteration.operation());
+        }
+        if alteration.collapses() {
+            warn!("scale alteration has a zero factor and will flatten {} vertices",count);
+        }
+
+        match selection.into_owned() {
             Selection::Specific(v) => self.apply_specific(v,alteration,vertices),
-            Selection::Range(v) => self.apply_range(v,alteration,vertices),
-            Selection::All => self.apply_all(alteration,vertices)
+            Selection::Range(r) => self.apply_range(span(&r,vertices.len()),alteration,vertices),
+            Selection::Weighted(v) => self.apply_weighted(v,alteration,vertices),
+            _ => self.apply_all(alteration,vertices)
         }
+        Ok(())
     }
 
-    fn apply_specific(&self, indices: Vec<Index>, alteration: &Alteration, vertices: &mut Vec<Vertex>) {
+    fn apply_specific(&self, indices: Vec<Index>, alteration: &Alteration, vertices: &mut [Vertex]) {
         let matrix = alteration.matrix();
         for index in indices.into_iter() {
             vertices[index].transform(&matrix);
         }
     }
 
-    fn apply_range(&self, (start,end): (Index,Index), alteration: &Alteration, vertices: &mut Vec<Vertex>) {
+    fn apply_range(&self, (start,end): (Index,Index), alteration: &Alteration, vertices: &mut [Vertex]) {
         let matrix = alteration.matrix();
         for vertex in vertices[start..end].iter_mut() {
             vertex.transform(&matrix);
         }
     }
 
-    fn apply_all(&self, alteration: &Alteration, vertices: &mut Vec<Vertex>) {
+    fn apply_weighted(&self, weights: Vec<(Index,f64)>, alteration: &Alteration, vertices: &mut [Vertex]) {
+        for (index,weight) in weights.into_iter() {
+            vertices[index].transform(&alteration.weighted(weight));
+        }
+    }
+
+    fn apply_all(&self, alteration: &Alteration, vertices: &mut [Vertex]) {
         let matrix = alteration.matrix();
         for vertex in vertices.iter_mut() {
             vertex.transform(&matrix);
         }
     }
 
-    pub fn centroid(&self, vertices: &Vec<Vertex>) -> Vertex {
-        match self.clone() {
+    // The vertex ranges touched by this selection, given the
+    // total number of vertices in the geometry.
+    pub fn ranges(&self, count: usize) -> Vec<(Index,Index)> {
+        match self {
+            Selection::Specific(_) | Selection::Weighted(_) => {
+                let mut indices = self
+                    .weighted(count)
+                    .into_iter()
+                    .map(|(i,_)| i)
+                    .collect::<Vec<Index>>();
+                indices.sort_unstable();
+                indices.dedup();
+
+                let mut result: Vec<(Index,Index)> = Vec::new();
+                for index in indices.into_iter().filter(|i| *i < count) {
+                    match result.last_mut() {
+                        Some((_,end)) if *end == index => *end += 1,
+                        _ => result.push((index,index + 1)),
+                    }
+                }
+                result
+            },
+            Selection::Range(r) => {
+                let (start,end) = span(r,count);
+                vec![(start,end.min(count))]
+            },
+            // unresolved selections could touch anything
+            _ => vec![(0,count)],
+        }
+    }
+
+    pub fn centroid(&self, vertices: &[Vertex]) -> Result<Vertex,Error> {
+        let selection = self.local(vertices)?;
+        selection.validate(vertices.len())?;
+        Ok(match selection.into_owned() {
             Selection::Specific(v) => self.centroid_specific(v,vertices),
-            Selection::Range(v) => self.centroid_range(v,vertices),
-            Selection::All => self.centroid_all(vertices)
+            Selection::Range(r) => self.centroid_range(span(&r,vertices.len()),vertices),
+            Selection::Weighted(v) => self.centroid_weighted(v,vertices),
+            _ => self.centroid_all(vertices)
+        })
+    }
+
+    fn centroid_weighted(&self, weights: Vec<(Index,f64)>, vertices: &[Vertex]) -> Vertex {
+        let mut result = Vertex::new(0.0,0.0,0.0);
+        let mut total = 0.0;
+
+        for (index,weight) in weights.into_iter() {
+            result = result + vertices[index] * weight;
+            total += weight;
+        }
+
+        if total > 0.0 {
+            result * (1.0 / total)
+        } else {
+            result
         }
     }
 
-    fn centroid_specific(&self, indices: Vec<Index>, vertices: &Vec<Vertex>) -> Vertex {
+    fn centroid_specific(&self, indices: Vec<Index>, vertices: &[Vertex]) -> Vertex {
         let mut result = Vertex::new(0.0,0.0,0.0);
         let length = indices.len();
 
@@ -89,7 +504,7 @@ impl Selection {
         result / length
     }
 
-    fn centroid_range(&self, (start,end): (Index,Index), vertices: &Vec<Vertex>) -> Vertex {
+    fn centroid_range(&self, (start,end): (Index,Index), vertices: &[Vertex]) -> Vertex {
         let mut result = Vertex::new(0.0,0.0,0.0);
         let mut count = 0;
 
@@ -103,7 +518,7 @@ impl Selection {
         result / count
     }
 
-    fn centroid_all(&self, vertices: &Vec<Vertex>) -> Vertex {
+    fn centroid_all(&self, vertices: &[Vertex]) -> Vertex {
         let mut result = Vertex::new(0.0,0.0,0.0);
         let mut count = 0;
 
@@ -119,10 +534,75 @@ impl Selection {
 
 }
 
+// The concrete bounds of a range selection for `count` vertices
+fn span(&(start,end): &(Index,Option<Index>), count: usize) -> (Index,Index) {
+    (start,end.unwrap_or(count))
+}
+
+// True if the matrix keeps boxes lined up with the axes, by scaling,
+// flipping or swapping them but not turning them part way
+fn aligned(matrix: &Matrix) -> bool {
+    let m = matrix.unpack();
+    [0,4,8].iter().all(|r| m[*r..*r + 3].iter().filter(|v| v.abs() > f64::EPSILON).count() <= 1)
+}
+
+// Smoothly decreasing weight for a vertex `distance` away from
+// a selection, reaching 0 at `radius`.
+fn falloff(distance: f64, radius: f64) -> f64 {
+    if distance >= radius {
+        return if distance == 0.0 { 1.0 } else { 0.0 };
+    }
+    let t = distance / radius;
+    1.0 - t * t * (3.0 - 2.0 * t)
+}
+
+impl Cache {
+
+    fn get(&self, revision: u64) -> Option<Arc<Selection>> {
+        match self.0.lock().ok()?.as_ref() {
+            Some((r,s)) if *r == revision => Some(s.clone()),
+            _ => None,
+        }
+    }
+
+    fn set(&self, revision: u64, selection: Arc<Selection>) {
+        if let Ok(mut entry) = self.0.lock() {
+            *entry = Some((revision,selection));
+        }
+    }
+
+}
+
+impl Clone for Cache {
+    fn clone(&self) -> Self {
+        let entry = self.0
+            .lock()
+            .ok()
+            .and_then(|e| e.clone());
+        Self(Mutex::new(entry))
+    }
+}
+
 impl AttributeItem {
 
-    fn new(selection: Selection, alteration: Alteration) -> Self {
-        Self { selection, alteration }
+    pub fn new(selection: Selection, alteration: Alteration) -> Self {
+        Self { selection, alteration, cache: Cache::default() }
+    }
+
+    pub fn selection(&self) -> &Selection {
+        &self.selection
+    }
+
+    pub fn alteration(&self) -> &Alteration {
+        &self.alteration
+    }
+
+    // Moves the selection and any pivot along with `vertices`, which
+    // are about to be moved by `matrix`, dropping the cached resolution
+    pub(crate) fn follow(&mut self, matrix: &Matrix, vertices: &[Vertex]) {
+        self.selection.follow(matrix,vertices);
+        self.alteration.transform(matrix);
+        self.cache = Cache::default();
     }
 
     pub fn scale_specific<T: Into<Vec<Index>>>(dimension: Vector, indices: T) -> Self {
@@ -132,9 +612,9 @@ impl AttributeItem {
         )
     }
 
-    pub fn scale_range(dimension: Vector, start: usize, end: usize) -> Self {
+    pub fn scale_range<R: RangeBounds<Index>>(dimension: Vector, range: R) -> Self {
         Self::new(
-            Selection::range(start,end),
+            Selection::range(range),
             Alteration::scale(dimension)
         )
     }
@@ -146,6 +626,27 @@ impl AttributeItem {
         )
     }
 
+    pub fn scale_relative_specific<T: Into<Vec<Index>>>(dimension: Vector, indices: T) -> Self {
+        Self::new(
+            Selection::specific(indices),
+            Alteration::scale_relative(dimension)
+        )
+    }
+
+    pub fn scale_relative_range<R: RangeBounds<Index>>(dimension: Vector, range: R) -> Self {
+        Self::new(
+            Selection::range(range),
+            Alteration::scale_relative(dimension)
+        )
+    }
+
+    pub fn scale_relative_all(dimension: Vector) -> Self {
+        Self::new(
+            Selection::all(),
+            Alteration::scale_relative(dimension)
+        )
+    }
+
     pub fn rotate_specific<T: Into<Vec<Index>>>(dimension: Vector, indices: T) -> Self {
         Self::new(
             Selection::specific(indices),
@@ -153,9 +654,9 @@ impl AttributeItem {
         )
     }
 
-    pub fn rotate_range(dimension: Vector, start: usize, end: usize) -> Self {
+    pub fn rotate_range<R: RangeBounds<Index>>(dimension: Vector, range: R) -> Self {
         Self::new(
-            Selection::range(start,end),
+            Selection::range(range),
             Alteration::rotate(dimension)
         )
     }
@@ -174,9 +675,9 @@ impl AttributeItem {
         )
     }
 
-    pub fn translate_range(dimension: Vector, start: usize, end: usize) -> Self {
+    pub fn translate_range<R: RangeBounds<Index>>(dimension: Vector, range: R) -> Self {
         Self::new(
-            Selection::range(start,end),
+            Selection::range(range),
             Alteration::translate(dimension)
         )
     }
@@ -196,12 +697,68 @@ impl AttributeItem {
         self.alteration.update_dimension(dimension);
     }
 
-    pub fn apply(&self, vertices: &mut Vec<Vertex>) {
-        self.selection.apply(&self.alteration,vertices);
+    pub fn validate(&self, count: usize) -> Result<(),Error> {
+        self.selection.validate(count)
     }
 
-    pub fn centroid(&self, geometry: &Geometry) -> Vertex {
-        self.selection.centroid(geometry.vertices())
+    pub fn apply(&self, vertices: &mut [Vertex]) -> Result<(),Error> {
+        self.selection.apply(&self.alteration,vertices)
+    }
+
+    // Resolves the selection against the geometry, reusing the previous
+    // result if the geometry hasn't been modified since then.
+    pub fn resolve(&self, geometry: &Geometry) -> Result<Arc<Selection>,Error> {
+        let revision = geometry.revision();
+
+        if let Some(selection) = self.cache.get(revision) {
+            trace!("reusing cached selection for revision {}",revision);
+            return Ok(selection);
+        }
+
+        let selection = Arc::new(self.selection.resolve(geometry)?);
+
+        // revision 0 isn't unique, so it can't be cached
+        if revision != 0 {
+            self.cache.set(revision,selection.clone());
+        }
+
+        Ok(selection)
+    }
+
+    pub fn revise(&self, geometry: &mut Geometry) -> Result<(),Error> {
+        let selection = self.resolve(geometry)?;
+        self.revise_with(&selection,geometry)
+    }
+
+    // Selects vertices in `base` and alters the same vertices in
+    // `geometry`, which must have the same layout.
+    pub fn revise_from(&self, base: &Geometry, geometry: &mut Geometry) -> Result<(),Error> {
+        let selection = self.resolve(base)?;
+        self.revise_with(&selection,geometry)
+    }
+
+    fn revise_with(&self, selection: &Selection, geometry: &mut Geometry) -> Result<(),Error> {
+        span!("attribute.item.revise", operation = ?self.alteration.operation());
+
+        // nothing would move, so don't touch the geometry at all
+        if self.alteration.is_identity() {
+            return selection.validate(geometry.vertices().len());
+        }
+
+        let ranges = selection.ranges(geometry.vertices().len());
+        let mut result = Ok(());
+        geometry.alter(&ranges,|v| result = selection.apply(&self.alteration,v));
+        result
+    }
+
+    pub fn centroid(&self, geometry: &Geometry) -> Result<Vertex,Error> {
+        self.resolve(geometry)?.centroid(geometry.vertices())
+    }
+
+    // The influence of this item on every vertex, for previewing
+    // soft selections before the alteration is applied.
+    pub fn weights(&self, geometry: &Geometry) -> Result<Vec<f64>,Error> {
+        Ok(self.resolve(geometry)?.weight_map(geometry.vertices().len()))
     }
 
 }
@@ -209,30 +766,357 @@ impl AttributeItem {
 impl Attribute {
     
     pub fn new(name: String, items: Vec<AttributeItem>) -> Self {
-        Self { name, items }
+        Self { name, value: 0.0, items, nominal: false }
+    }
+
+    // Treats values as nominal lumber sizes in inches, like the 4 in
+    // 2x4, which are applied as the actual size in meters
+    pub fn with_nominal(mut self, value: bool) -> Self {
+        self.nominal = value;
+        self.update(self.value);
+        self
+    }
+
+    pub fn is_nominal(&self) -> bool {
+        self.nominal
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
     }
 
     pub fn update(&mut self, value: f64) {
+        debug!("attribute '{}' changed from {} to {}",self.name,self.value,value);
+        self.value = value;
+        let magnitude = match self.nominal {
+            true => nominal::actual_meters(value),
+            false => value,
+        };
         for item in self.items.iter_mut() {
-            item.update_magnitude(value);
+            item.update_magnitude(magnitude);
         }
     }
 
-    pub fn apply(&self, vertices: &mut Vec<Vertex>) {
-        for item in self.items.iter() {
-            item.apply(vertices);
+    // True if any item makes its change in world space, so the
+    // result depends on where the part is placed
+    pub fn is_world(&self) -> bool {
+        self.items
+            .iter()
+            .any(|i| i.alteration().space() == Space::World)
+    }
+
+    // Moves the selection and any pivot of every item along with
+    // `vertices`, which are about to be moved by `matrix`
+    pub(crate) fn follow(&mut self, matrix: &Matrix, vertices: &[Vertex]) {
+        for item in self.items.iter_mut() {
+            item.follow(matrix,vertices);
+        }
+    }
+
+    // A copy with changes in world space made through `frame`, the
+    // transform of the instance the part is placed by
+    pub fn framed(&self, frame: Matrix) -> Attribute {
+        let mut attribute = self.clone();
+        for item in attribute.items.iter_mut() {
+            item.alteration = item.alteration.with_frame(frame);
         }
+        attribute
+    }
+
+    // Checks every item before any are applied, so a bad
+    // selection doesn't leave the vertices half-modified.
+    pub fn validate(&self, count: usize) -> Result<(),Error> {
+        for (index,item) in self.items.iter().enumerate() {
+            item.validate(count).in_item(index)?;
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, vertices: &mut [Vertex]) -> Result<(),Error> {
+        self.validate(vertices.len())?;
+        for (index,item) in self.items.iter().enumerate() {
+            item.apply(vertices).in_item(index)?;
+        }
+        Ok(())
+    }
+
+    // Resolves the selection of every item against the geometry
+    pub fn resolve(&self, geometry: &Geometry) -> Result<Vec<Arc<Selection>>,Error> {
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(n,i)| i.resolve(geometry).in_item(n))
+            .collect()
+    }
+
+    pub fn revise(&self, geometry: &mut Geometry) -> Result<(),Error> {
+        let selections = self.resolve(geometry)?;
+        self.revise_with(&selections,geometry)
+    }
+
+    // Selects vertices in `base` and alters the same vertices in
+    // `geometry`, which must have the same layout.
+    pub fn revise_from(&self, base: &Geometry, geometry: &mut Geometry) -> Result<(),Error> {
+        let selections = self.resolve(base)?;
+        self.revise_with(&selections,geometry)
+    }
+
+    // Every selection is resolved before any are applied, so a bad
+    // selection doesn't leave the geometry half-modified.
+    fn revise_with(&self, selections: &[Arc<Selection>], geometry: &mut Geometry) -> Result<(),Error> {
+        span!("attribute.revise", name = %self.name, items = self.items.len());
+        debug!("revising attribute '{}' with {} items",self.name,self.items.len());
+        for (index,(item,selection)) in self.items.iter().zip(selections.iter()).enumerate() {
+            item.revise_with(selection,geometry).in_item(index)?;
+        }
+        Ok(())
+    }
+
+    // True if any item scales by zero and would flatten the geometry
+    pub fn collapses(&self) -> bool {
+        self.items
+            .iter()
+            .any(|i| i.alteration().collapses())
     }
 
-    pub fn revise(&self, geometry: &mut Geometry) {
-        let vertices = geometry.vertices_mut();
-        self.apply(vertices);
+    pub fn distance(&self, geometry: &Geometry, start: usize, end: usize) -> Result<f64,Error> {
+        let a = self.item(start)?.centroid(geometry)?;
+        let b = self.item(end)?.centroid(geometry)?;
+        Ok(a.distance(&b))
     }
 
-    pub fn distance(&self, geometry: &Geometry, start: usize, end: usize) -> f64 {
-        let a = self.items[start].centroid(geometry);
-        let b = self.items[end].centroid(geometry);
-        a.distance(&b)
+    fn item(&self, index: usize) -> Result<&AttributeItem,Error> {
+        self.items
+            .get(index)
+            .ok_or(Error::IndexOutOfRange { index, len: self.items.len() })
+    }
+
+    pub fn items(&self) -> &[AttributeItem] {
+        &self.items
+    }
+
+    // Builds attributes from `a` lines, keeping the order in which
+    // their names first appear. Other lines are ignored.
+    pub fn parse_lines(text: &str) -> Result<Vec<Attribute>,Error> {
+        let mut result: Vec<Attribute> = Vec::new();
+        let mut values: Vec<f64> = Vec::new();
+
+        for line in text.lines().map(utilities::strip_comment) {
+            let rest = match line.strip_prefix(ATTRIBUTE_TAG) {
+                Some(r) if r.starts_with(' ') => r.trim(),
+                _ => continue,
+            };
+
+            let (name,rest) = utilities::unquote(rest)?;
+            if rest.trim().is_empty() {
+                return Err(Error::ParseError);
+            }
+
+            let index = match result.iter().position(|a| a.name == name) {
+                Some(i) => i,
+                None => {
+                    result.push(Attribute::new(name,Vec::new()));
+                    values.push(0.0);
+                    result.len() - 1
+                },
+            };
+
+            match rest.trim().strip_prefix("= ") {
+                Some(value) => values[index] = value.trim().parse()?,
+                None if rest.trim() == "nominal" => result[index].nominal = true,
+                None => result[index].items.push(AttributeItem::try_from(rest)?),
+            }
+        }
+
+        for (attribute,value) in result.iter_mut().zip(values) {
+            attribute.update(value);
+        }
+
+        Ok(result)
+    }
+}
+
+// Selections are written as a single token:
+//
+//   *                   every vertex
+//   2:6 or 4:           a range, with an optional end
+//   1,2,5 or -          specific vertices, or none
+//   g:<name>            a named group, in quotes if it has spaces
+//   b:x,y,z,x,y,z       vertices within bounds
+//   c:<face>,<angle>    faces connected to a seed face
+//   s:x,y,z,<distance>  vertices near a point over the surface
+//   w:<i>=<w>,...       weighted vertices
+//   <selection>~<r>     a falloff around another selection
+impl From<&Selection> for String {
+    fn from(selection: &Selection) -> Self {
+        let list = |v: &mut dyn Iterator<Item = String>| v.collect::<Vec<String>>().join(",");
+        match selection {
+            Selection::All => "*".into(),
+            Selection::Range((start,Some(end))) => format!("{}:{}",start,end),
+            Selection::Range((start,None)) => format!("{}:",start),
+            Selection::Specific(v) if v.is_empty() => "-".into(),
+            Selection::Specific(v) => list(&mut v.iter().map(|i| i.to_string())),
+            Selection::Group(name) => format!("g:{}",utilities::quote(name,&['~'])),
+            Selection::Within(b) => format!("b:{},{},{},{},{},{}",
+                b.min.x,b.min.y,b.min.z,b.max.x,b.max.y,b.max.z),
+            Selection::Connected { face, angle } => format!("c:{},{}",face,angle),
+            Selection::Surface { from, distance } => format!("s:{},{},{},{}",from.x,from.y,from.z,distance),
+            Selection::Weighted(v) => format!("w:{}",
+                list(&mut v.iter().map(|(i,w)| format!("{}={}",i,w)))),
+            Selection::Falloff { core, radius } => format!("{}~{}",String::from(core.as_ref()),radius),
+        }
+    }
+}
+
+impl TryFrom<&str> for Selection {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let value = value.trim();
+
+        // a `~` in a quoted group name isn't a falloff
+        if let Some((core,radius)) = value.rsplit_once('~').filter(|(_,r)| !r.contains('"')) {
+            return Ok(Selection::falloff(Selection::try_from(core)?,radius.parse()?));
+        }
+
+        let numbers = |v: &str| v
+            .split(',')
+            .map(str::parse::<f64>)
+            .collect::<Result<Vec<f64>,_>>();
+
+        Ok(match value {
+            "*" => Selection::All,
+            "-" => Selection::Specific(Vec::new()),
+            v if v.starts_with("g:") => match utilities::unquote(&v[2..])? {
+                (name,"") => Selection::group(name),
+                _ => return Err(Error::ParseError),
+            },
+            v if v.starts_with("b:") => match numbers(&v[2..])?.as_slice() {
+                [a,b,c,d,e,f] => Selection::within(Bounds::new(
+                    Vertex::new(*a,*b,*c),
+                    Vertex::new(*d,*e,*f))),
+                _ => return Err(Error::ParseError),
+            },
+            v if v.starts_with("c:") => {
+                let (face,angle) = v[2..].split_once(',').ok_or(Error::ParseError)?;
+                Selection::connected_from(face.parse()?,angle.parse()?)
+            },
+            v if v.starts_with("s:") => match numbers(&v[2..])?.as_slice() {
+                [x,y,z,d] => Selection::along_surface(Vertex::new(*x,*y,*z),*d),
+                _ => return Err(Error::ParseError),
+            },
+            "w:" => Selection::Weighted(Vec::new()),
+            v if v.starts_with("w:") => Selection::Weighted(v[2..]
+                .split(',')
+                .map(|p| {
+                    let (i,w) = p.split_once('=').ok_or(Error::ParseError)?;
+                    Ok((i.parse()?,w.parse()?))
+                })
+                .collect::<Result<Vec<(Index,f64)>,Error>>()?),
+            v => match v.split_once(':') {
+                Some((start,"")) => Selection::range(start.parse::<Index>()?..),
+                Some((start,end)) => Selection::range(start.parse::<Index>()?..end.parse()?),
+                None => Selection::Specific(v
+                    .split(',')
+                    .map(str::parse)
+                    .collect::<Result<Vec<Index>,_>>()?),
+            },
+        })
+    }
+}
+
+// Items are written as `<operation> <selection> <x> <y> <z>`
+impl From<&AttributeItem> for String {
+    fn from(item: &AttributeItem) -> Self {
+        let alteration = item.alteration();
+        let operation = match (alteration.operation(),alteration.scaling()) {
+            (MatrixType::Scale,Scaling::Relative) => "scale+",
+            (MatrixType::Scale,Scaling::Absolute) => "scale",
+            (MatrixType::Rotate,_) => "rotate",
+            (MatrixType::Translate,_) => "translate",
+        };
+        let space = match alteration.space() {
+            Space::Local => String::new(),
+            Space::World => "@world".into(),
+            Space::Pivot(p) => format!("@{},{},{}",p.x,p.y,p.z),
+        };
+        let d = alteration.dimension();
+        format!("{}{} {} {} {} {}",
+            operation,
+            space,
+            String::from(item.selection()),
+            d.x,d.y,d.z)
+    }
+}
+
+impl TryFrom<&str> for AttributeItem {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        // the selection is one token, but a group name in it can
+        // have spaces inside quotes
+        let (operation,rest) = utilities::token(value);
+        let (selection,rest) = utilities::token(rest);
+        if operation.is_empty() || selection.is_empty() {
+            return Err(Error::ParseError);
+        }
+        let selection = Selection::try_from(selection)?;
+        let dimension = Vector::from(utilities::extract::<f64>(' ',rest)?);
+
+        // the space is written after the operation, like
+        // "rotate@world" or "rotate@x,y,z" for a pivot
+        let (operation,space) = match operation.split_once('@') {
+            None => (operation,Space::Local),
+            Some((operation,"world")) => (operation,Space::World),
+            Some((operation,point)) => match point.split(',').map(str::parse).collect::<Result<Vec<f64>,_>>()?.as_slice() {
+                [x,y,z] => (operation,Space::Pivot(Vertex::new(*x,*y,*z))),
+                _ => return Err(Error::ParseError),
+            },
+        };
+
+        let alteration = match operation {
+            "scale" => Alteration::scale(dimension),
+            "scale+" => Alteration::scale_relative(dimension),
+            "rotate" => Alteration::rotate(dimension),
+            "translate" => Alteration::translate(dimension),
+            _ => return Err(Error::ParseError),
+        };
+
+        Ok(AttributeItem::new(selection,alteration.with_space(space)))
+    }
+}
+
+// An attribute is written as a line with its value followed
+// by one line per item, all tagged with the attribute name, which
+// is quoted if it has spaces.
+impl From<&Attribute> for String {
+    fn from(attribute: &Attribute) -> Self {
+        let name = utilities::quote(&attribute.name,&[]);
+        let mut lines = vec![format!("{} {} = {}",
+            ATTRIBUTE_TAG,
+            name,
+            attribute.value)];
+
+        if attribute.nominal {
+            lines.push(format!("{} {} nominal",ATTRIBUTE_TAG,name));
+        }
+
+        for item in attribute.items.iter() {
+            lines.push(format!("{} {} {}",
+                ATTRIBUTE_TAG,
+                name,
+                String::from(item)));
+        }
+
+        lines.join("\n")
     }
 }
 
@@ -241,6 +1125,7 @@ mod tests {
 
     use super::*;
     use crate::models;
+    use proptest::prelude::*;
 
     macro_rules! fassert_eq {
         ( $v: expr, $e: expr ) => {
@@ -248,6 +1133,411 @@ mod tests {
         }
     }
 
+    // Collects log messages from the current thread, so tests
+    // running in parallel don't see each other's output.
+    struct Capture;
+
+    thread_local! {
+        static LOGS: std::cell::RefCell<Vec<(log::Level,String)>> = Default::default();
+    }
+
+    impl log::Log for Capture {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            LOGS.with(|l| l.borrow_mut().push((record.level(),record.args().to_string())));
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn capture<F: FnOnce()>(f: F) -> Vec<(log::Level,String)> {
+        static INIT: std::sync::Once = std::sync::Once::new();
+        INIT.call_once(|| {
+            let _ = log::set_logger(&Capture);
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+        LOGS.with(|l| l.borrow_mut().clear());
+        f();
+        LOGS.with(|l| l.take())
+    }
+
+    fn warnings(logs: &[(log::Level,String)]) -> Vec<&str> {
+        logs.iter()
+            .filter(|(l,_)| *l == log::Level::Warn)
+            .map(|(_,m)| m.as_str())
+            .collect()
+    }
+
+    proptest! {
+
+        #[test]
+        fn test_selection_specific_never_panics(indices in prop::collection::vec(0usize..64,0..16)) {
+            let mut vertices = vec![Vertex::new(1.0,1.0,1.0); 8];
+            let valid = indices.iter().all(|i| *i < 8);
+            let item = AttributeItem::translate_specific(Vector::new(1.0,0.0,0.0),indices);
+            prop_assert_eq!(item.apply(&mut vertices).is_ok(),valid);
+            prop_assert_eq!(item.selection.centroid(&vertices).is_ok(),valid);
+        }
+
+        #[test]
+        fn test_selection_range_never_panics(start in 0usize..64, end in 0usize..64) {
+            let mut vertices = vec![Vertex::new(1.0,1.0,1.0); 8];
+            let valid = start <= end && end <= 8;
+            let item = AttributeItem::translate_range(Vector::new(1.0,0.0,0.0),start..end);
+            prop_assert_eq!(item.apply(&mut vertices).is_ok(),valid);
+            prop_assert_eq!(item.selection.centroid(&vertices).is_ok(),valid);
+        }
+
+    }
+
+    #[test]
+    fn test_selection_specific_out_of_range() {
+        let mut vertices = vec![Vertex::new(1.0,1.0,1.0); 4];
+        let item = AttributeItem::translate_specific(Vector::new(1.0,0.0,0.0),[1,4]);
+
+        match item.apply(&mut vertices) {
+            Err(Error::IndexOutOfRange { index, len }) => {
+                assert_eq!(index,4);
+                assert_eq!(len,4);
+            },
+            other => panic!("unexpected result: {:?}",other),
+        }
+
+        // nothing was moved
+        fassert_eq!(vertices[1].x, 1.0);
+    }
+
+    #[test]
+    fn test_selection_range_invalid() {
+        let mut vertices = vec![Vertex::new(1.0,1.0,1.0); 4];
+        let item = AttributeItem::translate_range(
+            Vector::new(1.0,0.0,0.0),
+            std::ops::Range { start: 3, end: 2 });
+
+        assert!(matches!(
+            item.apply(&mut vertices),
+            Err(Error::InvalidRange { start: 3, end: 2, len: 4 })
+        ));
+    }
+
+    #[test]
+    fn test_selection_range_syntax() {
+        let count = 6;
+        let ranges = |s: Selection| {
+            s.validate(count).map(|_| s.ranges(count))
+        };
+
+        assert_eq!(ranges(Selection::range(1..3)).unwrap(),vec![(1,3)]);
+        assert_eq!(ranges(Selection::range(1..=3)).unwrap(),vec![(1,4)]);
+        assert_eq!(ranges(Selection::range(4..)).unwrap(),vec![(4,6)]);
+        assert_eq!(ranges(Selection::range(..2)).unwrap(),vec![(0,2)]);
+        assert_eq!(ranges(Selection::range(..)).unwrap(),vec![(0,6)]);
+        assert_eq!(ranges(Selection::range(6..)).unwrap(),vec![(6,6)]);
+
+        assert!(matches!(
+            ranges(Selection::range(7..)),
+            Err(Error::InvalidRange { start: 7, end: 6, len: 6 })));
+        assert!(matches!(
+            ranges(Selection::range(0..=6)),
+            Err(Error::InvalidRange { start: 0, end: 7, len: 6 })));
+    }
+
+    #[test]
+    fn test_attribute_revise_is_atomic() {
+        let mut geometry = models::M2X4.geometry();
+
+        let mut length = Attribute::new("Length".into(),vec![
+            AttributeItem::translate_specific(Vector::new(1.0,0.0,0.0),vec![4,5,6,7]),
+            AttributeItem::translate_specific(Vector::new(-1.0,0.0,0.0),vec![0,1,2,30])
+        ]);
+
+        length.update(2.0);
+        assert!(length.revise(&mut geometry).is_err());
+        assert!(geometry.changes().is_empty());
+        fassert_eq!(geometry.vertices()[4].x, 1.2192);
+    }
+
+    #[test]
+    fn test_selection_group() {
+        let mut geometry = models::M2X4.geometry();
+        geometry.add_group("front",[4,5,6,7]).unwrap();
+
+        let mut item = AttributeItem::new(
+            Selection::group("front"),
+            Alteration::translate(Vector::new(1.0,0.0,0.0)));
+
+        item.update_magnitude(1.0);
+        item.revise(&mut geometry).unwrap();
+
+        fassert_eq!(geometry.vertices()[4].x, 2.2192);
+        fassert_eq!(geometry.vertices()[0].x, -1.2192);
+        assert_eq!(geometry.changes().ranges(),&[(4,8)]);
+    }
+
+    #[test]
+    fn test_selection_unknown_group() {
+        let mut geometry = models::M2X4.geometry();
+        let item = AttributeItem::new(
+            Selection::group("front"),
+            Alteration::translate(Vector::new(1.0,0.0,0.0)));
+
+        let result = item.revise(&mut geometry);
+        assert!(matches!(result,Err(Error::UnknownGroup(n)) if n == "front"));
+
+        // groups can't be found without a geometry
+        let result = item.apply(&mut geometry.vertices().clone());
+        assert!(matches!(result,Err(Error::UnresolvedSelection)));
+    }
+
+    #[test]
+    fn test_selection_within() {
+        let mut geometry = models::M2X4.geometry();
+
+        let mut item = AttributeItem::new(
+            Selection::within(Bounds::new(
+                Vertex::new(1.0,-1.0,-1.0),
+                Vertex::new(2.0,1.0,1.0))),
+            Alteration::translate(Vector::new(1.0,0.0,0.0)));
+
+        item.update_magnitude(1.0);
+        item.revise(&mut geometry).unwrap();
+
+        fassert_eq!(geometry.vertices()[7].x, 2.2192);
+        fassert_eq!(geometry.vertices()[3].x, -1.2192);
+        assert_eq!(geometry.changes().ranges(),&[(4,8)]);
+    }
+
+    #[test]
+    fn test_selection_within_turned() {
+        let vertices = (0..9)
+            .map(|i| Vertex::new((i % 3) as f64,(i / 3) as f64,0.0))
+            .collect::<Vec<Vertex>>();
+        let mut geometry = Geometry::new(vertices,vec![]);
+
+        let mut selection = Selection::within(Bounds::new(
+            Vertex::new(0.5,-0.5,-0.5),
+            Vertex::new(1.5,2.5,0.5)));
+        let before = selection.weights(&geometry).unwrap();
+
+        // the box around the turned box would pick up the corners
+        let matrix = Matrix::rotate_z(std::f64::consts::FRAC_PI_4);
+        selection.follow(&matrix,geometry.vertices());
+        geometry.transform(&matrix);
+        assert_eq!(selection.weights(&geometry).unwrap(),before);
+
+        // boxes that stay along the axes are moved as boxes
+        let mut selection = Selection::within(Bounds::new(
+            Vertex::new(0.5,-0.5,-0.5),
+            Vertex::new(1.5,2.5,0.5)));
+        selection.follow(&Matrix::translate(1.0,0.0,0.0),geometry.vertices());
+        assert!(matches!(selection,Selection::Within(_)));
+    }
+
+    #[test]
+    fn test_selection_connected_top() {
+        let geometry = models::M2X4.geometry();
+
+        // face 4 is one half of the top of the board
+        let selection = Selection::connected_from(4,0.1)
+            .resolve(&geometry)
+            .unwrap();
+
+        assert!(matches!(selection,Selection::Specific(v) if v == vec![1,2,5,6]));
+    }
+
+    #[test]
+    fn test_selection_connected_whole() {
+        let geometry = models::M2X4.geometry();
+
+        // every face of the box is within a right angle of its neighbours
+        let selection = Selection::connected_from(0,std::f64::consts::FRAC_PI_2 + 0.01)
+            .resolve(&geometry)
+            .unwrap();
+
+        assert!(matches!(selection,Selection::Specific(v) if v == (0..8).collect::<Vec<_>>()));
+    }
+
+    #[test]
+    fn test_selection_connected_invalid_face() {
+        let geometry = models::M2X4.geometry();
+        let result = Selection::connected_from(12,0.1).resolve(&geometry);
+        assert!(matches!(result,Err(Error::IndexOutOfRange { index: 12, len: 12 })));
+    }
+
+    #[test]
+    fn test_selection_along_surface() {
+        let geometry = models::M2X4.geometry();
+
+        // from the middle of one corner's edge, the two ends of that
+        // edge are close, and the opposite corner is right across
+        let corner = geometry.vertices()[0];
+        let other = geometry.vertices()[1];
+        let middle = (corner + other) * 0.5;
+        let selection = Selection::along_surface(middle,corner.distance(&other) / 2.0 + 1e-9)
+            .resolve(&geometry)
+            .unwrap();
+        assert!(matches!(selection,Selection::Specific(v) if v == vec![0,1]));
+
+        let everything = Selection::along_surface(middle,1e9).resolve(&geometry).unwrap();
+        assert!(matches!(everything,Selection::Specific(v) if v.len() == 8));
+        assert!(Selection::along_surface(middle,1.0).local(geometry.vertices()).is_err());
+    }
+
+    #[test]
+    fn test_selection_fit() {
+        let geometry = models::M2X4.geometry();
+        let vertices = geometry.vertices();
+
+        // the top of the board makes a datum plane
+        let top = Selection::connected_from(4,0.1);
+        let fit = top.fit_plane(&geometry).unwrap();
+        assert!(fit.residual < 1e-9);
+        for index in [1,2,5,6] {
+            assert_relative_eq!(fit.plane.distance(&vertices[index]),0.0,epsilon = 1e-9);
+        }
+
+        let edge = Selection::specific([1,2]).fit_line(&geometry).unwrap();
+        let direction = (vertices[2] - vertices[1]).normalize();
+        assert_relative_eq!(edge.direction.vector().dot(&direction).abs(),1.0,epsilon = 1e-9);
+        assert_relative_eq!(edge.length,vertices[1].distance(&vertices[2]),epsilon = 1e-9);
+
+        // a flat face isn't enough to fit a sphere or cylinder
+        assert!(matches!(top.fit_sphere(&geometry),Err(Error::InvalidFit(4))));
+        assert!(matches!(Selection::specific([1]).fit_cylinder(&geometry),Err(Error::InvalidFit(1))));
+        assert!(matches!(Selection::group("hole").fit_cylinder(&geometry),Err(Error::UnknownGroup(_))));
+    }
+
+    #[test]
+    fn test_selection_points_weighted() {
+        let geometry = Geometry::new(line(),vec![]);
+        let selection = Selection::falloff(Selection::specific([0]),2.0);
+        assert_eq!(selection.points(&geometry).unwrap(),line()[..2].to_vec());
+
+        let fit = selection.fit_line(&geometry).unwrap();
+        assert_relative_eq!(fit.direction.vector().x.abs(),1.0);
+        assert_relative_eq!(fit.length,1.0);
+    }
+
+    fn line() -> Vec<Vertex> {
+        (0..5).map(|i| Vertex::new(i as f64,0.0,0.0)).collect()
+    }
+
+    #[test]
+    fn test_selection_falloff_weights() {
+        let geometry = Geometry::new(line(),vec![]);
+        let selection = Selection::falloff(Selection::specific([0]),2.0);
+        let weights = selection.weights(&geometry).unwrap();
+
+        fassert_eq!(weights[0], 1.0);
+        fassert_eq!(weights[1], 0.5);
+        fassert_eq!(weights[2], 0.0);
+        fassert_eq!(weights[4], 0.0);
+    }
+
+    #[test]
+    fn test_selection_hard_weights() {
+        let geometry = Geometry::new(line(),vec![]);
+        let weights = Selection::range(1..3).weights(&geometry).unwrap();
+        assert_eq!(weights,vec![0.0,1.0,1.0,0.0,0.0]);
+    }
+
+    #[test]
+    fn test_selection_falloff_translate() {
+        let mut vertices = line();
+        let mut item = AttributeItem::new(
+            Selection::falloff(Selection::specific([0]),2.0),
+            Alteration::translate(Vector::new(0.0,1.0,0.0)));
+
+        item.update_magnitude(2.0);
+        item.apply(&mut vertices).unwrap();
+
+        fassert_eq!(vertices[0].y, 2.0);
+        fassert_eq!(vertices[1].y, 1.0);
+        fassert_eq!(vertices[2].y, 0.0);
+    }
+
+    #[test]
+    fn test_selection_falloff_scale() {
+        let mut vertices = line();
+        let mut item = AttributeItem::new(
+            Selection::falloff(Selection::specific([4]),2.0),
+            Alteration::scale(Vector::new(1.0,1.0,1.0)));
+
+        // partially weighted vertices scale part of the way
+        item.update_magnitude(2.0);
+        item.apply(&mut vertices).unwrap();
+
+        fassert_eq!(vertices[4].x, 8.0);
+        fassert_eq!(vertices[3].x, 4.5);
+        fassert_eq!(vertices[2].x, 2.0);
+    }
+
+    #[test]
+    fn test_attributeitem_weights() {
+        let mut geometry = Geometry::new(line(),vec![]);
+        geometry.add_group("end",[4]).unwrap();
+
+        let item = AttributeItem::new(
+            Selection::falloff(Selection::group("end"),2.0),
+            Alteration::translate(Vector::new(0.0,1.0,0.0)));
+
+        let weights = item.weights(&geometry).unwrap();
+        assert_eq!(weights,vec![0.0,0.0,0.0,0.5,1.0]);
+    }
+
+    #[test]
+    fn test_selection_cached() {
+        let mut geometry = models::M2X4.geometry();
+        geometry.add_group("front",[4,5,6,7]).unwrap();
+
+        let item = AttributeItem::new(
+            Selection::group("front"),
+            Alteration::translate(Vector::new(1.0,0.0,0.0)));
+
+        let a = item.resolve(&geometry).unwrap();
+        let b = item.resolve(&geometry).unwrap();
+        assert!(Arc::ptr_eq(&a,&b));
+
+        // clones carry the cache with them
+        let c = item.clone().resolve(&geometry).unwrap();
+        assert!(Arc::ptr_eq(&a,&c));
+
+        // editing the geometry invalidates the cache
+        geometry.add_group("front",[0,1,2,3]).unwrap();
+        let d = item.resolve(&geometry).unwrap();
+        assert!(!Arc::ptr_eq(&a,&d));
+        assert!(matches!(d.as_ref(),Selection::Specific(v) if v == &vec![0,1,2,3]));
+    }
+
+    #[test]
+    fn test_selection_ranges() {
+        let selection = Selection::specific([7,1,2,3,5,9]);
+        assert_eq!(selection.ranges(8),vec![(1,4),(5,6),(7,8)]);
+
+        let selection = Selection::range(2..6);
+        assert_eq!(selection.ranges(4),vec![(2,4)]);
+
+        let selection = Selection::all();
+        assert_eq!(selection.ranges(4),vec![(0,4)]);
+    }
+
+    #[test]
+    fn test_attribute_revise_marks_changes() {
+        let mut geometry = models::M2X4.geometry();
+
+        let mut length = Attribute::new("Length".into(),vec![
+            AttributeItem::translate_specific(Vector::new(1.0,0.0,0.0),vec![4,5,6,7]),
+        ]);
+
+        length.update(2.0);
+        length.revise(&mut geometry).unwrap();
+
+        assert_eq!(geometry.changes().ranges(),&[(4,8)]);
+    }
+
     #[test]
     fn test_attributeitem_scale_specific() {
 
@@ -271,7 +1561,7 @@ mod tests {
         // scale by a factor of 2.1
         item.update_magnitude(2.1);
 
-        item.apply(&mut vertices);
+        item.apply(&mut vertices).unwrap();
 
         fassert_eq!(vertices[0].x, 2.1);
         fassert_eq!(vertices[0].y, 2.1);
@@ -307,12 +1597,12 @@ mod tests {
         // scale in all directions
         let mut item = AttributeItem::scale_range(
             Vector::new(1.0,1.0,1.0),
-            0, 3
+            0..3
         );
 
         // scale by a factor of 2.1
         item.update_magnitude(2.1);
-        item.apply(&mut vertices);
+        item.apply(&mut vertices).unwrap();
 
         fassert_eq!(vertices[0].x, 2.1);
         fassert_eq!(vertices[0].y, 2.1);
@@ -351,7 +1641,7 @@ mod tests {
 
         // scale by a factor of 2.1
         item.update_magnitude(2.1);
-        item.apply(&mut vertices);
+        item.apply(&mut vertices).unwrap();
 
         fassert_eq!(vertices[0].x, 2.1);
         fassert_eq!(vertices[0].y, 2.1);
@@ -408,7 +1698,7 @@ mod tests {
 
         // rotate by 2.1 radians
         item.update_magnitude(2.1);
-        item.apply(&mut vertices);
+        item.apply(&mut vertices).unwrap();
 
         // verified by https://matrixcalc.org
         fassert_eq!(vertices[0].x, 1.5538668421853181);
@@ -441,12 +1731,12 @@ mod tests {
         // rotate in all directions
         let mut item = AttributeItem::rotate_range(
             Vector::new(1.0,1.0,1.0),
-            0,3
+            0..3
         );
 
         // rotate by 2.1 radians
         item.update_magnitude(2.1);
-        item.apply(&mut vertices);
+        item.apply(&mut vertices).unwrap();
 
         // verified by https://matrixcalc.org
         fassert_eq!(vertices[0].x, 1.5538668421853181);
@@ -482,7 +1772,7 @@ mod tests {
 
         // rotate by 2.1 radians
         item.update_magnitude(2.1);
-        item.apply(&mut vertices);
+        item.apply(&mut vertices).unwrap();
 
         // verified by https://matrixcalc.org
         fassert_eq!(vertices[0].x, 1.5538668421853181);
@@ -541,7 +1831,7 @@ mod tests {
 
         // translate by 2.1
         item.update_magnitude(2.1);
-        item.apply(&mut vertices);
+        item.apply(&mut vertices).unwrap();
 
         fassert_eq!(vertices[0].x, 3.1);
         fassert_eq!(vertices[0].y, 3.1);
@@ -577,12 +1867,12 @@ mod tests {
         // translate in all directions
         let mut item = AttributeItem::translate_range(
             Vector::new(1.0,1.0,1.0),
-            0,3
+            0..3
         );
 
         // translate by 2.1
         item.update_magnitude(2.1);
-        item.apply(&mut vertices);
+        item.apply(&mut vertices).unwrap();
 
         fassert_eq!(vertices[0].x, 3.1);
         fassert_eq!(vertices[0].y, 3.1);
@@ -621,7 +1911,7 @@ mod tests {
 
         // translate by 2.1
         item.update_magnitude(2.1);
-        item.apply(&mut vertices);
+        item.apply(&mut vertices).unwrap();
 
         fassert_eq!(vertices[0].x, 3.1);
         fassert_eq!(vertices[0].y, 3.1);
@@ -657,4 +1947,193 @@ mod tests {
 
 
     }
+
+    #[test]
+    fn test_attribute_logs_alterations() {
+        let mut geometry = models::M2X4.geometry();
+        let mut attribute = Attribute::new("Length".into(),vec![
+            AttributeItem::translate_range(Vector::new(1.0,0.0,0.0),4..8)
+        ]);
+
+        let logs = capture(|| {
+            attribute.update(1.0);
+            attribute.revise(&mut geometry).unwrap();
+        });
+
+        assert!(warnings(&logs).is_empty());
+        assert!(logs.iter().any(|(_,m)| m == "attribute 'Length' changed from 0 to 1"));
+        assert!(logs.iter().any(|(_,m)| m.starts_with("Translate by") && m.ends_with("applied to 4 vertices")));
+    }
+
+    #[test]
+    fn test_attribute_logs_suspicious() {
+        let mut geometry = models::M2X4.geometry();
+        let mut attribute = Attribute::new("Squash".into(),vec![
+            AttributeItem::scale_all(Vector::new(1.0,0.0,1.0)),
+            AttributeItem::translate_specific(Vector::new(1.0,0.0,0.0),vec![]),
+        ]);
+        attribute.update(1.0);
+
+        let logs = capture(|| attribute.revise(&mut geometry).unwrap());
+        let warnings = warnings(&logs);
+
+        assert_eq!(warnings.len(),3);
+        assert!(warnings.iter().any(|m| m.contains("flatten 8 vertices")));
+        assert!(warnings.iter().any(|m| m.contains("specific selection doesn't contain")));
+        assert!(warnings.iter().any(|m| m.contains("selects no vertices")));
+    }
+
+    #[test]
+    fn test_attribute_identity_skips_geometry() {
+        let mut geometry = models::M2X4.geometry();
+        geometry.clear_changes();
+        let revision = geometry.revision();
+
+        // a value of zero doesn't translate anything
+        let attribute = Attribute::new("Length".into(),vec![
+            AttributeItem::translate_all(Vector::new(1.0,0.0,0.0))
+        ]);
+        attribute.revise(&mut geometry).unwrap();
+
+        assert_eq!(geometry.revision(),revision);
+        assert!(geometry.changes().is_empty());
+
+        // but bad selections are still reported
+        let attribute = Attribute::new("Length".into(),vec![
+            AttributeItem::translate_specific(Vector::new(1.0,0.0,0.0),vec![9])
+        ]);
+        assert!(attribute.revise(&mut geometry).is_err());
+    }
+
+    #[test]
+    fn test_attribute_scale_relative_unset() {
+        let mut geometry = models::M2X4.geometry();
+        let mut attribute = Attribute::new("Width".into(),vec![
+            AttributeItem::scale_relative_all(Vector::new(0.0,1.0,0.0))
+        ]);
+
+        // an attribute that was never set leaves the geometry alone
+        attribute.revise(&mut geometry).unwrap();
+        fassert_eq!(geometry.vertices()[2].y, 0.04445);
+
+        attribute.update(1.0);
+        attribute.revise(&mut geometry).unwrap();
+        fassert_eq!(geometry.vertices()[2].y, 0.0889);
+    }
+
+    #[test]
+    fn test_selection_string_round_trip() {
+        let selections = vec![
+            Selection::all(),
+            Selection::range(2..6),
+            Selection::range(4..),
+            Selection::specific(vec![1,2,5]),
+            Selection::specific(vec![]),
+            Selection::group("front"),
+            Selection::within(Bounds::new(Vertex::new(-1.0,0.0,0.5),Vertex::new(1.0,2.0,3.5))),
+            Selection::connected_from(3,0.25),
+            Selection::along_surface(Vertex::new(0.5,1.0,-2.0),0.1),
+            Selection::Weighted(vec![(0,1.0),(3,0.5)]),
+            Selection::Weighted(vec![]),
+            Selection::falloff(Selection::group("front"),0.5),
+            Selection::group("label top"),
+            Selection::falloff(Selection::group("a~b"),0.5),
+        ];
+
+        for selection in selections {
+            let text = String::from(&selection);
+            let result = Selection::try_from(text.as_str()).unwrap();
+            assert_eq!(String::from(&result),text);
+        }
+
+        assert!(Selection::try_from("b:1,2").is_err());
+        assert!(Selection::try_from("1,x").is_err());
+        assert!(Selection::try_from("c:1").is_err());
+        assert!(Selection::try_from("s:1,2,3").is_err());
+    }
+
+    #[test]
+    fn test_attribute_parse_lines() {
+        let text = "\
+            v 1 2 3\n\
+            a Length translate 4:8 1 0 0\n\
+            a Width scale 0:4 1 2 1\n\
+            a Length = 2\n\
+            a Length translate 0:4 -1 0 0";
+
+        let attributes = Attribute::parse_lines(text).unwrap();
+        assert_eq!(attributes.len(),2);
+        assert_eq!(attributes[0].name(),"Length");
+        assert_eq!(attributes[0].items().len(),2);
+        fassert_eq!(attributes[0].value(), 2.0);
+        fassert_eq!(attributes[0].items()[1].alteration().magnitude(), 2.0);
+        fassert_eq!(attributes[1].value(), 0.0);
+
+        let text = String::from(&attributes[0]);
+        assert_eq!(text,"\
+            a Length = 2\n\
+            a Length translate 4:8 1 0 0\n\
+            a Length translate 0:4 -1 0 0");
+
+        assert!(Attribute::parse_lines("a Length").is_err());
+        assert!(Attribute::parse_lines("a Length = x").is_err());
+    }
+
+    #[test]
+    fn test_attribute_quoted_name() {
+        let mut attribute = Attribute::new("Wall Thickness".into(),vec![
+            AttributeItem::new(Selection::group("label top"),Alteration::translate(Vector::new(0.0,0.0,1.0)))
+        ]);
+        attribute.update(0.25);
+
+        let text = String::from(&attribute);
+        assert_eq!(text,"\
+            a \"Wall Thickness\" = 0.25\n\
+            a \"Wall Thickness\" translate g:\"label top\" 0 0 1");
+
+        let attributes = Attribute::parse_lines(&text).unwrap();
+        assert_eq!(attributes[0].name(),"Wall Thickness");
+        assert!(matches!(attributes[0].items()[0].selection(),Selection::Group(g) if g == "label top"));
+        assert_eq!(String::from(&attributes[0]),text);
+        assert!(Attribute::parse_lines("a \"Wall Thickness = 1").is_err());
+    }
+
+    #[test]
+    fn test_attributeitem_space() {
+        use std::f64::consts::FRAC_PI_2;
+
+        let text = "\
+            a Swing = 1.5707963267948966\n\
+            a Swing rotate@0.5,0,-1 * 0 0 1\n\
+            a Slide = 1\n\
+            a Slide translate@world 4:8 1 0 0";
+
+        let attributes = Attribute::parse_lines(text).unwrap();
+        let (swing,slide) = (&attributes[0],&attributes[1]);
+        assert_eq!(swing.items()[0].alteration().space(),Space::Pivot(Vertex::new(0.5,0.0,-1.0)));
+        assert_eq!(slide.items()[0].alteration().space(),Space::World);
+        assert!(!swing.is_world());
+        assert!(slide.is_world());
+        assert_eq!(format!("{}\n{}",String::from(swing),String::from(slide)),text);
+        assert!(Attribute::parse_lines("a Swing rotate@1,2 * 0 0 1").is_err());
+
+        // a quarter turn around the pivot rather than the origin
+        let mut data = vec![Vertex::new(1.5,0.0,-1.0)];
+        swing.apply(&mut data).unwrap();
+        assert_relative_eq!(data[0].x,0.5,epsilon = 1e-12);
+        assert_relative_eq!(data[0].y,1.0,epsilon = 1e-12);
+
+        // a part turned a quarter to the left moves along its own -Y
+        // to go along X in the assembly
+        let mut data = vec![Vertex::default();8];
+        slide.framed(Matrix::rotate_z(FRAC_PI_2)).apply(&mut data).unwrap();
+        assert_relative_eq!(data[4].x,0.0,epsilon = 1e-12);
+        assert_relative_eq!(data[4].y,-1.0,epsilon = 1e-12);
+
+        // without a frame it's the same as local
+        let mut data = vec![Vertex::default();8];
+        slide.apply(&mut data).unwrap();
+        assert_relative_eq!(data[4].x,1.0,epsilon = 1e-12);
+    }
+
 }
@@ -0,0 +1,149 @@
+use std::collections::{BTreeMap,HashMap};
+
+use crate::geometry::Matrix;
+use crate::part::Part;
+
+/// A part in a catalog with the same shape as another one
+#[derive(Debug,Clone)]
+pub struct Duplicate {
+    /// the part it matches, which comes first by name
+    pub original: String,
+    /// the part with the same shape
+    pub duplicate: String,
+    /// moves the duplicate onto the original
+    pub transform: Matrix,
+}
+
+/// A library of parts looked up by name
+#[derive(Default,Debug,Clone)]
+pub struct Catalog {
+    parts: BTreeMap<String,Part>,
+}
+
+impl Catalog {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_part(mut self, part: Part) -> Self {
+        self.add(part);
+        self
+    }
+
+    // Adds a part, returning any it replaced with the same name
+    pub fn add(&mut self, part: Part) -> Option<Part> {
+        self.parts.insert(part.name().into(),part)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Part> {
+        self.parts.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Part> {
+        self.parts.get(name)
+    }
+
+    pub fn parts(&self) -> impl Iterator<Item = &Part> {
+        self.parts.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.parts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    // Adds every part from another catalog, replacing any with
+    // the same name
+    pub fn extend(&mut self, other: Catalog) {
+        self.parts.extend(other.parts);
+    }
+
+    // Parts with the same evaluated geometry as another part within
+    // `tolerance`, after moving and turning but not mirroring it, so
+    // they can be merged or drawn as instances of one part. Parts are
+    // hashed by their vertex and face counts first so that only ones
+    // that could match are compared, and each duplicate is reported
+    // against the first part by name that it matches.
+    pub fn find_duplicates(&self, tolerance: f64) -> Vec<Duplicate> {
+        span!("catalog.duplicates", parts = self.parts.len());
+        let mut buckets: HashMap<(usize,usize),Vec<&Part>> = HashMap::new();
+        let mut result = Vec::new();
+
+        for part in self.parts.values() {
+            let geometry = part.geometry();
+            if geometry.is_empty() {
+                continue;
+            }
+
+            let originals = buckets
+                .entry((geometry.vertices().len(),geometry.size()))
+                .or_default();
+
+            let found = originals
+                .iter()
+                .find_map(|o| geometry
+                    .congruent_to(o.geometry(),tolerance)
+                    .map(|m| (o.name().to_string(),m)));
+
+            match found {
+                Some((original,transform)) => result.push(Duplicate {
+                    original,
+                    duplicate: part.name().into(),
+                    transform,
+                }),
+                None => originals.push(part),
+            }
+        }
+
+        result
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::models::{Lumber,SheetGoods};
+    use crate::geometry::{Geometry,Transform};
+
+    fn moved(part: Part, name: &str, matrix: Matrix) -> Part {
+        let mut geometry = part.geometry().clone();
+        geometry.transform(&matrix);
+        Part::new(name).with_geometry(geometry)
+    }
+
+    #[test]
+    fn test_catalog_find_duplicates() {
+        let turn = Matrix::translate(3.0,-1.0,0.5) * Matrix::rotate(0.3,1.1,-0.7);
+        let catalog = Catalog::new()
+            .with_part(Lumber::TwoByFour.part())
+            .with_part(moved(Lumber::TwoByFour.part(),"stud",turn))
+            .with_part(moved(Lumber::TwoBySix.part(),"joist",Matrix::translate(0.0,0.0,1.0)))
+            .with_part(SheetGoods::Plywood.part())
+            .with_part(Part::new("empty"));
+        assert_eq!(catalog.len(),5);
+
+        let duplicates = catalog.find_duplicates(1e-6);
+        assert_eq!(duplicates.len(),1);
+        assert_eq!(duplicates[0].original,"2x4");
+        assert_eq!(duplicates[0].duplicate,"stud");
+
+        // the transform puts the copy back where the original is,
+        // though maybe turned end for end
+        let mut geometry: Geometry = catalog.get("stud").unwrap().geometry().clone();
+        geometry.transform(&duplicates[0].transform);
+        let original = catalog.get("2x4").unwrap().geometry();
+        for a in geometry.vertices() {
+            assert!(original.vertices().iter().any(|b| a.distance(b) < 1e-6));
+        }
+
+        // a rougher tolerance doesn't make different boards match
+        assert_eq!(catalog.find_duplicates(1e-3).len(),1);
+    }
+
+}
@@ -0,0 +1,145 @@
+use std::convert::TryFrom;
+
+use crate::errors::Error;
+
+/// An RGBA color with 8 bits per channel, in sRGB
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq,Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+
+    // An opaque color
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 255 }
+    }
+
+    pub const fn with_alpha(mut self, a: u8) -> Self {
+        self.a = a;
+        self
+    }
+
+    pub fn rgba(&self) -> [u8;4] {
+        [self.r,self.g,self.b,self.a]
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        self.a == 255
+    }
+
+    // Channels from 0 to 1 with the color in linear space,
+    // which is what glTF expects.
+    pub fn linear(&self) -> [f64;4] {
+        let convert = |c: u8| {
+            let c = c as f64 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        [convert(self.r),convert(self.g),convert(self.b),self.a as f64 / 255.0]
+    }
+
+    // A color from blue at 0 through green at a half to red at 1,
+    // for showing values like distances on a surface
+    pub fn ramp(value: f64) -> Self {
+        let value = if value.is_nan() { 0.5 } else { value.clamp(0.0,1.0) };
+        let byte = |v: f64| (v * 255.0).round() as u8;
+        match value < 0.5 {
+            true => Self::new(0,byte(value * 2.0),byte(1.0 - value * 2.0)),
+            false => Self::new(byte(value * 2.0 - 1.0),byte(2.0 - value * 2.0),0),
+        }
+    }
+
+    // Five bits each of blue, green and red from the lowest bit up,
+    // with the top bit set to mark the color as valid.
+    pub fn rgb555(&self) -> u16 {
+        let five = |c: u8| (c >> 3) as u16;
+        0x8000 | (five(self.r) << 10) | (five(self.g) << 5) | five(self.b)
+    }
+
+}
+
+impl From<Color> for [u8;4] {
+    fn from(color: Color) -> Self {
+        color.rgba()
+    }
+}
+
+// Colors are written as `#rrggbb`, or `#rrggbbaa` if they
+// aren't opaque.
+impl From<&Color> for String {
+    fn from(color: &Color) -> Self {
+        match color.is_opaque() {
+            true => format!("#{:02x}{:02x}{:02x}",color.r,color.g,color.b),
+            false => format!("#{:02x}{:02x}{:02x}{:02x}",color.r,color.g,color.b,color.a),
+        }
+    }
+}
+
+impl TryFrom<&str> for Color {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let hex = value
+            .trim()
+            .strip_prefix('#')
+            .filter(|h| h.is_ascii() && (h.len() == 6 || h.len() == 8))
+            .ok_or(Error::ParseError)?;
+
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2],16);
+        let color = Color::new(channel(0)?,channel(2)?,channel(4)?);
+        match hex.len() {
+            8 => Ok(color.with_alpha(channel(6)?)),
+            _ => Ok(color),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_color_string_round_trip() {
+        for text in ["#ff8000","#00000080","#1a2b3c"] {
+            let color = Color::try_from(text).unwrap();
+            assert_eq!(String::from(&color),text);
+        }
+
+        assert_eq!(Color::try_from("#ff8000").unwrap(),Color::new(255,128,0));
+        assert!(Color::try_from("ff8000").is_err());
+        assert!(Color::try_from("#ff80").is_err());
+        assert!(Color::try_from("#gg8000").is_err());
+    }
+
+    #[test]
+    fn test_color_conversions() {
+        let color = Color::new(255,128,0).with_alpha(51);
+        assert_eq!(color.rgb555(),0x8000 | (31 << 10) | (16 << 5));
+        assert_eq!(<[u8;4]>::from(color),[255,128,0,51]);
+
+        let [r,g,b,a] = color.linear();
+        assert_eq!(r,1.0);
+        assert_relative_eq!(g,0.2158605,epsilon = 1e-6);
+        assert_eq!(b,0.0);
+        assert_relative_eq!(a,0.2);
+    }
+
+    #[test]
+    fn test_color_ramp() {
+        assert_eq!(Color::ramp(0.0),Color::new(0,0,255));
+        assert_eq!(Color::ramp(0.5),Color::new(0,255,0));
+        assert_eq!(Color::ramp(1.0),Color::new(255,0,0));
+        assert_eq!(Color::ramp(0.25),Color::new(0,128,128));
+        assert_eq!(Color::ramp(-3.0),Color::ramp(0.0));
+        assert_eq!(Color::ramp(f64::NAN),Color::ramp(0.5));
+    }
+
+}
\ No newline at end of file
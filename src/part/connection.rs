@@ -1,5 +1,204 @@
+use std::convert::TryFrom;
+
+use crate::geometry::{Vertex,Direction,Matrix,Transform};
+use crate::part::{Joint,Interface,Shape,Gender};
+use crate::errors::Error;
+use crate::constant::CONNECTION_TAG;
+use crate::utilities;
 
 #[derive(Default,Debug,Clone)]
 pub struct Connection {
+    point: Vertex,
+    radius: f64,
+    tolerance: f64,
+    joint: Joint,
+    interface: Option<Interface>,
+}
+
+impl Connection {
+
+    pub fn new(point: Vertex, radius: f64) -> Self {
+        Self { point, radius, tolerance: 0.0, joint: Joint::Fixed, interface: None }
+    }
+
+    // How far either way the connection can be from its point
+    // once it's made, in meters
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance.abs();
+        self
+    }
+
+    // How the part moves around the connection once it's made
+    pub fn with_joint(mut self, joint: Joint) -> Self {
+        self.joint = joint;
+        self
+    }
+
+    // The kind of connector, which connections it's mated with
+    // have to fit
+    pub fn with_interface(mut self, interface: Interface) -> Self {
+        self.interface = Some(interface);
+        self
+    }
+
+    pub fn point(&self) -> Vertex {
+        self.point
+    }
+
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    pub fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+
+    pub fn joint(&self) -> &Joint {
+        &self.joint
+    }
+
+    pub fn interface(&self) -> Option<&Interface> {
+        self.interface.as_ref()
+    }
+
+    // Checks that the connectors fit, with sizes allowed to be off
+    // by the larger tolerance. Connections without an interface
+    // can be mated with anything.
+    pub fn mates(&self, other: &Connection) -> Result<(),Error> {
+        match (&self.interface,&other.interface) {
+            (Some(a),Some(b)) => a.check(b,self.tolerance.max(other.tolerance)),
+            _ => Ok(()),
+        }
+    }
+
+}
+
+// Moves the connection along with the part's geometry
+impl Transform for Connection {
+    fn transform(&mut self, matrix: &Matrix) {
+        self.point.transform(matrix);
+        self.joint.transform(matrix);
+    }
+}
+
+// A connection is written on one line as its point, radius,
+// tolerance and joint, followed by its interface if it has one:
+//
+//   connection <x> <y> <z> <radius> <tolerance> fixed|revolute:<axis>,<min>,<max>|prismatic:<axis>,<min>,<max> [<shape> <size> <gender> [<standard>]]
+impl From<&Connection> for String {
+    fn from(connection: &Connection) -> Self {
+        let p = connection.point;
+        let joint = match connection.joint {
+            Joint::Fixed => "fixed".into(),
+            Joint::Revolute { axis, min, max } => format!("revolute:{},{},{},{},{}",axis.x,axis.y,axis.z,min,max),
+            Joint::Prismatic { axis, min, max } => format!("prismatic:{},{},{},{},{}",axis.x,axis.y,axis.z,min,max),
+        };
+
+        let mut result = format!("{} {} {} {} {} {} {}",CONNECTION_TAG,p.x,p.y,p.z,connection.radius,connection.tolerance,joint);
+        if let Some(interface) = &connection.interface {
+            let gender = match interface.gender() {
+                Gender::Male => "male",
+                Gender::Female => "female",
+                Gender::Neutral => "neutral",
+            };
+            result.push_str(&format!(" {} {} {}",interface.shape().name(),interface.size(),gender));
+            if let Some(standard) = interface.standard() {
+                result.push(' ');
+                result.push_str(&utilities::quote(standard,&[]));
+            }
+        }
+        result
+    }
+}
+
+impl TryFrom<&str> for Connection {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let value = value
+            .trim()
+            .strip_prefix(CONNECTION_TAG)
+            .filter(|r| r.starts_with(' '))
+            .ok_or(Error::ParseError)?;
+
+        let mut fields = value.split_whitespace();
+        let mut next = || fields.next().ok_or(Error::ParseError);
+        let point = Vertex::new(next()?.parse()?,next()?.parse()?,next()?.parse()?);
+        let (radius,tolerance) = (next()?.parse()?,next()?.parse()?);
+
+        let joint = next()?;
+        let joint = match joint.split_once(':').map(|(k,v)| (k,utilities::list::<f64>(v))) {
+            None if joint == "fixed" => Joint::Fixed,
+            Some(("revolute",Ok(v))) if v.len() == 5 => Joint::Revolute {
+                axis: Direction::new(v[0],v[1],v[2]), min: v[3], max: v[4]
+            },
+            Some(("prismatic",Ok(v))) if v.len() == 5 => Joint::Prismatic {
+                axis: Direction::new(v[0],v[1],v[2]), min: v[3], max: v[4]
+            },
+            _ => return Err(Error::ParseError),
+        };
+
+        // the standard is last, since it's a name that can be quoted
+        let interface = match fields.next() {
+            None => None,
+            Some(shape) => {
+                let shape = [Shape::Round,Shape::Square,Shape::Hex,Shape::Flat]
+                    .into_iter()
+                    .find(|s| s.name() == shape)
+                    .ok_or(Error::ParseError)?;
+                let size = fields.next().ok_or(Error::ParseError)?.parse()?;
+                let gender = match fields.next() {
+                    Some("male") => Gender::Male,
+                    Some("female") => Gender::Female,
+                    Some("neutral") => Gender::Neutral,
+                    _ => return Err(Error::ParseError),
+                };
+                let interface = Interface::new(shape,size,gender);
+                let rest = fields.collect::<Vec<&str>>().join(" ");
+                match rest.is_empty() {
+                    true => Some(interface),
+                    false => match utilities::unquote(&rest)? {
+                        (standard,"") => Some(interface.with_standard(standard)),
+                        _ => return Err(Error::ParseError),
+                    },
+                }
+            },
+        };
+
+        Ok(Self { point, radius, tolerance, joint, interface })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_connection_string() {
+        let connections = [
+            Connection::new(Vertex::new(0.1,-2.0,1e-9),0.004),
+            Connection::new(Vertex::new(1.0,0.0,0.0),0.01905)
+                .with_tolerance(0.001)
+                .with_joint(Joint::revolute(Direction::new(0.0,1.0,1.0),-0.5,1.0)),
+            Connection::new(Vertex::new(0.0,0.0,0.5),0.004)
+                .with_joint(Joint::prismatic(Direction::new(1.0,0.0,0.0),0.0,0.3))
+                .with_interface(Interface::bolt(0.008).with_standard("M8 x 1.25")),
+            Connection::new(Vertex::new(0.0,0.0,0.5),0.004)
+                .with_interface(Interface::new(Shape::Flat,0.0,Gender::Neutral)),
+        ];
+        for connection in connections.iter() {
+            let text = String::from(connection);
+            let parsed = Connection::try_from(text.as_str()).unwrap();
+            assert_eq!(String::from(&parsed),text);
+            assert_eq!(parsed.joint(),connection.joint());
+            assert_eq!(parsed.interface(),connection.interface());
+        }
+        assert_eq!(String::from(&connections[2]),"connection 0 0 0.5 0.004 0 prismatic:1,0,0,0,0.3 round 0.008 male \"M8 x 1.25\"");
+
+        assert!(Connection::try_from("connection 0 0 0 0.1 0 hinge").is_err());
+        assert!(Connection::try_from("connection 0 0 0 0.1 0 fixed round 0.008").is_err());
+        assert!(Connection::try_from("connection 0 0 0 0.1 0 fixed round 0.008 male M8 x").is_err());
+    }
 
-}
\ No newline at end of file
+}
@@ -0,0 +1,325 @@
+use std::fmt;
+use std::convert::TryFrom;
+use std::collections::BTreeMap;
+
+use crate::geometry::Matrix;
+use crate::errors::Error;
+use crate::constant::TOLERANCE;
+
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Units {
+    #[default]
+    Meters,
+    Centimeters,
+    Millimeters,
+    Inches,
+    Feet,
+}
+
+/// Everything besides the geometry that affects the result of
+/// evaluating a part or assembly. Evaluating the same geometry
+/// with the same context always gives the same result.
+#[derive(Debug,Clone,PartialEq)]
+pub struct EvalContext {
+    parameters: BTreeMap<String,f64>,
+    tolerance: f64,
+    units: Units,
+    seed: u64,
+    allow_collapse: bool,
+    frame: Option<Matrix>,
+}
+
+impl Units {
+
+    // How many of these units make up one meter
+    pub fn per_meter(&self) -> f64 {
+        match self {
+            Units::Meters => 1.0,
+            Units::Centimeters => 100.0,
+            Units::Millimeters => 1000.0,
+            Units::Inches => 1.0 / 0.0254,
+            Units::Feet => 1.0 / 0.3048,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Units::Meters => "m",
+            Units::Centimeters => "cm",
+            Units::Millimeters => "mm",
+            Units::Inches => "in",
+            Units::Feet => "ft",
+        }
+    }
+
+}
+
+impl TryFrom<&str> for Units {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "m" => Ok(Units::Meters),
+            "cm" => Ok(Units::Centimeters),
+            "mm" => Ok(Units::Millimeters),
+            "in" => Ok(Units::Inches),
+            "ft" => Ok(Units::Feet),
+            _ => Err(Error::ParseError),
+        }
+    }
+}
+
+impl Default for EvalContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EvalContext {
+
+    pub fn new() -> Self {
+        Self {
+            parameters: BTreeMap::new(),
+            tolerance: TOLERANCE,
+            units: Units::Meters,
+            seed: 0,
+            allow_collapse: true,
+            frame: None,
+        }
+    }
+
+    pub fn with_parameter<T: Into<String>>(mut self, name: T, value: f64) -> Self {
+        self.parameters.insert(name.into(),value);
+        self
+    }
+
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn with_units(mut self, units: Units) -> Self {
+        self.units = units;
+        self
+    }
+
+    // The seed that noise in displacements and the jitter of patterns
+    // are mixed with, through `seed_for` their names
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    // Whether attributes may scale geometry by zero. Collapsing is
+    // always logged as a warning, but can be made an error instead.
+    pub fn with_allow_collapse(mut self, allow: bool) -> Self {
+        self.allow_collapse = allow;
+        self
+    }
+
+    // The transform of the instance a part is being evaluated for,
+    // which alterations in world space are made through
+    pub fn with_frame(mut self, frame: Matrix) -> Self {
+        self.frame = Some(frame);
+        self
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<f64> {
+        self.parameters.get(name).copied()
+    }
+
+    pub fn parameters(&self) -> &BTreeMap<String,f64> {
+        &self.parameters
+    }
+
+    pub fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+
+    pub fn units(&self) -> Units {
+        self.units
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn allow_collapse(&self) -> bool {
+        self.allow_collapse
+    }
+
+    pub fn frame(&self) -> Option<&Matrix> {
+        self.frame.as_ref()
+    }
+
+    // A seed for one named consumer (a part, an attribute) so that
+    // adding a consumer doesn't change the randomness of the others.
+    pub fn seed_for(&self, name: &str) -> u64 {
+        let mut hash = fnv(&self.seed.to_le_bytes(),FNV_OFFSET);
+        hash = fnv(name.as_bytes(),hash);
+        hash
+    }
+
+    // The context seen by an assembly instance: parameters named
+    // `<prefix>.<name>` override the plain `<name>` ones.
+    pub fn scoped(&self, prefix: &str) -> Self {
+        let mut context = self.clone();
+        let start = format!("{}.",prefix);
+        for (name,value) in self.parameters.iter() {
+            if let Some(name) = name.strip_prefix(&start) {
+                context.parameters.insert(name.into(),*value);
+            }
+        }
+        context.seed = self.seed_for(prefix);
+        context
+    }
+
+    // A stable hash of the whole context, for caching build results
+    // across runs.
+    pub fn key(&self) -> u64 {
+        fnv(String::from(self).as_bytes(),FNV_OFFSET)
+    }
+
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+fn fnv(data: &[u8], hash: u64) -> u64 {
+    data.iter().fold(hash,|h,b| (h ^ *b as u64).wrapping_mul(FNV_PRIME))
+}
+
+impl From<&EvalContext> for String {
+    fn from(context: &EvalContext) -> Self {
+        let mut result = format!(
+            "seed {}\ntolerance {}\nunits {}\nallow_collapse {}",
+            context.seed,
+            context.tolerance,
+            context.units.symbol(),
+            context.allow_collapse);
+
+        if let Some(frame) = &context.frame {
+            let values = frame.unpack().map(|v| v.to_string());
+            result.push_str(&format!("\nframe {}",values.join(" ")));
+        }
+
+        for (name,value) in context.parameters.iter() {
+            result.push_str(&format!("\nparameter {} {}",name,value));
+        }
+
+        result
+    }
+}
+
+impl fmt::Display for EvalContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", String::from(self))
+    }
+}
+
+impl TryFrom<&str> for EvalContext {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut context = EvalContext::new();
+
+        for line in value.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let (key,rest) = line
+                .split_once(' ')
+                .ok_or(Error::ParseError)?;
+
+            match key {
+                "seed" => context.seed = rest.trim().parse()?,
+                "tolerance" => context.tolerance = rest.trim().parse()?,
+                "units" => context.units = Units::try_from(rest.trim())?,
+                "allow_collapse" => context.allow_collapse = rest
+                    .trim()
+                    .parse()
+                    .or(Err(Error::ParseError))?,
+                "frame" => {
+                    let values = rest
+                        .split_whitespace()
+                        .map(str::parse)
+                        .collect::<Result<Vec<f64>,_>>()?;
+                    let values: [f64;16] = values
+                        .try_into()
+                        .or(Err(Error::ParseError))?;
+                    context.frame = Some(Matrix::new(values));
+                },
+                "parameter" => {
+                    // names may contain spaces, the value is last
+                    let (name,value) = rest
+                        .trim()
+                        .rsplit_once(' ')
+                        .ok_or(Error::ParseError)?;
+                    context.parameters.insert(name.trim().into(),value.parse()?);
+                },
+                _ => return Err(Error::ParseError),
+            }
+        }
+
+        Ok(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn context() -> EvalContext {
+        EvalContext::new()
+            .with_seed(7)
+            .with_units(Units::Millimeters)
+            .with_parameter("Length",2.5)
+            .with_parameter("left.Length",1.5)
+            .with_parameter("Board Width",0.1)
+            .with_allow_collapse(false)
+            .with_frame(Matrix::translate(1.0,-2.0,0.5) * Matrix::rotate_z(0.25))
+    }
+
+    #[test]
+    fn test_context_round_trip() {
+        let context = context();
+        let text = String::from(&context);
+        let result = EvalContext::try_from(text.as_str()).unwrap();
+
+        assert_eq!(result,context);
+        assert_eq!(result.key(),context.key());
+        assert_eq!(result.parameter("Board Width"),Some(0.1));
+    }
+
+    #[test]
+    fn test_context_parse_invalid() {
+        assert!(EvalContext::try_from("units furlongs").is_err());
+        assert!(EvalContext::try_from("seed").is_err());
+        assert!(EvalContext::try_from("colour red").is_err());
+    }
+
+    #[test]
+    fn test_context_scoped() {
+        let context = context();
+        let left = context.scoped("left");
+        let right = context.scoped("right");
+
+        assert_eq!(left.parameter("Length"),Some(1.5));
+        assert_eq!(right.parameter("Length"),Some(2.5));
+        assert_ne!(left.seed(),right.seed());
+        assert_eq!(left.seed(),context.scoped("left").seed());
+    }
+
+    #[test]
+    fn test_context_key_changes() {
+        let a = context();
+        let b = context().with_parameter("Length",2.6);
+        assert_ne!(a.key(),b.key());
+        assert_ne!(a.seed_for("a"),a.seed_for("b"));
+    }
+
+    #[test]
+    fn test_units_per_meter() {
+        assert_relative_eq!(Units::Inches.per_meter() * 0.0254,1.0);
+        assert_eq!(Units::try_from("cm").unwrap(),Units::Centimeters);
+    }
+
+}
@@ -0,0 +1,331 @@
+use crate::part::{Part,Attribute,Connection,Metadata,Feature};
+use crate::geometry::Geometry;
+use crate::constant::{Index,TOLERANCE};
+
+/// One way that a part differs from another
+#[derive(Debug,Clone,PartialEq)]
+pub enum Change {
+    /// the part was renamed
+    Name { from: String, to: String },
+    /// an attribute that only the new part has
+    AddAttribute(String),
+    /// an attribute that only the old part has
+    RemoveAttribute(String),
+    /// an attribute set to a different value
+    Value { attribute: String, from: f64, to: f64 },
+    /// an attribute that moves the geometry differently, by
+    /// selecting other vertices or moving them another way
+    Items(String),
+    /// a feature that only the new part has
+    AddFeature(String),
+    /// a feature that only the old part has
+    RemoveFeature(String),
+    /// a feature that's made differently, moved to another place in
+    /// the history, or suppressed or put back
+    Feature(String),
+    /// a connection that only the new part has
+    AddConnection(Index),
+    /// a connection that only the old part has
+    RemoveConnection(Index),
+    /// a connection that moved or works differently
+    Connection(Index),
+    /// a piece of metadata that changed, as it's written, with None
+    /// where it isn't set. Tags are added and removed one at a time.
+    Metadata { field: String, from: Option<String>, to: Option<String> },
+    /// a different number of vertices or faces in the base geometry
+    Size { vertices: (usize,usize), faces: (usize,usize) },
+    /// vertices of the base geometry that moved, and the furthest
+    /// any of them went
+    Moved { count: usize, distance: f64 },
+    /// faces of the base geometry that join different vertices
+    Faces(usize),
+    /// a group of vertices that was added, removed or changed
+    Group(String),
+}
+
+/// Every change from one part to another, for reviewing changes to
+/// a shared library of parts
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Diff {
+    changes: Vec<Change>,
+}
+
+impl Diff {
+
+    // Compares the base geometry rather than the evaluated one, so
+    // that changed attribute values aren't reported twice
+    pub(crate) fn new(from: &Part, to: &Part) -> Self {
+        let mut changes = Vec::new();
+        if from.name() != to.name() {
+            changes.push(Change::Name { from: from.name().into(), to: to.name().into() });
+        }
+        attributes(&mut changes,from.attributes(),to.attributes());
+        features(&mut changes,from,to);
+        connections(&mut changes,from.connections(),to.connections());
+        metadata(&mut changes,from.metadata(),to.metadata());
+        geometry(&mut changes,from.base(),to.base());
+        Self { changes }
+    }
+
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+}
+
+fn attributes(changes: &mut Vec<Change>, from: &[Attribute], to: &[Attribute]) {
+    let items = |a: &Attribute| a.items().iter().map(String::from).collect::<Vec<String>>();
+    for old in from.iter() {
+        match to.iter().find(|a| a.name() == old.name()) {
+            None => changes.push(Change::RemoveAttribute(old.name().into())),
+            Some(new) => {
+                if old.value() != new.value() {
+                    changes.push(Change::Value {
+                        attribute: old.name().into(),
+                        from: old.value(),
+                        to: new.value(),
+                    });
+                }
+                if items(old) != items(new) || old.is_nominal() != new.is_nominal() {
+                    changes.push(Change::Items(old.name().into()));
+                }
+            },
+        }
+    }
+    for new in to.iter().filter(|n| from.iter().all(|o| o.name() != n.name())) {
+        changes.push(Change::AddAttribute(new.name().into()));
+    }
+}
+
+fn features(changes: &mut Vec<Change>, from: &Part, to: &Part) {
+    for (index,old) in from.features().iter().enumerate() {
+        match to.features().iter().position(|f| f.name() == old.name()) {
+            None => changes.push(Change::RemoveFeature(old.name().into())),
+            Some(i) => {
+                let new = &to.features()[i];
+                let same = String::from(old) == String::from(new) &&
+                    from.is_suppressed(old.name()) == to.is_suppressed(new.name()) &&
+                    index == i;
+                if !same {
+                    changes.push(Change::Feature(old.name().into()));
+                }
+            },
+        }
+    }
+    let added = to
+        .features()
+        .iter()
+        .map(Feature::name)
+        .filter(|n| from.features().iter().all(|o| o.name() != *n));
+    for name in added {
+        changes.push(Change::AddFeature(name.into()));
+    }
+}
+
+fn connections(changes: &mut Vec<Change>, from: &[Connection], to: &[Connection]) {
+    for (index,(old,new)) in from.iter().zip(to.iter()).enumerate() {
+        let same = old.point().distance(&new.point()) <= TOLERANCE &&
+            (old.radius() - new.radius()).abs() <= TOLERANCE &&
+            (old.tolerance() - new.tolerance()).abs() <= TOLERANCE &&
+            old.joint() == new.joint() &&
+            old.interface() == new.interface();
+        if !same {
+            changes.push(Change::Connection(index));
+        }
+    }
+    changes.extend((to.len()..from.len()).map(Change::RemoveConnection));
+    changes.extend((from.len()..to.len()).map(Change::AddConnection));
+}
+
+fn metadata(changes: &mut Vec<Change>, from: &Metadata, to: &Metadata) {
+    let mut field = |name: &str, old: Option<String>, new: Option<String>| {
+        if old != new {
+            changes.push(Change::Metadata { field: name.into(), from: old, to: new });
+        }
+    };
+
+    field("color",from.color().as_ref().map(String::from),to.color().as_ref().map(String::from));
+    field("layer",from.layer().map(String::from),to.layer().map(String::from));
+    field("material",from.material().map(String::from),to.material().map(String::from));
+    field("sku",from.sku().map(String::from),to.sku().map(String::from));
+
+    let grain = |m: &Metadata| m.grain().map(|g| format!("{} {} {}",g.x,g.y,g.z));
+    if from.grain().zip(to.grain()).is_none_or(|(a,b)| a.vector().distance(&b.vector()) > TOLERANCE) {
+        field("grain",grain(from),grain(to));
+    }
+
+    for tag in from.tags().difference(to.tags()) {
+        field("tag",Some(tag.clone()),None);
+    }
+    for tag in to.tags().difference(from.tags()) {
+        field("tag",None,Some(tag.clone()));
+    }
+}
+
+fn geometry(changes: &mut Vec<Change>, from: &Geometry, to: &Geometry) {
+    let (vertices,faces) = ((from.vertices().len(),to.vertices().len()),(from.size(),to.size()));
+    if vertices.0 != vertices.1 || faces.0 != faces.1 {
+        changes.push(Change::Size { vertices, faces });
+    }
+
+    // only vertices and faces at the same index can be compared
+    let moved = from
+        .vertices()
+        .iter()
+        .zip(to.vertices())
+        .map(|(a,b)| a.distance(b))
+        .filter(|d| *d > TOLERANCE)
+        .collect::<Vec<f64>>();
+    if !moved.is_empty() {
+        let distance = moved.iter().copied().fold(0.0,f64::max);
+        changes.push(Change::Moved { count: moved.len(), distance });
+    }
+
+    let rejoined = from
+        .faces()
+        .iter()
+        .zip(to.faces())
+        .filter(|(f,g)| (f.a,f.b,f.c) != (g.a,g.b,g.c))
+        .count();
+    if rejoined > 0 {
+        changes.push(Change::Faces(rejoined));
+    }
+
+    for (name,indices) in from.groups() {
+        if to.group(name) != Some(indices) {
+            changes.push(Change::Group(name.clone()));
+        }
+    }
+    for name in to.groups().keys().filter(|n| from.group(n).is_none()) {
+        changes.push(Change::Group(name.clone()));
+    }
+}
+
+// A change is written as a short line for a person to read
+impl From<&Change> for String {
+    fn from(change: &Change) -> Self {
+        let value = |v: &Option<String>| v.clone().unwrap_or_else(|| "none".into());
+        match change {
+            Change::Name { from, to } => format!("renamed from '{}' to '{}'",from,to),
+            Change::AddAttribute(name) => format!("added attribute '{}'",name),
+            Change::RemoveAttribute(name) => format!("removed attribute '{}'",name),
+            Change::Value { attribute, from, to } => format!("attribute '{}' changed from {} to {}",attribute,from,to),
+            Change::Items(name) => format!("attribute '{}' moves the geometry differently",name),
+            Change::AddFeature(name) => format!("added feature '{}'",name),
+            Change::RemoveFeature(name) => format!("removed feature '{}'",name),
+            Change::Feature(name) => format!("feature '{}' changed",name),
+            Change::AddConnection(index) => format!("added connection {}",index),
+            Change::RemoveConnection(index) => format!("removed connection {}",index),
+            Change::Connection(index) => format!("connection {} changed",index),
+            Change::Metadata { field, from, to } => format!("{} changed from {} to {}",field,value(from),value(to)),
+            Change::Size { vertices, faces } => format!("geometry went from {} vertices and {} faces to {} and {}",
+                vertices.0,faces.0,vertices.1,faces.1),
+            Change::Moved { count, distance } => format!("{} vertices moved, up to {}",count,distance),
+            Change::Faces(count) => format!("{} faces join different vertices",count),
+            Change::Group(name) => format!("group '{}' changed",name),
+        }
+    }
+}
+
+// A diff is written as one change per line
+impl From<&Diff> for String {
+    fn from(diff: &Diff) -> Self {
+        diff.changes
+            .iter()
+            .map(String::from)
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::models::{Lumber,SheetGoods};
+    use crate::part::{Color,Joint,AttributeItem,Hole,Depth,Pattern};
+    use crate::assembly::Array;
+    use crate::geometry::{Vector,Vertex,Direction};
+
+    #[test]
+    fn test_part_diff() {
+        let old = Lumber::TwoByFour.part().build().unwrap();
+        assert!(old.diff(&old.clone()).is_empty());
+
+        let mut new = old.clone();
+        new.set("Length",0.5).unwrap();
+        new.metadata_mut().set_color(Some(Color::new(255,0,0)));
+        new.metadata_mut().add_tag("framing");
+        new.metadata_mut().set_grain(Some(Direction::new(1.0,0.0,0.0)));
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.changes(),&[
+            Change::Value { attribute: "Length".into(), from: 0.0, to: 0.5 },
+            Change::Metadata { field: "color".into(), from: None, to: Some("#ff0000".into()) },
+            Change::Metadata { field: "grain".into(), from: None, to: Some("1 0 0".into()) },
+            Change::Metadata { field: "tag".into(), from: None, to: Some("framing".into()) },
+        ]);
+        assert_eq!(String::from(&diff).lines().next(),Some("attribute 'Length' changed from 0 to 0.5"));
+
+        // a different part altogether
+        let sheet = SheetGoods::Plywood.part();
+        let diff = old.diff(&sheet);
+        let changes = diff.changes();
+        assert!(changes.contains(&Change::Name { from: "2x4".into(), to: "plywood".into() }));
+        assert!(changes.contains(&Change::AddAttribute("Width".into())));
+        assert!(changes.contains(&Change::AddConnection(2)));
+        assert!(changes.contains(&Change::Connection(0)));
+        assert!(changes.iter().any(|c| matches!(c,Change::Faces(_))));
+        assert!(changes.iter().any(|c| matches!(c,Change::Moved { count: 8, .. })));
+        assert!(!changes.iter().any(|c| matches!(c,Change::Size { .. })));
+
+        // the moving end of a board turns into a hinge
+        let mut base = old.clone();
+        let mut hinged = Part::new("2x4")
+            .with_geometry(old.base().clone())
+            .with_attribute(Attribute::new("Length".into(),vec![
+                AttributeItem::translate_all(Vector::new(1.0,0.0,0.0))
+            ]))
+            .with_connection(old.connections()[0].clone())
+            .with_connection(Connection::new(Vertex::new(1.2192,0.0,0.0),0.01905)
+                .with_joint(Joint::revolute(Direction::new(0.0,0.0,1.0),0.0,1.0)));
+        hinged.metadata_mut().set_layer(Some("doors".into()));
+        base.metadata_mut().set_layer(None);
+        assert_eq!(base.diff(&hinged).changes(),&[
+            Change::Items("Length".into()),
+            Change::Connection(1),
+            Change::Metadata { field: "layer".into(), from: None, to: Some("doors".into()) },
+        ]);
+    }
+
+    #[test]
+    fn test_part_diff_features() {
+        let old = Lumber::TwoByFour.part()
+            .with_feature(Hole::new("bore","front",0.01))
+            .with_feature(Hole::new("pin","front",0.005))
+            .with_feature(Hole::new("slot","front",0.02));
+        assert!(old.diff(&old.clone()).is_empty());
+
+        let new = Lumber::TwoByFour.part()
+            .with_feature(Hole::new("pin","front",0.005))
+            .with_feature(Hole::new("bore","front",0.01))
+            .with_feature(Hole::new("slot","front",0.02).with_depth(Depth::Blind(0.01)))
+            .with_feature(Pattern::new("row","slot",Array::linear(2,Vector::new(0.1,0.0,0.0))));
+        assert_eq!(old.diff(&new).changes(),&[
+            Change::Feature("bore".into()),
+            Change::Feature("pin".into()),
+            Change::Feature("slot".into()),
+            Change::AddFeature("row".into()),
+        ]);
+        assert_eq!(String::from(&new.diff(&old)).lines().last(),Some("removed feature 'row'"));
+    }
+
+}
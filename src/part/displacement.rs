@@ -0,0 +1,364 @@
+use std::f64::consts::PI;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::geometry::*;
+use crate::part::{Attribute,AttributeItem,Alteration,Selection};
+use crate::render::Image;
+use crate::utilities::Noise;
+use crate::errors::Error;
+use crate::constant::{Index,TOLERANCE};
+
+/// A height from 0 to 1 at every point of a surface, which moves
+/// the surface out along its normals by that much of the amplitude
+#[derive(Clone)]
+pub enum HeightField {
+    /// rings around an axis through the origin, a spacing apart and
+    /// wobbling along it like the growth rings of a log
+    Grain {
+        spacing: f64,
+        axis: Direction,
+    },
+    /// raised diamonds a pitch apart, like the grip on a handle
+    Knurl {
+        pitch: f64,
+    },
+    /// the brightness of an image repeated every `size` across
+    Image {
+        image: Image,
+        size: f64,
+    },
+    /// smooth random bumps about `scale` across, with finer bumps
+    /// added on top for each octave past the first
+    Noise {
+        noise: Noise,
+        scale: f64,
+        octaves: usize,
+    },
+    /// any height for a point on the surface
+    Function(Arc<dyn Fn(&Vertex) -> f64 + Send + Sync>),
+}
+
+/// A pattern pressed into the surface of a part. The amplitude is
+/// added to the part as an attribute named `<name> Amplitude`, so
+/// it can be changed like any other attribute.
+#[derive(Debug,Clone)]
+pub struct Displacement {
+    name: String,
+    field: HeightField,
+    selection: Selection,
+    amplitude: f64,
+}
+
+impl HeightField {
+
+    pub fn function<F>(f: F) -> Self
+        where F: Fn(&Vertex) -> f64 + Send + Sync + 'static
+    {
+        Self::Function(Arc::new(f))
+    }
+
+    // A copy with the seed of any noise mixed with another, so the
+    // same field gives different bumps for different builds
+    pub fn seeded(&self, seed: u64) -> Self {
+        match self {
+            HeightField::Noise { noise, scale, octaves } => HeightField::Noise {
+                noise: Noise::new(noise.seed() ^ seed),
+                scale: *scale,
+                octaves: *octaves,
+            },
+            field => field.clone(),
+        }
+    }
+
+    // The height at a point on the surface facing along `normal`.
+    // Knurls and images are laid flat onto the surface from the axis
+    // the normal is closest to, like a box around the part.
+    pub fn sample(&self, point: &Vertex, normal: &Vector) -> f64 {
+        match self {
+            HeightField::Grain { spacing, axis } => {
+                let axis = axis.vector().normalize();
+                let along = point.dot(&axis);
+                let radius = (*point - axis * along).magnitude();
+                let wobble = 0.2 * spacing * (2.0 * PI * along / (spacing * 10.0)).sin();
+                0.5 + 0.5 * (2.0 * PI * (radius + wobble) / spacing).cos()
+            },
+            HeightField::Knurl { pitch } => {
+                let (u,v) = project(point,normal);
+                let wave = |x: f64| 1.0 - 2.0 * (x.rem_euclid(1.0) - 0.5).abs();
+                wave((u + v) / pitch).min(wave((u - v) / pitch))
+            },
+            HeightField::Image { image, size } => {
+                let (u,v) = project(point,normal);
+                brightness(image,u / size,v / size)
+            },
+            HeightField::Noise { noise, scale, octaves } => {
+                let scale = scale.max(TOLERANCE);
+                0.5 + 0.5 * noise.fractal(point.x / scale,point.y / scale,point.z / scale,*octaves)
+            },
+            HeightField::Function(f) => f(point),
+        }
+    }
+
+}
+
+impl fmt::Debug for HeightField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeightField::Grain { spacing, axis } => f
+                .debug_struct("Grain")
+                .field("spacing",spacing)
+                .field("axis",axis)
+                .finish(),
+            HeightField::Knurl { pitch } => f
+                .debug_struct("Knurl")
+                .field("pitch",pitch)
+                .finish(),
+            HeightField::Image { image, size } => f
+                .debug_struct("Image")
+                .field("width",&image.width())
+                .field("height",&image.height())
+                .field("size",size)
+                .finish(),
+            HeightField::Noise { noise, scale, octaves } => f
+                .debug_struct("Noise")
+                .field("noise",noise)
+                .field("scale",scale)
+                .field("octaves",octaves)
+                .finish(),
+            HeightField::Function(_) => f.write_str("Function"),
+        }
+    }
+}
+
+impl Displacement {
+
+    pub fn new<T: Into<String>>(name: T, field: HeightField) -> Self {
+        Self {
+            name: name.into(),
+            field,
+            selection: Selection::All,
+            amplitude: 0.0,
+        }
+    }
+
+    // The vertices that are moved, which is all of them by default.
+    // Soft selections fade the pattern out towards their edges.
+    pub fn with_selection(mut self, selection: Selection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    // How far the highest parts of the pattern stand out, which
+    // sinks the pattern in instead when it's negative
+    pub fn with_amplitude(mut self, value: f64) -> Self {
+        self.amplitude = value;
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn field(&self) -> &HeightField {
+        &self.field
+    }
+
+    pub fn selection(&self) -> &Selection {
+        &self.selection
+    }
+
+    pub fn amplitude(&self) -> f64 {
+        self.amplitude
+    }
+
+    // A copy with its field seeded like `HeightField::seeded`
+    pub fn seeded(&self, seed: u64) -> Self {
+        Self {
+            field: self.field.seeded(seed),
+            ..self.clone()
+        }
+    }
+
+    // The name of the attribute that controls the amplitude
+    pub fn attribute(&self) -> String {
+        format!("{} Amplitude",self.name)
+    }
+
+    // The attribute that moves each selected vertex along its normal
+    // by its height, with the amplitude as its value. Vertices that
+    // face the same way share an item, so flat areas only need one.
+    pub(crate) fn build_attribute(&self, base: &Geometry) -> Result<Attribute,Error> {
+        let weights = self.selection
+            .resolve(base)?
+            .weight_map(base.vertices().len());
+
+        let mut smooth = base.clone();
+        smooth.compute_normals();
+        let normals = smooth.normals().unwrap_or_default();
+
+        let mut items: Vec<(Vector,Vec<(Index,f64)>)> = Vec::new();
+        for (index,weight) in weights.into_iter().enumerate().filter(|(_,w)| *w > 0.0) {
+            let (vertex,normal) = (base.vertices()[index],normals[index].vector());
+            if normal.magnitude() <= TOLERANCE {
+                continue;
+            }
+            let height = self.field.sample(&vertex,&normal) * weight;
+            match items.iter_mut().find(|(n,_)| (*n - normal).magnitude() <= TOLERANCE) {
+                Some((_,heights)) => heights.push((index,height)),
+                None => items.push((normal,vec![(index,height)])),
+            }
+        }
+
+        let items = items
+            .into_iter()
+            .map(|(normal,heights)| AttributeItem::new(Selection::Weighted(heights),Alteration::translate(normal)))
+            .collect();
+
+        let mut attribute = Attribute::new(self.attribute(),items);
+        attribute.update(self.amplitude);
+        Ok(attribute)
+    }
+
+}
+
+// The two coordinates across the axis a normal is closest to
+fn project(point: &Vertex, normal: &Vector) -> (f64,f64) {
+    let (x,y,z) = (normal.x.abs(),normal.y.abs(),normal.z.abs());
+    if x >= y && x >= z {
+        (point.y,point.z)
+    } else if y >= z {
+        (point.x,point.z)
+    } else {
+        (point.x,point.y)
+    }
+}
+
+// The brightness of an image from 0 to 1, blended between the four
+// pixels around a point where the image is a unit across and repeats
+fn brightness(image: &Image, u: f64, v: f64) -> f64 {
+    let (width,height) = (image.width(),image.height());
+    if width == 0 || height == 0 {
+        return 0.0;
+    }
+
+    let pixel = |x: i64, y: i64| {
+        let x = x.rem_euclid(width as i64) as usize;
+        let y = y.rem_euclid(height as i64) as usize;
+        image
+            .get(x,y)
+            .map(|[r,g,b,_]| (0.2126 * r as f64 + 0.7152 * g as f64 + 0.0722 * b as f64) / 255.0)
+            .unwrap_or_default()
+    };
+
+    // the top row of the image is at the top of the surface
+    let x = u * width as f64 - 0.5;
+    let y = (1.0 - v) * height as f64 - 0.5;
+    let (x0,y0) = (x.floor(),y.floor());
+    let (tx,ty) = (x - x0,y - y0);
+    let (x0,y0) = (x0 as i64,y0 as i64);
+
+    let top = pixel(x0,y0) * (1.0 - tx) + pixel(x0 + 1,y0) * tx;
+    let bottom = pixel(x0,y0 + 1) * (1.0 - tx) + pixel(x0 + 1,y0 + 1) * tx;
+    top * (1.0 - ty) + bottom * ty
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::part::{Part,EvalContext};
+
+    // A flat square 1 across in XY, split into a grid of cells
+    fn plate(cells: usize) -> Geometry {
+        let step = 1.0 / cells as f64;
+        let mut values = Vec::new();
+        for j in 0..=cells {
+            for i in 0..=cells {
+                values.extend([i as f64 * step,j as f64 * step,0.0]);
+            }
+        }
+        let mut indices = Vec::new();
+        for j in 0..cells {
+            for i in 0..cells {
+                let a = j * (cells + 1) + i + 1;
+                let (b,c,d) = (a + 1,a + cells + 2,a + cells + 1);
+                indices.extend([a,b,c,a,c,d]);
+            }
+        }
+        Geometry::make(values,indices)
+    }
+
+    #[test]
+    fn test_displacement_knurl() {
+        let mut part = Part::new("handle")
+            .with_geometry(plate(20))
+            .build()
+            .unwrap();
+
+        let displacement = Displacement::new("grip",HeightField::Knurl { pitch: 0.2 })
+            .with_amplitude(0.01)
+            .build();
+        part.displace(&displacement).unwrap();
+
+        // a flat plate moves straight up by one item
+        assert_eq!(part.attribute("grip Amplitude").unwrap().items().len(),1);
+        let heights = part.geometry().vertices().iter().map(|v| v.z).collect::<Vec<f64>>();
+        assert!(heights.iter().all(|z| (-1e-9..=0.01 + 1e-9).contains(z)));
+        assert_relative_eq!(heights[0],0.0,epsilon = 1e-9);
+        assert_relative_eq!(heights[2],0.01,epsilon = 1e-9);
+
+        // the amplitude is an attribute like any other
+        part.set("grip Amplitude",-0.02).unwrap();
+        assert_relative_eq!(part.geometry().vertices()[2].z,-0.02,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_displacement_fields() {
+        let up = Vector::new(0.0,0.0,1.0);
+        let grain = HeightField::Grain { spacing: 0.1, axis: Direction::new(0.0,1.0,0.0) };
+        assert_relative_eq!(grain.sample(&Vertex::new(0.0,0.0,0.0),&up),1.0);
+        assert_relative_eq!(grain.sample(&Vertex::new(0.05,0.0,0.0),&up),0.0,epsilon = 1e-9);
+
+        // a black and white checkerboard, two pixels a side
+        let mut image = Image::new(2,2,[0,0,0,255]);
+        image.set(0,0,[255,255,255,255]);
+        image.set(1,1,[255,255,255,255]);
+        let field = HeightField::Image { image, size: 1.0 };
+        assert_relative_eq!(field.sample(&Vertex::new(0.25,0.75,0.0),&up),1.0);
+        assert_relative_eq!(field.sample(&Vertex::new(0.75,0.75,0.0),&up),0.0);
+        assert_relative_eq!(field.sample(&Vertex::new(0.5,0.75,0.0),&up),0.5);
+
+        // only the selected half of the plate moves
+        let mut part = Part::new("plate").with_geometry(plate(4)).build().unwrap();
+        let half = Selection::within(Bounds::new(Vertex::new(-1.0,-1.0,-1.0),Vertex::new(0.5,2.0,1.0)));
+        let displacement = Displacement::new("bump",HeightField::function(|_| 1.0))
+            .with_selection(half)
+            .with_amplitude(0.1);
+        part.displace(&displacement).unwrap();
+        let raised = part.geometry().vertices().iter().filter(|v| v.z > 0.05).count();
+        assert_eq!(raised,15);
+
+        // the same seed gives the same bumps
+        let noise = |seed| HeightField::Noise { noise: Noise::new(seed), scale: 0.3, octaves: 3 };
+        let point = Vertex::new(0.45,0.2,0.0);
+        assert_eq!(noise(5).sample(&point,&up),noise(5).sample(&point,&up));
+        assert_ne!(noise(5).sample(&point,&up),noise(6).sample(&point,&up));
+        assert!((0.0..=1.0).contains(&noise(5).sample(&point,&up)));
+
+        // and the seed of the build changes them
+        let displacement = Displacement::new("bumps",noise(5)).with_amplitude(0.1);
+        let bumped = |seed| {
+            let mut part = Part::new("plate").with_geometry(plate(4)).build().unwrap();
+            part.displace_with(&displacement,&EvalContext::new().with_seed(seed)).unwrap();
+            part.geometry().vertices().to_vec()
+        };
+        assert_eq!(bumped(1),bumped(1));
+        assert_ne!(bumped(1),bumped(2));
+    }
+
+}
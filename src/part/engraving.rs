@@ -0,0 +1,128 @@
+use crate::geometry::*;
+use crate::geometry::text::Font;
+use crate::errors::Error;
+use crate::constant::Index;
+
+/// Text raised from or cut into a face of a part. The depth is
+/// added to the part as an attribute named `<name> Depth`, so it
+/// can be changed like any other attribute.
+#[derive(Debug,Clone)]
+pub struct Engraving {
+    name: String,
+    text: String,
+    font: Font,
+    size: f64,
+    depth: f64,
+    face: Index,
+    engraved: bool,
+}
+
+impl Engraving {
+
+    pub fn new<T: Into<String>, U: Into<String>>(name: T, text: U, font: Font) -> Self {
+        Self {
+            name: name.into(),
+            text: text.into(),
+            font,
+            size: 1.0,
+            depth: 0.0,
+            face: 0,
+            engraved: false,
+        }
+    }
+
+    // The height of the font's em square
+    pub fn with_size(mut self, value: f64) -> Self {
+        self.size = value;
+        self
+    }
+
+    pub fn with_depth(mut self, value: f64) -> Self {
+        self.depth = value;
+        self
+    }
+
+    // The face of the base geometry the text is centered on
+    pub fn with_face(mut self, value: Index) -> Self {
+        self.face = value;
+        self
+    }
+
+    // Sinks the text into the face instead of raising it. The part's
+    // own face isn't cut, so this only shows the floor and walls.
+    pub fn with_engraved(mut self, value: bool) -> Self {
+        self.engraved = value;
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn size(&self) -> f64 {
+        self.size
+    }
+
+    pub fn depth(&self) -> f64 {
+        self.depth
+    }
+
+    pub fn face(&self) -> Index {
+        self.face
+    }
+
+    pub fn is_engraved(&self) -> bool {
+        self.engraved
+    }
+
+    // The name of the attribute that controls the depth
+    pub fn attribute(&self) -> String {
+        format!("{} Depth",self.name)
+    }
+
+    // The name of the group holding the vertices the attribute moves
+    pub fn group(&self) -> String {
+        format!("{}.top",self.name)
+    }
+
+    // Flat text centered on the origin, which the depth attribute
+    // pulls out of the XY plane
+    pub fn geometry(&self) -> Result<Geometry,Error> {
+        let mut geometry = self.font.extrude(&self.text,self.size,0.0)?;
+        if let Some(bounds) = Bounds::from_points(geometry.vertices()) {
+            let center = bounds.center();
+            geometry.transform(&Matrix::translate(-center.x,-center.y,0.0));
+        }
+        Ok(geometry)
+    }
+
+    // Moves text from the XY plane onto a triangle, reading along
+    // its first edge, and gives the direction the depth moves in
+    pub fn placement(&self, triangle: &Triangle) -> (Matrix,Vector) {
+        let z = triangle.normal().vector();
+        let x = (triangle.p2 - triangle.p1).normalize();
+        let y = z.cross(&x);
+        let o = triangle.centroid();
+
+        let matrix = Matrix::new([
+            x.x, y.x, z.x, o.x,
+            x.y, y.y, z.y, o.y,
+            x.z, y.z, z.z, o.z,
+            0.0, 0.0, 0.0, 1.0,
+        ]);
+
+        match self.engraved {
+            true => (matrix,z * -1.0),
+            false => (matrix,z),
+        }
+    }
+
+}
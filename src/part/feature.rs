@@ -0,0 +1,553 @@
+use crate::geometry::{Geometry,Transform};
+#[cfg(feature = "lyon")]
+use crate::geometry::{Profile,Polyline,Matrix};
+use crate::assembly::Array;
+use crate::part::{Hole,EvalContext};
+use crate::errors::{Error,Context};
+use crate::constant::FEATURE_TAG;
+use crate::utilities;
+
+/// Whether the solid a feature makes is added to the part or cut
+/// out of it
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Action {
+    #[default]
+    Join,
+    Cut,
+}
+
+/// A profile extruded into a solid, placed on the part by a
+/// transform, with the profile on the XY plane and the solid going
+/// up +Z from it
+#[cfg(feature = "lyon")]
+#[derive(Debug,Clone,PartialEq)]
+pub struct Extrusion {
+    name: String,
+    profile: Profile,
+    depth: f64,
+    placement: Matrix,
+    action: Action,
+}
+
+/// Copies of an earlier feature, one for each place in an array
+/// after the first, which is where the feature already is
+#[derive(Debug,Clone,PartialEq)]
+pub struct Pattern {
+    name: String,
+    feature: String,
+    array: Array,
+}
+
+/// One step of the history of a part. Features are applied in order
+/// after the attributes, each to the result of the ones before it,
+/// and are all made again from the start whenever anything before
+/// them changes.
+#[derive(Debug,Clone,PartialEq)]
+pub enum Feature {
+    #[cfg(feature = "lyon")]
+    Extrude(Extrusion),
+    Hole(Hole),
+    Pattern(Pattern),
+}
+
+/// How one feature went when the history of a part was rebuilt
+#[derive(Debug)]
+pub struct HistoryStep {
+    /// the name of the feature
+    pub name: String,
+    /// why the feature couldn't be applied, in which case it was
+    /// left out and the history carried on without it
+    pub error: Option<Error>,
+    /// true if the feature was left out because it's suppressed, it
+    /// copies a suppressed feature or it's after the rollback
+    pub suppressed: bool,
+    /// the number of faces in the part after the step
+    pub faces: usize,
+}
+
+#[cfg(feature = "lyon")]
+impl Extrusion {
+
+    pub fn new<T: Into<String>>(name: T, profile: Profile, depth: f64) -> Self {
+        Self {
+            name: name.into(),
+            profile,
+            depth,
+            placement: Matrix::identity(),
+            action: Action::Join,
+        }
+    }
+
+    pub fn with_placement(mut self, placement: Matrix) -> Self {
+        self.placement = placement;
+        self
+    }
+
+    pub fn with_action(mut self, action: Action) -> Self {
+        self.action = action;
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    pub fn depth(&self) -> f64 {
+        self.depth
+    }
+
+    pub fn placement(&self) -> &Matrix {
+        &self.placement
+    }
+
+    pub fn action(&self) -> Action {
+        self.action
+    }
+
+}
+
+#[cfg(feature = "lyon")]
+impl Extrusion {
+
+    // Reads an extrusion named `name` from text written by
+    // `String::from(&Feature)`
+    fn parse(name: &str, text: &str) -> Result<Self,Error> {
+        let fields = text.split_whitespace().collect::<Vec<&str>>();
+        let [action,depth,placement,outer,holes @ ..] = fields.as_slice() else {
+            return Err(Error::ParseError);
+        };
+
+        let action = match *action {
+            "join" => Action::Join,
+            "cut" => Action::Cut,
+            _ => return Err(Error::ParseError),
+        };
+        let placement = <[f64;16]>::try_from(utilities::list::<f64>(placement)?)
+            .or(Err(Error::ParseError))?;
+        let polyline = |text: &str| match utilities::list::<f64>(text)?.as_slice() {
+            values if values.len() % 2 == 0 => Ok(Polyline::closed(values
+                .chunks(2)
+                .map(|c| (c[0],c[1]))
+                .collect())),
+            _ => Err(Error::ParseError),
+        };
+
+        let mut profile = Profile::new(polyline(outer)?);
+        for hole in holes.iter() {
+            profile = profile.with_hole(polyline(hole)?);
+        }
+        Ok(Self {
+            name: name.into(),
+            profile,
+            depth: depth.parse()?,
+            placement: Matrix::new(placement),
+            action,
+        })
+    }
+
+}
+
+impl Pattern {
+
+    pub fn new<T: Into<String>, U: Into<String>>(name: T, feature: U, array: Array) -> Self {
+        Self {
+            name: name.into(),
+            feature: feature.into(),
+            array,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // The name of the feature that's copied
+    pub fn feature(&self) -> &str {
+        &self.feature
+    }
+
+    pub fn array(&self) -> &Array {
+        &self.array
+    }
+
+}
+
+impl Feature {
+
+    pub fn name(&self) -> &str {
+        match self {
+            #[cfg(feature = "lyon")]
+            Feature::Extrude(extrusion) => extrusion.name(),
+            Feature::Hole(hole) => hole.name(),
+            Feature::Pattern(pattern) => pattern.name(),
+        }
+    }
+
+    // The solids the feature adds to or cuts out of the part, finding
+    // datums on `reference`, which is the geometry before any
+    // features. A pattern can only copy features that come before it,
+    // and its jitter is mixed with the context's seed for its name.
+    pub(crate) fn solids(&self, reference: &Geometry, earlier: &[Feature], context: &EvalContext) -> Result<Vec<(Geometry,Action)>,Error> {
+        match self {
+            #[cfg(feature = "lyon")]
+            Feature::Extrude(extrusion) => {
+                let mut solid = extrusion.profile.extrude(extrusion.depth)?;
+                solid.transform(&extrusion.placement);
+                Ok(vec![(solid,extrusion.action)])
+            },
+            Feature::Hole(hole) => Ok(hole
+                .tools(reference)?
+                .into_iter()
+                .map(|t| (t,Action::Cut))
+                .collect()),
+            Feature::Pattern(pattern) => {
+                let index = earlier
+                    .iter()
+                    .position(|f| f.name() == pattern.feature)
+                    .ok_or_else(|| Error::UnknownFeature(pattern.feature.clone()))?;
+                let solids = earlier[index]
+                    .solids(reference,&earlier[..index],context)
+                    .in_feature(&pattern.feature)?;
+
+                let mut result = Vec::new();
+                let array = pattern.array.seeded(context.seed_for(&pattern.name));
+                for matrix in array.transforms().iter().skip(1) {
+                    for (solid,action) in solids.iter() {
+                        let mut solid = solid.clone();
+                        solid.transform(matrix);
+                        result.push((solid,*action));
+                    }
+                }
+                Ok(result)
+            },
+        }
+    }
+
+    // Builds features from `feature` lines, in the order they're
+    // written. Other lines are ignored.
+    pub fn parse_lines(text: &str) -> Result<Vec<Feature>,Error> {
+        text.lines()
+            .map(utilities::strip_comment)
+            .filter_map(|l| l.strip_prefix(FEATURE_TAG).filter(|r| r.starts_with(' ')))
+            .map(Feature::parse)
+            .collect()
+    }
+
+    fn parse(text: &str) -> Result<Self,Error> {
+        let (name,rest) = utilities::unquote(text)?;
+        let (kind,rest) = utilities::token(rest);
+        let feature = match kind {
+            #[cfg(feature = "lyon")]
+            "extrude" => Extrusion::parse(&name,rest).map(Feature::Extrude),
+            "hole" => Hole::parse(&name,rest).map(Feature::Hole),
+            "pattern" => utilities::unquote(rest)
+                .and_then(|(feature,array)| Ok(Pattern::new(&name,feature,Array::try_from(array)?)))
+                .map(Feature::Pattern),
+            _ => Err(Error::ParseError),
+        };
+        feature.in_feature(&name)
+    }
+
+    // Changes the geometry by the solids of the feature, in order
+    pub(crate) fn apply(&self, reference: &Geometry, earlier: &[Feature], context: &EvalContext, geometry: &mut Geometry) -> Result<(),Error> {
+        for (solid,action) in self.solids(reference,earlier,context)? {
+            *geometry = match action {
+                Action::Join => geometry.union(&solid),
+                Action::Cut => geometry.difference(&solid),
+            };
+        }
+        Ok(())
+    }
+
+}
+
+// A feature is written on one line, as its name, quoted if it has
+// spaces, its kind and then its definition:
+//
+//   feature <name> hole <datum> <diameter> <depth> <head> [<direction>]
+//   feature <name> pattern <feature> <layout> [<jitter>]
+//   feature <name> extrude join|cut <depth> <placement> <outline> [<hole> ...]
+//
+// where the placement is the sixteen values of the matrix, and each
+// loop of the profile is its points, all separated by commas. Holes
+// and arrays write the rest of their own lines.
+impl From<&Feature> for String {
+    fn from(feature: &Feature) -> Self {
+        let definition = match feature {
+            #[cfg(feature = "lyon")]
+            Feature::Extrude(extrusion) => {
+                let points = |line: &Polyline| line
+                    .points()
+                    .iter()
+                    .map(|(x,y)| format!("{},{}",x,y))
+                    .collect::<Vec<String>>()
+                    .join(",");
+                let placement = extrusion.placement
+                    .unpack()
+                    .iter()
+                    .map(f64::to_string)
+                    .collect::<Vec<String>>()
+                    .join(",");
+                let mut result = format!("extrude {} {} {} {}",
+                    match extrusion.action {
+                        Action::Join => "join",
+                        Action::Cut => "cut",
+                    },
+                    extrusion.depth,
+                    placement,
+                    points(extrusion.profile.outer()));
+                for hole in extrusion.profile.holes() {
+                    result.push(' ');
+                    result.push_str(&points(hole));
+                }
+                result
+            },
+            Feature::Hole(hole) => format!("hole {}",String::from(hole)),
+            Feature::Pattern(pattern) => format!("pattern {} {}",
+                utilities::quote(&pattern.feature,&[]),
+                String::from(&pattern.array)),
+        };
+        format!("{} {} {}",FEATURE_TAG,utilities::quote(feature.name(),&[]),definition)
+    }
+}
+
+#[cfg(feature = "lyon")]
+impl From<Extrusion> for Feature {
+    fn from(extrusion: Extrusion) -> Self {
+        Feature::Extrude(extrusion)
+    }
+}
+
+impl From<Hole> for Feature {
+    fn from(hole: Hole) -> Self {
+        Feature::Hole(hole)
+    }
+}
+
+impl From<Pattern> for Feature {
+    fn from(pattern: Pattern) -> Self {
+        Feature::Pattern(pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::f64::consts::TAU;
+
+    use super::*;
+    use crate::geometry::{Vertex,Vector};
+    use crate::assembly::Jitter;
+    use crate::part::{Part,Depth,Attribute,AttributeItem,EvalContext};
+    use crate::testing;
+
+    // A unit cube with its top corners in a "top" group
+    fn block() -> Part {
+        let geometry = testing::cube_with_top("top");
+        Part::new("block")
+            .with_geometry(geometry)
+            .build()
+            .unwrap()
+    }
+
+    // The area of the 32 sided circle a hole is cut with
+    fn area(diameter: f64) -> f64 {
+        16.0 * (diameter / 2.0).powi(2) * (TAU / 32.0).sin()
+    }
+
+    #[test]
+    fn test_feature_pattern() {
+        let mut part = block();
+        part.add_feature(Hole::new("bore","top",0.1)).unwrap();
+        part.add_feature(Pattern::new("row","bore",Array::linear(3,Vector::new(-0.2,0.0,0.0)))).unwrap();
+        assert_relative_eq!(part.geometry().volume(),1.0 - 3.0 * area(0.1),epsilon = 1e-9);
+
+        // changing the hole changes every copy of it
+        let old = part.replace_feature("bore",Hole::new("bore","top",0.15)).unwrap();
+        assert!(matches!(old,Feature::Hole(h) if h.diameter() == 0.1));
+        assert_relative_eq!(part.geometry().volume(),1.0 - 3.0 * area(0.15),epsilon = 1e-9);
+
+        // the pattern needs the hole, so it can't be removed
+        let error = part.remove_feature("bore").unwrap_err();
+        assert!(matches!(error.root(),Error::UnknownFeature(n) if n == "bore"));
+        assert!(matches!(part.add_feature(Hole::new("row","top",0.1)).unwrap_err().root(),Error::DuplicateFeature(_)));
+
+        part.remove_feature("row").unwrap();
+        assert_relative_eq!(part.geometry().volume(),1.0 - area(0.15),epsilon = 1e-9);
+
+        // jittered copies move with the seed of the build
+        let row = Array::linear(3,Vector::new(-0.2,0.0,0.0))
+            .with_jitter(Jitter::new(1).with_position(Vector::new(0.05,0.05,0.0)));
+        let part = block()
+            .with_feature(Hole::new("bore","top",0.1))
+            .with_feature(Pattern::new("row","bore",row))
+            .build()
+            .unwrap();
+        let build = |seed| part.evaluate_with(&EvalContext::new().with_seed(seed)).unwrap().vertices().to_vec();
+        assert_eq!(build(1),build(1));
+        assert_ne!(build(1),build(2));
+    }
+
+    #[test]
+    fn test_feature_history() {
+        let blind = Hole::new("blind","top",0.2).with_depth(Depth::Blind(0.5));
+        let part = block()
+            .with_feature(blind)
+            .with_feature(Hole::new("side","front",0.1))
+            .with_feature(Pattern::new("copies","missing",Array::linear(2,Vector::new(0.1,0.0,0.0))));
+
+        // every failure is reported against its own feature
+        let steps = part.history().unwrap();
+        assert_eq!(steps.iter().map(|s| s.name.as_str()).collect::<Vec<&str>>(),["blind","side","copies"]);
+        assert!(steps[0].error.is_none());
+        assert!(steps[0].faces > 12);
+        assert!(matches!(&steps[1].error,Some(Error::InPart(_,e)) if matches!(e.as_ref(),Error::InFeature(n,_) if n == "side")));
+        assert!(matches!(steps[2].error.as_ref().map(Error::root),Some(Error::UnknownFeature(n)) if n == "missing"));
+        assert_eq!(steps[2].faces,steps[0].faces);
+
+        // building stops at the first one
+        let error = part.build().unwrap_err();
+        assert!(matches!(error.root(),Error::UnknownGroup(n) if n == "front"));
+
+        // the history is found for a context like the geometry is,
+        // here with the block twice as tall so the hole stops short
+        let base = block().base().clone();
+        let top = base.group("top").unwrap().clone();
+        let block = Part::new("block")
+            .with_geometry(base)
+            .with_attribute(Attribute::new("Height".into(),vec![
+                AttributeItem::translate_specific(Vector::new(0.0,0.0,1.0),top)
+            ]))
+            .with_feature(Hole::new("blind","top",0.2).with_depth(Depth::Blind(1.5)))
+            .build()
+            .unwrap();
+        let context = EvalContext::new().with_parameter("Height",1.0);
+        let tall = block.history_with(&context).unwrap();
+        assert!(tall[0].error.is_none());
+        assert_eq!(block.history().unwrap()[0].faces,block.geometry().faces().len());
+        assert_eq!(tall[0].faces,block.evaluate_with(&context).unwrap().faces().len());
+        assert_ne!(tall[0].faces,block.geometry().faces().len());
+    }
+
+    #[test]
+    fn test_feature_suppress() {
+        let mut part = block();
+        part.add_feature(Hole::new("bore","top",0.1)).unwrap();
+        part.add_feature(Pattern::new("row","bore",Array::linear(3,Vector::new(-0.2,0.0,0.0)))).unwrap();
+        part.add_feature(Hole::new("pin","top",0.05).with_depth(Depth::Blind(0.5))).unwrap();
+        let full = part.geometry().volume();
+        assert_relative_eq!(full,1.0 - 3.0 * area(0.1),epsilon = 1e-9);
+
+        // the pattern goes with the hole it copies
+        part.suppress("bore").unwrap();
+        assert!(part.is_suppressed("bore"));
+        assert_relative_eq!(part.geometry().volume(),1.0 - area(0.05) * 0.5,epsilon = 1e-9);
+        let steps = part.history().unwrap();
+        assert_eq!(steps.iter().map(|s| s.suppressed).collect::<Vec<bool>>(),[true,true,false]);
+
+        // the definition is kept, so it comes back the same
+        part.unsuppress("bore").unwrap();
+        assert_relative_eq!(part.geometry().volume(),full,epsilon = 1e-12);
+        assert!(matches!(part.suppress("missing").unwrap_err().root(),Error::UnknownFeature(_)));
+    }
+
+    #[test]
+    fn test_feature_rollback() {
+        let mut part = block();
+        part.add_feature(Hole::new("bore","top",0.1)).unwrap();
+        part.add_feature(Pattern::new("row","bore",Array::linear(3,Vector::new(-0.2,0.0,0.0)))).unwrap();
+
+        part.roll_back_to("bore").unwrap();
+        assert_eq!(part.rollback(),Some(1));
+        assert_relative_eq!(part.geometry().volume(),1.0 - area(0.1),epsilon = 1e-9);
+
+        // features added while rolled back go in at the rollback
+        part.add_feature(Hole::new("pin","top",0.2).with_depth(Depth::Blind(0.5))).unwrap();
+        assert_eq!(part.features().iter().map(Feature::name).collect::<Vec<&str>>(),["bore","pin","row"]);
+        assert_eq!(part.rollback(),Some(2));
+        let pinned = part.geometry().volume();
+        assert_relative_eq!(pinned,1.0 - area(0.1) - (area(0.2) - area(0.1)) * 0.5,epsilon = 1e-9);
+
+        part.roll_back(0).unwrap();
+        assert_relative_eq!(part.geometry().volume(),1.0,epsilon = 1e-9);
+        assert!(part.roll_back(4).is_err());
+
+        part.roll_forward().unwrap();
+        assert_eq!(part.rollback(),None);
+        assert!(part.geometry().volume() < pinned - area(0.1));
+    }
+
+    #[test]
+    fn test_feature_string() {
+        use crate::geometry::Direction;
+        use crate::part::{Head,Screw};
+        use crate::geometry::ParseMode;
+
+        let mut part = block();
+        part.add_feature(Hole::clearance("cap screw","top",Screw::M4).with_head(Screw::M4.counterbore())).unwrap();
+        part.add_feature(Hole::tapped("tap","top",Screw::M3)
+            .with_head(Head::Countersink { diameter: 0.01, angle: 1.2 })
+            .with_direction(Direction::new(0.1,0.0,-1.0))).unwrap();
+        let ring = Array::circular(3,Vertex::new(0.5,0.5,0.0),Direction::new(0.0,0.0,1.0),0.4)
+            .with_jitter(crate::assembly::Jitter::new(7).with_position(Vector::new(0.01,0.01,0.0)));
+        part.add_feature(Pattern::new("ring","cap screw",ring)).unwrap();
+        #[cfg(feature = "lyon")]
+        {
+            use crate::geometry::Polyline;
+            let outline = Polyline::closed(vec![(0.0,0.0),(0.4,0.0),(0.4,0.4),(0.0,0.4)]);
+            let window = Polyline::closed(vec![(0.1,0.1),(0.1,0.3),(0.3,0.3),(0.3,0.1)]);
+            let frame = Extrusion::new("frame",Profile::new(outline).with_hole(window),0.1)
+                .with_placement(Matrix::translate(0.3,0.3,1.0) * Matrix::rotate_z(0.3))
+                .build();
+            part.add_feature(frame).unwrap();
+        }
+
+        let text = String::from(&part);
+        assert!(text.contains("\nfeature \"cap screw\" hole top 0.0045 through counterbore:0.008,0.004"));
+        let copy = Part::parse(&text,ParseMode::Strict).unwrap();
+        assert_eq!(copy.features(),part.features());
+        assert!(copy.diff(&part).is_empty());
+        assert_relative_eq!(copy.geometry().volume(),part.geometry().volume(),epsilon = 1e-12);
+
+        // a feature that can't be read is named in the error
+        let broken = text.replace("pattern \"cap screw\" circular:3","pattern \"cap screw\" circular:x");
+        let error = Part::parse(&broken,ParseMode::Lenient).unwrap_err();
+        assert!(matches!(&error,Error::InPart(_,e) if matches!(e.as_ref(),Error::InFeature(n,_) if n == "ring")));
+        assert!(matches!(error.root(),Error::ParseError));
+    }
+
+    #[cfg(feature = "lyon")]
+    #[test]
+    fn test_feature_extrude() {
+        use crate::geometry::{Polyline,Matrix};
+
+        let square = Profile::from(Polyline::closed(vec![(0.0,0.0),(0.2,0.0),(0.2,0.2),(0.0,0.2)]));
+        let boss = Extrusion::new("boss",square.clone(),0.5)
+            .with_placement(Matrix::translate(0.1,0.1,1.0))
+            .build();
+        let pocket = Extrusion::new("pocket",square,0.5)
+            .with_placement(Matrix::translate(0.6,0.6,0.75))
+            .with_action(Action::Cut)
+            .build();
+
+        let mut part = block();
+        part.add_feature(boss).unwrap();
+        part.add_feature(pocket).unwrap();
+        assert_relative_eq!(part.geometry().volume(),1.0 + 0.02 - 0.01,epsilon = 1e-9);
+
+        // features can go anywhere in the history
+        let hole = Hole::new("bore","top",0.05).with_direction(crate::geometry::Direction::new(0.0,0.0,-1.0));
+        part.insert_feature(0,hole).unwrap();
+        assert_eq!(part.features()[0].name(),"bore");
+        assert_relative_eq!(part.geometry().volume(),1.0 + 0.02 - 0.01 - area(0.05),epsilon = 1e-9);
+    }
+
+}
@@ -0,0 +1,464 @@
+use std::f64::consts::TAU;
+
+use crate::geometry::*;
+use crate::errors::Error;
+use crate::utilities;
+
+// the number of flat sides the round parts of a hole are cut with
+const SIDES: usize = 32;
+
+/// Standard metric screw sizes
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Screw {
+    M2,
+    M2_5,
+    M3,
+    M4,
+    M5,
+    M6,
+    M8,
+    M10,
+    M12,
+}
+
+/// How deep a hole goes
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub enum Depth {
+    /// all the way through the part
+    #[default]
+    Through,
+    /// a distance into the part, with a flat bottom
+    Blind(f64),
+}
+
+/// The shape at the top of a hole, where the head of a screw sits
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub enum Head {
+    /// a plain hole
+    #[default]
+    Plain,
+    /// a wider flat-bottomed hole, for socket head cap screws
+    Counterbore {
+        diameter: f64,
+        depth: f64,
+    },
+    /// a cone that narrows down to the hole, for flat head screws,
+    /// with the angle across the cone in radians
+    Countersink {
+        diameter: f64,
+        angle: f64,
+    },
+}
+
+/// A round hole cut into a part at a datum, which is the middle of a
+/// named vertex group. The hole goes in against the way the faces of
+/// the group face unless it's given a direction, and it's cut again
+/// wherever the datum ends up whenever the part's attributes change.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Hole {
+    name: String,
+    datum: String,
+    diameter: f64,
+    depth: Depth,
+    head: Head,
+    direction: Option<Direction>,
+}
+
+impl Screw {
+
+    // Sizes in millimeters, as the nominal diameter, the normal
+    // clearance hole from ISO 273, the tap drill, the counterbore for
+    // an ISO 4762 cap screw and the countersink for an ISO 10642 flat
+    // head screw
+    fn sizes(&self) -> [f64;5] {
+        match self {
+            Screw::M2 => [2.0,2.4,1.6,4.4,4.4],
+            Screw::M2_5 => [2.5,2.9,2.05,5.5,5.5],
+            Screw::M3 => [3.0,3.4,2.5,6.5,6.72],
+            Screw::M4 => [4.0,4.5,3.3,8.0,8.96],
+            Screw::M5 => [5.0,5.5,4.2,10.0,11.2],
+            Screw::M6 => [6.0,6.6,5.0,11.0,13.44],
+            Screw::M8 => [8.0,9.0,6.8,15.0,17.92],
+            Screw::M10 => [10.0,11.0,8.5,18.0,22.4],
+            Screw::M12 => [12.0,13.5,10.2,20.0,26.88],
+        }
+    }
+
+    fn size(&self, index: usize) -> f64 {
+        self.sizes()[index] / 1000.0
+    }
+
+    // The diameter of the thread
+    pub fn diameter(&self) -> f64 {
+        self.size(0)
+    }
+
+    // The hole the screw passes through freely
+    pub fn clearance(&self) -> f64 {
+        self.size(1)
+    }
+
+    // The hole that's drilled before the thread is tapped
+    pub fn tap_drill(&self) -> f64 {
+        self.size(2)
+    }
+
+    // A counterbore deep enough for the head to sit flush, which is
+    // as deep as the screw is wide
+    pub fn counterbore(&self) -> Head {
+        Head::Counterbore {
+            diameter: self.size(3),
+            depth: self.diameter(),
+        }
+    }
+
+    // A 90 degree countersink wide enough for the head to sit flush
+    pub fn countersink(&self) -> Head {
+        Head::Countersink {
+            diameter: self.size(4),
+            angle: 90f64.to_radians(),
+        }
+    }
+
+}
+
+impl Hole {
+
+    pub fn new<T: Into<String>, U: Into<String>>(name: T, datum: U, diameter: f64) -> Self {
+        Self {
+            name: name.into(),
+            datum: datum.into(),
+            diameter: diameter.abs(),
+            depth: Depth::Through,
+            head: Head::Plain,
+            direction: None,
+        }
+    }
+
+    // A through hole a screw passes through freely. Its head can be
+    // sunk into the part with the screw's `counterbore` or
+    // `countersink` through `with_head`.
+    pub fn clearance<T: Into<String>, U: Into<String>>(name: T, datum: U, screw: Screw) -> Self {
+        Self::new(name,datum,screw.clearance())
+    }
+
+    // A hole for a screw to be tapped into, which is drilled one and
+    // a half times the screw's diameter deep
+    pub fn tapped<T: Into<String>, U: Into<String>>(name: T, datum: U, screw: Screw) -> Self {
+        Self::new(name,datum,screw.tap_drill())
+            .with_depth(Depth::Blind(screw.diameter() * 1.5))
+    }
+
+    pub fn with_depth(mut self, depth: Depth) -> Self {
+        self.depth = match depth {
+            Depth::Blind(value) => Depth::Blind(value.abs()),
+            Depth::Through => Depth::Through,
+        };
+        self
+    }
+
+    pub fn with_head(mut self, head: Head) -> Self {
+        self.head = head;
+        self
+    }
+
+    // The way the hole goes into the part
+    pub fn with_direction(mut self, direction: Direction) -> Self {
+        self.direction = Some(direction.normalize());
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn datum(&self) -> &str {
+        &self.datum
+    }
+
+    pub fn diameter(&self) -> f64 {
+        self.diameter
+    }
+
+    pub fn depth(&self) -> Depth {
+        self.depth
+    }
+
+    pub fn head(&self) -> Head {
+        self.head
+    }
+
+    pub fn direction(&self) -> Option<Direction> {
+        self.direction
+    }
+
+    // Reads a hole named `name` from text written by `String::from`.
+    // The direction is read as it was written, since it was already
+    // made a unit vector.
+    pub(crate) fn parse(name: &str, text: &str) -> Result<Self,Error> {
+        let (datum,rest) = utilities::unquote(text)?;
+        let fields = rest.split_whitespace().collect::<Vec<&str>>();
+        let (diameter,depth,head,direction) = match fields.as_slice() {
+            [diameter,depth,head] => (diameter,depth,head,None),
+            [diameter,depth,head,direction] => (diameter,depth,head,Some(direction)),
+            _ => return Err(Error::ParseError),
+        };
+
+        let depth = match depth.split_once(':') {
+            None if *depth == "through" => Depth::Through,
+            Some(("blind",value)) => Depth::Blind(value.parse()?),
+            _ => return Err(Error::ParseError),
+        };
+        let head = match head.split_once(':').map(|(k,v)| (k,utilities::list::<f64>(v))) {
+            None if *head == "plain" => Head::Plain,
+            Some(("counterbore",Ok(v))) if v.len() == 2 => Head::Counterbore { diameter: v[0], depth: v[1] },
+            Some(("countersink",Ok(v))) if v.len() == 2 => Head::Countersink { diameter: v[0], angle: v[1] },
+            _ => return Err(Error::ParseError),
+        };
+        let direction = match direction.map(|d| utilities::list::<f64>(d)).transpose()?.as_deref() {
+            None => None,
+            Some(&[x,y,z]) => Some(Direction::new(x,y,z)),
+            Some(_) => return Err(Error::ParseError),
+        };
+
+        Ok(Self {
+            name: name.into(),
+            datum,
+            diameter: diameter.parse()?,
+            depth,
+            head,
+            direction,
+        })
+    }
+
+    // The solids that are cut out of the part for the hole, placed
+    // at the datum on `reference`. Each starts a little outside the
+    // surface so the top of the hole is cut cleanly.
+    pub(crate) fn tools(&self, reference: &Geometry) -> Result<Vec<Geometry>,Error> {
+        let (entry,inward) = self.placement(reference)?;
+        let reach = reference
+            .bounds()
+            .map(|b| b.size().magnitude())
+            .ok_or(Error::EmptyGeometry)?;
+
+        let radius = self.diameter / 2.0;
+        let margin = match self.head {
+            Head::Plain => radius,
+            Head::Counterbore { diameter, .. } => diameter / 2.0,
+            Head::Countersink { diameter, .. } => diameter / 2.0,
+        }
+        .max(radius) + reach * 0.01;
+        let start = entry - inward * margin;
+
+        let end = match self.depth {
+            Depth::Through => entry + inward * (reach * 2.0),
+            Depth::Blind(depth) => entry + inward * depth,
+        };
+        let mut tools = vec![frustum(start,end,radius,radius)];
+
+        match self.head {
+            Head::Plain => (),
+            Head::Counterbore { diameter, depth } => {
+                tools.push(frustum(start,entry + inward * depth,diameter / 2.0,diameter / 2.0));
+            },
+            Head::Countersink { diameter, angle } => {
+                let slope = (angle / 2.0).tan().max(f64::EPSILON);
+                let depth = (diameter / 2.0 - radius).max(0.0) / slope;
+                let top = diameter / 2.0 + margin * slope;
+                tools.push(frustum(start,entry + inward * depth,top,radius));
+            },
+        }
+        Ok(tools)
+    }
+
+    // The middle of the datum group and the way into the part there
+    fn placement(&self, reference: &Geometry) -> Result<(Vertex,Vector),Error> {
+        let indices = reference
+            .group(&self.datum)
+            .filter(|g| !g.is_empty())
+            .ok_or_else(|| Error::UnknownGroup(self.datum.clone()))?;
+
+        let vertices = reference.vertices();
+        let entry = indices
+            .iter()
+            .fold(Vertex::default(),|a,i| a + vertices[*i]) / indices.len();
+
+        if let Some(direction) = self.direction {
+            return Ok((entry,direction.vector()));
+        }
+
+        // the faces with all of their corners in the datum, weighted
+        // by their area, or the faces around it if there aren't any
+        let faces = reference
+            .faces()
+            .iter()
+            .filter(|f| f.is_valid(vertices))
+            .collect::<Vec<&Face>>();
+        let inside = faces
+            .iter()
+            .filter(|f| [f.a,f.b,f.c].iter().all(|i| indices.contains(i)))
+            .collect::<Vec<_>>();
+        let around = match inside.is_empty() {
+            true => faces
+                .iter()
+                .filter(|f| [f.a,f.b,f.c].iter().any(|i| indices.contains(i)))
+                .collect::<Vec<_>>(),
+            false => inside,
+        };
+
+        let outward = around
+            .iter()
+            .map(|f| f.triangle(vertices))
+            .fold(Vector::default(),|a,t| a + t.normal().vector() * t.area());
+        if outward.magnitude() <= f64::EPSILON {
+            return Err(Error::UnknownDirection(self.datum.clone()));
+        }
+        Ok((entry,outward.normalize() * -1.0))
+    }
+
+}
+
+// A hole is written as its datum, diameter, depth and head, and its
+// direction if it has one, without its name:
+//
+//   <datum> <diameter> through|blind:<depth> plain|counterbore:<diameter>,<depth>|countersink:<diameter>,<angle> [<x>,<y>,<z>]
+impl From<&Hole> for String {
+    fn from(hole: &Hole) -> Self {
+        let depth = match hole.depth {
+            Depth::Through => "through".into(),
+            Depth::Blind(depth) => format!("blind:{}",depth),
+        };
+        let head = match hole.head {
+            Head::Plain => "plain".into(),
+            Head::Counterbore { diameter, depth } => format!("counterbore:{},{}",diameter,depth),
+            Head::Countersink { diameter, angle } => format!("countersink:{},{}",diameter,angle),
+        };
+
+        let mut result = format!("{} {} {} {}",utilities::quote(&hole.datum,&[]),hole.diameter,depth,head);
+        if let Some(d) = hole.direction {
+            result.push_str(&format!(" {},{},{}",d.x,d.y,d.z));
+        }
+        result
+    }
+}
+
+// A closed solid between two points that's round with `SIDES` flat
+// sides, with one radius at `start` and another at `end`
+fn frustum(start: Vertex, end: Vertex, r0: f64, r1: f64) -> Geometry {
+    let axis = (end - start).normalize();
+    let side = match axis.x.abs() < 0.9 {
+        true => Vector::new(1.0,0.0,0.0),
+        false => Vector::new(0.0,1.0,0.0),
+    };
+    let u = axis.cross(&side).normalize();
+    let v = axis.cross(&u);
+
+    let ring = |center: Vertex, radius: f64| (0..SIDES).map(move |i| {
+        let (s,c) = (i as f64 * TAU / SIDES as f64).sin_cos();
+        center + u * (c * radius) + v * (s * radius)
+    });
+    let mut vertices = ring(start,r0).chain(ring(end,r1)).collect::<Vec<Vertex>>();
+    vertices.extend([start,end]);
+
+    // faces are numbered from 1
+    let (bottom,top) = (SIDES * 2 + 1,SIDES * 2 + 2);
+    let mut faces = Vec::with_capacity(SIDES * 4);
+    for i in 0..SIDES {
+        let j = (i + 1) % SIDES;
+        let (a,b,c,d) = (i + 1,j + 1,j + SIDES + 1,i + SIDES + 1);
+        faces.push(Face::make(a,b,c));
+        faces.push(Face::make(a,c,d));
+        faces.push(Face::make(bottom,b,a));
+        faces.push(Face::make(top,d,c));
+    }
+    Geometry::new(vertices,faces)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::part::{Part,Feature,Attribute,AttributeItem,Alteration,Selection};
+    use crate::testing;
+
+    // A unit cube with its top corners in a "top" group and a
+    // "Height" attribute that raises them
+    fn block() -> Part {
+        let geometry = testing::cube_with_top("top");
+        Part::new("block")
+            .with_geometry(geometry)
+            .with_attribute(Attribute::new("Height".into(),vec![
+                AttributeItem::new(Selection::group("top"),Alteration::translate(Vector::new(0.0,0.0,1.0)))
+            ]))
+            .build()
+            .unwrap()
+    }
+
+    fn area(diameter: f64) -> f64 {
+        SIDES as f64 / 2.0 * (diameter / 2.0).powi(2) * (TAU / SIDES as f64).sin()
+    }
+
+    #[test]
+    fn test_hole_drill() {
+        let mut part = block();
+        part.drill(&Hole::new("bore","top",0.2)).unwrap();
+        assert_relative_eq!(part.geometry().volume(),1.0 - area(0.2),epsilon = 1e-9);
+        assert!(!part.geometry().encloses(&Vertex::new(0.5,0.5,0.5)));
+
+        // the hole is cut again through the taller block
+        part.set("Height",0.5).unwrap();
+        assert_relative_eq!(part.geometry().volume(),1.5 * (1.0 - area(0.2)),epsilon = 1e-9);
+        assert!(matches!(part.feature("bore"),Some(Feature::Hole(h)) if h.diameter() == 0.2));
+
+        // a hole at a datum that doesn't exist leaves the part alone
+        let error = part.drill(&Hole::new("missing","side",0.1)).unwrap_err();
+        assert!(matches!(error.root(),Error::UnknownGroup(n) if n == "side"));
+        assert_eq!(part.features().len(),1);
+    }
+
+    #[test]
+    fn test_hole_heads() {
+        let counterbore = Hole::new("bore","top",0.2)
+            .with_depth(Depth::Blind(0.5))
+            .with_head(Head::Counterbore { diameter: 0.4, depth: 0.1 });
+        let mut part = block();
+        part.drill(&counterbore).unwrap();
+        let removed = area(0.2) * 0.4 + area(0.4) * 0.1;
+        assert_relative_eq!(part.geometry().volume(),1.0 - removed,epsilon = 1e-9);
+        assert!(part.geometry().encloses(&Vertex::new(0.5,0.5,0.4)));
+
+        // a 90 degree countersink is as deep as it's wide around the hole
+        let countersink = Hole::new("sink","top",0.2)
+            .with_head(Head::Countersink { diameter: 0.6, angle: 90f64.to_radians() });
+        let mut part = block();
+        part.drill(&countersink).unwrap();
+        let cone = (area(0.6) * 0.3 - area(0.2) * 0.1) / 3.0 - area(0.2) * 0.2;
+        assert_relative_eq!(part.geometry().volume(),1.0 - area(0.2) - cone,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_hole_frustum() {
+        // a cylinder a unit long is close to the real thing
+        let cylinder = frustum(Vertex::new(0.0,0.0,0.0),Vertex::new(0.0,0.0,1.0),0.5,0.5);
+        let area = SIDES as f64 / 2.0 * 0.25 * (TAU / SIDES as f64).sin();
+        assert_relative_eq!(cylinder.volume(),area,epsilon = 1e-9);
+        assert!(cylinder.edge_faces().values().all(|f| f.len() == 2));
+
+        let cone = frustum(Vertex::new(0.0,0.0,0.0),Vertex::new(1.0,0.0,0.0),0.5,0.0);
+        assert_relative_eq!(cone.volume(),area / 3.0,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_hole_screws() {
+        assert_relative_eq!(Screw::M3.clearance(),0.0034);
+        assert_relative_eq!(Screw::M6.tap_drill(),0.005);
+        let hole = Hole::tapped("mount","top",Screw::M4);
+        assert_eq!(hole.depth(),Depth::Blind(0.006));
+        assert!(matches!(Screw::M8.counterbore(),Head::Counterbore { diameter, .. } if diameter == 0.015));
+    }
+
+}
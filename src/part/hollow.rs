@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+
+use crate::geometry::*;
+use crate::part::{Attribute,AttributeItem,Alteration,Selection};
+use crate::errors::Error;
+use crate::constant::{Index,TOLERANCE};
+
+// the attribute that sets how thick the walls of a hollow part are
+pub(crate) const WALL_THICKNESS: &str = "Wall Thickness";
+
+// the group with the vertices of the inside of the walls
+pub(crate) const CAVITY: &str = "cavity";
+
+// the furthest a corner of the cavity moves for each unit of wall
+// thickness, so sharp points don't shoot out through the part
+const SPIKE: f64 = 4.0;
+
+/// Holes through the walls of a hollowed part, so that resin or
+/// powder can drain out of the inside after printing
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Drain {
+    diameter: f64,
+    holes: Vec<Vertex>,
+}
+
+impl Drain {
+
+    pub fn new(diameter: f64) -> Self {
+        Self {
+            diameter: diameter.abs(),
+            holes: Vec::new(),
+        }
+    }
+
+    // A hole through the wall at the point on the surface nearest to
+    // `point`. The hole takes out the faces with their middles within
+    // the diameter, or the nearest face if none are, so its shape
+    // follows the mesh.
+    pub fn with_hole(mut self, point: Vertex) -> Self {
+        self.holes.push(point);
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn diameter(&self) -> f64 {
+        self.diameter
+    }
+
+    pub fn holes(&self) -> &[Vertex] {
+        &self.holes
+    }
+
+}
+
+// Puts a copy of the surface, turned inside out, in the same place
+// as the outside so it can be moved in by the wall thickness, and
+// joins the two with a tube around each drain hole. Returns the new
+// geometry and the attribute that moves the inside in, which is
+// zero to start with. Groups and channels cover both surfaces.
+pub(crate) fn hollow(base: &Geometry, drain: &Drain) -> Result<(Geometry,Attribute),Error> {
+    let count = base.vertices().len();
+    let faces = base
+        .faces()
+        .iter()
+        .filter(|f| f.is_valid(base.vertices()))
+        .collect::<Vec<&Face>>();
+
+    let removed = drained(base,&faces,drain);
+
+    // the faces on the other side of each edge, going the other way
+    let edges = faces
+        .iter()
+        .enumerate()
+        .flat_map(|(i,f)| f.edges().into_iter().map(move |e| (e,i)))
+        .collect::<BTreeMap<(Index,Index),Index>>();
+
+    let mut result = Vec::with_capacity(faces.len() * 2);
+    for (index,face) in faces.iter().enumerate() {
+        if removed[index] {
+            for (a,b) in face.edges() {
+                if edges.get(&(b,a)).is_some_and(|other| !removed[*other]) {
+                    result.push(Face::make(a + 1,b + 1,b + count + 1));
+                    result.push(Face::make(a + 1,b + count + 1,a + count + 1));
+                }
+            }
+            continue;
+        }
+        result.push(Face::make(face.a + 1,face.b + 1,face.c + 1));
+        result.push(Face::make(face.a + count + 1,face.c + count + 1,face.b + count + 1));
+    }
+
+    let mut vertices = base.vertices().clone();
+    vertices.extend_from_within(..);
+    let mut geometry = Geometry::new(vertices,result);
+
+    for (name,indices) in base.groups().iter() {
+        let both = indices
+            .iter()
+            .copied()
+            .chain(indices.iter().map(|i| i + count))
+            .collect::<Vec<Index>>();
+        geometry.add_group(name.clone(),both)?;
+    }
+    for (name,values) in base.channels().iter() {
+        let both = values.repeat(2);
+        geometry.set_channel(name.clone(),both)?;
+    }
+    geometry.add_group(CAVITY,(count..count * 2).collect::<Vec<Index>>())?;
+
+    let items = inward(base,&faces)
+        .into_iter()
+        .enumerate()
+        .filter(|(_,v)| v.magnitude() > TOLERANCE)
+        .map(|(i,v)| AttributeItem::new(Selection::specific(vec![i + count]),Alteration::translate(v)))
+        .collect();
+
+    Ok((geometry,Attribute::new(WALL_THICKNESS.into(),items)))
+}
+
+// Which faces are taken out for the drain holes
+fn drained(base: &Geometry, faces: &[&Face], drain: &Drain) -> Vec<bool> {
+    let centroids = faces
+        .iter()
+        .map(|f| f.triangle(base.vertices()).centroid())
+        .collect::<Vec<Vertex>>();
+
+    let mut removed = vec![false;faces.len()];
+    for point in drain.holes.iter() {
+        let mut inside = centroids
+            .iter()
+            .enumerate()
+            .filter(|(_,c)| c.distance(point) <= drain.diameter / 2.0)
+            .map(|(i,_)| i)
+            .peekable();
+
+        if inside.peek().is_none() {
+            let nearest = centroids
+                .iter()
+                .enumerate()
+                .min_by(|(_,a),(_,b)| a.distance(point).total_cmp(&b.distance(point)))
+                .map(|(i,_)| i);
+            if let Some(i) = nearest {
+                removed[i] = true;
+            }
+            continue;
+        }
+        for i in inside {
+            removed[i] = true;
+        }
+    }
+    removed
+}
+
+// How far each vertex moves into the part for a unit of wall
+// thickness. Each moves against the normals around it, weighted by
+// the angle of each face at the vertex so the way a face is split
+// into triangles doesn't matter, far enough that the faces around
+// it each move in by a unit.
+fn inward(base: &Geometry, faces: &[&Face]) -> Vec<Vector> {
+    let vertices = base.vertices();
+    let mut sums = vec![Vector::default();vertices.len()];
+    let mut normals: Vec<Vec<Vector>> = vec![Vec::new();vertices.len()];
+    for face in faces.iter() {
+        let normal = face.normal(vertices).vector();
+        for (index,before,after) in [(face.a,face.c,face.b),(face.b,face.a,face.c),(face.c,face.b,face.a)] {
+            let corner = vertices[index];
+            let angle = (vertices[before] - corner).angle(&(vertices[after] - corner));
+            sums[index] = sums[index] + normal * angle;
+            normals[index].push(normal);
+        }
+    }
+
+    sums.into_iter()
+        .zip(normals)
+        .map(|(sum,around)| {
+            if sum.magnitude() <= f64::EPSILON {
+                return Vector::default();
+            }
+            let direction = sum.normalize();
+            let nearest = around
+                .iter()
+                .map(|n| n.dot(&direction))
+                .fold(1.0,f64::min)
+                .max(1.0 / SPIKE);
+            direction * (-1.0 / nearest)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::part::Part;
+    use crate::testing;
+
+    #[test]
+    fn test_hollow_walls() {
+        let mut part = Part::new("cube").with_geometry(testing::cube()).build().unwrap();
+        part.hollow(0.1,&Drain::default()).unwrap();
+        assert_eq!(part.geometry().size(),24);
+        assert_eq!(part.geometry().group(CAVITY).map(Vec::len),Some(8));
+        assert_relative_eq!(part.geometry().volume(),1.0 - 0.8f64.powi(3),epsilon = 1e-9);
+
+        // the walls are an attribute like any other
+        part.set(WALL_THICKNESS,0.2).unwrap();
+        assert_relative_eq!(part.geometry().volume(),1.0 - 0.6f64.powi(3),epsilon = 1e-9);
+        assert_relative_eq!(part.attribute(WALL_THICKNESS).unwrap().value(),0.2);
+    }
+
+    #[test]
+    fn test_hollow_drain() {
+        let mut part = Part::new("cube").with_geometry(testing::cube()).build().unwrap();
+        let drain = Drain::new(0.05).with_hole(Vertex::new(0.5,0.5,0.0));
+        part.hollow(0.1,&drain).unwrap();
+
+        // one face out of each side and a tube of three sides
+        let geometry = part.geometry();
+        assert_eq!(geometry.size(),12 + 12 - 2 + 6);
+        assert!(geometry.edge_faces().values().all(|f| f.len() == 2));
+        assert!(geometry.volume() < 1.0 - 0.8f64.powi(3));
+        assert!(geometry.volume() > 0.0);
+    }
+
+}
@@ -0,0 +1,172 @@
+use crate::errors::Error;
+use crate::constant::TOLERANCE;
+
+/// The cross-section of a connector
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Shape {
+    /// bolts, dowels and the holes they go in
+    #[default]
+    Round,
+    /// tenons and mortises
+    Square,
+    /// hex heads and sockets
+    Hex,
+    /// faces that are glued or screwed together
+    Flat,
+}
+
+/// Which side of a pair of connectors this one is
+#[derive(Default,Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Gender {
+    /// goes into the other connector, like a bolt
+    Male,
+    /// takes the other connector, like a hole
+    Female,
+    /// meets a connector like itself, like two faces
+    #[default]
+    Neutral,
+}
+
+/// What kind of connector a connection is, so that only connectors
+/// that fit each other can be mated
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Interface {
+    shape: Shape,
+    size: f64,
+    gender: Gender,
+    standard: Option<String>,
+}
+
+impl Shape {
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shape::Round => "round",
+            Shape::Square => "square",
+            Shape::Hex => "hex",
+            Shape::Flat => "flat",
+        }
+    }
+
+}
+
+impl Interface {
+
+    // A connector with a shape, a size across it in meters, and
+    // a gender
+    pub fn new(shape: Shape, size: f64, gender: Gender) -> Self {
+        Self { shape, size: size.abs(), gender, standard: None }
+    }
+
+    // A round male connector, like a bolt or dowel
+    pub fn bolt(size: f64) -> Self {
+        Self::new(Shape::Round,size,Gender::Male)
+    }
+
+    // A round female connector that a bolt of the same size fits
+    pub fn hole(size: f64) -> Self {
+        Self::new(Shape::Round,size,Gender::Female)
+    }
+
+    // The standard the connector is made to, like "M8" or "1/4-20",
+    // which connectors that both have one need to share
+    pub fn with_standard<T: Into<String>>(mut self, standard: T) -> Self {
+        self.standard = Some(standard.into());
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    pub fn size(&self) -> f64 {
+        self.size
+    }
+
+    pub fn gender(&self) -> Gender {
+        self.gender
+    }
+
+    pub fn standard(&self) -> Option<&str> {
+        self.standard.as_deref()
+    }
+
+    // True if the connectors fit together: the same shape, a male
+    // with a female or two neutral ones, the same standard if both
+    // have one, and sizes within `tolerance` of each other
+    pub fn fits(&self, other: &Interface, tolerance: f64) -> bool {
+        let genders = matches!(
+            (self.gender,other.gender),
+            (Gender::Male,Gender::Female) |
+            (Gender::Female,Gender::Male) |
+            (Gender::Neutral,Gender::Neutral)
+        );
+        let standards = match (&self.standard,&other.standard) {
+            (Some(a),Some(b)) => a == b,
+            _ => true,
+        };
+        let sizes = (self.size - other.size).abs() <= tolerance.max(TOLERANCE);
+        self.shape == other.shape && genders && standards && sizes
+    }
+
+    // Fails with an error naming both connectors if they don't fit
+    pub fn check(&self, other: &Interface, tolerance: f64) -> Result<(),Error> {
+        match self.fits(other,tolerance) {
+            true => Ok(()),
+            false => Err(Error::Incompatible(String::from(self),String::from(other))),
+        }
+    }
+
+}
+
+// An interface is described the way it would be asked for at
+// a hardware store, like "M8 bolt" or "6mm hole"
+impl From<&Interface> for String {
+    fn from(interface: &Interface) -> Self {
+        let size = match &interface.standard {
+            Some(standard) => standard.clone(),
+            None => format!("{}mm",(interface.size * 1e4).round() / 10.0),
+        };
+        let noun = match (interface.shape,interface.gender) {
+            (Shape::Round,Gender::Male) => "bolt",
+            (Shape::Round,Gender::Female) => "hole",
+            (Shape::Square,Gender::Male) => "tenon",
+            (Shape::Square,Gender::Female) => "mortise",
+            (Shape::Hex,Gender::Male) => "hex head",
+            (Shape::Hex,Gender::Female) => "hex socket",
+            (Shape::Flat,_) => "face",
+            (shape,Gender::Neutral) => return format!("{} {} face",size,shape.name()),
+        };
+        format!("{} {}",size,noun)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_interface_fits() {
+        let bolt = Interface::bolt(0.008).with_standard("M8");
+        let hole = Interface::hole(0.008);
+        assert!(bolt.fits(&hole,0.0));
+        assert!(hole.fits(&bolt,0.0));
+        assert!(!bolt.fits(&bolt,0.0));
+        assert!(!bolt.fits(&Interface::hole(0.008).with_standard("5/16"),0.0));
+        assert!(!bolt.fits(&Interface::new(Shape::Hex,0.008,Gender::Female),0.0));
+        assert!(bolt.fits(&Interface::hole(0.0085),0.001));
+
+        let error = bolt.check(&Interface::hole(0.006),0.0).unwrap_err();
+        assert_eq!(error.to_string(),"M8 bolt cannot mate with 6mm hole");
+
+        let face = Interface::new(Shape::Flat,0.0,Gender::Neutral);
+        assert!(face.fits(&face,0.0));
+        assert_eq!(String::from(&Interface::new(Shape::Square,0.0127,Gender::Neutral)),"12.7mm square face");
+    }
+
+}
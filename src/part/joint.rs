@@ -0,0 +1,122 @@
+use crate::geometry::{Vertex,Direction,Matrix,Transform};
+
+/// How a part can move around one of its connections. Axes are in
+/// the part's own space, and values are radians for revolute joints
+/// and meters for prismatic ones.
+#[derive(Default,Debug,Copy,Clone,PartialEq)]
+pub enum Joint {
+    /// the part can't move, like a glued or screwed joint
+    #[default]
+    Fixed,
+    /// the part turns around an axis through the connection,
+    /// like a door on a hinge
+    Revolute { axis: Direction, min: f64, max: f64 },
+    /// the part slides along an axis, like a drawer
+    Prismatic { axis: Direction, min: f64, max: f64 },
+}
+
+impl Joint {
+
+    // A hinge turning around `axis` between two angles in radians
+    pub fn revolute(axis: Direction, min: f64, max: f64) -> Self {
+        Self::Revolute { axis: axis.normalize(), min: min.min(max), max: min.max(max) }
+    }
+
+    // A slide along `axis` between two distances in meters
+    pub fn prismatic(axis: Direction, min: f64, max: f64) -> Self {
+        Self::Prismatic { axis: axis.normalize(), min: min.min(max), max: min.max(max) }
+    }
+
+    // How many ways the joint lets the part move
+    pub fn freedom(&self) -> usize {
+        match self {
+            Self::Fixed => 0,
+            _ => 1,
+        }
+    }
+
+    // The axis the joint turns around or slides along, or None
+    // for a fixed joint
+    pub fn axis(&self) -> Option<Direction> {
+        match self {
+            Self::Fixed => None,
+            Self::Revolute { axis, .. } |
+            Self::Prismatic { axis, .. } => Some(*axis),
+        }
+    }
+
+    // The smallest and largest value the joint can take
+    pub fn range(&self) -> (f64,f64) {
+        match self {
+            Self::Fixed => (0.0,0.0),
+            Self::Revolute { min, max, .. } |
+            Self::Prismatic { min, max, .. } => (*min,*max),
+        }
+    }
+
+    // The value moved into the joint's range
+    pub fn clamp(&self, value: f64) -> f64 {
+        let (min,max) = self.range();
+        value.clamp(min,max)
+    }
+
+    // The motion of a part with the joint at `value`, where `point`
+    // is the connection the joint is at. Values outside the range
+    // are clamped.
+    pub fn motion(&self, point: Vertex, value: f64) -> Matrix {
+        let value = self.clamp(value);
+        match self {
+            Self::Fixed => Matrix::identity(),
+            Self::Revolute { axis, .. } => {
+                Matrix::translate(point.x,point.y,point.z) *
+                Matrix::rotate_axis(axis.vector(),value) *
+                Matrix::translate(-point.x,-point.y,-point.z)
+            },
+            Self::Prismatic { axis, .. } => {
+                let offset = axis.vector() * value;
+                Matrix::translate(offset.x,offset.y,offset.z)
+            },
+        }
+    }
+
+}
+
+// Turns the axis, which keeps prismatic distances in meters
+// along the new axis
+impl Transform for Joint {
+    fn transform(&mut self, matrix: &Matrix) {
+        if let Self::Revolute { axis, .. } | Self::Prismatic { axis, .. } = self {
+            axis.transform(matrix);
+            *axis = axis.normalize();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::f64::consts::FRAC_PI_2;
+
+    #[test]
+    fn test_joint_motion() {
+        let hinge = Joint::revolute(Direction::new(0.0,0.0,2.0),FRAC_PI_2,0.0);
+        assert_eq!(hinge.range(),(0.0,FRAC_PI_2));
+        assert_eq!(hinge.freedom(),1);
+
+        // a quarter turn around a hinge at x = 1
+        let mut v = Vertex::new(2.0,0.0,0.0);
+        v.transform(&hinge.motion(Vertex::new(1.0,0.0,0.0),3.0));
+        assert_relative_eq!(v.x,1.0,epsilon = 1e-12);
+        assert_relative_eq!(v.y,1.0,epsilon = 1e-12);
+
+        let slide = Joint::prismatic(Direction::new(0.0,-1.0,0.0),0.0,0.5);
+        let mut v = Vertex::new(0.0,0.0,0.0);
+        v.transform(&slide.motion(Vertex::new(1.0,1.0,1.0),0.25));
+        assert_eq!(v,Vertex::new(0.0,-0.25,0.0));
+
+        assert_eq!(Joint::Fixed.motion(Vertex::new(1.0,1.0,1.0),1.0).unpack(),Matrix::identity().unpack());
+        assert_eq!(Joint::Fixed.freedom(),0);
+    }
+
+}
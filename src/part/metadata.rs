@@ -1,5 +1,380 @@
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
 
+use crate::part::Color;
+use crate::geometry::{Vector,Direction};
+use crate::errors::Error;
+use crate::constant::METADATA_TAG;
+use crate::utilities;
+
+/// Information about a part that doesn't change its shape
 #[derive(Default,Debug,Clone)]
 pub struct Metadata {
+    color: Option<Color>,
+    layer: Option<String>,
+    tags: BTreeSet<String>,
+    grain: Option<Direction>,
+    material: Option<String>,
+    sku: Option<String>,
+}
+
+/// Picks parts by their layer and tags
+#[derive(Default,Debug,Clone,PartialEq)]
+pub enum Filter {
+    /// every part
+    #[default]
+    All,
+    /// parts on the named layer
+    Layer(String),
+    /// parts with the named tag
+    Tag(String),
+    /// parts that don't match the inner filter
+    Not(Box<Filter>),
+    /// parts that match every inner filter
+    Every(Vec<Filter>),
+}
+
+impl Metadata {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The color the part is drawn and exported in
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn with_layer<T: Into<String>>(mut self, layer: T) -> Self {
+        self.layer = Some(layer.into());
+        self
+    }
+
+    pub fn with_tag<T: Into<String>>(mut self, tag: T) -> Self {
+        self.tags.insert(tag.into());
+        self
+    }
+
+    // The direction the grain runs in, in the part's own space
+    pub fn with_grain(mut self, grain: Direction) -> Self {
+        self.grain = Some(grain.normalize());
+        self
+    }
+
+    // The name of the stock material the part is made from
+    pub fn with_material<T: Into<String>>(mut self, material: T) -> Self {
+        self.material = Some(material.into());
+        self
+    }
+
+    // The code a supplier sells the part under
+    pub fn with_sku<T: Into<String>>(mut self, sku: T) -> Self {
+        self.sku = Some(sku.into());
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub fn color(&self) -> Option<Color> {
+        self.color
+    }
+
+    pub fn set_color(&mut self, color: Option<Color>) {
+        self.color = color;
+    }
+
+    pub fn layer(&self) -> Option<&str> {
+        self.layer.as_deref()
+    }
+
+    pub fn set_layer(&mut self, layer: Option<String>) {
+        self.layer = layer;
+    }
+
+    pub fn tags(&self) -> &BTreeSet<String> {
+        &self.tags
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+
+    // Returns false if the tag was already there
+    pub fn add_tag<T: Into<String>>(&mut self, tag: T) -> bool {
+        self.tags.insert(tag.into())
+    }
+
+    // Returns false if the tag wasn't there
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        self.tags.remove(tag)
+    }
+
+    pub fn material(&self) -> Option<&str> {
+        self.material.as_deref()
+    }
+
+    pub fn set_material(&mut self, material: Option<String>) {
+        self.material = material;
+    }
+
+    pub fn sku(&self) -> Option<&str> {
+        self.sku.as_deref()
+    }
+
+    pub fn set_sku(&mut self, sku: Option<String>) {
+        self.sku = sku;
+    }
+
+    pub fn grain(&self) -> Option<Direction> {
+        self.grain
+    }
+
+    pub fn set_grain(&mut self, grain: Option<Direction>) {
+        self.grain = grain.map(|g| g.normalize());
+    }
+
+    // Builds metadata from `meta` lines. Other lines are ignored.
+    pub fn parse_lines(text: &str) -> Result<Metadata,Error> {
+        let mut result = Metadata::new();
+        for line in text.lines().map(utilities::strip_comment) {
+            let rest = match line.strip_prefix(METADATA_TAG) {
+                Some(r) if r.starts_with(' ') => r,
+                _ => continue,
+            };
+
+            let (field,rest) = utilities::token(rest);
+            if field == "grain" {
+                let values = rest
+                    .split_whitespace()
+                    .map(str::parse::<f64>)
+                    .collect::<Result<Vec<f64>,_>>()?;
+                match values.as_slice() {
+                    &[x,y,z] => result.grain = Some(Direction::new(x,y,z)),
+                    _ => return Err(Error::ParseError),
+                }
+                continue;
+            }
+
+            let value = match utilities::unquote(rest)? {
+                (value,rest) if rest.trim().is_empty() => value,
+                _ => return Err(Error::ParseError),
+            };
+            match field {
+                "color" => result.color = Some(Color::try_from(value.as_str())?),
+                "layer" => result.layer = Some(value),
+                "tag" => { result.tags.insert(value); },
+                "material" => result.material = Some(value),
+                "sku" => result.sku = Some(value),
+                _ => return Err(Error::ParseError),
+            }
+        }
+        Ok(result)
+    }
+
+    // The angle in radians between a load and the grain, from 0 when
+    // the load runs along the grain to PI/2 when it runs across it.
+    // None if the part doesn't have a grain.
+    pub fn grain_angle(&self, load: &Vector) -> Option<f64> {
+        self.grain.map(|g| g.axis_angle(load))
+    }
+
+}
+
+impl Filter {
+
+    pub fn layer<T: Into<String>>(name: T) -> Self {
+        Self::Layer(name.into())
+    }
+
+    pub fn tag<T: Into<String>>(name: T) -> Self {
+        Self::Tag(name.into())
+    }
+
+    pub fn matches(&self, metadata: &Metadata) -> bool {
+        match self {
+            Filter::All => true,
+            Filter::Layer(name) => metadata.layer() == Some(name.as_str()),
+            Filter::Tag(name) => metadata.has_tag(name),
+            Filter::Not(filter) => !filter.matches(metadata),
+            Filter::Every(filters) => filters.iter().all(|f| f.matches(metadata)),
+        }
+    }
+
+}
+
+// Metadata is written as a line for each piece that's set, with
+// names quoted if they have spaces or a `#`, and nothing if none are:
+//
+//   meta color "<#rrggbb>"
+//   meta layer <name>
+//   meta tag <name>
+//   meta grain <x> <y> <z>
+//   meta material <name>
+//   meta sku <code>
+impl From<&Metadata> for String {
+    fn from(metadata: &Metadata) -> Self {
+        let line = |field: &str, value: &str| format!("{} {} {}",METADATA_TAG,field,utilities::quote(value,&[]));
+        let mut lines = Vec::new();
+        if let Some(color) = &metadata.color {
+            lines.push(line("color",&String::from(color)));
+        }
+        if let Some(layer) = &metadata.layer {
+            lines.push(line("layer",layer));
+        }
+        for tag in metadata.tags.iter() {
+            lines.push(line("tag",tag));
+        }
+        if let Some(g) = metadata.grain {
+            lines.push(format!("{} grain {} {} {}",METADATA_TAG,g.x,g.y,g.z));
+        }
+        if let Some(material) = &metadata.material {
+            lines.push(line("material",material));
+        }
+        if let Some(sku) = &metadata.sku {
+            lines.push(line("sku",sku));
+        }
+        lines.join("\n")
+    }
+}
+
+// Filters are written as comma separated terms that all have to
+// match, like `layer=framing,tag=exterior,!tag=temporary`, or `*`
+// for everything.
+impl TryFrom<&str> for Filter {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let term = |text: &str| -> Result<Filter,Error> {
+            let (negated,text) = match text.strip_prefix('!') {
+                Some(rest) => (true,rest.trim()),
+                None => (false,text),
+            };
+
+            let filter = match text.split_once('=') {
+                _ if text == "*" => Filter::All,
+                Some(("layer",name)) if !name.trim().is_empty() => Filter::layer(name.trim()),
+                Some(("tag",name)) if !name.trim().is_empty() => Filter::tag(name.trim()),
+                _ => return Err(Error::ParseError),
+            };
+
+            match negated {
+                true => Ok(Filter::Not(Box::new(filter))),
+                false => Ok(filter),
+            }
+        };
+
+        let mut filters = value
+            .split(',')
+            .map(str::trim)
+            .map(term)
+            .collect::<Result<Vec<Filter>,Error>>()?;
+
+        match filters.len() {
+            1 => Ok(filters.remove(0)),
+            _ => Ok(Filter::Every(filters)),
+        }
+    }
+}
+
+// Filters are written the way they're read. There's no way to
+// write a negated group, so only negated terms round trip.
+impl From<&Filter> for String {
+    fn from(filter: &Filter) -> Self {
+        match filter {
+            Filter::All => "*".into(),
+            Filter::Layer(name) => format!("layer={}",name),
+            Filter::Tag(name) => format!("tag={}",name),
+            Filter::Not(inner) => format!("!{}",String::from(inner.as_ref())),
+            Filter::Every(filters) => filters
+                .iter()
+                .map(String::from)
+                .collect::<Vec<String>>()
+                .join(","),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::f64::consts::{FRAC_PI_2,FRAC_PI_4};
+
+    #[test]
+    fn test_filter_matches() {
+        let metadata = Metadata::new()
+            .with_layer("framing")
+            .with_tag("exterior");
+
+        assert!(Filter::All.matches(&metadata));
+        assert!(Filter::layer("framing").matches(&metadata));
+        assert!(!Filter::layer("roof").matches(&metadata));
+        assert!(Filter::tag("exterior").matches(&metadata));
+        assert!(!Filter::tag("exterior").matches(&Metadata::new()));
+        assert!(!Filter::Not(Box::new(Filter::tag("exterior"))).matches(&metadata));
+    }
+
+    #[test]
+    fn test_filter_from_string() {
+        let metadata = Metadata::new()
+            .with_layer("framing")
+            .with_tag("exterior");
+
+        let filter = Filter::try_from("layer=framing, !tag=temporary").unwrap();
+        assert!(filter.matches(&metadata));
+        assert!(!filter.matches(&metadata.clone().with_tag("temporary")));
+
+        assert_eq!(Filter::try_from("layer=framing").unwrap(),Filter::layer("framing"));
+        assert_eq!(Filter::try_from("*").unwrap(),Filter::All);
+        assert!(Filter::try_from("layer=").is_err());
+        assert!(Filter::try_from("size=2").is_err());
+        assert!(Filter::try_from("").is_err());
+
+        let text = String::from(&filter);
+        assert_eq!(text,"layer=framing,!tag=temporary");
+        assert_eq!(Filter::try_from(text.as_str()).unwrap(),filter);
+    }
+
+    #[test]
+    fn test_metadata_grain() {
+        let mut metadata = Metadata::new().with_grain(Direction::new(2.0,0.0,0.0));
+        assert_eq!(metadata.grain(),Some(Direction::new(1.0,0.0,0.0)));
+
+        let angle = |m: &Metadata, x, y| m.grain_angle(&Vector::new(x,y,0.0)).unwrap();
+        assert_eq!(angle(&metadata,-3.0,0.0),0.0);
+        assert_relative_eq!(angle(&metadata,0.0,1.0),FRAC_PI_2);
+        assert_relative_eq!(angle(&metadata,-1.0,1.0),FRAC_PI_4);
+
+        metadata.set_grain(None);
+        assert_eq!(metadata.grain_angle(&Vector::new(1.0,0.0,0.0)),None);
+    }
+
+    #[test]
+    fn test_metadata_string() {
+        assert_eq!(String::from(&Metadata::new()),"");
+
+        let metadata = Metadata::new()
+            .with_color(Color::new(255,128,0).with_alpha(10))
+            .with_layer("wall framing")
+            .with_tag("exterior")
+            .with_tag("load \"bearing\"")
+            .with_grain(Direction::new(1.0,2.0,0.5))
+            .with_material("SPF")
+            .with_sku("2x4-8");
+        let text = String::from(&metadata);
+        assert!(text.starts_with("meta color \"#ff80000a\"\nmeta layer \"wall framing\"\nmeta tag exterior\n"));
+
+        let parsed = Metadata::parse_lines(&text).unwrap();
+        assert_eq!(String::from(&parsed),text);
+        assert_eq!(parsed.grain(),metadata.grain());
+        assert!(parsed.has_tag("load \"bearing\""));
+
+        assert!(Metadata::parse_lines("meta weight 2").is_err());
+        assert!(Metadata::parse_lines("meta grain 1 0").is_err());
+        assert!(Metadata::parse_lines("meta layer wall framing").is_err());
+    }
 
-}
\ No newline at end of file
+}
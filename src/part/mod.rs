@@ -1,12 +1,39 @@
 
+#[allow(clippy::module_inception)]
 mod part;
 mod attribute;
 mod connection;
+mod joint;
+mod interface;
 mod metadata;
 mod alteration;
+mod context;
+mod color;
+mod catalog;
+mod diff;
+mod hollow;
+mod displacement;
+mod hole;
+mod feature;
+#[cfg(feature = "text")]
+mod engraving;
 
-pub use part::Part;
-pub use attribute::{Attribute,AttributeItem};
+pub use part::{Part,Origin};
+pub use attribute::{Attribute,AttributeItem,Selection};
 pub use connection::Connection;
-pub use metadata::Metadata;
-pub use alteration::Alteration;
\ No newline at end of file
+pub use joint::Joint;
+pub use interface::{Interface,Shape,Gender};
+pub use metadata::{Metadata,Filter};
+pub use alteration::{Alteration,Scaling,Space};
+pub use context::{EvalContext,Units};
+pub use color::Color;
+pub use catalog::{Catalog,Duplicate};
+pub use diff::{Diff,Change};
+pub use hollow::Drain;
+pub use displacement::{Displacement,HeightField};
+pub use hole::{Hole,Screw,Depth,Head};
+pub use feature::{Feature,Pattern,Action,HistoryStep};
+#[cfg(feature = "lyon")]
+pub use feature::Extrusion;
+#[cfg(feature = "text")]
+pub use engraving::Engraving;
\ No newline at end of file
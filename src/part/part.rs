@@ -1,11 +1,35 @@
+use std::convert::TryFrom;
+use std::collections::BTreeSet;
+
+use crate::utilities;
 use crate::geometry::*;
 use crate::part::*;
+use crate::part::hollow;
+use crate::errors::{Error,Context};
+use crate::constant::{OBJECT_TAG,SUPPRESS_TAG,ROLLBACK_TAG,CONNECTION_TAG};
+
+/// Where a part's own origin is put by `Part::normalize_origin`
+#[derive(Default,Debug,Clone,PartialEq,Eq)]
+pub enum Origin {
+    /// the centre of the volume
+    #[default]
+    Centroid,
+    /// the lowest corner of the bounding box
+    Corner,
+    /// the middle of the vertices in a named group
+    Datum(String),
+}
 
-#[derive(Default,Debug)]
+#[derive(Default,Debug,Clone)]
 pub struct Part {
     name: String,
+    base: Geometry,
     geometry: Geometry,
-    // attributes: Vec<Attribute>,
+    attributes: Vec<Attribute>,
+    features: Vec<Feature>,
+    suppressed: BTreeSet<String>,
+    rollback: Option<usize>,
+    frame: Option<Matrix>,
     connections: Vec<Connection>,
     metadata: Metadata,
 }
@@ -19,15 +43,27 @@ impl Part {
         }
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     pub fn with_geometry(mut self, geometry: Geometry) -> Self {
+        self.base = geometry.clone();
         self.geometry = geometry;
         self
     }
 
-    // pub fn with_attribute(mut self, attribute: Attribute) -> Self {
-    //     self.attributes.push(attribute);
-    //     self
-    // }
+    pub fn with_attribute(mut self, attribute: Attribute) -> Self {
+        self.attributes.push(attribute);
+        self
+    }
+
+    // Adds a feature to the end of the history, which is applied
+    // when the part is built
+    pub fn with_feature<T: Into<Feature>>(mut self, feature: T) -> Self {
+        self.features.push(feature.into());
+        self
+    }
 
     pub fn with_connection(mut self, connection: Connection) -> Self {
         self.connections.push(connection);
@@ -39,16 +75,792 @@ impl Part {
         self
     }
 
-    pub fn build(mut self) -> Self {
-        /*
-            verify:
-                1. attributes map to real geometry
-                2. connection points are on surface
-                3. geometry is not empty
-        */
-        self
+    // The geometry with every attribute and feature applied
+    pub fn geometry(&self) -> &Geometry {
+        &self.geometry
+    }
+
+    // The geometry before any attributes are applied
+    pub fn base(&self) -> &Geometry {
+        &self.base
+    }
+
+    pub fn attributes(&self) -> &[Attribute] {
+        &self.attributes
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes
+            .iter()
+            .find(|a| a.name() == name)
+    }
+
+    // Features in the order they're applied
+    pub fn features(&self) -> &[Feature] {
+        &self.features
+    }
+
+    pub fn feature(&self, name: &str) -> Option<&Feature> {
+        self.features
+            .iter()
+            .find(|f| f.name() == name)
+    }
+
+    // The transform of the instance the part is in, which alterations
+    // in world space are made through whenever the part is rebuilt
+    pub fn frame(&self) -> Option<&Matrix> {
+        self.frame.as_ref()
+    }
+
+    // Set by the instance holding the part when it's created or moved,
+    // rebuilding the part if it makes changes in world space. The
+    // frame is left as it was if the part can't be rebuilt with it.
+    pub(crate) fn set_frame(&mut self, frame: Option<Matrix>) -> Result<(),Error> {
+        let old = std::mem::replace(&mut self.frame,frame);
+        if self.attributes.iter().any(Attribute::is_world) {
+            if let Err(error) = self.evaluate() {
+                self.frame = old;
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    // Gives the part the frame of its instance without rebuilding it
+    pub(crate) fn place(&mut self, frame: Option<Matrix>) {
+        self.frame = frame;
+    }
+
+    // The context the part is rebuilt with when it isn't given one
+    fn context(&self) -> EvalContext {
+        match self.frame {
+            Some(frame) => EvalContext::new().with_frame(frame),
+            None => EvalContext::new(),
+        }
+    }
+
+    // Changes the value of an attribute and re-evaluates the geometry
+    pub fn set(&mut self, name: &str, value: f64) -> Result<(),Error> {
+        self.set_with(name,value,&self.context())
+    }
+
+    // Like `set`, re-evaluating the geometry for a context, which
+    // for a part in an assembly has the frame of its instance. The
+    // part is left unchanged if the geometry can't be evaluated.
+    pub fn set_with(&mut self, name: &str, value: f64, context: &EvalContext) -> Result<(),Error> {
+        let index = self.attribute_index(name)?;
+        let old = self.attributes[index].clone();
+        self.attributes[index].update(value);
+        match self.evaluate_with(context) {
+            Ok(geometry) => {
+                self.geometry = geometry;
+                Ok(())
+            },
+            Err(error) => {
+                self.attributes[index] = old;
+                Err(error)
+            },
+        }
+    }
+
+    // Removes an attribute and re-evaluates the geometry without it.
+    // The part is left unchanged if the geometry can't be evaluated.
+    pub fn remove_attribute(&mut self, name: &str) -> Result<Attribute,Error> {
+        let index = self.attribute_index(name)?;
+        let attribute = self.attributes.remove(index);
+        if let Err(error) = self.evaluate() {
+            self.attributes.insert(index,attribute);
+            return Err(error);
+        }
+        Ok(attribute)
+    }
+
+    fn attribute_index(&self, name: &str) -> Result<usize,Error> {
+        self.attributes
+            .iter()
+            .position(|a| a.name() == name)
+            .ok_or_else(|| Error::UnknownAttribute(name.into()))
+            .in_part(&self.name)
+    }
+
+    // Rebuilds the geometry by applying every attribute, in the
+    // order they were added, to the base geometry, and then every
+    // feature, through the frame of the part's instance if it has
+    // one. The geometry is left unchanged if any attribute or
+    // feature fails to apply.
+    pub fn evaluate(&mut self) -> Result<(),Error> {
+        self.geometry = self.evaluate_with(&self.context())?;
+        Ok(())
+    }
+
+    // Builds the geometry for a context without changing the part.
+    // Parameters in the context override attribute values, and the
+    // result is converted into the context's units.
+    pub fn evaluate_with(&self, context: &EvalContext) -> Result<Geometry,Error> {
+        span!("part.evaluate", name = %self.name, attributes = self.attributes.len(), features = self.features.len());
+        let mut geometry = self.shape(context)?;
+
+        // features find their datums on the geometry before any of
+        // them are applied, since booleans don't keep groups
+        if !self.features.is_empty() {
+            let reference = geometry.clone();
+            let active = self.active();
+            for (index,feature) in self.features.iter().enumerate().filter(|(i,_)| active[*i]) {
+                feature
+                    .apply(&reference,&self.features[..index],context,&mut geometry)
+                    .in_feature(feature.name())
+                    .in_part(&self.name)?;
+            }
+        }
+
+        let scale = context.units().per_meter();
+        if scale != 1.0 {
+            geometry.transform(&Matrix::scale(scale,scale,scale));
+        }
+
+        Ok(geometry)
+    }
+
+    // Rebuilds the part from the start like `evaluate`, but carries on
+    // past features that fail, leaving them out, so every problem in
+    // the history is found at once. Fails only if an attribute does.
+    pub fn history(&self) -> Result<Vec<HistoryStep>,Error> {
+        self.history_with(&self.context())
+    }
+
+    // Like `history`, building the geometry for a context the way
+    // `evaluate_with` does
+    pub fn history_with(&self, context: &EvalContext) -> Result<Vec<HistoryStep>,Error> {
+        let mut geometry = self.shape(context)?;
+        let reference = geometry.clone();
+
+        let active = self.active();
+        let mut steps = Vec::with_capacity(self.features.len());
+        for (index,feature) in self.features.iter().enumerate() {
+            if !active[index] {
+                steps.push(HistoryStep {
+                    name: feature.name().into(),
+                    error: None,
+                    suppressed: true,
+                    faces: geometry.faces().len(),
+                });
+                continue;
+            }
+            let mut result = geometry.clone();
+            let error = feature
+                .apply(&reference,&self.features[..index],context,&mut result)
+                .in_feature(feature.name())
+                .in_part(&self.name)
+                .err();
+            if error.is_none() {
+                geometry = result;
+            }
+            steps.push(HistoryStep {
+                name: feature.name().into(),
+                error,
+                suppressed: false,
+                faces: geometry.faces().len(),
+            });
+        }
+        Ok(steps)
+    }
+
+    // Which features are applied. Suppressed features aren't, nor
+    // are patterns of them or features after the rollback.
+    fn active(&self) -> Vec<bool> {
+        let end = self.rollback.unwrap_or(self.features.len());
+        let mut result: Vec<bool> = Vec::with_capacity(self.features.len());
+        for (index,feature) in self.features.iter().enumerate() {
+            let mut active = index < end && !self.suppressed.contains(feature.name());
+            if let Feature::Pattern(pattern) = feature {
+                let source = self.features[..index]
+                    .iter()
+                    .position(|f| f.name() == pattern.feature());
+                if let Some(source) = source {
+                    active &= result[source];
+                }
+            }
+            result.push(active);
+        }
+        result
+    }
+
+    // The base geometry with every attribute applied
+    fn shape(&self, context: &EvalContext) -> Result<Geometry,Error> {
+        let mut geometry = self.base.clone();
+        geometry.clear_changes();
+
+        // selections are resolved against the base geometry so
+        // they can be cached between evaluations
+        for attribute in self.attributes.iter() {
+            let result = match context.parameter(attribute.name()) {
+                Some(value) if value != attribute.value() => {
+                    let mut attribute = attribute.clone();
+                    attribute.update(value);
+                    Self::revise(context,&attribute,&self.base,&mut geometry)
+                },
+                _ => Self::revise(context,attribute,&self.base,&mut geometry),
+            };
+            result
+                .in_attribute(attribute.name())
+                .in_part(&self.name)?;
+        }
+        Ok(geometry)
+    }
+
+    fn revise(context: &EvalContext, attribute: &Attribute, base: &Geometry, geometry: &mut Geometry) -> Result<(),Error> {
+        if !context.allow_collapse() && attribute.collapses() {
+            return Err(Error::FixedAttribute);
+        }
+        match context.frame() {
+            Some(frame) if attribute.is_world() => attribute.framed(*frame).revise_from(base,geometry),
+            _ => attribute.revise_from(base,geometry),
+        }
+    }
+
+    // Reads a part written by `String::from(&Part)`, taking the
+    // name from the first `o` line.
+    pub fn parse(text: &str, mode: ParseMode) -> Result<Self,Error> {
+        let name = utilities::object_name(text).unwrap_or_default();
+        let geometry = Geometry::parse(text,mode).in_part(&name)?;
+
+        let mut part = Part::new(name).with_geometry(geometry);
+        part.attributes = Attribute::parse_lines(text).in_part(&part.name)?;
+        part.features = Feature::parse_lines(text).in_part(&part.name)?;
+        part.metadata = Metadata::parse_lines(text).in_part(&part.name)?;
+        part.read_lines(text).in_part(&part.name)?;
+        part.build()
+    }
+
+    // Reads the suppressed features, the rollback and the
+    // connections, once the features are read
+    fn read_lines(&mut self, text: &str) -> Result<(),Error> {
+        for line in text.lines().map(utilities::strip_comment) {
+            let (tag,rest) = utilities::token(line);
+            match tag {
+                SUPPRESS_TAG => {
+                    let name = match utilities::unquote(rest)? {
+                        (name,rest) if rest.trim().is_empty() => name,
+                        _ => return Err(Error::ParseError),
+                    };
+                    if self.features.iter().all(|f| f.name() != name) {
+                        return Err(Error::UnknownFeature(name));
+                    }
+                    self.suppressed.insert(name);
+                },
+                ROLLBACK_TAG => {
+                    let count = rest.trim().parse()?;
+                    if count > self.features.len() {
+                        return Err(Error::IndexOutOfRange { index: count, len: self.features.len() });
+                    }
+                    self.rollback = Some(count);
+                },
+                CONNECTION_TAG => self.connections.push(Connection::try_from(line)?),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn connections(&self) -> &[Connection] {
+        &self.connections
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    // Everything that changed from this part to `other`, like the
+    // attributes, features, connections and metadata, and how the base
+    // geometry moved. It's empty if the parts are the same.
+    pub fn diff(&self, other: &Part) -> Diff {
+        Diff::new(self,other)
+    }
+
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
+    // Adds text to a face of the base geometry, along with an
+    // attribute that sets how far it's raised or sunk. The part
+    // is left unchanged if the face doesn't exist or the part can't
+    // be built with the text.
+    #[cfg(feature = "text")]
+    pub fn engrave(&mut self, engraving: &Engraving) -> Result<(),Error> {
+        let len = self.base.faces().len();
+        let face = self.base
+            .faces()
+            .get(engraving.face())
+            .ok_or(Error::IndexOutOfRange { index: engraving.face(), len })?;
+
+        let (matrix,direction) = engraving.placement(&face.triangle(self.base.vertices()));
+        let text = engraving.geometry()?;
+
+        let mut base = self.base.clone();
+        base.append_prefixed(engraving.name(),&text,&matrix);
+
+        let mut attribute = Attribute::new(engraving.attribute(),vec![
+            AttributeItem::new(Selection::group(engraving.group()),Alteration::translate(direction))
+        ]);
+        attribute.update(engraving.depth());
+        self.extend(base,attribute)
+    }
+
+    // Presses a pattern into the surface of the base geometry by
+    // moving the selected vertices along their normals, along with
+    // an attribute that sets the amplitude. The pattern is only as
+    // fine as the mesh, so coarse meshes need remeshing first. The
+    // attribute isn't added if the part can't be built with it.
+    pub fn displace(&mut self, displacement: &Displacement) -> Result<(),Error> {
+        self.displace_with(displacement,&self.context())
+    }
+
+    // Like `displace`, with any noise in the pattern mixed with the
+    // context's seed for the displacement's name
+    pub fn displace_with(&mut self, displacement: &Displacement, context: &EvalContext) -> Result<(),Error> {
+        span!("part.displace", name = %self.name, field = ?displacement.field());
+        let attribute = displacement
+            .seeded(context.seed_for(displacement.name()))
+            .build_attribute(&self.base)
+            .in_attribute(&displacement.attribute())
+            .in_part(&self.name)?;
+        self.attributes.push(attribute);
+        if let Err(error) = self.evaluate() {
+            self.attributes.pop();
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    // Adds a feature to the end of the history, or at the rollback
+    // if the part is rolled back, and rebuilds the geometry. The part
+    // is left unchanged if the feature can't be applied, or if it
+    // has the same name as another feature.
+    pub fn add_feature<T: Into<Feature>>(&mut self, feature: T) -> Result<(),Error> {
+        self.insert_feature(self.rollback.unwrap_or(self.features.len()),feature)
+    }
+
+    // Like `add_feature`, putting the feature at an index in the
+    // history so it's applied before the ones after it. A feature
+    // put before the rollback moves the rollback down with it.
+    pub fn insert_feature<T: Into<Feature>>(&mut self, index: usize, feature: T) -> Result<(),Error> {
+        let feature = feature.into();
+        let len = self.features.len();
+        if index > len {
+            return Err(Error::IndexOutOfRange { index, len }).in_part(&self.name);
+        }
+        if self.feature(feature.name()).is_some() {
+            return Err(Error::DuplicateFeature(feature.name().into())).in_part(&self.name);
+        }
+        let rollback = self.rollback;
+        self.features.insert(index,feature);
+        self.rollback = rollback.map(|r| if index <= r { r + 1 } else { r });
+        if let Err(error) = self.evaluate() {
+            self.features.remove(index);
+            self.rollback = rollback;
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    // Swaps a feature for a new version of it, like a hole with a new
+    // diameter, and rebuilds everything after it in the history.
+    // A suppressed feature stays suppressed under its new name.
+    // Returns the old version, or leaves the part unchanged if the
+    // new one can't be applied.
+    pub fn replace_feature<T: Into<Feature>>(&mut self, name: &str, feature: T) -> Result<Feature,Error> {
+        let feature = feature.into();
+        let index = self.feature_index(name)?;
+        if feature.name() != name && self.feature(feature.name()).is_some() {
+            return Err(Error::DuplicateFeature(feature.name().into())).in_part(&self.name);
+        }
+        let suppressed = self.suppressed.clone();
+        if self.suppressed.remove(name) {
+            self.suppressed.insert(feature.name().into());
+        }
+        let old = std::mem::replace(&mut self.features[index],feature);
+        if let Err(error) = self.evaluate() {
+            self.features[index] = old;
+            self.suppressed = suppressed;
+            return Err(error);
+        }
+        Ok(old)
+    }
+
+    // Takes a feature out of the history and rebuilds the geometry
+    // without it. The part is left unchanged if a later feature
+    // can't be applied without it, like a pattern of it.
+    pub fn remove_feature(&mut self, name: &str) -> Result<Feature,Error> {
+        let index = self.feature_index(name)?;
+        let (suppressed,rollback) = (self.suppressed.clone(),self.rollback);
+        let feature = self.features.remove(index);
+        self.suppressed.remove(name);
+        self.rollback = rollback.map(|r| if index < r { r - 1 } else { r });
+        if let Err(error) = self.evaluate() {
+            self.features.insert(index,feature);
+            self.suppressed = suppressed;
+            self.rollback = rollback;
+            return Err(error);
+        }
+        Ok(feature)
+    }
+
+    // Leaves a feature out of the geometry without taking it out of
+    // the history, along with any patterns of it
+    pub fn suppress(&mut self, name: &str) -> Result<(),Error> {
+        self.feature_index(name)?;
+        if self.suppressed.insert(name.into()) {
+            if let Err(error) = self.evaluate() {
+                self.suppressed.remove(name);
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    // Puts a suppressed feature back into the geometry. The feature
+    // stays suppressed if it can't be applied.
+    pub fn unsuppress(&mut self, name: &str) -> Result<(),Error> {
+        self.feature_index(name)?;
+        if self.suppressed.remove(name) {
+            if let Err(error) = self.evaluate() {
+                self.suppressed.insert(name.into());
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_suppressed(&self, name: &str) -> bool {
+        self.suppressed.contains(name)
+    }
+
+    // Shows the part as it was just after a feature, leaving out
+    // the ones after it until the part is rolled forward. Features
+    // added in the meantime go in at the rollback.
+    pub fn roll_back_to(&mut self, name: &str) -> Result<(),Error> {
+        let index = self.feature_index(name)?;
+        self.roll_back(index + 1)
+    }
+
+    // Shows the part with only the first `count` features, which is
+    // just the attributes for zero
+    pub fn roll_back(&mut self, count: usize) -> Result<(),Error> {
+        let len = self.features.len();
+        if count > len {
+            return Err(Error::IndexOutOfRange { index: count, len }).in_part(&self.name);
+        }
+        let rollback = self.rollback.replace(count);
+        if let Err(error) = self.evaluate() {
+            self.rollback = rollback;
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    // Applies every feature again after a rollback. The part stays
+    // rolled back if a feature after the rollback can't be applied.
+    pub fn roll_forward(&mut self) -> Result<(),Error> {
+        let rollback = self.rollback.take();
+        if let Err(error) = self.evaluate() {
+            self.rollback = rollback;
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    // The number of features applied while the part is rolled back
+    pub fn rollback(&self) -> Option<usize> {
+        self.rollback
+    }
+
+    fn feature_index(&self, name: &str) -> Result<usize,Error> {
+        self.features
+            .iter()
+            .position(|f| f.name() == name)
+            .ok_or_else(|| Error::UnknownFeature(name.into()))
+            .in_part(&self.name)
+    }
+
+    // Cuts a hole into the part at its datum. The hole is kept as a
+    // feature and cut again after the attributes are applied, so it
+    // follows the datum as they change. The result of cutting is a
+    // new mesh, so the geometry of a part with holes has no groups.
+    // The part is left unchanged if the hole can't be cut.
+    pub fn drill(&mut self, hole: &Hole) -> Result<(),Error> {
+        span!("part.drill", name = %self.name, hole = %hole.name());
+        self.add_feature(hole.clone())
+    }
+
+    // Makes the part a shell with walls `wall_thickness` thick and
+    // holes through them for draining, to save material when it's
+    // printed. The thickness is added as a "Wall Thickness" attribute,
+    // and the inside of the walls is a "cavity" group. Groups are
+    // extended to the inside too, so attributes that select by group
+    // or position move the walls with the outside. The part is left
+    // unchanged if it can't be built as a shell.
+    pub fn hollow(&mut self, wall_thickness: f64, drain: &Drain) -> Result<(),Error> {
+        span!("part.hollow", name = %self.name, holes = drain.holes().len());
+        if self.base.is_empty() {
+            return Err(Error::EmptyGeometry).in_part(&self.name);
+        }
+
+        let (base,mut attribute) = hollow::hollow(&self.base,drain).in_part(&self.name)?;
+        attribute.update(wall_thickness);
+        self.extend(base,attribute)
+    }
+
+    // Swaps in a new base geometry and adds an attribute for it,
+    // putting the old base back if the part can't be built with them
+    fn extend(&mut self, base: Geometry, attribute: Attribute) -> Result<(),Error> {
+        let old = std::mem::replace(&mut self.base,base);
+        self.attributes.push(attribute);
+        if let Err(error) = self.evaluate() {
+            self.base = old;
+            self.attributes.pop();
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    // Fills the inside of the part with a lattice, added to the base
+    // geometry as a "lattice" group. The width of the struts is a
+    // "Lattice Width" attribute, starting at the width for the
+    // density, so it can be changed like any other attribute. The
+    // cell size sets where the struts are, so changing it means
+    // filling the part again. The part is left unchanged if it can't
+    // be built with the lattice.
+    pub fn fill_lattice(&mut self, settings: &Lattice) -> Result<(),Error> {
+        span!("part.lattice", name = %self.name, cell = settings.cell());
+        let (lattice,offsets) = crate::geometry::lattice(&self.base,settings);
+        if lattice.is_empty() {
+            return Err(Error::EmptyGeometry).in_part(&self.name);
+        }
+
+        let start = self.base.vertices().len();
+        let mut base = self.base.clone();
+        base.append(&lattice,&Matrix::identity());
+        base.add_group("lattice",(start..base.vertices().len()).collect::<Vec<usize>>())
+            .in_part(&self.name)?;
+
+        let items = offsets
+            .into_iter()
+            .map(|(offset,indices)| AttributeItem::new(
+                Selection::specific(indices.into_iter().map(|i| i + start).collect::<Vec<usize>>()),
+                Alteration::translate(offset)))
+            .collect();
+        let mut attribute = Attribute::new("Lattice Width".into(),items);
+        attribute.update(settings.width());
+        self.extend(base,attribute)
+    }
+
+    // Moves the base geometry so the origin is at a point on it, and
+    // connections and selections made by position along with it, so
+    // that rotations turn the part around that point. Returns the
+    // transform that puts the part back where it was, which an
+    // instance of it needs to be multiplied by to stay in place.
+    pub fn normalize_origin(&mut self, origin: Origin) -> Result<Matrix,Error> {
+        let point = match &origin {
+            Origin::Centroid => self.base.centroid(),
+            Origin::Corner => self.base.bounds().map(|b| b.min),
+            Origin::Datum(name) => {
+                let indices = self.base
+                    .group(name)
+                    .ok_or_else(|| Error::UnknownGroup(name.clone()))
+                    .in_part(&self.name)?;
+                let vertices = self.base.vertices();
+                let points = indices.iter().filter_map(|i| vertices.get(*i));
+                (!indices.is_empty()).then(|| points.fold(Vertex::default(),|a,b| a + *b) / indices.len())
+            },
+        }
+        .ok_or(Error::EmptyGeometry)
+        .in_part(&self.name)?;
+
+        self.reshape(&Matrix::translate(-point.x,-point.y,-point.z))?;
+        Ok(Matrix::translate(point.x,point.y,point.z))
+    }
+
+    // Stretches the base geometry along X, around the middle of its
+    // bounds, so it's `value` long. Connections and selections made
+    // by position are moved with it. Returns the scale factor. The
+    // part can't be flattened or turned inside out, so `value` has
+    // to be more than 0.
+    pub fn set_overall_length(&mut self, value: f64) -> Result<f64,Error> {
+        self.set_overall(0,value)
+    }
+
+    // Like `set_overall_length`, along Y
+    pub fn set_overall_width(&mut self, value: f64) -> Result<f64,Error> {
+        self.set_overall(1,value)
+    }
+
+    // Like `set_overall_length`, along Z
+    pub fn set_overall_height(&mut self, value: f64) -> Result<f64,Error> {
+        self.set_overall(2,value)
+    }
+
+    fn set_overall(&mut self, axis: usize, value: f64) -> Result<f64,Error> {
+        if !value.is_finite() || value <= 0.0 {
+            return Err(Error::InvalidSize(value)).in_part(&self.name);
+        }
+
+        let bounds = self.base
+            .bounds()
+            .ok_or(Error::EmptyGeometry)
+            .in_part(&self.name)?;
+
+        let size = bounds.size();
+        let current = [size.x,size.y,size.z][axis];
+        if current <= f64::EPSILON {
+            return Err(Error::FlatAxis(axis)).in_part(&self.name);
+        }
+
+        let mut factors = [1.0;3];
+        factors[axis] = value / current;
+
+        let c = bounds.center();
+        self.reshape(&(
+            Matrix::translate(c.x,c.y,c.z) *
+            Matrix::scale(factors[0],factors[1],factors[2]) *
+            Matrix::translate(-c.x,-c.y,-c.z)))?;
+        Ok(factors[axis])
+    }
+
+    // Transforms the base geometry along with the connections and
+    // anything attributes select by position, then re-evaluates. The
+    // part is left unchanged if it can't be evaluated.
+    fn reshape(&mut self, matrix: &Matrix) -> Result<(),Error> {
+        let old = (self.attributes.clone(),self.base.clone(),self.connections.clone());
+        for attribute in self.attributes.iter_mut() {
+            attribute.follow(matrix,self.base.vertices());
+        }
+        self.base.transform(matrix);
+        self.connections.transform(matrix);
+        if let Err(error) = self.evaluate() {
+            (self.attributes,self.base,self.connections) = old;
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    pub fn build(mut self) -> Result<Self,Error> {
+        self.validate()?;
+        self.evaluate()?;
+        Ok(self)
+    }
+
+    // Verifies that the geometry is not empty, that faces and
+    // attributes map to real geometry, that features have different names and
+    // that connection points are on the surface of the part.
+    pub fn validate(&self) -> Result<(),Error> {
+        self.validate_with(&EvalContext::default())
+    }
+
+    // Like `validate`, using the tolerance from the context
+    pub fn validate_with(&self, context: &EvalContext) -> Result<(),Error> {
+        self.check(context).in_part(&self.name)
+    }
+
+    fn check(&self, context: &EvalContext) -> Result<(),Error> {
+        if self.base.is_empty() {
+            return Err(Error::EmptyGeometry);
+        }
+
+        let len = self.base.vertices().len();
+        for face in self.base.faces().iter() {
+            if let Some(index) = [face.a,face.b,face.c].into_iter().find(|i| *i >= len) {
+                return Err(Error::IndexOutOfRange { index, len });
+            }
+        }
+
+        for attribute in self.attributes.iter() {
+            if attribute.name().is_empty() {
+                return Err(Error::UnnamedAttribute);
+            }
+            if attribute.is_empty() {
+                return Err(Error::EmptyAttribute);
+            }
+            attribute
+                .resolve(&self.base)
+                .in_attribute(attribute.name())?;
+        }
+
+        for (index,feature) in self.features.iter().enumerate() {
+            if self.features[..index].iter().any(|f| f.name() == feature.name()) {
+                return Err(Error::DuplicateFeature(feature.name().into()));
+            }
+        }
+
+        for (index,connection) in self.connections.iter().enumerate() {
+            let distance = self.base.distance(&connection.point());
+            if distance > context.tolerance() {
+                return Err(Error::DetachedConnection { index, distance });
+            }
+        }
+
+        Ok(())
+    }
+
+}
+
+// A part is written as its base geometry followed by its attributes,
+// its features and which of them are suppressed or rolled back, its
+// connections and its metadata, so the text carries everything needed
+// to rebuild it.
+impl From<&Part> for String {
+    fn from(part: &Part) -> Self {
+        let mut result = String::new();
+        if !part.name.is_empty() {
+            result.push_str(&format!("{} {}\n",OBJECT_TAG,utilities::quote(&part.name,&[])));
+        }
+
+        result.push_str(&String::from(part.base.clone()));
+        if !part.attributes.is_empty() {
+            result.push('\n');
+        }
+
+        for attribute in part.attributes.iter() {
+            result.push('\n');
+            result.push_str(&String::from(attribute));
+        }
+
+        if !part.features.is_empty() {
+            result.push('\n');
+        }
+        for feature in part.features.iter() {
+            result.push('\n');
+            result.push_str(&String::from(feature));
+        }
+        for name in part.suppressed.iter() {
+            result.push_str(&format!("\n{} {}",SUPPRESS_TAG,utilities::quote(name,&[])));
+        }
+        if let Some(count) = part.rollback {
+            result.push_str(&format!("\n{} {}",ROLLBACK_TAG,count));
+        }
+
+        if !part.connections.is_empty() {
+            result.push('\n');
+        }
+        for connection in part.connections.iter() {
+            result.push('\n');
+            result.push_str(&String::from(connection));
+        }
+
+        let metadata = String::from(&part.metadata);
+        if !metadata.is_empty() {
+            result.push_str("\n\n");
+            result.push_str(&metadata);
+        }
+        result
     }
+}
 
+impl TryFrom<String> for Part {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Part::parse(&value,ParseMode::Lenient)
+    }
 }
 
 #[cfg(test)]
@@ -56,10 +868,11 @@ mod tests {
 
     use super::*;
     use crate::models;
+    use crate::testing;
 
     #[test]
     fn test_attribute_transforms_geometry() {
-        let mut geometry = models::M2X4.clone();
+        let mut geometry = models::M2X4.geometry();
 
         let mut length = Attribute::new("Length".into(),vec![
             // translate front end of 2x4 frontwards
@@ -70,17 +883,668 @@ mod tests {
 
         // add 2 meters to the front and back
         length.update(2.0);
-        length.revise(&mut geometry);
+        length.revise(&mut geometry).unwrap();
 
-        let result = length.distance(&geometry,0,1);
+        let result = length.distance(&geometry,0,1).unwrap();
         dbg!(result);
     }
 
     #[test]
     fn test_part_create() {
         let part = Part::new("2x4")
-            .with_geometry(models::M2X4.clone())
+            .with_geometry(models::M2X4.geometry())
+            .build()
+            .unwrap();
+
+        assert_eq!(part.name(),"2x4");
+    }
+
+    fn length() -> Attribute {
+        Attribute::new("Length".into(),vec![
+            AttributeItem::translate_specific(Vector::new(1.0,0.0,0.0),vec![4,5,6,7]),
+            AttributeItem::translate_specific(Vector::new(-1.0,0.0,0.0),vec![0,1,2,3])
+        ])
+    }
+
+    #[test]
+    fn test_part_set_attribute() {
+        let mut part = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_attribute(length())
+            .build()
+            .unwrap();
+
+        part.set("Length",1.0).unwrap();
+        assert_relative_eq!(part.geometry().vertices()[4].x,2.2192,epsilon = 1e-9);
+        assert_relative_eq!(part.geometry().vertices()[0].x,-2.2192,epsilon = 1e-9);
+
+        // values replace each other rather than accumulating
+        part.set("Length",0.5).unwrap();
+        assert_relative_eq!(part.geometry().vertices()[4].x,1.7192,epsilon = 1e-9);
+        assert_relative_eq!(part.attribute("Length").unwrap().value(),0.5);
+
+        // the base geometry is never modified
+        assert_relative_eq!(part.base().vertices()[4].x,1.2192);
+        assert_eq!(part.geometry().changes().ranges(),&[(0,8)]);
+    }
+
+    #[test]
+    fn test_part_evaluate_with_context() {
+        let mut part = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_attribute(length())
+            .build()
+            .unwrap();
+
+        let context = EvalContext::new()
+            .with_parameter("Length",1.0)
+            .with_units(Units::Millimeters);
+
+        let a = part.evaluate_with(&context).unwrap();
+        part.set("Length",0.5).unwrap();
+        let b = part.evaluate_with(&context).unwrap();
+
+        // the result depends only on the context, not the part's state
+        assert_eq!(a.vertices(),b.vertices());
+        assert_relative_eq!(a.vertices()[4].x,2219.2,epsilon = 1e-9);
+        assert_relative_eq!(part.geometry().vertices()[4].x,1.7192,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_part_reject_collapse() {
+        let part = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_attribute(Attribute::new("Thickness".into(),vec![
+                AttributeItem::scale_all(Vector::new(1.0,1.0,0.0))
+            ]));
+
+        let context = EvalContext::new().with_parameter("Thickness",2.0);
+        assert!(part.evaluate_with(&context).is_ok());
+
+        let context = context.with_allow_collapse(false);
+        let result = part.evaluate_with(&context);
+        assert!(matches!(result.unwrap_err().root(),Error::FixedAttribute));
+    }
+
+    #[test]
+    fn test_part_validate_with_tolerance() {
+        let part = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_connection(Connection::new(Vertex::new(-1.2195,0.0,0.0),0.01));
+
+        assert!(part.validate().is_err());
+        assert!(part.validate_with(&EvalContext::new().with_tolerance(1e-3)).is_ok());
+    }
+
+    #[test]
+    fn test_part_string_round_trip() {
+        let mut part = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_attribute(length())
+            .with_attribute(Attribute::new("Width".into(),vec![
+                AttributeItem::scale_relative_range(Vector::new(0.0,1.0,0.0),4..)
+            ]))
+            .build()
+            .unwrap();
+
+        part.set("Length",0.5).unwrap();
+        part.set("Width",1.0).unwrap();
+
+        let text = String::from(&part);
+        assert!(text.contains("a Length = 0.5\na Length translate 4,5,6,7 1 0 0"));
+        assert!(text.contains("a Width scale+ 4: 0 1 0"));
+
+        let result = Part::try_from(text).unwrap();
+        assert_eq!(result.name(),"2x4");
+        assert_eq!(result.attributes().len(),2);
+        assert_relative_eq!(result.attribute("Length").unwrap().value(),0.5);
+        assert_eq!(result.geometry().vertices(),part.geometry().vertices());
+        assert_eq!(result.base().vertices(),part.base().vertices());
+    }
+
+    #[test]
+    fn test_part_string_names() {
+        for name in ["a # b"," lead","trail ","say \"hi\"","two  spaces"] {
+            let part = Part::new(name)
+                .with_geometry(models::M2X4.geometry())
+                .build()
+                .unwrap();
+            let result = Part::try_from(String::from(&part)).unwrap();
+            assert_eq!(result.name(),name);
+        }
+
+        // names from other tools aren't quoted
+        let text = "o left stud # a comment\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3";
+        assert_eq!(Part::parse(text,ParseMode::Strict).unwrap().name(),"left stud");
+    }
+
+    #[test]
+    fn test_part_string_generated_attributes() {
+        let mut geometry = testing::cube_with_top("top face");
+        geometry.set_channel("heat",(0..8).map(|i| i as f64).collect()).unwrap();
+
+        let mut part = Part::new("block")
+            .with_geometry(geometry)
+            .with_attribute(Attribute::new("Top Lift".into(),vec![
+                AttributeItem::new(Selection::group("top face"),Alteration::translate(Vector::new(0.0,0.0,1.0)))
+            ]))
+            .build()
+            .unwrap();
+
+        part.set("Top Lift",0.5).unwrap();
+        part.fill_lattice(&Lattice::new().with_cell(0.5).with_density(0.03)).unwrap();
+        part.hollow(0.1,&Drain::default()).unwrap();
+        part.displace(&Displacement::new("grip",HeightField::function(|_| 1.0))
+            .with_selection(Selection::group("cavity"))
+            .with_amplitude(0.01)).unwrap();
+
+        let result = Part::try_from(String::from(&part)).unwrap();
+        let names = |p: &Part| p.attributes().iter().map(|a| a.name().to_string()).collect::<Vec<String>>();
+        assert_eq!(names(&result),["Top Lift","Lattice Width","Wall Thickness","grip Amplitude"]);
+        assert_eq!(result.base().groups(),part.base().groups());
+        assert_eq!(result.base().channel("heat"),part.base().channel("heat"));
+        assert_eq!(result.geometry().vertices(),part.geometry().vertices());
+    }
+
+    #[test]
+    fn test_part_string_complete() {
+        let mut geometry = testing::cube_with_top("top");
+        geometry.compute_normals();
+
+        let mut part = Part::new("block")
+            .with_geometry(geometry)
+            .with_connection(Connection::new(Vertex::new(0.5,0.5,1.0),0.004)
+                .with_joint(Joint::revolute(Direction::new(0.0,0.0,1.0),0.0,1.0))
+                .with_interface(Interface::hole(0.008).with_standard("M8")))
+            .with_connection(Connection::new(Vertex::new(0.0,0.5,0.5),0.01))
+            .build()
+            .unwrap();
+        part.metadata_mut().set_layer(Some("jigs".into()));
+        part.metadata_mut().add_tag("spare part");
+        part.add_feature(Hole::new("bore","top",0.1)).unwrap();
+        part.add_feature(Hole::new("pin","top",0.05).with_depth(Depth::Blind(0.5))).unwrap();
+        part.add_feature(Hole::new("vent","top",0.02)).unwrap();
+        part.suppress("pin").unwrap();
+        part.roll_back(2).unwrap();
+
+        let text = String::from(&part);
+        let result = Part::parse(&text,ParseMode::Strict).unwrap();
+        assert_eq!(String::from(&result),text);
+        assert!(part.diff(&result).is_empty());
+        assert!(result.metadata().has_tag("spare part"));
+        assert!(result.is_suppressed("pin"));
+        assert_eq!(result.rollback(),Some(2));
+        assert_eq!(result.base().normals(),part.base().normals());
+        assert_eq!(result.connections()[0].interface(),part.connections()[0].interface());
+        assert_eq!(result.geometry().vertices(),part.geometry().vertices());
+
+        // suppressed features have to be in the history
+        let error = Part::parse(&text.replace("suppress pin","suppress peg"),ParseMode::Strict).unwrap_err();
+        assert!(matches!(error.root(),Error::UnknownFeature(n) if n == "peg"));
+        assert!(Part::parse(&text.replace("rollback 2","rollback 4"),ParseMode::Strict).is_err());
+    }
+
+    #[test]
+    fn test_part_string_comments() {
+        let mut part = Part::new("block # 1")
+            .with_geometry(testing::cube_with_top("top"))
+            .with_attribute(Attribute::new("Height".into(),vec![
+                AttributeItem::new(Selection::group("top"),Alteration::translate(Vector::new(0.0,0.0,1.0)))
+            ]))
+            .with_connection(Connection::new(Vertex::new(0.5,0.5,1.0),0.004)
+                .with_interface(Interface::hole(0.008).with_standard("M8")))
+            .build()
+            .unwrap();
+        part.set("Height",0.5).unwrap();
+        part.metadata_mut().set_layer(Some("framing".into()));
+        part.metadata_mut().set_color(Some(Color::new(200,10,10)));
+        part.add_feature(Hole::new("bore","top",0.1)).unwrap();
+        part.add_feature(Hole::new("pin","top",0.05)).unwrap();
+        part.suppress("pin").unwrap();
+        part.roll_back(1).unwrap();
+
+        // a comment at the end of every line, and some on their own
+        let text = String::from(&part)
+            .lines()
+            .map(|l| match l.is_empty() {
+                true => "# between sections".to_string(),
+                false => format!("{} # note",l),
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        for mode in [ParseMode::Strict,ParseMode::Lenient] {
+            let result = Part::parse(&text,mode).unwrap();
+            assert_eq!(result.name(),"block # 1");
+            assert!(part.diff(&result).is_empty());
+            assert_eq!(result.rollback(),Some(1));
+            assert!(result.is_suppressed("pin"));
+            assert_eq!(result.metadata().layer(),Some("framing"));
+        }
+    }
+
+    #[test]
+    fn test_part_nominal_attribute() {
+        // the value is the nominal width, the geometry moves by the
+        // actual width in meters
+        let width = Attribute::new("Width".into(),vec![
+            AttributeItem::translate_specific(Vector::new(0.0,1.0,0.0),vec![4,5,6,7])
+        ]).with_nominal(true);
+
+        let mut part = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_attribute(width)
+            .build()
+            .unwrap();
+
+        part.set("Width",4.0).unwrap();
+        let moved = part.geometry().vertices()[4].y - part.base().vertices()[4].y;
+        assert_relative_eq!(moved,0.0889,epsilon = 1e-9);
+
+        let text = String::from(&part);
+        assert!(text.contains("a Width = 4\na Width nominal\n"));
+
+        let result = Part::try_from(text).unwrap();
+        assert!(result.attribute("Width").unwrap().is_nominal());
+        assert_eq!(result.geometry().vertices(),part.geometry().vertices());
+    }
+
+    #[test]
+    fn test_part_parse_strict() {
+        let text = "\
+            # a hand-written triangle\n\
+            o corner brace\n\
+            v 0 0 0\n\
+            v 1 0 0\n\
+            v 0 1 0\n\
+            f 1 2 3\n\
+            \n\
+            # stretch along x\n\
+            a Length translate 1 1 0 0";
+
+        let part = Part::parse(text,ParseMode::Strict).unwrap();
+        assert_eq!(part.name(),"corner brace");
+        assert_eq!(part.attributes().len(),1);
+
+        let text = text.replace("# stretch","stretch");
+        let result = Part::parse(&text,ParseMode::Strict);
+        assert!(matches!(result.unwrap_err().root(),Error::InvalidLine { line: 8, .. }));
+        assert!(Part::parse(&text,ParseMode::Lenient).is_ok());
+    }
+
+    #[test]
+    fn test_part_string_invalid_attribute() {
+        let text = String::from(models::M2X4.geometry()) + "\na Length stretch * 1 0 0";
+        assert!(Part::try_from(text).is_err());
+
+        let text = String::from(models::M2X4.geometry()) + "\na Length translate 9 1 0 0";
+        assert!(Part::try_from(text).is_err());
+    }
+
+    #[test]
+    fn test_part_group_attribute() {
+        let mut geometry = models::M2X4.geometry();
+        geometry.add_group("front",[4,5,6,7]).unwrap();
+
+        let mut part = Part::new("2x4")
+            .with_geometry(geometry)
+            .with_attribute(Attribute::new("Length".into(),vec![
+                AttributeItem::new(
+                    Selection::group("front"),
+                    Alteration::translate(Vector::new(1.0,0.0,0.0)))
+            ]))
+            .build()
+            .unwrap();
+
+        // repeated evaluation resolves against the unchanged base
+        part.set("Length",1.0).unwrap();
+        part.set("Length",2.0).unwrap();
+        assert_relative_eq!(part.geometry().vertices()[4].x,3.2192,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_part_unknown_group() {
+        let result = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_attribute(Attribute::new("Length".into(),vec![
+                AttributeItem::new(
+                    Selection::group("front"),
+                    Alteration::translate(Vector::new(1.0,0.0,0.0)))
+            ]))
             .build();
+
+        let error = result.unwrap_err();
+        assert!(matches!(error.root(),Error::UnknownGroup(_)));
+        assert_eq!(
+            error.to_string(),
+            "In part '2x4': In attribute 'Length': In item 0: \
+            Geometry doesn't have a vertex group named 'front'");
+    }
+
+    #[test]
+    fn test_part_set_unknown_attribute() {
+        let mut part = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .build()
+            .unwrap();
+
+        let error = part.set("Width",1.0).unwrap_err();
+        assert!(matches!(error.root(),Error::UnknownAttribute(n) if n == "Width"));
+        assert_eq!(error.to_string(),"In part '2x4': Part doesn't have an attribute named 'Width'");
+    }
+
+    #[test]
+    fn test_part_failed_edit_unchanged() {
+        let mut part = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_attribute(length())
+            .with_attribute(Attribute::new("Thickness".into(),vec![
+                AttributeItem::scale_all(Vector::new(1.0,1.0,0.0))
+            ]))
+            .build()
+            .unwrap();
+        part.set("Length",0.5).unwrap();
+        let before = part.geometry().vertices().to_vec();
+
+        // a value the part can't be built with isn't kept
+        let context = EvalContext::new().with_allow_collapse(false);
+        assert!(part.set_with("Length",2.0,&context).is_err());
+        assert_relative_eq!(part.attribute("Length").unwrap().value(),0.5);
+        assert_eq!(part.geometry().vertices(),before.as_slice());
+
+        // nor is the removal of an attribute, here because a hole
+        // needs a group that's gone from the base
+        let mut geometry = models::M2X4.geometry();
+        geometry.add_group("front",[4,5,6,7]).unwrap();
+        let mut part = Part::new("2x4")
+            .with_geometry(geometry)
+            .with_attribute(length())
+            .with_attribute(Attribute::new("Width".into(),vec![
+                AttributeItem::translate_all(Vector::new(0.0,1.0,0.0))
+            ]))
+            .with_feature(Hole::new("bore","front",0.01))
+            .build()
+            .unwrap();
+        part.base.remove_group("front");
+        assert!(part.remove_attribute("Length").is_err());
+        assert_eq!(part.attributes()[0].name(),"Length");
+        assert_eq!(part.attributes().len(),2);
+
+        // or a shell
+        let count = part.base().vertices().len();
+        assert!(matches!(part.hollow(0.01,&Drain::default()).unwrap_err().root(),Error::UnknownGroup(_)));
+        assert_eq!(part.base().vertices().len(),count);
+        assert!(part.attribute("Wall Thickness").is_none());
+
+        // or a lattice
+        assert!(matches!(part.fill_lattice(&Lattice::new().with_cell(0.02)).unwrap_err().root(),Error::UnknownGroup(_)));
+        assert_eq!(part.base().vertices().len(),count);
+        assert!(part.base().group("lattice").is_none());
+        assert!(part.attribute("Lattice Width").is_none());
+
+        // or a pattern pressed into it
+        let displacement = Displacement::new("grip",HeightField::function(|_| 1.0));
+        assert!(part.displace(&displacement).is_err());
+        assert!(part.attribute("grip Amplitude").is_none());
+    }
+
+    #[test]
+    fn test_part_remove_attribute() {
+        let mut part = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_attribute(length())
+            .build()
+            .unwrap();
+
+        part.set("Length",1.0).unwrap();
+
+        let attribute = part.remove_attribute("Length").unwrap();
+        assert_eq!(attribute.name(),"Length");
+        assert!(part.attribute("Length").is_none());
+        assert_relative_eq!(part.geometry().vertices()[4].x,1.2192);
+    }
+
+    #[test]
+    fn test_part_empty_geometry() {
+        let result = Part::new("2x4").build();
+        assert!(matches!(result.unwrap_err().root(),Error::EmptyGeometry));
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_part_attribute_out_of_range() {
+        let result = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_attribute(Attribute::new("Length".into(),vec![
+                AttributeItem::translate_specific(Vector::new(1.0,0.0,0.0),vec![4,5,6,8])
+            ]))
+            .build();
+
+        assert!(matches!(result.unwrap_err().root(),Error::IndexOutOfRange { index: 8, len: 8 }));
+    }
+
+    #[test]
+    fn test_part_face_out_of_range() {
+        let geometry = Geometry::new(
+            vec![Vertex::new(0.0,0.0,0.0),Vertex::new(1.0,0.0,0.0),Vertex::new(0.0,1.0,0.0)],
+            vec![Face::new(1,2,4)]);
+
+        let result = Part::new("triangle")
+            .with_geometry(geometry)
+            .with_connection(Connection::new(Vertex::new(0.0,0.0,0.0),0.01))
+            .build();
+
+        assert!(matches!(result.unwrap_err().root(),Error::IndexOutOfRange { index: 3, len: 3 }));
+    }
+
+    #[test]
+    fn test_part_attribute_unnamed() {
+        let result = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_attribute(Attribute::new("".into(),vec![
+                AttributeItem::translate_all(Vector::new(1.0,0.0,0.0))
+            ]))
+            .build();
+
+        assert!(matches!(result.unwrap_err().root(),Error::UnnamedAttribute));
+    }
+
+    #[test]
+    fn test_part_attribute_empty() {
+        let result = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_attribute(Attribute::new("Length".into(),vec![]))
+            .build();
+
+        assert!(matches!(result.unwrap_err().root(),Error::EmptyAttribute));
+    }
+
+    #[test]
+    fn test_part_connection_on_surface() {
+        let part = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            // the center of the back end
+            .with_connection(Connection::new(Vertex::new(-1.2192,0.0,0.0),0.01))
+            .build();
+
+        assert!(part.is_ok());
+    }
+
+    #[test]
+    fn test_part_connection_detached() {
+        let result = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .with_connection(Connection::new(Vertex::new(-1.2192,0.0,0.0),0.01))
+            .with_connection(Connection::new(Vertex::new(-1.5,0.0,0.0),0.01))
+            .build();
+
+        match result.unwrap_err().root() {
+            Error::DetachedConnection { index, distance } => {
+                assert_eq!(*index,1);
+                assert_relative_eq!(*distance,0.2808,epsilon = 1e-9);
+            },
+            other => panic!("unexpected result: {:?}",other),
+        }
+    }
+
+    #[test]
+    fn test_part_normalize_origin() {
+        // a 2x4 imported a long way from the origin
+        let mut geometry = models::M2X4.geometry();
+        geometry.transform(&Matrix::translate(100.0,50.0,0.0));
+        geometry.add_group("front",[4,5,6,7]).unwrap();
+        let front = Bounds::new(Vertex::new(101.0,49.0,-1.0),Vertex::new(102.0,51.0,1.0));
+
+        let mut part = Part::new("2x4")
+            .with_geometry(geometry)
+            .with_connection(Connection::new(Vertex::new(98.7808,50.0,0.0),0.01))
+            .with_attribute(Attribute::new("Length".into(),vec![
+                AttributeItem::new(
+                    Selection::within(front),
+                    Alteration::translate(Vector::new(1.0,0.0,0.0)))
+            ]))
+            .build()
+            .unwrap();
+        part.set("Length",0.5).unwrap();
+        let before = part.geometry().vertices()[4];
+
+        let back = part.normalize_origin(Origin::Corner).unwrap();
+        let min = part.base().bounds().unwrap().min;
+        assert_relative_eq!(min.magnitude(),0.0,epsilon = 1e-9);
+        assert_relative_eq!(part.connections()[0].point().x,0.0,epsilon = 1e-9);
+        part.validate().unwrap();
+
+        // the selection still picks the front end, and the transform
+        // puts it back where it was
+        let mut after = part.geometry().vertices()[4];
+        after.transform(&back);
+        assert_relative_eq!(after.x,before.x,epsilon = 1e-9);
+        assert_relative_eq!(after.y,before.y,epsilon = 1e-9);
+
+        part.normalize_origin(Origin::Datum("front".into())).unwrap();
+        assert_relative_eq!(part.base().vertices()[4].x,0.0,epsilon = 1e-9);
+        assert_relative_eq!(part.geometry().vertices()[4].x,0.5,epsilon = 1e-9);
+
+        part.normalize_origin(Origin::Centroid).unwrap();
+        assert_relative_eq!(part.base().centroid().unwrap().magnitude(),0.0,epsilon = 1e-9);
+
+        let error = part.normalize_origin(Origin::Datum("top".into())).unwrap_err();
+        assert!(matches!(error.root(),Error::UnknownGroup(_)));
+    }
+
+    #[test]
+    fn test_part_set_overall() {
+        let mut part = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            // the center of the back end
+            .with_connection(Connection::new(Vertex::new(-1.2192,0.0,0.0),0.01))
+            .build()
+            .unwrap();
+
+        let factor = part.set_overall_length(1.2192).unwrap();
+        assert_relative_eq!(factor,0.5,epsilon = 1e-9);
+        assert_relative_eq!(part.geometry().bounds().unwrap().size().x,1.2192,epsilon = 1e-9);
+        assert_relative_eq!(part.connections()[0].point().x,-0.6096,epsilon = 1e-9);
+        part.validate().unwrap();
+
+        let width = part.geometry().bounds().unwrap().size().y;
+        assert_relative_eq!(part.set_overall_width(width * 3.0).unwrap(),3.0,epsilon = 1e-9);
+        assert_relative_eq!(part.geometry().bounds().unwrap().size().x,1.2192,epsilon = 1e-9);
+
+        // sizes that would flatten the part or turn it inside out
+        for value in [0.0,-1.0,f64::NAN,f64::INFINITY] {
+            let error = part.set_overall_height(value).unwrap_err();
+            assert!(matches!(error.root(),Error::InvalidSize(_)));
+        }
+        assert_relative_eq!(part.geometry().bounds().unwrap().size().y,width * 3.0,epsilon = 1e-9);
+
+        let mut flat = Part::new("flat")
+            .with_geometry(Geometry::new(
+                vec![Vertex::new(0.0,0.0,0.0),Vertex::new(1.0,0.0,0.0),Vertex::new(0.0,1.0,0.0)],
+                vec![Face::new(1,2,3)]))
+            .build()
+            .unwrap();
+        let error = flat.set_overall_height(1.0).unwrap_err();
+        assert!(matches!(error.root(),Error::FlatAxis(2)));
+
+        // a hole in the end can't find its way in once the end is
+        // squashed to nothing, so the part is left as it was
+        let mut geometry = models::M2X4.geometry();
+        geometry.add_group("front",[4,5,6,7]).unwrap();
+        let mut part = Part::new("2x4")
+            .with_geometry(geometry)
+            .with_connection(Connection::new(Vertex::new(-1.2192,0.0,0.0),0.01))
+            .with_feature(Hole::new("bore","front",0.01))
+            .build()
+            .unwrap();
+        let before = part.clone();
+        let error = part.set_overall_height(1e-300).unwrap_err();
+        assert!(matches!(error.root(),Error::UnknownDirection(_)));
+        assert!(before.diff(&part).is_empty());
+        assert_eq!(part.base().vertices(),before.base().vertices());
+        assert_eq!(part.connections()[0].point(),before.connections()[0].point());
+    }
+
+    #[test]
+    fn test_part_fill_lattice() {
+        let mut part = Part::new("block")
+            .with_geometry(testing::cube())
+            .build()
+            .unwrap();
+
+        let settings = Lattice::new().with_cell(0.5).with_density(0.03);
+        part.fill_lattice(&settings).unwrap();
+        assert_eq!(part.base().group("lattice").map(Vec::len),Some(36 * 8));
+
+        // the struts are as wide as the attribute says
+        let volume = |part: &Part| part.geometry().volume() - 1.0;
+        assert_relative_eq!(volume(&part),12.0 * settings.width().powi(2),epsilon = 1e-6);
+        part.set("Lattice Width",0.01).unwrap();
+        assert_relative_eq!(volume(&part),12.0 * 0.01 * 0.01,epsilon = 1e-6);
+
+        let mut empty = Part::new("empty");
+        assert!(matches!(empty.fill_lattice(&settings).unwrap_err().root(),Error::EmptyGeometry));
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn test_part_engrave() {
+        use crate::geometry::text::Font;
+
+        let Ok(font) = Font::load("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf") else { return };
+        let mut part = Part::new("2x4")
+            .with_geometry(models::M2X4.geometry())
+            .build()
+            .unwrap();
+
+        let engraving = Engraving::new("label","A",font)
+            .with_size(1.0)
+            .with_depth(0.25)
+            .with_face(0)
+            .build();
+
+        let count = part.base().vertices().len();
+        part.engrave(&engraving).unwrap();
+        assert!(part.base().vertices().len() > count);
+
+        let normal = part.base().get(0).normal().vector();
+        let top = part.geometry().group("label.top").unwrap()[0];
+        let lift = |part: &Part| {
+            let v = part.geometry().vertices()[top] - part.base().vertices()[top];
+            v.dot(&normal)
+        };
+        assert!((lift(&part) - 0.25).abs() < 1e-9);
+
+        part.set("label Depth",0.5).unwrap();
+        assert!((lift(&part) - 0.5).abs() < 1e-9);
+
+        let missing = engraving.clone().with_face(10_000);
+        assert!(part.engrave(&missing).is_err());
+
+        // nothing is added if the part can't be built with the text
+        let count = part.base().vertices().len();
+        part.features.push(Hole::new("bore","missing",0.01).into());
+        assert!(part.engrave(&engraving).is_err());
+        assert_eq!(part.base().vertices().len(),count);
+        assert_eq!(part.attributes().len(),1);
+    }
+
+}
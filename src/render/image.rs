@@ -0,0 +1,171 @@
+#[cfg(feature = "png")]
+use crate::errors::Error;
+
+pub type Pixel = [u8;4];
+
+/// An RGBA image buffer, stored row by row from the top left
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    data: Vec<u8>,
+}
+
+impl Image {
+
+    pub fn new(width: usize, height: usize, fill: Pixel) -> Self {
+        Self {
+            width,
+            height,
+            data: fill.repeat(width * height),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<Pixel> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let i = (y * self.width + x) * 4;
+        Some([self.data[i],self.data[i + 1],self.data[i + 2],self.data[i + 3]])
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, pixel: Pixel) {
+        if x < self.width && y < self.height {
+            let i = (y * self.width + x) * 4;
+            self.data[i..i + 4].copy_from_slice(&pixel);
+        }
+    }
+
+    // The largest difference between any two channels, for comparing
+    // renders against golden images with some tolerance.
+    pub fn difference(&self, other: &Image) -> Option<u8> {
+        if self.width != other.width || self.height != other.height {
+            return None;
+        }
+        self.data
+            .iter()
+            .zip(other.data.iter())
+            .map(|(a,b)| a.abs_diff(*b))
+            .max()
+            .or(Some(0))
+    }
+
+    #[cfg(feature = "png")]
+    pub fn encode_png(&self) -> Result<Vec<u8>,Error> {
+        let mut result = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(
+                &mut result,
+                self.width as u32,
+                self.height as u32);
+
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+
+            encoder
+                .write_header()
+                .and_then(|mut w| w.write_image_data(&self.data))
+                .map_err(|e| Error::EncodeError(e.to_string()))?;
+        }
+        Ok(result)
+    }
+
+    #[cfg(feature = "png")]
+    pub fn write_png<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(),Error> {
+        std::fs::write(path,self.encode_png()?)?;
+        Ok(())
+    }
+
+    // Reads an eight bit PNG of any color type, filling in
+    // missing channels so every pixel is RGBA
+    #[cfg(feature = "png")]
+    pub fn decode_png(data: &[u8]) -> Result<Self,Error> {
+        let error = |e: png::DecodingError| Error::DecodeError(e.to_string());
+        let mut decoder = png::Decoder::new(data);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+        let mut reader = decoder.read_info().map_err(error)?;
+        let mut buffer = vec![0;reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(error)?;
+        let buffer = &buffer[..info.buffer_size()];
+
+        let data = match info.color_type {
+            png::ColorType::Rgba => buffer.to_vec(),
+            png::ColorType::Rgb => buffer.chunks_exact(3).flat_map(|p| [p[0],p[1],p[2],255]).collect(),
+            png::ColorType::GrayscaleAlpha => buffer.chunks_exact(2).flat_map(|p| [p[0],p[0],p[0],p[1]]).collect(),
+            png::ColorType::Grayscale => buffer.iter().flat_map(|p| [*p,*p,*p,255]).collect(),
+            png::ColorType::Indexed => return Err(Error::DecodeError("indexed colors weren't expanded".into())),
+        };
+
+        Ok(Self {
+            width: info.width as usize,
+            height: info.height as usize,
+            data,
+        })
+    }
+
+    #[cfg(feature = "png")]
+    pub fn read_png<P: AsRef<std::path::Path>>(path: P) -> Result<Self,Error> {
+        Self::decode_png(&std::fs::read(path)?)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_image_get_set() {
+        let mut image = Image::new(4,2,[0,0,0,255]);
+        image.set(3,1,[1,2,3,4]);
+        image.set(4,1,[9,9,9,9]);
+
+        assert_eq!(image.get(3,1),Some([1,2,3,4]));
+        assert_eq!(image.get(0,0),Some([0,0,0,255]));
+        assert_eq!(image.get(4,1),None);
+        assert_eq!(image.data().len(),32);
+    }
+
+    #[test]
+    fn test_image_difference() {
+        let a = Image::new(2,2,[10,10,10,255]);
+        let mut b = a.clone();
+        b.set(1,1,[10,14,10,255]);
+
+        assert_eq!(a.difference(&b),Some(4));
+        assert_eq!(a.difference(&Image::new(1,1,[0;4])),None);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_image_encode_png() {
+        let image = Image::new(3,3,[255,0,0,255]);
+        let data = image.encode_png().unwrap();
+        assert_eq!(&data[..8],&[137,80,78,71,13,10,26,10]);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_image_decode_png() {
+        let mut image = Image::new(3,2,[255,0,0,255]);
+        image.set(2,1,[1,2,3,4]);
+        let decoded = Image::decode_png(&image.encode_png().unwrap()).unwrap();
+        assert_eq!(decoded,image);
+        assert!(matches!(Image::decode_png(&[1,2,3]),Err(Error::DecodeError(_))));
+    }
+
+}
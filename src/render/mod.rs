@@ -0,0 +1,5 @@
+mod image;
+mod renderer;
+
+pub use image::Image;
+pub use renderer::Renderer;
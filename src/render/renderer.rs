@@ -0,0 +1,387 @@
+use crate::geometry::{Vector,Vertex,Normal,Matrix,Geometry,Bounds,Transform,Frustum};
+use crate::assembly::Assembly;
+use crate::render::Image;
+use crate::render::image::Pixel;
+
+/// A small software rasterizer for previews and thumbnails. Faces
+/// are flat shaded against a single directional light and sorted
+/// with a depth buffer.
+#[derive(Debug,Clone)]
+pub struct Renderer {
+    width: usize,
+    height: usize,
+    view: Option<Matrix>,
+    projection: Option<Matrix>,
+    background: Pixel,
+    color: Pixel,
+    light: Normal,
+    outline: Option<(Pixel,f64)>,
+}
+
+impl Renderer {
+
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            view: None,
+            projection: None,
+            background: [255,255,255,0],
+            color: [180,180,180,255],
+            light: Normal::new(0.3,0.5,1.0).normalize(),
+            outline: None,
+        }
+    }
+
+    // Without a camera the geometry is framed automatically
+    pub fn with_camera(mut self, view: Matrix, projection: Matrix) -> Self {
+        self.view = Some(view);
+        self.projection = Some(projection);
+        self
+    }
+
+    pub fn with_background(mut self, background: Pixel) -> Self {
+        self.background = background;
+        self
+    }
+
+    pub fn with_color(mut self, color: Pixel) -> Self {
+        self.color = color;
+        self
+    }
+
+    // The direction pointing towards the light
+    pub fn with_light(mut self, light: Normal) -> Self {
+        self.light = light.normalize();
+        self
+    }
+
+    // Draws silhouettes and edges sharper than `angle` radians over
+    // the shaded faces.
+    pub fn with_outline(mut self, color: Pixel, angle: f64) -> Self {
+        self.outline = Some((color,angle));
+        self
+    }
+
+    // With a camera, instances the camera can't see are left out
+    // before anything is drawn
+    pub fn render_assembly(&self, assembly: &Assembly) -> Image {
+        match (self.view,self.projection) {
+            (Some(view),Some(projection)) => {
+                let frustum = Frustum::new(&view,&projection);
+                let visible = assembly
+                    .visible_parts(&frustum)
+                    .cloned()
+                    .fold(Assembly::new(assembly.name()),|a,i| a.with_instance(i));
+                self.render(&visible.flatten())
+            },
+            _ => self.render(&assembly.flatten()),
+        }
+    }
+
+    pub fn render(&self, geometry: &Geometry) -> Image {
+        span!("render", faces = geometry.size(), width = self.width, height = self.height);
+        let mut image = Image::new(self.width,self.height,self.background);
+        let mut depth = vec![f64::INFINITY; self.width * self.height];
+
+        let bounds = match geometry.bounds() {
+            Some(b) => b,
+            None => return image,
+        };
+
+        let (view,projection) = match (self.view,self.projection) {
+            (Some(v),Some(p)) => (v,p),
+            _ => self.framing(&bounds),
+        };
+
+        let matrix = projection * view;
+        let vertices = geometry
+            .vertices()
+            .iter()
+            .map(|v| self.project(&matrix,v))
+            .collect::<Vec<Option<Vertex>>>();
+
+        for (i,face) in geometry.faces().iter().enumerate() {
+            if !face.is_valid(geometry.vertices()) {
+                continue;
+            }
+
+            // skip faces that reach behind the camera
+            let (a,b,c) = match (vertices[face.a],vertices[face.b],vertices[face.c]) {
+                (Some(a),Some(b),Some(c)) => (a,b,c),
+                _ => continue,
+            };
+
+            let shade = 0.2 + 0.8 * geometry.get(i).normal().dot(&self.light).abs();
+            let pixel = [
+                (self.color[0] as f64 * shade).round() as u8,
+                (self.color[1] as f64 * shade).round() as u8,
+                (self.color[2] as f64 * shade).round() as u8,
+                self.color[3],
+            ];
+
+            self.rasterize(&mut image,&mut depth,[a,b,c],pixel);
+        }
+
+        if let Some((color,angle)) = self.outline {
+            let mut edges = geometry.feature_edges(angle);
+            edges.extend(geometry.silhouette_edges(&eye(&view)));
+            edges.sort_unstable();
+            edges.dedup();
+
+            for (a,b) in edges {
+                if let (Some(a),Some(b)) = (vertices[a],vertices[b]) {
+                    self.line(&mut image,&depth,a,b,color);
+                }
+            }
+        }
+
+        image
+    }
+
+    // Transforms a vertex into screen space, keeping the normalized
+    // depth in z. Returns None for points behind the camera.
+    fn project(&self, matrix: &Matrix, vertex: &Vertex) -> Option<Vertex> {
+        let [
+            _, _, _, _,
+            _, _, _, _,
+            _, _, _, _,
+            m41, m42, m43, m44
+        ] = matrix.unpack();
+
+        let w = m41 * vertex.x + m42 * vertex.y + m43 * vertex.z + m44;
+        if w <= 0.0 {
+            return None;
+        }
+
+        let mut v = *vertex;
+        v.transform(matrix);
+
+        Some(Vertex::new(
+            (v.x + 1.0) * 0.5 * self.width as f64,
+            (1.0 - v.y) * 0.5 * self.height as f64,
+            v.z))
+    }
+
+    fn rasterize(&self, image: &mut Image, depth: &mut [f64], [a,b,c]: [Vertex;3], pixel: Pixel) {
+        let area = edge(&a,&b,&c);
+        if area == 0.0 {
+            return;
+        }
+
+        let x0 = a.x.min(b.x).min(c.x).floor().max(0.0) as usize;
+        let y0 = a.y.min(b.y).min(c.y).floor().max(0.0) as usize;
+        let x1 = (a.x.max(b.x).max(c.x).ceil().max(0.0) as usize).min(self.width);
+        let y1 = (a.y.max(b.y).max(c.y).ceil().max(0.0) as usize).min(self.height);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                // sample at the center of the pixel
+                let p = Vertex::new(x as f64 + 0.5,y as f64 + 0.5,0.0);
+
+                let w0 = edge(&b,&c,&p) / area;
+                let w1 = edge(&c,&a,&p) / area;
+                let w2 = edge(&a,&b,&p) / area;
+
+                if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                    continue;
+                }
+
+                let z = w0 * a.z + w1 * b.z + w2 * c.z;
+                let i = y * self.width + x;
+
+                if (-1.0..=1.0).contains(&z) && z < depth[i] {
+                    depth[i] = z;
+                    image.set(x,y,pixel);
+                }
+            }
+        }
+    }
+
+    // Draws a line that is hidden behind faces but not by the faces
+    // it lies on.
+    fn line(&self, image: &mut Image, depth: &[f64], a: Vertex, b: Vertex, pixel: Pixel) {
+        let steps = (b.x - a.x).abs().max((b.y - a.y).abs()).ceil().max(1.0) as usize;
+        for step in 0..=steps {
+            let t = step as f64 / steps as f64;
+            let p = a + (b - a) * t;
+
+            if p.x < 0.0 || p.y < 0.0 {
+                continue;
+            }
+
+            let (x,y) = (p.x as usize,p.y as usize);
+            if x < self.width && y < self.height && p.z <= depth[y * self.width + x] + 1e-3 {
+                image.set(x,y,pixel);
+            }
+        }
+    }
+
+    // A camera looking at the bounds from above and to the front
+    // right, far enough away that everything fits in view.
+    fn framing(&self, bounds: &Bounds) -> (Matrix,Matrix) {
+        let center = bounds.center();
+        let radius = (bounds.size().magnitude() / 2.0).max(f64::EPSILON);
+
+        let fov = 40f64.to_radians();
+        let aspect = self.width as f64 / self.height.max(1) as f64;
+
+        // distance at which a sphere around the bounds fits the
+        // narrower of the two fields of view
+        let narrow = (fov / 2.0).tan().min((fov / 2.0).tan() * aspect).atan();
+        let distance = radius / narrow.sin();
+
+        let direction = Vector::new(1.0,-1.0,0.8).normalize();
+        let eye = center + direction * distance;
+
+        let view = Matrix::look_at(eye,center,Vector::new(0.0,0.0,1.0));
+        let projection = Matrix::perspective(
+            fov,
+            aspect,
+            (distance - radius) * 0.5,
+            distance + radius * 2.0);
+
+        (view,projection)
+    }
+
+}
+
+// The camera position of a view matrix
+fn eye(view: &Matrix) -> Vertex {
+    let [
+        m11, m12, m13, m14,
+        m21, m22, m23, m24,
+        m31, m32, m33, m34,
+        _, _, _, _
+    ] = view.unpack();
+
+    Vertex::new(
+        -(m11 * m14 + m21 * m24 + m31 * m34),
+        -(m12 * m14 + m22 * m24 + m32 * m34),
+        -(m13 * m14 + m23 * m24 + m33 * m34))
+}
+
+// Twice the signed area of the triangle abc in screen space
+fn edge(a: &Vertex, b: &Vertex, c: &Vertex) -> f64 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::part::Part;
+
+    // a square facing +Z covering -1..1 on x and y
+    fn square(z: f64) -> Geometry {
+        Geometry::make(
+            vec![-1.0,-1.0,z, 1.0,-1.0,z, 1.0,1.0,z, -1.0,1.0,z],
+            vec![1, 2, 3, 1, 3, 4])
+    }
+
+    fn camera() -> (Matrix,Matrix) {
+        (Matrix::identity(),Matrix::orthographic(-2.0,2.0,-2.0,2.0,-10.0,10.0))
+    }
+
+    #[test]
+    fn test_render_flat() {
+        let (view,projection) = camera();
+        let image = Renderer::new(8,8)
+            .with_camera(view,projection)
+            .with_background([0,0,0,0])
+            .with_color([200,100,50,255])
+            .with_light(Normal::new(0.0,0.0,1.0))
+            .render(&square(0.0));
+
+        // the square covers the middle half of the image
+        assert_eq!(image.get(4,4),Some([200,100,50,255]));
+        assert_eq!(image.get(0,0),Some([0,0,0,0]));
+        assert_eq!(image.get(7,7),Some([0,0,0,0]));
+    }
+
+    #[test]
+    fn test_render_depth() {
+        let (view,projection) = camera();
+
+        // a small bright square in front of a larger dim one
+        let mut front = square(1.0);
+        front.transform(&Matrix::scale(0.5,0.5,1.0));
+
+        let mut back = square(-1.0);
+        back.transform(&Matrix::scale(2.0,2.0,1.0));
+
+        let mut geometry = Geometry::default();
+        geometry.append(&front,&Matrix::identity());
+        geometry.append(&back,&Matrix::rotate_x(0.5));
+
+        let image = Renderer::new(16,16)
+            .with_camera(view,projection)
+            .with_light(Normal::new(0.0,0.0,1.0))
+            .with_color([100,100,100,255])
+            .render(&geometry);
+
+        // drawing order doesn't matter, the nearer face wins
+        assert_eq!(image.get(8,8),Some([100,100,100,255]));
+        assert_ne!(image.get(8,1),Some([100,100,100,255]));
+    }
+
+    #[test]
+    fn test_render_outline() {
+        let view = Matrix::look_at(
+            Vertex::new(0.0,0.0,5.0),
+            Vertex::new(0.0,0.0,0.0),
+            Vector::new(0.0,1.0,0.0));
+        let projection = Matrix::orthographic(-2.0,2.0,-2.0,2.0,0.1,10.0);
+
+        let image = Renderer::new(16,16)
+            .with_camera(view,projection)
+            .with_light(Normal::new(0.0,0.0,1.0))
+            .with_color([100,100,100,255])
+            .with_outline([255,0,0,255],0.1)
+            .render(&square(0.0));
+
+        // the border is outlined but the diagonal isn't a feature
+        assert_eq!(image.get(4,8),Some([255,0,0,255]));
+        assert_eq!(image.get(8,8),Some([100,100,100,255]));
+        assert_eq!(eye(&view),Vertex::new(0.0,0.0,5.0));
+    }
+
+    #[test]
+    fn test_render_framing() {
+        let image = Renderer::new(32,24)
+            .with_background([0,0,0,0])
+            .render(&square(0.0));
+
+        let drawn = (0..32)
+            .flat_map(|x| (0..24).map(move |y| (x,y)))
+            .filter(|&(x,y)| image.get(x,y).unwrap()[3] > 0)
+            .count();
+
+        // visible, but not running off the edges
+        assert!(drawn > 0);
+        for x in 0..32 {
+            assert_eq!(image.get(x,0).unwrap()[3],0);
+            assert_eq!(image.get(x,23).unwrap()[3],0);
+        }
+    }
+
+    #[test]
+    fn test_render_assembly() {
+        let part = Part::new("square").with_geometry(square(0.0));
+        let assembly = Assembly::new("pair")
+            .with_part(part.clone(),Matrix::translate(-1.5,0.0,0.0))
+            .with_part(part,Matrix::translate(1.5,0.0,0.0));
+
+        let renderer = Renderer::new(16,16);
+        assert_ne!(
+            renderer.render_assembly(&assembly),
+            renderer.render(&Geometry::default()));
+
+        // parts out of view don't change the picture
+        let (view,projection) = camera();
+        let renderer = renderer.with_camera(view,projection);
+        let far = assembly.clone().with_part(Part::new("far").with_geometry(square(0.0)),Matrix::translate(50.0,0.0,0.0));
+        assert_eq!(renderer.render_assembly(&far),renderer.render_assembly(&assembly));
+    }
+
+}
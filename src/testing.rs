@@ -0,0 +1,242 @@
+use std::f64::consts::{PI,TAU};
+
+use crate::geometry::{Geometry,Vertex,Bounds};
+use crate::part::Part;
+use crate::models::{Lumber,SheetGoods};
+
+#[cfg(feature = "png")]
+use std::path::Path;
+#[cfg(feature = "png")]
+use crate::render::Image;
+
+// set to write golden images instead of comparing against them
+pub const UPDATE_GOLDEN: &str = "CONSTRUCT_UPDATE_GOLDEN";
+
+// A unit cube from the origin with outward facing triangles
+pub fn cube() -> Geometry {
+    Geometry::from_bounds(&Bounds::new(Vertex::new(0.0,0.0,0.0),Vertex::new(1.0,1.0,1.0)))
+}
+
+// A unit cube from the origin with its four top corners in a group
+pub fn cube_with_top(group: &str) -> Geometry {
+    let mut geometry = cube();
+    let top = (0..8).filter(|i| geometry.vertices()[*i].z > 0.5).collect::<Vec<usize>>();
+    geometry.add_group(group,top).expect("the cube has eight corners");
+    geometry
+}
+
+// A flat square grid on the XY plane, one unit between vertices,
+// with two triangles facing +Z in every cell
+pub fn grid(columns: usize, rows: usize) -> Geometry {
+    let (columns,rows) = (columns.max(1),rows.max(1));
+    let mut values = Vec::new();
+    for y in 0..=rows {
+        for x in 0..=columns {
+            values.extend([x as f64,y as f64,0.0]);
+        }
+    }
+
+    let mut indices = Vec::new();
+    for y in 0..rows {
+        for x in 0..columns {
+            let a = y * (columns + 1) + x + 1;
+            let (b,c) = (a + 1,a + columns + 1);
+            indices.extend([a,b,c + 1,a,c + 1,c]);
+        }
+    }
+    Geometry::make(values,indices)
+}
+
+// A closed sphere around the origin, from `count` points spread
+// evenly over it in a spiral
+pub fn sphere(radius: f64, count: usize) -> Geometry {
+    let count = count.max(4);
+    Geometry::hull(&(0..count)
+        .map(|i| {
+            let z = 1.0 - (2.0 * i as f64 + 1.0) / count as f64;
+            let angle = i as f64 * PI * (3.0 - 5f64.sqrt());
+            let r = (1.0 - z * z).sqrt();
+            Vertex::new(angle.cos() * r,angle.sin() * r,z) * radius
+        })
+        .collect::<Vec<Vertex>>())
+}
+
+// A closed cylinder standing on the XY plane around the Z axis,
+// with `sides` flat sides
+pub fn cylinder(radius: f64, height: f64, sides: usize) -> Geometry {
+    let sides = sides.max(3);
+    Geometry::hull(&(0..sides * 2)
+        .map(|i| {
+            let angle = (i % sides) as f64 * TAU / sides as f64;
+            Vertex::new(angle.cos() * radius,angle.sin() * radius,(i / sides) as f64 * height)
+        })
+        .collect::<Vec<Vertex>>())
+}
+
+// A 2x4 stud with a "Length" attribute that moves its front end
+// and a connection in the middle of each end
+pub fn stud() -> Part {
+    Lumber::TwoByFour
+        .part()
+        .build()
+        .expect("the stud fixture is valid")
+}
+
+// A sheet of plywood lying flat, with "Length", "Width" and
+// "Thickness" attributes and a connection on each edge
+pub fn panel() -> Part {
+    SheetGoods::Plywood
+        .part()
+        .build()
+        .expect("the panel fixture is valid")
+}
+
+// The first difference between two geometries, or None if they
+// have the same faces and groups and every vertex is within
+// `tolerance` of the one at the same index
+pub fn compare(a: &Geometry, b: &Geometry, tolerance: f64) -> Option<String> {
+    if a.vertices().len() != b.vertices().len() {
+        return Some(format!("{} vertices != {} vertices",a.vertices().len(),b.vertices().len()));
+    }
+    if a.faces().len() != b.faces().len() {
+        return Some(format!("{} faces != {} faces",a.faces().len(),b.faces().len()));
+    }
+    for (index,(u,v)) in a.vertices().iter().zip(b.vertices()).enumerate() {
+        if u.distance(v) > tolerance {
+            return Some(format!("vertex {} is {} away: {:?} != {:?}",index,u.distance(v),u,v));
+        }
+    }
+    for (index,(f,g)) in a.faces().iter().zip(b.faces()).enumerate() {
+        if (f.a,f.b,f.c) != (g.a,g.b,g.c) {
+            return Some(format!("face {}: {:?} != {:?}",index,(f.a,f.b,f.c),(g.a,g.b,g.c)));
+        }
+    }
+    if a.groups() != b.groups() {
+        return Some(format!("groups {:?} != {:?}",a.groups(),b.groups()));
+    }
+    None
+}
+
+// Panics unless the image is within `tolerance` on every channel of
+// the golden PNG at `path`. The golden image is written instead if
+// it doesn't exist yet or the CONSTRUCT_UPDATE_GOLDEN environment
+// variable is set. On a mismatch the image is written next to the
+// golden one with `.actual.png` on the end, for looking at.
+#[cfg(feature = "png")]
+pub fn assert_golden<P: AsRef<Path>>(image: &Image, path: P, tolerance: u8) {
+    let path = path.as_ref();
+    if !path.exists() || std::env::var_os(UPDATE_GOLDEN).is_some() {
+        image.write_png(path).expect("golden image can be written");
+        return;
+    }
+
+    let golden = Image::read_png(path).expect("golden image can be read");
+    let difference = image.difference(&golden);
+    if difference.is_none_or(|d| d > tolerance) {
+        let actual = path.with_extension("actual.png");
+        let _ = image.write_png(&actual);
+        match difference {
+            Some(d) => panic!("image differs from {} by {} (see {})",path.display(),d,actual.display()),
+            None => panic!("image is {}x{} but {} isn't (see {})",
+                image.width(),image.height(),path.display(),actual.display()),
+        }
+    }
+}
+
+// Panics with the first difference between two geometries, with
+// vertices compared within a tolerance of 1e-9 unless one is given
+#[macro_export]
+macro_rules! assert_geometry_eq {
+    ( $a: expr, $b: expr ) => {
+        $crate::assert_geometry_eq!($a,$b,1e-9)
+    };
+    ( $a: expr, $b: expr, $tolerance: expr ) => {
+        if let Some(difference) = $crate::testing::compare(&$a,&$b,$tolerance) {
+            panic!("geometries differ: {}",difference);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::geometry::{Matrix,Transform};
+
+    #[test]
+    fn test_testing_fixtures() {
+        let block = cube();
+        assert_relative_eq!(block.volume(),1.0,epsilon = 1e-9);
+
+        let flat = grid(3,2);
+        assert_eq!(flat.vertices().len(),12);
+        assert_eq!(flat.size(),12);
+        assert!(flat.faces().iter().all(|f| f.normal(flat.vertices()).z > 0.99));
+
+        let ball = sphere(2.0,200);
+        assert_eq!(ball.vertices().len(),200);
+        assert!(ball.vertices().iter().all(|v| (v.magnitude() - 2.0).abs() < 1e-9));
+        assert!(ball.volume() > 30.0 && ball.volume() < 4.0 / 3.0 * PI * 8.0);
+
+        let can = cylinder(1.0,2.0,32);
+        assert_eq!(can.vertices().len(),64);
+        assert_relative_eq!(can.volume(),PI * 2.0,max_relative = 0.01);
+
+        let mut board = stud();
+        board.set("Length",1.0).unwrap();
+        assert_relative_eq!(board.geometry().vertices()[4].x,2.2192,epsilon = 1e-9);
+        assert_eq!(board.connections().len(),2);
+
+        let mut sheet = panel();
+        sheet.set("Thickness",0.01).unwrap();
+        assert_relative_eq!(sheet.geometry().bounds().unwrap().size().z,0.02905,epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_testing_compare() {
+        let a = cube();
+        let mut b = cube();
+        assert_eq!(compare(&a,&b,0.0),None);
+        assert_geometry_eq!(a,b);
+
+        b.transform(&Matrix::translate(1e-6,0.0,0.0));
+        assert_geometry_eq!(a,b,1e-5);
+        assert!(compare(&a,&b,1e-9).unwrap().starts_with("vertex 0"));
+        assert!(compare(&a,&grid(1,1),1.0).unwrap().contains("vertices"));
+
+        b = cube();
+        b.add_group("top",[4,5,6,7]).unwrap();
+        assert!(compare(&a,&b,0.0).unwrap().starts_with("groups"));
+    }
+
+    #[test]
+    #[should_panic(expected = "geometries differ: 8 vertices != 4 vertices")]
+    fn test_testing_assert_geometry_eq() {
+        assert_geometry_eq!(cube(),grid(1,1));
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_testing_golden() {
+        let directory = std::env::temp_dir().join(format!("construct-golden-{}",std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("square.png");
+        let _ = std::fs::remove_file(&path);
+
+        // the first run writes the image, and the next ones match it
+        let image = Image::new(4,4,[10,20,30,255]);
+        assert_golden(&image,&path,0);
+        assert!(path.exists());
+        assert_golden(&image,&path,0);
+
+        let mut changed = image.clone();
+        changed.set(0,0,[12,20,30,255]);
+        assert_golden(&changed,&path,2);
+        let result = std::panic::catch_unwind(|| assert_golden(&changed,&path,1));
+        assert!(result.is_err());
+        assert!(directory.join("square.actual.png").exists());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+}
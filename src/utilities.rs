@@ -1,6 +1,105 @@
 use crate::errors::Error;
+use crate::constant::{COMMENT_TAG,OBJECT_TAG};
 use itertools::Itertools;
 
+// Opens a tracing span that lasts until the end of the enclosing
+// scope. Compiles to nothing unless the `tracing` feature is enabled.
+#[cfg(feature = "tracing")]
+macro_rules! span {
+    ( $name: expr $(, $($fields: tt)+ )? ) => {
+        let _span = tracing::debug_span!($name $(, $($fields)+ )?).entered();
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! span {
+    ( $($tokens: tt)* ) => {}
+}
+
+// Builds a geometry from lines like an OBJ file, with "v x y z" for
+// each vertex and "f a b c" for each face, counting vertices from one.
+// Lines with the wrong number of values don't compile.
+//
+//     let triangle = geometry! {
+//         v 0 0 0;
+//         v 1 0 0;
+//         v 0 1 0;
+//         f 1 2 3;
+//     };
+#[macro_export]
+macro_rules! geometry {
+    ( $( $line: tt )* ) => {{
+        const PRIMITIVE: $crate::geometry::Primitive = $crate::primitive!($( $line )*);
+        PRIMITIVE.geometry()
+    }};
+}
+
+// Builds a primitive at compile time from lines like the ones the
+// `geometry!` macro takes, for keeping models in a `static`
+//
+//     static TRIANGLE: Primitive = primitive! {
+//         v 0 0 0;
+//         v 1 0 0;
+//         v 0 1 0;
+//         f 1 2 3;
+//     };
+#[macro_export]
+macro_rules! primitive {
+    ( @ [ $( $v: expr, )* ] [ $( $f: expr, )* ] v $x: literal $y: literal $z: literal $( ; $( $rest: tt )* )? ) => {
+        $crate::primitive!(@ [ $( $v, )* $crate::geometry::Vertex::new($x as f64,$y as f64,$z as f64), ] [ $( $f, )* ] $( $( $rest )* )?)
+    };
+    ( @ [ $( $v: expr, )* ] [ $( $f: expr, )* ] f $a: literal $b: literal $c: literal $( ; $( $rest: tt )* )? ) => {
+        $crate::primitive!(@ [ $( $v, )* ] [ $( $f, )* $crate::geometry::Face::new($a,$b,$c), ] $( $( $rest )* )?)
+    };
+    ( @ [ $( $v: expr, )* ] [ $( $f: expr, )* ] ) => {
+        $crate::geometry::Primitive::new(&[ $( $v ),* ],&[ $( $f ),* ])
+    };
+    ( $( $line: tt )* ) => {
+        $crate::primitive!(@ [] [] $( $line )*)
+    };
+}
+
+// Maps every item, spread across threads when the `parallel`
+// feature is enabled. Results are in the same order as the items.
+#[cfg(feature = "parallel")]
+pub fn map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync + Send
+{
+    use rayon::prelude::*;
+    items.par_iter().map(f).collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+pub fn map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    F: Fn(&T) -> R
+{
+    items.iter().map(f).collect()
+}
+
+// Like `map`, with mutable access to each item and its index
+#[cfg(feature = "parallel")]
+pub fn map_mut<T, R, F>(items: &mut [T], f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(usize,&mut T) -> R + Sync + Send
+{
+    use rayon::prelude::*;
+    items.par_iter_mut().enumerate().map(|(i,t)| f(i,t)).collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+pub fn map_mut<T, R, F>(items: &mut [T], f: F) -> Vec<R>
+where
+    F: Fn(usize,&mut T) -> R
+{
+    items.iter_mut().enumerate().map(|(i,t)| f(i,t)).collect()
+}
+
 pub fn extract<T: std::str::FromStr>(tag: char, line: &str) -> Result<(T,T,T),Error> {
     line
         .trim_start_matches([tag,' '])
@@ -12,4 +111,356 @@ pub fn extract<T: std::str::FromStr>(tag: char, line: &str) -> Result<(T,T,T),Er
         .into_iter()
         .collect_tuple::<(_,_,_)>()
         .ok_or(Error::ParseError)
-}
\ No newline at end of file
+}
+
+// Every value after the tag on a line
+pub fn values<T: std::str::FromStr>(line: &str) -> Result<Vec<T>,Error> {
+    line.split_whitespace()
+        .skip(1)
+        .map(str::parse)
+        .collect::<Result<Vec<T>,_>>()
+        .or(Err(Error::ParseError))
+}
+
+// Every value in text that's separated by commas
+pub fn list<T: std::str::FromStr>(text: &str) -> Result<Vec<T>,Error> {
+    text.split(',')
+        .map(str::parse)
+        .collect::<Result<Vec<T>,_>>()
+        .or(Err(Error::ParseError))
+}
+
+// The part of a line before any comment, which can't start inside
+// a quoted name
+pub fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i,c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            COMMENT_TAG if !quoted => return line[..i].trim(),
+            _ => (),
+        }
+    }
+    line.trim()
+}
+
+// The number of values after the tag on a line
+pub fn fields(line: &str) -> usize {
+    line.split_whitespace().count().saturating_sub(1)
+}
+
+// The name given by the first `o` line, if there is one
+pub fn object_name(text: &str) -> Option<String> {
+    text.lines()
+        .map(strip_comment)
+        .find_map(|l| l
+            .strip_prefix(OBJECT_TAG)
+            .filter(|n| n.starts_with(' ')))
+        .map(section_name)
+}
+
+// The name after an `o` or `g` tag, written by `quote` or, from other
+// tools, as the rest of the line
+pub fn section_name(text: &str) -> String {
+    match unquote(text) {
+        Ok((name,rest)) if rest.trim().is_empty() => name,
+        _ => text.trim().to_string(),
+    }
+}
+
+// Writes a name so it reads back as a single token, in quotes with
+// quotes and backslashes escaped if it's empty or has spaces, a `#`
+// or any of those in it. `special` are other characters that need
+// quotes.
+pub fn quote(name: &str, special: &[char]) -> String {
+    let plain = !name.is_empty() && !name
+        .chars()
+        .any(|c| c.is_whitespace() || c == '"' || c == '\\' || c == COMMENT_TAG || special.contains(&c));
+    if plain {
+        return name.into();
+    }
+    let mut result = String::from('"');
+    for c in name.chars() {
+        if c == '"' || c == '\\' {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result.push('"');
+    result
+}
+
+// Reads a name written by `quote` from the start of the text, and
+// returns it along with the rest of the text
+pub fn unquote(text: &str) -> Result<(String,&str),Error> {
+    let text = text.trim_start();
+    let Some(quoted) = text.strip_prefix('"') else {
+        let end = text.find(char::is_whitespace).unwrap_or(text.len());
+        return match end {
+            0 => Err(Error::ParseError),
+            _ => Ok((text[..end].into(),&text[end..])),
+        };
+    };
+
+    let mut name = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((i,c)) = chars.next() {
+        match c {
+            '"' => return Ok((name,&quoted[i + 1..])),
+            '\\' => name.push(chars.next().ok_or(Error::ParseError)?.1),
+            c => name.push(c),
+        }
+    }
+    Err(Error::ParseError)
+}
+
+// Splits off the first token of the text, where whitespace inside
+// quotes doesn't end the token
+pub fn token(text: &str) -> (&str,&str) {
+    let text = text.trim_start();
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i,c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => return (&text[..i],&text[i..]),
+            _ => (),
+        }
+    }
+    (text,"")
+}
+
+/// A generator of pseudo-random numbers that gives the same sequence
+/// for the same seed on every platform, so anything built from it
+/// can be reproduced exactly
+#[derive(Default,Debug,Clone,PartialEq,Eq)]
+pub struct Random {
+    state: u64,
+}
+
+/// Smooth pseudo-random values that change gradually from point to
+/// point, laid out the same way for the same seed
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Noise {
+    seed: u64,
+    permutation: Vec<u8>,
+}
+
+// the directions of the gradients at the corners of the simplex grid
+const GRADIENTS: [(f64,f64);12] = [
+    (1.0,1.0),(-1.0,1.0),(1.0,-1.0),(-1.0,-1.0),
+    (1.0,0.0),(-1.0,0.0),(1.0,0.0),(-1.0,0.0),
+    (0.0,1.0),(0.0,-1.0),(0.0,1.0),(0.0,-1.0),
+];
+
+impl Random {
+
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    // The next number in the sequence, using splitmix64
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // A number from 0 up to but not including 1
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // A number from `low` up to but not including `high`
+    pub fn range(&mut self, low: f64, high: f64) -> f64 {
+        low + (high - low) * self.next_f64()
+    }
+
+    // A number from `-limit` up to `limit`, for jittering a value
+    // around where it is
+    pub fn spread(&mut self, limit: f64) -> f64 {
+        self.range(-limit,limit)
+    }
+
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Noise {
+
+    // Shuffles the lattice of gradients with a seed
+    pub fn new(seed: u64) -> Self {
+        let mut random = Random::new(seed);
+        let mut permutation = (0..=255).collect::<Vec<u8>>();
+        for i in (1..permutation.len()).rev() {
+            let j = (random.next_u64() % (i as u64 + 1)) as usize;
+            permutation.swap(i,j);
+        }
+        Self { seed, permutation }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn hash(&self, i: i64) -> usize {
+        self.permutation[(i & 255) as usize] as usize
+    }
+
+    // Improved Perlin noise at a point, from about -1 to 1, which is
+    // zero at every whole number
+    pub fn perlin(&self, x: f64, y: f64, z: f64) -> f64 {
+        let fade = |t: f64| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let lerp = |t: f64, a: f64, b: f64| a + t * (b - a);
+        let gradient = |hash: usize, x: f64, y: f64, z: f64| {
+            let h = hash & 15;
+            let u = if h < 8 { x } else { y };
+            let v = match h {
+                0..=3 => y,
+                12 | 14 => x,
+                _ => z,
+            };
+            (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+        };
+
+        let (fx,fy,fz) = (x.floor(),y.floor(),z.floor());
+        let (i,j,k) = (fx as i64,fy as i64,fz as i64);
+        let (x,y,z) = (x - fx,y - fy,z - fz);
+        let (u,v,w) = (fade(x),fade(y),fade(z));
+
+        let a = self.hash(i) as i64 + j;
+        let (aa,ab) = (self.hash(a) as i64 + k,self.hash(a + 1) as i64 + k);
+        let b = self.hash(i + 1) as i64 + j;
+        let (ba,bb) = (self.hash(b) as i64 + k,self.hash(b + 1) as i64 + k);
+
+        lerp(w,
+            lerp(v,
+                lerp(u,gradient(self.hash(aa),x,y,z),gradient(self.hash(ba),x - 1.0,y,z)),
+                lerp(u,gradient(self.hash(ab),x,y - 1.0,z),gradient(self.hash(bb),x - 1.0,y - 1.0,z))),
+            lerp(v,
+                lerp(u,gradient(self.hash(aa + 1),x,y,z - 1.0),gradient(self.hash(ba + 1),x - 1.0,y,z - 1.0)),
+                lerp(u,gradient(self.hash(ab + 1),x,y - 1.0,z - 1.0),gradient(self.hash(bb + 1),x - 1.0,y - 1.0,z - 1.0))))
+    }
+
+    // Simplex noise at a point in 2D, from about -1 to 1, which has
+    // fewer straight lines along the axes than Perlin noise
+    pub fn simplex(&self, x: f64, y: f64) -> f64 {
+        let f2 = 0.5 * (3f64.sqrt() - 1.0);
+        let g2 = (3.0 - 3f64.sqrt()) / 6.0;
+
+        // the corner of the triangle the point is in, in the skewed
+        // grid, and the point from that corner unskewed
+        let s = (x + y) * f2;
+        let (i,j) = ((x + s).floor(),(y + s).floor());
+        let t = (i + j) * g2;
+        let (x0,y0) = (x - (i - t),y - (j - t));
+        let (i1,j1) = if x0 > y0 { (1,0) } else { (0,1) };
+
+        let corners = [
+            (x0,y0,0,0),
+            (x0 - i1 as f64 + g2,y0 - j1 as f64 + g2,i1,j1),
+            (x0 - 1.0 + 2.0 * g2,y0 - 1.0 + 2.0 * g2,1,1),
+        ];
+
+        let (i,j) = (i as i64,j as i64);
+        let sum = corners
+            .into_iter()
+            .map(|(x,y,di,dj)| {
+                let t = 0.5 - x * x - y * y;
+                if t < 0.0 {
+                    return 0.0;
+                }
+                let index = self.hash(i + di + self.hash(j + dj) as i64) % 12;
+                let (gx,gy) = GRADIENTS[index];
+                t * t * t * t * (gx * x + gy * y)
+            })
+            .sum::<f64>();
+
+        70.0 * sum
+    }
+
+    // Perlin noise added up over `octaves` scales, each twice as fine
+    // and half as strong as the last, for detail at every size. The
+    // result is scaled back to about -1 to 1.
+    pub fn fractal(&self, x: f64, y: f64, z: f64, octaves: usize) -> f64 {
+        let mut total = 0.0;
+        let mut weight = 0.0;
+        let (mut amplitude,mut frequency) = (1.0,1.0);
+        for _ in 0..octaves.max(1) {
+            total += amplitude * self.perlin(x * frequency,y * frequency,z * frequency);
+            weight += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        total / weight
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_quoted_names() {
+        for name in ["Length","Wall Thickness","say \"hi\"","back\\slash","#2",""] {
+            let text = quote(name,&[]) + " rest";
+            let (result,rest) = unquote(&text).unwrap();
+            assert_eq!(result,name);
+            assert_eq!(rest," rest");
+            assert_eq!(token(&text),(quote(name,&[]).as_str()," rest"));
+        }
+        assert_eq!(quote("a~b",&['~']),"\"a~b\"");
+        assert_eq!(strip_comment("vg \"#2 # left\" 0 1 # the left side"),"vg \"#2 # left\" 0 1");
+        assert!(unquote("\"open").is_err());
+        assert!(unquote("  ").is_err());
+    }
+
+    #[test]
+    fn test_random_seeded() {
+        let mut a = Random::new(42);
+        let mut b = Random::new(42);
+        let values = (0..100).map(|_| a.next_f64()).collect::<Vec<f64>>();
+        assert_eq!(values,(0..100).map(|_| b.next_f64()).collect::<Vec<f64>>());
+        assert!(values.iter().all(|v| (0.0..1.0).contains(v)));
+        assert_ne!(Random::new(43).next_u64(),Random::new(42).next_u64());
+
+        let mut random = Random::new(7);
+        assert!((0..100).map(|_| random.range(2.0,3.0)).all(|v| (2.0..3.0).contains(&v)));
+    }
+
+    #[test]
+    fn test_noise_seeded() {
+        let noise = Noise::new(1);
+        assert_eq!(noise,Noise::new(1));
+        assert_ne!(noise,Noise::new(2));
+
+        // zero on the lattice, smooth and bounded between
+        assert_eq!(noise.perlin(3.0,-2.0,5.0),0.0);
+        let samples = (0..1000)
+            .map(|i| i as f64 * 0.037)
+            .map(|t| (noise.perlin(t,t * 0.7,t * 1.3),noise.simplex(t,-t * 0.4),noise.fractal(t,0.5,t,4)))
+            .collect::<Vec<(f64,f64,f64)>>();
+        assert!(samples.iter().all(|(p,s,f)| p.abs() <= 1.0 && s.abs() <= 1.0 && f.abs() <= 1.0));
+        assert!(samples.iter().any(|(p,_,_)| p.abs() > 0.1));
+        assert!(samples.iter().any(|(_,s,_)| s.abs() > 0.1));
+        assert!(samples.windows(2).all(|w| (w[0].0 - w[1].0).abs() < 0.2));
+
+        // the same point gives the same value every time
+        assert_eq!(noise.simplex(0.3,0.8),Noise::new(1).simplex(0.3,0.8));
+        assert_ne!(noise.simplex(0.3,0.8),Noise::new(2).simplex(0.3,0.8));
+    }
+
+}